@@ -0,0 +1,21 @@
+use anyhow::{bail, Result};
+use pasture_io::tiles3d::PntsTiler;
+
+fn main() -> Result<()> {
+    // Builds a 3D Tiles tileset from one or more point cloud files (e.g. a whole collection of LAS files). The points
+    // of all files are sorted into an octree, where each tile contains a subsample of the points in its bounds. Points
+    // that do not fit into memory are stored in temporary files within the output directory during tiling
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 2 {
+        bail!("Usage: tile_3dtiles <OUTPUT_DIRECTORY> <INPUT_FILE>...");
+    }
+
+    let tiler = PntsTiler::new(&args[0]).with_max_points_per_tile(50_000);
+    let tileset = tiler.tile_files(&args[1..])?;
+    println!(
+        "Wrote tileset with geometric error {} to {}",
+        tileset.geometric_error, args[0]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use std::convert::TryInto;
+
+use anyhow::{anyhow, bail, Result};
+use pasture_core::layout::{attributes, PointAttributeDataType, PointAttributeDefinition};
+
+/// Names of the components of vector attributes, as they are used in the header of ASCII files
+const COMPONENT_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+
+/// A single column of an ASCII point cloud file. A format for `AsciiReader` and `AsciiWriter` is a list of columns,
+/// which makes it possible to read and write arbitrary attributes, including custom attributes and single
+/// components of vector attributes such as `NORMAL`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AsciiColumn {
+    /// A column that is ignored while reading and left empty while writing
+    Skip,
+    /// A column with the value of a scalar attribute, e.g. `INTENSITY` or `SCAN_ANGLE`
+    Attribute(PointAttributeDefinition),
+    /// A column with the component at the given index of a vector attribute, e.g. the x-component (index 0)
+    /// of `NORMAL`
+    Component(PointAttributeDefinition, usize),
+}
+
+impl AsciiColumn {
+    /// Returns the columns that store all values of `attribute`: A single column for scalar attributes, and one
+    /// column per component for vector attributes
+    ///
+    /// # Errors
+    ///
+    /// If the datatype of `attribute` can't be represented in an ASCII file (`ByteArray` and `Custom`), an error
+    /// is returned
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pasture_core::layout::attributes::NORMAL;
+    /// use pasture_io::ascii::AsciiColumn;
+    ///
+    /// let columns = AsciiColumn::all_components(NORMAL).unwrap();
+    /// assert_eq!(3, columns.len());
+    /// assert_eq!(AsciiColumn::Component(NORMAL, 0), columns[0]);
+    /// assert_eq!("Normal.y", columns[1].header_name());
+    /// ```
+    pub fn all_components(attribute: PointAttributeDefinition) -> Result<Vec<Self>> {
+        let (_, num_components) = split_datatype(attribute.datatype())?;
+        if num_components == 1 {
+            Ok(vec![Self::Attribute(attribute)])
+        } else {
+            Ok((0..num_components)
+                .map(|component| Self::Component(attribute.clone(), component))
+                .collect())
+        }
+    }
+
+    /// Returns the attribute of this column, or `None` for `AsciiColumn::Skip`
+    pub fn attribute(&self) -> Option<&PointAttributeDefinition> {
+        match self {
+            AsciiColumn::Skip => None,
+            AsciiColumn::Attribute(attribute) | AsciiColumn::Component(attribute, _) => {
+                Some(attribute)
+            }
+        }
+    }
+
+    /// Returns the name of this column in the header line of an ASCII file. Scalar attributes use their name,
+    /// components of vector attributes use the name of the attribute and the component, e.g. `Normal.x`. As an
+    /// exception, the components of `POSITION_3D` are called `X`, `Y` and `Z`
+    pub fn header_name(&self) -> String {
+        match self {
+            AsciiColumn::Skip => String::new(),
+            AsciiColumn::Attribute(attribute) => attribute.name().to_owned(),
+            AsciiColumn::Component(attribute, component) => {
+                let component_name = COMPONENT_NAMES.get(*component).copied().unwrap_or("?");
+                if attribute.name() == attributes::POSITION_3D.name() {
+                    component_name.to_uppercase()
+                } else {
+                    format!("{}.{}", attribute.name(), component_name)
+                }
+            }
+        }
+    }
+
+    /// Parses a column name of the form `<attribute>.<component>` (e.g. `Normal.x`) into a column for a
+    /// component of one of the builtin vector attributes
+    pub(crate) fn from_component_name(column_name: &str) -> Option<Self> {
+        let (attribute_name, component_name) = column_name.trim().rsplit_once('.')?;
+        let component = COMPONENT_NAMES
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(component_name))?;
+        [
+            attributes::POSITION_3D,
+            attributes::COLOR_RGB,
+            attributes::NORMAL,
+        ]
+        .iter()
+        .find(|attribute| attribute.name().eq_ignore_ascii_case(attribute_name))
+        .filter(|attribute| {
+            split_datatype(attribute.datatype())
+                .map(|(_, num_components)| component < num_components)
+                .unwrap_or(false)
+        })
+        .map(|attribute| AsciiColumn::Component(attribute.clone(), component))
+    }
+
+    /// Checks that the datatype of the attribute of this column can be represented in an ASCII file and that
+    /// the component index is valid
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            AsciiColumn::Skip => Ok(()),
+            AsciiColumn::Attribute(attribute) => {
+                let (_, num_components) = split_datatype(attribute.datatype())?;
+                if num_components != 1 {
+                    bail!(
+                        "Attribute {} is a vector attribute, use one AsciiColumn::Component for each component",
+                        attribute
+                    );
+                }
+                Ok(())
+            }
+            AsciiColumn::Component(attribute, component) => {
+                let (_, num_components) = split_datatype(attribute.datatype())?;
+                if *component >= num_components {
+                    bail!(
+                        "Component {} is out of range for attribute {}",
+                        component,
+                        attribute
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Splits the given datatype into the datatype of a single component and the number of components. Scalar
+/// datatypes have a single component
+pub(crate) fn split_datatype(
+    datatype: PointAttributeDataType,
+) -> Result<(PointAttributeDataType, usize)> {
+    match datatype {
+        PointAttributeDataType::Vec3u8 => Ok((PointAttributeDataType::U8, 3)),
+        PointAttributeDataType::Vec3u16 => Ok((PointAttributeDataType::U16, 3)),
+        PointAttributeDataType::Vec3f32 => Ok((PointAttributeDataType::F32, 3)),
+        PointAttributeDataType::Vec3i32 => Ok((PointAttributeDataType::I32, 3)),
+        PointAttributeDataType::Vec3f64 => Ok((PointAttributeDataType::F64, 3)),
+        PointAttributeDataType::Vec4u8 => Ok((PointAttributeDataType::U8, 4)),
+        PointAttributeDataType::ByteArray(_) | PointAttributeDataType::Custom { .. } => {
+            Err(anyhow!(
+                "Datatype {} can't be represented in an ASCII file",
+                datatype
+            ))
+        }
+        scalar => Ok((scalar, 1)),
+    }
+}
+
+/// Parses `value_str` as a value of the scalar `datatype` and writes it in native byte order into `target`
+pub(crate) fn parse_scalar(
+    datatype: PointAttributeDataType,
+    value_str: &str,
+    target: &mut [u8],
+) -> Result<()> {
+    fn parse<V: std::str::FromStr>(value_str: &str) -> Result<V> {
+        value_str.parse::<V>().map_err(|_| {
+            anyhow!(
+                "ParseError expected {} found '{}'.",
+                std::any::type_name::<V>(),
+                value_str
+            )
+        })
+    }
+
+    match datatype {
+        PointAttributeDataType::U8 => {
+            target.copy_from_slice(&parse::<u8>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::I8 => {
+            target.copy_from_slice(&parse::<i8>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::U16 => {
+            target.copy_from_slice(&parse::<u16>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::I16 => {
+            target.copy_from_slice(&parse::<i16>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::U32 => {
+            target.copy_from_slice(&parse::<u32>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::I32 => {
+            target.copy_from_slice(&parse::<i32>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::U64 => {
+            target.copy_from_slice(&parse::<u64>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::I64 => {
+            target.copy_from_slice(&parse::<i64>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::F32 => {
+            target.copy_from_slice(&parse::<f32>(value_str)?.to_ne_bytes())
+        }
+        PointAttributeDataType::F64 => {
+            target.copy_from_slice(&parse::<f64>(value_str)?.to_ne_bytes())
+        }
+        other => bail!("{} is no scalar datatype", other),
+    }
+    Ok(())
+}
+
+/// Formats the value of the scalar `datatype` in native byte order in `source`. Floating point values are written
+/// with at most `precision` decimal places
+pub(crate) fn format_scalar(
+    datatype: PointAttributeDataType,
+    source: &[u8],
+    precision: usize,
+) -> Result<String> {
+    let value = match datatype {
+        PointAttributeDataType::U8 => source[0].to_string(),
+        PointAttributeDataType::I8 => (source[0] as i8).to_string(),
+        PointAttributeDataType::U16 => u16::from_ne_bytes(source.try_into()?).to_string(),
+        PointAttributeDataType::I16 => i16::from_ne_bytes(source.try_into()?).to_string(),
+        PointAttributeDataType::U32 => u32::from_ne_bytes(source.try_into()?).to_string(),
+        PointAttributeDataType::I32 => i32::from_ne_bytes(source.try_into()?).to_string(),
+        PointAttributeDataType::U64 => u64::from_ne_bytes(source.try_into()?).to_string(),
+        PointAttributeDataType::I64 => i64::from_ne_bytes(source.try_into()?).to_string(),
+        PointAttributeDataType::F32 => {
+            format_float(f32::from_ne_bytes(source.try_into()?) as f64, precision)
+        }
+        PointAttributeDataType::F64 => {
+            format_float(f64::from_ne_bytes(source.try_into()?), precision)
+        }
+        other => bail!("{} is no scalar datatype", other),
+    };
+    Ok(value)
+}
+
+/// Formats `value` with `precision` decimal places and removes trailing zeros, keeping at least one decimal place
+pub(crate) fn format_float(value: f64, precision: usize) -> String {
+    let formatted = format!("{:.1$}", value, precision);
+    if !formatted.contains('.') {
+        return formatted;
+    }
+    let trimmed = formatted.trim_end_matches('0');
+    if trimmed.ends_with('.') {
+        format!("{}0", trimmed)
+    } else {
+        trimmed.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_components() -> Result<()> {
+        assert_eq!(
+            vec![AsciiColumn::Attribute(attributes::SCAN_ANGLE)],
+            AsciiColumn::all_components(attributes::SCAN_ANGLE)?
+        );
+        let color_columns = AsciiColumn::all_components(attributes::COLOR_RGB)?;
+        assert_eq!(3, color_columns.len());
+        assert_eq!("ColorRGB.z", color_columns[2].header_name());
+        assert_eq!(
+            Some(color_columns[2].clone()),
+            AsciiColumn::from_component_name("colorrgb.Z")
+        );
+        assert_eq!(None, AsciiColumn::from_component_name("Normal.w"));
+        assert_eq!(
+            "X",
+            AsciiColumn::Component(attributes::POSITION_3D, 0).header_name()
+        );
+
+        let byte_array =
+            attributes::POINT_ID.with_custom_datatype(PointAttributeDataType::ByteArray(4));
+        assert!(AsciiColumn::all_components(byte_array).is_err());
+        assert!(AsciiColumn::Attribute(attributes::NORMAL)
+            .validate()
+            .is_err());
+        assert!(AsciiColumn::Component(attributes::NORMAL, 3)
+            .validate()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_and_format_scalar() -> Result<()> {
+        let mut bytes = [0; 8];
+        parse_scalar(PointAttributeDataType::I16, "-1234", &mut bytes[..2])?;
+        assert_eq!(
+            "-1234",
+            format_scalar(PointAttributeDataType::I16, &bytes[..2], 3)?
+        );
+        parse_scalar(PointAttributeDataType::F32, "0.25", &mut bytes[..4])?;
+        assert_eq!(
+            "0.25",
+            format_scalar(PointAttributeDataType::F32, &bytes[..4], 5)?
+        );
+        parse_scalar(
+            PointAttributeDataType::U64,
+            "18446744073709551615",
+            &mut bytes,
+        )?;
+        assert_eq!(u64::MAX, u64::from_ne_bytes(bytes));
+        assert!(parse_scalar(PointAttributeDataType::U8, "256", &mut bytes[..1]).is_err());
+        assert!(parse_scalar(PointAttributeDataType::I32, "1.5", &mut bytes[..4]).is_err());
+
+        assert_eq!("1.0", format_float(1.0, 5));
+        assert_eq!("1.23", format_float(1.23, 2));
+        assert_eq!("3", format_float(3.0, 0));
+        Ok(())
+    }
+}
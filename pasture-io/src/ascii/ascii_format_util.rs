@@ -1,4 +1,11 @@
+use std::borrow::Cow;
+
 use anyhow::{bail, Result};
+use pasture_core::layout::{attributes, PointAttributeDataType, PointAttributeDefinition};
+
+use crate::base::MAGIC_BYTES_LENGTH;
+
+use super::AsciiColumn;
 // This enum maps the different entrys on an ascii file to later map these entries to the corresponding attribute.
 #[derive(Debug)]
 pub(crate) enum PointDataType {
@@ -20,6 +27,7 @@ pub(crate) enum PointDataType {
     ScanDirectionFlag, //bool
     ScanAngleRank,     //I8
     Nir,               //U16
+    Column(AsciiColumn),
 }
 
 impl std::fmt::Display for PointDataType {
@@ -81,4 +89,242 @@ impl PointDataType {
         }
         Ok(parse_layout)
     }
+
+    /// Maps the name of a column in the header of an ASCII file onto a `PointDataType`. Matching ignores case,
+    /// whitespace, `_` and `-`, so `Intensity`, `intensity` and `INTENSITY` are all mapped to the intensity. Unknown
+    /// column names are mapped onto a custom `F64` attribute with the (trimmed) column name, except for names of
+    /// the form `<attribute>.<component>` (e.g. `Normal.x`), which are mapped onto the component of the builtin
+    /// vector attribute. Empty column names are skipped
+    pub(crate) fn from_column_name(column_name: &str) -> PointDataType {
+        if let Some(column) = AsciiColumn::from_component_name(column_name) {
+            return PointDataType::Column(column);
+        }
+        let normalized_name = column_name
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+        match normalized_name.as_str() {
+            "x" => PointDataType::CoordinateX,
+            "y" => PointDataType::CoordinateY,
+            "z" => PointDataType::CoordinateZ,
+            "i" | "intensity" => PointDataType::Intensity,
+            "returnnumber" | "return" => PointDataType::ReturnNumber,
+            "numberofreturns" | "returns" => PointDataType::NumberOfReturns,
+            "classification" | "class" => PointDataType::Classification,
+            "userdata" => PointDataType::UserData,
+            "r" | "red" => PointDataType::ColorR,
+            "g" | "green" => PointDataType::ColorG,
+            "b" | "blue" => PointDataType::ColorB,
+            "gpstime" | "time" => PointDataType::GpsTime,
+            "pointsourceid" => PointDataType::PointSourceID,
+            "edgeofflightline" => PointDataType::EdgeOfFlightLine,
+            "scandirectionflag" => PointDataType::ScanDirectionFlag,
+            "scananglerank" => PointDataType::ScanAngleRank,
+            "nir" => PointDataType::Nir,
+            "scanangle" => PointDataType::Column(AsciiColumn::Attribute(attributes::SCAN_ANGLE)),
+            "pointid" => PointDataType::Column(AsciiColumn::Attribute(attributes::POINT_ID)),
+            "" => PointDataType::Skip,
+            _ => PointDataType::Column(AsciiColumn::Attribute(PointAttributeDefinition::custom(
+                Cow::Owned(column_name.trim().to_owned()),
+                PointAttributeDataType::F64,
+            ))),
+        }
+    }
+
+    /// Returns the `AsciiColumn` that corresponds to this `PointDataType`
+    pub(crate) fn as_column(&self) -> AsciiColumn {
+        match self {
+            PointDataType::Skip => AsciiColumn::Skip,
+            PointDataType::CoordinateX => AsciiColumn::Component(attributes::POSITION_3D, 0),
+            PointDataType::CoordinateY => AsciiColumn::Component(attributes::POSITION_3D, 1),
+            PointDataType::CoordinateZ => AsciiColumn::Component(attributes::POSITION_3D, 2),
+            PointDataType::Intensity => AsciiColumn::Attribute(attributes::INTENSITY),
+            PointDataType::ReturnNumber => AsciiColumn::Attribute(attributes::RETURN_NUMBER),
+            PointDataType::NumberOfReturns => AsciiColumn::Attribute(attributes::NUMBER_OF_RETURNS),
+            PointDataType::Classification => AsciiColumn::Attribute(attributes::CLASSIFICATION),
+            PointDataType::UserData => AsciiColumn::Attribute(attributes::USER_DATA),
+            PointDataType::ColorR => AsciiColumn::Component(attributes::COLOR_RGB, 0),
+            PointDataType::ColorG => AsciiColumn::Component(attributes::COLOR_RGB, 1),
+            PointDataType::ColorB => AsciiColumn::Component(attributes::COLOR_RGB, 2),
+            PointDataType::GpsTime => AsciiColumn::Attribute(attributes::GPS_TIME),
+            PointDataType::PointSourceID => AsciiColumn::Attribute(attributes::POINT_SOURCE_ID),
+            PointDataType::EdgeOfFlightLine => {
+                AsciiColumn::Attribute(attributes::EDGE_OF_FLIGHT_LINE)
+            }
+            PointDataType::ScanDirectionFlag => {
+                AsciiColumn::Attribute(attributes::SCAN_DIRECTION_FLAG)
+            }
+            PointDataType::ScanAngleRank => AsciiColumn::Attribute(attributes::SCAN_ANGLE_RANK),
+            PointDataType::Nir => AsciiColumn::Attribute(attributes::NIR),
+            PointDataType::Column(column) => column.clone(),
+        }
+    }
+}
+
+/// Delimiters that are recognized when guessing whether some data is an ASCII point cloud
+const ASCII_DELIMITERS: [char; 4] = [',', ';', '\t', ' '];
+
+/// Is `line` a record of an ASCII point cloud, i.e. does it contain at least three numeric columns?
+fn is_numeric_ascii_record(line: &str) -> bool {
+    let columns = line
+        .split(&ASCII_DELIMITERS[..])
+        .filter(|column| !column.is_empty())
+        .collect::<Vec<_>>();
+    columns.len() >= 3 && columns.iter().all(|column| column.parse::<f64>().is_ok())
+}
+
+/// Heuristic that guesses whether `bytes`, which are the first bytes of a file, belong to an ASCII point cloud.
+/// This is the case if `bytes` are valid UTF-8 without control characters (other than whitespace) and the first line
+/// that is not empty and not a comment is a record with at least three numeric columns. A single header line with
+/// column names (as it is common for CSV files) is allowed before the first record.
+///
+/// If `bytes` has the length of the whole sniffing window (see [`MAGIC_BYTES_LENGTH`]), the file most likely continues
+/// behind the window, so the last line might be cut off and only the complete lines are examined
+pub(crate) fn looks_like_ascii_point_cloud(bytes: &[u8]) -> bool {
+    let complete_lines = if bytes.len() < MAGIC_BYTES_LENGTH {
+        bytes
+    } else {
+        match bytes.iter().rposition(|c| *c == b'\n') {
+            Some(end_of_last_line) => &bytes[..end_of_last_line],
+            None => return false,
+        }
+    };
+    // Since UTF-8 never uses the byte of '\n' within a multi-byte character, `complete_lines` can only contain
+    // whole characters
+    let text = match std::str::from_utf8(complete_lines) {
+        Ok(text) => text,
+        Err(_) => return false,
+    };
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return false;
+    }
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("//"));
+    match lines.next() {
+        Some(first_line) if is_numeric_ascii_record(first_line) => true,
+        Some(_) => lines.next().map(is_numeric_ascii_record).unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Guesses the delimiter of an ASCII point cloud from one of its lines. Tabs, `;` and `,` are preferred in this
+/// order, if none of them occurs in `line`, the columns are assumed to be separated by whitespace (which is
+/// represented by a single space)
+pub(crate) fn detect_delimiter(line: &str) -> &'static str {
+    ["\t", ";", ","]
+        .iter()
+        .find(|delimiter| line.contains(*delimiter))
+        .copied()
+        .unwrap_or(" ")
+}
+
+/// Is `line` the header of an ASCII point cloud, i.e. does it contain a column that is not numeric?
+pub(crate) fn is_ascii_header(line: &str, delimiter: &str) -> bool {
+    split_ascii_columns(line, delimiter).any(|column| column.parse::<f64>().is_err())
+}
+
+/// Splits `line` into its trimmed columns. A whitespace `delimiter` matches any amount of whitespace
+pub(crate) fn split_ascii_columns<'a>(
+    line: &'a str,
+    delimiter: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    if delimiter.trim().is_empty() {
+        itertools::Either::Left(line.split_whitespace())
+    } else {
+        itertools::Either::Right(line.split(delimiter).map(str::trim))
+    }
+}
+
+/// Is `line` a comment or an empty line that precedes the header or the first record of an ASCII file?
+pub(crate) fn is_ascii_comment(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#') || line.starts_with("//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_column_name() {
+        assert!(matches!(
+            PointDataType::from_column_name(" X"),
+            PointDataType::CoordinateX
+        ));
+        assert!(matches!(
+            PointDataType::from_column_name("Number_Of_Returns"),
+            PointDataType::NumberOfReturns
+        ));
+        assert!(matches!(
+            PointDataType::from_column_name("GPS Time"),
+            PointDataType::GpsTime
+        ));
+        assert!(matches!(
+            PointDataType::from_column_name(""),
+            PointDataType::Skip
+        ));
+        assert_eq!(
+            AsciiColumn::Component(attributes::NORMAL, 1),
+            PointDataType::from_column_name("Normal.y").as_column()
+        );
+        match PointDataType::from_column_name(" Reflectance ") {
+            PointDataType::Column(AsciiColumn::Attribute(attribute)) => {
+                assert_eq!("Reflectance", attribute.name());
+                assert_eq!(PointAttributeDataType::F64, attribute.datatype());
+            }
+            other => panic!("Unexpected PointDataType {}", other),
+        }
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(",", detect_delimiter("1.0, 2.0, 3.0"));
+        assert_eq!(";", detect_delimiter("1.5; 2.5; 3.5"));
+        assert_eq!("\t", detect_delimiter("x\ty\tz"));
+        assert_eq!(" ", detect_delimiter("1 2   3"));
+        assert_eq!(
+            vec!["1", "2", "3"],
+            split_ascii_columns("1 2   3", " ").collect::<Vec<_>>()
+        );
+        assert!(is_ascii_header("X, Y, Z", ","));
+        assert!(!is_ascii_header("1.0, 2.0, 3.0", ","));
+    }
+
+    #[test]
+    fn test_looks_like_ascii_point_cloud() {
+        assert!(looks_like_ascii_point_cloud(
+            b"1.0, 2.0, 3.0, 17\n4 5 6 18\n"
+        ));
+        assert!(looks_like_ascii_point_cloud(b"# comment\nx;y;z\n1;2;3"));
+        assert!(looks_like_ascii_point_cloud(b"-1.5e3\t2\t3"));
+        assert!(!looks_like_ascii_point_cloud(b""));
+        assert!(!looks_like_ascii_point_cloud(b"1.0, 2.0\n3.0, 4.0\n"));
+        assert!(!looks_like_ascii_point_cloud(b"ply\nformat ascii 1.0\n"));
+        assert!(!looks_like_ascii_point_cloud(b"LASF\0\0\x01\x02"));
+        assert!(looks_like_ascii_point_cloud(
+            "X;Y;Z;Intensität\n1;2;3;4\n".as_bytes()
+        ));
+        assert!(!looks_like_ascii_point_cloud(b"x y z\n1 2 3\n\xff\xfe\n"));
+    }
+
+    #[test]
+    fn test_looks_like_ascii_point_cloud_cut_off_window() {
+        // A window that ends within the multi-byte character of a comment and within a record
+        let mut bytes = b"x y z\n1 2 3\n".to_vec();
+        while bytes.len() < MAGIC_BYTES_LENGTH - 1 {
+            bytes.extend_from_slice(b"# H\xc3\xb6he\n");
+        }
+        bytes.truncate(MAGIC_BYTES_LENGTH - 1);
+        bytes.push(0xc3);
+        assert!(looks_like_ascii_point_cloud(&bytes));
+
+        // The first record is cut off, so the window does not contain a single complete record
+        let mut bytes = b"x y z\n".to_vec();
+        bytes.resize(MAGIC_BYTES_LENGTH, b'1');
+        assert!(!looks_like_ascii_point_cloud(&bytes));
+        assert!(!looks_like_ascii_point_cloud(&[b'1'; MAGIC_BYTES_LENGTH]));
+    }
 }
@@ -1,16 +1,19 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use pasture_core::containers::BorrowedMutBuffer;
-use pasture_core::layout::PointLayout;
+use pasture_core::layout::{PointAttributeDataType, PointAttributeDefinition, PointLayout};
 use pasture_core::meta::Metadata;
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
-use crate::ascii::RawAsciiReader;
-use crate::base::PointReader;
+use crate::ascii::{
+    detect_delimiter, is_ascii_comment, is_ascii_header, split_ascii_columns, AsciiColumn,
+    PointDataType, RawAsciiReader,
+};
+use crate::base::{PointReader, SeekToPoint};
 
 /// `PointReader` implementation for ascii files
-
 pub struct AsciiReader<R: BufRead + Read> {
     raw_reader: RawAsciiReader<R>,
 }
@@ -43,6 +46,125 @@ impl AsciiReader<BufReader<File>> {
         let file = BufReader::new(File::open(path)?);
         Self::from_read(file, format, delimiter)
     }
+
+    /// Creates a new `AsciiReader` by opening the file at the given `path`, reading the given `columns`.
+    /// For more information see [`AsciiReader::from_read_with_columns`].
+    ///
+    /// # Errors
+    ///
+    /// If `path` does not exist or cannot be opened, an error is returned.
+    ///
+    /// If one of the `columns` is invalid, an error is returned.
+    pub fn from_path_with_columns<P: AsRef<Path>>(
+        path: P,
+        columns: &[AsciiColumn],
+        delimiter: &str,
+    ) -> Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        Self::from_read_with_columns(file, columns, delimiter)
+    }
+
+    /// Creates a new `AsciiReader` by opening the file at the given `path`. The delimiter and the columns are
+    /// inferred from the content of the file. For more information see [`AsciiReader::from_read_inferred`].
+    ///
+    /// # Errors
+    ///
+    /// If `path` does not exist, cannot be opened or does not contain any point records, an error is returned.
+    pub fn from_path_inferred<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = BufReader::new(
+            File::open(path.as_ref())
+                .with_context(|| format!("Could not open file {}", path.as_ref().display()))?,
+        );
+        Self::from_read_inferred(file)
+    }
+}
+
+impl<R: BufRead + Seek> AsciiReader<R> {
+    /// Creates a new `AsciiReader` from the given `read`, inferring the delimiter and the columns from the content.
+    /// Empty lines, comments (starting with `#` or `//`) and a point count (as in PTS files) before the first record
+    /// are skipped. The delimiter is
+    /// a tab, `;`, `,` or whitespace, in this order of preference.
+    ///
+    /// If the first line is a header (i.e. it contains a column that is not numeric), the column names are mapped
+    /// onto the builtin attributes, ignoring case, whitespace, `_` and `-`. `X`, `Y` and `Z` make up the position,
+    /// `Red`, `Green` and `Blue` (or `R`, `G` and `B`) the color and `Intensity`, `ReturnNumber`, `NumberOfReturns`,
+    /// `Classification`, `UserData`, `GpsTime`, `PointSourceID`, `EdgeOfFlightLine`, `ScanDirectionFlag`,
+    /// `ScanAngleRank` and `NIR` the respective attributes. All other columns become custom attributes with the
+    /// column name and `F64` datatype. Without a header, the first three columns are the position and all other
+    /// columns become custom `F64` attributes named `Column3`, `Column4` etc.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use anyhow::Result;
+    /// use pasture_core::containers::VectorBuffer;
+    /// use pasture_io::ascii::AsciiReader;
+    /// use pasture_io::base::PointReader;
+    /// fn main() -> Result<()> {
+    ///     let data = "X,Y,Z,Intensity,Reflectance\n0.0,1.0,2.0,11,-3.5\n1.0,-2.0,2.0,22,-4.0\n";
+    ///     let mut reader = AsciiReader::from_read_inferred(Cursor::new(data))?;
+    ///     let points = reader.read::<VectorBuffer>(2)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the given `Read` does not contain any point records, an error is returned.
+    pub fn from_read_inferred(mut read: R) -> Result<Self> {
+        let mut line = String::new();
+        loop {
+            let line_start = read.stream_position()?;
+            line.clear();
+            if read.read_line(&mut line)? == 0 {
+                bail!("ASCII file contains no point records");
+            }
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            // PTS files start with the number of points, which we skip
+            if is_ascii_comment(line) || line.trim().parse::<usize>().is_ok() {
+                continue;
+            }
+
+            let delimiter = detect_delimiter(line);
+            let (parse_layout, data_start) = if is_ascii_header(line, delimiter) {
+                let parse_layout = split_ascii_columns(line, delimiter)
+                    .map(PointDataType::from_column_name)
+                    .collect();
+                (parse_layout, read.stream_position()?)
+            } else {
+                let num_columns = split_ascii_columns(line, delimiter).count();
+                read.seek(SeekFrom::Start(line_start))?;
+                (default_parse_layout(num_columns), line_start)
+            };
+            return Ok(Self {
+                raw_reader: RawAsciiReader::from_parse_layout(
+                    read,
+                    parse_layout,
+                    delimiter,
+                    data_start,
+                ),
+            });
+        }
+    }
+}
+
+/// Returns the parse layout for an ASCII file without a header: The first three columns are the position, all other
+/// columns are custom `F64` attributes
+fn default_parse_layout(num_columns: usize) -> Vec<PointDataType> {
+    let mut parse_layout = vec![
+        PointDataType::CoordinateX,
+        PointDataType::CoordinateY,
+        PointDataType::CoordinateZ,
+    ];
+    parse_layout.truncate(num_columns);
+    parse_layout.extend((3..num_columns).map(|column| {
+        PointDataType::Column(AsciiColumn::Attribute(PointAttributeDefinition::custom(
+            Cow::Owned(format!("Column{}", column)),
+            PointAttributeDataType::F64,
+        )))
+    }));
+    parse_layout
 }
 
 impl<R: BufRead + Read> AsciiReader<R> {
@@ -98,6 +220,55 @@ impl<R: BufRead + Read> AsciiReader<R> {
         })
     }
 
+    /// Creates a new `AsciiReader` from the given `read`, where each column is described by one of the given
+    /// `columns`. In contrast to the format literals of [`AsciiReader::from_read`], this supports arbitrary
+    /// attributes of any scalar or vector datatype. The default `PointLayout` contains the attributes of all
+    /// `columns` in the order of their first column. If the attributes of a target buffer have a different datatype,
+    /// the values are parsed using the datatype of the buffer. Whitespace around values is ignored, and a
+    /// whitespace `delimiter` matches any amount of whitespace.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufReader;
+    /// use anyhow::Result;
+    /// use pasture_core::containers::VectorBuffer;
+    /// use pasture_core::layout::attributes::{NORMAL, POSITION_3D, SCAN_ANGLE};
+    /// use pasture_io::ascii::{AsciiColumn, AsciiReader};
+    /// use pasture_io::base::PointReader;
+    /// fn main() -> Result<()> {
+    ///     let data = "0.0 1.0 2.0 0.0 0.0 1.0 -120\n1.0 -2.0 2.0 1.0 0.0 0.0 45".as_bytes();
+    ///     let mut columns = AsciiColumn::all_components(POSITION_3D)?;
+    ///     columns.extend(AsciiColumn::all_components(NORMAL)?);
+    ///     columns.push(AsciiColumn::Attribute(SCAN_ANGLE));
+    ///     let mut reader = AsciiReader::from_read_with_columns(BufReader::new(data), &columns, " ")?;
+    ///     let points = reader.read::<VectorBuffer>(2)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If one of the `columns` has a datatype that can't be represented in an ASCII file, or a component index
+    /// that is out of range, an error is returned.
+    pub fn from_read_with_columns(
+        read: R,
+        columns: &[AsciiColumn],
+        delimiter: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            raw_reader: RawAsciiReader::from_columns(read, columns, delimiter)?,
+        })
+    }
+
+    /// Enables or disables parallel parsing. In parallel mode, the input is read in large batches of lines, which
+    /// are split into newline-aligned blocks that are parsed concurrently using rayon. The points are written
+    /// directly into the target buffer in their original order, and parse errors are reported for the first
+    /// erroneous line, exactly as in sequential mode. Parallel parsing is disabled by default
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.raw_reader.set_parallel(parallel);
+    }
+
     pub fn print_format_literals() {
         println!(
             "The following literals can be interpreted from this AsciiReader:
@@ -143,3 +314,134 @@ impl<R: BufRead + Read> PointReader for AsciiReader<R> {
         self.raw_reader.get_default_point_layout()
     }
 }
+
+impl<R: BufRead + Seek> SeekToPoint for AsciiReader<R> {
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        self.raw_reader.seek_point(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii::{get_test_file_path, test_data_positions};
+    use crate::base::{read_all, GenericPointReader};
+    use pasture_core::containers::{BorrowedBuffer, VectorBuffer};
+    use pasture_core::layout::attributes;
+    use pasture_core::nalgebra::Vector3;
+    use std::io::Cursor;
+
+    /// Writes the points from `10_points_ascii.txt` with a header line and the given `delimiter`
+    fn ascii_with_header(header: &str, delimiter: &str) -> Result<String> {
+        let data = std::fs::read_to_string(get_test_file_path("10_points_ascii.txt"))?;
+        let mut ascii = format!("{}\n", header);
+        for line in data.lines() {
+            ascii.push_str(&line.split(", ").collect::<Vec<_>>().join(delimiter));
+            ascii.push('\n');
+        }
+        Ok(ascii)
+    }
+
+    #[test]
+    fn test_read_inferred_with_header() -> Result<()> {
+        let ascii = ascii_with_header("X;Y;Z;Intensity;Edge_Of_Flight_Line;Red;Green;Blue", ";")?;
+        let mut reader = AsciiReader::from_read_inferred(Cursor::new(ascii))?;
+        let layout = reader.get_default_point_layout().clone();
+        assert!(layout.has_attribute(&attributes::POSITION_3D));
+        assert!(layout.has_attribute(&attributes::INTENSITY));
+        assert!(layout.has_attribute(&attributes::EDGE_OF_FLIGHT_LINE));
+        assert!(layout.has_attribute(&attributes::COLOR_RGB));
+
+        let points = reader.read::<VectorBuffer>(20)?;
+        assert_eq!(10, points.len());
+        assert_eq!(
+            test_data_positions(),
+            points
+                .view_attribute::<Vector3<f64>>(&attributes::POSITION_3D)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![67, 30, 76, 20, 45, 11, 61, 67, 23, 37],
+            points
+                .view_attribute::<u16>(&attributes::INTENSITY)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_inferred_custom_columns() -> Result<()> {
+        let ascii = "# exported point cloud\n\nx y z reflectance\n1 2 3 -0.5\n4  5  6  0.25\n";
+        let mut reader = AsciiReader::from_read_inferred(Cursor::new(ascii))?;
+        let reflectance = PointAttributeDefinition::custom(
+            Cow::Borrowed("reflectance"),
+            PointAttributeDataType::F64,
+        );
+        let points = reader.read::<VectorBuffer>(2)?;
+        assert_eq!(
+            vec![-0.5, 0.25],
+            points
+                .view_attribute::<f64>(&reflectance)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        let mut reader = AsciiReader::from_read_inferred(Cursor::new("1\t2\t3\t4\n"))?;
+        let column =
+            PointAttributeDefinition::custom(Cow::Borrowed("Column3"), PointAttributeDataType::F64);
+        let points = reader.read::<VectorBuffer>(1)?;
+        assert_eq!(
+            Vector3::new(1.0, 2.0, 3.0),
+            points
+                .view_attribute::<Vector3<f64>>(&attributes::POSITION_3D)
+                .at(0)
+        );
+        assert_eq!(4.0, points.view_attribute::<f64>(&column).at(0));
+
+        assert!(AsciiReader::from_read_inferred(Cursor::new("# only a comment\n")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seek_inferred() -> Result<()> {
+        let ascii = ascii_with_header("x,y,z,i,edge,r,g,b", ",")?;
+        let mut reader = AsciiReader::from_read_inferred(Cursor::new(ascii))?;
+        assert_eq!(10, reader.point_count()?);
+        assert_eq!(7, reader.seek_point(SeekFrom::End(-3))?);
+        let points = reader.read::<VectorBuffer>(10)?;
+        assert_eq!(
+            test_data_positions()[7..].to_vec(),
+            points
+                .view_attribute::<Vector3<f64>>(&attributes::POSITION_3D)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(8, reader.seek_point(SeekFrom::Current(-2))?);
+        assert_eq!(2, reader.seek_point(SeekFrom::Start(2))?);
+        assert!(reader.seek_point(SeekFrom::Current(-3)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_all_ascii() -> Result<()> {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_read_all_ascii.csv");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+        std::fs::write(&path, ascii_with_header("X,Y,Z,Intensity,Edge,R,G,B", ",")?)?;
+
+        assert_eq!("ASCII", GenericPointReader::open_file(&path)?.format_name());
+        let points = read_all::<VectorBuffer, _>(&path)?;
+        assert_eq!(
+            test_data_positions(),
+            points
+                .view_attribute::<Vector3<f64>>(&attributes::POSITION_3D)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
@@ -9,7 +9,7 @@ use pasture_core::{containers::BorrowedBuffer, layout::PointLayout};
 
 use crate::base::PointWriter;
 
-use super::{AsciiFormat, RawAsciiWriter};
+use super::{AsciiColumn, AsciiFormat, RawAsciiWriter};
 
 /// `PointWriterFormatting` implementation for Ascii files
 pub struct AsciiWriter<T: Write + Seek> {
@@ -43,6 +43,22 @@ impl AsciiWriter<BufWriter<File>> {
         let file = BufWriter::new(File::create(path)?);
         Self::from_write(file, format)
     }
+
+    /// Creates a new `AsciiWriter` by opening the file at the given `path`, writing the given `columns`.
+    /// For more information see [`AsciiWriter::from_write_with_columns`].
+    ///
+    /// # Errors
+    ///
+    /// If `path` cannot be created or overwritten, an error is returned.
+    ///
+    /// If one of the `columns` is invalid, an error is returned.
+    pub fn from_path_with_columns<P: AsRef<Path>>(
+        path: P,
+        columns: &[AsciiColumn],
+    ) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Self::from_write_with_columns(file, columns)
+    }
 }
 
 impl<T: Write + Seek> AsciiWriter<T> {
@@ -92,6 +108,60 @@ impl<T: Write + Seek> AsciiWriter<T> {
             raw_writer: RawAsciiWriter::from_write(write, format)?,
         })
     }
+
+    /// Creates a new `AsciiWriter` from the given `write`, where each column is described by one of the given
+    /// `columns`. In contrast to the format literals of [`AsciiWriter::from_write`], this supports arbitrary
+    /// attributes of any scalar or vector datatype, see [`AsciiColumn`]. Attributes are looked up by name in the
+    /// written buffers and written using their datatype within the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::BufWriter;
+    /// use std::fs::File;
+    /// use anyhow::Result;
+    /// use pasture_core::layout::attributes::{NORMAL, POSITION_3D, POINT_ID};
+    /// use pasture_io::ascii::{AsciiColumn, AsciiWriter};
+    /// fn main() -> Result<()> {
+    ///     let mut columns = AsciiColumn::all_components(POSITION_3D)?;
+    ///     columns.extend(AsciiColumn::all_components(NORMAL)?);
+    ///     columns.push(AsciiColumn::Attribute(POINT_ID));
+    ///     let write = BufWriter::new(File::create("output.csv")?);
+    ///     let mut writer = AsciiWriter::from_write_with_columns(write, &columns)?;
+    ///     writer.set_delimiter(",");
+    ///     writer.set_precision(3);
+    ///     writer.set_write_header(true);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If one of the `columns` has a datatype that can't be represented in an ASCII file, or a component index
+    /// that is out of range, an error is returned.
+    pub fn from_write_with_columns(write: T, columns: &[AsciiColumn]) -> Result<Self> {
+        Ok(Self {
+            raw_writer: RawAsciiWriter::from_write_with_columns(write, columns)?,
+        })
+    }
+
+    /// Sets the delimiter between two columns. The default delimiter is `", "`
+    pub fn set_delimiter(&mut self, delimiter: &str) {
+        self.raw_writer.set_delimiter(delimiter);
+    }
+
+    /// Sets the maximum number of decimal places of floating point values. The default precision is 5
+    pub fn set_precision(&mut self, precision: usize) {
+        self.raw_writer.set_precision(precision);
+    }
+
+    /// Enables or disables writing a header line with the names of all columns before the first point (see
+    /// [`AsciiColumn::header_name`]). Files with a header can be read with [`AsciiReader::from_read_inferred`]
+    ///
+    /// [`AsciiReader::from_read_inferred`]: super::AsciiReader::from_read_inferred
+    pub fn set_write_header(&mut self, write_header: bool) {
+        self.raw_writer.set_write_header(write_header);
+    }
 }
 
 impl<T: Write + Seek> PointWriter for AsciiWriter<T> {
@@ -117,3 +187,148 @@ impl<T: Write + Seek> AsciiFormat for AsciiWriter<T> {
         self.raw_writer.set_precision(precision);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, io::Cursor};
+
+    use pasture_core::{
+        containers::{
+            BorrowedBuffer, BorrowedMutBuffer, InterleavedBuffer, MakeBufferFromLayout,
+            OwningBuffer, VectorBuffer,
+        },
+        layout::{
+            attributes::{NORMAL, POINT_ID, POSITION_3D, SCAN_ANGLE},
+            PointAttributeDataType, PointAttributeDefinition,
+        },
+        nalgebra::Vector3,
+    };
+
+    use super::*;
+    use crate::{ascii::AsciiReader, base::PointReader};
+
+    const TEMPERATURE: PointAttributeDefinition =
+        PointAttributeDefinition::custom(Cow::Borrowed("Temperature"), PointAttributeDataType::F32);
+
+    fn test_points() -> VectorBuffer {
+        let layout =
+            PointLayout::from_attributes(&[POSITION_3D, NORMAL, SCAN_ANGLE, POINT_ID, TEMPERATURE]);
+        let mut points = VectorBuffer::new_from_layout(layout);
+        points.resize(3);
+        for index in 0..3 {
+            let value = index as f64;
+            points
+                .view_attribute_mut(&POSITION_3D)
+                .set_at(index, Vector3::new(value + 0.5, -value, 1000.126));
+            points
+                .view_attribute_mut(&NORMAL)
+                .set_at(index, Vector3::new(0.0_f32, 0.5, -0.75));
+            points
+                .view_attribute_mut(&SCAN_ANGLE)
+                .set_at(index, -15_000_i16 + index as i16);
+            points
+                .view_attribute_mut(&POINT_ID)
+                .set_at(index, u64::MAX - index as u64);
+            points
+                .view_attribute_mut(&TEMPERATURE)
+                .set_at(index, 21.25_f32 * value as f32);
+        }
+        points
+    }
+
+    fn test_columns() -> Result<Vec<AsciiColumn>> {
+        let mut columns = AsciiColumn::all_components(POSITION_3D)?;
+        columns.extend(AsciiColumn::all_components(NORMAL)?);
+        columns.push(AsciiColumn::Attribute(SCAN_ANGLE));
+        columns.push(AsciiColumn::Attribute(POINT_ID));
+        columns.push(AsciiColumn::Attribute(TEMPERATURE));
+        Ok(columns)
+    }
+
+    #[test]
+    fn test_write_columns_with_header() -> Result<()> {
+        let points = test_points();
+        let mut bytes = Cursor::new(vec![]);
+        {
+            let mut writer = AsciiWriter::from_write_with_columns(&mut bytes, &test_columns()?)?;
+            writer.set_delimiter(",");
+            writer.set_precision(2);
+            writer.set_write_header(true);
+            writer.write(&points)?;
+            writer.flush()?;
+        }
+        let ascii = String::from_utf8(bytes.into_inner())?;
+        let lines = ascii.lines().collect::<Vec<_>>();
+        assert_eq!(
+            "X,Y,Z,Normal.x,Normal.y,Normal.z,ScanAngle,PointID,Temperature",
+            lines[0]
+        );
+        assert_eq!(
+            "1.5,-1.0,1000.13,0.0,0.5,-0.75,-14999,18446744073709551614,21.25",
+            lines[2]
+        );
+
+        // Reading back into the original layout uses the datatypes of the layout
+        let mut reader = AsciiReader::from_read_inferred(Cursor::new(ascii))?;
+        let mut read_points = VectorBuffer::new_from_layout(points.point_layout().clone());
+        read_points.resize(3);
+        assert_eq!(3, reader.read_into(&mut read_points, 3)?);
+        for attribute in [NORMAL, SCAN_ANGLE, POINT_ID, TEMPERATURE].iter() {
+            let attribute_offset = points.point_layout().offset_of(attribute).unwrap() as usize;
+            let size = attribute.size() as usize;
+            for index in 0..3 {
+                assert_eq!(
+                    &points.get_point_ref(index)[attribute_offset..attribute_offset + size],
+                    &read_points.get_point_ref(index)[attribute_offset..attribute_offset + size],
+                    "Values of attribute {} differ",
+                    attribute
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_columns() -> Result<()> {
+        let ascii = "1 2 3 0.5 0.25 1 -3 17 4.5\n";
+        let mut reader =
+            AsciiReader::from_read_with_columns(Cursor::new(ascii), &test_columns()?, " ")?;
+        assert_eq!(
+            &PointLayout::from_attributes(&[
+                POSITION_3D,
+                NORMAL,
+                SCAN_ANGLE,
+                POINT_ID,
+                TEMPERATURE
+            ]),
+            reader.get_default_point_layout()
+        );
+        let points = reader.read::<VectorBuffer>(1)?;
+        assert_eq!(
+            Vector3::new(0.5_f32, 0.25, 1.0),
+            points.view_attribute::<Vector3<f32>>(&NORMAL).at(0)
+        );
+        assert_eq!(-3, points.view_attribute::<i16>(&SCAN_ANGLE).at(0));
+        assert_eq!(17, points.view_attribute::<u64>(&POINT_ID).at(0));
+        assert_eq!(4.5, points.view_attribute::<f32>(&TEMPERATURE).at(0));
+
+        let mut reader = AsciiReader::from_read_with_columns(
+            Cursor::new("1 2 3 1.5\n"),
+            &[
+                AsciiColumn::Skip,
+                AsciiColumn::Skip,
+                AsciiColumn::Skip,
+                AsciiColumn::Attribute(SCAN_ANGLE),
+            ],
+            " ",
+        )?;
+        assert!(reader.read::<VectorBuffer>(1).is_err());
+        assert!(AsciiReader::from_read_with_columns(
+            Cursor::new(""),
+            &[AsciiColumn::Attribute(NORMAL)],
+            " "
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -7,6 +7,9 @@ pub use self::ascii_writer::*;
 mod ascii_metadata;
 pub use self::ascii_metadata::*;
 
+mod ascii_column;
+pub use self::ascii_column::*;
+
 mod raw_reader;
 pub(crate) use self::raw_reader::*;
 
@@ -6,14 +6,14 @@ use pasture_core::layout::attributes;
 use pasture_core::layout::PointAttributeDefinition;
 use pasture_core::layout::PointLayout;
 use pasture_core::meta::Metadata;
-use std::collections::HashSet;
-use std::io::{BufRead, Read};
-use std::iter::FromIterator;
+use rayon::prelude::*;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::str::FromStr;
 
 use super::AsciiMetadata;
-use super::PointDataType;
-use crate::base::PointReader;
+use super::{parse_scalar, split_ascii_columns, split_datatype, AsciiColumn, PointDataType};
+use crate::base::{PointReader, SeekToPoint};
 use pasture_core::containers::{UntypedPoint, UntypedPointBuffer};
 
 pub(crate) struct RawAsciiReader<T: Read + BufRead> {
@@ -22,26 +22,188 @@ pub(crate) struct RawAsciiReader<T: Read + BufRead> {
     delimiter: String,
     point_layout: PointLayout,
     parse_layout: Vec<PointDataType>,
+    current_point: usize,
+    /// Byte offset of the first point record within `reader`, used for seeking
+    data_start: u64,
+    parallel: bool,
+    lines_per_block: usize,
 }
+
+/// Number of lines that are read at once when parsing in parallel
+const PARALLEL_LINES_PER_BATCH: usize = 1 << 16;
+/// Number of lines that are parsed by a single task when parsing in parallel
+const PARALLEL_LINES_PER_BLOCK: usize = 1 << 10;
 impl<T: Read + BufRead> RawAsciiReader<T> {
     pub fn from_read(read: T, format: &str, delimiter: &str) -> Result<Self> {
         let parse_layout = PointDataType::get_parse_layout(format)?;
+        Ok(Self::from_parse_layout(read, parse_layout, delimiter, 0))
+    }
+
+    pub fn from_columns(read: T, columns: &[AsciiColumn], delimiter: &str) -> Result<Self> {
+        let parse_layout = columns
+            .iter()
+            .map(|column| {
+                column.validate()?;
+                Ok(PointDataType::Column(column.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_parse_layout(read, parse_layout, delimiter, 0))
+    }
+
+    /// Creates a new `RawAsciiReader` from an existing `parse_layout`. The first point record in `read` starts at
+    /// byte offset `data_start`, which must be the current position of `read`
+    pub fn from_parse_layout(
+        read: T,
+        parse_layout: Vec<PointDataType>,
+        delimiter: &str,
+        data_start: u64,
+    ) -> Self {
         let layout = Self::get_point_layout_from_parse_layout(&parse_layout);
         let metadata = AsciiMetadata;
 
-        Ok(Self {
+        Self {
             reader: read,
             metadata,
             delimiter: delimiter.to_string(),
             point_layout: layout,
             parse_layout,
-        })
+            current_point: 0,
+            data_start,
+            parallel: false,
+            lines_per_block: PARALLEL_LINES_PER_BLOCK,
+        }
+    }
+
+    /// Enables or disables parsing the lines in parallel
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    fn read_into_sequential<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let layout = point_buffer.point_layout().clone();
+        let mut temp_point = UntypedPointBuffer::new(&layout);
+        let mut points_read = 0;
+        //read line by line
+        for (index, line) in (&mut self.reader).lines().take(count).enumerate() {
+            let line = line?;
+            //parse the line in an untypedpoint
+            Self::parse_point(&mut temp_point, &line, &self.delimiter, &self.parse_layout)
+                .with_context(|| format!("ReadError in line {}.", index))?;
+            //put it in the buffer
+            unsafe {
+                point_buffer.set_point(index, temp_point.get_buffer());
+            }
+            points_read += 1;
+        }
+        Ok(points_read)
+    }
+
+    /// Reads batches of lines sequentially and splits each batch into newline-aligned blocks, which are parsed in
+    /// parallel. Interleaved buffers are parsed into directly, for all other buffers each batch is parsed into a
+    /// temporary interleaved buffer first. Errors are reported for the first erroneous line, like in the sequential
+    /// case
+    fn read_into_parallel<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let layout = point_buffer.point_layout().clone();
+        let size_of_point = layout.size_of_point_entry() as usize;
+        let bytes_per_block = (self.lines_per_block * size_of_point).max(1);
+        let mut text = vec![];
+        let mut line_ranges: Vec<Range<usize>> = vec![];
+        let mut batch_points = vec![];
+        let mut points_read = 0;
+        while points_read < count {
+            text.clear();
+            line_ranges.clear();
+            let lines_in_batch = PARALLEL_LINES_PER_BATCH.min(count - points_read);
+            while line_ranges.len() < lines_in_batch {
+                let line_start = text.len();
+                if self.reader.read_until(b'\n', &mut text)? == 0 {
+                    break;
+                }
+                let line = &text[line_start..];
+                let line = line.strip_suffix(b"\n").unwrap_or(line);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                line_ranges.push(line_start..line_start + line.len());
+            }
+            if line_ranges.is_empty() {
+                break;
+            }
+
+            let first_point_in_batch = points_read;
+            let num_points_in_batch = line_ranges.len();
+            let delimiter = self.delimiter.as_str();
+            let parse_layout = self.parse_layout.as_slice();
+            let lines_per_block = self.lines_per_block;
+            let parse_block =
+                |block_index: usize, block_lines: &[Range<usize>], block_points: &mut [u8]| {
+                    let mut temp_point = UntypedPointBuffer::new(&layout);
+                    for (index_in_block, (line_range, point)) in block_lines
+                        .iter()
+                        .zip(block_points.chunks_exact_mut(size_of_point))
+                        .enumerate()
+                    {
+                        let index =
+                            first_point_in_batch + block_index * lines_per_block + index_in_block;
+                        let line = std::str::from_utf8(&text[line_range.clone()])
+                            .with_context(|| format!("ReadError in line {}.", index))?;
+                        Self::parse_point(&mut temp_point, line, delimiter, parse_layout)
+                            .with_context(|| format!("ReadError in line {}.", index))?;
+                        point.copy_from_slice(temp_point.get_buffer());
+                    }
+                    Ok(())
+                };
+            let parse_batch = |batch_points: &mut [u8]| -> Result<()> {
+                line_ranges
+                    .par_chunks(lines_per_block)
+                    .zip(batch_points.par_chunks_mut(bytes_per_block))
+                    .enumerate()
+                    .map(|(block_index, (block_lines, block_points))| {
+                        parse_block(block_index, block_lines, block_points)
+                    })
+                    .collect::<Vec<Result<()>>>()
+                    .into_iter()
+                    .collect()
+            };
+
+            let batch_range = first_point_in_batch..(first_point_in_batch + num_points_in_batch);
+            if let Some(interleaved) = point_buffer.as_interleaved_mut() {
+                parse_batch(interleaved.get_point_range_mut(batch_range))?;
+            } else {
+                batch_points.resize(num_points_in_batch * size_of_point, 0);
+                parse_batch(&mut batch_points)?;
+                // Safe because `batch_points` contains the points in the layout of `point_buffer`
+                unsafe {
+                    point_buffer.set_point_range(batch_range, &batch_points);
+                }
+            }
+            points_read += num_points_in_batch;
+        }
+        Ok(points_read)
     }
 
     fn get_point_layout_from_parse_layout(parse_layout: &[PointDataType]) -> PointLayout {
-        let hashset = parse_layout
+        // Attributes are ordered by their first column, so that the layout is deterministic
+        let attributes = parse_layout
             .iter()
-            .filter(|data_type| !matches!(data_type, PointDataType::Skip))
+            .filter(|data_type| {
+                !matches!(
+                    data_type,
+                    PointDataType::Skip | PointDataType::Column(AsciiColumn::Skip)
+                )
+            })
             .map(|data_type| match data_type {
                 PointDataType::CoordinateX
                 | PointDataType::CoordinateY
@@ -60,10 +222,14 @@ impl<T: Read + BufRead> RawAsciiReader<T> {
                 PointDataType::ScanDirectionFlag => attributes::SCAN_DIRECTION_FLAG,
                 PointDataType::ScanAngleRank => attributes::SCAN_ANGLE_RANK,
                 PointDataType::Nir => attributes::NIR,
+                PointDataType::Column(column) => {
+                    column.attribute().expect("Skip should be filtered").clone()
+                }
                 PointDataType::Skip => panic!("Skip should be filtered"),
             })
-            .collect::<HashSet<_>>();
-        PointLayout::from_attributes(&Vec::from_iter(hashset))
+            .unique()
+            .collect::<Vec<_>>();
+        PointLayout::from_attributes(&attributes)
     }
 
     fn parse_point(
@@ -72,7 +238,7 @@ impl<T: Read + BufRead> RawAsciiReader<T> {
         delimiter: &str,
         parse_layout: &[PointDataType],
     ) -> Result<()> {
-        for pair in line.split(delimiter).zip_longest(parse_layout) {
+        for pair in split_ascii_columns(line, delimiter).zip_longest(parse_layout) {
             match pair {
                 Both(value_str, data_type) => match data_type {
                     PointDataType::CoordinateX => {
@@ -178,6 +344,11 @@ impl<T: Read + BufRead> RawAsciiReader<T> {
                         Self::parse_to_point_u16(point, &attributes::NIR, 0, value_str)
                             .with_context(|| generate_parse_error(data_type, 'I'))?;
                     }
+                    PointDataType::Column(column) => {
+                        Self::parse_to_point_column(point, column, value_str).with_context(
+                            || format!("ParseError at parsing column {}.", column.header_name()),
+                        )?;
+                    }
                     PointDataType::Skip => {}
                 },
                 Left(_) => continue,
@@ -272,6 +443,42 @@ impl<T: Read + BufRead> RawAsciiReader<T> {
         Ok(())
     }
 
+    /// Parses the value of `column`. The value is parsed using the datatype that the attribute has within the
+    /// layout of `point`, which might differ from the datatype of the attribute of `column`
+    fn parse_to_point_column(
+        point: &mut UntypedPointBuffer,
+        column: &AsciiColumn,
+        value_str: &str,
+    ) -> Result<()> {
+        let (attribute, component) = match column {
+            AsciiColumn::Skip => return Ok(()),
+            AsciiColumn::Attribute(attribute) => (attribute, 0),
+            AsciiColumn::Component(attribute, component) => (attribute, *component),
+        };
+        let (attribute_offset, attribute_datatype) =
+            match point.get_layout().get_attribute_by_name(attribute.name()) {
+                Some(member) => (member.offset() as usize, member.datatype()),
+                None => return Ok(()),
+            };
+        let (component_datatype, num_components) = split_datatype(attribute_datatype)?;
+        if component >= num_components {
+            bail!(
+                "Component {} is out of range for attribute {} with datatype {}",
+                component,
+                attribute.name(),
+                attribute_datatype
+            );
+        }
+        let size_of_component = component_datatype.size() as usize;
+        let start = attribute_offset + component * size_of_component;
+        let mut cursor = point.get_cursor();
+        parse_scalar(
+            component_datatype,
+            value_str,
+            &mut cursor.get_mut()[start..start + size_of_component],
+        )
+    }
+
     fn parse_string<V: FromStr>(value_str: &str) -> Result<V, anyhow::Error> {
         value_str.parse::<V>().map_err(|_| {
             anyhow::anyhow!(
@@ -299,20 +506,13 @@ impl<T: Read + BufRead> PointReader for RawAsciiReader<T> {
     where
         'a: 'b,
     {
-        let layout = point_buffer.point_layout().clone();
-        let mut temp_point = UntypedPointBuffer::new(&layout);
-        //read line by line
-        for (index, line) in (&mut self.reader).lines().take(count).enumerate() {
-            let line = line?;
-            //parse the line in an untypedpoint
-            Self::parse_point(&mut temp_point, &line, &self.delimiter, &self.parse_layout)
-                .with_context(|| format!("ReadError in line {}.", index))?;
-            //put it in the buffer
-            unsafe {
-                point_buffer.set_point(index, temp_point.get_buffer());
-            }
-        }
-        Ok(count)
+        let points_read = if self.parallel {
+            self.read_into_parallel(point_buffer, count)?
+        } else {
+            self.read_into_sequential(point_buffer, count)?
+        };
+        self.current_point += points_read;
+        Ok(points_read)
     }
 
     fn get_default_point_layout(&self) -> &PointLayout {
@@ -324,6 +524,45 @@ impl<T: Read + BufRead> PointReader for RawAsciiReader<T> {
     }
 }
 
+impl<T: Read + BufRead + Seek> RawAsciiReader<T> {
+    /// Moves to the start of the point records and skips `count` records. Returns the number of skipped records,
+    /// which is less than `count` if the end of the file is reached
+    fn skip_from_start(&mut self, count: usize) -> Result<usize> {
+        self.reader.seek(SeekFrom::Start(self.data_start))?;
+        let mut skipped = 0;
+        let mut line = String::new();
+        while skipped < count {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            skipped += 1;
+        }
+        self.current_point = skipped;
+        Ok(skipped)
+    }
+}
+
+impl<T: Read + BufRead + Seek> SeekToPoint for RawAsciiReader<T> {
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        let target_point = match position {
+            SeekFrom::Start(point) => point as i64,
+            SeekFrom::Current(offset) => self.current_point as i64 + offset,
+            SeekFrom::End(offset) => {
+                // ASCII files have no header with the number of points, so we have to count all records
+                let current_point = self.current_point;
+                let point_count = self.skip_from_start(usize::MAX)?;
+                self.skip_from_start(current_point)?;
+                point_count as i64 + offset
+            }
+        };
+        if target_point < 0 {
+            bail!("Can't seek to a position before the first point");
+        }
+        self.skip_from_start(target_point as usize)
+    }
+}
+
 // Ascii Tests
 //  - Reading
 //      - `read` has to be correct.
@@ -347,7 +586,8 @@ mod tests {
     };
     use anyhow::Result;
     use pasture_core::containers::{
-        BorrowedBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+        BorrowedBuffer, HashMapBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer,
+        VectorBuffer,
     };
     use pasture_core::layout::{attributes, PointType};
     use pasture_core::nalgebra::Vector3;
@@ -594,6 +834,84 @@ mod tests {
         Ok(())
     }
 
+    fn parallel_reader(path: &str, format: &str) -> Result<RawAsciiReader<BufReader<File>>> {
+        let reader = BufReader::new(File::open(get_test_file_path(path))?);
+        let mut ascii_reader = RawAsciiReader::from_read(reader, format, ", ")?;
+        ascii_reader.set_parallel(true);
+        // Use small blocks, so that the test files are split into multiple blocks
+        ascii_reader.lines_per_block = 3;
+        Ok(ascii_reader)
+    }
+
+    #[test]
+    fn test_read_parallel() -> Result<()> {
+        let path = get_test_file_path("10_points_ascii_all_attributes.txt");
+        let format = "xyzirncuRGBtpedaI";
+        let expected_points =
+            RawAsciiReader::from_read(BufReader::new(File::open(&path)?), format, ", ")?
+                .read::<VectorBuffer>(10)?;
+
+        let mut ascii_reader = parallel_reader("10_points_ascii_all_attributes.txt", format)?;
+        let points = ascii_reader.read::<VectorBuffer>(4)?;
+        assert_eq!(
+            expected_points.get_point_range_ref(0..4),
+            points.get_point_range_ref(0..4)
+        );
+        // Non-interleaved buffers are supported as well
+        let points = ascii_reader.read::<HashMapBuffer>(20)?;
+        assert_eq!(6, points.len());
+        assert_eq!(
+            test_data_positions()[4..].to_vec(),
+            points
+                .view_attribute::<Vector3<f64>>(&attributes::POSITION_3D)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            test_data_nirs()[4..].to_vec(),
+            points
+                .view_attribute::<u16>(&attributes::NIR)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0, ascii_reader.read::<VectorBuffer>(10)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors_parallel() {
+        let errors = [
+            (
+                "sssi",
+                "ParseError at parsing Intensity for format literal 'i'",
+                0,
+            ),
+            ("sssse", "ParseError expected bool found", 1),
+            (
+                "x",
+                "ParseError at parsing CoordinateX for format literal 'x'",
+                2,
+            ),
+        ];
+        for (format, expected_error, expected_line) in errors.iter() {
+            let error = parallel_reader("10_points_ascii_parsing_errors.txt", format)
+                .unwrap()
+                .read::<VectorBuffer>(10)
+                .expect_err("Parsing should fail");
+            let error_message = format!("{:#}", error);
+            assert!(
+                error_message.starts_with(&format!("ReadError in line {}.", expected_line)),
+                "Unexpected error {}",
+                error_message
+            );
+            assert!(
+                error_message.contains(expected_error),
+                "Unexpected error {}",
+                error_message
+            );
+        }
+    }
+
     #[test]
     #[should_panic(expected = "FormatError can't interpret format literal")]
     fn test_error_format_unrecognized_literal() {
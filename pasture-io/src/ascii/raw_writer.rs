@@ -1,6 +1,7 @@
-use super::PointDataType;
+use super::{format_scalar, split_datatype, AsciiColumn, PointDataType};
 use crate::base::PointWriter;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use pasture_core::containers::{BorrowedBuffer, UntypedPoint, UntypedPointSlice};
 use pasture_core::layout::{attributes, PointLayout};
 use pasture_core::nalgebra::Vector3;
@@ -16,6 +17,8 @@ pub(crate) struct RawAsciiWriter<T: std::io::Write + std::io::Seek> {
     precision: usize,
     parse_layout: Vec<PointDataType>,
     default_layout: PointLayout,
+    write_header: bool,
+    header_written: bool,
 }
 
 impl<T: std::io::Write + std::io::Seek> RawAsciiWriter<T> {
@@ -26,8 +29,82 @@ impl<T: std::io::Write + std::io::Seek> RawAsciiWriter<T> {
             precision: 5,
             parse_layout: PointDataType::get_parse_layout(format)?,
             default_layout: PointLayout::default(),
+            write_header: false,
+            header_written: false,
         })
     }
+
+    pub fn from_write_with_columns(write: T, columns: &[AsciiColumn]) -> Result<Self> {
+        let parse_layout = columns
+            .iter()
+            .map(|column| {
+                column.validate()?;
+                Ok(PointDataType::Column(column.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            writer: write,
+            delimiter: String::from(", "),
+            precision: 5,
+            parse_layout,
+            default_layout: PointLayout::default(),
+            write_header: false,
+            header_written: false,
+        })
+    }
+
+    /// Enables or disables writing a header line with the names of all columns before the first point
+    pub fn set_write_header(&mut self, write_header: bool) {
+        self.write_header = write_header;
+    }
+
+    fn write_header_if_required(&mut self) -> Result<()> {
+        if !self.write_header || self.header_written {
+            return Ok(());
+        }
+        let header = self
+            .parse_layout
+            .iter()
+            .map(|data_type| data_type.as_column().header_name())
+            .join(&self.delimiter);
+        self.writer.write_all(header.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.header_written = true;
+        Ok(())
+    }
+
+    /// Formats the value of `column` using the datatype that the attribute has within the layout of `point`
+    fn format_column<P: UntypedPoint>(
+        point: &P,
+        column: &AsciiColumn,
+        precision: usize,
+    ) -> Result<String> {
+        let (attribute, component) = match column {
+            AsciiColumn::Skip => return Ok(String::new()),
+            AsciiColumn::Attribute(attribute) => (attribute, 0),
+            AsciiColumn::Component(attribute, component) => (attribute, *component),
+        };
+        let member = point
+            .get_layout()
+            .get_attribute_by_name(attribute.name())
+            .with_context(|| "Cannot find attribute.")?;
+        let (component_datatype, num_components) = split_datatype(member.datatype())?;
+        if component >= num_components {
+            bail!(
+                "Component {} is out of range for attribute {} with datatype {}",
+                component,
+                member.name(),
+                member.datatype()
+            );
+        }
+        let size_of_component = component_datatype.size() as usize;
+        let value = point.get_raw_attribute(member.attribute_definition())?;
+        format_scalar(
+            component_datatype,
+            &value[component * size_of_component..(component + 1) * size_of_component],
+            precision,
+        )
+    }
 }
 
 impl<T: std::io::Write + std::io::Seek> AsciiFormat for RawAsciiWriter<T> {
@@ -43,6 +120,7 @@ impl<T: std::io::Write + std::io::Seek> PointWriterFormatting for RawAsciiWriter
 impl<T: std::io::Write + std::io::Seek> PointWriter for RawAsciiWriter<T> {
     fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> anyhow::Result<()> {
         //let point = UntypedPointBuffer::new(&self.default_layout);
+        self.write_header_if_required()?;
         let buffer_layout = points.point_layout();
 
         // Similar to RawLASReader, write points in chunks of a fixed size to prevent overhead of
@@ -189,6 +267,10 @@ impl<T: std::io::Write + std::io::Seek> PointWriter for RawAsciiWriter<T> {
                             let nir = point.get_attribute::<u64>(&attributes::NIR)?;
                             self.writer.write_all(nir.to_string().as_bytes())?;
                         }
+                        PointDataType::Column(column) => {
+                            let value = Self::format_column(&point, column, self.precision)?;
+                            self.writer.write_all(value.as_bytes())?;
+                        }
                     }
                     if index != self.parse_layout.len() - 1 {
                         self.writer.write_all(self.delimiter.as_bytes())?;
@@ -201,6 +283,7 @@ impl<T: std::io::Write + std::io::Seek> PointWriter for RawAsciiWriter<T> {
     }
 
     fn flush(&mut self) -> anyhow::Result<()> {
+        self.write_header_if_required()?;
         self.writer.flush().context("Flush failed")
     }
 
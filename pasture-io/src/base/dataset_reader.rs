@@ -0,0 +1,610 @@
+use std::any::Any;
+use std::fmt::Display;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::containers::{
+    BorrowedBuffer, BorrowedMutBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer,
+    VectorBuffer,
+};
+use pasture_core::layout::attributes::POSITION_3D;
+use pasture_core::layout::conversion::BufferLayoutConverter;
+use pasture_core::layout::{PointAttributeDefinition, PointLayout};
+use pasture_core::math::AABB;
+use pasture_core::meta::Metadata;
+use pasture_core::nalgebra::{Point3, Vector3};
+
+use super::{format_for_extension, GenericPointReader, PointReader, SeekToPoint};
+
+/// A single file of a [`DatasetReader`], with the information from its header
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetFile {
+    pub path: PathBuf,
+    /// Bounds of the points in the file as stated in its header, or `None` if the format of the file has no bounds
+    /// information in its header
+    pub bounds: Option<AABB<f64>>,
+    pub point_count: usize,
+    /// Index of the first point of this file within the whole dataset
+    pub first_point_index: usize,
+}
+
+impl DatasetFile {
+    /// Returns the range of the points of this file within the whole dataset
+    pub fn point_range(&self) -> Range<usize> {
+        self.first_point_index..(self.first_point_index + self.point_count)
+    }
+
+    /// Returns `true` if points of this file might lie within the given `bounds`. Files without bounds information
+    /// might always contain such points
+    pub fn may_intersect(&self, bounds: &AABB<f64>) -> bool {
+        match &self.bounds {
+            Some(file_bounds) => file_bounds.intersects(bounds),
+            None => true,
+        }
+    }
+}
+
+/// `Metadata` implementation for a [`DatasetReader`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetMetadata {
+    bounds: Option<AABB<f64>>,
+    point_count: usize,
+    file_count: usize,
+}
+
+impl DatasetMetadata {
+    /// Returns the number of files in the dataset
+    pub fn file_count(&self) -> usize {
+        self.file_count
+    }
+}
+
+impl Metadata for DatasetMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        self.bounds
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.point_count)
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "FILE_COUNT" => Some(Box::new(self.file_count)),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
+
+impl Display for DatasetMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "DatasetMetadata {{")?;
+        writeln!(f, "\tfile_count: {}", self.file_count)?;
+        writeln!(f, "\tpoint_count: {}", self.point_count)?;
+        match &self.bounds {
+            Some(bounds) => writeln!(f, "\tbounds: {} - {}", bounds.min(), bounds.max())?,
+            None => writeln!(f, "\tbounds: unknown")?,
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// `PointReader` for a dataset that consists of many point cloud files, e.g. a survey that is split into LAS/LAZ
+/// tiles. The headers of all files are read upfront, after that the `DatasetReader` behaves like a single
+/// `PointReader` for all points of all files, in the order in which the files were given. Points are indexed
+/// globally, so [`seek_point`](SeekToPoint::seek_point) can be used to jump to any point in any file. Only a
+/// single file is open at any time.
+///
+/// The default `PointLayout` contains all attributes of all files, in the order in which they first appear. The
+/// points of each file are converted into the `PointLayout` of the target buffer using a `BufferLayoutConverter`,
+/// attributes that a file does not contain are filled with default values.
+///
+/// Using [`read_query`](Self::read_query), all points within a bounding box can be read. This only opens the files
+/// whose header bounds intersect the bounding box
+pub struct DatasetReader {
+    files: Vec<DatasetFile>,
+    file_layouts: Vec<PointLayout>,
+    layout: PointLayout,
+    metadata: DatasetMetadata,
+    current_point_index: usize,
+    /// The currently open file, together with its index in `files`
+    current_reader: Option<(usize, GenericPointReader)>,
+}
+
+impl DatasetReader {
+    /// Creates a new `DatasetReader` for the point cloud files at the given `paths`. The file formats are determined
+    /// in the same way as for [`GenericPointReader::open_file`]
+    ///
+    /// # Errors
+    ///
+    /// If `paths` is empty, or if one of the files can't be opened, an error is returned
+    pub fn from_paths<P: AsRef<Path>, I: IntoIterator<Item = P>>(paths: I) -> Result<Self> {
+        let mut files = vec![];
+        let mut file_layouts = vec![];
+        let mut first_point_index = 0;
+        for path in paths {
+            let path = path.as_ref();
+            let mut reader = GenericPointReader::open_file(path)
+                .with_context(|| format!("Could not open point cloud file {}", path.display()))?;
+            let point_count = match reader.point_count() {
+                Some(point_count) => point_count,
+                None => SeekToPoint::point_count(&mut reader).with_context(|| {
+                    format!("Could not determine point count of {}", path.display())
+                })?,
+            };
+            files.push(DatasetFile {
+                path: path.to_owned(),
+                bounds: reader.get_metadata().bounds(),
+                point_count,
+                first_point_index,
+            });
+            file_layouts.push(reader.get_default_point_layout().clone());
+            first_point_index += point_count;
+        }
+        if files.is_empty() {
+            bail!("DatasetReader requires at least one point cloud file");
+        }
+
+        let mut attributes: Vec<PointAttributeDefinition> = vec![];
+        for file_layout in &file_layouts {
+            for attribute in file_layout.attributes() {
+                if !attributes
+                    .iter()
+                    .any(|existing| existing.name() == attribute.name())
+                {
+                    attributes.push(attribute.attribute_definition().clone());
+                }
+            }
+        }
+
+        let bounds = files
+            .iter()
+            .map(|file| file.bounds)
+            .reduce(|a, b| Some(AABB::union(&a?, &b?)))
+            .flatten();
+        let metadata = DatasetMetadata {
+            bounds,
+            point_count: first_point_index,
+            file_count: files.len(),
+        };
+        Ok(Self {
+            files,
+            file_layouts,
+            layout: PointLayout::from_attributes(&attributes),
+            metadata,
+            current_point_index: 0,
+            current_reader: None,
+        })
+    }
+
+    /// Creates a new `DatasetReader` for all point cloud files in the given `directory`. Only files whose extension
+    /// belongs to a registered format that can be read are used, in the order of their file names. Subdirectories
+    /// are not searched
+    ///
+    /// # Errors
+    ///
+    /// If `directory` can't be read, if it contains no point cloud files, or if one of the files can't be opened,
+    /// an error is returned
+    pub fn from_directory<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        let directory = directory.as_ref();
+        let mut paths = vec![];
+        for entry in std::fs::read_dir(directory)
+            .with_context(|| format!("Could not read directory {}", directory.display()))?
+        {
+            let path = entry?.path();
+            let is_point_cloud_file = path.is_file()
+                && path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(format_for_extension)
+                    .map(|format| format.can_read())
+                    .unwrap_or(false);
+            if is_point_cloud_file {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            bail!(
+                "Directory {} contains no point cloud files",
+                directory.display()
+            );
+        }
+        paths.sort();
+        Self::from_paths(paths)
+    }
+
+    /// Returns all files of the dataset
+    pub fn files(&self) -> &[DatasetFile] {
+        &self.files
+    }
+
+    /// Returns all files whose points might lie within the given `bounds`, based on the bounds in their headers
+    pub fn query_files(&self, bounds: &AABB<f64>) -> Vec<&DatasetFile> {
+        self.files
+            .iter()
+            .filter(|file| file.point_count > 0 && file.may_intersect(bounds))
+            .collect()
+    }
+
+    /// Reads all points within the given `bounds` into a new buffer of type `B`. Only the files returned by
+    /// [`query_files`](Self::query_files) are opened. The `PointLayout` of the new buffer will be equal to the default
+    /// `PointLayout` of this `DatasetReader`. This does not change the current point of the `DatasetReader`
+    ///
+    /// # Errors
+    ///
+    /// If one of the files can't be read, or if it contains no `POSITION_3D` attribute, an error is returned
+    pub fn read_query<'b, B: OwningBuffer<'b> + MakeBufferFromLayout<'b> + 'b>(
+        &self,
+        bounds: &AABB<f64>,
+    ) -> Result<B> {
+        let mut buffer = B::new_from_layout(self.layout.clone());
+        for (file, file_layout) in self.files.iter().zip(&self.file_layouts) {
+            if file.point_count == 0 || !file.may_intersect(bounds) {
+                continue;
+            }
+            if !file_layout.has_attribute_with_name(POSITION_3D.name()) {
+                bail!(
+                    "Point cloud file {} has no POSITION_3D attribute",
+                    file.path.display()
+                );
+            }
+            let mut reader = GenericPointReader::open_file(&file.path).with_context(|| {
+                format!("Could not open point cloud file {}", file.path.display())
+            })?;
+            let file_points = reader.read::<VectorBuffer>(file.point_count)?;
+            let mut points_in_bounds =
+                VectorBuffer::with_capacity(file_points.len(), file_layout.clone());
+            for (index, position) in file_points
+                .view_attribute_with_conversion::<Vector3<f64>>(&POSITION_3D)?
+                .into_iter()
+                .enumerate()
+            {
+                if bounds.contains(&Point3::from(position)) {
+                    // Safe because both buffers have the same `PointLayout`
+                    unsafe {
+                        points_in_bounds.push_points(file_points.get_point_ref(index));
+                    }
+                }
+            }
+
+            let first_new_point = buffer.len();
+            buffer.resize(first_new_point + points_in_bounds.len());
+            BufferLayoutConverter::for_layouts_with_default(file_layout, &self.layout)
+                .convert_into_range(
+                    &points_in_bounds,
+                    0..points_in_bounds.len(),
+                    &mut buffer,
+                    first_new_point..(first_new_point + points_in_bounds.len()),
+                );
+        }
+        Ok(buffer)
+    }
+
+    /// Returns the index of the file that contains the point with the given global `point_index`
+    fn file_index_for_point(&self, point_index: usize) -> Option<usize> {
+        let file_index = self
+            .files
+            .partition_point(|file| file.point_range().end <= point_index);
+        (file_index < self.files.len()).then_some(file_index)
+    }
+
+    /// Makes sure that the file with the given `file_index` is open and positioned at the current point
+    fn open_current_file(&mut self, file_index: usize) -> Result<&mut GenericPointReader> {
+        let is_open =
+            matches!(&self.current_reader, Some((open_index, _)) if *open_index == file_index);
+        if !is_open {
+            let file = &self.files[file_index];
+            let mut reader = GenericPointReader::open_file(&file.path).with_context(|| {
+                format!("Could not open point cloud file {}", file.path.display())
+            })?;
+            let point_in_file = self.current_point_index - file.first_point_index;
+            if point_in_file > 0 {
+                reader.seek_point(SeekFrom::Start(point_in_file as u64))?;
+            }
+            self.current_reader = Some((file_index, reader));
+        }
+        Ok(&mut self
+            .current_reader
+            .as_mut()
+            .expect("Current reader must exist")
+            .1)
+    }
+}
+
+impl PointReader for DatasetReader {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let target_layout = point_buffer.point_layout().clone();
+        let mut num_read = 0;
+        while num_read < count {
+            let file_index = match self.file_index_for_point(self.current_point_index) {
+                Some(file_index) => file_index,
+                None => break,
+            };
+            let file = self.files[file_index].clone();
+            let num_to_read =
+                (file.point_range().end - self.current_point_index).min(count - num_read);
+            let file_layout = self.file_layouts[file_index].clone();
+            let reader = self.open_current_file(file_index)?;
+            let mut file_points = VectorBuffer::with_capacity(num_to_read, file_layout.clone());
+            file_points.resize(num_to_read);
+            let num_read_from_file = reader.read_into(&mut file_points, num_to_read)?;
+            if num_read_from_file != num_to_read {
+                bail!(
+                    "Point cloud file {} contains fewer points than stated in its header",
+                    file.path.display()
+                );
+            }
+            BufferLayoutConverter::for_layouts_with_default(&file_layout, &target_layout)
+                .convert_into_range(
+                    &file_points,
+                    0..num_to_read,
+                    point_buffer,
+                    num_read..(num_read + num_to_read),
+                );
+
+            num_read += num_to_read;
+            self.current_point_index += num_to_read;
+            if self.current_point_index == file.point_range().end {
+                // Close files that have been read completely, so that only one file is open at a time
+                self.current_reader = None;
+            }
+        }
+        Ok(num_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+impl SeekToPoint for DatasetReader {
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        let new_position = match position {
+            SeekFrom::Start(from_start) => from_start as i64,
+            SeekFrom::End(from_end) => self.metadata.point_count as i64 + from_end,
+            SeekFrom::Current(from_current) => self.current_point_index as i64 + from_current,
+        };
+        if new_position < 0 {
+            bail!("DatasetReader::seek_point: Can't seek to a point position smaller than zero");
+        }
+        let clamped_position = (new_position as usize).min(self.metadata.point_count);
+        if clamped_position == self.current_point_index {
+            return Ok(clamped_position);
+        }
+
+        self.current_point_index = clamped_position;
+        match (
+            self.file_index_for_point(clamped_position),
+            self.current_reader.as_mut(),
+        ) {
+            // Seeking within the open file is done by the reader of the file, otherwise the file that contains the
+            // new position is opened on the next read
+            (Some(file_index), Some((open_index, reader))) if file_index == *open_index => {
+                let point_in_file = clamped_position - self.files[file_index].first_point_index;
+                reader.seek_point(SeekFrom::Start(point_in_file as u64))?;
+            }
+            _ => self.current_reader = None,
+        }
+        Ok(clamped_position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pasture_core::layout::attributes::{COLOR_RGB, INTENSITY};
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::base::write_all;
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct IntensityPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+    }
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct ColorPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u16>,
+    }
+
+    fn test_directory(name: &str) -> Result<PathBuf> {
+        let directory =
+            std::env::temp_dir().join(format!("pasture_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    fn intensity_points(count: usize, x_offset: f64) -> VectorBuffer {
+        (0..count)
+            .map(|index| IntensityPoint {
+                position: Vector3::new(x_offset + index as f64, 1.0, 2.0),
+                intensity: index as u16,
+            })
+            .collect()
+    }
+
+    /// Writes a dataset of three files with different `PointLayout`s (10 intensity points, 5 color points and 7 more
+    /// intensity points) into `directory`
+    fn write_mixed_dataset(directory: &Path, extension: &str) -> Result<()> {
+        write_all(
+            &intensity_points(10, 0.0),
+            directory.join(format!("a.{}", extension)),
+        )?;
+        let color_points: VectorBuffer = (0..5)
+            .map(|index| ColorPoint {
+                position: Vector3::new(100.0 + index as f64, 1.0, 2.0),
+                color: Vector3::new(index, 2 * index, 3 * index),
+            })
+            .collect();
+        write_all(&color_points, directory.join(format!("b.{}", extension)))?;
+        write_all(
+            &intensity_points(7, 200.0),
+            directory.join(format!("c.{}", extension)),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_reader_reads_all_files() -> Result<()> {
+        let directory = test_directory("dataset_reader_all_files")?;
+        write_mixed_dataset(&directory, "pasture")?;
+        fs::write(directory.join("notes.md"), "Not a point cloud")?;
+
+        let mut reader = DatasetReader::from_directory(&directory)?;
+        assert_eq!(3, reader.files().len());
+        assert_eq!(
+            vec![0..10, 10..15, 15..22],
+            reader
+                .files()
+                .iter()
+                .map(|file| file.point_range())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(22), reader.get_metadata().number_of_points());
+        let layout = reader.get_default_point_layout().clone();
+        assert!(layout.has_attribute(&POSITION_3D));
+        assert!(layout.has_attribute(&INTENSITY));
+        assert!(layout.has_attribute(&COLOR_RGB));
+
+        // Read in chunks that span multiple files
+        let mut points = VectorBuffer::new_from_layout(layout);
+        loop {
+            let chunk = reader.read::<VectorBuffer>(8)?;
+            if chunk.is_empty() {
+                break;
+            }
+            points.append(&chunk);
+        }
+        assert_eq!(22, points.len());
+        let positions = points
+            .view_attribute::<Vector3<f64>>(&POSITION_3D)
+            .into_iter()
+            .map(|position| position.x)
+            .collect::<Vec<_>>();
+        let expected_positions = (0..10)
+            .map(|x| x as f64)
+            .chain((100..105).map(|x| x as f64))
+            .chain((200..207).map(|x| x as f64))
+            .collect::<Vec<_>>();
+        assert_eq!(expected_positions, positions);
+        // Attributes that a file does not contain are filled with default values
+        let intensities = points.view_attribute::<u16>(&INTENSITY);
+        assert_eq!(9, intensities.at(9));
+        assert_eq!(0, intensities.at(12));
+        assert_eq!(6, intensities.at(21));
+        let colors = points.view_attribute::<Vector3<u16>>(&COLOR_RGB);
+        assert_eq!(Vector3::new(0, 0, 0), colors.at(0));
+        assert_eq!(Vector3::new(2, 4, 6), colors.at(12));
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_reader_seek() -> Result<()> {
+        let directory = test_directory("dataset_reader_seek")?;
+        write_mixed_dataset(&directory, "pasture")?;
+        let mut reader = DatasetReader::from_paths(
+            ["c.pasture", "a.pasture", "b.pasture"]
+                .iter()
+                .map(|name| directory.join(name)),
+        )?;
+        assert_eq!(22, reader.point_count()?);
+        assert_eq!(0, reader.point_index()?);
+
+        let read_x = |reader: &mut DatasetReader, count: usize| -> Result<Vec<f64>> {
+            let points = reader.read::<VectorBuffer>(count)?;
+            Ok(points
+                .view_attribute::<Vector3<f64>>(&POSITION_3D)
+                .into_iter()
+                .map(|position| position.x)
+                .collect())
+        };
+        assert_eq!(vec![200.0, 201.0], read_x(&mut reader, 2)?);
+        // Seek within the current file
+        assert_eq!(5, reader.seek_point(SeekFrom::Current(3))?);
+        assert_eq!(vec![205.0, 206.0, 0.0], read_x(&mut reader, 3)?);
+        // Seek into another file
+        assert_eq!(18, reader.seek_point(SeekFrom::End(-4))?);
+        assert_eq!(vec![101.0, 102.0], read_x(&mut reader, 2)?);
+        assert_eq!(9, reader.seek_point(SeekFrom::Start(9))?);
+        assert_eq!(vec![2.0], read_x(&mut reader, 1)?);
+        assert_eq!(22, reader.seek_point(SeekFrom::Start(100))?);
+        assert!(read_x(&mut reader, 1)?.is_empty());
+        assert!(reader.seek_point(SeekFrom::Current(-23)).is_err());
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_reader_spatial_query() -> Result<()> {
+        let directory = test_directory("dataset_reader_spatial_query")?;
+        for tile in 0..3 {
+            write_all(
+                &intensity_points(10, tile as f64 * 100.0),
+                directory.join(format!("tile_{}.las", tile)),
+            )?;
+        }
+        let reader = DatasetReader::from_directory(&directory)?;
+        assert_eq!(
+            Some(AABB::from_min_max(
+                Point3::new(0.0, 1.0, 2.0),
+                Point3::new(209.0, 1.0, 2.0)
+            )),
+            reader.get_metadata().bounds()
+        );
+
+        let query_bounds =
+            AABB::from_min_max(Point3::new(95.0, 0.0, 0.0), Point3::new(104.5, 10.0, 10.0));
+        let files = reader.query_files(&query_bounds);
+        assert_eq!(1, files.len());
+        assert_eq!(directory.join("tile_1.las"), files[0].path);
+
+        // Files outside of the query bounds are not opened, so corrupting them does not affect the query
+        fs::write(directory.join("tile_2.las"), "Not a LAS file")?;
+        let points = reader.read_query::<VectorBuffer>(&query_bounds)?;
+        assert_eq!(
+            vec![100.0, 101.0, 102.0, 103.0, 104.0],
+            points
+                .view_attribute::<Vector3<f64>>(&POSITION_3D)
+                .into_iter()
+                .map(|position| position.x)
+                .collect::<Vec<_>>()
+        );
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
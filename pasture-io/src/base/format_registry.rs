@@ -0,0 +1,782 @@
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use lazy_static::lazy_static;
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, BorrowedMutBuffer, ColumnarBuffer, ColumnarBufferMut, ExternalMemoryBuffer,
+        InterleavedBuffer, InterleavedBufferMut,
+    },
+    layout::{PointAttributeDefinition, PointAttributeMember, PointLayout},
+    meta::Metadata,
+};
+
+use crate::{
+    ascii::{looks_like_ascii_point_cloud, AsciiReader},
+    e57::{E57FileHeader, E57Reader},
+    las::{read_is_compressed_las_file, LASReader, LASWriter},
+    native::{NativeHeader, NativeMemoryLayout, NativeReader, NativeWriter},
+    pcd::{PcdDataEncoding, PcdReader, PcdWriter},
+    ply::{PlyFormat, PlyReader, PlyWriter},
+    tiles3d::{PntsReader, PntsWriter},
+};
+
+use super::{PointReader, PointWriter, SeekToPoint};
+
+/// Maximum number of bytes at the start of a file that are passed to the magic bytes sniffer of a `PointCloudFormat`
+pub const MAGIC_BYTES_LENGTH: usize = 256;
+
+/// Name of the builtin LAS format
+pub(crate) const LAS_FORMAT_NAME: &str = "LAS";
+/// Name of the builtin 3D Tiles format
+pub(crate) const TILES3D_FORMAT_NAME: &str = "3D Tiles";
+
+/// A seekable source of bytes, such as a `File` or a `Cursor<Vec<u8>>`, from which a reader of a registered
+/// point cloud format can be created
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Object-safe version of `PointReader` and `SeekToPoint`, used to store readers of arbitrary formats in a
+/// `GenericPointReader`
+pub(crate) trait AnyPointReader {
+    fn read_into_dyn<'a, 'b>(
+        &mut self,
+        point_buffer: &'b mut dyn BorrowedMutBuffer<'a>,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b;
+    fn metadata(&self) -> &dyn Metadata;
+    fn default_point_layout(&self) -> &PointLayout;
+    fn seek_to_point(&mut self, position: std::io::SeekFrom) -> Result<usize>;
+}
+
+impl<T: PointReader + SeekToPoint> AnyPointReader for T {
+    fn read_into_dyn<'a, 'b>(
+        &mut self,
+        point_buffer: &'b mut dyn BorrowedMutBuffer<'a>,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        self.read_into(&mut DynBufferMut(point_buffer), count)
+    }
+
+    fn metadata(&self) -> &dyn Metadata {
+        self.get_metadata()
+    }
+
+    fn default_point_layout(&self) -> &PointLayout {
+        self.get_default_point_layout()
+    }
+
+    fn seek_to_point(&mut self, position: std::io::SeekFrom) -> Result<usize> {
+        self.seek_point(position)
+    }
+}
+
+/// Object-safe version of `PointWriter`, used to store writers of arbitrary formats in a `GenericPointWriter`.
+/// Points are always passed to the writer as an interleaved buffer
+pub(crate) trait AnyPointWriter {
+    fn write_interleaved(&mut self, points: &ExternalMemoryBuffer<&[u8]>) -> Result<()>;
+    fn flush_dyn(&mut self) -> Result<()>;
+    fn default_point_layout(&self) -> &PointLayout;
+}
+
+impl<T: PointWriter> AnyPointWriter for T {
+    fn write_interleaved(&mut self, points: &ExternalMemoryBuffer<&[u8]>) -> Result<()> {
+        self.write(points)
+    }
+
+    fn flush_dyn(&mut self) -> Result<()> {
+        self.flush()
+    }
+
+    fn default_point_layout(&self) -> &PointLayout {
+        self.get_default_point_layout()
+    }
+}
+
+/// Wrapper that implements `BorrowedMutBuffer` for a `dyn BorrowedMutBuffer` by forwarding all calls, so that a
+/// type-erased buffer can be passed to `PointReader::read_into`
+struct DynBufferMut<'a, 'b>(&'b mut dyn BorrowedMutBuffer<'a>);
+
+impl<'a, 'b> BorrowedBuffer<'a> for DynBufferMut<'a, 'b> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn point_layout(&self) -> &PointLayout {
+        self.0.point_layout()
+    }
+
+    fn get_point(&self, index: usize, data: &mut [u8]) {
+        self.0.get_point(index, data)
+    }
+
+    fn get_point_range(&self, range: Range<usize>, data: &mut [u8]) {
+        self.0.get_point_range(range, data)
+    }
+
+    fn get_attribute(&self, attribute: &PointAttributeDefinition, index: usize, data: &mut [u8]) {
+        self.0.get_attribute(attribute, index, data)
+    }
+
+    fn get_attribute_range(
+        &self,
+        attribute: &PointAttributeDefinition,
+        point_range: Range<usize>,
+        data: &mut [u8],
+    ) {
+        self.0.get_attribute_range(attribute, point_range, data)
+    }
+
+    unsafe fn get_attribute_unchecked(
+        &self,
+        attribute_member: &PointAttributeMember,
+        index: usize,
+        data: &mut [u8],
+    ) {
+        self.0
+            .get_attribute_unchecked(attribute_member, index, data)
+    }
+
+    fn as_interleaved(&self) -> Option<&dyn InterleavedBuffer<'a>> {
+        self.0.as_interleaved()
+    }
+
+    fn as_columnar(&self) -> Option<&dyn ColumnarBuffer<'a>> {
+        self.0.as_columnar()
+    }
+}
+
+impl<'a, 'b> BorrowedMutBuffer<'a> for DynBufferMut<'a, 'b> {
+    unsafe fn set_point(&mut self, index: usize, point_data: &[u8]) {
+        self.0.set_point(index, point_data)
+    }
+
+    unsafe fn set_point_range(&mut self, point_range: Range<usize>, point_data: &[u8]) {
+        self.0.set_point_range(point_range, point_data)
+    }
+
+    unsafe fn set_attribute(
+        &mut self,
+        attribute: &PointAttributeDefinition,
+        index: usize,
+        attribute_data: &[u8],
+    ) {
+        self.0.set_attribute(attribute, index, attribute_data)
+    }
+
+    unsafe fn set_attribute_range(
+        &mut self,
+        attribute: &PointAttributeDefinition,
+        point_range: Range<usize>,
+        attribute_data: &[u8],
+    ) {
+        self.0
+            .set_attribute_range(attribute, point_range, attribute_data)
+    }
+
+    fn swap(&mut self, from_index: usize, to_index: usize) {
+        self.0.swap(from_index, to_index)
+    }
+
+    fn as_interleaved_mut(&mut self) -> Option<&mut dyn InterleavedBufferMut<'a>> {
+        self.0.as_interleaved_mut()
+    }
+
+    fn as_columnar_mut(&mut self) -> Option<&mut dyn ColumnarBufferMut<'a>> {
+        self.0.as_columnar_mut()
+    }
+}
+
+type ReaderFactory = dyn Fn(&Path) -> Result<Box<dyn AnyPointReader>> + Send + Sync;
+type ReadReaderFactory = dyn Fn(Box<dyn ReadSeek>) -> Result<Box<dyn AnyPointReader>> + Send + Sync;
+type WriterFactory = dyn Fn(&Path, &PointLayout) -> Result<Box<dyn AnyPointWriter>> + Send + Sync;
+type MagicBytesSniffer = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+/// A point cloud file format that can take part in `GenericPointReader`, `GenericPointWriter`, `read_all`,
+/// `read_all_into` and `write_all`. A format has a unique name, a set of file extensions, an optional sniffer
+/// that identifies files of this format from their first bytes, and factories for a reader and/or a writer.
+/// Readers can be created from a path, from any [`ReadSeek`] source, or both. Formats are made available by
+/// calling [`register_format`]
+///
+/// # Example
+///
+/// ```
+/// use pasture_io::base::{register_format, PointCloudFormat};
+/// use pasture_io::pcd::PcdReader;
+///
+/// let format = PointCloudFormat::new("MyPCD", &["mypcd"])
+///     .with_magic_bytes(|bytes| bytes.starts_with(b"# .PCD"))
+///     .with_reader(|path| PcdReader::from_path(path))
+///     .with_reader_from_read(|read| PcdReader::from_read(std::io::BufReader::new(read)));
+/// register_format(format).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct PointCloudFormat {
+    name: String,
+    extensions: Vec<String>,
+    sniffer: Option<Arc<MagicBytesSniffer>>,
+    reader_factory: Option<Arc<ReaderFactory>>,
+    read_reader_factory: Option<Arc<ReadReaderFactory>>,
+    writer_factory: Option<Arc<WriterFactory>>,
+}
+
+impl PointCloudFormat {
+    /// Creates a new `PointCloudFormat` with the given `name` and file `extensions`. Extensions are given without
+    /// the leading dot and are matched case-insensitively
+    pub fn new<S: Into<String>>(name: S, extensions: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.to_lowercase())
+                .collect(),
+            sniffer: None,
+            reader_factory: None,
+            read_reader_factory: None,
+            writer_factory: None,
+        }
+    }
+
+    /// Sets the magic bytes sniffer of this format. It is called with (at most) the first [`MAGIC_BYTES_LENGTH`]
+    /// bytes of a file and returns `true` if the file is of this format
+    pub fn with_magic_bytes<F: Fn(&[u8]) -> bool + Send + Sync + 'static>(
+        mut self,
+        sniffer: F,
+    ) -> Self {
+        self.sniffer = Some(Arc::new(sniffer));
+        self
+    }
+
+    /// Sets the factory that creates a reader for a file of this format from a path
+    pub fn with_reader<R, F>(mut self, factory: F) -> Self
+    where
+        R: PointReader + SeekToPoint + 'static,
+        F: Fn(&Path) -> Result<R> + Send + Sync + 'static,
+    {
+        self.reader_factory = Some(Arc::new(move |path: &Path| {
+            let reader = factory(path)?;
+            Ok(Box::new(reader) as Box<dyn AnyPointReader>)
+        }));
+        self
+    }
+
+    /// Sets the factory that creates a reader for this format from a [`ReadSeek`] source, which is positioned at
+    /// the start of the point cloud data. If the format has no reader factory for paths, this factory is also
+    /// used to read files
+    pub fn with_reader_from_read<R, F>(mut self, factory: F) -> Self
+    where
+        R: PointReader + SeekToPoint + 'static,
+        F: Fn(Box<dyn ReadSeek>) -> Result<R> + Send + Sync + 'static,
+    {
+        self.read_reader_factory = Some(Arc::new(move |read: Box<dyn ReadSeek>| {
+            let reader = factory(read)?;
+            Ok(Box::new(reader) as Box<dyn AnyPointReader>)
+        }));
+        self
+    }
+
+    /// Sets the factory that creates a writer for a file of this format, writing points in the given `PointLayout`
+    pub fn with_writer<W, F>(mut self, factory: F) -> Self
+    where
+        W: PointWriter + 'static,
+        F: Fn(&Path, &PointLayout) -> Result<W> + Send + Sync + 'static,
+    {
+        self.writer_factory = Some(Arc::new(move |path: &Path, point_layout: &PointLayout| {
+            let writer = factory(path, point_layout)?;
+            Ok(Box::new(writer) as Box<dyn AnyPointWriter>)
+        }));
+        self
+    }
+
+    /// Returns the name of this format
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the file extensions of this format, in lowercase and without the leading dot
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// Returns `true` if files of this format can be read
+    pub fn can_read(&self) -> bool {
+        self.reader_factory.is_some() || self.read_reader_factory.is_some()
+    }
+
+    /// Returns `true` if this format can be read from a [`ReadSeek`] source
+    pub fn can_read_from_read(&self) -> bool {
+        self.read_reader_factory.is_some()
+    }
+
+    /// Returns `true` if this format has a magic bytes sniffer
+    pub fn has_magic_bytes(&self) -> bool {
+        self.sniffer.is_some()
+    }
+
+    /// Returns `true` if files of this format can be written
+    pub fn can_write(&self) -> bool {
+        self.writer_factory.is_some()
+    }
+
+    /// Returns `true` if `extension` is one of the file extensions of this format
+    pub fn matches_extension(&self, extension: &str) -> bool {
+        let extension = extension.to_lowercase();
+        self.extensions.contains(&extension)
+    }
+
+    /// Returns `true` if this format has a magic bytes sniffer which identifies `bytes` as the start of a file
+    /// of this format
+    pub fn matches_magic_bytes(&self, bytes: &[u8]) -> bool {
+        self.sniffer
+            .as_ref()
+            .map(|sniffer| sniffer(bytes))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn open_reader(&self, path: &Path) -> Result<Box<dyn AnyPointReader>> {
+        if let Some(factory) = &self.reader_factory {
+            return factory(path);
+        }
+        let factory = self
+            .read_reader_factory
+            .as_ref()
+            .ok_or_else(|| anyhow!("Reading {} files is not supported", self.name))?;
+        let file = BufReader::new(
+            File::open(path).with_context(|| format!("Could not open file {}", path.display()))?,
+        );
+        factory(Box::new(file))
+    }
+
+    pub(crate) fn open_reader_from_read(
+        &self,
+        read: Box<dyn ReadSeek>,
+    ) -> Result<Box<dyn AnyPointReader>> {
+        let factory = self.read_reader_factory.as_ref().ok_or_else(|| {
+            anyhow!(
+                "Reading {} files from a Read source is not supported",
+                self.name
+            )
+        })?;
+        factory(read)
+    }
+
+    pub(crate) fn open_writer(
+        &self,
+        path: &Path,
+        point_layout: &PointLayout,
+    ) -> Result<Box<dyn AnyPointWriter>> {
+        let factory = self
+            .writer_factory
+            .as_ref()
+            .ok_or_else(|| anyhow!("Writing {} files is not supported", self.name))?;
+        factory(path, point_layout)
+    }
+}
+
+impl Debug for PointCloudFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PointCloudFormat")
+            .field("name", &self.name)
+            .field("extensions", &self.extensions)
+            .field("can_read", &self.can_read())
+            .field("can_write", &self.can_write())
+            .finish()
+    }
+}
+
+/// Returns the point cloud formats that pasture-io supports out of the box
+fn builtin_formats() -> Vec<PointCloudFormat> {
+    vec![
+        // The ASCII heuristic is the weakest sniffer, so it comes first and is checked last
+        PointCloudFormat::new("ASCII", &["txt", "csv", "xyz", "pts"])
+            .with_magic_bytes(looks_like_ascii_point_cloud)
+            .with_reader(|path| AsciiReader::from_path_inferred(path))
+            .with_reader_from_read(|read| AsciiReader::from_read_inferred(BufReader::new(read))),
+        PointCloudFormat::new("E57", &["e57"])
+            .with_magic_bytes(|bytes| bytes.starts_with(E57FileHeader::SIGNATURE))
+            .with_reader(|path| E57Reader::from_path(path))
+            .with_reader_from_read(|read| E57Reader::from_read(BufReader::new(read))),
+        PointCloudFormat::new(LAS_FORMAT_NAME, &["las", "laz"])
+            .with_magic_bytes(|bytes| bytes.starts_with(b"LASF"))
+            .with_reader(|path| LASReader::from_path(path, false))
+            .with_reader_from_read(|mut read| {
+                let is_compressed = read_is_compressed_las_file(&mut read)?;
+                LASReader::from_read(read, is_compressed, false)
+            })
+            .with_writer(|path, point_layout| {
+                LASWriter::from_path_and_point_layout(path, point_layout)
+            }),
+        PointCloudFormat::new("Native", &["pasture"])
+            .with_magic_bytes(|bytes| bytes.starts_with(NativeHeader::MAGIC))
+            .with_reader(|path| NativeReader::from_path(path))
+            .with_reader_from_read(|read| NativeReader::from_read(BufReader::new(read)))
+            .with_writer(|path, point_layout| {
+                NativeWriter::from_path_and_layout(
+                    path,
+                    point_layout.clone(),
+                    NativeMemoryLayout::Interleaved,
+                )
+            }),
+        PointCloudFormat::new("PCD", &["pcd"])
+            .with_magic_bytes(|bytes| bytes.starts_with(b"# .PCD") || bytes.starts_with(b"VERSION"))
+            .with_reader(|path| PcdReader::from_path(path))
+            .with_reader_from_read(|read| PcdReader::from_read(BufReader::new(read)))
+            .with_writer(|path, point_layout| {
+                PcdWriter::from_path_and_layout(path, point_layout.clone(), PcdDataEncoding::Binary)
+            }),
+        PointCloudFormat::new("PLY", &["ply"])
+            .with_magic_bytes(|bytes| bytes.starts_with(b"ply\n") || bytes.starts_with(b"ply\r\n"))
+            .with_reader(|path| PlyReader::from_path(path))
+            .with_reader_from_read(|read| PlyReader::from_read(BufReader::new(read)))
+            .with_writer(|path, point_layout| {
+                PlyWriter::from_path_and_layout(
+                    path,
+                    point_layout.clone(),
+                    PlyFormat::BinaryLittleEndian,
+                )
+            }),
+        PointCloudFormat::new(TILES3D_FORMAT_NAME, &["pnts"])
+            .with_magic_bytes(|bytes| bytes.starts_with(b"pnts"))
+            .with_reader(|path| PntsReader::from_path(path))
+            .with_reader_from_read(|read| PntsReader::from_read(BufReader::new(read)))
+            .with_writer(|path, point_layout| {
+                let file = BufWriter::new(File::create(path).context(format!(
+                    "Could not open file {} for writing",
+                    path.display()
+                ))?);
+                Ok(PntsWriter::from_write_and_layout(
+                    file,
+                    point_layout.clone(),
+                ))
+            }),
+    ]
+}
+
+lazy_static! {
+    static ref FORMAT_REGISTRY: RwLock<Vec<PointCloudFormat>> = RwLock::new(builtin_formats());
+}
+
+/// Registers the given `format`, so that files of this format can be read and written with `GenericPointReader`,
+/// `GenericPointWriter`, `read_all`, `read_all_into` and `write_all`. If multiple formats match the same file
+/// extension or magic bytes, the format that was registered last is used, so this can also be used to replace
+/// the builtin format for a file extension
+///
+/// # Errors
+///
+/// If a format with the same name is already registered, an error is returned
+pub fn register_format(format: PointCloudFormat) -> Result<()> {
+    let mut formats = FORMAT_REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    if formats
+        .iter()
+        .any(|registered| registered.name == format.name)
+    {
+        bail!(
+            "A point cloud format named {} is already registered",
+            format.name
+        );
+    }
+    formats.push(format);
+    Ok(())
+}
+
+/// Returns all registered point cloud formats, including the builtin formats
+pub fn registered_formats() -> Vec<PointCloudFormat> {
+    FORMAT_REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Returns the registered format with the given file `extension`
+pub(crate) fn format_for_extension(extension: &str) -> Option<PointCloudFormat> {
+    registered_formats()
+        .into_iter()
+        .rev()
+        .find(|format| format.matches_extension(extension))
+}
+
+/// Returns the registered format that identifies the given `bytes` as the start of one of its files
+pub(crate) fn format_for_magic_bytes(bytes: &[u8]) -> Option<PointCloudFormat> {
+    registered_formats()
+        .into_iter()
+        .rev()
+        .find(|format| format.matches_magic_bytes(bytes))
+}
+
+/// Reads (at most) the first [`MAGIC_BYTES_LENGTH`] bytes from `read` and restores its position afterwards
+fn read_magic_bytes<R: Read + Seek>(read: &mut R) -> Result<Vec<u8>> {
+    let start_position = read.stream_position()?;
+    let mut magic_bytes = Vec::with_capacity(MAGIC_BYTES_LENGTH);
+    read.take(MAGIC_BYTES_LENGTH as u64)
+        .read_to_end(&mut magic_bytes)?;
+    read.seek(SeekFrom::Start(start_position))?;
+    Ok(magic_bytes)
+}
+
+/// Detects the format of the point cloud data in `read` from its content, using the magic bytes sniffers of all
+/// registered formats. The position of `read` is restored afterwards. Returns `None` if no format matches
+pub fn detect_format<R: Read + Seek>(read: &mut R) -> Result<Option<PointCloudFormat>> {
+    let magic_bytes = read_magic_bytes(read)?;
+    Ok(format_for_magic_bytes(&magic_bytes))
+}
+
+/// Determines the format of the file at `path`. If `sniff_content` is `false`, only the file extension is used.
+/// Otherwise the format for the file extension is used if its sniffer accepts the first bytes of the file (or if
+/// it has no sniffer). If it doesn't, or if the extension is missing or unknown, the format is detected from the
+/// content of the file. Only if that fails as well, the format for the file extension is used as a fallback
+pub(crate) fn format_for_path(path: &Path, sniff_content: bool) -> Result<PointCloudFormat> {
+    let extension = path
+        .extension()
+        .map(|extension| {
+            extension.to_str().ok_or_else(|| {
+                anyhow!(
+                    "File extension of path {} is no valid Unicode string",
+                    path.display()
+                )
+            })
+        })
+        .transpose()?;
+    let extension_format = extension.and_then(format_for_extension);
+
+    if sniff_content {
+        let mut file =
+            File::open(path).with_context(|| format!("Could not open file {}", path.display()))?;
+        let magic_bytes = read_magic_bytes(&mut file)?;
+        match &extension_format {
+            Some(format)
+                if !format.has_magic_bytes() || format.matches_magic_bytes(&magic_bytes) =>
+            {
+                return Ok(format.clone())
+            }
+            _ => {
+                if let Some(format) = format_for_magic_bytes(&magic_bytes) {
+                    return Ok(format);
+                }
+            }
+        }
+    }
+
+    match (extension_format, extension) {
+        (Some(format), _) => Ok(format),
+        (None, Some(extension)) => bail!("Unsupported file extension {extension}"),
+        (None, None) => bail!(
+            "File format could not be determined from path {}",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use pasture_core::{
+        containers::{HashMapBuffer, VectorBuffer},
+        layout::PointType,
+        nalgebra::Vector3,
+    };
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::base::{read_all, write_all, GenericPointReader, GenericPointWriter, PointReader};
+    use crate::las::get_test_las_path;
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct RegistryTestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(attribute = "SegmentID")]
+        segment_id: u32,
+    }
+
+    fn test_points() -> Vec<RegistryTestPoint> {
+        (0..16)
+            .map(|idx| RegistryTestPoint {
+                position: Vector3::new(idx as f64, -(idx as f64), 0.5),
+                segment_id: idx * 3,
+            })
+            .collect()
+    }
+
+    fn test_file_path(name: &str) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push(name);
+        path
+    }
+
+    #[test]
+    fn test_register_custom_format() -> Result<()> {
+        let format = PointCloudFormat::new("RegistryTestFormat", &["RegTest"])
+            .with_reader(|path| NativeReader::from_path(path))
+            .with_writer(|path, point_layout| {
+                NativeWriter::from_path_and_layout(
+                    path,
+                    point_layout.clone(),
+                    NativeMemoryLayout::Columnar,
+                )
+            });
+        assert!(format.can_read() && format.can_write());
+        assert!(format.matches_extension("regtest"));
+        register_format(format.clone())?;
+        assert!(register_format(format).is_err());
+        assert!(registered_formats()
+            .iter()
+            .any(|format| format.name() == "RegistryTestFormat"));
+
+        let path = test_file_path("test_register_custom_format.regtest");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        // A columnar buffer is passed to the writer in interleaved chunks
+        let points = test_points().into_iter().collect::<HashMapBuffer>();
+        write_all(&points, &path)?;
+        let reader = GenericPointReader::open_file(&path)?;
+        assert_eq!("RegistryTestFormat", reader.format_name());
+        assert_eq!(Some(points.len()), reader.point_count());
+
+        let read_points = read_all::<VectorBuffer, _>(&path)?;
+        assert_eq!(
+            test_points(),
+            read_points
+                .view::<RegistryTestPoint>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_format_from_magic_bytes() -> Result<()> {
+        let path = test_file_path("test_detect_format_from_magic_bytes.pcd");
+        let path_without_extension = path.with_extension("");
+        scopeguard::defer! {
+            std::fs::remove_file(&path_without_extension).expect("Removing test file failed!");
+        }
+
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        write_all(&points, &path)?;
+        std::fs::rename(&path, &path_without_extension)?;
+
+        let reader = GenericPointReader::open_file(&path_without_extension)?;
+        assert_eq!("PCD", reader.format_name());
+        assert_eq!(Some(points.len()), reader.point_count());
+        assert!(GenericPointReader::open_file(test_file_path("Cargo.toml")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_reader_and_writer_variants() -> Result<()> {
+        let reader = GenericPointReader::open_file(get_test_las_path(0))?;
+        assert!(matches!(reader, GenericPointReader::LAS(_)));
+        assert_eq!("LAS", reader.format_name());
+
+        let las_path = test_file_path("test_generic_reader_and_writer_variants.las");
+        let pcd_path = las_path.with_extension("pcd");
+        scopeguard::defer! {
+            std::fs::remove_file(&las_path).expect("Removing test file failed!");
+            std::fs::remove_file(&pcd_path).expect("Removing test file failed!");
+        }
+        let layout = RegistryTestPoint::layout();
+        assert!(matches!(
+            GenericPointWriter::open_file(&las_path, &layout)?,
+            GenericPointWriter::LAS(_)
+        ));
+        let writer = GenericPointWriter::open_file(&pcd_path, &layout)?;
+        assert_eq!("PCD", writer.format_name());
+        assert!(matches!(writer, GenericPointWriter::Registered(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_format_with_wrong_extension() -> Result<()> {
+        let ply_path = test_file_path("test_detect_format_with_wrong_extension.ply");
+        let wrong_paths = [
+            ply_path.with_extension("bin"),
+            ply_path.with_extension("las"),
+        ];
+        scopeguard::defer! {
+            for path in &wrong_paths {
+                std::fs::remove_file(path).expect("Removing test file failed!");
+            }
+        }
+
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        write_all(&points, &ply_path)?;
+        std::fs::copy(&ply_path, &wrong_paths[0])?;
+        std::fs::rename(&ply_path, &wrong_paths[1])?;
+
+        for path in &wrong_paths {
+            let reader = GenericPointReader::open_file(path)?;
+            assert_eq!("PLY", reader.format_name());
+            assert_eq!(Some(points.len()), reader.point_count());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_reader_from_read() -> Result<()> {
+        let path = test_file_path("test_generic_reader_from_read.pasture");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        write_all(&points, &path)?;
+        let bytes = std::fs::read(&path)?;
+
+        let mut reader = GenericPointReader::from_read(Cursor::new(bytes))?;
+        assert_eq!("Native", reader.format_name());
+        let read_points = reader.read::<VectorBuffer>(points.len())?;
+        assert_eq!(
+            test_points(),
+            read_points
+                .view::<RegistryTestPoint>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        assert!(GenericPointReader::from_read(Cursor::new(vec![0_u8; 64])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_ascii_format() -> Result<()> {
+        let mut ascii = Cursor::new(b"x,y,z,intensity\n1.0,2.0,3.0,42\n4.0,5.0,6.0,17\n".to_vec());
+        let format = detect_format(&mut ascii)?.expect("ASCII format was not detected");
+        assert_eq!("ASCII", format.name());
+        assert_eq!(0, ascii.position());
+
+        let mut text = Cursor::new(b"Just some text\nthat is no point cloud\n".to_vec());
+        assert!(detect_format(&mut text)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_operations() {
+        let layout = RegistryTestPoint::layout();
+        assert!(GenericPointWriter::open_file(test_file_path("unsupported.e57"), &layout).is_err());
+        assert!(
+            GenericPointWriter::open_file(test_file_path("unsupported.xyz123"), &layout).is_err()
+        );
+        assert!(GenericPointWriter::open_file(test_file_path("no_extension"), &layout).is_err());
+    }
+}
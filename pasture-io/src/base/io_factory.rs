@@ -2,76 +2,90 @@
 
 use std::{
     fs::File,
-    io::{BufReader, BufWriter},
+    io::{BufReader, BufWriter, Read, Seek},
     path::Path,
 };
 
 use anyhow::{anyhow, Context, Result};
-use pasture_core::{containers::BorrowedMutBuffer, layout::PointLayout};
-
-// use crate::las::{LASReader, LASWriter};
+use pasture_core::{
+    containers::{BorrowedBuffer, BorrowedMutBuffer, ExternalMemoryBuffer},
+    layout::PointLayout,
+};
 
 use crate::{
     las::{LASReader, LASWriter},
     tiles3d::{PntsReader, PntsWriter},
 };
 
-use super::{PointReader, PointWriter, SeekToPoint};
-
-#[derive(Debug)]
-enum SupportedFileExtensions {
-    Las,
-    Tiles3D,
-}
-
-/// Returns a lookup value for the file extension of the given file path
-fn get_extension_lookup(path: &Path) -> Result<SupportedFileExtensions> {
-    let extension = path.extension().ok_or_else(|| {
-        anyhow!(
-            "File extension could not be determined from path {}",
-            path.display()
-        )
-    })?;
-    let extension_str = extension.to_str().ok_or_else(|| {
-        anyhow!(
-            "File extension of path {} is no valid Unicode string",
-            path.display()
-        )
-    })?;
-    match extension_str.to_lowercase().as_str() {
-        "las" | "laz" => Ok(SupportedFileExtensions::Las),
-        "pnts" => Ok(SupportedFileExtensions::Tiles3D),
-        other => Err(anyhow!("Unsupported file extension {other}")),
-    }
-}
+use super::{
+    detect_format, format_for_path, AnyPointReader, AnyPointWriter, PointCloudFormat, PointReader,
+    PointWriter, SeekToPoint, LAS_FORMAT_NAME, TILES3D_FORMAT_NAME,
+};
 
+/// `PointReader` for any registered point cloud format. The format is determined from the content of the file
+/// (e.g. the `LASF` magic bytes of LAS files), so files with a wrong or missing extension can be read as well. If
+/// the content matches no format, the file extension is used. Readers can also be created from any `Read + Seek`
+/// source, such as an in-memory `Cursor<Vec<u8>>`, using [`GenericPointReader::from_read`]. See
+/// [`register_format`](super::register_format) for adding new formats
+///
+/// LAS/LAZ and 3D Tiles files that are opened with [`GenericPointReader::open_file`] use the `LAS` and `Tiles3D`
+/// variants, all other formats and sources use the `Registered` variant
 pub enum GenericPointReader {
     LAS(LASReader<'static, BufReader<File>>),
     Tiles3D(PntsReader<BufReader<File>>),
+    Registered(RegisteredFormatReader),
 }
 
 impl GenericPointReader {
+    /// Opens the point cloud file at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the format of the file can't be determined, if the format can't be read, or if the file is no valid file
+    /// of its format, an error is returned
     pub fn open_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let extension = get_extension_lookup(path.as_ref())?;
-        match extension {
-            SupportedFileExtensions::Las => {
-                let reader = LASReader::from_path(path, false)?;
-                Ok(Self::LAS(reader))
-            }
-            SupportedFileExtensions::Tiles3D => {
-                let reader = PntsReader::from_path(path)?;
-                Ok(Self::Tiles3D(reader))
+        let format = format_for_path(path.as_ref(), true)?;
+        match format.name() {
+            LAS_FORMAT_NAME => Ok(Self::LAS(LASReader::from_path(path, false)?)),
+            TILES3D_FORMAT_NAME => Ok(Self::Tiles3D(PntsReader::from_path(path)?)),
+            _ => {
+                let reader = format.open_reader(path.as_ref())?;
+                Ok(Self::Registered(RegisteredFormatReader::new(
+                    &format, reader,
+                )))
             }
         }
     }
 
+    /// Creates a new `GenericPointReader` that reads from `read`, starting at its current position. Since there is
+    /// no file extension, the format is determined solely from the content
+    ///
+    /// # Errors
+    ///
+    /// If the format of the data can't be determined, if the format can't be read from a `Read` source, or if
+    /// the data is no valid point cloud of its format, an error is returned
+    pub fn from_read<R: Read + Seek + Send + 'static>(mut read: R) -> Result<Self> {
+        let format = detect_format(&mut read)?
+            .ok_or_else(|| anyhow!("Point cloud format could not be determined from content"))?;
+        let reader = format.open_reader_from_read(Box::new(read))?;
+        Ok(Self::Registered(RegisteredFormatReader::new(
+            &format, reader,
+        )))
+    }
+
+    /// Returns the name of the format of the underlying point cloud file
+    pub fn format_name(&self) -> &str {
+        match self {
+            GenericPointReader::LAS(_) => LAS_FORMAT_NAME,
+            GenericPointReader::Tiles3D(_) => TILES3D_FORMAT_NAME,
+            GenericPointReader::Registered(reader) => reader.format_name(),
+        }
+    }
+
     /// Returns the total number of points in the underlying point cloud file. Returns `None` if the number of
     /// points is unknown (e.g. for ASCII files which don't have header information)
     pub fn point_count(&self) -> Option<usize> {
-        match self {
-            GenericPointReader::LAS(reader) => reader.get_metadata().number_of_points(),
-            GenericPointReader::Tiles3D(reader) => reader.get_metadata().number_of_points(),
-        }
+        self.get_metadata().number_of_points()
     }
 }
 
@@ -87,6 +101,7 @@ impl PointReader for GenericPointReader {
         match self {
             GenericPointReader::LAS(reader) => reader.read_into(point_buffer, count),
             GenericPointReader::Tiles3D(reader) => reader.read_into(point_buffer, count),
+            GenericPointReader::Registered(reader) => reader.read_into(point_buffer, count),
         }
     }
 
@@ -94,6 +109,7 @@ impl PointReader for GenericPointReader {
         match self {
             GenericPointReader::LAS(reader) => reader.get_metadata(),
             GenericPointReader::Tiles3D(reader) => reader.get_metadata(),
+            GenericPointReader::Registered(reader) => reader.get_metadata(),
         }
     }
 
@@ -101,6 +117,7 @@ impl PointReader for GenericPointReader {
         match self {
             GenericPointReader::LAS(reader) => reader.get_default_point_layout(),
             GenericPointReader::Tiles3D(reader) => reader.get_default_point_layout(),
+            GenericPointReader::Registered(reader) => reader.get_default_point_layout(),
         }
     }
 }
@@ -110,43 +127,112 @@ impl SeekToPoint for GenericPointReader {
         match self {
             GenericPointReader::LAS(reader) => reader.seek_point(position),
             GenericPointReader::Tiles3D(reader) => reader.seek_point(position),
+            GenericPointReader::Registered(reader) => reader.seek_point(position),
+        }
+    }
+}
+
+/// `PointReader` for a point cloud format from the format registry (see [`register_format`](super::register_format))
+pub struct RegisteredFormatReader {
+    format_name: String,
+    reader: Box<dyn AnyPointReader>,
+}
+
+impl RegisteredFormatReader {
+    fn new(format: &PointCloudFormat, reader: Box<dyn AnyPointReader>) -> Self {
+        Self {
+            format_name: format.name().to_owned(),
+            reader,
         }
     }
+
+    /// Returns the name of the format of the underlying point cloud file
+    pub fn format_name(&self) -> &str {
+        &self.format_name
+    }
 }
 
+impl PointReader for RegisteredFormatReader {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        self.reader.read_into_dyn(point_buffer, count)
+    }
+
+    fn get_metadata(&self) -> &dyn pasture_core::meta::Metadata {
+        self.reader.metadata()
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        self.reader.default_point_layout()
+    }
+}
+
+impl SeekToPoint for RegisteredFormatReader {
+    fn seek_point(&mut self, position: std::io::SeekFrom) -> Result<usize> {
+        self.reader.seek_to_point(position)
+    }
+}
+
+/// `PointWriter` for any registered point cloud format. The format is determined from the file extension. See
+/// [`register_format`](super::register_format) for adding new formats
+///
+/// LAS/LAZ and 3D Tiles files use the `LAS` and `Tiles3D` variants, all other formats use the `Registered` variant
 pub enum GenericPointWriter {
     LAS(LASWriter<BufWriter<File>>),
     Tiles3D(PntsWriter<BufWriter<File>>),
+    Registered(RegisteredFormatWriter),
 }
 
 impl GenericPointWriter {
     pub fn open_file<P: AsRef<Path>>(path: P, point_layout: &PointLayout) -> Result<Self> {
-        let extension = get_extension_lookup(path.as_ref())?;
-        match extension {
-            SupportedFileExtensions::Las => {
-                let writer = LASWriter::from_path_and_point_layout(path, point_layout)?;
-                Ok(Self::LAS(writer))
-            }
-            SupportedFileExtensions::Tiles3D => {
+        let format = format_for_path(path.as_ref(), false)?;
+        match format.name() {
+            LAS_FORMAT_NAME => Ok(Self::LAS(LASWriter::from_path_and_point_layout(
+                path,
+                point_layout,
+            )?)),
+            TILES3D_FORMAT_NAME => {
                 let file = BufWriter::new(File::create(path.as_ref()).context(format!(
                     "Could not open file {} for writing",
                     path.as_ref().display()
                 ))?);
-                let writer = PntsWriter::from_write_and_layout(file, point_layout.clone());
-                Ok(Self::Tiles3D(writer))
+                Ok(Self::Tiles3D(PntsWriter::from_write_and_layout(
+                    file,
+                    point_layout.clone(),
+                )))
+            }
+            _ => {
+                let writer = format.open_writer(path.as_ref(), point_layout)?;
+                Ok(Self::Registered(RegisteredFormatWriter {
+                    format_name: format.name().to_owned(),
+                    writer,
+                }))
             }
         }
     }
+
+    /// Returns the name of the format of the underlying point cloud file
+    pub fn format_name(&self) -> &str {
+        match self {
+            GenericPointWriter::LAS(_) => LAS_FORMAT_NAME,
+            GenericPointWriter::Tiles3D(_) => TILES3D_FORMAT_NAME,
+            GenericPointWriter::Registered(writer) => writer.format_name(),
+        }
+    }
 }
 
 impl PointWriter for GenericPointWriter {
-    fn write<'a, B: pasture_core::containers::BorrowedBuffer<'a>>(
-        &mut self,
-        points: &'a B,
-    ) -> Result<()> {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
         match self {
             GenericPointWriter::LAS(writer) => writer.write(points),
             GenericPointWriter::Tiles3D(writer) => writer.write(points),
+            GenericPointWriter::Registered(writer) => writer.write(points),
         }
     }
 
@@ -154,6 +240,7 @@ impl PointWriter for GenericPointWriter {
         match self {
             GenericPointWriter::LAS(writer) => writer.flush(),
             GenericPointWriter::Tiles3D(writer) => writer.flush(),
+            GenericPointWriter::Registered(writer) => writer.flush(),
         }
     }
 
@@ -161,6 +248,63 @@ impl PointWriter for GenericPointWriter {
         match self {
             GenericPointWriter::LAS(writer) => writer.get_default_point_layout(),
             GenericPointWriter::Tiles3D(writer) => writer.get_default_point_layout(),
+            GenericPointWriter::Registered(writer) => writer.get_default_point_layout(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// `PointWriter` for a point cloud format from the format registry (see [`register_format`](super::register_format))
+pub struct RegisteredFormatWriter {
+    format_name: String,
+    writer: Box<dyn AnyPointWriter>,
+}
+
+impl RegisteredFormatWriter {
+    /// Returns the name of the format of the underlying point cloud file
+    pub fn format_name(&self) -> &str {
+        &self.format_name
+    }
+}
+
+impl PointWriter for RegisteredFormatWriter {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        let point_layout = points.point_layout().clone();
+        if let Some(interleaved) = points.as_interleaved() {
+            let point_data = interleaved.get_point_range_ref(0..points.len());
+            return self
+                .writer
+                .write_interleaved(&ExternalMemoryBuffer::new(point_data, point_layout));
+        }
+
+        // Points in other memory layouts are passed to the writer in interleaved chunks of ~1MiB size
+        const CHUNK_MEM_SIZE: usize = 1 << 20;
+        let size_of_point = point_layout.size_of_point_entry() as usize;
+        let num_points_per_chunk = usize::max(1, CHUNK_MEM_SIZE / size_of_point.max(1));
+        let mut chunk = vec![0; num_points_per_chunk.min(points.len()) * size_of_point];
+        let mut first_point_in_chunk = 0;
+        while first_point_in_chunk < points.len() {
+            let points_in_chunk = num_points_per_chunk.min(points.len() - first_point_in_chunk);
+            let chunk_bytes = &mut chunk[..points_in_chunk * size_of_point];
+            points.get_point_range(
+                first_point_in_chunk..(first_point_in_chunk + points_in_chunk),
+                chunk_bytes,
+            );
+            self.writer
+                .write_interleaved(&ExternalMemoryBuffer::new(
+                    &chunk_bytes[..],
+                    point_layout.clone(),
+                ))
+                .context("Failed to write chunk of points")?;
+            first_point_in_chunk += points_in_chunk;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush_dyn()
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        self.writer.default_point_layout()
+    }
+}
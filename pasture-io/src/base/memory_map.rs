@@ -0,0 +1,36 @@
+use std::{convert::TryInto, fs::File};
+
+use anyhow::Result;
+use memmap2::{Mmap, MmapOptions};
+
+/// Memory-mapped point data of a file, which is the external memory of the buffers returned by the
+/// `map_from_path` functions of the readers (e.g. [`LASReader::map_from_path`](crate::las::LASReader::map_from_path)).
+/// Empty point data is not mapped at all, since memory maps must have a non-zero length
+pub struct MappedPointData {
+    mmap: Option<Mmap>,
+}
+
+impl MappedPointData {
+    /// Maps `len` bytes of `file` starting at `offset`
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it is mapped, which is the same contract that all memory-mapped file
+    /// APIs have
+    pub(crate) unsafe fn map(file: &File, offset: u64, len: u64) -> Result<Self> {
+        if len == 0 {
+            return Ok(Self { mmap: None });
+        }
+        let mmap = MmapOptions::new()
+            .offset(offset)
+            .len(len.try_into()?)
+            .map(file)?;
+        Ok(Self { mmap: Some(mmap) })
+    }
+}
+
+impl AsRef<[u8]> for MappedPointData {
+    fn as_ref(&self) -> &[u8] {
+        self.mmap.as_deref().unwrap_or(&[])
+    }
+}
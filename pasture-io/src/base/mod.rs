@@ -14,9 +14,24 @@ pub use self::writer::*;
 mod seek;
 pub use self::seek::*;
 
+mod format_registry;
+pub use self::format_registry::*;
+
 mod io_factory;
 pub use self::io_factory::*;
 
+mod dataset_reader;
+pub use self::dataset_reader::*;
+
+mod tiling_writer;
+pub use self::tiling_writer::*;
+
+mod memory_map;
+pub use self::memory_map::*;
+
+mod octree_sampling;
+pub(crate) use self::octree_sampling::*;
+
 /// Try to read all points in the given point cloud file. This function uses the default `IOFactory` to determine the
 /// file type from the file extension of `path`. If this succeeds, an appropriate reader is created and all points are
 /// read into an implementation-defined `PointBuffer` type. If you want to use a specific type of `PointBuffer`, use
@@ -28,10 +43,10 @@ pub fn read_all<'a, B: MakeBufferFromLayout<'a> + OwningBuffer<'a> + 'a, P: AsRe
         "Could not create appropriate reader for point cloud file {}",
         path.as_ref().display()
     ))?;
-    // While not pretty, it is allowed to call `read_into` with a value larger than the total number of points
-    // By falling back to `usize::MAX` for unknown point counts, we guarantee that we always read the whole
-    // point cloud
-    let num_points = reader.point_count().unwrap_or(usize::MAX);
+    let num_points = total_point_count(&mut reader).context(format!(
+        "Could not determine number of points in point cloud file {}",
+        path.as_ref().display()
+    ))?;
     reader.read::<B>(num_points)
 }
 
@@ -45,13 +60,22 @@ pub fn read_all_into<'a, B: OwningBuffer<'a>, P: AsRef<Path>>(
         "Could not create appropriate reader for point cloud file {}",
         path.as_ref().display()
     ))?;
-    let num_points = reader.point_count().context(format!(
+    let num_points = total_point_count(&mut reader).context(format!(
         "Could not determine number of points in point cloud file {}",
         path.as_ref().display()
     ))?;
     reader.read_into(buffer, num_points)
 }
 
+/// Returns the number of points of `reader`. If the header of the file contains no point count (e.g. for ASCII
+/// files), the points are counted by seeking to the end of the file
+fn total_point_count(reader: &mut GenericPointReader) -> Result<usize> {
+    match GenericPointReader::point_count(reader) {
+        Some(point_count) => Ok(point_count),
+        None => SeekToPoint::point_count(reader),
+    }
+}
+
 /// Writes all points in the given `buffer` into the file at `path`
 pub fn write_all<'a, B: BorrowedBuffer<'a>, P: AsRef<Path>>(buffer: &'a B, path: P) -> Result<()> {
     let mut writer =
@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+
+use pasture_core::math::AABB;
+use pasture_core::nalgebra::{Point3, Vector3};
+
+/// One of the eight child nodes (octants) of an octree node. Each flag is `true` if the octant lies in the upper
+/// half of the node along the respective axis
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Octant {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl Octant {
+    /// Returns the bounds of this octant within the given `node_bounds`
+    pub(crate) fn bounds_within(&self, node_bounds: &AABB<f64>) -> AABB<f64> {
+        let center = node_bounds.center();
+        let mut min = *node_bounds.min();
+        let mut max = *node_bounds.max();
+        for (axis, is_upper_half) in [self.x, self.y, self.z].iter().enumerate() {
+            if *is_upper_half {
+                min[axis] = center[axis];
+            } else {
+                max[axis] = center[axis];
+            }
+        }
+        AABB::from_min_max_unchecked(min, max)
+    }
+}
+
+/// Samples the points of an octree node using a regular grid with `grid_size`³ cells over the bounds of the node. The
+/// first point in each cell is kept in the node, all other points are passed on to the child nodes. The grid cells
+/// are aligned with the child nodes, so the cell of a point determines its child node
+pub(crate) struct GridSampler {
+    node_min: Point3<f64>,
+    node_extent: Vector3<f64>,
+    grid_size: i64,
+    occupied_cells: HashSet<(i64, i64, i64)>,
+}
+
+impl GridSampler {
+    /// Creates a new `GridSampler` for the node with the given `node_bounds`. `grid_size` must be even
+    pub(crate) fn new(node_bounds: &AABB<f64>, grid_size: i64) -> Self {
+        debug_assert!(grid_size > 0 && grid_size % 2 == 0);
+        Self {
+            node_min: *node_bounds.min(),
+            node_extent: node_bounds.extent(),
+            grid_size,
+            occupied_cells: HashSet::new(),
+        }
+    }
+
+    /// Samples the point at `position`. Returns `None` if the point is kept in the node, or the octant of the child
+    /// node that the point has to be passed on to otherwise
+    pub(crate) fn sample(&mut self, position: &Point3<f64>) -> Option<Octant> {
+        let cell = self.cell(position);
+        if self.occupied_cells.insert(cell) {
+            return None;
+        }
+        Some(self.octant_of_cell(cell))
+    }
+
+    /// Returns the octant of the child node that contains the point at `position`
+    pub(crate) fn octant(&self, position: &Point3<f64>) -> Octant {
+        self.octant_of_cell(self.cell(position))
+    }
+
+    fn cell(&self, position: &Point3<f64>) -> (i64, i64, i64) {
+        let local_position = position - self.node_min;
+        let grid_size = self.grid_size;
+        let to_cell = |local: f64, extent: f64| -> i64 {
+            ((local / extent) * grid_size as f64).clamp(0.0, (grid_size - 1) as f64) as i64
+        };
+        (
+            to_cell(local_position.x, self.node_extent.x),
+            to_cell(local_position.y, self.node_extent.y),
+            to_cell(local_position.z, self.node_extent.z),
+        )
+    }
+
+    fn octant_of_cell(&self, cell: (i64, i64, i64)) -> Octant {
+        let half_grid = self.grid_size / 2;
+        Octant {
+            x: cell.0 >= half_grid,
+            y: cell.1 >= half_grid,
+            z: cell.2 >= half_grid,
+        }
+    }
+}
+
+/// Parameters for building an octree with `build_octree`
+pub(crate) struct OctreeParameters {
+    /// Nodes with at most this many points are not subdivided. Subdivided nodes keep at most this many of their
+    /// sampled points, the remaining points are passed on to the child nodes
+    pub max_points_per_node: usize,
+    /// Number of grid cells along each axis of a node that are used for sampling the points of the node
+    pub grid_size: i64,
+    /// Maximum depth of the octree. All remaining points of a node at this depth are stored in the node itself
+    pub max_depth: u32,
+}
+
+/// Sorts the points with the given `positions` into an octree with the given cubic `root_bounds`, using a
+/// `GridSampler` for each node (see `OctreeParameters` for when nodes are subdivided). The octree formats differ in how they number the children of a node, so
+/// `child_index` maps an octant to the index of the child node, and `child_key` returns the key of the child node
+/// with a given index. Returns the keys of all nodes together with the indices of the points within each node
+pub(crate) fn build_octree<K, I, C>(
+    positions: &[Point3<f64>],
+    root_key: K,
+    root_bounds: &AABB<f64>,
+    parameters: &OctreeParameters,
+    child_index: I,
+    child_key: C,
+) -> Vec<(K, Vec<usize>)>
+where
+    I: Fn(Octant) -> usize,
+    C: Fn(&K, usize) -> K,
+{
+    let mut nodes = vec![];
+    if positions.is_empty() {
+        return nodes;
+    }
+
+    let mut nodes_to_process = vec![(
+        root_key,
+        *root_bounds,
+        0,
+        (0..positions.len()).collect::<Vec<_>>(),
+    )];
+    while let Some((key, node_bounds, depth, point_indices)) = nodes_to_process.pop() {
+        if point_indices.len() <= parameters.max_points_per_node || depth >= parameters.max_depth {
+            nodes.push((key, point_indices));
+            continue;
+        }
+
+        let mut sampler = GridSampler::new(&node_bounds, parameters.grid_size);
+        let mut points_in_node = vec![];
+        let mut points_in_children: [(Option<Octant>, Vec<usize>); 8] = Default::default();
+        for point_index in point_indices {
+            let position = &positions[point_index];
+            let octant = match sampler.sample(position) {
+                None if points_in_node.len() < parameters.max_points_per_node => {
+                    points_in_node.push(point_index);
+                    continue;
+                }
+                None => sampler.octant(position),
+                Some(octant) => octant,
+            };
+            let (child_octant, points_in_child) = &mut points_in_children[child_index(octant)];
+            *child_octant = Some(octant);
+            points_in_child.push(point_index);
+        }
+
+        for (index, (octant, points_in_child)) in points_in_children.iter_mut().enumerate() {
+            if let Some(octant) = octant {
+                nodes_to_process.push((
+                    child_key(&key, index),
+                    octant.bounds_within(&node_bounds),
+                    depth + 1,
+                    std::mem::take(points_in_child),
+                ));
+            }
+        }
+        nodes.push((key, points_in_node));
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_sampler() {
+        let bounds = AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(4.0, 4.0, 4.0));
+        let mut sampler = GridSampler::new(&bounds, 2);
+        assert_eq!(None, sampler.sample(&Point3::new(0.5, 0.5, 0.5)));
+        assert_eq!(None, sampler.sample(&Point3::new(3.5, 0.5, 4.0)));
+        assert_eq!(
+            Some(Octant {
+                x: false,
+                y: false,
+                z: false
+            }),
+            sampler.sample(&Point3::new(1.0, 1.0, 1.0))
+        );
+        assert_eq!(
+            Some(Octant {
+                x: true,
+                y: false,
+                z: true
+            }),
+            sampler.sample(&Point3::new(3.0, 0.0, 3.0))
+        );
+
+        let octant = Octant {
+            x: true,
+            y: false,
+            z: true,
+        };
+        assert_eq!(
+            AABB::from_min_max(Point3::new(2.0, 0.0, 2.0), Point3::new(4.0, 2.0, 4.0)),
+            octant.bounds_within(&bounds)
+        );
+    }
+
+    #[test]
+    fn test_build_octree() {
+        let positions = (0..1000)
+            .map(|index| Point3::new((index % 10) as f64, ((index / 10) % 10) as f64, 0.0))
+            .collect::<Vec<_>>();
+        let bounds = AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(16.0, 16.0, 16.0));
+        let nodes = build_octree(
+            &positions,
+            String::new(),
+            &bounds,
+            &OctreeParameters {
+                max_points_per_node: 50,
+                grid_size: 4,
+                max_depth: 8,
+            },
+            |octant| octant.x as usize | ((octant.y as usize) << 1) | ((octant.z as usize) << 2),
+            |name, child_index| format!("{}{}", name, child_index),
+        );
+
+        let mut all_points = nodes
+            .iter()
+            .flat_map(|(_, points)| points.iter().copied())
+            .collect::<Vec<_>>();
+        all_points.sort_unstable();
+        assert_eq!((0..positions.len()).collect::<Vec<_>>(), all_points);
+
+        for (name, points) in &nodes {
+            let node_bounds = name.chars().fold(bounds, |node_bounds, child| {
+                let child_index = child.to_digit(8).unwrap();
+                Octant {
+                    x: child_index & 1 != 0,
+                    y: child_index & 2 != 0,
+                    z: child_index & 4 != 0,
+                }
+                .bounds_within(&node_bounds)
+            });
+            for point in points {
+                assert!(node_bounds.contains(&positions[*point]));
+            }
+        }
+        // Subdivided nodes store at most one point per grid cell, and no node stores more than 50 points
+        assert!(nodes.len() > 1);
+        assert!(nodes.iter().all(|(_, points)| points.len() <= 50));
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::containers::{BorrowedBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer};
+use pasture_core::layout::attributes::POSITION_3D;
+use pasture_core::layout::conversion::BufferLayoutConverter;
+use pasture_core::layout::PointLayout;
+use pasture_core::nalgebra::Vector3;
+
+use super::{GenericPointReader, GenericPointWriter, PointReader, PointWriter};
+
+/// Number of points that are copied at once when a tile is reopened
+const POINTS_PER_CHUNK: usize = 1 << 16;
+
+/// Index of a tile within the regular grid of a [`TilingWriter`]
+type TileIndex = (i64, i64);
+
+/// A single output file of a [`TilingWriter`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTile {
+    /// Index of the tile within the grid. The tile with index `(i, j)` covers the area from `(i * tile_size, j *
+    /// tile_size)` (inclusive) to `((i + 1) * tile_size, (j + 1) * tile_size)` (exclusive)
+    pub index: (i64, i64),
+    /// Lower-left corner of the tile, i.e. its minimum x and y coordinates
+    pub lower_left: (f64, f64),
+    pub path: PathBuf,
+    /// Number of points that have been written into the tile
+    pub point_count: usize,
+}
+
+/// `PointWriter` that splits the points into square tiles of a regular grid in the XY plane and writes each tile
+/// into its own file. The points are routed by their `POSITION_3D` attribute to a [`GenericPointWriter`] for
+/// their tile, so all formats that the `GenericPointWriter` supports can be used (LAS by default). The grid is
+/// aligned with the origin and each file is named after the index of its tile within the grid (see
+/// [`OutputTile::index`]), e.g. `512_5403.las` for the 1km tile whose lower-left corner is at `(512000, 5403000)`.
+///
+/// At most [`with_max_open_files`](Self::with_max_open_files) tiles are open at the same time. If a point belongs
+/// to another tile, the least recently used tile is flushed and closed. Closed tiles are reopened when they receive
+/// new points, which requires copying the points that have been written into the tile so far. Writing spatially
+/// coherent chunks of points keeps the number of reopened tiles small.
+///
+/// *NOTE*: Headers of the tiles (e.g. the bounds and point counts of LAS files) are only correct once all tiles have
+/// been closed. `flush` closes all tiles, so once you are done writing points, make sure to call `flush`!
+pub struct TilingWriter {
+    output_directory: PathBuf,
+    layout: PointLayout,
+    tile_size: f64,
+    extension: String,
+    file_name_prefix: String,
+    max_open_files: usize,
+    tiles: BTreeMap<TileIndex, OutputTile>,
+    open_writers: HashMap<TileIndex, GenericPointWriter>,
+    /// Indices of the open tiles, from the least recently used to the most recently used
+    usage_order: VecDeque<TileIndex>,
+}
+
+impl TilingWriter {
+    /// The default maximum number of tiles that are open at the same time
+    pub const DEFAULT_MAX_OPEN_FILES: usize = 64;
+
+    /// Creates a new `TilingWriter` that writes points with the given `point_layout` into square tiles with an edge
+    /// length of `tile_size` in the given `output_directory`. The directory is created if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// If `point_layout` does not contain the `POSITION_3D` attribute, if `tile_size` is not a positive number, or if
+    /// the output directory can't be created, an error is returned
+    pub fn from_path_and_point_layout<P: AsRef<Path>>(
+        output_directory: P,
+        point_layout: &PointLayout,
+        tile_size: f64,
+    ) -> Result<Self> {
+        if !point_layout.has_attribute_with_name(POSITION_3D.name()) {
+            bail!("PointLayout for TilingWriter must contain the POSITION_3D attribute");
+        }
+        if !(tile_size.is_finite() && tile_size > 0.0) {
+            bail!("Tile size must be a positive number, but was {}", tile_size);
+        }
+        let output_directory = output_directory.as_ref().to_owned();
+        fs::create_dir_all(&output_directory).with_context(|| {
+            format!(
+                "Could not create output directory {}",
+                output_directory.display()
+            )
+        })?;
+        Ok(Self {
+            output_directory,
+            layout: point_layout.clone(),
+            tile_size,
+            extension: "las".into(),
+            file_name_prefix: String::new(),
+            max_open_files: Self::DEFAULT_MAX_OPEN_FILES,
+            tiles: Default::default(),
+            open_writers: Default::default(),
+            usage_order: Default::default(),
+        })
+    }
+
+    /// Sets the file extension of the tiles, which determines their format (e.g. `laz`)
+    pub fn with_extension<S: Into<String>>(mut self, extension: S) -> Self {
+        self.extension = extension.into();
+        self
+    }
+
+    /// Sets a prefix for the file names of the tiles
+    pub fn with_file_name_prefix<S: Into<String>>(mut self, file_name_prefix: S) -> Self {
+        self.file_name_prefix = file_name_prefix.into();
+        self
+    }
+
+    /// Sets the maximum number of tiles that are open at the same time
+    pub fn with_max_open_files(mut self, max_open_files: usize) -> Self {
+        self.max_open_files = max_open_files.max(1);
+        self
+    }
+
+    /// Returns all tiles that points have been written into, ordered by the lower-left corner of the tiles
+    pub fn tiles(&self) -> impl Iterator<Item = &OutputTile> + '_ {
+        self.tiles.values()
+    }
+
+    /// Returns the index of the tile that contains the given `position`
+    fn tile_index(&self, position: &Vector3<f64>) -> TileIndex {
+        (
+            (position.x / self.tile_size).floor() as i64,
+            (position.y / self.tile_size).floor() as i64,
+        )
+    }
+
+    /// Returns the writer for the tile with the given `tile_index`, opening the tile if necessary. If too many tiles
+    /// are open, the least recently used tile is closed
+    fn writer_for_tile(&mut self, tile_index: TileIndex) -> Result<&mut GenericPointWriter> {
+        if self.open_writers.contains_key(&tile_index) {
+            self.usage_order
+                .retain(|open_index| *open_index != tile_index);
+        } else {
+            while self.open_writers.len() >= self.max_open_files {
+                let least_recently_used = self
+                    .usage_order
+                    .pop_front()
+                    .expect("Open tiles must be in the usage order");
+                self.close_tile(least_recently_used)?;
+            }
+
+            let tile_size = self.tile_size;
+            let path = self.output_directory.join(format!(
+                "{}{}_{}.{}",
+                self.file_name_prefix, tile_index.0, tile_index.1, self.extension
+            ));
+            let tile = self.tiles.entry(tile_index).or_insert_with(|| OutputTile {
+                index: tile_index,
+                lower_left: (
+                    tile_index.0 as f64 * tile_size,
+                    tile_index.1 as f64 * tile_size,
+                ),
+                path,
+                point_count: 0,
+            });
+            let writer = if tile.point_count == 0 {
+                GenericPointWriter::open_file(&tile.path, &self.layout)?
+            } else {
+                Self::reopen_tile(&tile.path, &self.layout)?
+            };
+            self.open_writers.insert(tile_index, writer);
+        }
+        self.usage_order.push_back(tile_index);
+        Ok(self
+            .open_writers
+            .get_mut(&tile_index)
+            .expect("Writer for tile must be open"))
+    }
+
+    /// Flushes and closes the tile with the given `tile_index`
+    fn close_tile(&mut self, tile_index: TileIndex) -> Result<()> {
+        if let Some(mut writer) = self.open_writers.remove(&tile_index) {
+            writer.flush().with_context(|| {
+                format!(
+                    "Failed to flush tile {}",
+                    self.tiles[&tile_index].path.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Opens a new writer for the existing tile file at `path` and copies all points of the file into it
+    fn reopen_tile(path: &Path, point_layout: &PointLayout) -> Result<GenericPointWriter> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        let previous_path = path.with_extension(format!("previous.{}", extension));
+        fs::rename(path, &previous_path)
+            .with_context(|| format!("Could not reopen tile {}", path.display()))?;
+
+        let mut writer = GenericPointWriter::open_file(path, point_layout)?;
+        {
+            let mut reader = GenericPointReader::open_file(&previous_path)?;
+            let reader_layout = reader.get_default_point_layout().clone();
+            loop {
+                let chunk = reader.read::<VectorBuffer>(POINTS_PER_CHUNK)?;
+                if chunk.is_empty() {
+                    break;
+                }
+                if reader_layout == *point_layout {
+                    writer.write(&chunk)?;
+                } else {
+                    let converted = BufferLayoutConverter::for_layouts_with_default(
+                        &reader_layout,
+                        point_layout,
+                    )
+                    .convert::<VectorBuffer, _>(&chunk);
+                    writer.write(&converted)?;
+                }
+            }
+        }
+        fs::remove_file(&previous_path)?;
+        Ok(writer)
+    }
+}
+
+impl PointWriter for TilingWriter {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if *points.point_layout() != self.layout {
+            bail!("PointLayout of buffer does not match the PointLayout that this TilingWriter was constructed with");
+        }
+
+        let mut points_per_tile: BTreeMap<TileIndex, Vec<usize>> = BTreeMap::new();
+        for (point_index, position) in points
+            .view_attribute_with_conversion::<Vector3<f64>>(&POSITION_3D)?
+            .into_iter()
+            .enumerate()
+        {
+            points_per_tile
+                .entry(self.tile_index(&position))
+                .or_default()
+                .push(point_index);
+        }
+
+        let mut point = vec![0; self.layout.size_of_point_entry() as usize];
+        for (tile_index, point_indices) in points_per_tile {
+            let mut tile_points = VectorBuffer::new_from_layout(self.layout.clone());
+            for point_index in &point_indices {
+                points.get_point(*point_index, &mut point);
+                // Safe because `tile_points` has the same `PointLayout` as `points`
+                unsafe {
+                    tile_points.push_points(&point);
+                }
+            }
+            self.writer_for_tile(tile_index)?.write(&tile_points)?;
+            self.tiles
+                .get_mut(&tile_index)
+                .expect("Tile must exist")
+                .point_count += point_indices.len();
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes all open tiles. Writing more points afterwards reopens the tiles as needed
+    fn flush(&mut self) -> Result<()> {
+        while let Some(tile_index) = self.usage_order.pop_front() {
+            self.close_tile(tile_index)?;
+        }
+        Ok(())
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+impl Drop for TilingWriter {
+    fn drop(&mut self) {
+        self.flush().expect("Error while flushing TilingWriter")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pasture_core::layout::attributes::INTENSITY;
+    use pasture_core::layout::PointType;
+    use pasture_core::math::AABB;
+    use pasture_derive::PointType;
+
+    use super::*;
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct TestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+    }
+
+    /// Returns `count` points on a diagonal line through the tiles of a 1000 unit grid, starting at `start`
+    fn points_on_line(start: Vector3<f64>, count: usize, first_intensity: u16) -> VectorBuffer {
+        (0..count)
+            .map(|index| TestPoint {
+                position: start + Vector3::new(index as f64 * 150.0, index as f64 * 75.0, 0.5),
+                intensity: first_intensity + index as u16,
+            })
+            .collect()
+    }
+
+    fn run_tiling_writer_test(extension: &str) -> Result<()> {
+        let directory = std::env::temp_dir().join(format!(
+            "pasture_tiling_writer_{}_{}",
+            extension,
+            std::process::id()
+        ));
+        let first_points = points_on_line(Vector3::new(-1200.0, 300.0, 10.0), 20, 0);
+        let second_points = points_on_line(Vector3::new(-1100.0, 310.0, 20.0), 20, 20);
+        let tiles = {
+            // Only two open files force closing and reopening tiles, since the points span several tiles
+            let mut writer =
+                TilingWriter::from_path_and_point_layout(&directory, &TestPoint::layout(), 1000.0)?
+                    .with_extension(extension)
+                    .with_file_name_prefix("tile_")
+                    .with_max_open_files(2);
+            writer.write(&first_points)?;
+            writer.write(&second_points)?;
+            writer.flush()?;
+            writer.tiles().cloned().collect::<Vec<_>>()
+        };
+
+        let all_points = first_points
+            .view::<TestPoint>()
+            .into_iter()
+            .chain(second_points.view::<TestPoint>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            all_points.len(),
+            tiles.iter().map(|tile| tile.point_count).sum::<usize>()
+        );
+        assert!(tiles.len() > 2);
+        assert_eq!(
+            directory.join(format!("tile_-2_0.{}", extension)),
+            tiles[0].path
+        );
+        assert_eq!((-2, 0), tiles[0].index);
+
+        for tile in &tiles {
+            let mut expected_points = all_points
+                .iter()
+                .filter(|point| {
+                    // Tiles contain their lower-left border but not their upper-right border
+                    let position = point.position;
+                    (position.x / 1000.0).floor() * 1000.0 == tile.lower_left.0
+                        && (position.y / 1000.0).floor() * 1000.0 == tile.lower_left.1
+                })
+                .copied()
+                .collect::<Vec<_>>();
+            expected_points.sort_by_key(|point| point.intensity);
+            assert_eq!(expected_points.len(), tile.point_count);
+
+            let mut reader = GenericPointReader::open_file(&tile.path)?;
+            assert_eq!(Some(tile.point_count), reader.point_count());
+            let mut tile_points = VectorBuffer::new_from_layout(TestPoint::layout());
+            tile_points.resize(tile.point_count);
+            reader.read_into(&mut tile_points, tile.point_count)?;
+            let mut actual_points = tile_points
+                .view::<TestPoint>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            actual_points.sort_by_key(|point| point.intensity);
+            assert_eq!(expected_points, actual_points);
+
+            if let Some(bounds) = reader.get_metadata().bounds() {
+                let expected_bounds = expected_points.iter().skip(1).fold(
+                    {
+                        let position = expected_points[0].position;
+                        AABB::from_min_max_unchecked(position.into(), position.into())
+                    },
+                    |bounds, point| {
+                        let position = point.position;
+                        AABB::extend_with_point(&bounds, &position.into())
+                    },
+                );
+                assert_eq!(expected_bounds, bounds);
+            }
+        }
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiling_writer_las() -> Result<()> {
+        run_tiling_writer_test("las")
+    }
+
+    #[test]
+    fn test_tiling_writer_native() -> Result<()> {
+        run_tiling_writer_test("pasture")
+    }
+
+    #[test]
+    fn test_tiling_writer_requires_positions() {
+        let layout = PointLayout::from_attributes(&[INTENSITY]);
+        assert!(
+            TilingWriter::from_path_and_point_layout(std::env::temp_dir(), &layout, 1.0).is_err()
+        );
+        assert!(TilingWriter::from_path_and_point_layout(
+            std::env::temp_dir(),
+            &TestPoint::layout(),
+            0.0
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_tiling_writer_layout_mismatch() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!(
+            "pasture_tiling_writer_layout_mismatch_{}",
+            std::process::id()
+        ));
+        let mut writer = TilingWriter::from_path_and_point_layout(
+            &directory,
+            &PointLayout::from_attributes(&[POSITION_3D]),
+            1.0,
+        )?
+        .with_extension("pasture");
+        assert!(writer
+            .write(&points_on_line(Vector3::new(0.0, 0.0, 0.0), 2, 0))
+            .is_err());
+        assert_eq!(0, writer.tiles().count());
+        drop(writer);
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiling_writer_file_names_with_fractional_tile_size() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!(
+            "pasture_tiling_writer_fractional_{}",
+            std::process::id()
+        ));
+        let points = [
+            TestPoint {
+                position: Vector3::new(0.35, 0.05, 0.0),
+                intensity: 0,
+            },
+            TestPoint {
+                position: Vector3::new(-0.15, 0.25, 0.0),
+                intensity: 1,
+            },
+        ]
+        .iter()
+        .copied()
+        .collect::<VectorBuffer>();
+        let tiles = {
+            let mut writer =
+                TilingWriter::from_path_and_point_layout(&directory, &TestPoint::layout(), 0.1)?
+                    .with_extension("pasture");
+            writer.write(&points)?;
+            writer.flush()?;
+            writer.tiles().cloned().collect::<Vec<_>>()
+        };
+        fs::remove_dir_all(&directory)?;
+
+        // `3.0 * 0.1` is not exactly `0.3`, so the file names must not be derived from the lower-left corners
+        let file_names = tiles
+            .iter()
+            .map(|tile| tile.path.file_name().unwrap().to_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["-2_2.pasture", "3_0.pasture"], file_names);
+        Ok(())
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::VecDeque;
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use las_rs::{raw, Builder, Header, Vlr};
+use laz::record::{LayeredPointRecordDecompressor, RecordDecompressor};
+use laz::LazVlr;
+use pasture_core::containers::{
+    BorrowedMutBuffer, ExternalMemoryBuffer, MakeBufferFromLayout, OwningBuffer,
+};
+use pasture_core::layout::PointLayout;
+use pasture_core::math::AABB;
+
+use super::{CopcHierarchyEntry, CopcInfo, COPC_USER_ID};
+use crate::las::{
+    get_default_las_converter, map_laz_err, point_layout_from_las_metadata,
+    read_evlrs_into_metadata, LASMetadata,
+};
+
+/// A query for the nodes of a COPC octree. By default, a query selects all nodes. The query can be restricted
+/// to nodes that intersect a bounding box, and to nodes up to a maximum depth or resolution
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CopcQuery {
+    bounds: Option<AABB<f64>>,
+    max_depth: Option<i32>,
+    resolution: Option<f64>,
+}
+
+impl CopcQuery {
+    /// Creates a new `CopcQuery` that selects all nodes
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Only select nodes whose bounds intersect the given `bounds`
+    pub fn with_bounds(mut self, bounds: AABB<f64>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Only select nodes with a level less than or equal to `max_depth`. The root node is at level 0
+    pub fn with_max_depth(mut self, max_depth: i32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Only select nodes up to (and including) the first level whose point spacing is less than or
+    /// equal to `resolution`. If both a maximum depth and a resolution are set, the smaller depth wins
+    pub fn with_resolution(mut self, resolution: f64) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// Returns the maximum level that this query selects, given the `CopcInfo` of a COPC file. Returns `None`
+    /// if this query is not restricted in depth
+    pub fn max_level(&self, copc_info: &CopcInfo) -> Option<i32> {
+        let level_from_resolution = self.resolution.map(|resolution| {
+            if resolution <= 0.0 || copc_info.spacing <= resolution {
+                0
+            } else {
+                (copc_info.spacing / resolution).log2().ceil() as i32
+            }
+        });
+        match (self.max_depth, level_from_resolution) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Returns `true` if the node with the given `entry` is selected by this query
+    pub fn matches(&self, entry: &CopcHierarchyEntry, copc_info: &CopcInfo) -> bool {
+        if let Some(max_level) = self.max_level(copc_info) {
+            if entry.key.level > max_level {
+                return false;
+            }
+        }
+        match &self.bounds {
+            Some(bounds) => entry
+                .key
+                .bounds(&copc_info.root_bounds())
+                .intersects(bounds),
+            None => true,
+        }
+    }
+}
+
+/// Reader for Cloud Optimized Point Cloud (COPC) files. COPC files are LAZ 1.4 files whose points are organized
+/// in an octree, with each octree node stored as a separate LAZ chunk. Instead of reading all points sequentially
+/// (which is possible with the [`LASReader`](crate::las::LASReader)), the `CopcReader` reads only the nodes
+/// selected by a [`CopcQuery`], e.g. all nodes intersecting a bounding box up to a maximum depth
+pub struct CopcReader<R: Read + Seek + Send> {
+    read: R,
+    laszip_vlr: LazVlr,
+    metadata: LASMetadata,
+    copc_info: CopcInfo,
+    layout: PointLayout,
+    las_point_records_layout: PointLayout,
+    hierarchy: Vec<CopcHierarchyEntry>,
+    size_of_point_in_file: u64,
+}
+
+impl CopcReader<BufReader<File>> {
+    /// Creates a new `CopcReader` by opening the file at the given `path`. See [`CopcReader::from_read`] for
+    /// more information
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+        point_layout_matches_memory_layout: bool,
+    ) -> Result<Self> {
+        let file = BufReader::new(
+            File::open(path.as_ref())
+                .with_context(|| format!("Could not open file {}", path.as_ref().display()))?,
+        );
+        Self::from_read(file, point_layout_matches_memory_layout)
+    }
+}
+
+impl<R: Read + Seek + Send> CopcReader<R> {
+    /// Creates a new `CopcReader` from the given `read`. This parses the LAS header, the COPC info VLR and all
+    /// hierarchy pages. If `point_layout_matches_memory_layout` is `true`, the default `PointLayout` of the
+    /// `CopcReader` will exactly match the binary layout of the LAS point records, see
+    /// [`point_layout_from_las_point_format`](crate::las::point_layout_from_las_point_format) for more information
+    ///
+    /// # Errors
+    ///
+    /// If `read` does not point to a valid COPC file, an error is returned
+    pub fn from_read(mut read: R, point_layout_matches_memory_layout: bool) -> Result<Self> {
+        let raw_header = raw::Header::read_from(&mut read)?;
+        let offset_to_first_point_in_file = raw_header.offset_to_point_data as u64;
+        let size_of_point_in_file = raw_header.point_data_record_length as u64;
+        let number_of_vlrs = raw_header.number_of_variable_length_records;
+        let header_size = raw_header.header_size as u64;
+        let evlr_info = raw_header.evlr;
+
+        let mut header_builder = Builder::new(raw_header)?;
+        read.seek(SeekFrom::Start(header_size))?;
+        for _ in 0..number_of_vlrs {
+            let vlr = raw::Vlr::read_from(&mut read, false).map(Vlr::new)?;
+            header_builder.vlrs.push(vlr);
+        }
+
+        let position_after_reading_vlrs = read.stream_position()?;
+        if position_after_reading_vlrs < offset_to_first_point_in_file {
+            read.by_ref()
+                .take(offset_to_first_point_in_file - position_after_reading_vlrs)
+                .read_to_end(&mut header_builder.vlr_padding)?;
+        }
+
+        let header = header_builder.into_header()?;
+        let copc_info_vlr = header
+            .vlrs()
+            .iter()
+            .find(|vlr| vlr.user_id == COPC_USER_ID && vlr.record_id == CopcInfo::RECORD_ID)
+            .ok_or_else(|| anyhow!("COPC info VLR not found in file"))?;
+        let copc_info = CopcInfo::try_from(copc_info_vlr)?;
+        if !header.point_format().is_extended {
+            bail!(
+                "COPC files must use LAS point formats 6, 7 or 8, but file uses point format {:?}",
+                header.point_format()
+            );
+        }
+
+        let hierarchy = Self::read_hierarchy(&mut read, &copc_info)?;
+
+        let mut metadata: LASMetadata = header
+            .clone()
+            .try_into()
+            .context("Could not parse LAS header")?;
+        read_evlrs_into_metadata(&mut read, evlr_info.as_ref(), &mut metadata)?;
+        let point_layout =
+            point_layout_from_las_metadata(&metadata, point_layout_matches_memory_layout)?;
+        let matching_memory_layout = point_layout_from_las_metadata(&metadata, true)?;
+
+        let laszip_vlr = header
+            .vlrs()
+            .iter()
+            .find(|vlr| vlr.user_id == LazVlr::USER_ID && vlr.record_id == LazVlr::RECORD_ID)
+            .ok_or_else(|| anyhow!("LAZ variable length record not found in file!"))?;
+        let laszip_vlr = LazVlr::from_buffer(&laszip_vlr.data).map_err(map_laz_err)?;
+
+        Ok(Self {
+            read,
+            laszip_vlr,
+            metadata,
+            copc_info,
+            layout: point_layout,
+            las_point_records_layout: matching_memory_layout,
+            hierarchy,
+            size_of_point_in_file,
+        })
+    }
+
+    /// Reads all hierarchy pages starting at the root page. Returns the entries of all nodes that contain points
+    fn read_hierarchy(read: &mut R, copc_info: &CopcInfo) -> Result<Vec<CopcHierarchyEntry>> {
+        let mut hierarchy = vec![];
+        let mut pages_to_read = VecDeque::new();
+        pages_to_read.push_back((
+            copc_info.root_hierarchy_offset,
+            copc_info.root_hierarchy_size,
+        ));
+        while let Some((page_offset, page_size)) = pages_to_read.pop_front() {
+            let mut page = vec![0; page_size as usize];
+            read.seek(SeekFrom::Start(page_offset))?;
+            read.read_exact(&mut page).with_context(|| {
+                format!("Failed to read COPC hierarchy page at offset {page_offset}")
+            })?;
+            for entry in CopcHierarchyEntry::parse_page(&page)? {
+                if entry.is_child_page() {
+                    pages_to_read.push_back((entry.offset, entry.byte_size as u64));
+                } else if entry.has_points() {
+                    hierarchy.push(entry);
+                }
+            }
+        }
+        hierarchy.sort_by_key(|entry| entry.key);
+        Ok(hierarchy)
+    }
+
+    /// Returns the LAS metadata of the associated `CopcReader`
+    pub fn las_metadata(&self) -> &LASMetadata {
+        &self.metadata
+    }
+
+    /// Returns the LAS header of the associated `CopcReader`
+    pub fn header(&self) -> &Header {
+        self.metadata.raw_las_header().expect("Missing LAS header")
+    }
+
+    /// Returns the COPC info VLR of the associated `CopcReader`
+    pub fn copc_info(&self) -> &CopcInfo {
+        &self.copc_info
+    }
+
+    /// Returns the default `PointLayout` of the associated `CopcReader`
+    pub fn point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+
+    /// Returns the hierarchy entries of all octree nodes that contain points, sorted by their `VoxelKey`
+    pub fn hierarchy_entries(&self) -> &[CopcHierarchyEntry] {
+        &self.hierarchy
+    }
+
+    /// Returns the maximum depth of the octree, i.e. the largest level of all nodes that contain points
+    pub fn max_depth(&self) -> i32 {
+        self.hierarchy
+            .iter()
+            .map(|entry| entry.key.level)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns the hierarchy entries of all nodes that are selected by the given `query`
+    pub fn query_nodes(&self, query: &CopcQuery) -> Vec<CopcHierarchyEntry> {
+        self.hierarchy
+            .iter()
+            .filter(|entry| query.matches(entry, &self.copc_info))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the total number of points in all nodes selected by the given `query`
+    pub fn query_point_count(&self, query: &CopcQuery) -> usize {
+        self.hierarchy
+            .iter()
+            .filter(|entry| query.matches(entry, &self.copc_info))
+            .map(|entry| entry.point_count as usize)
+            .sum()
+    }
+
+    /// Decompresses the points of the node with the given `entry` into the given `point_buffer`. Uses the
+    /// `PointLayout` of `point_buffer` for reading, which can either be the exact binary layout of the LAS point
+    /// records or any layout that the default LAS conversions support. Overwrites existing data in `point_buffer`
+    /// starting at the first point, so the length of `point_buffer` must be at least `entry.point_count`. Returns
+    /// the number of points that were read
+    ///
+    /// # Errors
+    ///
+    /// If the point data of the node can't be decompressed or converted into the `PointLayout` of `point_buffer`,
+    /// an error is returned
+    pub fn read_node_into<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &mut self,
+        entry: &CopcHierarchyEntry,
+        point_buffer: &'c mut B,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        self.read_node_into_range(entry, point_buffer, 0)
+    }
+
+    /// Reads the points of all nodes selected by `query` into the given `point_buffer`. The points of each node
+    /// are stored consecutively, in the order returned by [`query_nodes`](CopcReader::query_nodes). The length of
+    /// `point_buffer` must be at least [`query_point_count`](CopcReader::query_point_count). Returns the number
+    /// of points that were read
+    pub fn read_query_into<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &mut self,
+        query: &CopcQuery,
+        point_buffer: &'c mut B,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        let mut num_points_read = 0;
+        for entry in self.query_nodes(query) {
+            num_points_read += self.read_node_into_range(&entry, point_buffer, num_points_read)?;
+        }
+        Ok(num_points_read)
+    }
+
+    /// Reads the points of all nodes selected by `query` into a new buffer of type `B`. The `PointLayout` of the
+    /// new buffer will be equal to the default `PointLayout` of this `CopcReader`
+    pub fn read_query<'b, B: OwningBuffer<'b> + MakeBufferFromLayout<'b> + 'b>(
+        &mut self,
+        query: &CopcQuery,
+    ) -> Result<B> {
+        let mut buffer = B::new_from_layout(self.layout.clone());
+        buffer.resize(self.query_point_count(query));
+        self.read_query_into(query, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn read_node_into_range<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &mut self,
+        entry: &CopcHierarchyEntry,
+        point_buffer: &'c mut B,
+        first_point_in_buffer: usize,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        if !entry.has_points() {
+            return Ok(0);
+        }
+        let num_points = entry.point_count as usize;
+        let target_range = first_point_in_buffer..(first_point_in_buffer + num_points);
+        if target_range.end > point_buffer.len() {
+            bail!(
+                "Point buffer is too small to hold the {num_points} points of COPC node {}",
+                entry.key
+            );
+        }
+
+        if *point_buffer.point_layout() == self.las_point_records_layout {
+            if let Some(interleaved_buffer) = point_buffer.as_interleaved_mut() {
+                let point_data = interleaved_buffer.get_point_range_mut(target_range);
+                self.decompress_node(entry, point_data)?;
+                return Ok(num_points);
+            }
+        }
+
+        let mut point_data = vec![0; num_points * self.size_of_point_in_file as usize];
+        self.decompress_node(entry, &mut point_data)?;
+
+        if *point_buffer.point_layout() == self.las_point_records_layout {
+            // Safe because the buffer has the exact binary memory layout of the LAS point records
+            unsafe {
+                point_buffer.set_point_range(target_range, &point_data);
+            }
+        } else {
+            let source_buffer = ExternalMemoryBuffer::new(
+                point_data.as_slice(),
+                self.las_point_records_layout.clone(),
+            );
+            let target_layout = point_buffer.point_layout().clone();
+            let converter = get_default_las_converter(
+                &self.las_point_records_layout,
+                &target_layout,
+                self.header(),
+            )
+            .context("Unsupported conversion")?;
+            converter.convert_into_range(&source_buffer, 0..num_points, point_buffer, target_range);
+        }
+
+        Ok(num_points)
+    }
+
+    /// Decompresses all points of the node with the given `entry` into `point_data`. Each node is a separate LAZ
+    /// chunk, and since COPC only allows the point formats 6 to 8, all chunks use the layered LAZ compression, which
+    /// stores the number of points at the start of the chunk. So each chunk can be decompressed on its own, without
+    /// knowing about the preceding chunks or the chunk table
+    fn decompress_node(&mut self, entry: &CopcHierarchyEntry, point_data: &mut [u8]) -> Result<()> {
+        self.read
+            .seek(SeekFrom::Start(entry.offset))
+            .with_context(|| format!("Failed to seek to COPC node {}", entry.key))?;
+        let mut decompressor = LayeredPointRecordDecompressor::new(&mut self.read);
+        decompressor
+            .set_fields_from(self.laszip_vlr.items())
+            .map_err(map_laz_err)?;
+        decompressor
+            .decompress_many(point_data)
+            .with_context(|| format!("Failed to decompress COPC node {}", entry.key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pasture_core::containers::{BorrowedBuffer, VectorBuffer};
+    use pasture_core::nalgebra::Point3;
+
+    use super::*;
+    use crate::base::PointReader;
+    use crate::copc::VoxelKey;
+    use crate::las::{LASReader, LasPointFormat7};
+
+    fn test_copc_info() -> CopcInfo {
+        CopcInfo {
+            center: Point3::new(0.0, 0.0, 0.0),
+            halfsize: 8.0,
+            spacing: 2.0,
+            root_hierarchy_offset: 0,
+            root_hierarchy_size: 0,
+            gps_time_minimum: 0.0,
+            gps_time_maximum: 0.0,
+        }
+    }
+
+    fn entry(key: VoxelKey) -> CopcHierarchyEntry {
+        CopcHierarchyEntry {
+            key,
+            offset: 0,
+            byte_size: 0,
+            point_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_copc_query_max_level() {
+        let info = test_copc_info();
+        assert_eq!(None, CopcQuery::new().max_level(&info));
+        assert_eq!(Some(3), CopcQuery::new().with_max_depth(3).max_level(&info));
+        assert_eq!(
+            Some(0),
+            CopcQuery::new().with_resolution(4.0).max_level(&info)
+        );
+        assert_eq!(
+            Some(1),
+            CopcQuery::new().with_resolution(1.0).max_level(&info)
+        );
+        assert_eq!(
+            Some(2),
+            CopcQuery::new().with_resolution(0.6).max_level(&info)
+        );
+        assert_eq!(
+            Some(1),
+            CopcQuery::new()
+                .with_resolution(0.1)
+                .with_max_depth(1)
+                .max_level(&info)
+        );
+    }
+
+    #[test]
+    fn test_copc_query_matches() {
+        let info = test_copc_info();
+        let query = CopcQuery::new()
+            .with_bounds(AABB::from_min_max(
+                Point3::new(1.0, 1.0, 1.0),
+                Point3::new(2.0, 2.0, 2.0),
+            ))
+            .with_max_depth(1);
+
+        assert!(query.matches(&entry(VoxelKey::ROOT), &info));
+        assert!(query.matches(&entry(VoxelKey::new(1, 1, 1, 1)), &info));
+        assert!(!query.matches(&entry(VoxelKey::new(1, 0, 0, 0)), &info));
+        assert!(!query.matches(&entry(VoxelKey::new(2, 2, 2, 2)), &info));
+        assert!(CopcQuery::new().matches(&entry(VoxelKey::new(5, 0, 0, 0)), &info));
+    }
+
+    #[test]
+    fn test_read_copc_fixture() -> Result<()> {
+        // A COPC file whose root node is the LAZ chunk of `10_points_format_7.laz`, with variable-size chunks and a
+        // hierarchy that contains an empty node and a child hierarchy page
+        let test_file_path = |file_name: &str| {
+            format!(
+                "{}/resources/test/{}",
+                env!("CARGO_MANIFEST_DIR"),
+                file_name
+            )
+        };
+        let mut reader =
+            CopcReader::from_path(test_file_path("10_points_format_7.copc.laz"), false)?;
+        assert_eq!(
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(9.0, 9.0, 9.0)),
+            reader.copc_info().root_bounds()
+        );
+        let keys = reader
+            .hierarchy_entries()
+            .iter()
+            .map(|entry| entry.key)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![VoxelKey::ROOT], keys);
+
+        let expected_points =
+            LASReader::from_path(test_file_path("10_points_format_7.las"), false)?
+                .read::<VectorBuffer>(10)?;
+        let actual_points = reader.read_query::<VectorBuffer>(&CopcQuery::new())?;
+        assert_eq!(
+            expected_points
+                .view::<LasPointFormat7>()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            actual_points
+                .view::<LasPointFormat7>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            0,
+            reader.query_point_count(&CopcQuery::new().with_bounds(AABB::from_min_max(
+                Point3::new(10.0, 10.0, 10.0),
+                Point3::new(11.0, 11.0, 11.0)
+            )))
+        );
+        Ok(())
+    }
+}
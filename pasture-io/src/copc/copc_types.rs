@@ -0,0 +1,419 @@
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::{Cursor, Write};
+
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use las_rs::Vlr;
+use pasture_core::math::AABB;
+use pasture_core::nalgebra::{Point3, Vector3};
+
+/// The user ID of all COPC-specific VLRs and EVLRs
+pub const COPC_USER_ID: &str = "copc";
+
+/// Key of a single node in the COPC octree. The root node has the key `(0, 0, 0, 0)`, the eight children of a node
+/// with key `(l, x, y, z)` have the keys `(l + 1, 2x + [0|1], 2y + [0|1], 2z + [0|1])`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoxelKey {
+    pub level: i32,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl VoxelKey {
+    /// The key of the root node of a COPC octree
+    pub const ROOT: VoxelKey = VoxelKey {
+        level: 0,
+        x: 0,
+        y: 0,
+        z: 0,
+    };
+    /// The maximum level of a node in a COPC octree. The coordinates of a key at level `l` are in `[0;2^l)`, so keys
+    /// at deeper levels can't be represented with 32-bit coordinates
+    pub const MAX_LEVEL: i32 = 31;
+
+    pub fn new(level: i32, x: i32, y: i32, z: i32) -> Self {
+        Self { level, x, y, z }
+    }
+
+    /// Returns the key of the child node with the given `child_index`. The child index is in `[0;7]`, with
+    /// bit 0 corresponding to the x-axis, bit 1 to the y-axis and bit 2 to the z-axis
+    ///
+    /// # Panics
+    ///
+    /// If `child_index` is larger than 7
+    pub fn child(&self, child_index: usize) -> VoxelKey {
+        assert!(child_index < 8);
+        VoxelKey {
+            level: self.level + 1,
+            x: (self.x << 1) | (child_index & 1) as i32,
+            y: (self.y << 1) | ((child_index >> 1) & 1) as i32,
+            z: (self.z << 1) | ((child_index >> 2) & 1) as i32,
+        }
+    }
+
+    /// Returns the key of the parent node, or `None` if this is the root key
+    pub fn parent(&self) -> Option<VoxelKey> {
+        if self.level == 0 {
+            None
+        } else {
+            Some(VoxelKey {
+                level: self.level - 1,
+                x: self.x >> 1,
+                y: self.y >> 1,
+                z: self.z >> 1,
+            })
+        }
+    }
+
+    /// Returns `true` if this is the key of a node in a COPC octree, i.e. if the level is in `[0;MAX_LEVEL]` and all
+    /// coordinates are in `[0;2^level)`
+    /// ```
+    /// # use pasture_io::copc::VoxelKey;
+    /// assert!(VoxelKey::new(2, 3, 0, 1).is_valid());
+    /// assert!(!VoxelKey::new(2, 4, 0, 1).is_valid());
+    /// assert!(!VoxelKey::new(-1, 0, 0, 0).is_valid());
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        if self.level < 0 || self.level > Self::MAX_LEVEL {
+            return false;
+        }
+        let nodes_per_axis = 1_i64 << self.level;
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|coordinate| (0..nodes_per_axis).contains(&(*coordinate as i64)))
+    }
+
+    /// Returns the bounding box of the node with this key, given the bounding box of the root node
+    /// ```
+    /// # use pasture_io::copc::VoxelKey;
+    /// # use pasture_core::math::AABB;
+    /// # use pasture_core::nalgebra::Point3;
+    /// let root_bounds = AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(4.0, 4.0, 4.0));
+    /// let bounds = VoxelKey::new(1, 1, 0, 1).bounds(&root_bounds);
+    /// assert_eq!(*bounds.min(), Point3::new(2.0, 0.0, 2.0));
+    /// assert_eq!(*bounds.max(), Point3::new(4.0, 2.0, 4.0));
+    /// ```
+    pub fn bounds(&self, root_bounds: &AABB<f64>) -> AABB<f64> {
+        let node_size = root_bounds.extent() / 2.0_f64.powi(self.level);
+        let min = root_bounds.min()
+            + Vector3::new(
+                node_size.x * self.x as f64,
+                node_size.y * self.y as f64,
+                node_size.z * self.z as f64,
+            );
+        AABB::from_min_max_unchecked(min, min + node_size)
+    }
+}
+
+impl Display for VoxelKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}-{}-{}", self.level, self.x, self.y, self.z)
+    }
+}
+
+/// The COPC info VLR, which is the first VLR in every COPC file. It stores the cubic bounds of the octree root
+/// node, the point spacing at the root level and the location of the root hierarchy page
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopcInfo {
+    /// Center of the cubic bounds of the root node
+    pub center: Point3<f64>,
+    /// Half the side length of the cubic bounds of the root node
+    pub halfsize: f64,
+    /// Spacing between points at the root level of the octree
+    pub spacing: f64,
+    /// Absolute file offset to the first byte of the root hierarchy page
+    pub root_hierarchy_offset: u64,
+    /// Size of the root hierarchy page in bytes
+    pub root_hierarchy_size: u64,
+    pub gps_time_minimum: f64,
+    pub gps_time_maximum: f64,
+}
+
+impl CopcInfo {
+    pub const RECORD_ID: u16 = 1;
+    /// Size of the COPC info VLR data in bytes, including the reserved bytes at the end
+    pub const SIZE: usize = 160;
+
+    /// Returns the cubic bounding box of the root node of the COPC octree
+    pub fn root_bounds(&self) -> AABB<f64> {
+        let half_extent = Vector3::new(self.halfsize, self.halfsize, self.halfsize);
+        AABB::from_min_max_unchecked(self.center - half_extent, self.center + half_extent)
+    }
+
+    /// Returns the point spacing at the given level of the COPC octree
+    pub fn resolution_at_level(&self, level: i32) -> f64 {
+        self.spacing / 2.0_f64.powi(level)
+    }
+}
+
+impl TryFrom<&Vlr> for CopcInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Vlr) -> std::result::Result<Self, Self::Error> {
+        if value.user_id != COPC_USER_ID || value.record_id != Self::RECORD_ID {
+            bail!(
+                "Expected COPC info VLR ({COPC_USER_ID}, {}) but got ({}, {})",
+                Self::RECORD_ID,
+                value.user_id,
+                value.record_id
+            );
+        }
+        if value.data.len() != Self::SIZE {
+            bail!(
+                "COPC info VLR is defined to have a size of {} bytes, but got {} bytes instead",
+                Self::SIZE,
+                value.data.len()
+            );
+        }
+
+        let mut reader = Cursor::new(&value.data);
+        let center_x = reader.read_f64::<LittleEndian>()?;
+        let center_y = reader.read_f64::<LittleEndian>()?;
+        let center_z = reader.read_f64::<LittleEndian>()?;
+        Ok(Self {
+            center: Point3::new(center_x, center_y, center_z),
+            halfsize: reader.read_f64::<LittleEndian>()?,
+            spacing: reader.read_f64::<LittleEndian>()?,
+            root_hierarchy_offset: reader.read_u64::<LittleEndian>()?,
+            root_hierarchy_size: reader.read_u64::<LittleEndian>()?,
+            gps_time_minimum: reader.read_f64::<LittleEndian>()?,
+            gps_time_maximum: reader.read_f64::<LittleEndian>()?,
+        })
+    }
+}
+
+impl From<&CopcInfo> for Vlr {
+    fn from(value: &CopcInfo) -> Self {
+        let mut data = Vec::with_capacity(CopcInfo::SIZE);
+        // Writing into a Vec<u8> can't fail
+        (|| -> std::io::Result<()> {
+            data.write_f64::<LittleEndian>(value.center.x)?;
+            data.write_f64::<LittleEndian>(value.center.y)?;
+            data.write_f64::<LittleEndian>(value.center.z)?;
+            data.write_f64::<LittleEndian>(value.halfsize)?;
+            data.write_f64::<LittleEndian>(value.spacing)?;
+            data.write_u64::<LittleEndian>(value.root_hierarchy_offset)?;
+            data.write_u64::<LittleEndian>(value.root_hierarchy_size)?;
+            data.write_f64::<LittleEndian>(value.gps_time_minimum)?;
+            data.write_f64::<LittleEndian>(value.gps_time_maximum)?;
+            data.write_all(&[0; 88])
+        })()
+        .expect("Failed to serialize COPC info VLR");
+        Vlr {
+            user_id: COPC_USER_ID.to_owned(),
+            record_id: CopcInfo::RECORD_ID,
+            description: "COPC info VLR".to_owned(),
+            data,
+        }
+    }
+}
+
+impl Display for CopcInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "COPC info")?;
+        writeln!(
+            f,
+            "\tCenter: {} {} {}",
+            self.center.x, self.center.y, self.center.z
+        )?;
+        writeln!(f, "\tHalfsize: {}", self.halfsize)?;
+        writeln!(f, "\tSpacing: {}", self.spacing)?;
+        writeln!(
+            f,
+            "\tRoot hierarchy page: {} bytes at offset {}",
+            self.root_hierarchy_size, self.root_hierarchy_offset
+        )?;
+        writeln!(
+            f,
+            "\tGPS time: [{};{}]",
+            self.gps_time_minimum, self.gps_time_maximum
+        )
+    }
+}
+
+/// A single entry within a COPC hierarchy page. Depending on `point_count`, an entry either refers to
+/// the compressed point data of an octree node (`point_count > 0`), to an empty node (`point_count == 0`)
+/// or to another hierarchy page (`point_count == -1`)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CopcHierarchyEntry {
+    pub key: VoxelKey,
+    /// Absolute file offset to the point data chunk or the child hierarchy page
+    pub offset: u64,
+    /// Size of the point data chunk or the child hierarchy page in bytes
+    pub byte_size: i32,
+    pub point_count: i32,
+}
+
+impl CopcHierarchyEntry {
+    /// The record ID of the hierarchy EVLR
+    pub const HIERARCHY_RECORD_ID: u16 = 1000;
+    /// Size of a single hierarchy entry in bytes
+    pub const SIZE: usize = 32;
+
+    /// Does this entry refer to another hierarchy page?
+    pub fn is_child_page(&self) -> bool {
+        self.point_count == -1
+    }
+
+    /// Does this entry refer to a node that contains point data?
+    pub fn has_points(&self) -> bool {
+        self.point_count > 0
+    }
+
+    /// Parses all entries of a COPC hierarchy page from the given bytes
+    pub fn parse_page(page: &[u8]) -> Result<Vec<CopcHierarchyEntry>> {
+        if page.len() % Self::SIZE != 0 {
+            bail!(
+                "Size of COPC hierarchy page ({} bytes) must be a multiple of {} bytes",
+                page.len(),
+                Self::SIZE
+            );
+        }
+        let mut reader = Cursor::new(page);
+        (0..page.len() / Self::SIZE)
+            .map(|_| -> Result<Self> {
+                let level = reader.read_i32::<LittleEndian>()?;
+                let x = reader.read_i32::<LittleEndian>()?;
+                let y = reader.read_i32::<LittleEndian>()?;
+                let z = reader.read_i32::<LittleEndian>()?;
+                let entry = Self {
+                    key: VoxelKey::new(level, x, y, z),
+                    offset: reader.read_u64::<LittleEndian>()?,
+                    byte_size: reader.read_i32::<LittleEndian>()?,
+                    point_count: reader.read_i32::<LittleEndian>()?,
+                };
+                if !entry.key.is_valid() {
+                    return Err(anyhow!("Invalid key {} in COPC hierarchy entry", entry.key));
+                }
+                if entry.point_count < -1 {
+                    return Err(anyhow!(
+                        "Invalid point count {} in COPC hierarchy entry {}",
+                        entry.point_count,
+                        entry.key
+                    ));
+                }
+                Ok(entry)
+            })
+            .collect::<Result<Vec<_>>>()
+            .context("Failed to parse COPC hierarchy page")
+    }
+
+    /// Writes the binary representation of this entry to the given `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_i32::<LittleEndian>(self.key.level)?;
+        writer.write_i32::<LittleEndian>(self.key.x)?;
+        writer.write_i32::<LittleEndian>(self.key.y)?;
+        writer.write_i32::<LittleEndian>(self.key.z)?;
+        writer.write_u64::<LittleEndian>(self.offset)?;
+        writer.write_i32::<LittleEndian>(self.byte_size)?;
+        writer.write_i32::<LittleEndian>(self.point_count)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voxel_key_child_parent() {
+        let key = VoxelKey::new(2, 1, 3, 0);
+        for child_index in 0..8 {
+            let child = key.child(child_index);
+            assert_eq!(3, child.level);
+            assert_eq!(Some(key), child.parent());
+        }
+        assert_eq!(VoxelKey::new(3, 3, 6, 1), key.child(0b101));
+        assert_eq!(None, VoxelKey::ROOT.parent());
+    }
+
+    #[test]
+    fn test_copc_info_round_trip() -> Result<()> {
+        let info = CopcInfo {
+            center: Point3::new(1.0, 2.0, 3.0),
+            halfsize: 64.0,
+            spacing: 1.5,
+            root_hierarchy_offset: 12345,
+            root_hierarchy_size: 320,
+            gps_time_minimum: -1.0,
+            gps_time_maximum: 42.0,
+        };
+        let vlr: Vlr = (&info).into();
+        assert_eq!(CopcInfo::SIZE, vlr.data.len());
+        let parsed_info = CopcInfo::try_from(&vlr)?;
+        assert_eq!(info, parsed_info);
+
+        let root_bounds = parsed_info.root_bounds();
+        assert_eq!(Point3::new(-63.0, -62.0, -61.0), *root_bounds.min());
+        assert_eq!(Point3::new(65.0, 66.0, 67.0), *root_bounds.max());
+        assert_eq!(0.375, parsed_info.resolution_at_level(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copc_hierarchy_page_round_trip() -> Result<()> {
+        let entries = vec![
+            CopcHierarchyEntry {
+                key: VoxelKey::ROOT,
+                offset: 1024,
+                byte_size: 512,
+                point_count: 100,
+            },
+            CopcHierarchyEntry {
+                key: VoxelKey::new(1, 0, 1, 1),
+                offset: 4096,
+                byte_size: 64,
+                point_count: -1,
+            },
+        ];
+        let mut page = vec![];
+        for entry in &entries {
+            entry.write_to(&mut page)?;
+        }
+        assert_eq!(entries.len() * CopcHierarchyEntry::SIZE, page.len());
+
+        let parsed_entries = CopcHierarchyEntry::parse_page(&page)?;
+        assert_eq!(entries, parsed_entries);
+        assert!(parsed_entries[0].has_points());
+        assert!(parsed_entries[1].is_child_page());
+
+        assert!(CopcHierarchyEntry::parse_page(&page[..40]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_copc_hierarchy_page_invalid_keys() -> Result<()> {
+        let invalid_keys = [
+            VoxelKey::new(-1, 0, 0, 0),
+            VoxelKey::new(64, 0, 0, 0),
+            VoxelKey::new(VoxelKey::MAX_LEVEL + 1, 0, 0, 0),
+            VoxelKey::new(0, 1, 0, 0),
+            VoxelKey::new(2, 0, 4, 0),
+            VoxelKey::new(3, 0, 0, -1),
+        ];
+        for key in invalid_keys.iter() {
+            let mut page = vec![];
+            CopcHierarchyEntry {
+                key: *key,
+                offset: 1024,
+                byte_size: 512,
+                point_count: 100,
+            }
+            .write_to(&mut page)?;
+            assert!(
+                CopcHierarchyEntry::parse_page(&page).is_err(),
+                "Key {} must be rejected",
+                key
+            );
+        }
+
+        let deepest_key = VoxelKey::new(VoxelKey::MAX_LEVEL, i32::MAX, 0, i32::MAX);
+        assert!(deepest_key.is_valid());
+        let root_bounds =
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        assert!(root_bounds.contains(deepest_key.bounds(&root_bounds).min()));
+        Ok(())
+    }
+}
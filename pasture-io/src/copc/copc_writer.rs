@@ -0,0 +1,510 @@
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use las_rs::{point::Format, raw, Header, Vlr};
+use laz::{LasZipCompressor, LazItemRecordBuilder, LazVlr, LazVlrBuilder};
+use pasture_core::containers::BorrowedBuffer;
+use pasture_core::layout::PointLayout;
+use pasture_core::math::AABB;
+use pasture_core::nalgebra::Point3;
+
+use super::{CopcHierarchyEntry, CopcInfo, VoxelKey, COPC_USER_ID};
+use crate::base::{build_octree, OctreeParameters, PointWriter};
+use crate::las::{
+    las_header_builder_from_point_layout, map_laz_err, RawLASWriter, EVLR_HEADER_SIZE,
+};
+
+/// Number of grid cells along each axis of an octree node that are used for sampling the points of the node
+const GRID_SIZE: i64 = 128;
+/// Maximum depth of the octree. All remaining points of a node at this depth are stored in the node itself
+const MAX_DEPTH: u32 = 24;
+/// Byte offset of the GPS time within a LAS point record of the extended point formats (6 to 10)
+const GPS_TIME_OFFSET_EXTENDED_FORMATS: usize = 22;
+
+/// Sorts the points with the given `positions` into a COPC octree with the given cubic `root_bounds` (see
+/// `build_octree`). Returns the keys of all nodes, together with the indices of the points within each node,
+/// sorted by key
+fn build_copc_octree(
+    positions: &[Point3<f64>],
+    root_bounds: &AABB<f64>,
+    max_points_per_node: usize,
+) -> Vec<(VoxelKey, Vec<usize>)> {
+    let mut nodes = build_octree(
+        positions,
+        VoxelKey::ROOT,
+        root_bounds,
+        &OctreeParameters {
+            max_points_per_node,
+            grid_size: GRID_SIZE,
+            max_depth: MAX_DEPTH,
+        },
+        // COPC uses bit 0 of the child index for the x-axis, bit 1 for the y-axis and bit 2 for the z-axis
+        |octant| octant.x as usize | ((octant.y as usize) << 1) | ((octant.z as usize) << 2),
+        |key, child_index| key.child(child_index),
+    );
+    nodes.sort_by_key(|(key, _)| *key);
+    nodes
+}
+
+/// Writes the given raw LAS header and VLRs to the start of `writer`
+fn write_header_and_vlrs<W: Write + Seek>(
+    writer: &mut W,
+    header: &raw::Header,
+    vlrs: &[raw::Vlr],
+) -> Result<()> {
+    writer.seek(SeekFrom::Start(0))?;
+    header.write_to(&mut *writer)?;
+    for vlr in vlrs {
+        vlr.write_to(&mut *writer)?;
+    }
+    Ok(())
+}
+
+/// `PointWriter` implementation for Cloud Optimized Point Cloud (COPC) files. The points of all `write` calls are
+/// sorted into an octree, and each octree node is written as a separate LAZ chunk, together with the COPC info VLR
+/// and the hierarchy EVLR. The resulting files can be read with the [`CopcReader`](super::CopcReader) as well as
+/// with any other COPC or LAZ reader.
+///
+/// *NOTE*: The octree can only be built once all points are known, so the `CopcWriter` keeps all points in memory
+/// and writes the actual file on `flush`. Once you are done writing points, make sure to call `flush`!
+pub struct CopcWriter<T: Write + Seek + Send + 'static> {
+    writer: Option<T>,
+    las_writer: RawLASWriter<Cursor<Vec<u8>>>,
+    max_points_per_node: usize,
+    requires_flush: bool,
+}
+
+impl<T: Write + Seek + Send + 'static> CopcWriter<T> {
+    /// The default maximum number of points in an octree node before the node is subdivided
+    pub const DEFAULT_MAX_POINTS_PER_NODE: usize = 100_000;
+
+    /// Creates a new `CopcWriter` from the given `writer`. This uses a default-created LAS 1.4 header with the
+    /// matching COPC point format (6, 7 or 8) for the given `point_layout`, see
+    /// [`LASWriter::from_writer_and_point_layout`](crate::las::LASWriter::from_writer_and_point_layout)
+    pub fn from_writer_and_point_layout(writer: T, point_layout: &PointLayout) -> Result<Self> {
+        let mut header_builder = las_header_builder_from_point_layout(point_layout)?;
+        // COPC only supports point formats 6, 7 and 8
+        let point_format = &mut header_builder.point_format;
+        point_format.is_extended = true;
+        point_format.has_gps_time = true;
+        point_format.has_waveform = false;
+        if point_format.has_nir {
+            point_format.has_color = true;
+        }
+        let las_header = header_builder
+            .into_header()
+            .context("Could not default-create LAS header")?;
+        Self::from_writer_and_header(writer, las_header)
+    }
+
+    /// Creates a new `CopcWriter` from the given writer and LAS header
+    ///
+    /// # Errors
+    ///
+    /// COPC requires LAS version 1.4 and one of the point formats 6, 7 or 8. If the header does not meet these
+    /// requirements, an error is returned
+    pub fn from_writer_and_header(writer: T, header: Header) -> Result<Self> {
+        if header.version() < las_rs::Version::new(1, 4) {
+            bail!(
+                "COPC requires LAS version 1.4, but the LAS header has version {}",
+                header.version()
+            );
+        }
+        let point_format = header.point_format();
+        if !point_format.is_extended || point_format.has_waveform {
+            bail!(
+                "COPC requires LAS point format 6, 7 or 8, but the LAS header has point format {:?}",
+                point_format
+            );
+        }
+
+        let las_writer = RawLASWriter::from_write_and_header(Cursor::new(Vec::new()), header)?;
+        Ok(Self {
+            writer: Some(writer),
+            las_writer,
+            max_points_per_node: Self::DEFAULT_MAX_POINTS_PER_NODE,
+            requires_flush: true,
+        })
+    }
+
+    /// Sets the maximum number of points in an octree node. Nodes with more points are subdivided
+    pub fn with_max_points_per_node(mut self, max_points_per_node: usize) -> Self {
+        self.max_points_per_node = max_points_per_node.max(1);
+        self
+    }
+
+    /// Unwraps this `CopcWriter`, returning the underlying write type `T`. The COPC file is written before returning
+    /// the writer
+    pub fn into_inner(mut self) -> Result<T> {
+        self.flush()?;
+        self.writer
+            .take()
+            .ok_or_else(|| anyhow!("CopcWriter is in an invalid state"))
+    }
+
+    /// Writes the COPC file from all points that have been written so far
+    fn write_copc_file(&mut self) -> Result<()> {
+        self.las_writer.flush()?;
+        let las_data = self.las_writer.get_ref().get_ref();
+
+        // Parse the uncompressed LAS file that we wrote in memory
+        let mut las_reader = Cursor::new(las_data.as_slice());
+        let mut raw_header = raw::Header::read_from(&mut las_reader)?;
+        las_reader.seek(SeekFrom::Start(raw_header.header_size as u64))?;
+        let las_vlrs = (0..raw_header.number_of_variable_length_records)
+            .map(|_| raw::Vlr::read_from(&mut las_reader, false))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to read VLRs")?;
+        let las_evlrs = match &raw_header.evlr {
+            Some(evlr_info) => {
+                las_reader.seek(SeekFrom::Start(evlr_info.start_of_first_evlr))?;
+                (0..evlr_info.number_of_evlrs)
+                    .map(|_| raw::Vlr::read_from(&mut las_reader, true))
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("Failed to read EVLRs")?
+            }
+            None => vec![],
+        };
+
+        let point_count = raw_header
+            .large_file
+            .as_ref()
+            .map(|large_file| large_file.number_of_point_records as usize)
+            .unwrap_or(raw_header.number_of_point_records as usize);
+        let record_length = raw_header.point_data_record_length as usize;
+        let offset_to_point_data = raw_header.offset_to_point_data as usize;
+        let point_records =
+            &las_data[offset_to_point_data..(offset_to_point_data + point_count * record_length)];
+
+        let mut positions = Vec::with_capacity(point_count);
+        let mut gps_time_minimum = f64::MAX;
+        let mut gps_time_maximum = f64::MIN;
+        for record in point_records.chunks_exact(record_length) {
+            positions.push(Point3::new(
+                LittleEndian::read_i32(&record[0..4]) as f64 * raw_header.x_scale_factor
+                    + raw_header.x_offset,
+                LittleEndian::read_i32(&record[4..8]) as f64 * raw_header.y_scale_factor
+                    + raw_header.y_offset,
+                LittleEndian::read_i32(&record[8..12]) as f64 * raw_header.z_scale_factor
+                    + raw_header.z_offset,
+            ));
+            let gps_time = LittleEndian::read_f64(
+                &record[GPS_TIME_OFFSET_EXTENDED_FORMATS..(GPS_TIME_OFFSET_EXTENDED_FORMATS + 8)],
+            );
+            gps_time_minimum = gps_time_minimum.min(gps_time);
+            gps_time_maximum = gps_time_maximum.max(gps_time);
+        }
+
+        let root_bounds = if point_count == 0 {
+            gps_time_minimum = 0.0;
+            gps_time_maximum = 0.0;
+            AABB::from_min_max_unchecked(Point3::origin(), Point3::origin())
+        } else {
+            AABB::from_min_max_unchecked(
+                Point3::new(raw_header.min_x, raw_header.min_y, raw_header.min_z),
+                Point3::new(raw_header.max_x, raw_header.max_y, raw_header.max_z),
+            )
+            .as_cubic()
+        };
+        let halfsize = root_bounds.extent().x / 2.0;
+        let nodes = build_copc_octree(&positions, &root_bounds, self.max_points_per_node);
+
+        let mut copc_info = CopcInfo {
+            center: root_bounds.center(),
+            halfsize,
+            spacing: (2.0 * halfsize) / GRID_SIZE as f64,
+            root_hierarchy_offset: 0,
+            root_hierarchy_size: 0,
+            gps_time_minimum,
+            gps_time_maximum,
+        };
+
+        // The LAZ VLR has to use variable-sized chunks, as each octree node is a separate chunk
+        let point_format_id = raw_header.point_data_record_format & 0b1111;
+        let num_extra_bytes = record_length as u16 - Format::new(point_format_id)?.len();
+        let laz_items =
+            LazItemRecordBuilder::default_for_point_format_id(point_format_id, num_extra_bytes)
+                .map_err(map_laz_err)?;
+        let laz_vlr = LazVlrBuilder::new(laz_items)
+            .with_variable_chunk_size()
+            .build();
+        let mut laz_vlr_data = Cursor::new(Vec::<u8>::new());
+        laz_vlr.write_to(&mut laz_vlr_data)?;
+        let raw_laz_vlr = Vlr {
+            user_id: LazVlr::USER_ID.to_owned(),
+            record_id: LazVlr::RECORD_ID,
+            description: LazVlr::DESCRIPTION.to_owned(),
+            data: laz_vlr_data.into_inner(),
+        }
+        .into_raw(false)?;
+
+        // The COPC info VLR must be the first VLR. We don't know the location of the hierarchy yet, so we write the
+        // header and VLRs twice: Once now to reserve the space, and once after all other data has been written
+        let mut vlrs = vec![Vlr::from(&copc_info).into_raw(false)?, raw_laz_vlr];
+        vlrs.extend(las_vlrs);
+        raw_header.point_data_record_format = point_format_id | 0x80;
+        raw_header.number_of_variable_length_records = vlrs.len() as u32;
+
+        let mut writer = self
+            .writer
+            .take()
+            .ok_or_else(|| anyhow!("CopcWriter is in an invalid state"))?;
+        write_header_and_vlrs(&mut writer, &raw_header, &vlrs)?;
+        raw_header.offset_to_point_data = writer.stream_position()? as u32;
+
+        let mut compressor = LasZipCompressor::new(writer, laz_vlr).map_err(map_laz_err)?;
+        compressor.reserve_offset_to_chunk_table()?;
+        let mut hierarchy_entries = Vec::with_capacity(nodes.len());
+        let mut chunk_data = vec![];
+        for (key, point_indices) in &nodes {
+            chunk_data.clear();
+            for point_index in point_indices {
+                let record_start = point_index * record_length;
+                chunk_data.extend_from_slice(
+                    &point_records[record_start..(record_start + record_length)],
+                );
+            }
+
+            let chunk_start = compressor.get_mut().stream_position()?;
+            compressor
+                .compress_many(&chunk_data)
+                .with_context(|| format!("Failed to compress COPC node {key}"))?;
+            compressor.finish_current_chunk()?;
+            let chunk_end = compressor.get_mut().stream_position()?;
+
+            hierarchy_entries.push(CopcHierarchyEntry {
+                key: *key,
+                offset: chunk_start,
+                byte_size: (chunk_end - chunk_start) as i32,
+                point_count: point_indices.len() as i32,
+            });
+        }
+        compressor.done()?;
+        let mut writer = compressor.into_inner();
+
+        // All hierarchy entries are stored in a single page, the root page
+        let start_of_first_evlr = writer.stream_position()?;
+        let mut hierarchy_page =
+            Vec::with_capacity(hierarchy_entries.len() * CopcHierarchyEntry::SIZE);
+        for entry in &hierarchy_entries {
+            entry.write_to(&mut hierarchy_page)?;
+        }
+        copc_info.root_hierarchy_offset = start_of_first_evlr + EVLR_HEADER_SIZE;
+        copc_info.root_hierarchy_size = hierarchy_page.len() as u64;
+        let hierarchy_evlr = Vlr {
+            user_id: COPC_USER_ID.to_owned(),
+            record_id: CopcHierarchyEntry::HIERARCHY_RECORD_ID,
+            description: "COPC hierarchy".to_owned(),
+            data: hierarchy_page,
+        }
+        .into_raw(true)?;
+        hierarchy_evlr.write_to(&mut writer)?;
+        for evlr in &las_evlrs {
+            evlr.write_to(&mut writer)?;
+        }
+        let end_of_file = writer.stream_position()?;
+
+        raw_header.evlr = Some(raw::header::Evlr {
+            start_of_first_evlr,
+            number_of_evlrs: las_evlrs.len() as u32 + 1,
+        });
+        vlrs[0] = Vlr::from(&copc_info).into_raw(false)?;
+        write_header_and_vlrs(&mut writer, &raw_header, &vlrs)?;
+        writer.seek(SeekFrom::Start(end_of_file))?;
+        writer.flush()?;
+
+        self.writer = Some(writer);
+        Ok(())
+    }
+}
+
+impl CopcWriter<BufWriter<File>> {
+    /// Creates a new `CopcWriter` from the given path and LAS header
+    pub fn from_path_and_header<P: AsRef<Path>>(path: P, header: Header) -> Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+        Self::from_writer_and_header(writer, header)
+    }
+
+    /// Creates a new `CopcWriter` from the given `path` and `point_layout`
+    pub fn from_path_and_point_layout<P: AsRef<Path>>(
+        path: P,
+        point_layout: &PointLayout,
+    ) -> Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+        Self::from_writer_and_point_layout(writer, point_layout)
+    }
+}
+
+impl<T: Write + Seek + Send + 'static> PointWriter for CopcWriter<T> {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        self.las_writer.write(points)?;
+        self.requires_flush = true;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.requires_flush {
+            return Ok(());
+        }
+        self.write_copc_file()?;
+        self.requires_flush = false;
+        Ok(())
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        self.las_writer.get_default_point_layout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pasture_core::containers::VectorBuffer;
+    use pasture_core::layout::PointType;
+    use pasture_core::nalgebra::Vector3;
+
+    use super::*;
+    use crate::base::PointReader;
+    use crate::copc::{CopcQuery, CopcReader};
+    use crate::las::{LASReader, LasPointFormat7};
+
+    fn get_test_points() -> Vec<LasPointFormat7> {
+        (0..2000)
+            .map(|index| {
+                let x = (index % 20) as f64;
+                let y = ((index / 20) % 10) as f64;
+                let z = (index / 200) as f64;
+                LasPointFormat7 {
+                    position: Vector3::new(x * 0.5, y * 2.0, z),
+                    intensity: (index % 256) as u16,
+                    return_number: 1,
+                    number_of_returns: 1,
+                    classification: (index % 8) as u8,
+                    gps_time: index as f64,
+                    color_rgb: Vector3::new(index as u16, 0, 0),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn sorted_by_gps_time(mut points: Vec<LasPointFormat7>) -> Vec<LasPointFormat7> {
+        points.sort_by(|a, b| {
+            let (gps_time_a, gps_time_b) = (a.gps_time, b.gps_time);
+            gps_time_a.total_cmp(&gps_time_b)
+        });
+        points
+    }
+
+    fn write_copc_file(points: &[LasPointFormat7]) -> Result<Vec<u8>> {
+        let mut writer = CopcWriter::from_writer_and_point_layout(
+            Cursor::new(Vec::new()),
+            &LasPointFormat7::layout(),
+        )?
+        .with_max_points_per_node(100);
+        // Write in two batches to make sure that the points of all `write` calls end up in the octree
+        let (first_batch, second_batch) = points.split_at(points.len() / 2);
+        writer.write(&first_batch.iter().copied().collect::<VectorBuffer>())?;
+        writer.write(&second_batch.iter().copied().collect::<VectorBuffer>())?;
+        Ok(writer.into_inner()?.into_inner())
+    }
+
+    #[test]
+    fn test_build_octree() {
+        let positions = (0..1000)
+            .map(|index| Point3::new((index % 10) as f64, ((index / 10) % 10) as f64, 0.0))
+            .collect::<Vec<_>>();
+        let root_bounds =
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(16.0, 16.0, 16.0));
+        let nodes = build_copc_octree(&positions, &root_bounds, 50);
+
+        let mut all_indices = nodes
+            .iter()
+            .flat_map(|(_, indices)| indices.iter().copied())
+            .collect::<Vec<_>>();
+        all_indices.sort_unstable();
+        assert_eq!((0..1000).collect::<Vec<_>>(), all_indices);
+
+        for (key, indices) in &nodes {
+            let bounds = key.bounds(&root_bounds);
+            for index in indices {
+                assert!(bounds.contains(&positions[*index]));
+            }
+            if let Some(parent) = key.parent() {
+                assert!(nodes.iter().any(|(other_key, _)| *other_key == parent));
+            }
+            assert!(indices.len() <= 50);
+        }
+    }
+
+    #[test]
+    fn test_copc_writer_round_trip() -> Result<()> {
+        let points = get_test_points();
+        let copc_data = write_copc_file(&points)?;
+
+        let mut reader = CopcReader::from_read(Cursor::new(copc_data), false)?;
+        assert_eq!(points.len(), reader.las_metadata().point_count());
+        assert!(reader.hierarchy_entries().len() > 1);
+        assert!(reader.max_depth() > 0);
+        assert_eq!(points.len(), reader.query_point_count(&CopcQuery::new()));
+
+        let read_buffer = reader.read_query::<VectorBuffer>(&CopcQuery::new())?;
+        let read_points = read_buffer.view::<LasPointFormat7>().into_iter().collect();
+        assert_eq!(
+            sorted_by_gps_time(points.clone()),
+            sorted_by_gps_time(read_points)
+        );
+
+        let query_bounds =
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 4.0, 3.0));
+        let query = CopcQuery::new().with_bounds(query_bounds);
+        let queried_points: Vec<LasPointFormat7> = reader
+            .read_query::<VectorBuffer>(&query)?
+            .view::<LasPointFormat7>()
+            .into_iter()
+            .collect();
+        assert!(queried_points.len() < points.len());
+        let expected_points_in_bounds = points
+            .iter()
+            .filter(|point| query_bounds.contains(&point.position.into()))
+            .count();
+        let queried_points_in_bounds = queried_points
+            .iter()
+            .filter(|point| query_bounds.contains(&point.position.into()))
+            .count();
+        assert_eq!(expected_points_in_bounds, queried_points_in_bounds);
+
+        let root_points = reader
+            .read_query::<VectorBuffer>(&CopcQuery::new().with_max_depth(0))?
+            .len();
+        assert!(root_points > 0 && root_points < points.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copc_writer_output_is_valid_laz() -> Result<()> {
+        let points = get_test_points();
+        let copc_data = write_copc_file(&points)?;
+
+        let mut reader = LASReader::from_read(Cursor::new(copc_data), true, false)?;
+        let read_points = reader
+            .read::<VectorBuffer>(points.len())?
+            .view::<LasPointFormat7>()
+            .into_iter()
+            .collect();
+        assert_eq!(sorted_by_gps_time(points), sorted_by_gps_time(read_points));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copc_writer_requires_extended_point_format() -> Result<()> {
+        let mut header_builder = las_rs::Builder::from((1, 4));
+        header_builder.point_format = Format::new(3)?;
+        let header = header_builder.into_header()?;
+        assert!(CopcWriter::from_writer_and_header(Cursor::new(Vec::new()), header).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+mod copc_types;
+pub use self::copc_types::*;
+
+mod copc_reader;
+pub use self::copc_reader::*;
+
+mod copc_writer;
+pub use self::copc_writer::*;
@@ -0,0 +1,233 @@
+use std::{
+    convert::TryInto,
+    io::{Read, Seek},
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::{E57Field, E57FieldEncoding, E57PagedReader, E57Scan};
+
+/// Section ID of the binary section of a compressed vector
+const COMPRESSED_VECTOR_SECTION_ID: u8 = 1;
+/// Size of the header of a compressed vector binary section
+const SECTION_HEADER_SIZE: usize = 32;
+
+const INDEX_PACKET: u8 = 0;
+const DATA_PACKET: u8 = 1;
+const EMPTY_PACKET: u8 = 2;
+
+/// The bytestream of a single field of a compressed vector. Values are bit-packed with the least significant
+/// bits first
+#[derive(Default)]
+struct BitStream {
+    data: Vec<u8>,
+    bit_offset: u64,
+}
+
+impl BitStream {
+    fn available_bits(&self) -> u64 {
+        self.data.len() as u64 * 8 - self.bit_offset
+    }
+
+    fn read_bits(&mut self, bits: u32) -> u64 {
+        let mut value = 0_u64;
+        let mut bits_read = 0;
+        while bits_read < bits {
+            let byte = self.data[(self.bit_offset / 8) as usize];
+            let bit_in_byte = (self.bit_offset % 8) as u32;
+            let bits_from_byte = (8 - bit_in_byte).min(bits - bits_read);
+            let mask = ((1_u16 << bits_from_byte) - 1) as u8;
+            value |= (((byte >> bit_in_byte) & mask) as u64) << bits_read;
+            bits_read += bits_from_byte;
+            self.bit_offset += bits_from_byte as u64;
+        }
+        value
+    }
+
+    /// Removes all bytes that have been read completely
+    fn compact(&mut self) {
+        let consumed_bytes = (self.bit_offset / 8) as usize;
+        self.data.drain(..consumed_bytes);
+        self.bit_offset %= 8;
+    }
+}
+
+/// Decodes the point records of an E57 scan from the binary section of its compressed vector. Only the bit-packed
+/// codec is supported, which is the default codec that all common E57 writers use
+pub(crate) struct CompressedVectorDecoder {
+    fields: Vec<E57Field>,
+    streams: Vec<BitStream>,
+    next_packet_offset: u64,
+    section_end: u64,
+    remaining_records: usize,
+}
+
+impl CompressedVectorDecoder {
+    /// Creates a new decoder for the points of the given `scan`
+    pub(crate) fn new<R: Read + Seek>(
+        reader: &mut E57PagedReader<R>,
+        scan: &E57Scan,
+    ) -> Result<Self> {
+        let section_start = reader.physical_to_logical(scan.file_offset);
+        let mut section_header = [0; SECTION_HEADER_SIZE];
+        reader.read_logical(section_start, &mut section_header)?;
+        if section_header[0] != COMPRESSED_VECTOR_SECTION_ID {
+            bail!(
+                "Invalid section ID {} of E57 compressed vector section",
+                section_header[0]
+            );
+        }
+        let read_u64 = |offset: usize| {
+            u64::from_le_bytes(section_header[offset..offset + 8].try_into().unwrap())
+        };
+        let section_logical_length = read_u64(8);
+        let data_physical_offset = read_u64(16);
+
+        Ok(Self {
+            fields: scan.fields.clone(),
+            streams: scan.fields.iter().map(|_| BitStream::default()).collect(),
+            next_packet_offset: reader.physical_to_logical(data_physical_offset),
+            section_end: section_start + section_logical_length,
+            remaining_records: scan.record_count,
+        })
+    }
+
+    /// Returns the number of records that have not been decoded yet
+    pub(crate) fn remaining_records(&self) -> usize {
+        self.remaining_records
+    }
+
+    /// Reads the next data packet and appends its contents to the bytestreams. Returns `false` if there are no more
+    /// data packets
+    fn read_next_data_packet<R: Read + Seek>(
+        &mut self,
+        reader: &mut E57PagedReader<R>,
+    ) -> Result<bool> {
+        while self.next_packet_offset < self.section_end {
+            let mut packet_header = [0; 4];
+            reader.read_logical(self.next_packet_offset, &mut packet_header)?;
+            let packet_type = packet_header[0];
+            let packet_length =
+                u16::from_le_bytes([packet_header[2], packet_header[3]]) as usize + 1;
+
+            match packet_type {
+                DATA_PACKET => {
+                    let mut packet = vec![0; packet_length];
+                    reader.read_logical(self.next_packet_offset, &mut packet)?;
+                    self.next_packet_offset += packet_length as u64;
+
+                    let stream_count = u16::from_le_bytes([packet[4], packet[5]]) as usize;
+                    if stream_count != self.streams.len() {
+                        bail!("E57 data packet contains {stream_count} bytestreams, but the point prototype has {} fields", self.streams.len());
+                    }
+                    let mut buffer_offset = 6 + 2 * stream_count;
+                    for (idx, stream) in self.streams.iter_mut().enumerate() {
+                        let length_offset = 6 + 2 * idx;
+                        let buffer_length =
+                            u16::from_le_bytes([packet[length_offset], packet[length_offset + 1]])
+                                as usize;
+                        let buffer = packet
+                            .get(buffer_offset..buffer_offset + buffer_length)
+                            .ok_or_else(|| anyhow!("Bytestream exceeds E57 data packet"))?;
+                        stream.data.extend_from_slice(buffer);
+                        buffer_offset += buffer_length;
+                    }
+                    return Ok(true);
+                }
+                INDEX_PACKET | EMPTY_PACKET => {
+                    self.next_packet_offset += packet_length as u64;
+                }
+                other => bail!("Invalid E57 packet type {other}"),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Decodes the next `count` records. The values of the field with index `i` are written to `values[i]`
+    pub(crate) fn read_records<R: Read + Seek>(
+        &mut self,
+        reader: &mut E57PagedReader<R>,
+        count: usize,
+        values: &mut [Vec<f64>],
+    ) -> Result<()> {
+        if count > self.remaining_records {
+            bail!(
+                "Can't read {count} records from E57 compressed vector, only {} records remain",
+                self.remaining_records
+            );
+        }
+
+        // Make sure that all bytestreams contain enough data for `count` records
+        loop {
+            let has_enough_data =
+                self.fields
+                    .iter()
+                    .zip(self.streams.iter())
+                    .all(|(field, stream)| {
+                        stream.available_bits()
+                            >= field.encoding.bits_per_value() as u64 * count as u64
+                    });
+            if has_enough_data {
+                break;
+            }
+            if !self.read_next_data_packet(reader)? {
+                bail!("Unexpected end of E57 compressed vector data");
+            }
+        }
+
+        for ((field, stream), field_values) in self
+            .fields
+            .iter()
+            .zip(self.streams.iter_mut())
+            .zip(values.iter_mut())
+        {
+            field_values.clear();
+            let bits = field.encoding.bits_per_value();
+            field_values.extend((0..count).map(|_| {
+                let raw_value = stream.read_bits(bits);
+                match field.encoding {
+                    E57FieldEncoding::Float {
+                        double_precision: true,
+                    } => f64::from_bits(raw_value),
+                    E57FieldEncoding::Float {
+                        double_precision: false,
+                    } => f32::from_bits(raw_value as u32) as f64,
+                    E57FieldEncoding::Integer { minimum, .. } => {
+                        (minimum as i128 + raw_value as i128) as f64
+                    }
+                    E57FieldEncoding::ScaledInteger {
+                        minimum,
+                        scale,
+                        offset,
+                        ..
+                    } => (minimum as i128 + raw_value as i128) as f64 * scale + offset,
+                }
+            }));
+            stream.compact();
+        }
+        self.remaining_records -= count;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_stream() {
+        // The values 5, 0 and 1023 with 10 bits each, packed with the least significant bits first
+        let mut stream = BitStream {
+            data: vec![0b0000_0101, 0b0000_0000, 0b1111_0000, 0b1111_1111],
+            bit_offset: 0,
+        };
+        assert_eq!(32, stream.available_bits());
+        assert_eq!(5, stream.read_bits(10));
+        assert_eq!(0, stream.read_bits(10));
+        stream.compact();
+        assert_eq!(vec![0b1111_0000, 0b1111_1111], stream.data);
+        assert_eq!(1023, stream.read_bits(10));
+        assert_eq!(2, stream.available_bits());
+        assert_eq!(0, stream.read_bits(0));
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+/// Size of the CRC checksum at the end of each page of an E57 file
+const CHECKSUM_SIZE: u64 = 4;
+
+/// The header at the start of each E57 file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct E57FileHeader {
+    pub major_version: u32,
+    pub minor_version: u32,
+    /// Size of the file in bytes, including the page checksums
+    pub file_physical_length: u64,
+    /// Physical offset of the XML section
+    pub xml_physical_offset: u64,
+    /// Size of the XML section in bytes, excluding the page checksums
+    pub xml_logical_length: u64,
+    /// Size of a page in bytes, including the checksum at the end of the page
+    pub page_size: u64,
+}
+
+impl E57FileHeader {
+    /// The file signature of E57 files
+    pub const SIGNATURE: &'static [u8; 8] = b"ASTM-E57";
+    /// Size of the E57 file header in bytes
+    pub const SIZE: usize = 48;
+    /// Page size used by all E57 files written by libE57
+    pub const DEFAULT_PAGE_SIZE: u64 = 1024;
+
+    /// Reads an `E57FileHeader` from the given `reader`
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self> {
+        let mut signature = [0; 8];
+        reader.read_exact(&mut signature)?;
+        if &signature != Self::SIGNATURE {
+            bail!("Invalid E57 file signature");
+        }
+        let header = Self {
+            major_version: reader.read_u32::<LittleEndian>()?,
+            minor_version: reader.read_u32::<LittleEndian>()?,
+            file_physical_length: reader.read_u64::<LittleEndian>()?,
+            xml_physical_offset: reader.read_u64::<LittleEndian>()?,
+            xml_logical_length: reader.read_u64::<LittleEndian>()?,
+            page_size: reader.read_u64::<LittleEndian>()?,
+        };
+        if header.page_size <= CHECKSUM_SIZE {
+            bail!("Invalid page size {} in E57 header", header.page_size);
+        }
+        Ok(header)
+    }
+
+    /// Writes this `E57FileHeader` to the given `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(Self::SIGNATURE)?;
+        writer.write_u32::<LittleEndian>(self.major_version)?;
+        writer.write_u32::<LittleEndian>(self.minor_version)?;
+        writer.write_u64::<LittleEndian>(self.file_physical_length)?;
+        writer.write_u64::<LittleEndian>(self.xml_physical_offset)?;
+        writer.write_u64::<LittleEndian>(self.xml_logical_length)?;
+        writer.write_u64::<LittleEndian>(self.page_size)?;
+        Ok(())
+    }
+}
+
+/// Reads the logical contents of an E57 file. E57 files are divided into pages that end with a checksum, so the
+/// logical offsets of the data within the file differ from the physical offsets. The checksums are not verified
+pub(crate) struct E57PagedReader<R: Read + Seek> {
+    reader: R,
+    page_size: u64,
+}
+
+impl<R: Read + Seek> E57PagedReader<R> {
+    pub(crate) fn new(reader: R, page_size: u64) -> Self {
+        Self { reader, page_size }
+    }
+
+    fn page_payload_size(&self) -> u64 {
+        self.page_size - CHECKSUM_SIZE
+    }
+
+    /// Converts the physical offset `physical_offset` within the file into a logical offset
+    pub(crate) fn physical_to_logical(&self, physical_offset: u64) -> u64 {
+        let page = physical_offset / self.page_size;
+        let offset_in_page = physical_offset % self.page_size;
+        page * self.page_payload_size() + offset_in_page
+    }
+
+    /// Converts the logical offset `logical_offset` into a physical offset within the file
+    pub(crate) fn logical_to_physical(&self, logical_offset: u64) -> u64 {
+        let page = logical_offset / self.page_payload_size();
+        let offset_in_page = logical_offset % self.page_payload_size();
+        page * self.page_size + offset_in_page
+    }
+
+    /// Reads `buffer.len()` logical bytes starting at `logical_offset`, skipping over the page checksums
+    pub(crate) fn read_logical(&mut self, logical_offset: u64, buffer: &mut [u8]) -> Result<()> {
+        let mut logical_offset = logical_offset;
+        let mut remaining = buffer;
+        while !remaining.is_empty() {
+            let remaining_in_page =
+                self.page_payload_size() - logical_offset % self.page_payload_size();
+            let bytes_to_read = remaining_in_page.min(remaining.len() as u64) as usize;
+            self.reader
+                .seek(SeekFrom::Start(self.logical_to_physical(logical_offset)))?;
+            let (current, rest) = remaining.split_at_mut(bytes_to_read);
+            self.reader
+                .read_exact(current)
+                .context("Unexpected end of E57 file")?;
+            logical_offset += bytes_to_read as u64;
+            remaining = rest;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_e57_header_round_trip() -> Result<()> {
+        let header = E57FileHeader {
+            major_version: 1,
+            minor_version: 0,
+            file_physical_length: 4096,
+            xml_physical_offset: 2048,
+            xml_logical_length: 1500,
+            page_size: E57FileHeader::DEFAULT_PAGE_SIZE,
+        };
+        let mut bytes = vec![];
+        header.write_to(&mut bytes)?;
+        assert_eq!(E57FileHeader::SIZE, bytes.len());
+        assert_eq!(header, E57FileHeader::read_from(Cursor::new(&bytes))?);
+
+        bytes[0] = b'X';
+        assert!(E57FileHeader::read_from(Cursor::new(&bytes)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_e57_paged_reader() -> Result<()> {
+        // Three pages of 8 bytes each, with a 4-byte checksum at the end of each page
+        let physical = (0..24_u8)
+            .map(|idx| if idx % 8 < 4 { idx } else { 0xff })
+            .collect::<Vec<_>>();
+        let mut reader = E57PagedReader::new(Cursor::new(physical), 8);
+        assert_eq!(9, reader.logical_to_physical(5));
+        assert_eq!(5, reader.physical_to_logical(9));
+
+        let mut logical = [0; 7];
+        reader.read_logical(2, &mut logical)?;
+        assert_eq!([2, 3, 8, 9, 10, 11, 16], logical);
+        assert!(reader.read_logical(10, &mut logical).is_err());
+        Ok(())
+    }
+}
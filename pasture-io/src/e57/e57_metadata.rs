@@ -0,0 +1,118 @@
+use std::{any::Any, fmt::Display};
+
+use pasture_core::{math::AABB, meta::Metadata};
+
+use super::{E57FileHeader, E57Scan, E57ScanSelection};
+
+/// `Metadata` implementation for E57 files
+#[derive(Clone, Debug)]
+pub struct E57Metadata {
+    header: E57FileHeader,
+    guid: Option<String>,
+    scans: Vec<E57Scan>,
+    selection: E57ScanSelection,
+}
+
+impl E57Metadata {
+    /// Creates new `E57Metadata` from the given `header`, file `guid` and `scans`. `selection` determines which scans
+    /// the points described by this metadata belong to
+    pub fn new(
+        header: E57FileHeader,
+        guid: Option<String>,
+        scans: Vec<E57Scan>,
+        selection: E57ScanSelection,
+    ) -> Self {
+        Self {
+            header,
+            guid,
+            scans,
+            selection,
+        }
+    }
+
+    /// Returns the header of the E57 file
+    pub fn header(&self) -> &E57FileHeader {
+        &self.header
+    }
+
+    /// Returns the GUID of the E57 file
+    pub fn guid(&self) -> Option<&str> {
+        self.guid.as_deref()
+    }
+
+    /// Returns all scans within the E57 file
+    pub fn scans(&self) -> &[E57Scan] {
+        &self.scans
+    }
+
+    /// Returns the scans that are currently selected
+    pub fn selection(&self) -> E57ScanSelection {
+        self.selection
+    }
+
+    /// Returns the selected scans in the order in which their points are read
+    pub fn selected_scans(&self) -> &[E57Scan] {
+        match self.selection {
+            E57ScanSelection::Single(index) => &self.scans[index..=index],
+            E57ScanSelection::AllInWorldCoordinates => &self.scans,
+        }
+    }
+
+    pub(crate) fn set_selection(&mut self, selection: E57ScanSelection) {
+        self.selection = selection;
+    }
+}
+
+impl Display for E57Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "E57Metadata {{")?;
+        writeln!(
+            f,
+            "\t\"version\": {}.{}",
+            self.header.major_version, self.header.minor_version
+        )?;
+        if let Some(guid) = &self.guid {
+            writeln!(f, "\t\"guid\": {guid}")?;
+        }
+        writeln!(f, "\t\"scans\": [")?;
+        for scan in &self.scans {
+            writeln!(
+                f,
+                "\t\t{{ \"name\": {}, \"points\": {} }}",
+                scan.name.as_deref().unwrap_or("<unnamed>"),
+                scan.record_count
+            )?;
+        }
+        writeln!(f, "\t]")?;
+        writeln!(f, "\t\"selection\": {:?}", self.selection)?;
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl Metadata for E57Metadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        None
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(
+            self.selected_scans()
+                .iter()
+                .map(|scan| scan.record_count)
+                .sum(),
+        )
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "GUID" => self.guid.clone().map(|guid| Box::new(guid) as Box<dyn Any>),
+            "SCAN_COUNT" => Some(Box::new(self.scans.len())),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
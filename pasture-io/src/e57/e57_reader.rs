@@ -0,0 +1,945 @@
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::{
+    containers::{BorrowedMutBuffer, ExternalMemoryBuffer},
+    layout::{
+        attributes::{COLOR_RGB, INTENSITY, POSITION_3D},
+        conversion::BufferLayoutConverter,
+        PointAttributeDefinition, PointLayout,
+    },
+    meta::Metadata,
+    nalgebra::{clamp, Vector3},
+};
+
+use crate::base::{PointReader, SeekToPoint};
+
+use super::{
+    attributes::{COLUMN_INDEX, ROW_INDEX, SPHERICAL_COORDINATES},
+    parse_e57_xml, CompressedVectorDecoder, E57Field, E57FileHeader, E57Metadata, E57PagedReader,
+    E57Pose, E57Scan,
+};
+
+/// Which scans of an E57 file an `E57Reader` reads
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum E57ScanSelection {
+    /// Read only the scan with the given index. Positions are in the local coordinate system of the scan
+    Single(usize),
+    /// Read the points of all scans one after another. Positions are transformed into world coordinates using the
+    /// pose of each scan
+    AllInWorldCoordinates,
+}
+
+/// Indices of the fields of a scan that are converted into pasture attributes
+#[derive(Copy, Clone, Debug, Default)]
+struct ScanFieldIndices {
+    cartesian: Option<[usize; 3]>,
+    spherical: Option<[usize; 3]>,
+    intensity: Option<usize>,
+    color: Option<[usize; 3]>,
+    row_index: Option<usize>,
+    column_index: Option<usize>,
+}
+
+impl ScanFieldIndices {
+    fn new(scan: &E57Scan) -> Self {
+        Self {
+            cartesian: scan.field_indices(["cartesianX", "cartesianY", "cartesianZ"]),
+            spherical: scan.field_indices([
+                "sphericalRange",
+                "sphericalAzimuth",
+                "sphericalElevation",
+            ]),
+            intensity: scan.field_index("intensity"),
+            color: scan.field_indices(["colorRed", "colorGreen", "colorBlue"]),
+            row_index: scan.field_index("rowIndex"),
+            column_index: scan.field_index("columnIndex"),
+        }
+    }
+}
+
+/// Returns the range of values that is mapped to the full range of an `u16` value. Uses the explicit `limits`
+/// from the XML section if possible, then the range of the field encoding and `default` as a last resort
+fn value_limits(limits: Option<(f64, f64)>, field: &E57Field, default: (f64, f64)) -> (f64, f64) {
+    limits
+        .into_iter()
+        .chain(field.encoding.value_range())
+        .find(|(min, max)| max > min)
+        .unwrap_or(default)
+}
+
+fn normalize_to_u16(value: f64, (min, max): (f64, f64)) -> u16 {
+    let normalized = clamp((value - min) / (max - min), 0.0, 1.0);
+    (normalized * u16::MAX as f64).round() as u16
+}
+
+fn write_f64s(point: &mut [u8], offset: usize, values: &[f64]) {
+    for (idx, value) in values.iter().enumerate() {
+        let start = offset + idx * 8;
+        point[start..start + 8].copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+fn write_u16s(point: &mut [u8], offset: usize, values: &[u16]) {
+    for (idx, value) in values.iter().enumerate() {
+        let start = offset + idx * 2;
+        point[start..start + 2].copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+/// The scan that is currently being decoded
+struct CurrentScan {
+    decoder: CompressedVectorDecoder,
+    fields: ScanFieldIndices,
+    pose: E57Pose,
+    intensity_limits: (f64, f64),
+    color_limits: [(f64, f64); 3],
+}
+
+impl CurrentScan {
+    fn new<R: Read + Seek>(reader: &mut E57PagedReader<R>, scan: &E57Scan) -> Result<Self> {
+        let fields = ScanFieldIndices::new(scan);
+        let intensity_limits = fields
+            .intensity
+            .map(|idx| value_limits(scan.intensity_limits, &scan.fields[idx], (0.0, 1.0)))
+            .unwrap_or((0.0, 1.0));
+        let mut color_limits = [(0.0, 255.0); 3];
+        if let Some(color_fields) = fields.color {
+            for (channel, limits) in color_limits.iter_mut().enumerate() {
+                *limits = value_limits(
+                    scan.color_limits.map(|limits| limits[channel]),
+                    &scan.fields[color_fields[channel]],
+                    (0.0, 255.0),
+                );
+            }
+        }
+        Ok(Self {
+            decoder: CompressedVectorDecoder::new(reader, scan)?,
+            fields,
+            pose: scan.pose,
+            intensity_limits,
+            color_limits,
+        })
+    }
+}
+
+/// Offsets of the attributes within a point of the default `PointLayout` of an `E57Reader`
+#[derive(Copy, Clone, Debug)]
+struct AttributeOffsets {
+    position: usize,
+    spherical: Option<usize>,
+    intensity: Option<usize>,
+    color: Option<usize>,
+    row_index: Option<usize>,
+    column_index: Option<usize>,
+}
+
+impl AttributeOffsets {
+    fn new(layout: &PointLayout) -> Self {
+        let offset = |attribute: &PointAttributeDefinition| {
+            layout
+                .get_attribute(attribute)
+                .map(|member| member.offset() as usize)
+        };
+        Self {
+            position: offset(&POSITION_3D).expect("PointLayout has no POSITION_3D attribute"),
+            spherical: offset(&SPHERICAL_COORDINATES),
+            intensity: offset(&INTENSITY),
+            color: offset(&COLOR_RGB),
+            row_index: offset(&ROW_INDEX),
+            column_index: offset(&COLUMN_INDEX),
+        }
+    }
+}
+
+/// Returns the default `PointLayout` for reading the given `scans`
+fn default_layout(scans: &[E57Scan], selection: E57ScanSelection) -> PointLayout {
+    let fields = scans.iter().map(ScanFieldIndices::new).collect::<Vec<_>>();
+    let mut attributes = vec![POSITION_3D];
+    // Spherical coordinates are relative to the scanner, so they are only meaningful for a single scan
+    if selection != E57ScanSelection::AllInWorldCoordinates
+        && fields.iter().any(|fields| fields.spherical.is_some())
+    {
+        attributes.push(SPHERICAL_COORDINATES);
+    }
+    if fields.iter().any(|fields| fields.intensity.is_some()) {
+        attributes.push(INTENSITY);
+    }
+    if fields.iter().any(|fields| fields.color.is_some()) {
+        attributes.push(COLOR_RGB);
+    }
+    if fields.iter().any(|fields| fields.row_index.is_some()) {
+        attributes.push(ROW_INDEX);
+    }
+    if fields.iter().any(|fields| fields.column_index.is_some()) {
+        attributes.push(COLUMN_INDEX);
+    }
+    PointLayout::from_attributes_packed(&attributes, 1)
+}
+
+/// `PointReader` implementation for ASTM E57 files. Parses the XML section of the file and decodes the points of
+/// each scan from the binary sections of the `CompressedVector`s. Only the default bit-packed codec is supported and
+/// page checksums are not verified.
+///
+/// By default, the points of all scans are read one after another and transformed into world coordinates using the
+/// pose of each scan. Use [`E57Reader::select_scans`] to read a single scan in its local coordinate system instead.
+/// The pose of each scan is available through [`E57Reader::scans`].
+///
+/// The default `PointLayout` contains the following attributes, depending on the fields of the selected scans:
+/// - `POSITION_3D` from the cartesian coordinates, or computed from the spherical coordinates if a scan has no
+///   cartesian coordinates
+/// - [`SPHERICAL_COORDINATES`] as (range, azimuth, elevation), only when reading a single scan
+/// - `INTENSITY`, normalized from the `intensityLimits` of the scan to the full `u16` range
+/// - `COLOR_RGB`, normalized from the `colorLimits` of the scan to the full `u16` range
+/// - [`ROW_INDEX`] and [`COLUMN_INDEX`]
+///
+/// Attributes that are missing in some of the selected scans are zero for the points of these scans.
+pub struct E57Reader<R: Read + Seek> {
+    reader: E57PagedReader<R>,
+    metadata: E57Metadata,
+    layout: PointLayout,
+    current_scan: Option<CurrentScan>,
+    /// Index of the current scan within the selected scans
+    current_scan_index: usize,
+    current_point_index: usize,
+}
+
+impl<R: Read + Seek> E57Reader<R> {
+    /// Creates a new `E57Reader` from the given `read`, which reads the points of all scans in world coordinates
+    ///
+    /// # Errors
+    ///
+    /// If `read` does not contain a valid E57 file, an error is returned
+    pub fn from_read(mut read: R) -> Result<Self> {
+        read.seek(SeekFrom::Start(0))?;
+        let header = E57FileHeader::read_from(&mut read).context("Could not read E57 header")?;
+        let mut reader = E57PagedReader::new(read, header.page_size);
+
+        let mut xml = vec![0; header.xml_logical_length.try_into()?];
+        reader
+            .read_logical(
+                reader.physical_to_logical(header.xml_physical_offset),
+                &mut xml,
+            )
+            .context("Could not read XML section of E57 file")?;
+        let xml = String::from_utf8(xml).context("XML section of E57 file is no valid UTF-8")?;
+        let (guid, scans) = parse_e57_xml(&xml)?;
+
+        let selection = E57ScanSelection::AllInWorldCoordinates;
+        let layout = default_layout(&scans, selection);
+        Ok(Self {
+            reader,
+            metadata: E57Metadata::new(header, guid, scans, selection),
+            layout,
+            current_scan: None,
+            current_scan_index: 0,
+            current_point_index: 0,
+        })
+    }
+
+    /// Returns the `E57Metadata` of the associated E57 file
+    pub fn e57_metadata(&self) -> &E57Metadata {
+        &self.metadata
+    }
+
+    /// Returns all scans within the associated E57 file
+    pub fn scans(&self) -> &[E57Scan] {
+        self.metadata.scans()
+    }
+
+    /// Selects which scans this reader reads. This changes the default `PointLayout` and moves the reader to the
+    /// first point of the selected scans
+    ///
+    /// # Errors
+    ///
+    /// If `selection` refers to a scan that does not exist, an error is returned
+    pub fn select_scans(&mut self, selection: E57ScanSelection) -> Result<()> {
+        if let E57ScanSelection::Single(index) = selection {
+            if index >= self.scans().len() {
+                bail!(
+                    "Scan index {index} is out of bounds, E57 file has only {} scans",
+                    self.scans().len()
+                );
+            }
+        }
+        self.metadata.set_selection(selection);
+        self.layout = default_layout(self.metadata.selected_scans(), selection);
+        self.current_scan = None;
+        self.current_scan_index = 0;
+        self.current_point_index = 0;
+        Ok(())
+    }
+
+    fn selected_point_count(&self) -> usize {
+        self.metadata.number_of_points().unwrap_or(0)
+    }
+
+    /// Decodes the next `point_data.len() / size_of_point` points into `point_data` using the default `PointLayout`
+    fn decode_points(&mut self, point_data: &mut [u8]) -> Result<()> {
+        let size_of_point = self.layout.size_of_point_entry() as usize;
+        let offsets = AttributeOffsets::new(&self.layout);
+        let to_world_coordinates =
+            self.metadata.selection() == E57ScanSelection::AllInWorldCoordinates;
+        let mut points = point_data.chunks_exact_mut(size_of_point);
+        let mut remaining_points = points.len();
+        let mut values = vec![];
+
+        while remaining_points > 0 {
+            // Move on to the next scan with points if the current scan has no more points
+            loop {
+                match &self.current_scan {
+                    Some(scan) if scan.decoder.remaining_records() > 0 => break,
+                    Some(_) => {
+                        self.current_scan = None;
+                        self.current_scan_index += 1;
+                    }
+                    None => {
+                        let scan = self
+                            .metadata
+                            .selected_scans()
+                            .get(self.current_scan_index)
+                            .context("No more points in E57 file")?;
+                        self.current_scan = Some(CurrentScan::new(&mut self.reader, scan)?);
+                    }
+                }
+            }
+            let scan = self.current_scan.as_mut().unwrap();
+
+            let count = remaining_points.min(scan.decoder.remaining_records());
+            values.resize_with(
+                self.metadata.selected_scans()[self.current_scan_index]
+                    .fields
+                    .len(),
+                Vec::new,
+            );
+            scan.decoder
+                .read_records(&mut self.reader, count, &mut values)?;
+
+            let fields = scan.fields;
+            for (record, point) in (&mut points).take(count).enumerate() {
+                point.fill(0);
+                let value = |field: usize| values[field][record];
+
+                let mut position = match (fields.cartesian, fields.spherical) {
+                    (Some([x, y, z]), _) => Vector3::new(value(x), value(y), value(z)),
+                    (None, Some([range, azimuth, elevation])) => {
+                        let (range, azimuth, elevation) =
+                            (value(range), value(azimuth), value(elevation));
+                        Vector3::new(
+                            range * elevation.cos() * azimuth.cos(),
+                            range * elevation.cos() * azimuth.sin(),
+                            range * elevation.sin(),
+                        )
+                    }
+                    (None, None) => Vector3::zeros(),
+                };
+                if to_world_coordinates {
+                    position = scan.pose.transform_point(&position);
+                }
+                write_f64s(point, offsets.position, position.as_slice());
+
+                if let (Some(offset), Some(spherical)) = (offsets.spherical, fields.spherical) {
+                    write_f64s(point, offset, &spherical.map(value));
+                }
+                if let (Some(offset), Some(intensity)) = (offsets.intensity, fields.intensity) {
+                    let intensity = normalize_to_u16(value(intensity), scan.intensity_limits);
+                    write_u16s(point, offset, &[intensity]);
+                }
+                if let (Some(offset), Some(color)) = (offsets.color, fields.color) {
+                    let mut rgb = [0; 3];
+                    for (channel, channel_value) in rgb.iter_mut().enumerate() {
+                        *channel_value =
+                            normalize_to_u16(value(color[channel]), scan.color_limits[channel]);
+                    }
+                    write_u16s(point, offset, &rgb);
+                }
+                if let (Some(offset), Some(row_index)) = (offsets.row_index, fields.row_index) {
+                    point[offset..offset + 4]
+                        .copy_from_slice(&(value(row_index) as i32).to_ne_bytes());
+                }
+                if let (Some(offset), Some(column_index)) =
+                    (offsets.column_index, fields.column_index)
+                {
+                    point[offset..offset + 4]
+                        .copy_from_slice(&(value(column_index) as i32).to_ne_bytes());
+                }
+            }
+
+            remaining_points -= count;
+            self.current_point_index += count;
+        }
+        Ok(())
+    }
+
+    /// Skips the next `count` points
+    fn skip_points(&mut self, count: usize) -> Result<()> {
+        const CHUNK_SIZE: usize = 1 << 14;
+        let size_of_point = self.layout.size_of_point_entry() as usize;
+        let mut chunk = vec![0; CHUNK_SIZE.min(count) * size_of_point];
+        let mut remaining = count;
+        while remaining > 0 {
+            let points_in_chunk = remaining.min(CHUNK_SIZE);
+            self.decode_points(&mut chunk[..points_in_chunk * size_of_point])?;
+            remaining -= points_in_chunk;
+        }
+        Ok(())
+    }
+}
+
+impl E57Reader<BufReader<File>> {
+    /// Creates a new `E57Reader` by opening the file at the given `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_read(reader)
+    }
+}
+
+impl<R: Read + Seek> PointReader for E57Reader<R> {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let num_points_to_read = usize::min(
+            count,
+            self.selected_point_count() - self.current_point_index,
+        );
+        if num_points_to_read == 0 {
+            return Ok(0);
+        }
+
+        let source_layout = self.layout.clone();
+        let target_layout = point_buffer.point_layout().clone();
+        let converter = if source_layout == target_layout {
+            None
+        } else {
+            Some(BufferLayoutConverter::for_layouts_with_default(
+                &source_layout,
+                &target_layout,
+            ))
+        };
+
+        // Decode points in chunks of ~1MiB size so that we don't have to allocate a second buffer for all points
+        const CHUNK_MEM_SIZE: usize = 1 << 20;
+        let size_of_point = source_layout.size_of_point_entry() as usize;
+        let num_points_per_chunk = usize::max(1, CHUNK_MEM_SIZE / size_of_point);
+        let mut chunk = vec![0; num_points_per_chunk.min(num_points_to_read) * size_of_point];
+        let mut first_point_in_chunk = 0;
+        while first_point_in_chunk < num_points_to_read {
+            let points_in_chunk =
+                num_points_per_chunk.min(num_points_to_read - first_point_in_chunk);
+            let chunk_bytes = &mut chunk[..points_in_chunk * size_of_point];
+            self.decode_points(chunk_bytes)?;
+
+            let target_range = first_point_in_chunk..(first_point_in_chunk + points_in_chunk);
+            match &converter {
+                Some(converter) => {
+                    let chunk_buffer =
+                        ExternalMemoryBuffer::new(&chunk_bytes[..], source_layout.clone());
+                    converter.convert_into_range(
+                        &chunk_buffer,
+                        0..points_in_chunk,
+                        point_buffer,
+                        target_range,
+                    );
+                }
+                // Safe because the buffer has the same `PointLayout` as the chunk
+                None => unsafe {
+                    point_buffer.set_point_range(target_range, chunk_bytes);
+                },
+            }
+            first_point_in_chunk += points_in_chunk;
+        }
+
+        Ok(num_points_to_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+impl<R: Read + Seek> SeekToPoint for E57Reader<R> {
+    /// Seeks to the given point. E57 files have no random access to individual points, so seeking decodes all
+    /// points between the start of the scan that contains the new point and the new point
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        let point_count = self.selected_point_count();
+        let new_point_idx: i64 = match position {
+            SeekFrom::Start(offset) => offset.try_into()?,
+            SeekFrom::End(offset) => point_count as i64 + offset,
+            SeekFrom::Current(offset) => self.current_point_index as i64 + offset,
+        };
+        let new_point_idx = clamp(new_point_idx, 0, point_count as i64) as usize;
+
+        if new_point_idx < self.current_point_index {
+            // Restart at the scan that contains the new point
+            let mut scan_start = 0;
+            let mut scan_index = 0;
+            for scan in self.metadata.selected_scans() {
+                if scan_start + scan.record_count > new_point_idx {
+                    break;
+                }
+                scan_start += scan.record_count;
+                scan_index += 1;
+            }
+            self.current_scan = None;
+            self.current_scan_index = scan_index;
+            self.current_point_index = scan_start;
+        }
+        self.skip_points(new_point_idx - self.current_point_index)?;
+        Ok(self.current_point_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f64::consts::FRAC_PI_2, io::Cursor};
+
+    use pasture_core::containers::{
+        BorrowedBuffer, HashMapBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+    };
+
+    use super::*;
+    use crate::e57::E57FieldEncoding;
+
+    const PAGE_SIZE: usize = 1024;
+    const PAGE_PAYLOAD_SIZE: usize = PAGE_SIZE - 4;
+
+    fn logical_to_physical(logical_offset: usize) -> u64 {
+        (logical_offset / PAGE_PAYLOAD_SIZE * PAGE_SIZE + logical_offset % PAGE_PAYLOAD_SIZE) as u64
+    }
+
+    struct TestScan {
+        name: &'static str,
+        pose: E57Pose,
+        fields: Vec<E57Field>,
+        /// The values of each field
+        values: Vec<Vec<f64>>,
+    }
+
+    fn field(name: &str, encoding: E57FieldEncoding) -> E57Field {
+        E57Field {
+            name: name.to_owned(),
+            encoding,
+        }
+    }
+
+    fn field_xml(field: &E57Field) -> String {
+        match field.encoding {
+            E57FieldEncoding::Float { double_precision } => format!(
+                r#"<{} type="Float" precision="{}"/>"#,
+                field.name,
+                if double_precision { "double" } else { "single" }
+            ),
+            E57FieldEncoding::Integer { minimum, maximum } => format!(
+                r#"<{} type="Integer" minimum="{minimum}" maximum="{maximum}"/>"#,
+                field.name
+            ),
+            E57FieldEncoding::ScaledInteger {
+                minimum,
+                maximum,
+                scale,
+                offset,
+            } => format!(
+                r#"<{} type="ScaledInteger" minimum="{minimum}" maximum="{maximum}" scale="{scale}" offset="{offset}"/>"#,
+                field.name
+            ),
+        }
+    }
+
+    /// Bit-packs the `values` of the given `field`
+    fn encode_field(field: &E57Field, values: &[f64]) -> Vec<u8> {
+        let bits = field.encoding.bits_per_value();
+        let mut bytes = vec![];
+        let mut bit_offset = 0;
+        for value in values {
+            let raw_value = match field.encoding {
+                E57FieldEncoding::Float {
+                    double_precision: true,
+                } => value.to_bits(),
+                E57FieldEncoding::Float {
+                    double_precision: false,
+                } => (*value as f32).to_bits() as u64,
+                E57FieldEncoding::Integer { minimum, .. } => (*value as i64 - minimum) as u64,
+                E57FieldEncoding::ScaledInteger {
+                    minimum,
+                    scale,
+                    offset,
+                    ..
+                } => (((value - offset) / scale).round() as i64 - minimum) as u64,
+            };
+            for bit in 0..bits {
+                if bit_offset % 8 == 0 {
+                    bytes.push(0);
+                }
+                let bit_value = ((raw_value >> bit) & 1) as u8;
+                *bytes.last_mut().unwrap() |= bit_value << (bit_offset % 8);
+                bit_offset += 1;
+            }
+        }
+        bytes
+    }
+
+    fn data_packet(buffers: &[&[u8]]) -> Vec<u8> {
+        let mut packet = vec![1, 0, 0, 0];
+        packet.extend((buffers.len() as u16).to_le_bytes());
+        for buffer in buffers {
+            packet.extend((buffer.len() as u16).to_le_bytes());
+        }
+        for buffer in buffers {
+            packet.extend_from_slice(buffer);
+        }
+        packet.resize(packet.len().div_ceil(4) * 4, 0);
+        let length_minus_one = (packet.len() - 1) as u16;
+        packet[2..4].copy_from_slice(&length_minus_one.to_le_bytes());
+        packet
+    }
+
+    /// Writes an E57 file with the given scans. The data of each scan is split into two data packets with an empty
+    /// packet in between, so that values are split across packets
+    fn write_test_e57(scans: &[TestScan]) -> Vec<u8> {
+        let mut logical = vec![0; E57FileHeader::SIZE];
+        let mut scans_xml = String::new();
+        for scan in scans {
+            let section_start = logical.len();
+            let streams = scan
+                .fields
+                .iter()
+                .zip(scan.values.iter())
+                .map(|(field, values)| encode_field(field, values))
+                .collect::<Vec<_>>();
+            let first_halves = streams
+                .iter()
+                .map(|stream| &stream[..stream.len() / 2])
+                .collect::<Vec<_>>();
+            let second_halves = streams
+                .iter()
+                .map(|stream| &stream[stream.len() / 2..])
+                .collect::<Vec<_>>();
+            let mut packets = data_packet(&first_halves);
+            packets.extend([2, 0, 3, 0]);
+            packets.extend(data_packet(&second_halves));
+
+            logical.push(1);
+            logical.extend([0; 7]);
+            logical.extend((32 + packets.len() as u64).to_le_bytes());
+            logical.extend(logical_to_physical(section_start + 32).to_le_bytes());
+            logical.extend(0_u64.to_le_bytes());
+            logical.extend(packets);
+
+            let rotation = scan.pose.rotation.quaternion();
+            let translation = scan.pose.translation;
+            let prototype = scan.fields.iter().map(field_xml).collect::<String>();
+            scans_xml += &format!(
+                r#"<vectorChild type="Structure">
+    <name type="String"><![CDATA[{}]]></name>
+    <pose type="Structure">
+        <rotation type="Structure"><w type="Float">{}</w><x type="Float">{}</x><y type="Float">{}</y><z type="Float">{}</z></rotation>
+        <translation type="Structure"><x type="Float">{}</x><y type="Float">{}</y><z type="Float">{}</z></translation>
+    </pose>
+    <points type="CompressedVector" fileOffset="{}" recordCount="{}">
+        <prototype type="Structure">{}</prototype>
+        <codecs type="Vector" allowHeterogeneousChildren="1"/>
+    </points>
+</vectorChild>"#,
+                scan.name,
+                rotation.w,
+                rotation.i,
+                rotation.j,
+                rotation.k,
+                translation.x,
+                translation.y,
+                translation.z,
+                logical_to_physical(section_start),
+                scan.values[0].len(),
+                prototype
+            );
+        }
+
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<e57Root type="Structure" xmlns="http://www.astm.org/COMMIT/E57/2010-e57-v1.0">
+<guid type="String"><![CDATA[{{test-file}}]]></guid>
+<data3D type="Vector" allowHeterogeneousChildren="1">{scans_xml}</data3D>
+</e57Root>"#
+        );
+        let xml_offset = logical.len();
+        logical.extend(xml.as_bytes());
+
+        let page_count = logical.len().div_ceil(PAGE_PAYLOAD_SIZE);
+        let header = E57FileHeader {
+            major_version: 1,
+            minor_version: 0,
+            file_physical_length: (page_count * PAGE_SIZE) as u64,
+            xml_physical_offset: logical_to_physical(xml_offset),
+            xml_logical_length: xml.len() as u64,
+            page_size: PAGE_SIZE as u64,
+        };
+        header
+            .write_to(&mut logical[..E57FileHeader::SIZE])
+            .unwrap();
+
+        // Checksums are not verified, so we just fill them with zeros
+        let mut physical = vec![];
+        for page in logical.chunks(PAGE_PAYLOAD_SIZE) {
+            let page_start = physical.len();
+            physical.extend_from_slice(page);
+            physical.resize(page_start + PAGE_SIZE, 0);
+        }
+        physical
+    }
+
+    const SCAN_A_POINTS: usize = 50;
+    const SCAN_B_POINTS: usize = 30;
+
+    fn scan_b_pose() -> E57Pose {
+        E57Pose {
+            rotation: pasture_core::nalgebra::UnitQuaternion::from_axis_angle(
+                &Vector3::z_axis(),
+                FRAC_PI_2,
+            ),
+            translation: Vector3::new(10.0, 0.0, 0.0),
+        }
+    }
+
+    /// Spherical coordinates of the points in scan B, after rounding to the precision of the fields
+    fn scan_b_spherical(idx: usize) -> Vector3<f64> {
+        Vector3::new(
+            1.0 + idx as f64 * 0.5,
+            (idx as f32 * 0.1) as f64,
+            (idx as f32 * -0.01) as f64,
+        )
+    }
+
+    fn scan_b_local_position(idx: usize) -> Vector3<f64> {
+        let spherical = scan_b_spherical(idx);
+        Vector3::new(
+            spherical.x * spherical.z.cos() * spherical.y.cos(),
+            spherical.x * spherical.z.cos() * spherical.y.sin(),
+            spherical.x * spherical.z.sin(),
+        )
+    }
+
+    fn test_file() -> Vec<u8> {
+        let integer = |minimum, maximum| E57FieldEncoding::Integer { minimum, maximum };
+        let double = E57FieldEncoding::Float {
+            double_precision: true,
+        };
+        let single = E57FieldEncoding::Float {
+            double_precision: false,
+        };
+        let scan_a = TestScan {
+            name: "Scan A",
+            pose: E57Pose::identity(),
+            fields: vec![
+                field("cartesianX", double),
+                field("cartesianY", double),
+                field("cartesianZ", double),
+                field("intensity", integer(0, 4095)),
+                field("colorRed", integer(0, 255)),
+                field("colorGreen", integer(0, 255)),
+                field("colorBlue", integer(0, 255)),
+                field("rowIndex", integer(0, 4)),
+                field("columnIndex", integer(0, 9)),
+            ],
+            values: vec![
+                (0..SCAN_A_POINTS).map(|idx| idx as f64).collect(),
+                (0..SCAN_A_POINTS).map(|idx| idx as f64 * 2.0).collect(),
+                (0..SCAN_A_POINTS).map(|idx| idx as f64 * -0.5).collect(),
+                (0..SCAN_A_POINTS).map(|idx| (idx * 80) as f64).collect(),
+                (0..SCAN_A_POINTS).map(|idx| idx as f64).collect(),
+                (0..SCAN_A_POINTS).map(|idx| (255 - idx) as f64).collect(),
+                vec![7.0; SCAN_A_POINTS],
+                (0..SCAN_A_POINTS).map(|idx| (idx % 5) as f64).collect(),
+                (0..SCAN_A_POINTS).map(|idx| (idx / 5) as f64).collect(),
+            ],
+        };
+        let scan_b = TestScan {
+            name: "Scan B",
+            pose: scan_b_pose(),
+            fields: vec![
+                field(
+                    "sphericalRange",
+                    E57FieldEncoding::ScaledInteger {
+                        minimum: 0,
+                        maximum: 100_000,
+                        scale: 0.001,
+                        offset: 0.0,
+                    },
+                ),
+                field("sphericalAzimuth", single),
+                field("sphericalElevation", single),
+            ],
+            values: vec![
+                (0..SCAN_B_POINTS)
+                    .map(|idx| scan_b_spherical(idx).x)
+                    .collect(),
+                (0..SCAN_B_POINTS)
+                    .map(|idx| scan_b_spherical(idx).y)
+                    .collect(),
+                (0..SCAN_B_POINTS)
+                    .map(|idx| scan_b_spherical(idx).z)
+                    .collect(),
+            ],
+        };
+        write_test_e57(&[scan_a, scan_b])
+    }
+
+    fn assert_positions_eq(expected: Vector3<f64>, actual: Vector3<f64>) {
+        assert!(
+            (expected - actual).norm() < 1e-6,
+            "Expected position {} but got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_e57_scans_and_layout() -> Result<()> {
+        let mut reader = E57Reader::from_read(Cursor::new(test_file()))?;
+        assert_eq!(Some("{test-file}"), reader.e57_metadata().guid());
+        let names = reader
+            .scans()
+            .iter()
+            .map(|scan| scan.name.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["Scan A", "Scan B"], names);
+        assert!((reader.scans()[1].pose.translation - Vector3::new(10.0, 0.0, 0.0)).norm() < 1e-9);
+
+        assert_eq!(
+            Some(SCAN_A_POINTS + SCAN_B_POINTS),
+            reader.get_metadata().number_of_points()
+        );
+        let expected_layout = PointLayout::from_attributes_packed(
+            &[POSITION_3D, INTENSITY, COLOR_RGB, ROW_INDEX, COLUMN_INDEX],
+            1,
+        );
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+
+        reader.select_scans(E57ScanSelection::Single(1))?;
+        assert_eq!(
+            Some(SCAN_B_POINTS),
+            reader.get_metadata().number_of_points()
+        );
+        let expected_layout =
+            PointLayout::from_attributes_packed(&[POSITION_3D, SPHERICAL_COORDINATES], 1);
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+
+        assert!(reader.select_scans(E57ScanSelection::Single(2)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_single_e57_scan() -> Result<()> {
+        let mut reader = E57Reader::from_read(Cursor::new(test_file()))?;
+        reader.select_scans(E57ScanSelection::Single(0))?;
+        let points = reader.read::<HashMapBuffer>(100)?;
+        assert_eq!(SCAN_A_POINTS, points.len());
+
+        for (idx, position) in points
+            .view_attribute::<Vector3<f64>>(&POSITION_3D)
+            .into_iter()
+            .enumerate()
+        {
+            let idx = idx as f64;
+            assert_eq!(Vector3::new(idx, idx * 2.0, idx * -0.5), position);
+        }
+        let intensity = points.view_attribute::<u16>(&INTENSITY).at(10);
+        assert_eq!(normalize_to_u16(800.0, (0.0, 4095.0)), intensity);
+        let color = points.view_attribute::<Vector3<u16>>(&COLOR_RGB).at(10);
+        assert_eq!(Vector3::new(10 * 257, 245 * 257, 7 * 257), color);
+        assert_eq!(2, points.view_attribute::<i32>(&ROW_INDEX).at(12));
+        assert_eq!(9, points.view_attribute::<i32>(&COLUMN_INDEX).at(49));
+
+        // Scan B is read in its local coordinate system
+        reader.select_scans(E57ScanSelection::Single(1))?;
+        let points = reader.read::<VectorBuffer>(100)?;
+        assert_eq!(SCAN_B_POINTS, points.len());
+        for idx in 0..SCAN_B_POINTS {
+            assert_positions_eq(
+                scan_b_local_position(idx),
+                points.view_attribute(&POSITION_3D).at(idx),
+            );
+            assert_positions_eq(
+                scan_b_spherical(idx),
+                points.view_attribute(&SPHERICAL_COORDINATES).at(idx),
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_merged_e57_scans() -> Result<()> {
+        let mut reader = E57Reader::from_read(Cursor::new(test_file()))?;
+        let points = reader.read::<VectorBuffer>(100)?;
+        assert_eq!(SCAN_A_POINTS + SCAN_B_POINTS, points.len());
+
+        let positions = points
+            .view_attribute::<Vector3<f64>>(&POSITION_3D)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(Vector3::new(3.0, 6.0, -1.5), positions[3]);
+        for idx in 0..SCAN_B_POINTS {
+            assert_positions_eq(
+                scan_b_pose().transform_point(&scan_b_local_position(idx)),
+                positions[SCAN_A_POINTS + idx],
+            );
+        }
+        // Scan B has no intensities
+        assert_eq!(
+            0,
+            points
+                .view_attribute::<u16>(&INTENSITY)
+                .at(SCAN_A_POINTS + 1)
+        );
+
+        // Seeking backwards and forwards, across the boundary between the two scans
+        reader.seek_point(SeekFrom::Start(45))?;
+        let mut chunk = VectorBuffer::new_from_layout(reader.get_default_point_layout().clone());
+        chunk.resize(10);
+        assert_eq!(10, reader.read_into(&mut chunk, 10)?);
+        for idx in 0..10 {
+            assert_eq!(
+                positions[45 + idx],
+                chunk.view_attribute::<Vector3<f64>>(&POSITION_3D).at(idx)
+            );
+        }
+        assert_eq!(60, reader.seek_point(SeekFrom::Current(5))?);
+        assert_eq!(
+            positions[60],
+            reader
+                .read::<VectorBuffer>(1)?
+                .view_attribute(&POSITION_3D)
+                .at(0)
+        );
+        assert_eq!(80, reader.seek_point(SeekFrom::End(10))?);
+        assert_eq!(0, reader.read::<VectorBuffer>(1)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_e57_generic_reader() -> Result<()> {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_e57_generic_reader.e57");
+        std::fs::write(&path, test_file())?;
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        let points = crate::base::read_all::<VectorBuffer, _>(&path)?;
+        assert_eq!(SCAN_A_POINTS + SCAN_B_POINTS, points.len());
+        Ok(())
+    }
+}
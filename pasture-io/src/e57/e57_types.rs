@@ -0,0 +1,419 @@
+use anyhow::{anyhow, bail, Context, Result};
+use pasture_core::nalgebra::{Quaternion, UnitQuaternion, Vector3};
+use roxmltree::{Document, Node};
+
+pub mod attributes {
+    use std::borrow::Cow;
+
+    use pasture_core::layout::{PointAttributeDataType, PointAttributeDefinition};
+
+    /// Attribute definition for spherical coordinates in the local coordinate system of an E57 scan, stored as
+    /// (range, azimuth, elevation) with angles in radians
+    pub const SPHERICAL_COORDINATES: PointAttributeDefinition = PointAttributeDefinition::custom(
+        Cow::Borrowed("SphericalCoordinates"),
+        PointAttributeDataType::Vec3f64,
+    );
+    /// Attribute definition for the row index of a point within the grid of an E57 scan
+    pub const ROW_INDEX: PointAttributeDefinition =
+        PointAttributeDefinition::custom(Cow::Borrowed("RowIndex"), PointAttributeDataType::I32);
+    /// Attribute definition for the column index of a point within the grid of an E57 scan
+    pub const COLUMN_INDEX: PointAttributeDefinition =
+        PointAttributeDefinition::custom(Cow::Borrowed("ColumnIndex"), PointAttributeDataType::I32);
+}
+
+/// The pose of an E57 scan, which transforms points from the local coordinate system of the scan into world coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct E57Pose {
+    pub rotation: UnitQuaternion<f64>,
+    pub translation: Vector3<f64>,
+}
+
+impl E57Pose {
+    /// Returns the identity pose
+    pub fn identity() -> Self {
+        Self {
+            rotation: UnitQuaternion::identity(),
+            translation: Vector3::zeros(),
+        }
+    }
+
+    /// Transforms the given `point` from the local coordinate system of the scan into world coordinates
+    pub fn transform_point(&self, point: &Vector3<f64>) -> Vector3<f64> {
+        self.rotation * point + self.translation
+    }
+}
+
+impl Default for E57Pose {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// How the values of a field of an E57 scan are encoded
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum E57FieldEncoding {
+    /// Floating point values with either single or double precision
+    Float { double_precision: bool },
+    /// Integer values within the range `[minimum, maximum]`
+    Integer { minimum: i64, maximum: i64 },
+    /// Integer values within the range `[minimum, maximum]` that are converted to floating point values using
+    /// `value * scale + offset`
+    ScaledInteger {
+        minimum: i64,
+        maximum: i64,
+        scale: f64,
+        offset: f64,
+    },
+}
+
+impl E57FieldEncoding {
+    /// Returns the number of bits that a single value with this encoding takes up in a compressed vector
+    pub fn bits_per_value(&self) -> u32 {
+        match self {
+            E57FieldEncoding::Float { double_precision } => {
+                if *double_precision {
+                    64
+                } else {
+                    32
+                }
+            }
+            E57FieldEncoding::Integer { minimum, maximum }
+            | E57FieldEncoding::ScaledInteger {
+                minimum, maximum, ..
+            } => {
+                let range = (*maximum as i128 - *minimum as i128) as u128;
+                128 - range.leading_zeros()
+            }
+        }
+    }
+
+    /// Returns the range of values that this encoding can represent, or `None` for floating point values
+    pub fn value_range(&self) -> Option<(f64, f64)> {
+        match self {
+            E57FieldEncoding::Float { .. } => None,
+            E57FieldEncoding::Integer { minimum, maximum } => {
+                Some((*minimum as f64, *maximum as f64))
+            }
+            E57FieldEncoding::ScaledInteger {
+                minimum,
+                maximum,
+                scale,
+                offset,
+            } => Some((
+                *minimum as f64 * scale + offset,
+                *maximum as f64 * scale + offset,
+            )),
+        }
+    }
+}
+
+/// A single field of the point records of an E57 scan, e.g. `cartesianX` or `intensity`
+#[derive(Clone, Debug, PartialEq)]
+pub struct E57Field {
+    pub name: String,
+    pub encoding: E57FieldEncoding,
+}
+
+/// Description of a single scan (a `data3D` entry) within an E57 file
+#[derive(Clone, Debug, PartialEq)]
+pub struct E57Scan {
+    pub guid: Option<String>,
+    pub name: Option<String>,
+    pub pose: E57Pose,
+    /// Number of points in this scan
+    pub record_count: usize,
+    /// Physical offset of the binary section that contains the points of this scan
+    pub file_offset: u64,
+    /// The fields of each point record, in the order in which they are stored in the binary section
+    pub fields: Vec<E57Field>,
+    /// The range of intensity values from the `intensityLimits` of the scan
+    pub intensity_limits: Option<(f64, f64)>,
+    /// The ranges of the red, green and blue channels from the `colorLimits` of the scan
+    pub color_limits: Option<[(f64, f64); 3]>,
+}
+
+impl E57Scan {
+    /// Returns the index of the field with the given `name`, if this scan has such a field
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+
+    /// Returns the indices of the fields with the given `names`, if this scan has all of these fields
+    pub fn field_indices<const N: usize>(&self, names: [&str; N]) -> Option<[usize; N]> {
+        let mut indices = [0; N];
+        for (index, name) in indices.iter_mut().zip(names.iter()) {
+            *index = self.field_index(name)?;
+        }
+        Some(indices)
+    }
+}
+
+fn child_element<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn parse_attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<Option<T>> {
+    node.attribute(name)
+        .map(|value| {
+            value.trim().parse::<T>().map_err(|_| {
+                anyhow!(
+                    "Invalid value {} for attribute {} of E57 element {}",
+                    value,
+                    name,
+                    node.tag_name().name()
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Parses the value of a numeric E57 element (`Float`, `Integer` or `ScaledInteger`)
+fn parse_number(node: Node) -> Result<f64> {
+    let text = node.text().unwrap_or("").trim();
+    let value = if text.is_empty() {
+        0.0
+    } else {
+        text.parse::<f64>().with_context(|| {
+            format!(
+                "Invalid value {} of E57 element {}",
+                text,
+                node.tag_name().name()
+            )
+        })?
+    };
+    if node.attribute("type") == Some("ScaledInteger") {
+        let scale = parse_attribute::<f64>(node, "scale")?.unwrap_or(1.0);
+        let offset = parse_attribute::<f64>(node, "offset")?.unwrap_or(0.0);
+        Ok(value * scale + offset)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Parses the numeric child element `name` of `node`, returning `default` if the child does not exist
+fn parse_child_number(node: Node, name: &str, default: f64) -> Result<f64> {
+    child_element(node, name)
+        .map(parse_number)
+        .unwrap_or(Ok(default))
+}
+
+fn parse_string(node: Node, name: &str) -> Option<String> {
+    child_element(node, name)
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_owned())
+}
+
+fn parse_pose(node: Node) -> Result<E57Pose> {
+    let rotation = match child_element(node, "rotation") {
+        Some(rotation) => UnitQuaternion::from_quaternion(Quaternion::new(
+            parse_child_number(rotation, "w", 1.0)?,
+            parse_child_number(rotation, "x", 0.0)?,
+            parse_child_number(rotation, "y", 0.0)?,
+            parse_child_number(rotation, "z", 0.0)?,
+        )),
+        None => UnitQuaternion::identity(),
+    };
+    let translation = match child_element(node, "translation") {
+        Some(translation) => Vector3::new(
+            parse_child_number(translation, "x", 0.0)?,
+            parse_child_number(translation, "y", 0.0)?,
+            parse_child_number(translation, "z", 0.0)?,
+        ),
+        None => Vector3::zeros(),
+    };
+    Ok(E57Pose {
+        rotation,
+        translation,
+    })
+}
+
+fn parse_field(node: Node) -> Result<E57Field> {
+    let name = node.tag_name().name().to_owned();
+    let encoding = match node.attribute("type") {
+        Some("Float") => E57FieldEncoding::Float {
+            double_precision: node.attribute("precision") != Some("single"),
+        },
+        Some("Integer") => E57FieldEncoding::Integer {
+            minimum: parse_attribute(node, "minimum")?.unwrap_or(i64::MIN),
+            maximum: parse_attribute(node, "maximum")?.unwrap_or(i64::MAX),
+        },
+        Some("ScaledInteger") => E57FieldEncoding::ScaledInteger {
+            minimum: parse_attribute(node, "minimum")?.unwrap_or(i64::MIN),
+            maximum: parse_attribute(node, "maximum")?.unwrap_or(i64::MAX),
+            scale: parse_attribute(node, "scale")?.unwrap_or(1.0),
+            offset: parse_attribute(node, "offset")?.unwrap_or(0.0),
+        },
+        other => bail!(
+            "Unsupported type {} of field {} in E57 point prototype",
+            other.unwrap_or("<none>"),
+            name
+        ),
+    };
+    match encoding {
+        E57FieldEncoding::Integer { minimum, maximum }
+        | E57FieldEncoding::ScaledInteger {
+            minimum, maximum, ..
+        } if minimum > maximum => {
+            bail!("Minimum of field {name} in E57 point prototype is larger than its maximum")
+        }
+        _ => Ok(E57Field { name, encoding }),
+    }
+}
+
+fn parse_scan(node: Node) -> Result<E57Scan> {
+    let points = child_element(node, "points").ok_or_else(|| anyhow!("E57 scan has no points"))?;
+    if points.attribute("type") != Some("CompressedVector") {
+        bail!("Points of E57 scan are no CompressedVector");
+    }
+    let prototype = child_element(points, "prototype")
+        .ok_or_else(|| anyhow!("Points of E57 scan have no prototype"))?;
+    let fields = prototype
+        .children()
+        .filter(Node::is_element)
+        .map(parse_field)
+        .collect::<Result<Vec<_>>>()?;
+
+    let intensity_limits = match child_element(node, "intensityLimits") {
+        Some(limits) => Some((
+            parse_child_number(limits, "intensityMinimum", 0.0)?,
+            parse_child_number(limits, "intensityMaximum", 0.0)?,
+        )),
+        None => None,
+    };
+    let color_limits = match child_element(node, "colorLimits") {
+        Some(limits) => {
+            let mut channel_limits = [(0.0, 0.0); 3];
+            for (channel, name) in channel_limits
+                .iter_mut()
+                .zip(["Red", "Green", "Blue"].iter())
+            {
+                *channel = (
+                    parse_child_number(limits, &format!("color{name}Minimum"), 0.0)?,
+                    parse_child_number(limits, &format!("color{name}Maximum"), 0.0)?,
+                );
+            }
+            Some(channel_limits)
+        }
+        None => None,
+    };
+
+    Ok(E57Scan {
+        guid: parse_string(node, "guid"),
+        name: parse_string(node, "name"),
+        pose: match child_element(node, "pose") {
+            Some(pose) => parse_pose(pose)?,
+            None => E57Pose::identity(),
+        },
+        record_count: parse_attribute(points, "recordCount")?
+            .ok_or_else(|| anyhow!("Points of E57 scan have no recordCount"))?,
+        file_offset: parse_attribute(points, "fileOffset")?
+            .ok_or_else(|| anyhow!("Points of E57 scan have no fileOffset"))?,
+        fields,
+        intensity_limits,
+        color_limits,
+    })
+}
+
+/// Parses the XML section of an E57 file and returns the GUID of the file together with all its scans
+pub(crate) fn parse_e57_xml(xml: &str) -> Result<(Option<String>, Vec<E57Scan>)> {
+    let document = Document::parse(xml).context("Invalid XML section in E57 file")?;
+    let root = document.root_element();
+    if root.tag_name().name() != "e57Root" {
+        bail!("XML section of E57 file has no e57Root element");
+    }
+    let scans = match child_element(root, "data3D") {
+        Some(data3d) => data3d
+            .children()
+            .filter(Node::is_element)
+            .map(parse_scan)
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![],
+    };
+    Ok((parse_string(root, "guid"), scans))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<e57Root type="Structure" xmlns="http://www.astm.org/COMMIT/E57/2010-e57-v1.0">
+    <formatName type="String"><![CDATA[ASTM E57 3D Imaging Data File]]></formatName>
+    <guid type="String"><![CDATA[{file-guid}]]></guid>
+    <data3D type="Vector" allowHeterogeneousChildren="1">
+        <vectorChild type="Structure">
+            <guid type="String"><![CDATA[{scan-guid}]]></guid>
+            <name type="String"><![CDATA[Scan 1]]></name>
+            <pose type="Structure">
+                <rotation type="Structure">
+                    <w type="Float">0.7071067811865476</w>
+                    <x type="Float"/>
+                    <y type="Float">0</y>
+                    <z type="Float">0.7071067811865476</z>
+                </rotation>
+                <translation type="Structure">
+                    <x type="Float">10</x>
+                    <y type="Float">20</y>
+                    <z type="Float">30</z>
+                </translation>
+            </pose>
+            <intensityLimits type="Structure">
+                <intensityMinimum type="Integer">0</intensityMinimum>
+                <intensityMaximum type="Integer">2047</intensityMaximum>
+            </intensityLimits>
+            <points type="CompressedVector" fileOffset="1024" recordCount="42">
+                <prototype type="Structure">
+                    <cartesianX type="ScaledInteger" minimum="-1000" maximum="1000" scale="0.001" offset="5"/>
+                    <cartesianY type="Float"/>
+                    <cartesianZ type="Float" precision="single"/>
+                    <intensity type="Integer" minimum="0" maximum="2047"/>
+                    <rowIndex type="Integer" minimum="0" maximum="0"/>
+                </prototype>
+                <codecs type="Vector" allowHeterogeneousChildren="1"/>
+            </points>
+        </vectorChild>
+    </data3D>
+</e57Root>"#;
+
+    #[test]
+    fn test_parse_e57_xml() -> Result<()> {
+        let (guid, scans) = parse_e57_xml(TEST_XML)?;
+        assert_eq!(Some("{file-guid}".to_owned()), guid);
+        assert_eq!(1, scans.len());
+        let scan = &scans[0];
+        assert_eq!(Some("Scan 1".to_owned()), scan.name);
+        assert_eq!(42, scan.record_count);
+        assert_eq!(1024, scan.file_offset);
+        assert_eq!(Some((0.0, 2047.0)), scan.intensity_limits);
+        assert_eq!(None, scan.color_limits);
+
+        let transformed = scan.pose.transform_point(&Vector3::new(1.0, 0.0, 0.0));
+        assert!((transformed - Vector3::new(10.0, 21.0, 30.0)).norm() < 1e-9);
+
+        let bits = scan
+            .fields
+            .iter()
+            .map(|field| field.encoding.bits_per_value())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![11, 64, 32, 11, 0], bits);
+        assert_eq!(Some((4.0, 6.0)), scan.fields[0].encoding.value_range());
+        assert_eq!(
+            Some([0, 3]),
+            scan.field_indices(["cartesianX", "intensity"])
+        );
+        assert_eq!(None, scan.field_indices(["cartesianX", "colorRed"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_e57_xml() {
+        assert!(parse_e57_xml("<e57Root").is_err());
+        assert!(parse_e57_xml("<root/>").is_err());
+        let invalid_field = TEST_XML.replace(
+            r#"minimum="0" maximum="2047"/>"#,
+            r#"minimum="10" maximum="0"/>"#,
+        );
+        assert!(parse_e57_xml(&invalid_field).is_err());
+    }
+}
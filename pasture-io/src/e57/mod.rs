@@ -0,0 +1,14 @@
+mod e57_compressed_vector;
+pub(crate) use self::e57_compressed_vector::*;
+
+mod e57_file;
+pub use self::e57_file::*;
+
+mod e57_metadata;
+pub use self::e57_metadata::*;
+
+mod e57_reader;
+pub use self::e57_reader::*;
+
+mod e57_types;
+pub use self::e57_types::*;
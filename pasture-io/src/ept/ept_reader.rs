@@ -0,0 +1,610 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::containers::{
+    BorrowedMutBuffer, ExternalMemoryBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+};
+use pasture_core::layout::attributes::POSITION_3D;
+use pasture_core::layout::conversion::BufferLayoutConverter;
+use pasture_core::layout::PointLayout;
+use pasture_core::math::AABB;
+use pasture_core::meta::Metadata;
+use pasture_core::nalgebra::Vector3;
+
+use super::{
+    EptDataType, EptKey, EptMetadata, EptNode, EPT_DATA_DIRECTORY_NAME,
+    EPT_HIERARCHY_DIRECTORY_NAME, EPT_METADATA_FILE_NAME,
+};
+use crate::base::PointReader;
+use crate::las::LASReader;
+
+/// A query for the nodes of an EPT dataset. By default, a query selects all nodes. The query can be restricted
+/// to nodes that intersect a bounding box and to nodes up to a maximum depth
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EptQuery {
+    bounds: Option<AABB<f64>>,
+    max_depth: Option<u32>,
+}
+
+impl EptQuery {
+    /// Creates a new `EptQuery` that selects all nodes
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Only select nodes whose bounds intersect the given `bounds`
+    pub fn with_bounds(mut self, bounds: AABB<f64>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Only select nodes with a depth less than or equal to `max_depth`. The root node has depth 0
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Returns `true` if the node with the given `key` is selected by this query, given the bounding box of the
+    /// root node. If a node is not selected, none of its child nodes are selected either
+    pub fn matches(&self, key: &EptKey, root_bounds: &AABB<f64>) -> bool {
+        if self.max_depth.map(|max_depth| key.depth > max_depth) == Some(true) {
+            return false;
+        }
+        match &self.bounds {
+            Some(bounds) => key.bounds(root_bounds).intersects(bounds),
+            None => true,
+        }
+    }
+}
+
+/// Reader for Entwine Point Tile (EPT) datasets on the local file system. An EPT dataset consists of an `ept.json`
+/// file, the `ept-hierarchy` directory with the octree hierarchy as JSON files, and the `ept-data` directory with
+/// the points of each node. The `laszip` and `binary` data types are supported, LAZ files are read using the
+/// [`LASReader`].
+///
+/// The hierarchy is parsed on demand: [`query_nodes`](Self::query_nodes) only reads the hierarchy files of
+/// subtrees that are selected by the [`EptQuery`]. The points of single nodes can be read using
+/// [`read_node_into`](Self::read_node_into), and the points of all nodes selected by a query using
+/// [`read_query`](Self::read_query). As a `PointReader`, the `EptReader` reads the points of all nodes in
+/// breadth-first order.
+///
+/// The default `PointLayout` contains an attribute for each dimension of the schema in `ept.json`, see
+/// [`EptMetadata::point_layout`]. The positions are provided in world space using the `POSITION_3D` attribute with
+/// datatype `Vec3f64`
+pub struct EptReader {
+    directory: PathBuf,
+    metadata: EptMetadata,
+    /// `PointLayout` that exactly matches the binary layout of a single point in the `binary` data type
+    raw_layout: PointLayout,
+    layout: PointLayout,
+    /// All nodes of the dataset, which are determined on the first call to `read_into`
+    nodes: Option<Vec<EptNode>>,
+    current_node_index: usize,
+    current_node_points: Option<VectorBuffer>,
+    current_point_in_node: usize,
+}
+
+impl EptReader {
+    /// Creates a new `EptReader` for the EPT dataset at the given `path`. `path` can either be the directory of the
+    /// dataset or the path to its `ept.json` file
+    ///
+    /// # Errors
+    ///
+    /// If `ept.json` can't be read, or if it describes a dataset that pasture can't read, an error is returned
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata_path = if path.is_dir() {
+            path.join(EPT_METADATA_FILE_NAME)
+        } else {
+            path.to_owned()
+        };
+        let directory = metadata_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_owned();
+        let metadata: EptMetadata = serde_json::from_reader(BufReader::new(
+            File::open(&metadata_path)
+                .with_context(|| format!("Could not open {}", metadata_path.display()))?,
+        ))
+        .with_context(|| format!("Could not parse {}", metadata_path.display()))?;
+        metadata.verify()?;
+        if metadata.data_type == EptDataType::Zstandard {
+            bail!("The zstandard data type of EPT is not supported");
+        }
+
+        Ok(Self {
+            directory,
+            raw_layout: metadata.raw_point_layout()?,
+            layout: metadata.point_layout()?,
+            metadata,
+            nodes: None,
+            current_node_index: 0,
+            current_node_points: None,
+            current_point_in_node: 0,
+        })
+    }
+
+    /// Returns the metadata of the associated `EptReader`
+    pub fn metadata(&self) -> &EptMetadata {
+        &self.metadata
+    }
+
+    /// Returns the default `PointLayout` of the associated `EptReader`
+    pub fn point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+
+    /// Returns all nodes with points that are selected by the given `query`, in breadth-first order
+    ///
+    /// # Errors
+    ///
+    /// If one of the hierarchy files of the selected subtrees can't be read or is invalid, an error is returned
+    pub fn query_nodes(&self, query: &EptQuery) -> Result<Vec<EptNode>> {
+        let root_bounds = self.metadata.root_bounds();
+        let mut point_counts = self.read_hierarchy_file(&EptKey::ROOT)?;
+        let mut nodes = vec![];
+        let mut keys_to_visit = VecDeque::from(vec![EptKey::ROOT]);
+        while let Some(key) = keys_to_visit.pop_front() {
+            if !query.matches(&key, &root_bounds) {
+                continue;
+            }
+            let mut point_count = match point_counts.get(&key) {
+                Some(point_count) => *point_count,
+                None => continue,
+            };
+            // A point count of -1 means that the subtree of the node is stored in a separate hierarchy file
+            if point_count == -1 {
+                point_counts.extend(self.read_hierarchy_file(&key)?);
+                point_count = point_counts[&key];
+                if point_count == -1 {
+                    bail!("EPT hierarchy file {} does not contain its root node", key);
+                }
+            }
+            if point_count > 0 {
+                nodes.push(EptNode {
+                    key,
+                    num_points: point_count as u64,
+                });
+            }
+            keys_to_visit.extend(
+                key.children()
+                    .filter(|child_key| point_counts.contains_key(child_key)),
+            );
+        }
+        Ok(nodes)
+    }
+
+    /// Returns the total number of points in all nodes selected by the given `query`
+    pub fn query_point_count(&self, query: &EptQuery) -> Result<usize> {
+        Ok(self
+            .query_nodes(query)?
+            .iter()
+            .map(|node| node.num_points as usize)
+            .sum())
+    }
+
+    /// Reads the points of the given `node` into the given `point_buffer`. Uses the `PointLayout` of `point_buffer`
+    /// for reading, missing attributes are filled with default values. Overwrites existing data in `point_buffer`
+    /// starting at the first point, so the length of `point_buffer` must be at least `node.num_points`. Returns
+    /// the number of points that were read
+    ///
+    /// # Errors
+    ///
+    /// If the point data of the node can't be read, an error is returned
+    pub fn read_node_into<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &self,
+        node: &EptNode,
+        point_buffer: &'c mut B,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        self.read_node_into_range(node, point_buffer, 0)
+    }
+
+    /// Reads the points of the given `node` into a new buffer of type `B`. The `PointLayout` of the new buffer will
+    /// be equal to the default `PointLayout` of this `EptReader`
+    pub fn read_node<'b, B: OwningBuffer<'b> + MakeBufferFromLayout<'b> + 'b>(
+        &self,
+        node: &EptNode,
+    ) -> Result<B> {
+        let mut buffer = B::new_from_layout(self.layout.clone());
+        buffer.resize(node.num_points as usize);
+        self.read_node_into(node, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reads the points of all nodes selected by `query` into the given `point_buffer`. The points of each node
+    /// are stored consecutively, in the order returned by [`query_nodes`](EptReader::query_nodes). The length of
+    /// `point_buffer` must be at least [`query_point_count`](EptReader::query_point_count). Returns the number
+    /// of points that were read
+    pub fn read_query_into<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &self,
+        query: &EptQuery,
+        point_buffer: &'c mut B,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        let mut num_points_read = 0;
+        for node in self.query_nodes(query)? {
+            num_points_read += self.read_node_into_range(&node, point_buffer, num_points_read)?;
+        }
+        Ok(num_points_read)
+    }
+
+    /// Reads the points of all nodes selected by `query` into a new buffer of type `B`. The `PointLayout` of the
+    /// new buffer will be equal to the default `PointLayout` of this `EptReader`
+    pub fn read_query<'b, B: OwningBuffer<'b> + MakeBufferFromLayout<'b> + 'b>(
+        &self,
+        query: &EptQuery,
+    ) -> Result<B> {
+        let nodes = self.query_nodes(query)?;
+        let mut buffer = B::new_from_layout(self.layout.clone());
+        buffer.resize(nodes.iter().map(|node| node.num_points as usize).sum());
+        let mut num_points_read = 0;
+        for node in &nodes {
+            num_points_read += self.read_node_into_range(node, &mut buffer, num_points_read)?;
+        }
+        Ok(buffer)
+    }
+
+    /// Reads the hierarchy file of the subtree with the given root `key`, which maps the keys of the nodes in the
+    /// subtree to their point counts
+    fn read_hierarchy_file(&self, key: &EptKey) -> Result<HashMap<EptKey, i64>> {
+        let path = self
+            .directory
+            .join(EPT_HIERARCHY_DIRECTORY_NAME)
+            .join(format!("{}.json", key));
+        let entries: HashMap<String, i64> = serde_json::from_reader(BufReader::new(
+            File::open(&path).with_context(|| format!("Could not open {}", path.display()))?,
+        ))
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+        entries
+            .into_iter()
+            .map(|(key, point_count)| Ok((key.parse()?, point_count)))
+            .collect()
+    }
+
+    fn read_node_into_range<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &self,
+        node: &EptNode,
+        point_buffer: &'c mut B,
+        first_point_in_buffer: usize,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        let num_points = node.num_points as usize;
+        let target_range = first_point_in_buffer..(first_point_in_buffer + num_points);
+        if target_range.end > point_buffer.len() {
+            bail!(
+                "Point buffer is too small to hold the {} points of EPT node {}",
+                num_points,
+                node.key
+            );
+        }
+        let node_points = self.read_node_points(node)?;
+        let target_layout = point_buffer.point_layout().clone();
+        BufferLayoutConverter::for_layouts_with_default(&self.layout, &target_layout)
+            .convert_into_range(&node_points, 0..num_points, point_buffer, target_range);
+        Ok(num_points)
+    }
+
+    /// Reads the points of the given `node` into a new buffer with the default `PointLayout`
+    fn read_node_points(&self, node: &EptNode) -> Result<VectorBuffer> {
+        let path = self.directory.join(EPT_DATA_DIRECTORY_NAME).join(format!(
+            "{}.{}",
+            node.key,
+            self.metadata.data_type.extension()
+        ));
+        let num_points = node.num_points as usize;
+        let mut points = VectorBuffer::with_capacity(num_points, self.layout.clone());
+        points.resize(num_points);
+        match self.metadata.data_type {
+            EptDataType::Laszip => {
+                let mut reader = LASReader::from_path(&path, false)
+                    .with_context(|| format!("Could not open {}", path.display()))?;
+                let num_read = reader.read_into(&mut points, num_points)?;
+                if num_read != num_points {
+                    bail!(
+                        "{} contains {} points, but the EPT hierarchy expects {} points",
+                        path.display(),
+                        num_read,
+                        num_points
+                    );
+                }
+            }
+            EptDataType::Binary => {
+                let data = fs::read(&path)
+                    .with_context(|| format!("Could not read {}", path.display()))?;
+                let expected_size = num_points * self.raw_layout.size_of_point_entry() as usize;
+                if data.len() != expected_size {
+                    bail!(
+                        "{} has a size of {} bytes, but {} bytes are expected for {} points",
+                        path.display(),
+                        data.len(),
+                        expected_size,
+                        num_points
+                    );
+                }
+                let raw_points =
+                    ExternalMemoryBuffer::new(data.as_slice(), self.raw_layout.clone());
+                let mut converter =
+                    BufferLayoutConverter::for_layouts(&self.raw_layout, &self.layout);
+                let raw_position_attribute = self
+                    .raw_layout
+                    .get_attribute_by_name(POSITION_3D.name())
+                    .expect("Raw layout must contain positions")
+                    .attribute_definition()
+                    .clone();
+                let scale = self.metadata.scale();
+                let offset = self.metadata.offset();
+                converter.set_custom_mapping_with_transformation(
+                    &raw_position_attribute,
+                    &POSITION_3D,
+                    move |position: Vector3<f64>| -> Vector3<f64> {
+                        position.component_mul(&scale) + offset
+                    },
+                    false,
+                );
+                converter.convert_into(&raw_points, &mut points);
+            }
+            EptDataType::Zstandard => {
+                bail!("The zstandard data type of EPT is not supported")
+            }
+        }
+        Ok(points)
+    }
+}
+
+impl PointReader for EptReader {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        if self.nodes.is_none() {
+            self.nodes = Some(self.query_nodes(&EptQuery::new())?);
+        }
+        let target_layout = point_buffer.point_layout().clone();
+        let converter =
+            BufferLayoutConverter::for_layouts_with_default(&self.layout, &target_layout);
+        let mut num_read = 0;
+        while num_read < count {
+            let node = match self
+                .nodes
+                .as_ref()
+                .and_then(|nodes| nodes.get(self.current_node_index))
+            {
+                Some(node) => *node,
+                None => break,
+            };
+            if self.current_node_points.is_none() {
+                self.current_node_points = Some(self.read_node_points(&node)?);
+                self.current_point_in_node = 0;
+            }
+
+            let num_points_in_node = node.num_points as usize;
+            let num_to_copy =
+                (num_points_in_node - self.current_point_in_node).min(count - num_read);
+            let node_points = self
+                .current_node_points
+                .as_ref()
+                .expect("Node data must exist");
+            converter.convert_into_range(
+                node_points,
+                self.current_point_in_node..(self.current_point_in_node + num_to_copy),
+                point_buffer,
+                num_read..(num_read + num_to_copy),
+            );
+            num_read += num_to_copy;
+            self.current_point_in_node += num_to_copy;
+            if self.current_point_in_node == num_points_in_node {
+                self.current_node_points = None;
+                self.current_node_index += 1;
+            }
+        }
+        Ok(num_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use pasture_core::containers::BorrowedBuffer;
+    use pasture_core::layout::attributes::{COLOR_RGB, GPS_TIME, INTENSITY};
+    use pasture_core::layout::{PointAttributeDataType, PointAttributeDefinition};
+    use pasture_core::nalgebra::Point3;
+
+    use super::*;
+
+    fn test_dataset_path(name: &str) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resources/test");
+        path.push(name);
+        path
+    }
+
+    /// The nodes of the `ept_binary` test dataset together with their point counts, in breadth-first order
+    const BINARY_TEST_NODES: &[(&str, u64)] = &[
+        ("0-0-0-0", 4),
+        ("1-0-0-0", 3),
+        ("1-1-1-1", 2),
+        ("2-3-3-3", 2),
+    ];
+
+    /// Returns the expected positions of all points in the `ept_binary` test dataset. The points of each node are
+    /// evenly spaced along the diagonal of the node
+    fn expected_binary_positions() -> Vec<Vector3<f64>> {
+        let root_bounds =
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(8.0, 8.0, 8.0));
+        BINARY_TEST_NODES
+            .iter()
+            .flat_map(|(key, count)| {
+                let bounds = key.parse::<EptKey>().unwrap().bounds(&root_bounds);
+                (0..*count).map(move |index| {
+                    let fraction = (index + 1) as f64 / (*count + 1) as f64;
+                    bounds.min().coords + bounds.extent() * fraction
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ept_binary_queries() -> Result<()> {
+        let reader = EptReader::from_path(test_dataset_path("ept_binary"))?;
+        assert_eq!(Some(11), reader.get_metadata().number_of_points());
+        assert_eq!(
+            BINARY_TEST_NODES
+                .iter()
+                .map(|(key, count)| EptNode {
+                    key: key.parse().unwrap(),
+                    num_points: *count,
+                })
+                .collect::<Vec<_>>(),
+            reader.query_nodes(&EptQuery::new())?
+        );
+
+        assert_eq!(
+            4,
+            reader.query_point_count(&EptQuery::new().with_max_depth(0))?
+        );
+        let bounds_query = EptQuery::new().with_bounds(AABB::from_min_max(
+            Point3::new(6.5, 6.5, 6.5),
+            Point3::new(8.0, 8.0, 8.0),
+        ));
+        assert_eq!(
+            vec!["0-0-0-0", "1-1-1-1", "2-3-3-3"],
+            reader
+                .query_nodes(&bounds_query)?
+                .iter()
+                .map(|node| node.key.to_string())
+                .collect::<Vec<_>>()
+        );
+        let points = reader.read_query::<VectorBuffer>(&bounds_query)?;
+        assert_eq!(8, points.len());
+        assert_eq!(
+            vec![0, 1, 2, 3, 7, 8, 9, 10],
+            points
+                .view_attribute::<u16>(&INTENSITY)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        let deepest_node = reader.query_nodes(&EptQuery::new())?[3];
+        let node_points = reader.read_node::<VectorBuffer>(&deepest_node)?;
+        let node_bounds = deepest_node.key.bounds(&reader.metadata().root_bounds());
+        for position in node_points.view_attribute::<Vector3<f64>>(&POSITION_3D) {
+            assert!(node_bounds.contains(&Point3::from(position)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ept_binary_read() -> Result<()> {
+        let mut reader = EptReader::from_path(test_dataset_path("ept_binary").join("ept.json"))?;
+        let deviation = PointAttributeDefinition::custom(
+            Cow::Borrowed("Deviation"),
+            PointAttributeDataType::F32,
+        );
+        assert!(reader.point_layout().has_attribute(&deviation));
+        assert!(reader.point_layout().has_attribute(&COLOR_RGB));
+
+        // Read in chunks that don't align with the nodes
+        let mut points = VectorBuffer::new_from_layout(reader.point_layout().clone());
+        loop {
+            let chunk = reader.read::<VectorBuffer>(3)?;
+            if chunk.is_empty() {
+                break;
+            }
+            points.append(&chunk);
+        }
+        assert_eq!(11, points.len());
+
+        for (index, (expected_position, position)) in expected_binary_positions()
+            .iter()
+            .zip(points.view_attribute::<Vector3<f64>>(&POSITION_3D))
+            .enumerate()
+        {
+            assert!((expected_position - position).amax() <= 0.005);
+            assert_eq!(
+                index as u16,
+                points.view_attribute::<u16>(&INTENSITY).at(index)
+            );
+            assert_eq!(
+                Vector3::new(index as u16, 2 * index as u16, 3 * index as u16),
+                points.view_attribute::<Vector3<u16>>(&COLOR_RGB).at(index)
+            );
+            assert_eq!(
+                index as f64 * 0.5,
+                points.view_attribute::<f64>(&GPS_TIME).at(index)
+            );
+            assert_eq!(
+                index as f32 * 0.25,
+                points.view_attribute::<f32>(&deviation).at(index)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ept_laszip_read() -> Result<()> {
+        let mut reader = EptReader::from_path(test_dataset_path("ept_laz"))?;
+        assert_eq!(EptDataType::Laszip, reader.metadata().data_type);
+        let points = reader.read::<VectorBuffer>(10)?;
+        assert_eq!(10, points.len());
+
+        let mut las_reader =
+            LASReader::from_path(test_dataset_path("10_points_format_1.laz"), false)?;
+        let mut expected_points = VectorBuffer::new_from_layout(reader.point_layout().clone());
+        expected_points.resize(10);
+        las_reader.read_into(&mut expected_points, 10)?;
+        assert_eq!(
+            expected_points
+                .view_attribute::<Vector3<f64>>(&POSITION_3D)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            points
+                .view_attribute::<Vector3<f64>>(&POSITION_3D)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            expected_points
+                .view_attribute::<u16>(&INTENSITY)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            points
+                .view_attribute::<u16>(&INTENSITY)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            expected_points
+                .view_attribute::<f64>(&GPS_TIME)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            points
+                .view_attribute::<f64>(&GPS_TIME)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,557 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use pasture_core::layout::attributes::{
+    CLASSIFICATION, CLASSIFICATION_FLAGS, COLOR_RGB, EDGE_OF_FLIGHT_LINE, GPS_TIME, INTENSITY, NIR,
+    NUMBER_OF_RETURNS, POINT_SOURCE_ID, POSITION_3D, RETURN_NUMBER, SCANNER_CHANNEL, SCAN_ANGLE,
+    SCAN_ANGLE_RANK, SCAN_DIRECTION_FLAG, USER_DATA,
+};
+use pasture_core::layout::{PointAttributeDataType, PointAttributeDefinition, PointLayout};
+use pasture_core::math::AABB;
+use pasture_core::meta::Metadata;
+use pasture_core::nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Name of the file that stores the metadata of an EPT dataset
+pub const EPT_METADATA_FILE_NAME: &str = "ept.json";
+/// Name of the directory that stores the hierarchy files of an EPT dataset
+pub const EPT_HIERARCHY_DIRECTORY_NAME: &str = "ept-hierarchy";
+/// Name of the directory that stores the point data files of an EPT dataset
+pub const EPT_DATA_DIRECTORY_NAME: &str = "ept-data";
+
+/// Mapping between the dimension names of EPT (which are the dimension names of PDAL) and the builtin attributes of
+/// pasture. The `X`, `Y`, `Z` and `Red`, `Green`, `Blue` dimensions are combined into `POSITION_3D` and `COLOR_RGB`
+const EPT_BUILTIN_DIMENSIONS: &[(&str, PointAttributeDefinition)] = &[
+    ("Intensity", INTENSITY),
+    ("ReturnNumber", RETURN_NUMBER),
+    ("NumberOfReturns", NUMBER_OF_RETURNS),
+    ("ScanDirectionFlag", SCAN_DIRECTION_FLAG),
+    ("EdgeOfFlightLine", EDGE_OF_FLIGHT_LINE),
+    ("Classification", CLASSIFICATION),
+    ("ScanAngleRank", SCAN_ANGLE_RANK),
+    ("ScanAngle", SCAN_ANGLE),
+    ("UserData", USER_DATA),
+    ("PointSourceId", POINT_SOURCE_ID),
+    ("GpsTime", GPS_TIME),
+    ("Infrared", NIR),
+    ("ScanChannel", SCANNER_CHANNEL),
+    ("ClassFlags", CLASSIFICATION_FLAGS),
+];
+
+/// Storage format of the point data files in `ept-data`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EptDataType {
+    /// Each node is stored as a LAZ file
+    Laszip,
+    /// Each node is stored as uncompressed binary data, with the dimensions of each point in the order of the schema
+    Binary,
+    /// Each node is stored as binary data that is compressed with Zstandard
+    Zstandard,
+}
+
+impl EptDataType {
+    /// Returns the file extension of the point data files for this data type
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Laszip => "laz",
+            Self::Binary => "bin",
+            Self::Zstandard => "zst",
+        }
+    }
+}
+
+/// Type of a dimension in the EPT schema
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EptDimensionType {
+    Signed,
+    Unsigned,
+    Float,
+}
+
+/// A single dimension of the EPT schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EptDimension {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub dimension_type: EptDimensionType,
+    /// Size of the dimension in bytes
+    pub size: u64,
+    /// Scale of the dimension. Dimensions with a scale or an offset store scaled integer values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Offset of the dimension. Dimensions with a scale or an offset store scaled integer values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<f64>,
+}
+
+impl EptDimension {
+    /// Returns the pasture datatype that matches the binary representation of this dimension
+    ///
+    /// # Errors
+    ///
+    /// If the combination of type and size is not a valid EPT dimension type, an error is returned
+    pub fn datatype(&self) -> Result<PointAttributeDataType> {
+        match (self.dimension_type, self.size) {
+            (EptDimensionType::Signed, 1) => Ok(PointAttributeDataType::I8),
+            (EptDimensionType::Signed, 2) => Ok(PointAttributeDataType::I16),
+            (EptDimensionType::Signed, 4) => Ok(PointAttributeDataType::I32),
+            (EptDimensionType::Signed, 8) => Ok(PointAttributeDataType::I64),
+            (EptDimensionType::Unsigned, 1) => Ok(PointAttributeDataType::U8),
+            (EptDimensionType::Unsigned, 2) => Ok(PointAttributeDataType::U16),
+            (EptDimensionType::Unsigned, 4) => Ok(PointAttributeDataType::U32),
+            (EptDimensionType::Unsigned, 8) => Ok(PointAttributeDataType::U64),
+            (EptDimensionType::Float, 4) => Ok(PointAttributeDataType::F32),
+            (EptDimensionType::Float, 8) => Ok(PointAttributeDataType::F64),
+            (dimension_type, size) => bail!(
+                "Invalid size {} for EPT dimension {} of type {:?}",
+                size,
+                self.name,
+                dimension_type
+            ),
+        }
+    }
+
+    /// Returns the pasture attribute for this dimension. Dimensions that match a builtin attribute of pasture use
+    /// that attribute with the datatype of the dimension, all other dimensions become custom attributes with the
+    /// name of the dimension
+    pub fn point_attribute(&self) -> Result<PointAttributeDefinition> {
+        let datatype = self.datatype()?;
+        Ok(EPT_BUILTIN_DIMENSIONS
+            .iter()
+            .find(|(name, _)| *name == self.name)
+            .map(|(_, attribute)| attribute.with_custom_datatype(datatype))
+            .unwrap_or_else(|| {
+                PointAttributeDefinition::custom(Cow::Owned(self.name.clone()), datatype)
+            }))
+    }
+}
+
+/// The `srs` object of `ept.json`
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EptSrs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub horizontal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertical: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wkt: Option<String>,
+}
+
+/// The contents of the `ept.json` file of an Entwine Point Tile (EPT) dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EptMetadata {
+    /// Cubic bounds of the octree as `[xmin, ymin, zmin, xmax, ymax, zmax]`
+    pub bounds: [f64; 6],
+    /// Tight bounds of all points as `[xmin, ymin, zmin, xmax, ymax, zmax]`
+    #[serde(rename = "boundsConforming")]
+    pub bounds_conforming: [f64; 6],
+    #[serde(rename = "dataType")]
+    pub data_type: EptDataType,
+    #[serde(rename = "hierarchyType", default = "default_hierarchy_type")]
+    pub hierarchy_type: String,
+    /// Total number of points in the dataset
+    pub points: u64,
+    pub schema: Vec<EptDimension>,
+    /// Number of voxels along each axis of a node, which determines the resolution of each level
+    pub span: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub srs: Option<EptSrs>,
+    pub version: String,
+}
+
+fn default_hierarchy_type() -> String {
+    "json".into()
+}
+
+/// Converts EPT bounds in the form `[xmin, ymin, zmin, xmax, ymax, zmax]` into an `AABB`
+fn aabb_from_ept_bounds(bounds: &[f64; 6]) -> AABB<f64> {
+    AABB::from_min_max_unchecked(
+        Point3::new(bounds[0], bounds[1], bounds[2]),
+        Point3::new(bounds[3], bounds[4], bounds[5]),
+    )
+}
+
+impl EptMetadata {
+    /// Returns the cubic bounding box of the root node
+    pub fn root_bounds(&self) -> AABB<f64> {
+        aabb_from_ept_bounds(&self.bounds)
+    }
+
+    /// Returns the tight bounding box of all points
+    pub fn conforming_bounds(&self) -> AABB<f64> {
+        aabb_from_ept_bounds(&self.bounds_conforming)
+    }
+
+    /// Returns the dimension with the given `name`, if it exists in the schema
+    pub fn dimension(&self, name: &str) -> Option<&EptDimension> {
+        self.schema.iter().find(|dimension| dimension.name == name)
+    }
+
+    /// Returns the scale of the `X`, `Y` and `Z` dimensions. Dimensions without a scale have a scale of 1
+    pub fn scale(&self) -> Vector3<f64> {
+        let scale_of = |name: &str| {
+            self.dimension(name)
+                .and_then(|dimension| dimension.scale)
+                .unwrap_or(1.0)
+        };
+        Vector3::new(scale_of("X"), scale_of("Y"), scale_of("Z"))
+    }
+
+    /// Returns the offset of the `X`, `Y` and `Z` dimensions. Dimensions without an offset have an offset of 0
+    pub fn offset(&self) -> Vector3<f64> {
+        let offset_of = |name: &str| {
+            self.dimension(name)
+                .and_then(|dimension| dimension.offset)
+                .unwrap_or_default()
+        };
+        Vector3::new(offset_of("X"), offset_of("Y"), offset_of("Z"))
+    }
+
+    /// Checks that this metadata describes an EPT dataset that pasture can read
+    pub fn verify(&self) -> Result<()> {
+        if !self.version.starts_with("1.") {
+            bail!("Unsupported EPT version {}", self.version);
+        }
+        if self.hierarchy_type != "json" {
+            bail!(
+                "Unsupported EPT hierarchy type {} (only json is supported)",
+                self.hierarchy_type
+            );
+        }
+        self.schema_attributes()?;
+        Ok(())
+    }
+
+    /// Returns the `PointLayout` that exactly matches the binary layout of the points in the `binary` data type.
+    /// The `X`, `Y` and `Z` dimensions are combined into the `POSITION_3D` attribute, the `Red`, `Green` and `Blue`
+    /// dimensions into the `COLOR_RGB` attribute
+    pub fn raw_point_layout(&self) -> Result<PointLayout> {
+        let attributes = self
+            .schema_attributes()?
+            .into_iter()
+            .map(|(raw_attribute, _)| raw_attribute)
+            .collect::<Vec<_>>();
+        Ok(PointLayout::from_attributes_packed(&attributes, 1))
+    }
+
+    /// Returns the `PointLayout` for reading the points of this dataset. This is the same as the
+    /// [`raw_point_layout`](Self::raw_point_layout), except that positions are stored in world space using the
+    /// `POSITION_3D` attribute with datatype `Vec3f64`
+    pub fn point_layout(&self) -> Result<PointLayout> {
+        let attributes = self
+            .schema_attributes()?
+            .into_iter()
+            .map(|(_, attribute)| attribute)
+            .collect::<Vec<_>>();
+        Ok(PointLayout::from_attributes(&attributes))
+    }
+
+    /// Maps the dimensions of the schema onto pasture attributes. Returns pairs of the attribute in the binary
+    /// layout of the points and the attribute that the `EptReader` provides
+    fn schema_attributes(
+        &self,
+    ) -> Result<Vec<(PointAttributeDefinition, PointAttributeDefinition)>> {
+        let mut attributes = vec![];
+        let mut dimensions = self.schema.iter().peekable();
+        while let Some(dimension) = dimensions.next() {
+            match dimension.name.as_str() {
+                "X" => {
+                    let y = dimensions.next_if(|next| next.name == "Y");
+                    let z = dimensions.next_if(|next| next.name == "Z");
+                    let (y, z) = y.zip(z).ok_or_else(|| {
+                        anyhow!("The X, Y and Z dimensions of the EPT schema must be consecutive")
+                    })?;
+                    if y.datatype()? != dimension.datatype()?
+                        || z.datatype()? != dimension.datatype()?
+                    {
+                        bail!(
+                            "The X, Y and Z dimensions of the EPT schema must have the same type"
+                        );
+                    }
+                    let datatype = match dimension.datatype()? {
+                        PointAttributeDataType::I32 => PointAttributeDataType::Vec3i32,
+                        PointAttributeDataType::F32 => PointAttributeDataType::Vec3f32,
+                        PointAttributeDataType::F64 => PointAttributeDataType::Vec3f64,
+                        other => bail!("Unsupported datatype {} for EPT positions", other),
+                    };
+                    attributes.push((
+                        POSITION_3D.with_custom_datatype(datatype),
+                        POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+                    ));
+                }
+                "Red" => {
+                    let green = dimensions.next_if(|next| next.name == "Green");
+                    let blue = dimensions.next_if(|next| next.name == "Blue");
+                    let (green, blue) = green.zip(blue).ok_or_else(|| {
+                        anyhow!("The Red, Green and Blue dimensions of the EPT schema must be consecutive")
+                    })?;
+                    if green.datatype()? != dimension.datatype()?
+                        || blue.datatype()? != dimension.datatype()?
+                    {
+                        bail!("The Red, Green and Blue dimensions of the EPT schema must have the same type");
+                    }
+                    let datatype = match dimension.datatype()? {
+                        PointAttributeDataType::U8 => PointAttributeDataType::Vec3u8,
+                        PointAttributeDataType::U16 => PointAttributeDataType::Vec3u16,
+                        other => bail!("Unsupported datatype {} for EPT colors", other),
+                    };
+                    let color_attribute = COLOR_RGB.with_custom_datatype(datatype);
+                    attributes.push((color_attribute.clone(), color_attribute));
+                }
+                "Y" | "Z" | "Green" | "Blue" => bail!(
+                    "The EPT schema contains the {} dimension in an unsupported position",
+                    dimension.name
+                ),
+                _ => {
+                    let attribute = dimension.point_attribute()?;
+                    attributes.push((attribute.clone(), attribute));
+                }
+            }
+        }
+        if !attributes
+            .iter()
+            .any(|(attribute, _)| attribute.name() == POSITION_3D.name())
+        {
+            bail!("The EPT schema contains no X, Y and Z dimensions");
+        }
+        Ok(attributes)
+    }
+}
+
+impl Metadata for EptMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        Some(self.conforming_bounds())
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.points as usize)
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "SPAN" => Some(Box::new(self.span)),
+            "DATA_TYPE" => Some(Box::new(self.data_type)),
+            "SCALE" => Some(Box::new(self.scale())),
+            "OFFSET" => Some(Box::new(self.offset())),
+            "SRS" => self.srs.clone().map(|srs| Box::new(srs) as Box<dyn Any>),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
+
+impl Display for EptMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "EptMetadata {{")?;
+        writeln!(f, "\t\"version\": {}", self.version)?;
+        writeln!(f, "\t\"points\": {}", self.points)?;
+        writeln!(f, "\t\"span\": {}", self.span)?;
+        writeln!(f, "\t\"dataType\": {:?}", self.data_type)?;
+        writeln!(f, "\t\"bounds\": {:?}", self.bounds)?;
+        writeln!(
+            f,
+            "\t\"schema\": [{}]",
+            self.schema
+                .iter()
+                .map(|dimension| dimension.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Key of a node in the EPT octree, consisting of the depth of the node and its position within the grid of all
+/// nodes at that depth. The string representation is `D-X-Y-Z`, which is also the name of the hierarchy and point
+/// data files of the node
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EptKey {
+    pub depth: u32,
+    pub x: u64,
+    pub y: u64,
+    pub z: u64,
+}
+
+impl EptKey {
+    /// The key of the root node
+    pub const ROOT: EptKey = EptKey {
+        depth: 0,
+        x: 0,
+        y: 0,
+        z: 0,
+    };
+
+    /// Returns the keys of all eight potential child nodes of this node
+    pub fn children(&self) -> impl Iterator<Item = EptKey> + '_ {
+        (0..8).map(move |child_index| EptKey {
+            depth: self.depth + 1,
+            x: self.x * 2 + (child_index & 1),
+            y: self.y * 2 + ((child_index >> 1) & 1),
+            z: self.z * 2 + ((child_index >> 2) & 1),
+        })
+    }
+
+    /// Returns the bounding box of this node, given the bounding box of the root node
+    ///
+    /// ```
+    /// # use pasture_io::ept::EptKey;
+    /// # use pasture_core::math::AABB;
+    /// # use pasture_core::nalgebra::Point3;
+    /// let root_bounds = AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(8.0, 8.0, 8.0));
+    /// let key: EptKey = "2-1-0-3".parse().unwrap();
+    /// let bounds = key.bounds(&root_bounds);
+    /// assert_eq!(Point3::new(2.0, 0.0, 6.0), *bounds.min());
+    /// assert_eq!(Point3::new(4.0, 2.0, 8.0), *bounds.max());
+    /// ```
+    pub fn bounds(&self, root_bounds: &AABB<f64>) -> AABB<f64> {
+        let node_size = root_bounds.extent() / (1_u64 << self.depth) as f64;
+        let min = root_bounds.min()
+            + Vector3::new(self.x as f64, self.y as f64, self.z as f64).component_mul(&node_size);
+        AABB::from_min_max_unchecked(min, min + node_size)
+    }
+}
+
+impl Display for EptKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}-{}-{}", self.depth, self.x, self.y, self.z)
+    }
+}
+
+impl FromStr for EptKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split('-').collect::<Vec<_>>();
+        if parts.len() != 4 {
+            bail!("Invalid EPT key {} (expected D-X-Y-Z)", s);
+        }
+        let key = Self {
+            depth: parts[0]
+                .parse()
+                .with_context(|| format!("Invalid depth in EPT key {}", s))?,
+            x: parts[1]
+                .parse()
+                .with_context(|| format!("Invalid X in EPT key {}", s))?,
+            y: parts[2]
+                .parse()
+                .with_context(|| format!("Invalid Y in EPT key {}", s))?,
+            z: parts[3]
+                .parse()
+                .with_context(|| format!("Invalid Z in EPT key {}", s))?,
+        };
+        let nodes_per_axis = 1_u64
+            .checked_shl(key.depth)
+            .ok_or_else(|| anyhow!("Invalid depth in EPT key {}", s))?;
+        if key.x >= nodes_per_axis || key.y >= nodes_per_axis || key.z >= nodes_per_axis {
+            bail!("EPT key {} is out of bounds for its depth", s);
+        }
+        Ok(key)
+    }
+}
+
+/// A node of the EPT octree that contains points
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EptNode {
+    pub key: EptKey,
+    pub num_points: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ept_key() -> Result<()> {
+        let key: EptKey = "3-7-0-5".parse()?;
+        assert_eq!(
+            EptKey {
+                depth: 3,
+                x: 7,
+                y: 0,
+                z: 5
+            },
+            key
+        );
+        assert_eq!("3-7-0-5", key.to_string());
+        assert_eq!(
+            vec!["4-14-0-10", "4-15-0-10", "4-14-1-10", "4-15-1-10"],
+            key.children()
+                .take(4)
+                .map(|child| child.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert!("1-2-0-0".parse::<EptKey>().is_err());
+        assert!("1-0-0".parse::<EptKey>().is_err());
+        assert!("a-0-0-0".parse::<EptKey>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ept_schema_to_point_layout() -> Result<()> {
+        let json = r#"{
+            "bounds": [-10, -10, -10, 10, 10, 10],
+            "boundsConforming": [-8, -9, -2, 7, 8, 3],
+            "dataType": "binary",
+            "hierarchyType": "json",
+            "points": 100,
+            "schema": [
+                { "name": "X", "type": "signed", "size": 4, "scale": 0.01, "offset": 0 },
+                { "name": "Y", "type": "signed", "size": 4, "scale": 0.01, "offset": 0 },
+                { "name": "Z", "type": "signed", "size": 4, "scale": 0.001, "offset": 1.5 },
+                { "name": "Intensity", "type": "unsigned", "size": 2 },
+                { "name": "ScanAngleRank", "type": "float", "size": 4 },
+                { "name": "Red", "type": "unsigned", "size": 2 },
+                { "name": "Green", "type": "unsigned", "size": 2 },
+                { "name": "Blue", "type": "unsigned", "size": 2 },
+                { "name": "OriginId", "type": "unsigned", "size": 4 }
+            ],
+            "span": 128,
+            "srs": { "authority": "EPSG", "horizontal": "3857" },
+            "version": "1.0.0"
+        }"#;
+        let metadata: EptMetadata = serde_json::from_str(json)?;
+        metadata.verify()?;
+        assert_eq!(EptDataType::Binary, metadata.data_type);
+        assert_eq!(Vector3::new(0.01, 0.01, 0.001), metadata.scale());
+        assert_eq!(Vector3::new(0.0, 0.0, 1.5), metadata.offset());
+
+        let raw_layout = metadata.raw_point_layout()?;
+        assert_eq!(28, raw_layout.size_of_point_entry());
+        assert!(raw_layout
+            .has_attribute(&POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3i32)));
+
+        let layout = metadata.point_layout()?;
+        let expected_attributes = vec![
+            POSITION_3D,
+            INTENSITY,
+            SCAN_ANGLE_RANK.with_custom_datatype(PointAttributeDataType::F32),
+            COLOR_RGB,
+            PointAttributeDefinition::custom(
+                Cow::Borrowed("OriginId"),
+                PointAttributeDataType::U32,
+            ),
+        ];
+        assert_eq!(
+            expected_attributes,
+            layout
+                .attributes()
+                .map(|attribute| attribute.attribute_definition().clone())
+                .collect::<Vec<_>>()
+        );
+
+        let mut invalid_metadata = metadata.clone();
+        invalid_metadata.schema.swap(1, 3);
+        assert!(invalid_metadata.verify().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+mod ept_types;
+pub use self::ept_types::*;
+
+mod ept_reader;
+pub use self::ept_reader::*;
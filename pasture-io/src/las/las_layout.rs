@@ -1,4 +1,4 @@
-use std::{borrow::Cow, collections::HashSet};
+use std::{borrow::Cow, collections::HashSet, convert::TryInto};
 
 use anyhow::{anyhow, Result};
 use las::point::Format;
@@ -18,9 +18,9 @@ use pasture_core::{
 };
 
 use super::{
-    LASMetadata, LasPointFormat0, LasPointFormat1, LasPointFormat10, LasPointFormat2,
-    LasPointFormat3, LasPointFormat4, LasPointFormat5, LasPointFormat6, LasPointFormat7,
-    LasPointFormat8, LasPointFormat9,
+    ExtraBytesDataType, ExtraBytesEntryBuilder, ExtraBytesVlr, LASMetadata, LasPointFormat0,
+    LasPointFormat1, LasPointFormat10, LasPointFormat2, LasPointFormat3, LasPointFormat4,
+    LasPointFormat5, LasPointFormat6, LasPointFormat7, LasPointFormat8, LasPointFormat9,
 };
 
 /// Returns the offset to the first extra byte in the given LAS point format. Returns `None` if the format
@@ -174,7 +174,7 @@ pub fn point_layout_from_las_metadata(
         base_layout.add_attribute(
             PointAttributeDefinition::custom(
                 Cow::Borrowed("UndescribedExtraBytes"),
-                PointAttributeDataType::ByteArray(num_described_bytes as u64),
+                PointAttributeDataType::ByteArray(num_undescribed_bytes as u64),
             ),
             FieldAlignment::Packed(1),
         );
@@ -231,6 +231,52 @@ pub fn las_point_format_from_point_layout(point_layout: &PointLayout) -> Format
     format
 }
 
+/// Returns an `ExtraBytesVlr` describing all attributes of the given `PointLayout` that can be written to a LAS file
+/// as extra bytes. These are all attributes that are not known LAS attributes (see [`is_known_las_attribute`]) and that
+/// have a scalar datatype (e.g. `U32` or `F64`), as the LAS extra bytes only support scalar values. The entries in the
+/// resulting VLR are in the same order as the attributes in `point_layout`. Returns `None` if there are no such attributes.
+///
+/// Since LAS stores the name of extra bytes in a 32-byte array, attributes with longer names can't be written and
+/// converting the resulting VLR into a `Vlr` fails (see [`MAX_EXTRA_BYTES_NAME_LENGTH`](crate::las::MAX_EXTRA_BYTES_NAME_LENGTH)).
+/// The values of the attributes are written unscaled, so the entries use no scale and offset. Their minimum and maximum
+/// values depend on the point data, so the `LASWriter` fills them in when it is flushed
+/// ```
+/// # use pasture_io::las::*;
+/// # use pasture_core::layout::*;
+/// # use std::borrow::Cow;
+///
+/// let reflectance = PointAttributeDefinition::custom(Cow::Borrowed("Reflectance"), PointAttributeDataType::F32);
+/// let layout = PointLayout::from_attributes(&[attributes::POSITION_3D, attributes::NORMAL, reflectance]);
+/// let extra_bytes_vlr = extra_bytes_vlr_from_point_layout(&layout).unwrap();
+/// assert_eq!(1, extra_bytes_vlr.entries().len());
+/// assert_eq!("Reflectance", extra_bytes_vlr.entries()[0].name());
+/// ```
+pub fn extra_bytes_vlr_from_point_layout(point_layout: &PointLayout) -> Option<ExtraBytesVlr> {
+    let entries = point_layout
+        .attributes()
+        .filter(|attribute| {
+            let definition = attribute.attribute_definition();
+            !is_known_las_attribute(definition)
+                && *definition != ATTRIBUTE_LOCAL_LAS_POSITION
+                && *definition != ATTRIBUTE_BASIC_FLAGS
+                && *definition != ATTRIBUTE_EXTENDED_FLAGS
+        })
+        .filter_map(|attribute| {
+            let data_type: ExtraBytesDataType = attribute.datatype().try_into().ok()?;
+            Some(
+                ExtraBytesEntryBuilder::new(data_type, attribute.name().to_owned(), String::new())
+                    .build(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        None
+    } else {
+        Some(entries.into_iter().collect())
+    }
+}
+
 /// Returns `true` if the given `attribute` is a known LAS point attribute. This function only checks the name of
 /// the known attributes and ignores the datatype, so a `POSITION_3D` attribute with datatype `Vec3f32` is still
 /// considered to be a known LAS attribute (as pasture is able to perform type conversion).
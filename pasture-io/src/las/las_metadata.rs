@@ -3,12 +3,14 @@ use std::{
     borrow::Cow,
     convert::{TryFrom, TryInto},
     fmt::Display,
+    io::{Read, Seek, SeekFrom},
     iter::FromIterator,
     path::Path,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use bitfield::bitfield;
+use byteorder::{ByteOrder, LittleEndian};
 use chrono::Datelike;
 use las::{Bounds, Header};
 use las_rs::{point::Format, raw::vlr::RecordLength, Vector, Vlr};
@@ -74,6 +76,47 @@ pub fn path_is_compressed_las_file<P: AsRef<Path>>(path: P) -> Result<bool> {
         ))
 }
 
+/// Determines whether the LAS file in `read` is a compressed LAZ file by looking for the LASzip VLR. Unlike
+/// [`path_is_compressed_las_file`], this also works for files with a wrong or missing extension. The position
+/// of `read` is restored afterwards
+pub fn read_is_compressed_las_file<R: Read + Seek>(read: &mut R) -> Result<bool> {
+    let start_position = read.stream_position()?;
+    let is_compressed = contains_laszip_vlr(read, start_position);
+    read.seek(SeekFrom::Start(start_position))?;
+    is_compressed
+}
+
+fn contains_laszip_vlr<R: Read + Seek>(read: &mut R, start_position: u64) -> Result<bool> {
+    let mut header_start = [0; 104];
+    read.read_exact(&mut header_start)
+        .context("Unexpected end of LAS header")?;
+    if &header_start[..4] != b"LASF" {
+        bail!("Invalid LAS file signature");
+    }
+    let header_size = LittleEndian::read_u16(&header_start[94..96]) as u64;
+    let number_of_vlrs = LittleEndian::read_u32(&header_start[100..104]);
+
+    const VLR_HEADER_SIZE: u64 = 54;
+    let mut vlr_offset = start_position + header_size;
+    let mut vlr_header = [0; VLR_HEADER_SIZE as usize];
+    for _ in 0..number_of_vlrs {
+        read.seek(SeekFrom::Start(vlr_offset))?;
+        read.read_exact(&mut vlr_header)
+            .context("Unexpected end of LAS VLR header")?;
+        let user_id = vlr_header[2..18]
+            .split(|c| *c == 0)
+            .next()
+            .unwrap_or_default();
+        let record_id = LittleEndian::read_u16(&vlr_header[18..20]);
+        if user_id == laz::LazVlr::USER_ID.as_bytes() && record_id == laz::LazVlr::RECORD_ID {
+            return Ok(true);
+        }
+        let record_length = LittleEndian::read_u16(&vlr_header[20..22]) as u64;
+        vlr_offset += VLR_HEADER_SIZE + record_length;
+    }
+    Ok(false)
+}
+
 const KNOWN_VLR_USER_ID: &str = "LASF_Spec";
 
 #[derive(Clone, Debug, Default)]
@@ -192,6 +235,213 @@ impl Display for TextAreaDescription {
     }
 }
 
+/// VLR that describes the coordinate reference system of a LAS file as an OGC WKT string. Since WKT strings can be
+/// large, this record is often stored as an EVLR instead of a regular VLR
+#[derive(Clone, Debug)]
+pub struct OgcCoordinateSystemWkt {
+    wkt: String,
+}
+
+impl OgcCoordinateSystemWkt {
+    pub const USER_ID: &'static str = "LASF_Projection";
+    pub const RECORD_ID: u16 = 2112;
+
+    /// Creates a new `OgcCoordinateSystemWkt` from the given WKT string
+    pub fn new(wkt: String) -> Self {
+        Self { wkt }
+    }
+
+    pub fn wkt(&self) -> &str {
+        &self.wkt
+    }
+}
+
+impl TryFrom<&'_ Vlr> for OgcCoordinateSystemWkt {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Vlr) -> std::result::Result<Self, Self::Error> {
+        if value.user_id != Self::USER_ID {
+            return Err(anyhow!(
+                "Expected user_id {} but got {}",
+                Self::USER_ID,
+                value.user_id
+            ));
+        }
+        if value.record_id != Self::RECORD_ID {
+            return Err(anyhow!(
+                "Expected record ID {} but got {}",
+                Self::RECORD_ID,
+                value.record_id
+            ));
+        }
+
+        // The WKT string is null-terminated
+        let wkt_bytes = value
+            .data
+            .iter()
+            .position(|c| *c == 0)
+            .map(|null_terminator| &value.data[..null_terminator])
+            .unwrap_or(&value.data);
+        let wkt = std::str::from_utf8(wkt_bytes)
+            .context("OGC WKT coordinate system record contains invalid utf8")?;
+        Ok(Self {
+            wkt: wkt.to_owned(),
+        })
+    }
+}
+
+impl From<&OgcCoordinateSystemWkt> for Vlr {
+    fn from(value: &OgcCoordinateSystemWkt) -> Self {
+        let mut data = value.wkt.as_bytes().to_vec();
+        data.push(0);
+        Vlr {
+            user_id: OgcCoordinateSystemWkt::USER_ID.to_owned(),
+            record_id: OgcCoordinateSystemWkt::RECORD_ID,
+            description: "OGC WKT Coordinate System".to_owned(),
+            data,
+        }
+    }
+}
+
+impl Display for OgcCoordinateSystemWkt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "OGC WKT Coordinate System\n{}", self.wkt)
+    }
+}
+
+/// Waveform Packet Descriptor VLR, which describes how the sampled waveforms of all points that reference this
+/// descriptor are stored. There can be up to 255 of these VLRs in a LAS file, the descriptor index that points
+/// reference (the `WAVE_PACKET_DESCRIPTOR_INDEX` attribute) is the record ID of the VLR minus 99
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WaveformPacketDescriptor {
+    /// Index of this descriptor, as referenced by the `WAVE_PACKET_DESCRIPTOR_INDEX` attribute
+    pub index: u8,
+    pub bits_per_sample: u8,
+    /// Compression type of the waveform packets. 0 means no compression, which is the only type that the LAS
+    /// specification currently defines
+    pub compression_type: u8,
+    pub number_of_samples: u32,
+    /// Temporal spacing between two samples in picoseconds
+    pub temporal_sample_spacing: u32,
+    pub digitizer_gain: f64,
+    pub digitizer_offset: f64,
+}
+
+impl WaveformPacketDescriptor {
+    pub const MIN_RECORD_ID: u16 = 100;
+    pub const MAX_RECORD_ID: u16 = 354;
+    /// Size of the Waveform Packet Descriptor VLR data in bytes
+    pub const SIZE: usize = 26;
+
+    /// Is the given VLR a Waveform Packet Descriptor VLR?
+    pub fn is_waveform_packet_descriptor_vlr(vlr: &Vlr) -> bool {
+        vlr.user_id == KNOWN_VLR_USER_ID
+            && (Self::MIN_RECORD_ID..=Self::MAX_RECORD_ID).contains(&vlr.record_id)
+    }
+
+    /// Decodes the samples of a single waveform packet that was stored using this descriptor. The raw sample values
+    /// are converted into voltages using the digitizer gain and offset of this descriptor
+    ///
+    /// # Errors
+    ///
+    /// Only uncompressed waveform packets with 8, 16, 24 or 32 bits per sample are supported. If this descriptor
+    /// describes any other type of waveform packets, or if `packet` is too small, an error is returned
+    pub fn decode_samples(&self, packet: &[u8]) -> Result<Vec<f64>> {
+        if self.compression_type != 0 {
+            bail!(
+                "Unsupported waveform compression type {}",
+                self.compression_type
+            );
+        }
+        if self.bits_per_sample == 0 || self.bits_per_sample % 8 != 0 || self.bits_per_sample > 32 {
+            bail!(
+                "Unsupported number of bits per waveform sample ({}), only 8, 16, 24 and 32 bits are supported",
+                self.bits_per_sample
+            );
+        }
+        let bytes_per_sample = (self.bits_per_sample / 8) as usize;
+        let expected_size = self.number_of_samples as usize * bytes_per_sample;
+        if packet.len() < expected_size {
+            bail!(
+                "Waveform packet has {} bytes, but descriptor {} requires {expected_size} bytes",
+                packet.len(),
+                self.index
+            );
+        }
+        Ok(packet[..expected_size]
+            .chunks_exact(bytes_per_sample)
+            .map(|sample| {
+                let raw_value = LittleEndian::read_uint(sample, bytes_per_sample);
+                self.digitizer_gain * raw_value as f64 + self.digitizer_offset
+            })
+            .collect())
+    }
+}
+
+impl TryFrom<&'_ Vlr> for WaveformPacketDescriptor {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Vlr) -> std::result::Result<Self, Self::Error> {
+        if !Self::is_waveform_packet_descriptor_vlr(value) {
+            return Err(anyhow!(
+                "Expected Waveform Packet Descriptor VLR with user_id {KNOWN_VLR_USER_ID} and record ID in [{};{}] but got {} {}",
+                Self::MIN_RECORD_ID,
+                Self::MAX_RECORD_ID,
+                value.user_id,
+                value.record_id
+            ));
+        }
+        if value.data.len() != Self::SIZE {
+            return Err(anyhow!("Waveform Packet Descriptor VLR is defined to have a size of {} bytes, but got {} bytes instead", Self::SIZE, value.data.len()));
+        }
+
+        let data = &value.data;
+        Ok(Self {
+            index: (value.record_id - (Self::MIN_RECORD_ID - 1)) as u8,
+            bits_per_sample: data[0],
+            compression_type: data[1],
+            number_of_samples: LittleEndian::read_u32(&data[2..6]),
+            temporal_sample_spacing: LittleEndian::read_u32(&data[6..10]),
+            digitizer_gain: LittleEndian::read_f64(&data[10..18]),
+            digitizer_offset: LittleEndian::read_f64(&data[18..26]),
+        })
+    }
+}
+
+impl From<&WaveformPacketDescriptor> for Vlr {
+    fn from(value: &WaveformPacketDescriptor) -> Self {
+        let mut data = vec![0; WaveformPacketDescriptor::SIZE];
+        data[0] = value.bits_per_sample;
+        data[1] = value.compression_type;
+        LittleEndian::write_u32(&mut data[2..6], value.number_of_samples);
+        LittleEndian::write_u32(&mut data[6..10], value.temporal_sample_spacing);
+        LittleEndian::write_f64(&mut data[10..18], value.digitizer_gain);
+        LittleEndian::write_f64(&mut data[18..26], value.digitizer_offset);
+        Vlr {
+            user_id: KNOWN_VLR_USER_ID.to_owned(),
+            record_id: WaveformPacketDescriptor::MIN_RECORD_ID - 1 + value.index as u16,
+            description: "Waveform Packet Descriptor".to_owned(),
+            data,
+        }
+    }
+}
+
+impl Display for WaveformPacketDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Waveform Packet Descriptor {}", self.index)?;
+        writeln!(f, "	Bits per sample:         {}", self.bits_per_sample)?;
+        writeln!(f, "	Compression type:        {}", self.compression_type)?;
+        writeln!(f, "	Number of samples:       {}", self.number_of_samples)?;
+        writeln!(
+            f,
+            "	Temporal sample spacing: {} ps",
+            self.temporal_sample_spacing
+        )?;
+        writeln!(f, "	Digitizer gain:          {}", self.digitizer_gain)?;
+        writeln!(f, "	Digitizer offset:        {}", self.digitizer_offset)
+    }
+}
+
 /// Data type of VLR extra bytes record
 #[derive(Copy, Clone, Debug)]
 pub enum ExtraBytesDataType {
@@ -314,6 +564,26 @@ impl TryFrom<ExtraBytesDataType> for PointAttributeDataType {
     }
 }
 
+impl TryFrom<PointAttributeDataType> for ExtraBytesDataType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: PointAttributeDataType) -> std::result::Result<Self, Self::Error> {
+        match value {
+            PointAttributeDataType::U8 => Ok(Self::U8),
+            PointAttributeDataType::I8 => Ok(Self::I8),
+            PointAttributeDataType::U16 => Ok(Self::U16),
+            PointAttributeDataType::I16 => Ok(Self::I16),
+            PointAttributeDataType::U32 => Ok(Self::U32),
+            PointAttributeDataType::I32 => Ok(Self::I32),
+            PointAttributeDataType::U64 => Ok(Self::U64),
+            PointAttributeDataType::I64 => Ok(Self::I64),
+            PointAttributeDataType::F32 => Ok(Self::F32),
+            PointAttributeDataType::F64 => Ok(Self::F64),
+            other => bail!("Datatype {other} can't be represented as LAS extra bytes"),
+        }
+    }
+}
+
 impl Display for ExtraBytesDataType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:#?}", self)
@@ -338,7 +608,7 @@ pub(crate) struct RawExtraBytesEntry {
     reserved: [u8; 2],
     data_type: u8,
     options: u8,
-    name: [u8; 32],
+    name: [u8; MAX_EXTRA_BYTES_NAME_LENGTH],
     unused: [u8; 4],
     no_data: [u8; 8],
     deprecated_1: [u8; 16],
@@ -353,6 +623,8 @@ pub(crate) struct RawExtraBytesEntry {
     description: [u8; 32],
 }
 const RAW_EXTRA_BYTES_ENTRY_SIZE: usize = 192;
+/// Maximum length of the name of an `ExtraBytesEntry` in bytes
+pub const MAX_EXTRA_BYTES_NAME_LENGTH: usize = 32;
 const_assert_eq!(
     RAW_EXTRA_BYTES_ENTRY_SIZE,
     std::mem::size_of::<RawExtraBytesEntry>()
@@ -434,7 +706,7 @@ impl ExtraBytesEntry {
     }
 
     pub fn min_as_float(&self) -> Result<f64> {
-        if !self.data_type.is_unsigned() {
+        if !self.data_type.is_floating_point() {
             bail!("Extra bytes datatype is not a floating point type");
         }
 
@@ -465,7 +737,7 @@ impl ExtraBytesEntry {
     }
 
     pub fn max_as_float(&self) -> Result<f64> {
-        if !self.data_type.is_unsigned() {
+        if !self.data_type.is_floating_point() {
             bail!("Extra bytes datatype is not a floating point type");
         }
 
@@ -496,7 +768,7 @@ impl ExtraBytesEntry {
     }
 
     pub fn no_data_value_as_float(&self) -> Result<f64> {
-        if !self.data_type.is_unsigned() {
+        if !self.data_type.is_floating_point() {
             bail!("Extra bytes datatype is not a floating point type");
         }
 
@@ -504,6 +776,15 @@ impl ExtraBytesEntry {
         Ok(as_f64)
     }
 
+    /// Sets the minimum and maximum values of the extra bytes, encoded as `u64`, `i64` or `f64` depending on the data
+    /// type, and marks them as relevant
+    pub(crate) fn set_min_max_values(&mut self, min_value: [u8; 8], max_value: [u8; 8]) {
+        self.min_value = min_value;
+        self.max_value = max_value;
+        self.options.set_min_is_relevant(true);
+        self.options.set_max_is_relevant(true);
+    }
+
     /// Returns a matching `PointAttributeDefinition` for the extra bytes described by this `ExtraBytesEntry`
     pub fn get_point_attribute(&self) -> Result<PointAttributeDefinition> {
         let pasture_datatype: PointAttributeDataType =
@@ -725,6 +1006,19 @@ impl TryInto<Vlr> for &ExtraBytesVlr {
     type Error = anyhow::Error;
 
     fn try_into(self) -> Result<Vlr> {
+        // Names are stored in a fixed-size array, and truncated names would not match the attributes anymore
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .find(|entry| entry.name.len() > MAX_EXTRA_BYTES_NAME_LENGTH)
+        {
+            bail!(
+                "Name of extra bytes '{}' exceeds the maximum length of {} bytes",
+                entry.name,
+                MAX_EXTRA_BYTES_NAME_LENGTH
+            );
+        }
+
         let entries: Vec<u8> = self
             .entries
             .iter()
@@ -774,6 +1068,68 @@ fn display_generic_vlr(vlr: &Vlr, f: &mut std::fmt::Formatter<'_>) -> std::fmt::
     }
 }
 
+/// Size of the header of an EVLR in bytes
+pub(crate) const EVLR_HEADER_SIZE: u64 = 60;
+
+/// Header of an extended VLR (EVLR) within a LAS file. EVLRs are stored after the point records and can be very
+/// large (e.g. waveform data packets), so only their headers are read when a LAS file is opened. The payload of an
+/// EVLR can be read on demand using [`LASReader::read_evlr`](crate::las::LASReader::read_evlr)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvlrHeader {
+    pub user_id: String,
+    pub record_id: u16,
+    pub description: String,
+    /// Position of the payload of the EVLR within the LAS file, in bytes
+    pub data_offset: u64,
+    /// Size of the payload of the EVLR in bytes
+    pub data_length: u64,
+}
+
+impl EvlrHeader {
+    /// Reads an `EvlrHeader` from `read`, which must be at the given `position` within the LAS file
+    pub(crate) fn read_from<R: Read>(mut read: R, position: u64) -> Result<Self> {
+        let mut raw_header = [0; EVLR_HEADER_SIZE as usize];
+        read.read_exact(&mut raw_header)?;
+        let user_id: &[u8; 16] = raw_header[2..18].try_into()?;
+        let description: &[u8; 32] = raw_header[28..60].try_into()?;
+        Ok(Self {
+            user_id: las_string_to_rust_string(user_id).context("User ID is invalid string")?,
+            record_id: LittleEndian::read_u16(&raw_header[18..20]),
+            description: las_string_to_rust_string(description)
+                .context("Description is invalid string")?,
+            data_offset: position + EVLR_HEADER_SIZE,
+            data_length: LittleEndian::read_u64(&raw_header[20..28]),
+        })
+    }
+
+    /// Reads the payload of this EVLR from `read` and returns the full EVLR. This changes the position of `read`!
+    pub(crate) fn read_payload<R: Read + Seek>(&self, read: &mut R) -> Result<Vlr> {
+        let mut data = vec![0; self.data_length.try_into()?];
+        read.seek(SeekFrom::Start(self.data_offset))?;
+        read.read_exact(&mut data).with_context(|| {
+            format!(
+                "Failed to read payload of EVLR {} {}",
+                self.user_id, self.record_id
+            )
+        })?;
+        Ok(Vlr {
+            user_id: self.user_id.clone(),
+            record_id: self.record_id,
+            description: self.description.clone(),
+            data,
+        })
+    }
+}
+
+impl Display for EvlrHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "\t{}", self.description)?;
+        writeln!(f, "\t\tUser:      {}", self.user_id)?;
+        writeln!(f, "\t\tRecord:    {}", self.record_id)?;
+        writeln!(f, "\t\tSize:      {} bytes", self.data_length)
+    }
+}
+
 /// `Metadata` implementation for LAS/LAZ files
 #[derive(Debug, Clone)]
 pub struct LASMetadata {
@@ -783,6 +1139,9 @@ pub struct LASMetadata {
     classification_lookup_vlr: Option<Box<ClassificationLookup>>, //Boxed because it is large
     text_area_description_vlr: Option<TextAreaDescription>,
     extra_bytes_vlr: Option<ExtraBytesVlr>,
+    ogc_wkt_crs: Option<OgcCoordinateSystemWkt>,
+    waveform_packet_descriptors: Vec<WaveformPacketDescriptor>,
+    evlrs: Vec<EvlrHeader>,
     raw_las_header: Option<Header>,
 }
 
@@ -807,6 +1166,9 @@ impl LASMetadata {
             classification_lookup_vlr: None,
             extra_bytes_vlr: None,
             text_area_description_vlr: None,
+            ogc_wkt_crs: None,
+            waveform_packet_descriptors: vec![],
+            evlrs: vec![],
         }
     }
 
@@ -840,6 +1202,48 @@ impl LASMetadata {
     pub fn extra_bytes_vlr(&self) -> Option<&ExtraBytesVlr> {
         self.extra_bytes_vlr.as_ref()
     }
+
+    /// Returns the OGC WKT coordinate system record, if it exists. The record can either be stored as a regular
+    /// VLR or as an EVLR
+    pub fn ogc_wkt_crs(&self) -> Option<&OgcCoordinateSystemWkt> {
+        self.ogc_wkt_crs.as_ref()
+    }
+
+    /// Returns all Waveform Packet Descriptor VLRs, sorted by their descriptor index
+    pub fn waveform_packet_descriptors(&self) -> &[WaveformPacketDescriptor] {
+        &self.waveform_packet_descriptors
+    }
+
+    /// Returns the Waveform Packet Descriptor VLR with the given descriptor `index`, if it exists
+    pub fn waveform_packet_descriptor(&self, index: u8) -> Option<&WaveformPacketDescriptor> {
+        self.waveform_packet_descriptors
+            .iter()
+            .find(|descriptor| descriptor.index == index)
+    }
+
+    /// Returns the headers of all extended VLRs (EVLRs) of the LAS file that the associated `LASMetadata` was read
+    /// from. EVLRs are only supported by LAS 1.4 and are stored after the point records. Their payloads are not
+    /// part of the `LASMetadata`, use [`LASReader::read_evlr`](crate::las::LASReader::read_evlr) to read them
+    pub fn evlrs(&self) -> &[EvlrHeader] {
+        &self.evlrs
+    }
+
+    /// Returns the header of the first EVLR with the given `user_id` and `record_id`, if it exists
+    pub fn find_evlr(&self, user_id: &str, record_id: u16) -> Option<&EvlrHeader> {
+        self.evlrs
+            .iter()
+            .find(|evlr| evlr.user_id == user_id && evlr.record_id == record_id)
+    }
+
+    /// Sets the headers of the EVLRs of the LAS file that this `LASMetadata` was read from
+    pub(crate) fn set_evlrs(&mut self, evlrs: Vec<EvlrHeader>) {
+        self.evlrs = evlrs;
+    }
+
+    /// Sets the OGC WKT coordinate system record, which might be stored in an EVLR
+    pub(crate) fn set_ogc_wkt_crs(&mut self, ogc_wkt_crs: OgcCoordinateSystemWkt) {
+        self.ogc_wkt_crs = Some(ogc_wkt_crs);
+    }
 }
 
 impl Display for LASMetadata {
@@ -858,6 +1262,12 @@ impl Display for LASMetadata {
         if let Some(extra_bytes) = &self.extra_bytes_vlr {
             write!(f, "{}", extra_bytes)?;
         }
+        if let Some(ogc_wkt_crs) = &self.ogc_wkt_crs {
+            write!(f, "{}", ogc_wkt_crs)?;
+        }
+        for waveform_packet_descriptor in &self.waveform_packet_descriptors {
+            write!(f, "{}", waveform_packet_descriptor)?;
+        }
 
         if let Some(las_header) = &self.raw_las_header {
             writeln!(f, "Raw LAS header entries:")?;
@@ -956,12 +1366,12 @@ impl Display for LASMetadata {
                 }
             }
 
-            if las_header.evlrs().is_empty() {
+            if self.evlrs.is_empty() {
                 writeln!(f, "No extended VLRs")?;
             } else {
                 writeln!(f, "Extended VLRs")?;
-                for evlr in las_header.evlrs() {
-                    display_generic_vlr(evlr, f)?;
+                for evlr in &self.evlrs {
+                    write!(f, "{}", evlr)?;
                 }
             }
         }
@@ -1057,6 +1467,27 @@ impl TryFrom<&las::Header> for LASMetadata {
             .transpose()
             .context("Could not parse Extra Bytes VLR")?;
 
+        let ogc_wkt_crs = header
+            .vlrs()
+            .iter()
+            .chain(header.evlrs().iter())
+            .find(|vlr| {
+                vlr.user_id == OgcCoordinateSystemWkt::USER_ID
+                    && vlr.record_id == OgcCoordinateSystemWkt::RECORD_ID
+            })
+            .map(OgcCoordinateSystemWkt::try_from)
+            .transpose()
+            .context("Could not parse OGC WKT coordinate system record")?;
+
+        let mut waveform_packet_descriptors = header
+            .vlrs()
+            .iter()
+            .filter(|vlr| WaveformPacketDescriptor::is_waveform_packet_descriptor_vlr(vlr))
+            .map(WaveformPacketDescriptor::try_from)
+            .collect::<Result<Vec<_>>>()
+            .context("Could not parse Waveform Packet Descriptor VLR")?;
+        waveform_packet_descriptors.sort_by_key(|descriptor| descriptor.index);
+
         Ok(Self {
             bounds: las_bounds_to_pasture_bounds(header.bounds()),
             point_count: header.number_of_points() as usize,
@@ -1065,6 +1496,9 @@ impl TryFrom<&las::Header> for LASMetadata {
             classification_lookup_vlr: classification_lookup_vlr.map(Box::new),
             extra_bytes_vlr,
             text_area_description_vlr,
+            ogc_wkt_crs,
+            waveform_packet_descriptors,
+            evlrs: vec![],
         })
     }
 }
@@ -1076,3 +1510,27 @@ impl TryFrom<las::Header> for LASMetadata {
         (&value).try_into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+    use crate::las::{get_test_las_path, get_test_laz_path};
+
+    #[test]
+    fn test_read_is_compressed_las_file() -> Result<()> {
+        for format in 0..=10 {
+            let mut las_file = BufReader::new(File::open(get_test_las_path(format))?);
+            assert!(!read_is_compressed_las_file(&mut las_file)?);
+            assert_eq!(0, las_file.stream_position()?);
+
+            let mut laz_file = BufReader::new(File::open(get_test_laz_path(format))?);
+            assert!(read_is_compressed_las_file(&mut laz_file)?);
+            assert_eq!(0, laz_file.stream_position()?);
+        }
+
+        assert!(read_is_compressed_las_file(&mut std::io::Cursor::new(b"PSTR")).is_err());
+        Ok(())
+    }
+}
@@ -1,16 +1,29 @@
+use std::{convert::TryFrom, io::SeekFrom, ops::Range, path::Path};
 use std::{
     fs::File,
-    io::{BufReader, Read, Seek},
+    io::{BufReader, Cursor, Read, Seek},
 };
-use std::{io::SeekFrom, path::Path};
 
-use anyhow::Result;
-use las_rs::Header;
+use anyhow::{bail, Context, Result};
+use las_rs::{Header, Vlr};
 
-use crate::base::{PointReader, SeekToPoint};
-use pasture_core::{containers::BorrowedMutBuffer, layout::PointLayout, meta::Metadata};
+use crate::base::{MappedPointData, PointReader, SeekToPoint};
+use pasture_core::{
+    containers::{
+        BorrowedMutBuffer, ExternalMemoryBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+    },
+    layout::{PointLayout, PointType},
+    meta::Metadata,
+};
+
+use super::{
+    decode_waveforms, point_layout_from_las_metadata, read_is_compressed_las_file, EvlrHeader,
+    LASMetadata, LASReaderBase, RawLASReader, RawLAZReader, Waveform, WaveformPointAttributes,
+    WAVEFORM_DATA_PACKETS_RECORD_ID, WAVEFORM_DATA_PACKETS_USER_ID,
+};
 
-use super::{path_is_compressed_las_file, LASMetadata, LASReaderBase, RawLASReader, RawLAZReader};
+/// Size of the largest LAS header (LAS 1.4) in bytes
+const MAX_LAS_HEADER_SIZE: u64 = 375;
 
 pub enum LASReaderFlavor<'a, T: Read + Seek + Send + 'a> {
     LAS(RawLASReader<T>),
@@ -31,6 +44,20 @@ impl<'a, T: Read + Seek + Send + 'a> LASReaderFlavor<'a, T> {
             LASReaderFlavor::LAZ(reader) => reader.header(),
         }
     }
+
+    pub fn read_evlr(&mut self, evlr: &EvlrHeader) -> Result<Vlr> {
+        match self {
+            LASReaderFlavor::LAS(reader) => reader.read_evlr(evlr),
+            LASReaderFlavor::LAZ(reader) => reader.read_evlr(evlr),
+        }
+    }
+
+    pub fn read_bytes_at(&mut self, position: u64, buffer: &mut [u8]) -> Result<()> {
+        match self {
+            LASReaderFlavor::LAS(reader) => reader.read_bytes_at(position, buffer),
+            LASReaderFlavor::LAZ(reader) => reader.read_bytes_at(position, buffer),
+        }
+    }
 }
 
 impl<'a, T: Read + Seek + Send + 'a> PointReader for LASReaderFlavor<'a, T> {
@@ -73,17 +100,24 @@ impl<'a, T: Read + Seek + Send + 'a> SeekToPoint for LASReaderFlavor<'a, T> {
 }
 
 /// `PointReader` implementation for LAS/LAZ files
+///
+/// Uncompressed LAS files can also be memory-mapped using [`LASReader::map_from_path`], which does not copy the
+/// points at all
 pub struct LASReader<'a, R: Read + Seek + Send + 'a> {
     raw_reader: LASReaderFlavor<'a, R>,
+    external_waveform_data: Option<BufReader<File>>,
 }
 
 impl LASReader<'static, BufReader<File>> {
-    /// Creates a new `LASReader` by opening the file at the given `path`. Tries to determine whether
-    /// the file is compressed from the file extension (i.e. files with extension `.laz` are assumed to be
-    /// compressed). If `point_layout_matches_memory_layout`
+    /// Creates a new `LASReader` by opening the file at the given `path`. Whether the file is compressed
+    /// is determined from the presence of the LASzip VLR, so files with a wrong or missing extension are
+    /// supported as well. If `point_layout_matches_memory_layout`
     /// is `true`, the reader will return point data with a `PointLayout` that exactly matches the binary
     /// layout of the LAS point records. See [`point_layout_from_las_point_format`] for more information.
     ///
+    /// If a waveform data file with the same name as `path` and the extension `.wdp` exists, it is used
+    /// to resolve external waveform data packets (see [`LASReader::read_waveforms`])
+    ///
     /// # Errors
     ///
     /// If `path` does not exist, cannot be opened or does not point to a valid LAS/LAZ file, an error is returned.
@@ -91,9 +125,62 @@ impl LASReader<'static, BufReader<File>> {
         path: P,
         point_layout_matches_memory_layout: bool,
     ) -> Result<LASReader<'static, BufReader<File>>> {
-        let is_compressed = path_is_compressed_las_file(path.as_ref())?;
-        let file = BufReader::new(File::open(path)?);
-        Self::from_read(file, is_compressed, point_layout_matches_memory_layout)
+        let mut file = BufReader::new(File::open(path.as_ref())?);
+        let is_compressed = read_is_compressed_las_file(&mut file)?;
+        let mut reader = Self::from_read(file, is_compressed, point_layout_matches_memory_layout)?;
+        let waveform_data_path = path.as_ref().with_extension("wdp");
+        if waveform_data_path.is_file() {
+            reader.set_external_waveform_data_path(waveform_data_path)?;
+        }
+        Ok(reader)
+    }
+
+    /// Memory-maps the points of the uncompressed LAS file at `path`. Only the header, the VLRs and the EVLR headers
+    /// are parsed (see [`LASReader::read_evlr`]), the returned buffer points directly into the mapped file and has a `PointLayout` that exactly matches the binary layout of the LAS
+    /// point records (see [`point_layout_from_las_point_format`] and [`point_layout_from_las_metadata`]). Typed views
+    /// over the buffer thus read the points without copying them. Note that positions are the raw `Vector3<i32>`
+    /// values of the point records, which still have to be scaled and offset using the values in the LAS header.
+    /// For a LAS file without points, an empty buffer is returned
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be opened or mapped, is not a valid LAS file, or is a compressed LAZ file, an error is returned
+    pub fn map_from_path<P: AsRef<Path>>(path: P) -> Result<ExternalMemoryBuffer<MappedPointData>> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("Could not open file {}", path.as_ref().display()))?;
+        let mut read = BufReader::new(&file);
+        if read_is_compressed_las_file(&mut read)? {
+            bail!("Only uncompressed LAS files can be memory-mapped");
+        }
+        let reader =
+            LASReader::from_read(read, false, true).context("Could not read LAS header")?;
+        let point_layout = point_layout_from_las_metadata(reader.las_metadata(), true)?;
+        // The raw header is read from the file instead of being converted from `reader.header()`, since the
+        // conversion fails for the infinite bounds of files without points
+        let file_size = file.metadata()?.len();
+        let header_data =
+            unsafe { MappedPointData::map(&file, 0, file_size.min(MAX_LAS_HEADER_SIZE)) }
+                .context("Could not memory-map LAS header")?;
+        let raw_header = las_rs::raw::Header::read_from(Cursor::new(header_data.as_ref()))
+            .context("Could not read LAS header")?;
+        let point_record_length = raw_header.point_data_record_length as u64;
+        if point_layout.size_of_point_entry() != point_record_length {
+            bail!(
+                "Size of LAS point records ({} bytes) does not match the size of point format {:?}",
+                point_record_length,
+                reader.header().point_format()
+            );
+        }
+        let offset_to_point_data = raw_header.offset_to_point_data as u64;
+        let data_size = point_record_length * reader.header().number_of_points();
+        if file_size < offset_to_point_data + data_size {
+            bail!("Unexpected end of LAS file");
+        }
+        // Safe as long as the file is not modified while it is mapped, which is the same contract that all
+        // memory-mapped file APIs have
+        let point_data = unsafe { MappedPointData::map(&file, offset_to_point_data, data_size) }
+            .context("Could not memory-map LAS file")?;
+        Ok(ExternalMemoryBuffer::new(point_data, point_layout))
     }
 }
 
@@ -122,7 +209,10 @@ impl<'a, R: Read + Seek + Send> LASReader<'a, R> {
                 point_layout_matches_memory_layout,
             )?)
         };
-        Ok(Self { raw_reader })
+        Ok(Self {
+            raw_reader,
+            external_waveform_data: None,
+        })
     }
 
     pub fn remaining_points(&self) -> usize {
@@ -141,6 +231,138 @@ impl<'a, R: Read + Seek + Send> LASReader<'a, R> {
             LASReaderFlavor::LAZ(reader) => reader.las_metadata(),
         }
     }
+
+    /// Reads the payload of the given EVLR from the LAS file. Only the EVLR headers are read when opening a LAS
+    /// file, since EVLRs (e.g. waveform data packets) can be very large, so use this function to load the
+    /// data of a specific EVLR on demand. This does not change the current point position of the reader
+    ///
+    /// ```no_run
+    /// # use pasture_io::las::LASReader;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut reader = LASReader::from_path("file.las", false)?;
+    /// if let Some(evlr) = reader.las_metadata().find_evlr("pasture", 42).cloned() {
+    ///     let vlr = reader.read_evlr(&evlr)?;
+    ///     println!("EVLR has {} bytes of data", vlr.data.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the EVLR can't be read from the underlying reader, an error is returned
+    pub fn read_evlr(&mut self, evlr: &EvlrHeader) -> Result<Vlr> {
+        self.raw_reader.read_evlr(evlr)
+    }
+
+    /// Reads the first EVLR with the given `user_id` and `record_id` from the LAS file and parses it as the typed
+    /// record `T`, or returns `None` if no such EVLR exists (see [`LASMetadata::find_evlr`] and
+    /// [`LASReader::read_evlr`]). This does not change the current point position of the reader
+    ///
+    /// ```no_run
+    /// # use pasture_io::las::{LASReader, OgcCoordinateSystemWkt};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut reader = LASReader::from_path("file.las", false)?;
+    /// if let Some(crs) = reader.read_evlr_as::<OgcCoordinateSystemWkt>(
+    ///     OgcCoordinateSystemWkt::USER_ID,
+    ///     OgcCoordinateSystemWkt::RECORD_ID,
+    /// ) {
+    ///     println!("{}", crs?.wkt());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the EVLR can't be read from the underlying reader or can't be parsed as `T`, an error is returned
+    pub fn read_evlr_as<T>(&mut self, user_id: &str, record_id: u16) -> Option<Result<T>>
+    where
+        T: for<'v> TryFrom<&'v Vlr, Error = anyhow::Error>,
+    {
+        let evlr = self.las_metadata().find_evlr(user_id, record_id)?.clone();
+        Some(
+            self.read_evlr(&evlr)
+                .and_then(|vlr| T::try_from(&vlr))
+                .with_context(|| format!("Could not read EVLR {} {}", user_id, record_id)),
+        )
+    }
+
+    /// Sets the path of the external waveform data file (`.wdp`) from which waveform data packets are read
+    /// if the LAS file does not store its waveform data internally
+    ///
+    /// # Errors
+    ///
+    /// If the file at `path` cannot be opened, an error is returned
+    pub fn set_external_waveform_data_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let file = File::open(path.as_ref()).with_context(|| {
+            format!(
+                "Could not open waveform data file {}",
+                path.as_ref().display()
+            )
+        })?;
+        self.external_waveform_data = Some(BufReader::new(file));
+        Ok(())
+    }
+
+    /// Reads the waveforms of all points in the given range. Waveform data packets are read from the
+    /// internal waveform data packets EVLR if it exists, or from the external waveform data file otherwise.
+    /// Returns one entry per point, which is `None` for points that have no waveform. The range is clamped
+    /// to the number of points in the file. This does not change the current point position of the reader
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the point format of the LAS file has no waveform attributes, if a point references
+    /// a missing Waveform Packet Descriptor, if the waveform data packets can't be found, or if the samples
+    /// of a waveform can't be decoded
+    pub fn read_waveforms(&mut self, points: Range<usize>) -> Result<Vec<Option<Waveform>>> {
+        if !self.header().point_format().has_waveform {
+            bail!(
+                "LAS point format {:?} has no waveform attributes",
+                self.header().point_format()
+            );
+        }
+
+        let current_position = self.raw_reader.seek_point(SeekFrom::Current(0))?;
+        let start = self
+            .raw_reader
+            .seek_point(SeekFrom::Start(points.start as u64))?;
+        let count = points
+            .end
+            .saturating_sub(start)
+            .min(self.remaining_points());
+        let mut waveform_attributes =
+            VectorBuffer::new_from_layout(WaveformPointAttributes::layout());
+        waveform_attributes.resize(count);
+        let read_result = self.raw_reader.read_into(&mut waveform_attributes, count);
+        self.raw_reader
+            .seek_point(SeekFrom::Start(current_position as u64))?;
+        read_result?;
+
+        let metadata = self.las_metadata();
+        let descriptors = metadata.waveform_packet_descriptors().to_vec();
+        let waveform_data_evlr = metadata
+            .find_evlr(
+                WAVEFORM_DATA_PACKETS_USER_ID,
+                WAVEFORM_DATA_PACKETS_RECORD_ID,
+            )
+            .cloned();
+        let raw_reader = &mut self.raw_reader;
+        decode_waveforms(
+            &waveform_attributes,
+            &descriptors,
+            waveform_data_evlr.as_ref(),
+            |position, buffer| raw_reader.read_bytes_at(position, buffer),
+            self.external_waveform_data.as_mut(),
+        )
+    }
+
+    /// Reads the waveform of the point at `point_index`, or returns `None` if the point has no waveform.
+    /// See [`LASReader::read_waveforms`] for more information
+    pub fn read_waveform(&mut self, point_index: usize) -> Result<Option<Waveform>> {
+        let mut waveforms = self.read_waveforms(point_index..point_index + 1)?;
+        Ok(waveforms.pop().flatten())
+    }
 }
 
 impl<'a, R: Read + Seek + Send + 'a> PointReader for LASReader<'a, R> {
@@ -169,3 +391,107 @@ impl<'a, R: Read + Seek + Send + 'a> SeekToPoint for LASReader<'a, R> {
         self.raw_reader.seek_point(position)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use las_rs::point::Format;
+    use pasture_core::containers::{BorrowedBuffer, InterleavedBuffer};
+    use pasture_core::layout::attributes::INTENSITY;
+    use pasture_core::nalgebra::Vector3;
+    use scopeguard::defer;
+    use std::path::PathBuf;
+
+    use crate::base::PointWriter;
+    use crate::las::{
+        get_test_las_path, get_test_las_path_with_extra_bytes, get_test_laz_path,
+        point_layout_from_las_point_format, test_data_intensities, test_data_point_count,
+        test_data_positions, LASWriter, LasPointFormat0, ATTRIBUTE_LOCAL_LAS_POSITION,
+    };
+
+    use super::*;
+
+    fn test_map_las_file(path: &Path) -> Result<()> {
+        let mapped_points = LASReader::map_from_path(path)?;
+        let mut reader = LASReader::from_path(path, true)?;
+        assert_eq!(
+            reader.get_default_point_layout(),
+            mapped_points.point_layout()
+        );
+        assert_eq!(test_data_point_count(), mapped_points.len());
+
+        let read_points = reader.read::<VectorBuffer>(test_data_point_count())?;
+        assert_eq!(
+            read_points.get_point_range_ref(0..read_points.len()),
+            mapped_points.get_point_range_ref(0..mapped_points.len())
+        );
+
+        let intensities = mapped_points
+            .view_attribute::<u16>(&INTENSITY)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(test_data_intensities(), intensities);
+
+        let header = reader.header();
+        let transforms = header.transforms();
+        let positions = mapped_points
+            .view_attribute::<Vector3<i32>>(&ATTRIBUTE_LOCAL_LAS_POSITION)
+            .into_iter()
+            .map(|position| {
+                Vector3::new(
+                    (position.x as f64 * transforms.x.scale) + transforms.x.offset,
+                    (position.y as f64 * transforms.y.scale) + transforms.y.offset,
+                    (position.z as f64 * transforms.z.scale) + transforms.z.offset,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(test_data_positions(), positions);
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_las_from_path() -> Result<()> {
+        for format in 0..=10 {
+            let exact_layout = point_layout_from_las_point_format(&Format::new(format)?, true)?;
+            let path = get_test_las_path(format);
+            assert_eq!(
+                &exact_layout,
+                LASReader::map_from_path(&path)?.point_layout(),
+                "Wrong PointLayout for point format {}",
+                format
+            );
+            test_map_las_file(&path)
+                .with_context(|| format!("Test failed for point format {}", format))?;
+            test_map_las_file(&get_test_las_path_with_extra_bytes(format)).with_context(|| {
+                format!("Test failed for point format {} with extra bytes", format)
+            })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_empty_las_file() -> Result<()> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_map_empty_las_file.las");
+        defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+        {
+            let mut writer =
+                LASWriter::from_path_and_point_layout(&path, &LasPointFormat0::layout())?;
+            writer.flush()?;
+        }
+
+        let mapped_points = LASReader::map_from_path(&path)?;
+        assert_eq!(0, mapped_points.len());
+        assert_eq!(
+            &point_layout_from_las_point_format(&Format::new(0)?, true)?,
+            mapped_points.point_layout()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_laz_from_path_fails() {
+        assert!(LASReader::map_from_path(get_test_laz_path(0)).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use anyhow::{anyhow, bail, Context, Result};
+use pasture_core::containers::BorrowedBuffer;
+use pasture_core::nalgebra::Vector3;
+use pasture_derive::PointType;
+
+use super::{EvlrHeader, WaveformPacketDescriptor};
+
+/// The user ID of the EVLR that stores waveform data packets within a LAS file
+pub const WAVEFORM_DATA_PACKETS_USER_ID: &str = "LASF_Spec";
+/// The record ID of the EVLR that stores waveform data packets within a LAS file
+pub const WAVEFORM_DATA_PACKETS_RECORD_ID: u16 = 65535;
+/// Waveform data offsets are relative to the start of the waveform data packets EVLR header (or the start of
+/// an external `.wdp` file, which starts with the same header), which has this size in bytes
+const WAVEFORM_DATA_PACKETS_HEADER_SIZE: u64 = 60;
+
+/// A single decoded waveform of a LAS point
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waveform {
+    /// The descriptor that describes how the samples of this waveform were stored
+    pub descriptor: WaveformPacketDescriptor,
+    /// Temporal offset in picoseconds from the first sample of the waveform to the location of the point
+    pub return_point_location: f32,
+    /// Parameters that define the parametric line of the waveform, see the LAS specification
+    pub parameters: Vector3<f32>,
+    /// The decoded samples of the waveform. Digitizer gain and offset are already applied to these values
+    pub samples: Vec<f64>,
+}
+
+/// All LAS point attributes that are required to resolve the waveform of a point
+#[repr(C, packed)]
+#[derive(Debug, Copy, Clone, PointType, bytemuck::AnyBitPattern, bytemuck::NoUninit)]
+pub(crate) struct WaveformPointAttributes {
+    #[pasture(BUILTIN_WAVE_PACKET_DESCRIPTOR_INDEX)]
+    pub wave_packet_descriptor_index: u8,
+    #[pasture(BUILTIN_WAVEFORM_DATA_OFFSET)]
+    pub byte_offset_to_waveform_data: u64,
+    #[pasture(BUILTIN_WAVEFORM_PACKET_SIZE)]
+    pub waveform_packet_size: u32,
+    #[pasture(BUILTIN_RETURN_POINT_WAVEFORM_LOCATION)]
+    pub return_point_waveform_location: f32,
+    #[pasture(BUILTIN_WAVEFORM_PARAMETERS)]
+    pub waveform_parameters: Vector3<f32>,
+}
+
+/// Reads the raw bytes of the waveform packet at `offset` with the given `size`. If the LAS file has an internal
+/// waveform data packets EVLR (described by `waveform_data_evlr`), the packet is read directly from the LAS file using
+/// `read_from_las_file`, which reads bytes at an absolute position within the LAS file. Otherwise, the packet is read
+/// from `external_waveform_data`
+fn read_waveform_packet<R: Read + Seek, F: FnMut(u64, &mut [u8]) -> Result<()>>(
+    waveform_data_evlr: Option<&EvlrHeader>,
+    read_from_las_file: &mut F,
+    external_waveform_data: Option<&mut R>,
+    offset: u64,
+    size: u32,
+) -> Result<Vec<u8>> {
+    let mut packet = vec![0; size as usize];
+    if let Some(waveform_data_evlr) = waveform_data_evlr {
+        let start = offset
+            .checked_sub(WAVEFORM_DATA_PACKETS_HEADER_SIZE)
+            .ok_or_else(|| anyhow!("Invalid waveform data offset {offset}"))?;
+        if start + size as u64 > waveform_data_evlr.data_length {
+            bail!("Waveform packet at offset {offset} with size {size} is out of bounds of the waveform data packets EVLR");
+        }
+        read_from_las_file(waveform_data_evlr.data_offset + start, &mut packet)
+            .with_context(|| format!("Failed to read waveform packet at offset {offset}"))?;
+        return Ok(packet);
+    }
+
+    let external_waveform_data = external_waveform_data.ok_or_else(|| {
+        anyhow!("LAS file has neither internal waveform data packets nor an external waveform data file")
+    })?;
+    external_waveform_data.seek(SeekFrom::Start(offset))?;
+    external_waveform_data
+        .read_exact(&mut packet)
+        .with_context(|| format!("Failed to read waveform packet at offset {offset}"))?;
+    Ok(packet)
+}
+
+/// Decodes the waveforms of all points in `points`, which must have the `PointLayout` of `WaveformPointAttributes`.
+/// Points without a waveform (i.e. with a wave packet descriptor index of zero) yield `None`. See
+/// `read_waveform_packet` for how the waveform data packets are read
+pub(crate) fn decode_waveforms<'a, B, R, F>(
+    points: &'a B,
+    descriptors: &[WaveformPacketDescriptor],
+    waveform_data_evlr: Option<&EvlrHeader>,
+    mut read_from_las_file: F,
+    mut external_waveform_data: Option<&mut R>,
+) -> Result<Vec<Option<Waveform>>>
+where
+    B: BorrowedBuffer<'a>,
+    R: Read + Seek,
+    F: FnMut(u64, &mut [u8]) -> Result<()>,
+{
+    points
+        .view::<WaveformPointAttributes>()
+        .into_iter()
+        .map(|point| {
+            let descriptor_index = point.wave_packet_descriptor_index;
+            if descriptor_index == 0 {
+                return Ok(None);
+            }
+            let descriptor = descriptors
+                .iter()
+                .find(|descriptor| descriptor.index == descriptor_index)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Waveform Packet Descriptor VLR with index {descriptor_index} not found"
+                    )
+                })?;
+            let packet = read_waveform_packet(
+                waveform_data_evlr,
+                &mut read_from_las_file,
+                external_waveform_data.as_deref_mut(),
+                point.byte_offset_to_waveform_data,
+                point.waveform_packet_size,
+            )?;
+            Ok(Some(Waveform {
+                descriptor: *descriptor,
+                return_point_location: point.return_point_waveform_location,
+                parameters: point.waveform_parameters,
+                samples: descriptor.decode_samples(&packet)?,
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::fs::File;
+    use std::io::{BufWriter, Cursor, Write};
+    use std::path::PathBuf;
+
+    use las_rs::{point::Format, Builder, Vlr};
+    use pasture_core::containers::VectorBuffer;
+    use scopeguard::defer;
+
+    use super::*;
+    use crate::base::{PointWriter, SeekToPoint};
+    use crate::las::{LASReader, LASWriter, LasPointFormat4};
+
+    const NUM_SAMPLES: u32 = 4;
+
+    fn test_descriptor() -> WaveformPacketDescriptor {
+        WaveformPacketDescriptor {
+            index: 1,
+            bits_per_sample: 8,
+            compression_type: 0,
+            number_of_samples: NUM_SAMPLES,
+            temporal_sample_spacing: 1000,
+            digitizer_gain: 0.5,
+            digitizer_offset: 1.0,
+        }
+    }
+
+    /// Returns test points, the waveform data packets and the expected waveforms. The third point has no waveform
+    fn get_test_data() -> (Vec<LasPointFormat4>, Vec<u8>, Vec<Option<Waveform>>) {
+        let mut points = vec![];
+        let mut waveform_data = vec![];
+        let mut expected_waveforms = vec![];
+        for index in 0..4_u8 {
+            let has_waveform = index != 2;
+            let packet = (0..NUM_SAMPLES as u8)
+                .map(|sample| index * 10 + sample)
+                .collect::<Vec<_>>();
+            points.push(LasPointFormat4 {
+                position: Vector3::new(index as f64, 0.0, 0.0),
+                wave_packet_descriptor_index: has_waveform as u8,
+                byte_offset_to_waveform_data: WAVEFORM_DATA_PACKETS_HEADER_SIZE
+                    + waveform_data.len() as u64,
+                waveform_packet_size: NUM_SAMPLES,
+                return_point_waveform_location: index as f32,
+                waveform_parameters: Vector3::new(1.0, 2.0, index as f32),
+                ..Default::default()
+            });
+            expected_waveforms.push(has_waveform.then(|| {
+                Waveform {
+                    descriptor: test_descriptor(),
+                    return_point_location: index as f32,
+                    parameters: Vector3::new(1.0, 2.0, index as f32),
+                    samples: packet
+                        .iter()
+                        .map(|sample| 0.5 * *sample as f64 + 1.0)
+                        .collect(),
+                }
+            }));
+            waveform_data.extend_from_slice(&packet);
+        }
+        (points, waveform_data, expected_waveforms)
+    }
+
+    fn waveform_data_packets_evlr(waveform_data: Vec<u8>) -> Vlr {
+        Vlr {
+            user_id: WAVEFORM_DATA_PACKETS_USER_ID.to_owned(),
+            record_id: WAVEFORM_DATA_PACKETS_RECORD_ID,
+            description: "Waveform data packets".to_owned(),
+            data: waveform_data,
+        }
+    }
+
+    fn test_header(point_format: u8, waveform_data_evlr: Option<Vlr>) -> Result<las_rs::Header> {
+        let mut header_builder = Builder::from((1, 4));
+        header_builder.point_format = Format::new(point_format)?;
+        header_builder.vlrs.push((&test_descriptor()).into());
+        header_builder.evlrs.extend(waveform_data_evlr);
+        Ok(header_builder.into_header()?)
+    }
+
+    #[test]
+    fn test_waveform_packet_descriptor_round_trip() -> Result<()> {
+        let descriptor = WaveformPacketDescriptor {
+            index: 3,
+            bits_per_sample: 16,
+            ..test_descriptor()
+        };
+        let vlr: Vlr = (&descriptor).into();
+        assert_eq!(102, vlr.record_id);
+        assert_eq!(descriptor, WaveformPacketDescriptor::try_from(&vlr)?);
+
+        let samples = descriptor.decode_samples(&[1, 0, 0, 1, 2, 0, 255, 255])?;
+        assert_eq!(vec![1.5, 129.0, 2.0, 32768.5], samples);
+        assert!(descriptor.decode_samples(&[1, 2, 3]).is_err());
+        Ok(())
+    }
+
+    /// Writes the test points with an internal waveform data packets EVLR in the given point format (which is converted
+    /// from `LasPointFormat4`) and checks that the waveforms can be read back
+    fn test_read_waveforms_internal_with_format(
+        point_format: u8,
+        is_compressed: bool,
+    ) -> Result<()> {
+        let (points, waveform_data, expected_waveforms) = get_test_data();
+        let header = test_header(
+            point_format,
+            Some(waveform_data_packets_evlr(waveform_data)),
+        )?;
+
+        let mut writer =
+            LASWriter::from_writer_and_header(Cursor::new(vec![]), header, is_compressed)?;
+        writer.write(&points.iter().copied().collect::<VectorBuffer>())?;
+        let mut data = writer.into_inner()?;
+        data.set_position(0);
+
+        let mut reader = LASReader::from_read(data, is_compressed, false)?;
+        assert_eq!(
+            &[test_descriptor()],
+            reader.las_metadata().waveform_packet_descriptors()
+        );
+        assert_eq!(expected_waveforms, reader.read_waveforms(0..points.len())?);
+        assert_eq!(expected_waveforms[1], reader.read_waveform(1)?);
+        assert_eq!(expected_waveforms[2..], reader.read_waveforms(2..10)?[..]);
+        // Reading waveforms must not change the position of the reader
+        assert_eq!(points.len(), reader.remaining_points());
+        reader.seek_point(SeekFrom::Start(3))?;
+        assert_eq!(expected_waveforms[..2], reader.read_waveforms(0..2)?[..]);
+        assert_eq!(points.len() - 3, reader.remaining_points());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_waveforms_internal() -> Result<()> {
+        for point_format in [4, 5, 9, 10] {
+            test_read_waveforms_internal_with_format(point_format, false).with_context(|| {
+                format!("Reading waveforms from LAS format {point_format} failed")
+            })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_waveforms_internal_laz() -> Result<()> {
+        // LAZ files with the formats 9 and 10 are not supported, see `RawLAZReader`
+        for point_format in [4, 5] {
+            test_read_waveforms_internal_with_format(point_format, true).with_context(|| {
+                format!("Reading waveforms from LAZ format {point_format} failed")
+            })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_waveforms_external() -> Result<()> {
+        let (points, waveform_data, expected_waveforms) = get_test_data();
+
+        let mut las_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        las_path.push("test_read_waveforms_external.las");
+        let wdp_path = las_path.with_extension("wdp");
+        defer! {
+            std::fs::remove_file(&las_path).expect("Removing test file failed!");
+            std::fs::remove_file(&wdp_path).expect("Removing test file failed!");
+        }
+
+        {
+            let mut writer = LASWriter::from_path_and_header(&las_path, test_header(4, None)?)?;
+            writer.write(&points.iter().copied().collect::<VectorBuffer>())?;
+            writer.flush()?;
+        }
+        {
+            // External waveform data files start with the same header as the waveform data packets EVLR
+            let mut wdp_writer = BufWriter::new(File::create(&wdp_path)?);
+            waveform_data_packets_evlr(waveform_data)
+                .into_raw(true)?
+                .write_to(&mut wdp_writer)?;
+            wdp_writer.flush()?;
+        }
+
+        let mut reader = LASReader::from_path(&las_path, false)?;
+        assert_eq!(expected_waveforms, reader.read_waveforms(0..points.len())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_waveforms_without_waveform_data() -> Result<()> {
+        let (points, _, _) = get_test_data();
+        let mut writer =
+            LASWriter::from_writer_and_header(Cursor::new(vec![]), test_header(4, None)?, false)?;
+        writer.write(&points.iter().copied().collect::<VectorBuffer>())?;
+        let mut data = writer.into_inner()?;
+        data.set_position(0);
+
+        let mut reader = LASReader::from_read(data, false, false)?;
+        assert!(reader.read_waveforms(0..2).is_err());
+        Ok(())
+    }
+}
@@ -1,13 +1,50 @@
 #![allow(clippy::upper_case_acronyms)]
-use std::{fs::File, io::BufWriter, io::Seek, io::Write, path::Path};
+use std::{convert::TryInto, fs::File, io::BufWriter, io::Seek, io::Write, path::Path};
 
 use anyhow::{Context, Result};
-use las_rs::Builder;
+use las_rs::{Builder, Vlr};
 use pasture_core::{containers::BorrowedBuffer, layout::PointLayout};
 
 use crate::{base::PointWriter, las::las_point_format_from_point_layout};
 
-use super::{path_is_compressed_las_file, RawLASWriter, RawLAZWriter};
+use super::{
+    extra_bytes_vlr_from_point_layout, path_is_compressed_las_file, RawLASWriter, RawLAZWriter,
+};
+
+/// Returns a `Builder` for a default LAS 1.4 header that matches the given `point_layout`. The header uses the best matching
+/// LAS point format, a scale of 0.001 and an Extra Bytes VLR for all attributes that can be written as LAS extra bytes
+pub(crate) fn las_header_builder_from_point_layout(point_layout: &PointLayout) -> Result<Builder> {
+    let mut point_format = las_point_format_from_point_layout(point_layout);
+    let mut header_builder = Builder::from((1, 4));
+    if let Some(extra_bytes_vlr) = extra_bytes_vlr_from_point_layout(point_layout) {
+        point_format.extra_bytes = extra_bytes_vlr
+            .entries()
+            .iter()
+            .map(|entry| entry.data_type().size().unwrap_or_default() as u16)
+            .sum();
+        header_builder.vlrs.push(
+            (&extra_bytes_vlr)
+                .try_into()
+                .context("Could not create Extra Bytes VLR")?,
+        );
+    }
+    header_builder.point_format = point_format;
+    header_builder.transforms = las_rs::Vector {
+        x: las_rs::Transform {
+            offset: 0.0,
+            scale: 0.001,
+        },
+        y: las_rs::Transform {
+            offset: 0.0,
+            scale: 0.001,
+        },
+        z: las_rs::Transform {
+            offset: 0.0,
+            scale: 0.001,
+        },
+    };
+    Ok(header_builder)
+}
 
 enum WriterVariant<T: Write + Seek + Send + 'static> {
     LAS(RawLASWriter<T>),
@@ -28,29 +65,18 @@ impl<T: Write + Seek + Send + 'static> LASWriter<T> {
     /// with an appropriate point format determined from the given `point_layout`. The LAS header uses a scale
     /// of 0.001, which yields 1mm precision. LAS version 1.4 is used.
     /// If `is_compressed` is set, the writer will write compressed `LAZ` files instead of `LAS` files.
+    ///
+    /// All attributes in `point_layout` that are no known LAS attributes but have a scalar datatype are written
+    /// as LAS extra bytes, together with a matching Extra Bytes VLR (see [`extra_bytes_vlr_from_point_layout`]).
+    /// All other attributes that LAS does not support are ignored. If you need more control over the extra bytes
+    /// (e.g. scale and offset values), use [`from_writer_and_header`](Self::from_writer_and_header) with a header
+    /// that contains a custom [`ExtraBytesVlr`](super::ExtraBytesVlr)
     pub fn from_writer_and_point_layout(
         writer: T,
         point_layout: &PointLayout,
         is_compressed: bool,
     ) -> Result<Self> {
-        // TODO Support writing extra bytes, for now they will be ignored
-        let point_format = las_point_format_from_point_layout(point_layout);
-        let mut header_builder = Builder::from((1, 4));
-        header_builder.point_format = point_format;
-        header_builder.transforms = las_rs::Vector {
-            x: las_rs::Transform {
-                offset: 0.0,
-                scale: 0.001,
-            },
-            y: las_rs::Transform {
-                offset: 0.0,
-                scale: 0.001,
-            },
-            z: las_rs::Transform {
-                offset: 0.0,
-                scale: 0.001,
-            },
-        };
+        let header_builder = las_header_builder_from_point_layout(point_layout)?;
         let las_header = header_builder
             .into_header()
             .context("Could not default-create LAS header")?;
@@ -72,6 +98,21 @@ impl<T: Write + Seek + Send + 'static> LASWriter<T> {
         Ok(Self { writer: raw_writer })
     }
 
+    /// Adds the given extended VLR (EVLR) to this `LASWriter`. EVLRs are written after the point records when calling
+    /// `flush`, and their location is recorded in the LAS header. This makes it possible to add EVLRs whose contents
+    /// depend on the point data that was written. EVLRs that are known upfront can also be passed through the header
+    /// in [`from_writer_and_header`](Self::from_writer_and_header).
+    ///
+    /// # Errors
+    ///
+    /// EVLRs require LAS version 1.4. If the LAS header of this `LASWriter` has an older version, an error is returned
+    pub fn add_evlr(&mut self, evlr: Vlr) -> Result<()> {
+        match &mut self.writer {
+            WriterVariant::LAS(writer) => writer.add_evlr(evlr),
+            WriterVariant::LAZ(writer) => writer.add_evlr(evlr),
+        }
+    }
+
     /// Unwraps with LASWriter, returning the underlying write type `T`. All internal data is flushed before returning
     /// the writer
     pub fn into_inner(self) -> Result<T> {
@@ -126,12 +167,12 @@ impl<T: Write + Seek + Send + 'static> PointWriter for LASWriter<T> {
 
 #[cfg(test)]
 mod tests {
-    use std::{io::Cursor, path::PathBuf};
+    use std::{borrow::Cow, io::Cursor, path::PathBuf};
 
     use las::{point::Format, Builder};
     use pasture_core::{
         containers::{MakeBufferFromLayout, OwningBuffer, VectorBuffer},
-        layout::PointType,
+        layout::{PointAttributeDataType, PointAttributeDefinition, PointType},
         nalgebra::Vector3,
     };
     use scopeguard::defer;
@@ -140,7 +181,7 @@ mod tests {
         base::PointReader,
         las::{
             LASReader, LasPointFormat0, LasPointFormat1, LasPointFormat2, LasPointFormat3,
-            LasPointFormat4, LasPointFormat5,
+            LasPointFormat4, LasPointFormat5, OgcCoordinateSystemWkt,
         },
     };
     use pasture_derive::PointType;
@@ -169,6 +210,32 @@ mod tests {
         ]
     }
 
+    #[repr(C, packed)]
+    #[derive(Debug, Clone, Copy, PointType, bytemuck::AnyBitPattern, bytemuck::NoUninit)]
+    struct TestPointWithExtraBytes {
+        #[pasture(BUILTIN_POSITION_3D)]
+        pub position: Vector3<f64>,
+        #[pasture(attribute = "Reflectance")]
+        pub reflectance: f32,
+        #[pasture(attribute = "SegmentID")]
+        pub segment_id: u16,
+    }
+
+    fn get_test_points_with_extra_bytes() -> Vec<TestPointWithExtraBytes> {
+        vec![
+            TestPointWithExtraBytes {
+                position: Vector3::new(1.0, 2.0, 3.0),
+                reflectance: 0.25,
+                segment_id: 7,
+            },
+            TestPointWithExtraBytes {
+                position: Vector3::new(4.0, 5.0, 6.0),
+                reflectance: -12.5,
+                segment_id: 1024,
+            },
+        ]
+    }
+
     fn get_test_points_las_format_0() -> Vec<LasPointFormat0> {
         vec![
             LasPointFormat0 {
@@ -1242,4 +1309,177 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_extra_bytes_from_point_layout() -> Result<()> {
+        let source_points = get_test_points_with_extra_bytes();
+        let source_point_buffer = prepare_point_buffer(&source_points);
+
+        let reflectance_attribute = PointAttributeDefinition::custom(
+            Cow::Borrowed("Reflectance"),
+            PointAttributeDataType::F32,
+        );
+        let segment_id_attribute = PointAttributeDefinition::custom(
+            Cow::Borrowed("SegmentID"),
+            PointAttributeDataType::U16,
+        );
+
+        for is_compressed in [false, true] {
+            let mut writer = LASWriter::from_writer_and_point_layout(
+                Cursor::new(Vec::<u8>::new()),
+                source_point_buffer.point_layout(),
+                is_compressed,
+            )?;
+            writer.write(&source_point_buffer)?;
+            let data = writer.into_inner()?.into_inner();
+
+            let mut reader = LASReader::from_read(Cursor::new(data), is_compressed, false)?;
+            assert_eq!(6, reader.header().point_format().extra_bytes);
+            let extra_bytes_vlr = reader
+                .las_metadata()
+                .extra_bytes_vlr()
+                .expect("Extra Bytes VLR is missing");
+            assert_eq!(2, extra_bytes_vlr.entries().len());
+            assert_eq!("Reflectance", extra_bytes_vlr.entries()[0].name());
+            assert_eq!("SegmentID", extra_bytes_vlr.entries()[1].name());
+            assert_eq!(-12.5, extra_bytes_vlr.entries()[0].min_as_float()?);
+            assert_eq!(0.25, extra_bytes_vlr.entries()[0].max_as_float()?);
+            assert_eq!(7, extra_bytes_vlr.entries()[1].min_as_unsigned()?);
+            assert_eq!(1024, extra_bytes_vlr.entries()[1].max_as_unsigned()?);
+
+            let read_points = reader.read::<VectorBuffer>(source_points.len())?;
+            assert!(read_points
+                .point_layout()
+                .has_attribute(&reflectance_attribute));
+            assert!(read_points
+                .point_layout()
+                .has_attribute(&segment_id_attribute));
+
+            let expected_reflectances = source_points
+                .iter()
+                .map(|point| point.reflectance)
+                .collect::<Vec<_>>();
+            let actual_reflectances = read_points
+                .view_attribute::<f32>(&reflectance_attribute)
+                .into_iter()
+                .collect::<Vec<_>>();
+            assert_eq!(expected_reflectances, actual_reflectances);
+
+            let expected_segment_ids = source_points
+                .iter()
+                .map(|point| point.segment_id)
+                .collect::<Vec<_>>();
+            let actual_segment_ids = read_points
+                .view_attribute::<u16>(&segment_id_attribute)
+                .into_iter()
+                .collect::<Vec<_>>();
+            assert_eq!(expected_segment_ids, actual_segment_ids);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_extra_bytes_with_too_long_name_fails() {
+        let long_name_attribute = PointAttributeDefinition::custom(
+            Cow::Borrowed("AnAttributeNameThatIsLongerThan32Bytes"),
+            PointAttributeDataType::U16,
+        );
+        let layout = PointLayout::from_attributes(&[
+            pasture_core::layout::attributes::POSITION_3D,
+            long_name_attribute,
+        ]);
+        for is_compressed in [false, true] {
+            assert!(LASWriter::from_writer_and_point_layout(
+                Cursor::new(Vec::<u8>::new()),
+                &layout,
+                is_compressed
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_write_evlrs() -> Result<()> {
+        let source_points = get_test_points_las_format_0();
+        let source_point_buffer = prepare_point_buffer(&source_points);
+
+        let crs = OgcCoordinateSystemWkt::new("LOCAL_CS[\"Test\"]".to_owned());
+        let custom_evlr = Vlr {
+            user_id: "pasture".to_owned(),
+            record_id: 42,
+            description: "Test EVLR".to_owned(),
+            data: vec![1, 2, 3, 4],
+        };
+
+        for is_compressed in [false, true] {
+            let mut las_header_builder = Builder::from((1, 4));
+            las_header_builder.point_format = Format::new(0)?;
+            las_header_builder.evlrs.push((&crs).into());
+
+            let mut writer = LASWriter::from_writer_and_header(
+                Cursor::new(Vec::<u8>::new()),
+                las_header_builder.into_header().unwrap(),
+                is_compressed,
+            )?;
+            writer.write(&source_point_buffer)?;
+            writer.add_evlr(custom_evlr.clone())?;
+            let data = writer.into_inner()?.into_inner();
+
+            let mut reader = LASReader::from_read(Cursor::new(data), is_compressed, false)?;
+            let metadata = reader.las_metadata().clone();
+            assert_eq!(2, metadata.evlrs().len());
+            assert_eq!(
+                crs.wkt(),
+                metadata
+                    .ogc_wkt_crs()
+                    .expect("OGC WKT EVLR is missing")
+                    .wkt()
+            );
+            let custom_evlr_header = metadata
+                .find_evlr("pasture", 42)
+                .expect("Custom EVLR is missing");
+            assert_eq!(
+                custom_evlr.data.len() as u64,
+                custom_evlr_header.data_length
+            );
+            let read_custom_evlr = reader.read_evlr(custom_evlr_header)?;
+            assert_eq!(custom_evlr, read_custom_evlr);
+            let read_crs = reader
+                .read_evlr_as::<OgcCoordinateSystemWkt>(
+                    OgcCoordinateSystemWkt::USER_ID,
+                    OgcCoordinateSystemWkt::RECORD_ID,
+                )
+                .expect("OGC WKT EVLR is missing")?;
+            assert_eq!(crs.wkt(), read_crs.wkt());
+            assert!(reader
+                .read_evlr_as::<OgcCoordinateSystemWkt>("pasture", 43)
+                .is_none());
+
+            let read_points = reader.read::<VectorBuffer>(source_points.len())?;
+            let read_points: Vec<LasPointFormat0> = read_points.view().into_iter().collect();
+            assert_eq!(source_points, read_points);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_evlr_requires_las_1_4() -> Result<()> {
+        let mut las_header_builder = Builder::from((1, 2));
+        las_header_builder.point_format = Format::new(0)?;
+        let mut writer = LASWriter::from_writer_and_header(
+            Cursor::new(Vec::<u8>::new()),
+            las_header_builder.into_header().unwrap(),
+            false,
+        )?;
+        let evlr = Vlr {
+            user_id: "pasture".to_owned(),
+            record_id: 42,
+            description: "Test EVLR".to_owned(),
+            data: vec![1, 2, 3, 4],
+        };
+        assert!(writer.add_evlr(evlr).is_err());
+        Ok(())
+    }
 }
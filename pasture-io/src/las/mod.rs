@@ -13,6 +13,9 @@ pub use self::las_types::*;
 mod las_metadata;
 pub use self::las_metadata::*;
 
+mod las_waveform;
+pub use self::las_waveform::*;
+
 mod raw_readers;
 pub(crate) use self::raw_readers::*;
 
@@ -1,4 +1,4 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::io::{Read, Seek, SeekFrom};
 
 use anyhow::{anyhow, bail, Context, Result};
@@ -16,7 +16,8 @@ use pasture_core::nalgebra::Vector3;
 use pasture_core::{layout::PointLayout, meta::Metadata};
 
 use super::{
-    map_laz_err, point_layout_from_las_metadata, LASMetadata, ATTRIBUTE_LOCAL_LAS_POSITION,
+    map_laz_err, point_layout_from_las_metadata, EvlrHeader, LASMetadata, OgcCoordinateSystemWkt,
+    ATTRIBUTE_LOCAL_LAS_POSITION,
 };
 use crate::base::{PointReader, SeekToPoint};
 use crate::las::{ATTRIBUTE_BASIC_FLAGS, ATTRIBUTE_EXTENDED_FLAGS};
@@ -26,9 +27,70 @@ fn is_laszip_vlr(vlr: &Vlr) -> bool {
     vlr.user_id == laz::LazVlr::USER_ID && vlr.record_id == laz::LazVlr::RECORD_ID
 }
 
+/// Reads the headers of all EVLRs that the given `evlr_info` of a raw LAS header refers to and stores them in the given
+/// `metadata`. The payloads of the EVLRs are not read, as they can be very large (e.g. waveform data packets). The only
+/// exception is the OGC WKT coordinate system record, which is part of the `LASMetadata`. This changes the position of
+/// `reader`!
+pub(crate) fn read_evlrs_into_metadata<R: Read + Seek>(
+    reader: &mut R,
+    evlr_info: Option<&raw::header::Evlr>,
+    metadata: &mut LASMetadata,
+) -> Result<()> {
+    let evlr_info = match evlr_info {
+        Some(evlr_info) if evlr_info.number_of_evlrs > 0 => evlr_info,
+        _ => return Ok(()),
+    };
+
+    let mut evlrs = Vec::with_capacity(evlr_info.number_of_evlrs as usize);
+    let mut position = evlr_info.start_of_first_evlr;
+    for _ in 0..evlr_info.number_of_evlrs {
+        reader.seek(SeekFrom::Start(position))?;
+        let evlr =
+            EvlrHeader::read_from(&mut *reader, position).context("Failed to read EVLR header")?;
+        position = evlr.data_offset + evlr.data_length;
+        evlrs.push(evlr);
+    }
+
+    if metadata.ogc_wkt_crs().is_none() {
+        if let Some(crs_evlr) = evlrs.iter().find(|evlr| {
+            evlr.user_id == OgcCoordinateSystemWkt::USER_ID
+                && evlr.record_id == OgcCoordinateSystemWkt::RECORD_ID
+        }) {
+            let crs = OgcCoordinateSystemWkt::try_from(&crs_evlr.read_payload(reader)?)
+                .context("Could not parse OGC WKT coordinate system record")?;
+            metadata.set_ogc_wkt_crs(crs);
+        }
+    }
+    metadata.set_evlrs(evlrs);
+    Ok(())
+}
+
+/// Reads the bytes at `position` within `reader` into `buffer` and restores the position of `reader` afterwards
+pub(crate) fn read_bytes_at<R: Read + Seek>(
+    reader: &mut R,
+    position: u64,
+    buffer: &mut [u8],
+) -> Result<()> {
+    let current_position = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(position))?;
+    let result = reader
+        .read_exact(buffer)
+        .with_context(|| format!("Failed to read {} bytes at offset {position}", buffer.len()));
+    reader.seek(SeekFrom::Start(current_position))?;
+    result
+}
+
+/// Reads the payload of the given `evlr` from `reader` and restores the position of `reader` afterwards
+fn read_evlr_with_payload<R: Read + Seek>(reader: &mut R, evlr: &EvlrHeader) -> Result<Vlr> {
+    let current_position = reader.stream_position()?;
+    let evlr = evlr.read_payload(reader);
+    reader.seek(SeekFrom::Start(current_position))?;
+    evlr
+}
+
 /// Returns a `BufferLayoutConverter` that performs a conversion from the given raw LAS `PointLayout` into
 /// the given `target_layout`
-fn get_default_las_converter<'a>(
+pub(crate) fn get_default_las_converter<'a>(
     raw_las_layout: &'a PointLayout,
     target_layout: &'a PointLayout,
     las_header: &Header,
@@ -192,6 +254,7 @@ impl<T: Read + Seek> RawLASReader<T> {
         let raw_header = raw::Header::read_from(&mut reader)?;
         let offset_to_first_point_in_file = raw_header.offset_to_point_data as u64;
         let size_of_point_in_file = raw_header.point_data_record_length as u64;
+        let evlr_info = raw_header.evlr;
 
         // Manually read the VLRs
         reader.seek(SeekFrom::Start(raw_header.header_size as u64))?;
@@ -219,10 +282,11 @@ impl<T: Read + Seek> RawLASReader<T> {
 
         let header = builder.into_header().context("Invalid LAS header")?;
 
-        let metadata: LASMetadata = header
+        let mut metadata: LASMetadata = header
             .clone()
             .try_into()
             .context("Failed to parse LAS header")?;
+        read_evlrs_into_metadata(&mut reader, evlr_info.as_ref(), &mut metadata)?;
         let point_layout =
             point_layout_from_las_metadata(&metadata, point_layout_matches_memory_layout)?;
         let matching_memory_layout = point_layout_from_las_metadata(&metadata, true)?;
@@ -244,6 +308,17 @@ impl<T: Read + Seek> RawLASReader<T> {
         &self.metadata
     }
 
+    /// Reads the payload of the given `evlr`. This does not change the current point position
+    pub fn read_evlr(&mut self, evlr: &EvlrHeader) -> Result<Vlr> {
+        read_evlr_with_payload(&mut self.reader, evlr)
+    }
+
+    /// Reads the bytes at `position` within the LAS file into `buffer`. This does not change the current point
+    /// position
+    pub fn read_bytes_at(&mut self, position: u64, buffer: &mut [u8]) -> Result<()> {
+        read_bytes_at(&mut self.reader, position, buffer)
+    }
+
     fn read_into_default_layout<'a, 'b, B: BorrowedMutBuffer<'a>>(
         &mut self,
         point_buffer: &'b mut B,
@@ -416,11 +491,14 @@ impl<T: Read + Seek> SeekToPoint for RawLASReader<T> {
 }
 
 pub struct RawLAZReader<'a, T: Read + Seek + Send + 'a> {
-    reader: LasZipDecompressor<'a, T>,
+    // Only `None` if restarting the decompressor failed, see `seek_by_decompressing`
+    reader: Option<LasZipDecompressor<'a, T>>,
+    laz_vlr: laz::las::laszip::LazVlr,
     metadata: LASMetadata,
     layout: PointLayout,
     las_point_records_layout: PointLayout,
     current_point_index: usize,
+    offset_to_first_point_in_file: u64,
     size_of_point_in_file: u64,
 }
 
@@ -430,6 +508,7 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
         let offset_to_first_point_in_file = raw_header.offset_to_point_data as u64;
         let size_of_point_in_file = raw_header.point_data_record_length as u64;
         let number_of_vlrs = raw_header.number_of_variable_length_records;
+        let evlr_info = raw_header.evlr;
 
         let mut header_builder = Builder::new(raw_header)?;
         // Read VLRs
@@ -437,7 +516,6 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
             let vlr = las_rs::raw::Vlr::read_from(&mut read, false).map(Vlr::new)?;
             header_builder.vlrs.push(vlr);
         }
-        // TODO Read EVLRs
 
         // Put padding bytes into header (e.g. from leftover VLRs that have been deleted but not removed from the file)
         let position_after_reading_vlrs = read.stream_position()?;
@@ -454,10 +532,11 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
             ));
         }
 
-        let metadata: LASMetadata = header
+        let mut metadata: LASMetadata = header
             .clone()
             .try_into()
             .context("Could not parse LAS header")?;
+        read_evlrs_into_metadata(&mut read, evlr_info.as_ref(), &mut metadata)?;
         let point_layout =
             point_layout_from_las_metadata(&metadata, point_layout_matches_memory_layout)?;
         let matching_memory_layout = point_layout_from_las_metadata(&metadata, true)?;
@@ -474,14 +553,16 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
                 Ok(laz_record)
             }
         }?;
-        let reader = LasZipDecompressor::new(read, laszip_vlr).map_err(map_laz_err)?;
+        let reader = LasZipDecompressor::new(read, laszip_vlr.clone()).map_err(map_laz_err)?;
 
         Ok(Self {
-            reader,
+            reader: Some(reader),
+            laz_vlr: laszip_vlr,
             metadata,
             layout: point_layout,
             las_point_records_layout: matching_memory_layout,
             current_point_index: 0,
+            offset_to_first_point_in_file,
             size_of_point_in_file,
         })
     }
@@ -490,6 +571,54 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
         &self.metadata
     }
 
+    fn decompressor(&mut self) -> Result<&mut LasZipDecompressor<'a, T>> {
+        self.reader
+            .as_mut()
+            .ok_or_else(|| anyhow!("RawLAZReader is in an invalid state after a failed seek"))
+    }
+
+    /// Seeks to `point_index` without using `LasZipDecompressor::seek`, which is broken for point record formats 6
+    /// and higher (see https://github.com/laz-rs/laz-rs/issues/46). For seeking backwards, the decompressor is
+    /// restarted at the first point, after which all points up to `point_index` are decompressed and discarded
+    fn seek_by_decompressing(&mut self, point_index: usize) -> Result<()> {
+        if point_index < self.current_point_index {
+            let mut read = self
+                .reader
+                .take()
+                .ok_or_else(|| anyhow!("RawLAZReader is in an invalid state after a failed seek"))?
+                .into_inner();
+            read.seek(SeekFrom::Start(self.offset_to_first_point_in_file))?;
+            self.reader =
+                Some(LasZipDecompressor::new(read, self.laz_vlr.clone()).map_err(map_laz_err)?);
+            self.current_point_index = 0;
+        }
+
+        const MAX_POINTS_PER_CHUNK: usize = 1 << 12;
+        let size_of_point = self.size_of_point_in_file as usize;
+        let points_in_discard_buffer =
+            MAX_POINTS_PER_CHUNK.min(point_index - self.current_point_index);
+        let mut discard_buffer = vec![0; size_of_point * points_in_discard_buffer];
+        while self.current_point_index < point_index {
+            let num_points = MAX_POINTS_PER_CHUNK.min(point_index - self.current_point_index);
+            self.decompressor()?
+                .decompress_many(&mut discard_buffer[..num_points * size_of_point])
+                .context("Failed to skip point records")?;
+            self.current_point_index += num_points;
+        }
+        Ok(())
+    }
+
+    /// Reads the payload of the given `evlr`. This does not change the current point position
+    pub fn read_evlr(&mut self, evlr: &EvlrHeader) -> Result<Vlr> {
+        read_evlr_with_payload(self.decompressor()?.get_mut(), evlr)
+    }
+
+    /// Reads the bytes at `position` within the LAZ file into `buffer`. This does not change the current point
+    /// position
+    pub fn read_bytes_at(&mut self, position: u64, buffer: &mut [u8]) -> Result<()> {
+        read_bytes_at(self.decompressor()?.get_mut(), position, buffer)
+    }
+
     fn read_into_default_layout<'b, 'c, B: BorrowedMutBuffer<'b>>(
         &mut self,
         point_buffer: &'c mut B,
@@ -505,7 +634,7 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
 
         if let Some(interleaved_buffer) = point_buffer.as_interleaved_mut() {
             let new_point_data = interleaved_buffer.get_point_range_mut(0..num_points_to_read);
-            self.reader
+            self.decompressor()?
                 .decompress_many(new_point_data)
                 .context("Failed to read point records")?;
         } else {
@@ -524,7 +653,7 @@ impl<'a, T: Read + Seek + Send + 'a> RawLAZReader<'a, T> {
                     read_buffer.len()
                 };
                 let chunk_bytes = &mut read_buffer[..bytes_in_chunk];
-                self.reader
+                self.decompressor()?
                     .decompress_many(chunk_bytes)
                     .context("Failed to read chunk of points")?;
                 let first_point_in_chunk = chunk_idx * num_points_per_chunk;
@@ -626,8 +755,12 @@ impl<'a, T: Read + Seek + Send + 'a> SeekToPoint for RawLAZReader<'a, T> {
             std::cmp::min(self.metadata.point_count() as i64, new_position) as usize;
 
         if self.current_point_index != clamped_position {
-            self.reader.seek(clamped_position as u64)?;
-            self.current_point_index = clamped_position;
+            if self.metadata.point_format().is_extended {
+                self.seek_by_decompressing(clamped_position)?;
+            } else {
+                self.decompressor()?.seek(clamped_position as u64)?;
+                self.current_point_index = clamped_position;
+            }
         }
 
         Ok(self.current_point_index)
@@ -1092,12 +1225,40 @@ mod tests {
     test_read_with_format!(laz_format_4, 4, RawLAZReader, get_test_laz_path);
     test_read_with_format!(laz_format_5, 5, RawLAZReader, get_test_laz_path);
 
-    // There is currently a bug in `laz-rs` when seeking into files with point record format 6 or higher, so they are
-    // still unsupported in pasture. See this issue here: https://github.com/laz-rs/laz-rs/issues/46
-
-    // test_read_with_format!(laz_format_6, 6, RawLAZReader, get_test_laz_path);
-    // test_read_with_format!(laz_format_7, 7, RawLAZReader, get_test_laz_path);
-    // test_read_with_format!(laz_format_8, 8, RawLAZReader, get_test_laz_path);
+    // `laz-rs` can't seek in files with point record format 6 or higher (see https://github.com/laz-rs/laz-rs/issues/46),
+    // so `RawLAZReader` seeks by decompressing for these formats
+    test_read_with_format!(laz_format_6, 6, RawLAZReader, get_test_laz_path);
+    test_read_with_format!(laz_format_7, 7, RawLAZReader, get_test_laz_path);
+    test_read_with_format!(laz_format_8, 8, RawLAZReader, get_test_laz_path);
+
+    #[test]
+    fn test_raw_laz_reader_seek_backwards_extended_formats() -> Result<()> {
+        for format_number in 6..=8 {
+            let read = BufReader::new(File::open(get_test_laz_path(format_number))?);
+            let mut reader = RawLAZReader::from_read(read, false)?;
+            let format = Format::new(format_number)?;
+
+            reader.seek_point(SeekFrom::Start(8))?;
+            let points = reader.read::<VectorBuffer>(2)?;
+            compare_to_reference_data_range(&points, format, 8..10);
+
+            assert_eq!(3, reader.seek_point(SeekFrom::Current(-7))?);
+            let points = reader.read::<VectorBuffer>(7)?;
+            compare_to_reference_data_range(&points, format, 3..10);
+
+            assert_eq!(1, reader.seek_point(SeekFrom::Start(1))?);
+            let points = reader.read::<VectorBuffer>(1)?;
+            compare_to_reference_data_range(&points, format, 1..2);
+        }
+        Ok(())
+    }
 
     // Formats 9 and 10 seem to parse waveform data differently when using laz-rs, so they are unsupported for now
+    #[test]
+    fn test_raw_laz_reader_rejects_extended_waveform_formats() {
+        for format_number in [9, 10].iter() {
+            let read = BufReader::new(File::open(get_test_laz_path(*format_number)).unwrap());
+            assert!(RawLAZReader::from_read(read, false).is_err());
+        }
+    }
 }
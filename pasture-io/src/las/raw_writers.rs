@@ -1,10 +1,11 @@
 use std::{
     collections::HashMap,
-    convert::TryInto,
-    io::{Cursor, SeekFrom},
+    convert::{TryFrom, TryInto},
+    io::{Cursor, Read, SeekFrom, Write},
+    ops::Range,
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use byteorder::{LittleEndian, NativeEndian, ReadBytesExt, WriteBytesExt};
 use las_rs::{point::Format, Builder, Vlr};
 use laz::{LasZipCompressor, LazItemRecordBuilder, LazVlr};
@@ -14,14 +15,16 @@ use crate::base::PointWriter;
 
 use super::{
     get_classification_flags_reader, get_classification_reader, get_color_reader,
-    get_edge_of_flight_line_reader, get_extended_scan_angle_rank_reader, get_gps_time_reader,
-    get_intensity_reader, get_nir_reader, get_number_of_returns_reader, get_point_source_id_reader,
-    get_position_reader, get_return_number_reader, get_return_point_waveform_location_reader,
-    get_scan_angle_rank_reader, get_scan_direction_flag_reader, get_scanner_channel_reader,
-    get_user_data_reader, get_wave_packet_descriptor_index_reader, get_waveform_data_offset_reader,
-    get_waveform_packet_size_reader, get_waveform_parameters_reader, map_laz_err,
-    point_layout_from_las_metadata, write_las_bit_attributes, write_position_as_las_position,
-    BitAttributes, BitAttributesExtended, BitAttributesRegular,
+    get_edge_of_flight_line_reader, get_extended_scan_angle_rank_reader, get_extra_bytes_reader,
+    get_gps_time_reader, get_intensity_reader, get_nir_reader, get_number_of_returns_reader,
+    get_point_source_id_reader, get_position_reader, get_return_number_reader,
+    get_return_point_waveform_location_reader, get_scan_angle_rank_reader,
+    get_scan_direction_flag_reader, get_scanner_channel_reader, get_user_data_reader,
+    get_wave_packet_descriptor_index_reader, get_waveform_data_offset_reader,
+    get_waveform_packet_size_reader, get_waveform_parameters_reader, is_known_las_attribute,
+    map_laz_err, point_layout_from_las_metadata, write_las_bit_attributes,
+    write_position_as_las_position, BitAttributes, BitAttributesExtended, BitAttributesRegular,
+    ExtraBytesDataType, ExtraBytesEntry, ExtraBytesReaderFn, ExtraBytesVlr,
 };
 
 /// Update the bounds in the given `las_header` by including the given `new_position`
@@ -65,6 +68,207 @@ fn update_point_counts_in_las_header(
         });
 }
 
+/// Returns readers for all extra bytes attributes in the given `default_layout` of a LAS writer, i.e. all attributes that are
+/// not known LAS attributes. Each reader is paired with the byte range of its attribute within the extra bytes of a single
+/// LAS point record
+fn get_extra_bytes_readers(
+    default_layout: &PointLayout,
+    source_layout: &PointLayout,
+) -> Vec<(Range<usize>, ExtraBytesReaderFn)> {
+    let mut offset_in_extra_bytes = 0;
+    default_layout
+        .attributes()
+        .filter(|attribute| !is_known_las_attribute(attribute.attribute_definition()))
+        .map(|attribute| {
+            let size = attribute.size() as usize;
+            let range = offset_in_extra_bytes..(offset_in_extra_bytes + size);
+            offset_in_extra_bytes += size;
+            (
+                range,
+                get_extra_bytes_reader(source_layout, attribute.attribute_definition()),
+            )
+        })
+        .collect()
+}
+
+/// Records the location and number of the given `evlrs` in the `las_header`. EVLRs are always written directly after the
+/// point records (or the chunk table in a LAZ file), which end at `end_of_point_records`
+fn update_evlrs_in_las_header(
+    evlrs: &[las::raw::Vlr],
+    end_of_point_records: u64,
+    las_header: &mut las::raw::Header,
+) {
+    if evlrs.is_empty() {
+        las_header.evlr = None;
+    } else {
+        las_header.evlr = Some(las::raw::header::Evlr {
+            start_of_first_evlr: end_of_point_records,
+            number_of_evlrs: evlrs.len() as u32,
+        });
+    }
+}
+
+/// Converts the given `evlr` into a raw EVLR that can be written to a LAS file. EVLRs are a LAS 1.4 feature, so this
+/// returns an error if the `las_header` has an older version
+fn evlr_to_raw_evlr(evlr: Vlr, las_header: &las::raw::Header) -> Result<las::raw::Vlr> {
+    if las_header.version < las_rs::Version::new(1, 4) {
+        bail!(
+            "EVLRs require LAS version 1.4, but the LAS header has version {}",
+            las_header.version
+        );
+    }
+    Ok(evlr.into_raw(true)?)
+}
+
+/// A single extra bytes value, widened to the type that LAS uses for the minimum and maximum values in the Extra Bytes VLR
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+enum ExtraBytesValue {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+impl ExtraBytesValue {
+    /// Parses a value of the given `data_type` from its little-endian representation in `bytes`. Returns `None` if the
+    /// `data_type` has no known size
+    fn from_le_bytes(data_type: ExtraBytesDataType, bytes: &[u8]) -> Option<Self> {
+        let value = match data_type {
+            ExtraBytesDataType::U8 => Self::Unsigned(bytes[0] as u64),
+            ExtraBytesDataType::I8 => Self::Signed(bytes[0] as i8 as i64),
+            ExtraBytesDataType::U16 => {
+                Self::Unsigned(u16::from_le_bytes(bytes.try_into().ok()?) as u64)
+            }
+            ExtraBytesDataType::I16 => {
+                Self::Signed(i16::from_le_bytes(bytes.try_into().ok()?) as i64)
+            }
+            ExtraBytesDataType::U32 => {
+                Self::Unsigned(u32::from_le_bytes(bytes.try_into().ok()?) as u64)
+            }
+            ExtraBytesDataType::I32 => {
+                Self::Signed(i32::from_le_bytes(bytes.try_into().ok()?) as i64)
+            }
+            ExtraBytesDataType::U64 => Self::Unsigned(u64::from_le_bytes(bytes.try_into().ok()?)),
+            ExtraBytesDataType::I64 => Self::Signed(i64::from_le_bytes(bytes.try_into().ok()?)),
+            ExtraBytesDataType::F32 => {
+                Self::Float(f32::from_le_bytes(bytes.try_into().ok()?) as f64)
+            }
+            ExtraBytesDataType::F64 => Self::Float(f64::from_le_bytes(bytes.try_into().ok()?)),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn to_le_bytes(self) -> [u8; 8] {
+        match self {
+            Self::Unsigned(value) => value.to_le_bytes(),
+            Self::Signed(value) => value.to_le_bytes(),
+            Self::Float(value) => value.to_le_bytes(),
+        }
+    }
+}
+
+/// Minimum and maximum value of a single Extra Bytes entry
+struct ExtraBytesValueRange {
+    /// Byte range of the entry within the extra bytes of a point record
+    bytes: Range<usize>,
+    /// Minimum and maximum value, or `None` if no value has been written yet
+    min_max: Option<(ExtraBytesValue, ExtraBytesValue)>,
+}
+
+/// Keeps track of the minimum and maximum values of all extra bytes that a LAS writer writes, so that the entries in the
+/// Extra Bytes VLR can be updated when the writer is flushed
+struct ExtraBytesStatistics {
+    /// Position of the data of the Extra Bytes VLR within the LAS file
+    vlr_data_position: u64,
+    entries: Vec<ExtraBytesEntry>,
+    /// Value ranges of the entries. Entries after the first entry with an unknown size are not tracked, since their
+    /// byte range within a point record is unknown
+    value_ranges: Vec<ExtraBytesValueRange>,
+}
+
+impl ExtraBytesStatistics {
+    fn new(extra_bytes_vlr: ExtraBytesVlr, vlr_data_position: u64) -> Self {
+        let mut offset_in_extra_bytes = 0;
+        let value_ranges = extra_bytes_vlr
+            .entries()
+            .iter()
+            .map_while(|entry| {
+                let size = entry.data_type().size()?;
+                let bytes = offset_in_extra_bytes..(offset_in_extra_bytes + size);
+                offset_in_extra_bytes += size;
+                Some(ExtraBytesValueRange {
+                    bytes,
+                    min_max: None,
+                })
+            })
+            .collect();
+        Self {
+            vlr_data_position,
+            entries: extra_bytes_vlr.entries().to_vec(),
+            value_ranges,
+        }
+    }
+
+    /// Includes the values in the `extra_bytes` of a single point record
+    fn update(&mut self, extra_bytes: &[u8]) {
+        for (entry, value_range) in self.entries.iter().zip(self.value_ranges.iter_mut()) {
+            let value = match ExtraBytesValue::from_le_bytes(
+                entry.data_type(),
+                &extra_bytes[value_range.bytes.clone()],
+            ) {
+                Some(value) => value,
+                None => continue,
+            };
+            if matches!(value, ExtraBytesValue::Float(float) if float.is_nan()) {
+                continue;
+            }
+            value_range.min_max = match value_range.min_max {
+                Some((min, max)) => Some((
+                    if value < min { value } else { min },
+                    if value > max { value } else { max },
+                )),
+                None => Some((value, value)),
+            };
+        }
+    }
+
+    /// Overwrites the data of the Extra Bytes VLR in `writer` with entries that contain the current minimum and maximum
+    /// values. The position of `writer` is restored afterwards
+    fn write_vlr_data<W: std::io::Write + std::io::Seek>(&self, writer: &mut W) -> Result<()> {
+        let mut entries = self.entries.clone();
+        for (entry, value_range) in entries.iter_mut().zip(self.value_ranges.iter()) {
+            if let Some((min, max)) = value_range.min_max {
+                entry.set_min_max_values(min.to_le_bytes(), max.to_le_bytes());
+            }
+        }
+        let vlr: Vlr = (&entries.into_iter().collect::<ExtraBytesVlr>()).try_into()?;
+
+        let current_position = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(self.vlr_data_position))?;
+        writer.write_all(&vlr.data)?;
+        writer.seek(SeekFrom::Start(current_position))?;
+        Ok(())
+    }
+}
+
+/// Writes the given `vlr` and returns `ExtraBytesStatistics` if it is an Extra Bytes VLR
+fn write_vlr<W: std::io::Write + std::io::Seek>(
+    vlr: &Vlr,
+    writer: &mut W,
+) -> Result<Option<ExtraBytesStatistics>> {
+    let raw_vlr = vlr.clone().into_raw(false)?;
+    raw_vlr.write_to(&mut *writer)?;
+    let extra_bytes_vlr = match ExtraBytesVlr::try_from(vlr) {
+        Ok(extra_bytes_vlr) => extra_bytes_vlr,
+        Err(_) => return Ok(None),
+    };
+    let vlr_data_position = writer.stream_position()? - raw_vlr.data.len() as u64;
+    Ok(Some(ExtraBytesStatistics::new(
+        extra_bytes_vlr,
+        vlr_data_position,
+    )))
+}
+
 /// Do final checkup of the LAS header
 fn finalize_las_header(las_header: &mut las::raw::Header) {
     // Set the legacy point counts field, if desired. The LAS standard states that the legacy number of point records field
@@ -98,6 +302,7 @@ pub(crate) struct RawLASWriter<T: std::io::Write + std::io::Seek> {
     evlrs: Vec<las::raw::Vlr>,
     _point_start_index: u64,
     requires_flush: bool,
+    extra_bytes_statistics: Option<ExtraBytesStatistics>,
 }
 
 impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
@@ -129,12 +334,14 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
         }
 
         raw_header.write_to(&mut write)?;
+        let mut extra_bytes_statistics = None;
         for vlr in header.vlrs().iter() {
             if vlr.has_large_data() {
                 panic!("RawLASWriter::from_write_and_header: Header with large VLRs is currently unsupported! Please add any large VLRs to the 'evlrs' parameter of the header!");
             }
-            let raw_vlr = vlr.clone().into_raw(false)?;
-            raw_vlr.write_to(&mut write)?;
+            if let Some(statistics) = write_vlr(vlr, &mut write)? {
+                extra_bytes_statistics = Some(statistics);
+            }
         }
 
         let point_start_index = write.stream_position()?;
@@ -151,6 +358,7 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
                 .collect::<Result<Vec<_>, _>>()?,
             _point_start_index: point_start_index,
             requires_flush: true,
+            extra_bytes_statistics,
         })
     }
 
@@ -161,6 +369,20 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
         Ok(self.writer)
     }
 
+    /// Returns a reference to the underlying write type `T`. Call `flush` first to make sure that the header and EVLRs
+    /// are up to date
+    pub fn get_ref(&self) -> &T {
+        &self.writer
+    }
+
+    /// Adds the given `evlr` to this `RawLASWriter`. All EVLRs are written after the point records on `flush`
+    pub fn add_evlr(&mut self, evlr: Vlr) -> Result<()> {
+        self.evlrs
+            .push(evlr_to_raw_evlr(evlr, &self.current_header)?);
+        self.requires_flush = true;
+        Ok(())
+    }
+
     /// Writes the current header to the start of the file
     fn write_header(&mut self) -> Result<()> {
         finalize_las_header(&mut self.current_header);
@@ -169,6 +391,9 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
         self.writer.seek(SeekFrom::Start(0))?;
         self.current_header.write_to(&mut self.writer)?;
         self.writer.seek(SeekFrom::Start(current_position))?;
+        if let Some(statistics) = &self.extra_bytes_statistics {
+            statistics.write_vlr_data(&mut self.writer)?;
+        }
         Ok(())
     }
 
@@ -198,6 +423,9 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
         let mut chunk_buffer: Vec<u8> = vec![0; num_points_in_chunk * size_of_single_point];
 
         let source_format = Format::new(self.current_header.point_data_record_format)?;
+        let num_extra_bytes =
+            self.current_header.point_data_record_length as usize - source_format.len() as usize;
+        let mut extra_bytes_buffer: Vec<u8> = vec![0; num_extra_bytes];
 
         let mut points_by_return: HashMap<u8, u64> = HashMap::new();
         let max_return_number = if self.current_header.large_file.is_some() {
@@ -328,6 +556,14 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
                     self.writer.write_f32::<LittleEndian>(py)?;
                     self.writer.write_f32::<LittleEndian>(pz)?;
                 }
+
+                if num_extra_bytes > 0 {
+                    point_read.read_exact(&mut extra_bytes_buffer)?;
+                    if let Some(statistics) = self.extra_bytes_statistics.as_mut() {
+                        statistics.update(&extra_bytes_buffer);
+                    }
+                    self.writer.write_all(&extra_bytes_buffer)?;
+                }
             }
 
             chunk_buffer = point_read.into_inner();
@@ -357,6 +593,9 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
         let mut chunk_buffer: Vec<u8> = vec![0; num_points_in_chunk * size_of_single_point];
 
         let target_format = Format::new(self.current_header.point_data_record_format)?;
+        let num_extra_bytes =
+            self.current_header.point_data_record_length as usize - target_format.len() as usize;
+        let mut extra_bytes_buffer: Vec<u8> = vec![0; num_extra_bytes];
 
         let mut points_by_return: HashMap<u8, u64> = HashMap::new();
         let max_return_number = if self.current_header.large_file.is_some() {
@@ -444,6 +683,8 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
         } else {
             None
         };
+        let extra_bytes_readers =
+            get_extra_bytes_readers(&self.default_layout, points.point_layout());
 
         for chunk_index in 0..num_chunks {
             let points_in_cur_chunk = std::cmp::min(
@@ -568,6 +809,20 @@ impl<T: std::io::Write + std::io::Seek> RawLASWriter<T> {
                     self.writer.write_f32::<LittleEndian>(params.y)?;
                     self.writer.write_f32::<LittleEndian>(params.z)?;
                 }
+
+                if num_extra_bytes > 0 {
+                    for (range, reader) in extra_bytes_readers.iter() {
+                        reader(
+                            point_index,
+                            &point_read,
+                            &mut extra_bytes_buffer[range.clone()],
+                        );
+                    }
+                    if let Some(statistics) = self.extra_bytes_statistics.as_mut() {
+                        statistics.update(&extra_bytes_buffer);
+                    }
+                    self.writer.write_all(&extra_bytes_buffer)?;
+                }
             }
 
             chunk_buffer = point_read.into_inner();
@@ -598,7 +853,10 @@ impl<T: std::io::Write + std::io::Seek> PointWriter for RawLASWriter<T> {
             return Ok(());
         }
 
+        // EVLRs are written directly after the point records, but new point records might be written after this
+        // call to `flush`, so we have to restore the current position afterwards
         let current_index = self.writer.stream_position()?;
+        update_evlrs_in_las_header(&self.evlrs, current_index, &mut self.current_header);
         self.write_header()?;
         self.write_evlrs()?;
         self.writer.seek(SeekFrom::Start(current_index))?;
@@ -619,18 +877,20 @@ pub(crate) struct RawLAZWriter<T: std::io::Write + std::io::Seek + Send + 'stati
     current_header: las::raw::Header,
     evlrs: Vec<las::raw::Vlr>,
     requires_flush: bool,
+    extra_bytes_statistics: Option<ExtraBytesStatistics>,
 }
 
 impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
     pub fn from_write_and_header(mut write: T, header: las::Header) -> Result<Self> {
+        if header.point_format().is_extended && header.point_format().has_waveform {
+            bail!(
+                "Compressed LAZ files with extended formats 9 and 10 are currently not supported!"
+            );
+        }
         let las_metadata = (&header).try_into().context("Could not parse LAS header")?;
         let default_layout = point_layout_from_las_metadata(&las_metadata, false)
             .context("Could not determine PointLayout from given LAS header")?;
 
-        if header.point_format().extra_bytes != 0 {
-            panic!("Extra bytes in LAZ point records are currently unsupported!");
-        }
-
         let mut raw_header = header.clone().into_raw()?;
         // raw_header.version = Version::new(1, 2);
         raw_header.number_of_point_records = 0;
@@ -668,19 +928,30 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
         };
 
         let mut header_builder = Builder::new(raw_header)?;
+        // An existing LASzip VLR (e.g. from the header of another LAZ file) is replaced by the new one
+        header_builder.vlrs = header
+            .vlrs()
+            .iter()
+            .filter(|vlr| {
+                vlr.user_id != laz::LazVlr::USER_ID || vlr.record_id != laz::LazVlr::RECORD_ID
+            })
+            .cloned()
+            .collect();
         header_builder.vlrs.push(laz_vlr);
+        header_builder.vlr_padding = header.vlr_padding().to_vec();
         let header_with_laz_vlr = header_builder.into_header()?;
         header_with_laz_vlr
             .clone()
             .into_raw()
             .and_then(|raw_header_with_laz_vlr| raw_header_with_laz_vlr.write_to(&mut write))?;
+        let mut extra_bytes_statistics = None;
         for vlr in header_with_laz_vlr.vlrs() {
-            vlr.clone()
-                .into_raw(false)
-                .and_then(|raw_vlr| raw_vlr.write_to(&mut write))?;
+            if let Some(statistics) = write_vlr(vlr, &mut write)? {
+                extra_bytes_statistics = Some(statistics);
+            }
         }
-        if !header.vlr_padding().is_empty() {
-            write.write_all(header.vlr_padding())?;
+        if !header_with_laz_vlr.vlr_padding().is_empty() {
+            write.write_all(header_with_laz_vlr.vlr_padding())?;
         }
 
         let laz_writer = LasZipCompressor::new(write, raw_laz_vlr).map_err(map_laz_err)?;
@@ -695,6 +966,7 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
                 .map(|evlr| evlr.clone().into_raw(true))
                 .collect::<Result<Vec<_>, _>>()?,
             requires_flush: false,
+            extra_bytes_statistics,
         })
     }
 
@@ -705,6 +977,14 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
         Ok(self.writer.into_inner())
     }
 
+    /// Adds the given `evlr` to this `RawLAZWriter`. All EVLRs are written after the compressed point records on `flush`
+    pub fn add_evlr(&mut self, evlr: Vlr) -> Result<()> {
+        self.evlrs
+            .push(evlr_to_raw_evlr(evlr, &self.current_header)?);
+        self.requires_flush = true;
+        Ok(())
+    }
+
     fn write_points_default_layout<'a, B: BorrowedBuffer<'a>>(
         &mut self,
         points: &'a B,
@@ -724,6 +1004,9 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
             vec![0; num_points_in_chunk * self.current_header.point_data_record_length as usize];
 
         let source_format = Format::new(self.current_header.point_data_record_format)?;
+        let num_extra_bytes =
+            self.current_header.point_data_record_length as usize - source_format.len() as usize;
+        let mut extra_bytes_buffer: Vec<u8> = vec![0; num_extra_bytes];
 
         let mut points_by_return: HashMap<u8, u64> = HashMap::new();
         let max_return_number = if self.current_header.large_file.is_some() {
@@ -855,6 +1138,14 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
                     las_point_write.write_f32::<LittleEndian>(py)?;
                     las_point_write.write_f32::<LittleEndian>(pz)?;
                 }
+
+                if num_extra_bytes > 0 {
+                    point_read.read_exact(&mut extra_bytes_buffer)?;
+                    if let Some(statistics) = self.extra_bytes_statistics.as_mut() {
+                        statistics.update(&extra_bytes_buffer);
+                    }
+                    las_point_write.write_all(&extra_bytes_buffer)?;
+                }
             }
 
             las_point_buffer = las_point_write.into_inner();
@@ -892,6 +1183,9 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
             vec![0; num_points_in_chunk * self.current_header.point_data_record_length as usize];
 
         let target_format = Format::new(self.current_header.point_data_record_format)?;
+        let num_extra_bytes =
+            self.current_header.point_data_record_length as usize - target_format.len() as usize;
+        let mut extra_bytes_buffer: Vec<u8> = vec![0; num_extra_bytes];
 
         let mut points_by_return: HashMap<u8, u64> = HashMap::new();
         let max_return_number = if self.current_header.large_file.is_some() {
@@ -976,6 +1270,8 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
         } else {
             None
         };
+        let extra_bytes_readers =
+            get_extra_bytes_readers(&self.default_layout, points.point_layout());
 
         for chunk_index in 0..num_chunks {
             let points_in_cur_chunk = std::cmp::min(
@@ -1100,11 +1396,25 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
                     las_point_write.write_f32::<LittleEndian>(params.y)?;
                     las_point_write.write_f32::<LittleEndian>(params.z)?;
                 }
+
+                if num_extra_bytes > 0 {
+                    for (range, reader) in extra_bytes_readers.iter() {
+                        reader(
+                            point_index,
+                            &point_read,
+                            &mut extra_bytes_buffer[range.clone()],
+                        );
+                    }
+                    if let Some(statistics) = self.extra_bytes_statistics.as_mut() {
+                        statistics.update(&extra_bytes_buffer);
+                    }
+                    las_point_write.write_all(&extra_bytes_buffer)?;
+                }
             }
 
             las_point_buffer = las_point_write.into_inner();
             self.writer.compress_many(
-                &las_point_buffer[0..num_points_in_chunk
+                &las_point_buffer[0..points_in_cur_chunk
                     * self.current_header.point_data_record_length as usize],
             )?;
 
@@ -1131,6 +1441,9 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
         raw_writer.seek(SeekFrom::Start(0))?;
         self.current_header.write_to(&mut raw_writer)?;
         raw_writer.seek(SeekFrom::Start(current_position))?;
+        if let Some(statistics) = &self.extra_bytes_statistics {
+            statistics.write_vlr_data(raw_writer)?;
+        }
 
         Ok(())
     }
@@ -1147,6 +1460,8 @@ impl<T: std::io::Write + std::io::Seek + Send + 'static> RawLAZWriter<T> {
 
     fn do_flush(&mut self) -> Result<()> {
         self.writer.done()?;
+        let end_of_point_records = self.writer.get_mut().stream_position()?;
+        update_evlrs_in_las_header(&self.evlrs, end_of_point_records, &mut self.current_header);
         self.write_evlrs()?;
         self.write_header()
     }
@@ -1579,4 +1894,21 @@ mod tests {
     laz_write_tests!(laz_write_1, 1, LasPointFormat1);
     laz_write_tests!(laz_write_2, 2, LasPointFormat2);
     laz_write_tests!(laz_write_3, 3, LasPointFormat3);
+    laz_write_tests!(laz_write_4, 4, LasPointFormat4);
+    laz_write_tests!(laz_write_5, 5, LasPointFormat5);
+
+    // Formats 9 and 10 seem to parse waveform data differently when using laz-rs, so they are unsupported for now
+    #[test]
+    fn test_raw_laz_writer_rejects_extended_waveform_formats() -> Result<()> {
+        for format_number in [9, 10].iter() {
+            let mut header_builder = Builder::from((1, 4));
+            header_builder.point_format = Format::new(*format_number)?;
+            assert!(RawLAZWriter::from_write_and_header(
+                Cursor::new(vec![]),
+                header_builder.into_header()?
+            )
+            .is_err());
+        }
+        Ok(())
+    }
 }
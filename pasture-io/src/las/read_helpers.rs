@@ -5,7 +5,10 @@ use byteorder::{NativeEndian, ReadBytesExt};
 use pasture_core::{
     layout::attributes,
     layout::conversion::get_converter_for_attributes,
-    layout::{conversion::AttributeConversionFn, PointAttributeMember, PointLayout, PrimitiveType},
+    layout::{
+        conversion::AttributeConversionFn, PointAttributeDefinition, PointAttributeMember,
+        PointLayout, PrimitiveType,
+    },
     nalgebra::Vector3,
 };
 
@@ -488,6 +491,52 @@ make_get_reader_fn!(
     read_waveform_parameters_in_default_layout
 );
 
+/// Similar to `ReaderFn`, but for LAS extra bytes. Since extra bytes can have arbitrary datatypes, this function does not
+/// return a typed value but instead writes the binary representation of the extra bytes attribute of a single point
+/// into the given target slice
+pub(crate) type ExtraBytesReaderFn = Box<dyn Fn(usize, &Cursor<Vec<u8>>, &mut [u8])>;
+
+/// Returns an `ExtraBytesReaderFn` for the given `extra_bytes_attribute`. If `source_layout` contains an attribute with
+/// the same name, its value is copied into the target slice, converting it to the datatype of `extra_bytes_attribute` if
+/// necessary. If there is no matching attribute in `source_layout`, or if no conversion between the datatypes exists,
+/// the extra bytes are set to zero
+pub(crate) fn get_extra_bytes_reader(
+    source_layout: &PointLayout,
+    extra_bytes_attribute: &PointAttributeDefinition,
+) -> ExtraBytesReaderFn {
+    let source_attribute = match source_layout.get_attribute_by_name(extra_bytes_attribute.name()) {
+        Some(attribute) => attribute.clone(),
+        None => return Box::new(|_, _, target| target.fill(0)),
+    };
+
+    let size_of_single_point = source_layout.size_of_point_entry() as usize;
+    let offset_in_point = source_attribute.offset() as usize;
+    let attribute_size = source_attribute.size() as usize;
+    if source_attribute.datatype() == extra_bytes_attribute.datatype() {
+        return Box::new(move |current_point_index, point_read, target| {
+            let attribute_start = (current_point_index * size_of_single_point) + offset_in_point;
+            target.copy_from_slice(
+                &point_read.get_ref()[attribute_start..(attribute_start + attribute_size)],
+            );
+        });
+    }
+
+    match get_converter_for_attributes(
+        source_attribute.attribute_definition(),
+        extra_bytes_attribute,
+    ) {
+        Some(converter) => Box::new(move |current_point_index, point_read, target| {
+            let attribute_start = (current_point_index * size_of_single_point) + offset_in_point;
+            let attribute_slice =
+                &point_read.get_ref()[attribute_start..(attribute_start + attribute_size)];
+            unsafe {
+                converter(attribute_slice, target);
+            }
+        }),
+        None => Box::new(|_, _, target| target.fill(0)),
+    }
+}
+
 /// Attempts to convert the given LAS string (a fixed-size byte array, potentially null-terminated) into a
 /// Rust `String`. As per the LAS specification, `las_string` will be null-terminated ONLY IF the length of
 /// the string is less than the size of the array (i.e. `N`)!
@@ -4,5 +4,12 @@ pub extern crate las as las_rs;
 
 pub mod ascii;
 pub mod base;
+pub mod copc;
+pub mod e57;
+pub mod ept;
 pub mod las;
+pub mod native;
+pub mod pcd;
+pub mod ply;
+pub mod potree;
 pub mod tiles3d;
@@ -0,0 +1,11 @@
+mod native_header;
+pub use self::native_header::*;
+
+mod native_metadata;
+pub use self::native_metadata::*;
+
+mod native_reader;
+pub use self::native_reader::*;
+
+mod native_writer;
+pub use self::native_writer::*;
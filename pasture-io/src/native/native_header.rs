@@ -0,0 +1,280 @@
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use pasture_core::layout::PointLayout;
+use serde::{Deserialize, Serialize};
+
+/// The memory layout of the point data within a native pasture file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NativeMemoryLayout {
+    /// All points are stored in a single block, one point record after another, exactly like in a `VectorBuffer`
+    Interleaved,
+    /// Each attribute is stored in its own block, exactly like in a `HashMapBuffer`
+    Columnar,
+}
+
+/// A contiguous block of point data within a native pasture file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NativeDataBlock {
+    /// Offset of the block relative to the start of the data section
+    pub offset: u64,
+    /// Size of the block in bytes
+    pub length: u64,
+}
+
+/// The header of a native pasture file. It stores the full `PointLayout` of the points (including custom attributes,
+/// custom datatypes, offsets and alignments), so the point data can be copied without any conversion.
+///
+/// A native pasture file starts with the magic bytes `PSTR`, followed by the format version as a `u32` and the length
+/// of the header as a `u64`, both in little-endian byte order. The header itself is stored as JSON. It is followed by
+/// zero-padding up to the next multiple of [`NativeHeader::DATA_ALIGNMENT`], where the data section starts. All values
+/// within the data section are stored in the byte order of the machine that wrote the file, which is recorded in the
+/// header
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NativeHeader {
+    pub point_layout: PointLayout,
+    pub point_count: u64,
+    pub memory_layout: NativeMemoryLayout,
+    pub little_endian: bool,
+    /// The blocks of point data. Interleaved files have exactly one block, columnar files have one block per attribute
+    /// in the order of the attributes within `point_layout`
+    pub blocks: Vec<NativeDataBlock>,
+}
+
+impl NativeHeader {
+    /// The magic bytes at the start of each native pasture file
+    pub const MAGIC: &'static [u8; 4] = b"PSTR";
+    /// The version of the native pasture format
+    pub const VERSION: u32 = 1;
+    /// Size of the magic bytes, version and header length at the start of the file
+    pub const PREAMBLE_SIZE: u64 = 16;
+    /// Alignment of the data section and of all blocks within the data section, relative to the start of the file
+    pub const DATA_ALIGNMENT: u64 = 64;
+
+    /// Creates a new `NativeHeader` for `point_count` points in the given `point_layout` and `memory_layout`
+    pub fn new(
+        point_layout: PointLayout,
+        point_count: usize,
+        memory_layout: NativeMemoryLayout,
+    ) -> Self {
+        let point_count = point_count as u64;
+        let blocks = match memory_layout {
+            NativeMemoryLayout::Interleaved => vec![NativeDataBlock {
+                offset: 0,
+                length: point_count * point_layout.size_of_point_entry(),
+            }],
+            NativeMemoryLayout::Columnar => {
+                let mut offset = 0;
+                point_layout
+                    .attributes()
+                    .map(|attribute| {
+                        let block = NativeDataBlock {
+                            offset,
+                            length: point_count * attribute.size(),
+                        };
+                        offset = align_offset(offset + block.length);
+                        block
+                    })
+                    .collect()
+            }
+        };
+        Self {
+            point_layout,
+            point_count,
+            memory_layout,
+            little_endian: cfg!(target_endian = "little"),
+            blocks,
+        }
+    }
+
+    /// Returns the size of the data section in bytes
+    pub fn data_size(&self) -> u64 {
+        self.blocks
+            .last()
+            .map(|block| block.offset + block.length)
+            .unwrap_or_default()
+    }
+
+    /// Reads a `NativeHeader` from the given `reader`. Returns the header together with the offset of the data section
+    /// relative to the start of the file
+    ///
+    /// # Errors
+    ///
+    /// If `reader` does not contain a valid native pasture file, or if the file was written on a machine with a
+    /// different byte order, an error is returned
+    pub fn read_from<R: Read>(mut reader: R) -> Result<(Self, u64)> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != Self::MAGIC {
+            bail!("Invalid magic bytes of native pasture file");
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != Self::VERSION {
+            bail!("Unsupported version {version} of native pasture file");
+        }
+        let header_length = reader.read_u64::<LittleEndian>()?;
+        let mut header_json = vec![0; header_length as usize];
+        reader
+            .read_exact(&mut header_json)
+            .context("Unexpected end of native pasture header")?;
+        let header: Self =
+            serde_json::from_slice(&header_json).context("Invalid native pasture header")?;
+        if header.little_endian != cfg!(target_endian = "little") {
+            bail!("Native pasture file was written on a machine with a different byte order");
+        }
+        let expected_blocks = match header.memory_layout {
+            NativeMemoryLayout::Interleaved => 1,
+            NativeMemoryLayout::Columnar => header.point_layout.attributes().count(),
+        };
+        if header.blocks.len() != expected_blocks {
+            bail!(
+                "Native pasture file contains {} data blocks, but {expected_blocks} blocks were expected",
+                header.blocks.len()
+            );
+        }
+
+        let padding = padding_for(Self::PREAMBLE_SIZE + header_length);
+        let mut padding_bytes = vec![0; padding as usize];
+        reader.read_exact(&mut padding_bytes)?;
+        Ok((header, Self::PREAMBLE_SIZE + header_length + padding))
+    }
+
+    /// Returns the maximum length of the header of a file with the given `point_layout` and `memory_layout`, over all
+    /// possible point counts. Writers that write the header before they know the number of points reserve this many
+    /// bytes for the header (see [`NativeHeader::write_to_with_length`])
+    pub fn max_header_length(
+        point_layout: PointLayout,
+        memory_layout: NativeMemoryLayout,
+    ) -> Result<u64> {
+        // The point count and the blocks are the only values that depend on the number of points
+        let mut header = Self::new(point_layout, 0, memory_layout);
+        header.point_count = u64::MAX;
+        for block in header.blocks.iter_mut() {
+            block.offset = u64::MAX;
+            block.length = u64::MAX;
+        }
+        Ok(serde_json::to_vec(&header)?.len() as u64)
+    }
+
+    /// Writes this `NativeHeader` to the given `writer`, including the padding up to the start of the data section.
+    /// Returns the offset of the data section relative to the start of the file
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<u64> {
+        let header_length = serde_json::to_vec(self)?.len() as u64;
+        self.write_to_with_length(writer, header_length)
+    }
+
+    /// Like [`NativeHeader::write_to`], but pads the header with whitespace to exactly `header_length` bytes, so that
+    /// a header for a different number of points can later be written in the same place
+    ///
+    /// # Errors
+    ///
+    /// If the header is longer than `header_length` bytes, or if writing fails, an error is returned
+    pub fn write_to_with_length<W: Write>(&self, mut writer: W, header_length: u64) -> Result<u64> {
+        let mut header_json = serde_json::to_vec(self)?;
+        if header_json.len() as u64 > header_length {
+            bail!(
+                "Native pasture header has {} bytes, which is more than the {header_length} bytes reserved for it",
+                header_json.len()
+            );
+        }
+        header_json.resize(header_length as usize, b' ');
+        writer.write_all(Self::MAGIC)?;
+        writer.write_u32::<LittleEndian>(Self::VERSION)?;
+        writer.write_u64::<LittleEndian>(header_length)?;
+        writer.write_all(&header_json)?;
+        let header_end = Self::PREAMBLE_SIZE + header_length;
+        let padding = padding_for(header_end);
+        writer.write_all(&vec![0; padding as usize])?;
+        Ok(header_end + padding)
+    }
+}
+
+/// Returns the number of padding bytes required to align `offset` to `NativeHeader::DATA_ALIGNMENT`
+fn padding_for(offset: u64) -> u64 {
+    align_offset(offset) - offset
+}
+
+fn align_offset(offset: u64) -> u64 {
+    offset.div_ceil(NativeHeader::DATA_ALIGNMENT) * NativeHeader::DATA_ALIGNMENT
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pasture_core::layout::{
+        attributes::{INTENSITY, POSITION_3D},
+        FieldAlignment, PointAttributeDataType, PointAttributeDefinition,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_native_header_round_trip() -> Result<()> {
+        let custom_attribute =
+            PointAttributeDefinition::custom("Custom".into(), PointAttributeDataType::Vec3f32);
+        let mut layout = PointLayout::default();
+        layout.add_attribute(POSITION_3D, FieldAlignment::Default);
+        layout.add_attribute(INTENSITY, FieldAlignment::Default);
+        layout.add_attribute(custom_attribute, FieldAlignment::Packed(2));
+
+        let header = NativeHeader::new(layout, 3, NativeMemoryLayout::Columnar);
+        assert_eq!(
+            vec![
+                NativeDataBlock {
+                    offset: 0,
+                    length: 72
+                },
+                NativeDataBlock {
+                    offset: 128,
+                    length: 6
+                },
+                NativeDataBlock {
+                    offset: 192,
+                    length: 36
+                }
+            ],
+            header.blocks
+        );
+        assert_eq!(228, header.data_size());
+
+        let mut bytes = vec![];
+        let data_offset = header.write_to(&mut bytes)?;
+        assert_eq!(data_offset, bytes.len() as u64);
+        assert_eq!(0, data_offset % NativeHeader::DATA_ALIGNMENT);
+
+        let (read_header, read_data_offset) = NativeHeader::read_from(Cursor::new(&bytes))?;
+        assert_eq!(header, read_header);
+        assert_eq!(data_offset, read_data_offset);
+
+        bytes[0] = b'X';
+        assert!(NativeHeader::read_from(Cursor::new(&bytes)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_native_header_with_reserved_length() -> Result<()> {
+        let layout = PointLayout::from_attributes(&[POSITION_3D, INTENSITY]);
+        let max_header_length =
+            NativeHeader::max_header_length(layout.clone(), NativeMemoryLayout::Columnar)?;
+
+        let empty_header = NativeHeader::new(layout.clone(), 0, NativeMemoryLayout::Columnar);
+        let mut bytes = vec![];
+        let data_offset = empty_header.write_to_with_length(&mut bytes, max_header_length)?;
+
+        let header = NativeHeader::new(layout, 1 << 40, NativeMemoryLayout::Columnar);
+        let mut patched_bytes = vec![];
+        assert_eq!(
+            data_offset,
+            header.write_to_with_length(&mut patched_bytes, max_header_length)?
+        );
+        assert_eq!(bytes.len(), patched_bytes.len());
+        let (read_header, read_data_offset) = NativeHeader::read_from(Cursor::new(&patched_bytes))?;
+        assert_eq!(header, read_header);
+        assert_eq!(data_offset, read_data_offset);
+
+        assert!(header.write_to_with_length(vec![], 10).is_err());
+        Ok(())
+    }
+}
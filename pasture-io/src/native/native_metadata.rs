@@ -0,0 +1,65 @@
+use std::{any::Any, fmt::Display};
+
+use pasture_core::{math::AABB, meta::Metadata};
+
+use super::{NativeHeader, NativeMemoryLayout};
+
+/// `Metadata` implementation for native pasture files
+#[derive(Clone, Debug)]
+pub struct NativeMetadata {
+    header: NativeHeader,
+}
+
+impl NativeMetadata {
+    /// Creates new `NativeMetadata` from the given `NativeHeader`
+    pub fn new(header: NativeHeader) -> Self {
+        Self { header }
+    }
+
+    /// Returns the header of the native pasture file
+    pub fn header(&self) -> &NativeHeader {
+        &self.header
+    }
+
+    /// Returns the memory layout of the point data within the native pasture file
+    pub fn memory_layout(&self) -> NativeMemoryLayout {
+        self.header.memory_layout
+    }
+
+    /// Returns the number of points in the native pasture file
+    pub fn point_count(&self) -> usize {
+        self.header.point_count as usize
+    }
+}
+
+impl Display for NativeMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "NativeMetadata {{")?;
+        writeln!(f, "\t\"points\": {}", self.header.point_count)?;
+        writeln!(f, "\t\"memory_layout\": {:?}", self.header.memory_layout)?;
+        writeln!(f, "\t\"point_layout\": {}", self.header.point_layout)?;
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl Metadata for NativeMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        None
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.point_count())
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "MEMORY_LAYOUT" => Some(Box::new(self.header.memory_layout)),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::{
+    containers::{
+        BorrowedMutBuffer, ExternalMemoryBuffer, HashMapBuffer, MakeBufferFromLayout, OwningBuffer,
+    },
+    layout::{conversion::BufferLayoutConverter, PointLayout},
+    meta::Metadata,
+    nalgebra::clamp,
+};
+
+use crate::base::{MappedPointData, PointReader, SeekToPoint};
+
+use super::{NativeHeader, NativeMemoryLayout, NativeMetadata};
+
+/// `PointReader` implementation for native pasture files. Since native files store the points in their exact
+/// in-memory representation, reading them in their default `PointLayout` is a plain memory copy. Interleaved files
+/// are copied point range by point range, columnar files are copied attribute by attribute, which is very efficient
+/// for columnar buffers such as `HashMapBuffer`.
+///
+/// Interleaved files can also be memory-mapped using [`NativeReader::map_from_path`], which does not copy the
+/// points at all
+pub struct NativeReader<R: Read + Seek> {
+    reader: R,
+    metadata: NativeMetadata,
+    offset_to_point_data: u64,
+    current_point_index: usize,
+}
+
+impl<R: Read + Seek> NativeReader<R> {
+    /// Creates a new `NativeReader` from the given `read`
+    ///
+    /// # Errors
+    ///
+    /// If `read` does not contain a valid native pasture file, an error is returned
+    pub fn from_read(mut read: R) -> Result<Self> {
+        let (header, offset_to_point_data) =
+            NativeHeader::read_from(&mut read).context("Could not read native pasture header")?;
+        Ok(Self {
+            reader: read,
+            metadata: NativeMetadata::new(header),
+            offset_to_point_data,
+            current_point_index: 0,
+        })
+    }
+
+    /// Returns the `NativeMetadata` of the associated native pasture file
+    pub fn native_metadata(&self) -> &NativeMetadata {
+        &self.metadata
+    }
+
+    fn remaining_points(&self) -> usize {
+        self.metadata.point_count() - self.current_point_index
+    }
+
+    /// Reads the bytes of all attributes of the points in `point_range` from a columnar file, calling `f` with the
+    /// index of the attribute and its bytes
+    fn read_columns<F: FnMut(usize, &[u8])>(
+        &mut self,
+        point_range: std::ops::Range<usize>,
+        column: &mut Vec<u8>,
+        mut f: F,
+    ) -> Result<()> {
+        let header = self.metadata.header();
+        for (attribute_index, (attribute, block)) in header
+            .point_layout
+            .attributes()
+            .zip(header.blocks.iter())
+            .enumerate()
+        {
+            let size = attribute.size();
+            column.resize(point_range.len() * size as usize, 0);
+            self.reader.seek(SeekFrom::Start(
+                self.offset_to_point_data + block.offset + point_range.start as u64 * size,
+            ))?;
+            self.reader
+                .read_exact(column)
+                .context("Unexpected end of native pasture file")?;
+            f(attribute_index, column);
+        }
+        Ok(())
+    }
+}
+
+impl NativeReader<BufReader<File>> {
+    /// Creates a new `NativeReader` by opening the file at the given `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_read(reader)
+    }
+
+    /// Memory-maps the points of the interleaved native pasture file at `path`. Only the header is parsed, the returned
+    /// buffer points directly into the mapped file and has the `PointLayout` that the points were written with. For a
+    /// file without points, an empty buffer is returned
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be opened or mapped, is not a valid native pasture file, or stores its points in
+    /// columnar memory layout, an error is returned
+    pub fn map_from_path<P: AsRef<Path>>(path: P) -> Result<ExternalMemoryBuffer<MappedPointData>> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("Could not open file {}", path.as_ref().display()))?;
+        let (header, offset_to_point_data) = NativeHeader::read_from(BufReader::new(&file))
+            .context("Could not read native pasture header")?;
+        if header.memory_layout != NativeMemoryLayout::Interleaved {
+            bail!("Only native pasture files with interleaved memory layout can be memory-mapped");
+        }
+        let data_size = header.data_size();
+        if file.metadata()?.len() < offset_to_point_data + data_size {
+            bail!("Unexpected end of native pasture file");
+        }
+        // Safe as long as the file is not modified while it is mapped, which is the same contract that all
+        // memory-mapped file APIs have
+        let point_data = unsafe { MappedPointData::map(&file, offset_to_point_data, data_size) }
+            .context("Could not memory-map native pasture file")?;
+        Ok(ExternalMemoryBuffer::new(point_data, header.point_layout))
+    }
+}
+
+impl<R: Read + Seek> PointReader for NativeReader<R> {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let num_points_to_read = usize::min(count, self.remaining_points());
+        if num_points_to_read == 0 {
+            return Ok(0);
+        }
+
+        let source_layout = self.metadata.header().point_layout.clone();
+        let target_layout = point_buffer.point_layout().clone();
+        let converter = if source_layout == target_layout {
+            None
+        } else {
+            Some(BufferLayoutConverter::for_layouts_with_default(
+                &source_layout,
+                &target_layout,
+            ))
+        };
+
+        // Read point data in chunks of ~1MiB size so that we don't have to allocate a second buffer for all points
+        const CHUNK_MEM_SIZE: usize = 1 << 20;
+        let size_of_point = source_layout.size_of_point_entry() as usize;
+        let num_points_per_chunk = usize::max(1, CHUNK_MEM_SIZE / size_of_point.max(1));
+        let mut chunk = vec![];
+        let mut columnar_chunk = HashMapBuffer::new_from_layout(source_layout.clone());
+        let mut first_point_in_chunk = 0;
+        while first_point_in_chunk < num_points_to_read {
+            let points_in_chunk =
+                num_points_per_chunk.min(num_points_to_read - first_point_in_chunk);
+            let source_range =
+                self.current_point_index..(self.current_point_index + points_in_chunk);
+            let target_range = first_point_in_chunk..(first_point_in_chunk + points_in_chunk);
+
+            match self.metadata.memory_layout() {
+                NativeMemoryLayout::Interleaved => {
+                    chunk.resize(points_in_chunk * size_of_point, 0);
+                    self.reader
+                        .read_exact(&mut chunk)
+                        .context("Unexpected end of native pasture file")?;
+                    match &converter {
+                        Some(converter) => {
+                            let chunk_buffer =
+                                ExternalMemoryBuffer::new(&chunk[..], source_layout.clone());
+                            converter.convert_into_range(
+                                &chunk_buffer,
+                                0..points_in_chunk,
+                                point_buffer,
+                                target_range,
+                            );
+                        }
+                        // Safe because the buffer has the same `PointLayout` as the chunk
+                        None => unsafe {
+                            point_buffer.set_point_range(target_range, &chunk);
+                        },
+                    }
+                }
+                NativeMemoryLayout::Columnar => {
+                    let attributes = source_layout.attributes().collect::<Vec<_>>();
+                    match &converter {
+                        Some(converter) => {
+                            columnar_chunk.resize(points_in_chunk);
+                            self.read_columns(source_range, &mut chunk, |index, column| {
+                                // Safe because the chunk buffer has the same `PointLayout` as the file
+                                unsafe {
+                                    columnar_chunk.set_attribute_range(
+                                        attributes[index].attribute_definition(),
+                                        0..points_in_chunk,
+                                        column,
+                                    );
+                                }
+                            })?;
+                            converter.convert_into_range(
+                                &columnar_chunk,
+                                0..points_in_chunk,
+                                point_buffer,
+                                target_range,
+                            );
+                        }
+                        None => {
+                            self.read_columns(source_range, &mut chunk, |index, column| {
+                                // Safe because the buffer has the same `PointLayout` as the file
+                                unsafe {
+                                    point_buffer.set_attribute_range(
+                                        attributes[index].attribute_definition(),
+                                        target_range.clone(),
+                                        column,
+                                    );
+                                }
+                            })?;
+                        }
+                    }
+                }
+            }
+            self.current_point_index += points_in_chunk;
+            first_point_in_chunk += points_in_chunk;
+        }
+
+        Ok(num_points_to_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.metadata.header().point_layout
+    }
+}
+
+impl<R: Read + Seek> SeekToPoint for NativeReader<R> {
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        let point_count = self.metadata.point_count();
+        let new_point_idx: i64 = match position {
+            SeekFrom::Start(offset) => offset.try_into()?,
+            SeekFrom::End(offset) => point_count as i64 + offset,
+            SeekFrom::Current(offset) => self.current_point_index as i64 + offset,
+        };
+        let new_point_idx = clamp(new_point_idx, 0, point_count as i64) as usize;
+        // Columnar files seek to each attribute block during reading
+        if self.metadata.memory_layout() == NativeMemoryLayout::Interleaved {
+            let size_of_point = self.metadata.header().point_layout.size_of_point_entry();
+            self.reader.seek(SeekFrom::Start(
+                self.offset_to_point_data + new_point_idx as u64 * size_of_point,
+            ))?;
+        }
+        self.current_point_index = new_point_idx;
+        Ok(self.current_point_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pasture_core::{
+        containers::{BorrowedBuffer, InterleavedBuffer, VectorBuffer},
+        layout::{attributes::POSITION_3D, PointType},
+        nalgebra::Vector3,
+    };
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::{
+        base::PointWriter,
+        native::{NativeMemoryLayout, NativeWriter},
+    };
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct NativeTestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(attribute = "Curvature")]
+        curvature: f32,
+    }
+
+    fn test_points() -> Vec<NativeTestPoint> {
+        (0..10)
+            .map(|idx| NativeTestPoint {
+                position: Vector3::new(idx as f64, idx as f64 * 2.0, idx as f64 * 3.0),
+                curvature: idx as f32 * 0.25,
+            })
+            .collect()
+    }
+
+    fn write_test_points(memory_layout: NativeMemoryLayout) -> Result<Vec<u8>> {
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        let mut bytes = Cursor::new(vec![]);
+        let mut writer = NativeWriter::from_write_and_layout(
+            &mut bytes,
+            NativeTestPoint::layout(),
+            memory_layout,
+        );
+        writer.write(&points)?;
+        drop(writer);
+        Ok(bytes.into_inner())
+    }
+
+    fn test_seek_and_convert(memory_layout: NativeMemoryLayout) -> Result<()> {
+        let bytes = write_test_points(memory_layout)?;
+        let mut reader = NativeReader::from_read(Cursor::new(bytes))?;
+        assert_eq!(7, reader.seek_point(SeekFrom::Start(7))?);
+
+        let positions_layout = PointLayout::from_attributes(&[POSITION_3D]);
+        let mut positions = VectorBuffer::with_capacity(10, positions_layout);
+        positions.resize(10);
+        assert_eq!(3, reader.read_into(&mut positions, 10)?);
+        let expected_positions = test_points()[7..]
+            .iter()
+            .map(|point| point.position)
+            .collect::<Vec<_>>();
+        let actual_positions = positions
+            .view_attribute::<Vector3<f64>>(&POSITION_3D)
+            .into_iter()
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(expected_positions, actual_positions);
+
+        assert_eq!(10, reader.seek_point(SeekFrom::Current(5))?);
+        assert_eq!(0, reader.read_into(&mut positions, 10)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_native_seek_and_convert_interleaved() -> Result<()> {
+        test_seek_and_convert(NativeMemoryLayout::Interleaved)
+    }
+
+    #[test]
+    fn test_native_seek_and_convert_columnar() -> Result<()> {
+        test_seek_and_convert(NativeMemoryLayout::Columnar)
+    }
+
+    #[test]
+    fn test_native_memory_mapped_reading() -> Result<()> {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_native_memory_mapped_reading.pasture");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        std::fs::write(&path, write_test_points(NativeMemoryLayout::Interleaved)?)?;
+        let mapped_points = NativeReader::map_from_path(&path)?;
+        assert_eq!(&NativeTestPoint::layout(), mapped_points.point_layout());
+        let expected_points = test_points();
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&expected_points),
+            mapped_points.get_point_range_ref(0..mapped_points.len())
+        );
+        assert_eq!(
+            expected_points,
+            mapped_points
+                .view::<NativeTestPoint>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        std::fs::write(&path, write_test_points(NativeMemoryLayout::Columnar)?)?;
+        assert!(NativeReader::map_from_path(&path).is_err());
+
+        let mut empty_file = Cursor::new(vec![]);
+        NativeWriter::from_write_and_layout(
+            &mut empty_file,
+            NativeTestPoint::layout(),
+            NativeMemoryLayout::Interleaved,
+        )
+        .flush()?;
+        std::fs::write(&path, empty_file.into_inner())?;
+        let mapped_points = NativeReader::map_from_path(&path)?;
+        assert_eq!(0, mapped_points.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,539 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::{containers::BorrowedBuffer, layout::PointLayout};
+
+use crate::base::PointWriter;
+
+use super::{NativeHeader, NativeMemoryLayout};
+
+/// Number of points that are converted at once when writing points from a buffer whose memory layout differs from the
+/// memory layout of the file
+const POINTS_PER_CHUNK: usize = 1 << 16;
+
+/// Temporary files that hold the values of each attribute for a columnar file until the number of points, and with it
+/// the offset of each column within the file, is known. The files are removed once they are dropped
+struct TemporaryColumns {
+    paths: Vec<PathBuf>,
+    writers: Vec<BufWriter<File>>,
+}
+
+impl TemporaryColumns {
+    fn new(point_layout: &PointLayout) -> Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut columns = Self {
+            paths: vec![],
+            writers: vec![],
+        };
+        for attribute_index in 0..point_layout.attributes().count() {
+            let path = std::env::temp_dir().join(format!(
+                "pasture_native_{}_{}_{}.bin",
+                std::process::id(),
+                id,
+                attribute_index
+            ));
+            let file = File::create(&path)
+                .with_context(|| format!("Could not create temporary file {}", path.display()))?;
+            columns.paths.push(path);
+            columns.writers.push(BufWriter::new(file));
+        }
+        Ok(columns)
+    }
+}
+
+impl Drop for TemporaryColumns {
+    fn drop(&mut self) {
+        self.writers.clear();
+        for path in &self.paths {
+            // Removing a temporary file can only fail if it was already removed, so the error can be ignored
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Where a `NativeWriter` puts the points until they are flushed
+enum PointSink {
+    /// Points are written directly into the file, behind a header for which `reserved_header_length` bytes are
+    /// reserved. The header starts at `header_position` and is written once the first points arrive
+    Interleaved {
+        header_position: Option<u64>,
+        reserved_header_length: u64,
+    },
+    /// The values of each attribute are written into a temporary file, see `TemporaryColumns`
+    Columnar(Option<TemporaryColumns>),
+}
+
+/// `PointWriter` implementation for native pasture files. Points are written in their exact in-memory representation
+/// together with their `PointLayout`, so any `PointLayout` (including custom attributes and custom datatypes) is
+/// supported and the points can be read back without any loss or conversion. This makes native pasture files a good
+/// fit for caching intermediate results.
+///
+/// Points are not kept in memory. In interleaved files, the points are written directly behind the header, and the
+/// header is updated with the final number of points during `flush`. Columnar files store each attribute in a
+/// contiguous block whose position depends on the number of points, so the values of each attribute are written into
+/// a temporary file first, and the temporary files are copied into the columnar file during `flush`. Points can't be
+/// written after the writer has been flushed
+pub struct NativeWriter<W: Write + Seek> {
+    writer: W,
+    point_layout: PointLayout,
+    memory_layout: NativeMemoryLayout,
+    point_count: usize,
+    sink: PointSink,
+    requires_flush: bool,
+}
+
+impl<W: Write + Seek> NativeWriter<W> {
+    /// Creates a new `NativeWriter` that writes points with the given `point_layout` using the given `memory_layout`
+    /// to `writer`
+    pub fn from_write_and_layout(
+        writer: W,
+        point_layout: PointLayout,
+        memory_layout: NativeMemoryLayout,
+    ) -> Self {
+        let sink = match memory_layout {
+            NativeMemoryLayout::Interleaved => PointSink::Interleaved {
+                header_position: None,
+                reserved_header_length: 0,
+            },
+            NativeMemoryLayout::Columnar => PointSink::Columnar(None),
+        };
+        Self {
+            writer,
+            point_layout,
+            memory_layout,
+            point_count: 0,
+            sink,
+            requires_flush: true,
+        }
+    }
+
+    /// Returns the `NativeMemoryLayout` that this writer uses
+    pub fn memory_layout(&self) -> NativeMemoryLayout {
+        self.memory_layout
+    }
+
+    /// Writes the header with the current number of points, reserving enough space for the header of any number of
+    /// points
+    fn write_header_of_interleaved_file(&mut self) -> Result<()> {
+        if let PointSink::Interleaved {
+            header_position,
+            reserved_header_length,
+        } = &mut self.sink
+        {
+            let header = NativeHeader::new(
+                self.point_layout.clone(),
+                self.point_count,
+                self.memory_layout,
+            );
+            match header_position {
+                Some(position) => {
+                    let end_of_points = self.writer.stream_position()?;
+                    self.writer.seek(SeekFrom::Start(*position))?;
+                    header.write_to_with_length(&mut self.writer, *reserved_header_length)?;
+                    self.writer.seek(SeekFrom::Start(end_of_points))?;
+                }
+                None => {
+                    *header_position = Some(self.writer.stream_position()?);
+                    *reserved_header_length = NativeHeader::max_header_length(
+                        self.point_layout.clone(),
+                        self.memory_layout,
+                    )?;
+                    header.write_to_with_length(&mut self.writer, *reserved_header_length)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_interleaved<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if let PointSink::Interleaved {
+            header_position: None,
+            ..
+        } = self.sink
+        {
+            self.write_header_of_interleaved_file()
+                .context("Failed to write native pasture header")?;
+        }
+
+        if let Some(interleaved_points) = points.as_interleaved() {
+            self.writer
+                .write_all(interleaved_points.get_point_range_ref(0..points.len()))
+                .context("Failed to write points")?;
+            return Ok(());
+        }
+        let size_of_point = self.point_layout.size_of_point_entry() as usize;
+        let mut chunk = vec![0; size_of_point * POINTS_PER_CHUNK.min(points.len())];
+        for chunk_start in (0..points.len()).step_by(POINTS_PER_CHUNK) {
+            let chunk_range = chunk_start..(chunk_start + POINTS_PER_CHUNK).min(points.len());
+            let chunk_bytes = &mut chunk[..chunk_range.len() * size_of_point];
+            points.get_point_range(chunk_range, chunk_bytes);
+            self.writer
+                .write_all(chunk_bytes)
+                .context("Failed to write points")?;
+        }
+        Ok(())
+    }
+
+    fn write_columnar<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        let columns = match &mut self.sink {
+            PointSink::Columnar(columns) => {
+                if columns.is_none() {
+                    *columns = Some(TemporaryColumns::new(&self.point_layout)?);
+                }
+                columns.as_mut().expect("Temporary columns must exist")
+            }
+            PointSink::Interleaved { .. } => unreachable!("Sink must match the memory layout"),
+        };
+
+        let columnar_points = points.as_columnar();
+        let mut values = vec![];
+        for (attribute, column) in self
+            .point_layout
+            .attributes()
+            .zip(columns.writers.iter_mut())
+        {
+            let attribute = attribute.attribute_definition();
+            if let Some(columnar_points) = columnar_points {
+                column.write_all(
+                    columnar_points.get_attribute_range_ref(attribute, 0..points.len()),
+                )?;
+                continue;
+            }
+            for chunk_start in (0..points.len()).step_by(POINTS_PER_CHUNK) {
+                let chunk_range = chunk_start..(chunk_start + POINTS_PER_CHUNK).min(points.len());
+                values.resize(chunk_range.len() * attribute.size() as usize, 0);
+                points.get_attribute_range(attribute, chunk_range, &mut values);
+                column.write_all(&values)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the header and copies the values of each attribute from the temporary files into the columnar file
+    fn write_columnar_file(&mut self) -> Result<()> {
+        let columns = match &mut self.sink {
+            PointSink::Columnar(columns) => columns.take(),
+            PointSink::Interleaved { .. } => unreachable!("Sink must match the memory layout"),
+        };
+        let header = NativeHeader::new(
+            self.point_layout.clone(),
+            self.point_count,
+            self.memory_layout,
+        );
+        header
+            .write_to(&mut self.writer)
+            .context("Failed to write native pasture header")?;
+        let mut columns = match columns {
+            Some(columns) => columns,
+            None => return Ok(()),
+        };
+
+        let mut position = 0;
+        let column_writers = std::mem::take(&mut columns.writers);
+        for ((attribute, block), (column_writer, path)) in self
+            .point_layout
+            .attributes()
+            .zip(header.blocks.iter())
+            .zip(column_writers.into_iter().zip(columns.paths.iter()))
+        {
+            let padding = block.offset - position;
+            self.writer.write_all(&vec![0; padding as usize])?;
+            // Close the temporary file before reading it back
+            drop(column_writer.into_inner()?);
+            let mut column = BufReader::new(File::open(path)?);
+            let copied_bytes = io::copy(&mut column, &mut self.writer).with_context(|| {
+                format!("Failed to write values of attribute {}", attribute.name())
+            })?;
+            if copied_bytes != block.length {
+                bail!(
+                    "Temporary file for attribute {} has {} bytes, but {} bytes were expected",
+                    attribute.name(),
+                    copied_bytes,
+                    block.length
+                );
+            }
+            position = block.offset + block.length;
+        }
+        Ok(())
+    }
+}
+
+impl NativeWriter<BufWriter<File>> {
+    /// Creates a new `NativeWriter` that writes points with the given `point_layout` using the given `memory_layout`
+    /// to a new file at `path`
+    pub fn from_path_and_layout<P: AsRef<Path>>(
+        path: P,
+        point_layout: PointLayout,
+        memory_layout: NativeMemoryLayout,
+    ) -> Result<Self> {
+        let file = BufWriter::new(File::create(path.as_ref()).with_context(|| {
+            format!(
+                "Could not open file {} for writing",
+                path.as_ref().display()
+            )
+        })?);
+        Ok(Self::from_write_and_layout(
+            file,
+            point_layout,
+            memory_layout,
+        ))
+    }
+}
+
+impl<W: Write + Seek> PointWriter for NativeWriter<W> {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if points.point_layout() != &self.point_layout {
+            bail!("PointLayout of buffer does not match the PointLayout that this NativeWriter was constructed with");
+        }
+        if !self.requires_flush {
+            bail!("Can't write points into a NativeWriter that has already been flushed");
+        }
+        if points.is_empty() {
+            return Ok(());
+        }
+        match self.memory_layout {
+            NativeMemoryLayout::Interleaved => self.write_interleaved(points)?,
+            NativeMemoryLayout::Columnar => self.write_columnar(points)?,
+        }
+        self.point_count += points.len();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.requires_flush {
+            return Ok(());
+        }
+        match self.memory_layout {
+            NativeMemoryLayout::Interleaved => self
+                .write_header_of_interleaved_file()
+                .context("Failed to write native pasture header")?,
+            NativeMemoryLayout::Columnar => self.write_columnar_file()?,
+        }
+        self.writer.flush()?;
+        self.requires_flush = false;
+        Ok(())
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.point_layout
+    }
+}
+
+impl<W: Write + Seek> Drop for NativeWriter<W> {
+    fn drop(&mut self) {
+        self.flush().expect("Error while flushing NativeWriter")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, io::Cursor};
+
+    use pasture_core::{
+        containers::{
+            ColumnarBuffer, HashMapBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer,
+            SliceBuffer, VectorBuffer,
+        },
+        layout::{
+            attributes::{INTENSITY, NORMAL, POSITION_3D},
+            FieldAlignment, PointAttributeDataType, PointAttributeDefinition,
+        },
+    };
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{base::PointReader, native::NativeReader};
+
+    const SEGMENT_ID: PointAttributeDefinition =
+        PointAttributeDefinition::custom(Cow::Borrowed("SegmentID"), PointAttributeDataType::U32);
+
+    fn feature_attribute() -> PointAttributeDefinition {
+        PointAttributeDefinition::custom(
+            Cow::Borrowed("Feature"),
+            PointAttributeDataType::Custom {
+                size: 6,
+                min_alignment: 2,
+                name: Uuid::from_u128(0x6f1c_2a5e_93d4_4b7a_8e0f_1d2c_3b4a_5968),
+            },
+        )
+    }
+
+    /// A layout with custom attributes, a custom datatype and padding bytes between the attributes
+    fn test_layout() -> PointLayout {
+        let mut layout = PointLayout::default();
+        layout.add_attribute(POSITION_3D, FieldAlignment::Default);
+        layout.add_attribute(INTENSITY, FieldAlignment::Default);
+        layout.add_attribute(
+            NORMAL.with_custom_datatype(PointAttributeDataType::Vec3f32),
+            FieldAlignment::Default,
+        );
+        layout.add_attribute(SEGMENT_ID, FieldAlignment::Default);
+        layout.add_attribute(feature_attribute(), FieldAlignment::Default);
+        layout
+    }
+
+    /// Creates `count` points with arbitrary bytes, including the padding bytes
+    fn test_points(count: usize) -> VectorBuffer {
+        let layout = test_layout();
+        let bytes = (0..count * layout.size_of_point_entry() as usize)
+            .map(|idx| (idx * 31 + 7) as u8)
+            .collect::<Vec<_>>();
+        let mut points = VectorBuffer::new_from_layout(layout);
+        // Safe because `bytes` contains a whole number of points
+        unsafe {
+            points.push_points(&bytes);
+        }
+        points
+    }
+
+    fn assert_columns_eq<'a, 'b, L: ColumnarBuffer<'a>, R: ColumnarBuffer<'b>>(
+        expected: &'a L,
+        actual: &'b R,
+    ) {
+        assert_eq!(expected.point_layout(), actual.point_layout());
+        assert_eq!(expected.len(), actual.len());
+        for attribute in expected.point_layout().attributes() {
+            assert_eq!(
+                expected
+                    .get_attribute_range_ref(attribute.attribute_definition(), 0..expected.len()),
+                actual.get_attribute_range_ref(attribute.attribute_definition(), 0..actual.len()),
+                "Values of attribute {} differ",
+                attribute.name()
+            );
+        }
+    }
+
+    fn write_points<'a, B: BorrowedBuffer<'a>>(
+        points: &'a B,
+        memory_layout: NativeMemoryLayout,
+    ) -> Result<Vec<u8>> {
+        let mut bytes = Cursor::new(vec![]);
+        {
+            let mut writer = NativeWriter::from_write_and_layout(
+                &mut bytes,
+                points.point_layout().clone(),
+                memory_layout,
+            );
+            assert_eq!(memory_layout, writer.memory_layout());
+            writer.write(points)?;
+            writer.flush()?;
+            assert!(writer.write(points).is_err());
+        }
+        Ok(bytes.into_inner())
+    }
+
+    #[test]
+    fn test_native_round_trip_interleaved() -> Result<()> {
+        const COUNT: usize = 100;
+        let points = test_points(COUNT);
+        let bytes = write_points(&points, NativeMemoryLayout::Interleaved)?;
+
+        let mut reader = NativeReader::from_read(Cursor::new(&bytes))?;
+        assert_eq!(&test_layout(), reader.get_default_point_layout());
+        assert_eq!(Some(COUNT), reader.get_metadata().number_of_points());
+        let read_points = reader.read::<VectorBuffer>(COUNT)?;
+        assert_eq!(points.point_layout(), read_points.point_layout());
+        assert_eq!(
+            points.get_point_range_ref(0..COUNT),
+            read_points.get_point_range_ref(0..read_points.len())
+        );
+
+        let mut reader = NativeReader::from_read(Cursor::new(&bytes))?;
+        let read_columnar_points = reader.read::<HashMapBuffer>(COUNT)?;
+        let mut expected_columnar_points = HashMapBuffer::new_from_layout(test_layout());
+        expected_columnar_points.append(&points);
+        assert_columns_eq(&expected_columnar_points, &read_columnar_points);
+        Ok(())
+    }
+
+    #[test]
+    fn test_native_round_trip_columnar() -> Result<()> {
+        const COUNT: usize = 100;
+        let mut points = HashMapBuffer::new_from_layout(test_layout());
+        points.append(&test_points(COUNT));
+        let bytes = write_points(&points, NativeMemoryLayout::Columnar)?;
+
+        let mut reader = NativeReader::from_read(Cursor::new(&bytes))?;
+        assert_eq!(
+            NativeMemoryLayout::Columnar,
+            reader.native_metadata().memory_layout()
+        );
+        let read_points = reader.read::<HashMapBuffer>(COUNT)?;
+        assert_columns_eq(&points, &read_points);
+
+        let mut reader = NativeReader::from_read(Cursor::new(&bytes))?;
+        let read_interleaved_points = reader.read::<VectorBuffer>(COUNT)?;
+        // Padding bytes are not part of a columnar file, so we can only compare the attribute values
+        let mut read_columns = HashMapBuffer::new_from_layout(test_layout());
+        read_columns.append(&read_interleaved_points);
+        assert_columns_eq(&points, &read_columns);
+        Ok(())
+    }
+
+    #[test]
+    fn test_native_write_in_multiple_chunks() -> Result<()> {
+        const COUNT: usize = 100;
+        let interleaved_points = test_points(COUNT);
+        let mut columnar_points = HashMapBuffer::new_from_layout(test_layout());
+        columnar_points.append(&interleaved_points);
+
+        for memory_layout in [
+            NativeMemoryLayout::Interleaved,
+            NativeMemoryLayout::Columnar,
+        ]
+        .iter()
+        {
+            let mut bytes = Cursor::new(vec![]);
+            {
+                let mut writer =
+                    NativeWriter::from_write_and_layout(&mut bytes, test_layout(), *memory_layout);
+                writer.write(&interleaved_points.slice(0..30))?;
+                writer.write(&columnar_points.slice(30..30))?;
+                writer.write(&columnar_points.slice(30..80))?;
+                writer.write(&interleaved_points.slice(80..COUNT))?;
+            }
+
+            let mut reader = NativeReader::from_read(Cursor::new(bytes.into_inner()))?;
+            assert_eq!(Some(COUNT), reader.get_metadata().number_of_points());
+            let read_points = reader.read::<HashMapBuffer>(COUNT)?;
+            assert_columns_eq(&columnar_points, &read_points);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_native_generic_reader_and_writer() -> Result<()> {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_native_generic_reader_and_writer.pasture");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        let points = test_points(20);
+        crate::base::write_all(&points, &path)?;
+        let read_points = crate::base::read_all::<VectorBuffer, _>(&path)?;
+        assert_eq!(points.point_layout(), read_points.point_layout());
+        assert_eq!(
+            points.get_point_range_ref(0..points.len()),
+            read_points.get_point_range_ref(0..read_points.len())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_native_writer_layout_mismatch() {
+        let points = test_points(10);
+        let mut writer = NativeWriter::from_write_and_layout(
+            Cursor::new(vec![]),
+            PointLayout::from_attributes(&[POSITION_3D]),
+            NativeMemoryLayout::Interleaved,
+        );
+        assert!(writer.write(&points).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Implementation of the LZF compression format (as defined by liblzf), which is used by the `binary_compressed`
+//! encoding of PCD files
+
+use anyhow::{bail, Result};
+
+/// Maximum back-reference offset supported by LZF
+const MAX_OFFSET: usize = 1 << 13;
+/// Maximum length of a back-reference supported by LZF
+const MAX_REFERENCE_LENGTH: usize = (1 << 8) + (1 << 3);
+/// Maximum length of a literal run supported by LZF
+const MAX_LITERAL_LENGTH: usize = 1 << 5;
+const HASH_LOG: usize = 14;
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
+    (value.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+/// Compresses `data` using LZF
+pub(crate) fn lzf_compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + data.len() / 16 + 2);
+    let mut hash_table: Vec<Option<usize>> = vec![None; 1 << HASH_LOG];
+
+    // Every literal run is preceded by a control byte that stores the run length, which we only know
+    // once the run is finished, so we remember its position
+    let mut literal_control_position = output.len();
+    let mut literal_length = 0;
+    output.push(0);
+
+    let mut position = 0;
+    while position < data.len() {
+        let reference = if position + 2 < data.len() {
+            let hash_value = hash(&data[position..]);
+            let candidate = hash_table[hash_value];
+            hash_table[hash_value] = Some(position);
+            candidate.filter(|&candidate| {
+                position - candidate <= MAX_OFFSET
+                    && data[candidate..candidate + 3] == data[position..position + 3]
+            })
+        } else {
+            None
+        };
+
+        match reference {
+            Some(reference) => {
+                let max_length = MAX_REFERENCE_LENGTH.min(data.len() - position);
+                let mut length = 3;
+                while length < max_length && data[reference + length] == data[position + length] {
+                    length += 1;
+                }
+
+                if literal_length > 0 {
+                    output[literal_control_position] = (literal_length - 1) as u8;
+                } else {
+                    output.pop();
+                }
+
+                let offset = position - reference - 1;
+                let encoded_length = length - 2;
+                if encoded_length < 7 {
+                    output.push(((offset >> 8) + (encoded_length << 5)) as u8);
+                } else {
+                    output.push(((offset >> 8) + (7 << 5)) as u8);
+                    output.push((encoded_length - 7) as u8);
+                }
+                output.push((offset & 0xff) as u8);
+
+                literal_control_position = output.len();
+                literal_length = 0;
+                output.push(0);
+                position += length;
+            }
+            None => {
+                output.push(data[position]);
+                literal_length += 1;
+                position += 1;
+                if literal_length == MAX_LITERAL_LENGTH {
+                    output[literal_control_position] = (literal_length - 1) as u8;
+                    literal_control_position = output.len();
+                    literal_length = 0;
+                    output.push(0);
+                }
+            }
+        }
+    }
+
+    if literal_length > 0 {
+        output[literal_control_position] = (literal_length - 1) as u8;
+    } else {
+        output.pop();
+    }
+    output
+}
+
+/// Decompresses the LZF-compressed `data`, which must decompress to exactly `uncompressed_size` bytes
+pub(crate) fn lzf_decompress(data: &[u8], uncompressed_size: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(uncompressed_size);
+    let mut position = 0;
+    while position < data.len() {
+        let control = data[position] as usize;
+        position += 1;
+
+        if control < MAX_LITERAL_LENGTH {
+            let length = control + 1;
+            if position + length > data.len() {
+                bail!("Invalid LZF data: Literal run exceeds input");
+            }
+            output.extend_from_slice(&data[position..position + length]);
+            position += length;
+        } else {
+            let mut length = control >> 5;
+            if length == 7 {
+                if position >= data.len() {
+                    bail!("Invalid LZF data: Unexpected end of input");
+                }
+                length += data[position] as usize;
+                position += 1;
+            }
+            if position >= data.len() {
+                bail!("Invalid LZF data: Unexpected end of input");
+            }
+            let offset = ((control & 0x1f) << 8) + data[position] as usize + 1;
+            position += 1;
+            if offset > output.len() {
+                bail!("Invalid LZF data: Back-reference points before the start of the output");
+            }
+            // Back-references may overlap with the bytes that they produce, so copy byte by byte
+            let start = output.len() - offset;
+            for idx in 0..length + 2 {
+                let byte = output[start + idx];
+                output.push(byte);
+            }
+        }
+
+        if output.len() > uncompressed_size {
+            bail!("Invalid LZF data: Decompressed data exceeds the expected size");
+        }
+    }
+
+    if output.len() != uncompressed_size {
+        bail!(
+            "Invalid LZF data: Expected {} decompressed bytes but got {}",
+            uncompressed_size,
+            output.len()
+        );
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn test_round_trip(data: &[u8]) {
+        let compressed = lzf_compress(data);
+        let decompressed = lzf_decompress(&compressed, data.len()).expect("Decompression failed");
+        assert_eq!(data, decompressed.as_slice());
+    }
+
+    #[test]
+    fn test_lzf_round_trip() {
+        test_round_trip(&[]);
+        test_round_trip(&[42]);
+        test_round_trip(b"abcabcabcabcabcabcabcabcabcabc");
+        test_round_trip(&vec![7; 10_000]);
+
+        let mut rng = thread_rng();
+        let random_bytes = (0..100_000)
+            .map(|_| rng.gen_range(0..4_u8))
+            .collect::<Vec<_>>();
+        test_round_trip(&random_bytes);
+
+        let repetitive = (0..50_000_u32)
+            .flat_map(|idx| (idx / 100).to_le_bytes())
+            .collect::<Vec<_>>();
+        let compressed = lzf_compress(&repetitive);
+        assert!(compressed.len() < repetitive.len() / 4);
+        test_round_trip(&repetitive);
+    }
+
+    #[test]
+    fn test_lzf_decompress_known_data() {
+        // Literal 'a', followed by a back-reference of length 5 with offset 1
+        let compressed = [0, b'a', 3 << 5, 0];
+        assert_eq!(b"aaaaaa".to_vec(), lzf_decompress(&compressed, 6).unwrap());
+        assert!(lzf_decompress(&compressed, 5).is_err());
+        assert!(lzf_decompress(&[3 << 5, 0], 5).is_err());
+    }
+}
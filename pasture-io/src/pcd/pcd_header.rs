@@ -0,0 +1,479 @@
+use std::{
+    convert::TryInto,
+    fmt::Display,
+    io::{BufRead, Write},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use pasture_core::layout::PointAttributeDataType;
+
+/// The encoding of the point data in a PCD file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PcdDataEncoding {
+    /// Points are stored as whitespace-separated text, one point per line
+    Ascii,
+    /// Points are stored as binary records in interleaved memory layout
+    Binary,
+    /// Points are stored in columnar memory layout, compressed with LZF
+    BinaryCompressed,
+}
+
+impl PcdDataEncoding {
+    /// Returns the name of this encoding as it appears in the `DATA` line of a PCD header
+    pub fn name(&self) -> &'static str {
+        match self {
+            PcdDataEncoding::Ascii => "ascii",
+            PcdDataEncoding::Binary => "binary",
+            PcdDataEncoding::BinaryCompressed => "binary_compressed",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "ascii" => Ok(PcdDataEncoding::Ascii),
+            "binary" => Ok(PcdDataEncoding::Binary),
+            "binary_compressed" => Ok(PcdDataEncoding::BinaryCompressed),
+            other => bail!("Unsupported PCD data encoding {other}"),
+        }
+    }
+}
+
+impl Display for PcdDataEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The type of the values of a PCD field (the `TYPE` entry in the PCD header)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PcdFieldType {
+    /// Signed integer (`I`)
+    Signed,
+    /// Unsigned integer (`U`)
+    Unsigned,
+    /// Floating point (`F`)
+    Float,
+}
+
+impl PcdFieldType {
+    /// Returns the character that identifies this type in a PCD header
+    pub fn identifier(&self) -> char {
+        match self {
+            PcdFieldType::Signed => 'I',
+            PcdFieldType::Unsigned => 'U',
+            PcdFieldType::Float => 'F',
+        }
+    }
+
+    fn from_identifier(identifier: &str) -> Result<Self> {
+        match identifier {
+            "I" => Ok(PcdFieldType::Signed),
+            "U" => Ok(PcdFieldType::Unsigned),
+            "F" => Ok(PcdFieldType::Float),
+            other => bail!("Unsupported PCD field type {other}"),
+        }
+    }
+}
+
+/// A single field of a PCD file, as defined by the `FIELDS`, `SIZE`, `TYPE` and `COUNT` entries of the header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PcdField {
+    pub name: String,
+    /// Size of a single value of this field in bytes
+    pub size: usize,
+    pub field_type: PcdFieldType,
+    /// Number of values of this field per point
+    pub count: usize,
+}
+
+impl PcdField {
+    /// Creates a new `PcdField` with a single value per point
+    pub fn new(name: String, size: usize, field_type: PcdFieldType) -> Self {
+        Self {
+            name,
+            size,
+            field_type,
+            count: 1,
+        }
+    }
+
+    /// Returns the size of this field in bytes for a single point
+    pub fn byte_size(&self) -> usize {
+        self.size * self.count
+    }
+
+    /// Returns `true` if this field stores a color in the packed `0x00RRGGBB` format that PCL uses for its `rgb` and
+    /// `rgba` fields
+    pub fn is_packed_rgb(&self) -> bool {
+        (self.name == "rgb" || self.name == "rgba") && self.size == 4 && self.count == 1
+    }
+
+    /// Returns the scalar pasture datatype that corresponds to a single value of this field, if there is one
+    pub fn scalar_datatype(&self) -> Option<PointAttributeDataType> {
+        match (self.field_type, self.size) {
+            (PcdFieldType::Signed, 1) => Some(PointAttributeDataType::I8),
+            (PcdFieldType::Signed, 2) => Some(PointAttributeDataType::I16),
+            (PcdFieldType::Signed, 4) => Some(PointAttributeDataType::I32),
+            (PcdFieldType::Signed, 8) => Some(PointAttributeDataType::I64),
+            (PcdFieldType::Unsigned, 1) => Some(PointAttributeDataType::U8),
+            (PcdFieldType::Unsigned, 2) => Some(PointAttributeDataType::U16),
+            (PcdFieldType::Unsigned, 4) => Some(PointAttributeDataType::U32),
+            (PcdFieldType::Unsigned, 8) => Some(PointAttributeDataType::U64),
+            (PcdFieldType::Float, 4) => Some(PointAttributeDataType::F32),
+            (PcdFieldType::Float, 8) => Some(PointAttributeDataType::F64),
+            _ => None,
+        }
+    }
+
+    /// Returns the 3-component vector datatype whose components have the type of a single value of this field, if
+    /// pasture has one
+    pub fn vec3_datatype(&self) -> Option<PointAttributeDataType> {
+        match (self.field_type, self.size) {
+            (PcdFieldType::Unsigned, 1) => Some(PointAttributeDataType::Vec3u8),
+            (PcdFieldType::Unsigned, 2) => Some(PointAttributeDataType::Vec3u16),
+            (PcdFieldType::Signed, 4) => Some(PointAttributeDataType::Vec3i32),
+            (PcdFieldType::Float, 4) => Some(PointAttributeDataType::Vec3f32),
+            (PcdFieldType::Float, 8) => Some(PointAttributeDataType::Vec3f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the `PcdFieldType` and size of a single value of the given scalar pasture datatype, or `None` if
+    /// `datatype` is no scalar datatype
+    pub fn type_and_size_from_datatype(
+        datatype: PointAttributeDataType,
+    ) -> Option<(PcdFieldType, usize)> {
+        match datatype {
+            PointAttributeDataType::I8 => Some((PcdFieldType::Signed, 1)),
+            PointAttributeDataType::I16 => Some((PcdFieldType::Signed, 2)),
+            PointAttributeDataType::I32 => Some((PcdFieldType::Signed, 4)),
+            PointAttributeDataType::I64 => Some((PcdFieldType::Signed, 8)),
+            PointAttributeDataType::U8 => Some((PcdFieldType::Unsigned, 1)),
+            PointAttributeDataType::U16 => Some((PcdFieldType::Unsigned, 2)),
+            PointAttributeDataType::U32 => Some((PcdFieldType::Unsigned, 4)),
+            PointAttributeDataType::U64 => Some((PcdFieldType::Unsigned, 8)),
+            PointAttributeDataType::F32 => Some((PcdFieldType::Float, 4)),
+            PointAttributeDataType::F64 => Some((PcdFieldType::Float, 8)),
+            _ => None,
+        }
+    }
+
+    /// Parses a single ASCII value of this field and writes it as little-endian bytes into `bytes`. Packed colors
+    /// are written as integers by PCL, even if the field has the type `F`
+    pub(crate) fn parse_ascii_value(&self, token: &str, bytes: &mut [u8]) -> Result<()> {
+        let parse_error = || anyhow!("Invalid value {} for PCD field {}", token, self.name);
+        if self.is_packed_rgb() {
+            if let Ok(packed) = token.parse::<u32>() {
+                bytes.copy_from_slice(&packed.to_le_bytes());
+                return Ok(());
+            }
+        }
+        match (self.field_type, self.size) {
+            (PcdFieldType::Float, 4) => bytes.copy_from_slice(
+                &token
+                    .parse::<f32>()
+                    .map_err(|_| parse_error())?
+                    .to_le_bytes(),
+            ),
+            (PcdFieldType::Float, 8) => bytes.copy_from_slice(
+                &token
+                    .parse::<f64>()
+                    .map_err(|_| parse_error())?
+                    .to_le_bytes(),
+            ),
+            (PcdFieldType::Signed, size) => bytes.copy_from_slice(
+                &token
+                    .parse::<i64>()
+                    .map_err(|_| parse_error())?
+                    .to_le_bytes()[..size],
+            ),
+            (PcdFieldType::Unsigned, size) => bytes.copy_from_slice(
+                &token
+                    .parse::<u64>()
+                    .map_err(|_| parse_error())?
+                    .to_le_bytes()[..size],
+            ),
+            (PcdFieldType::Float, size) => bail!("Unsupported size {size} of float PCD field"),
+        }
+        Ok(())
+    }
+
+    /// Formats a single value of this field from its little-endian `bytes`
+    pub(crate) fn format_ascii_value(&self, bytes: &[u8]) -> String {
+        if self.is_packed_rgb() {
+            return u32::from_le_bytes(bytes[..4].try_into().unwrap()).to_string();
+        }
+        match (self.field_type, self.size) {
+            (PcdFieldType::Float, 4) => {
+                f32::from_le_bytes(bytes[..4].try_into().unwrap()).to_string()
+            }
+            (PcdFieldType::Float, _) => {
+                f64::from_le_bytes(bytes[..8].try_into().unwrap()).to_string()
+            }
+            (PcdFieldType::Signed, size) => {
+                // Sign-extend the value
+                let fill = if bytes[size - 1] & 0x80 != 0 { 0xff } else { 0 };
+                let mut extended = [fill; 8];
+                extended[..size].copy_from_slice(&bytes[..size]);
+                i64::from_le_bytes(extended).to_string()
+            }
+            (PcdFieldType::Unsigned, size) => {
+                let mut extended = [0; 8];
+                extended[..size].copy_from_slice(&bytes[..size]);
+                u64::from_le_bytes(extended).to_string()
+            }
+        }
+    }
+}
+
+/// The header of a PCD file
+#[derive(Clone, Debug, PartialEq)]
+pub struct PcdHeader {
+    pub version: String,
+    pub fields: Vec<PcdField>,
+    pub width: usize,
+    pub height: usize,
+    /// The acquisition viewpoint as translation (x, y, z) and quaternion (w, x, y, z)
+    pub viewpoint: [f64; 7],
+    pub points: usize,
+    pub data_encoding: PcdDataEncoding,
+}
+
+impl PcdHeader {
+    /// The default viewpoint of PCD files
+    pub const DEFAULT_VIEWPOINT: [f64; 7] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+
+    /// Creates a new `PcdHeader` for an unorganized point cloud with `points` points
+    pub fn new(fields: Vec<PcdField>, points: usize, data_encoding: PcdDataEncoding) -> Self {
+        Self {
+            version: "0.7".to_owned(),
+            fields,
+            width: points,
+            height: 1,
+            viewpoint: Self::DEFAULT_VIEWPOINT,
+            points,
+            data_encoding,
+        }
+    }
+
+    /// Returns the size in bytes of a single point record in a `binary` PCD file
+    pub fn size_of_point_record(&self) -> usize {
+        self.fields.iter().map(PcdField::byte_size).sum()
+    }
+
+    /// Reads a `PcdHeader` from the given `reader`. After this call, `reader` is positioned at the start of the
+    /// point data
+    pub fn read_from<R: BufRead>(mut reader: R) -> Result<Self> {
+        let mut version = None;
+        let mut names: Vec<String> = vec![];
+        let mut sizes: Vec<usize> = vec![];
+        let mut types: Vec<PcdFieldType> = vec![];
+        let mut counts: Option<Vec<usize>> = None;
+        let mut width = None;
+        let mut height = None;
+        let mut viewpoint = Self::DEFAULT_VIEWPOINT;
+        let mut points = None;
+
+        let parse_numbers = |values: &[&str], key: &str| -> Result<Vec<usize>> {
+            values
+                .iter()
+                .map(|value| {
+                    value
+                        .parse::<usize>()
+                        .with_context(|| format!("Invalid value {value} for {key} in PCD header"))
+                })
+                .collect()
+        };
+        let parse_single_number = |values: &[&str], key: &str| -> Result<usize> {
+            match parse_numbers(values, key)?.as_slice() {
+                [value] => Ok(*value),
+                _ => bail!("Expected a single value for {key} in PCD header"),
+            }
+        };
+
+        let mut line = String::new();
+        let data_encoding = loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                bail!("Unexpected end of file in PCD header");
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let tokens = trimmed.split_whitespace().collect::<Vec<_>>();
+            let (key, values) = (tokens[0], &tokens[1..]);
+            match key {
+                "VERSION" => version = values.first().map(|v| (*v).to_owned()),
+                "FIELDS" => names = values.iter().map(|v| (*v).to_owned()).collect(),
+                "SIZE" => sizes = parse_numbers(values, key)?,
+                "TYPE" => {
+                    types = values
+                        .iter()
+                        .map(|v| PcdFieldType::from_identifier(v))
+                        .collect::<Result<_>>()?
+                }
+                "COUNT" => counts = Some(parse_numbers(values, key)?),
+                "WIDTH" => width = Some(parse_single_number(values, key)?),
+                "HEIGHT" => height = Some(parse_single_number(values, key)?),
+                "VIEWPOINT" => {
+                    let values = values
+                        .iter()
+                        .map(|v| v.parse::<f64>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .context("Invalid VIEWPOINT in PCD header")?;
+                    viewpoint = values
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| anyhow!("VIEWPOINT in PCD header must have 7 values"))?;
+                }
+                "POINTS" => points = Some(parse_single_number(values, key)?),
+                "DATA" => {
+                    let encoding = values
+                        .first()
+                        .ok_or_else(|| anyhow!("Missing encoding for DATA in PCD header"))?;
+                    break PcdDataEncoding::from_name(encoding)?;
+                }
+                other => bail!("Unexpected entry {other} in PCD header"),
+            }
+        };
+
+        let counts = counts.unwrap_or_else(|| vec![1; names.len()]);
+        if sizes.len() != names.len() || types.len() != names.len() || counts.len() != names.len() {
+            bail!("The number of entries for FIELDS, SIZE, TYPE and COUNT in the PCD header do not match");
+        }
+        let fields = names
+            .into_iter()
+            .zip(sizes)
+            .zip(types)
+            .zip(counts)
+            .map(|(((name, size), field_type), count)| PcdField {
+                name,
+                size,
+                field_type,
+                count,
+            })
+            .collect();
+
+        let width = width.ok_or_else(|| anyhow!("Missing WIDTH in PCD header"))?;
+        let height = height.unwrap_or(1);
+        Ok(Self {
+            version: version.unwrap_or_else(|| "0.7".to_owned()),
+            fields,
+            width,
+            height,
+            viewpoint,
+            points: points.unwrap_or(width * height),
+            data_encoding,
+        })
+    }
+
+    /// Writes this `PcdHeader` to the given `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        let join = |values: Vec<String>| values.join(" ");
+        writeln!(
+            writer,
+            "# .PCD v{} - Point Cloud Data file format",
+            self.version
+        )?;
+        writeln!(writer, "VERSION {}", self.version)?;
+        writeln!(
+            writer,
+            "FIELDS {}",
+            join(self.fields.iter().map(|f| f.name.clone()).collect())
+        )?;
+        writeln!(
+            writer,
+            "SIZE {}",
+            join(self.fields.iter().map(|f| f.size.to_string()).collect())
+        )?;
+        writeln!(
+            writer,
+            "TYPE {}",
+            join(
+                self.fields
+                    .iter()
+                    .map(|f| f.field_type.identifier().to_string())
+                    .collect()
+            )
+        )?;
+        writeln!(
+            writer,
+            "COUNT {}",
+            join(self.fields.iter().map(|f| f.count.to_string()).collect())
+        )?;
+        writeln!(writer, "WIDTH {}", self.width)?;
+        writeln!(writer, "HEIGHT {}", self.height)?;
+        writeln!(
+            writer,
+            "VIEWPOINT {}",
+            join(self.viewpoint.iter().map(|v| v.to_string()).collect())
+        )?;
+        writeln!(writer, "POINTS {}", self.points)?;
+        writeln!(writer, "DATA {}", self.data_encoding)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_pcd_header_round_trip() -> Result<()> {
+        let header_str = "# .PCD v0.7 - Point Cloud Data file format\nVERSION 0.7\nFIELDS x y z rgb label\nSIZE 4 4 4 4 2\nTYPE F F F F U\nCOUNT 1 1 1 1 1\nWIDTH 10\nHEIGHT 2\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS 20\nDATA binary_compressed\n";
+        let header = PcdHeader::read_from(Cursor::new(header_str))?;
+        assert_eq!(5, header.fields.len());
+        assert_eq!(
+            PcdField::new("label".to_owned(), 2, PcdFieldType::Unsigned),
+            header.fields[4]
+        );
+        assert_eq!(20, header.points);
+        assert_eq!(PcdDataEncoding::BinaryCompressed, header.data_encoding);
+        assert_eq!(18, header.size_of_point_record());
+
+        let mut written = vec![];
+        header.write_to(&mut written)?;
+        assert_eq!(header, PcdHeader::read_from(Cursor::new(written))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pcd_header_defaults_and_errors() -> Result<()> {
+        let header = PcdHeader::read_from(Cursor::new(
+            "FIELDS x y\nSIZE 8 8\nTYPE F F\nWIDTH 3\nDATA ascii\n",
+        ))?;
+        assert_eq!(3, header.points);
+        assert!(header.fields.iter().all(|field| field.count == 1));
+
+        assert!(PcdHeader::read_from(Cursor::new(
+            "FIELDS x y\nSIZE 8\nTYPE F F\nWIDTH 3\nDATA ascii\n"
+        ))
+        .is_err());
+        assert!(PcdHeader::read_from(Cursor::new("FIELDS x\nSIZE 8\nTYPE F\nWIDTH 3\n")).is_err());
+        assert!(PcdHeader::read_from(Cursor::new(
+            "FIELDS x\nSIZE 8\nTYPE F\nWIDTH 3\nDATA lzma\n"
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pcd_ascii_values() -> Result<()> {
+        let field = PcdField::new("value".to_owned(), 2, PcdFieldType::Signed);
+        let mut bytes = [0; 2];
+        field.parse_ascii_value("-3", &mut bytes)?;
+        assert_eq!((-3_i16).to_le_bytes(), bytes);
+        assert_eq!("-3", field.format_ascii_value(&bytes));
+        assert!(field.parse_ascii_value("1.5", &mut bytes).is_err());
+
+        let rgb = PcdField::new("rgb".to_owned(), 4, PcdFieldType::Float);
+        let mut bytes = [0; 4];
+        rgb.parse_ascii_value("16711680", &mut bytes)?;
+        assert_eq!(0xff0000_u32.to_le_bytes(), bytes);
+        assert_eq!("16711680", rgb.format_ascii_value(&bytes));
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::{any::Any, fmt::Display};
+
+use pasture_core::{math::AABB, meta::Metadata};
+
+use super::{PcdDataEncoding, PcdHeader};
+
+/// `Metadata` implementation for PCD files
+#[derive(Clone, Debug)]
+pub struct PcdMetadata {
+    header: PcdHeader,
+}
+
+impl PcdMetadata {
+    /// Creates new `PcdMetadata` from the given `PcdHeader`
+    pub fn new(header: PcdHeader) -> Self {
+        Self { header }
+    }
+
+    /// Returns the header of the PCD file
+    pub fn header(&self) -> &PcdHeader {
+        &self.header
+    }
+
+    /// Returns the encoding of the point data of the PCD file
+    pub fn data_encoding(&self) -> PcdDataEncoding {
+        self.header.data_encoding
+    }
+
+    /// Returns the number of points in the PCD file
+    pub fn point_count(&self) -> usize {
+        self.header.points
+    }
+}
+
+impl Display for PcdMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "PcdMetadata {{")?;
+        writeln!(f, "\t\"version\": {}", self.header.version)?;
+        writeln!(f, "\t\"width\": {}", self.header.width)?;
+        writeln!(f, "\t\"height\": {}", self.header.height)?;
+        writeln!(f, "\t\"viewpoint\": {:?}", self.header.viewpoint)?;
+        writeln!(f, "\t\"points\": {}", self.header.points)?;
+        writeln!(f, "\t\"data\": {}", self.header.data_encoding)?;
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl Metadata for PcdMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        None
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.header.points)
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "VERSION" => Some(Box::new(self.header.version.clone())),
+            "WIDTH" => Some(Box::new(self.header.width)),
+            "HEIGHT" => Some(Box::new(self.header.height)),
+            "VIEWPOINT" => Some(Box::new(self.header.viewpoint)),
+            "DATA" => Some(Box::new(self.header.data_encoding)),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,742 @@
+use std::{
+    borrow::Cow,
+    convert::TryInto,
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt};
+use pasture_core::{
+    containers::{BorrowedMutBuffer, ExternalMemoryBuffer},
+    layout::{
+        attributes::{COLOR_RGB, NORMAL, POSITION_3D},
+        conversion::BufferLayoutConverter,
+        FieldAlignment, PointAttributeDataType, PointAttributeDefinition, PointLayout,
+    },
+    meta::Metadata,
+    nalgebra::clamp,
+};
+
+use crate::base::{PointReader, SeekToPoint};
+
+use super::{lzf_decompress, PcdDataEncoding, PcdField, PcdHeader, PcdMetadata};
+
+/// Name of the fields that PCL uses for padding
+pub(crate) const PCD_PADDING_FIELD_NAME: &str = "_";
+
+/// Builtin attributes that are stored as three separate fields in a PCD file, together with the names of these fields
+pub(crate) fn pcd_vector_attributes() -> [(PointAttributeDefinition, [&'static str; 3]); 2] {
+    [
+        (POSITION_3D, ["x", "y", "z"]),
+        (NORMAL, ["normal_x", "normal_y", "normal_z"]),
+    ]
+}
+
+/// Unpacks a PCL color, which is stored as `0xAARRGGBB` in a little-endian 4-byte value
+fn unpack_rgb(bytes: &[u8]) -> [u8; 3] {
+    let packed = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+    [(packed >> 16) as u8, (packed >> 8) as u8, packed as u8]
+}
+
+/// Describes where the value of a single PCD field goes within a point of the default `PointLayout`
+#[derive(Copy, Clone, Debug)]
+enum PcdFieldTarget {
+    /// Copy the bytes of the field to the given offset
+    Copy { offset_in_point: usize },
+    /// Unpack a PCL color into a `Vector3<u8>` at the given offset
+    PackedRgb { offset_in_point: usize },
+    /// Padding field that is ignored
+    Skip,
+}
+
+impl PcdFieldTarget {
+    /// Returns the offset within a point that this target writes to, or `None` for ignored fields
+    fn offset_in_point(&self) -> Option<usize> {
+        match self {
+            PcdFieldTarget::Copy { offset_in_point }
+            | PcdFieldTarget::PackedRgb { offset_in_point } => Some(*offset_in_point),
+            PcdFieldTarget::Skip => None,
+        }
+    }
+
+    /// Writes the `field_bytes` into `point`, which starts at `base_offset` within a point of the default `PointLayout`
+    fn apply(&self, field_bytes: &[u8], point: &mut [u8], base_offset: usize) {
+        match self {
+            PcdFieldTarget::Copy { offset_in_point } => {
+                let offset = offset_in_point - base_offset;
+                point[offset..offset + field_bytes.len()].copy_from_slice(field_bytes);
+            }
+            PcdFieldTarget::PackedRgb { offset_in_point } => {
+                let offset = offset_in_point - base_offset;
+                point[offset..offset + 3].copy_from_slice(&unpack_rgb(field_bytes));
+            }
+            PcdFieldTarget::Skip => {}
+        }
+    }
+}
+
+/// Creates the default `PointLayout` for the given PCD fields. The fields `x`/`y`/`z` and `normal_x`/`normal_y`/`normal_z`
+/// are combined into the `POSITION_3D` and `NORMAL` attributes, a packed `rgb` or `rgba` field becomes a `COLOR_RGB`
+/// attribute with datatype `Vec3u8`. All other fields become custom attributes with the same name. Their datatype
+/// is a scalar type for fields with a single value, a vector type for fields with three values (if possible) or
+/// a byte array otherwise
+fn layout_from_fields(fields: &[PcdField]) -> Result<(PointLayout, Vec<PcdFieldTarget>)> {
+    let field_index = |name: &str| fields.iter().position(|field| field.name == name);
+
+    // For each field: The attribute that it belongs to, the byte offset within this attribute and whether it is a packed color
+    let mut field_attributes: Vec<Option<(PointAttributeDefinition, usize, bool)>> =
+        vec![None; fields.len()];
+    for (attribute, field_names) in pcd_vector_attributes().iter() {
+        let indices = field_names
+            .iter()
+            .map(|name| field_index(name))
+            .collect::<Option<Vec<_>>>();
+        if let Some(indices) = indices {
+            let first_field = &fields[indices[0]];
+            let compatible = indices.iter().all(|idx| {
+                let field = &fields[*idx];
+                field.count == 1
+                    && field.size == first_field.size
+                    && field.field_type == first_field.field_type
+            });
+            if let (true, Some(datatype)) = (compatible, first_field.vec3_datatype()) {
+                for (component, idx) in indices.into_iter().enumerate() {
+                    field_attributes[idx] = Some((
+                        attribute.with_custom_datatype(datatype),
+                        component * first_field.size,
+                        false,
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(idx) = fields.iter().position(PcdField::is_packed_rgb) {
+        field_attributes[idx] = Some((
+            COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+            0,
+            true,
+        ));
+    }
+
+    let mut layout = PointLayout::default();
+    let mut field_targets = vec![];
+    for (field, field_attribute) in fields.iter().zip(field_attributes) {
+        if field.name == PCD_PADDING_FIELD_NAME {
+            field_targets.push(None);
+            continue;
+        }
+        let (attribute, offset_in_attribute, is_packed_rgb) = match field_attribute {
+            Some(field_attribute) => field_attribute,
+            None => {
+                if layout.has_attribute_with_name(&field.name) {
+                    bail!("Duplicate field {} in PCD file", field.name);
+                }
+                let datatype = match field.count {
+                    1 => field.scalar_datatype(),
+                    3 => field.vec3_datatype(),
+                    _ => None,
+                }
+                .unwrap_or(PointAttributeDataType::ByteArray(field.byte_size() as u64));
+                (
+                    PointAttributeDefinition::custom(Cow::Owned(field.name.clone()), datatype),
+                    0,
+                    false,
+                )
+            }
+        };
+        if !layout.has_attribute_with_name(attribute.name()) {
+            layout.add_attribute(attribute.clone(), FieldAlignment::Packed(1));
+        }
+        field_targets.push(Some((attribute, offset_in_attribute, is_packed_rgb)));
+    }
+
+    let field_targets = field_targets
+        .into_iter()
+        .map(|target| match target {
+            None => PcdFieldTarget::Skip,
+            Some((attribute, offset_in_attribute, is_packed_rgb)) => {
+                let offset_in_point = layout
+                    .get_attribute_by_name(attribute.name())
+                    .expect("Attribute not found in PointLayout")
+                    .offset() as usize
+                    + offset_in_attribute;
+                if is_packed_rgb {
+                    PcdFieldTarget::PackedRgb { offset_in_point }
+                } else {
+                    PcdFieldTarget::Copy { offset_in_point }
+                }
+            }
+        })
+        .collect();
+    Ok((layout, field_targets))
+}
+
+/// `PointReader` implementation for PCD files, the point cloud format of the [Point Cloud Library](https://pointclouds.org/).
+/// Supports the `ascii`, `binary` and `binary_compressed` encodings. Binary data is assumed to be little-endian.
+///
+/// `binary_compressed` files store their points in columnar memory layout. They are decompressed once when the reader
+/// is created. If the points are read with the default `PointLayout`, each attribute is copied as a whole into the
+/// target buffer, which is very efficient for columnar buffers such as `HashMapBuffer`
+pub struct PcdReader<R: BufRead + Seek> {
+    reader: R,
+    metadata: PcdMetadata,
+    layout: PointLayout,
+    field_targets: Vec<PcdFieldTarget>,
+    /// Offset of each field within a point record of a `binary` file
+    field_offsets: Vec<usize>,
+    offset_to_point_data: u64,
+    /// Decompressed point data of a `binary_compressed` file, in columnar memory layout
+    decompressed_data: Option<Vec<u8>>,
+    current_point_index: usize,
+}
+
+impl<R: BufRead + Seek> PcdReader<R> {
+    /// Creates a new `PcdReader` from the given `read`
+    ///
+    /// # Errors
+    ///
+    /// If `read` does not contain a valid PCD file, an error is returned
+    pub fn from_read(mut read: R) -> Result<Self> {
+        let header = PcdHeader::read_from(&mut read).context("Could not read PCD header")?;
+        let (layout, field_targets) = layout_from_fields(&header.fields)?;
+        let field_offsets = header
+            .fields
+            .iter()
+            .scan(0, |offset, field| {
+                let field_offset = *offset;
+                *offset += field.byte_size();
+                Some(field_offset)
+            })
+            .collect();
+        let offset_to_point_data = read.stream_position()?;
+
+        let decompressed_data = match header.data_encoding {
+            PcdDataEncoding::BinaryCompressed => {
+                let compressed_size = read.read_u32::<LittleEndian>()? as usize;
+                let uncompressed_size = read.read_u32::<LittleEndian>()? as usize;
+                let expected_size = header.size_of_point_record() * header.points;
+                if uncompressed_size != expected_size {
+                    bail!("Uncompressed size {uncompressed_size} of compressed PCD data does not match the expected size {expected_size}");
+                }
+                let mut compressed_data = vec![0; compressed_size];
+                read.read_exact(&mut compressed_data)
+                    .context("Could not read compressed PCD data")?;
+                Some(
+                    lzf_decompress(&compressed_data, uncompressed_size)
+                        .context("Could not decompress PCD data")?,
+                )
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            reader: read,
+            metadata: PcdMetadata::new(header),
+            layout,
+            field_targets,
+            field_offsets,
+            offset_to_point_data,
+            decompressed_data,
+            current_point_index: 0,
+        })
+    }
+
+    /// Returns the `PcdMetadata` of the associated PCD file
+    pub fn pcd_metadata(&self) -> &PcdMetadata {
+        &self.metadata
+    }
+
+    fn remaining_points(&self) -> usize {
+        self.metadata.point_count() - self.current_point_index
+    }
+
+    /// Returns the offset of the column of the field at `field_index` within the decompressed data
+    fn column_offset(&self, field_index: usize) -> usize {
+        self.metadata.header().fields[..field_index]
+            .iter()
+            .map(PcdField::byte_size)
+            .sum::<usize>()
+            * self.metadata.point_count()
+    }
+
+    /// Reads the next `point_data.len() / size_of_point` points into `point_data` using the default `PointLayout`
+    fn read_points(&mut self, point_data: &mut [u8]) -> Result<()> {
+        let size_of_point = self.layout.size_of_point_entry() as usize;
+        let header = self.metadata.header();
+        match header.data_encoding {
+            PcdDataEncoding::Ascii => {
+                let mut line = String::new();
+                let mut field_bytes = vec![];
+                for point in point_data.chunks_exact_mut(size_of_point) {
+                    line.clear();
+                    if self.reader.read_line(&mut line)? == 0 {
+                        bail!("Unexpected end of file while reading PCD points");
+                    }
+                    let mut tokens = line.split_whitespace();
+                    for (field, target) in header.fields.iter().zip(self.field_targets.iter()) {
+                        field_bytes.resize(field.byte_size(), 0);
+                        for value_bytes in field_bytes.chunks_exact_mut(field.size) {
+                            let token = tokens.next().ok_or_else(|| {
+                                anyhow!("Too few values in PCD point '{}'", line.trim())
+                            })?;
+                            field.parse_ascii_value(token, value_bytes)?;
+                        }
+                        target.apply(&field_bytes, point, 0);
+                    }
+                }
+            }
+            PcdDataEncoding::Binary => {
+                let mut record = vec![0; header.size_of_point_record()];
+                for point in point_data.chunks_exact_mut(size_of_point) {
+                    self.reader
+                        .read_exact(&mut record)
+                        .context("Failed to read PCD point")?;
+                    for ((field, target), field_offset) in header
+                        .fields
+                        .iter()
+                        .zip(self.field_targets.iter())
+                        .zip(self.field_offsets.iter())
+                    {
+                        let field_bytes = &record[*field_offset..*field_offset + field.byte_size()];
+                        target.apply(field_bytes, point, 0);
+                    }
+                }
+            }
+            PcdDataEncoding::BinaryCompressed => {
+                let data = self
+                    .decompressed_data
+                    .as_ref()
+                    .expect("Missing decompressed PCD data");
+                for (field_index, (field, target)) in header
+                    .fields
+                    .iter()
+                    .zip(self.field_targets.iter())
+                    .enumerate()
+                {
+                    let field_size = field.byte_size();
+                    let column_start =
+                        self.column_offset(field_index) + self.current_point_index * field_size;
+                    let column = &data[column_start..];
+                    for (point, field_bytes) in point_data
+                        .chunks_exact_mut(size_of_point)
+                        .zip(column.chunks_exact(field_size))
+                    {
+                        target.apply(field_bytes, point, 0);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads `count` points of a `binary_compressed` file attribute by attribute into `point_buffer`, which must
+    /// have the default `PointLayout`
+    fn read_compressed_attributes<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) where
+        'a: 'b,
+    {
+        let data = self
+            .decompressed_data
+            .as_ref()
+            .expect("Missing decompressed PCD data");
+        let fields = &self.metadata.header().fields;
+        for attribute in self.layout.attributes() {
+            let attribute_range = attribute.byte_range_within_point();
+            let attribute_size = attribute.size() as usize;
+            let fields_of_attribute = fields
+                .iter()
+                .zip(self.field_targets.iter())
+                .enumerate()
+                .filter(|(_, (_, target))| {
+                    target
+                        .offset_in_point()
+                        .map(|offset| attribute_range.contains(&offset))
+                        .unwrap_or(false)
+                })
+                .collect::<Vec<_>>();
+
+            match fields_of_attribute.as_slice() {
+                // The attribute is exactly one field, so we can copy the column of the field directly
+                [(field_index, (field, PcdFieldTarget::Copy { .. }))]
+                    if field.byte_size() == attribute_size =>
+                {
+                    let column_start = self.column_offset(*field_index)
+                        + self.current_point_index * attribute_size;
+                    let column = &data[column_start..column_start + count * attribute_size];
+                    // Safe because the column contains exactly `count` values of the attribute
+                    unsafe {
+                        point_buffer.set_attribute_range(
+                            attribute.attribute_definition(),
+                            0..count,
+                            column,
+                        );
+                    }
+                }
+                _ => {
+                    let mut attribute_data = vec![0; count * attribute_size];
+                    for (field_index, (field, target)) in fields_of_attribute {
+                        let field_size = field.byte_size();
+                        let column_start =
+                            self.column_offset(field_index) + self.current_point_index * field_size;
+                        for (value, field_bytes) in attribute_data
+                            .chunks_exact_mut(attribute_size)
+                            .zip(data[column_start..].chunks_exact(field_size))
+                        {
+                            target.apply(field_bytes, value, attribute_range.start);
+                        }
+                    }
+                    // Safe because `attribute_data` contains exactly `count` values of the attribute
+                    unsafe {
+                        point_buffer.set_attribute_range(
+                            attribute.attribute_definition(),
+                            0..count,
+                            &attribute_data,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skips `count` lines in an ASCII PCD file
+    fn skip_ascii_points(&mut self, count: usize) -> Result<()> {
+        let mut line = String::new();
+        for _ in 0..count {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                bail!("Unexpected end of file while seeking in PCD points");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PcdReader<BufReader<File>> {
+    /// Creates a new `PcdReader` by opening the file at the given `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_read(reader)
+    }
+}
+
+impl<R: BufRead + Seek> PointReader for PcdReader<R> {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let num_points_to_read = usize::min(count, self.remaining_points());
+        if num_points_to_read == 0 {
+            return Ok(0);
+        }
+
+        let source_layout = self.layout.clone();
+        let target_layout = point_buffer.point_layout().clone();
+        if self.metadata.data_encoding() == PcdDataEncoding::BinaryCompressed
+            && source_layout == target_layout
+        {
+            self.read_compressed_attributes(point_buffer, num_points_to_read);
+            self.current_point_index += num_points_to_read;
+            return Ok(num_points_to_read);
+        }
+
+        let converter = if source_layout == target_layout {
+            None
+        } else {
+            Some(BufferLayoutConverter::for_layouts_with_default(
+                &source_layout,
+                &target_layout,
+            ))
+        };
+
+        // Read point data in chunks of ~1MiB size so that we don't have to allocate a second buffer for all points
+        const CHUNK_MEM_SIZE: usize = 1 << 20;
+        let size_of_point = source_layout.size_of_point_entry() as usize;
+        let num_points_per_chunk = usize::max(1, CHUNK_MEM_SIZE / size_of_point.max(1));
+        let mut chunk = vec![0; num_points_per_chunk.min(num_points_to_read) * size_of_point];
+        let mut first_point_in_chunk = 0;
+        while first_point_in_chunk < num_points_to_read {
+            let points_in_chunk =
+                num_points_per_chunk.min(num_points_to_read - first_point_in_chunk);
+            let chunk_bytes = &mut chunk[..points_in_chunk * size_of_point];
+            self.read_points(chunk_bytes)?;
+            self.current_point_index += points_in_chunk;
+
+            let target_range = first_point_in_chunk..(first_point_in_chunk + points_in_chunk);
+            match &converter {
+                Some(converter) => {
+                    let chunk_buffer =
+                        ExternalMemoryBuffer::new(&chunk_bytes[..], source_layout.clone());
+                    converter.convert_into_range(
+                        &chunk_buffer,
+                        0..points_in_chunk,
+                        point_buffer,
+                        target_range,
+                    );
+                }
+                // Safe because the buffer has the same `PointLayout` as the chunk
+                None => unsafe {
+                    point_buffer.set_point_range(target_range, chunk_bytes);
+                },
+            }
+            first_point_in_chunk += points_in_chunk;
+        }
+
+        Ok(num_points_to_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+impl<R: BufRead + Seek> SeekToPoint for PcdReader<R> {
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        let point_count = self.metadata.point_count();
+        let new_point_idx: i64 = match position {
+            SeekFrom::Start(offset) => offset.try_into()?,
+            SeekFrom::End(offset) => point_count as i64 + offset,
+            SeekFrom::Current(offset) => self.current_point_index as i64 + offset,
+        };
+        let new_point_idx = clamp(new_point_idx, 0, point_count as i64) as usize;
+        if new_point_idx == self.current_point_index {
+            return Ok(new_point_idx);
+        }
+
+        match self.metadata.data_encoding() {
+            PcdDataEncoding::Ascii => {
+                // Points in ASCII files have variable size, so we have to skip them line by line
+                if new_point_idx < self.current_point_index {
+                    self.reader
+                        .seek(SeekFrom::Start(self.offset_to_point_data))?;
+                    self.current_point_index = 0;
+                }
+                self.skip_ascii_points(new_point_idx - self.current_point_index)?;
+            }
+            PcdDataEncoding::Binary => {
+                let offset = self.offset_to_point_data
+                    + (new_point_idx * self.metadata.header().size_of_point_record()) as u64;
+                self.reader.seek(SeekFrom::Start(offset))?;
+            }
+            PcdDataEncoding::BinaryCompressed => {}
+        }
+        self.current_point_index = new_point_idx;
+        Ok(self.current_point_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pasture_core::{
+        containers::{
+            BorrowedBuffer, HashMapBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+        },
+        layout::PointType,
+        nalgebra::Vector3,
+    };
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::pcd::lzf_compress;
+
+    const ASCII_PCD: &str = "# .PCD v0.7 - Point Cloud Data file format
+VERSION 0.7
+FIELDS x y z rgb intensity _ descriptor
+SIZE 4 4 4 4 2 1 4
+TYPE F F F F U U F
+COUNT 1 1 1 1 1 1 2
+WIDTH 3
+HEIGHT 1
+VIEWPOINT 0 0 0 1 0 0 0
+POINTS 3
+DATA ascii
+1 2 3 16711680 100 0 0.5 1.5
+4 5 6 65280 200 0 2.5 3.5
+7 8 9 255 300 0 4.5 5.5
+";
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct PcdTestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u16>,
+    }
+
+    fn intensity_attribute() -> PointAttributeDefinition {
+        PointAttributeDefinition::custom(Cow::Borrowed("intensity"), PointAttributeDataType::U16)
+    }
+
+    fn expected_layout() -> PointLayout {
+        PointLayout::from_attributes_packed(
+            &[
+                POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32),
+                COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+                intensity_attribute(),
+                PointAttributeDefinition::custom(
+                    Cow::Borrowed("descriptor"),
+                    PointAttributeDataType::ByteArray(8),
+                ),
+            ],
+            1,
+        )
+    }
+
+    fn check_points<'a, B: BorrowedBuffer<'a>>(points: &'a B) {
+        assert_eq!(3, points.len());
+        let positions = points
+            .view_attribute::<Vector3<f32>>(
+                &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Vector3::new(1.0, 2.0, 3.0),
+                Vector3::new(4.0, 5.0, 6.0),
+                Vector3::new(7.0, 8.0, 9.0)
+            ],
+            positions
+        );
+        let colors = points
+            .view_attribute::<Vector3<u8>>(
+                &COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Vector3::new(255, 0, 0),
+                Vector3::new(0, 255, 0),
+                Vector3::new(0, 0, 255)
+            ],
+            colors
+        );
+        let intensities = points
+            .view_attribute::<u16>(&intensity_attribute())
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(vec![100, 200, 300], intensities);
+    }
+
+    #[test]
+    fn test_read_ascii_pcd() -> Result<()> {
+        let mut reader = PcdReader::from_read(Cursor::new(ASCII_PCD))?;
+        assert_eq!(&expected_layout(), reader.get_default_point_layout());
+        assert_eq!(Some(3), reader.get_metadata().number_of_points());
+        let points = reader.read::<VectorBuffer>(3)?;
+        check_points(&points);
+        Ok(())
+    }
+
+    /// Encodes the points of `ASCII_PCD` as binary records
+    fn binary_records() -> Vec<Vec<u8>> {
+        let header = PcdHeader::read_from(Cursor::new(ASCII_PCD)).unwrap();
+        ASCII_PCD
+            .lines()
+            .skip(11)
+            .map(|line| {
+                let mut tokens = line.split_whitespace();
+                let mut record = vec![];
+                for field in &header.fields {
+                    for _ in 0..field.count {
+                        let mut bytes = vec![0; field.size];
+                        field
+                            .parse_ascii_value(tokens.next().unwrap(), &mut bytes)
+                            .unwrap();
+                        record.extend(bytes);
+                    }
+                }
+                record
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_binary_pcd() -> Result<()> {
+        let mut data = ASCII_PCD
+            .replace("DATA ascii", "DATA binary")
+            .lines()
+            .take(11)
+            .map(|line| format!("{line}\n"))
+            .collect::<String>()
+            .into_bytes();
+        for record in binary_records() {
+            data.extend(record);
+        }
+
+        let mut reader = PcdReader::from_read(Cursor::new(data))?;
+        let points = reader.read::<HashMapBuffer>(3)?;
+        check_points(&points);
+
+        reader.seek_point(SeekFrom::Start(1))?;
+        let mut custom_points = VectorBuffer::new_from_layout(PcdTestPoint::layout());
+        custom_points.resize(2);
+        assert_eq!(2, reader.read_into(&mut custom_points, 2)?);
+        assert_eq!(
+            PcdTestPoint {
+                position: Vector3::new(4.0, 5.0, 6.0),
+                color: Vector3::new(0, 255, 0),
+            },
+            custom_points.view::<PcdTestPoint>().at(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_binary_compressed_pcd() -> Result<()> {
+        let header = PcdHeader::read_from(Cursor::new(ASCII_PCD))?;
+        let records = binary_records();
+        // Transpose the records into columnar memory layout
+        let mut columns = vec![];
+        let mut field_offset = 0;
+        for field in &header.fields {
+            for record in &records {
+                columns.extend_from_slice(&record[field_offset..field_offset + field.byte_size()]);
+            }
+            field_offset += field.byte_size();
+        }
+        let compressed = lzf_compress(&columns);
+
+        let mut data = ASCII_PCD
+            .replace("DATA ascii", "DATA binary_compressed")
+            .lines()
+            .take(11)
+            .map(|line| format!("{line}\n"))
+            .collect::<String>()
+            .into_bytes();
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend((columns.len() as u32).to_le_bytes());
+        data.extend(compressed);
+
+        let mut reader = PcdReader::from_read(Cursor::new(data))?;
+        let points = reader.read::<HashMapBuffer>(3)?;
+        check_points(&points);
+
+        reader.seek_point(SeekFrom::Start(2))?;
+        let points = reader.read::<VectorBuffer>(3)?;
+        assert_eq!(1, points.len());
+        assert_eq!(
+            300,
+            points.view_attribute::<u16>(&intensity_attribute()).at(0)
+        );
+        Ok(())
+    }
+}
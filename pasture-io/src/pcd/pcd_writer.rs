@@ -0,0 +1,480 @@
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{LittleEndian, WriteBytesExt};
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+    },
+    layout::{attributes::COLOR_RGB, FieldAlignment, PointAttributeDataType, PointLayout},
+};
+
+use crate::base::PointWriter;
+
+use super::{
+    lzf_compress, pcd_vector_attributes, PcdDataEncoding, PcdField, PcdFieldType, PcdHeader,
+};
+
+/// Returns the `PcdFieldType` and size of a single component of the given 3-component vector datatype
+fn vec3_component_type_and_size(datatype: PointAttributeDataType) -> Option<(PcdFieldType, usize)> {
+    match datatype {
+        PointAttributeDataType::Vec3u8 => Some((PcdFieldType::Unsigned, 1)),
+        PointAttributeDataType::Vec3u16 => Some((PcdFieldType::Unsigned, 2)),
+        PointAttributeDataType::Vec3i32 => Some((PcdFieldType::Signed, 4)),
+        PointAttributeDataType::Vec3f32 => Some((PcdFieldType::Float, 4)),
+        PointAttributeDataType::Vec3f64 => Some((PcdFieldType::Float, 8)),
+        _ => None,
+    }
+}
+
+/// Describes how the value of a single PCD field is obtained from a point of the written `PointLayout`
+#[derive(Copy, Clone, Debug)]
+enum PcdFieldSource {
+    /// Copy the bytes of the field from the given offset
+    Copy { offset_in_point: usize },
+    /// Pack a `Vector3<u8>` color at the given offset into a PCL color
+    PackRgbU8 { offset_in_point: usize },
+    /// Pack a `Vector3<u16>` color at the given offset into a PCL color, keeping the upper 8 bits of each channel
+    PackRgbU16 { offset_in_point: usize },
+}
+
+impl PcdFieldSource {
+    /// Writes the bytes of the field for the given `point` into `field_bytes`
+    fn extract(&self, point: &[u8], field_bytes: &mut [u8]) {
+        match self {
+            PcdFieldSource::Copy { offset_in_point } => field_bytes
+                .copy_from_slice(&point[*offset_in_point..*offset_in_point + field_bytes.len()]),
+            PcdFieldSource::PackRgbU8 { offset_in_point } => {
+                let rgb = &point[*offset_in_point..*offset_in_point + 3];
+                let packed = ((rgb[0] as u32) << 16) | ((rgb[1] as u32) << 8) | rgb[2] as u32;
+                field_bytes.copy_from_slice(&packed.to_le_bytes());
+            }
+            PcdFieldSource::PackRgbU16 { offset_in_point } => {
+                let channel = |idx: usize| {
+                    let offset = offset_in_point + idx * 2;
+                    (u16::from_le_bytes(point[offset..offset + 2].try_into().unwrap()) >> 8) as u32
+                };
+                let packed = (channel(0) << 16) | (channel(1) << 8) | channel(2);
+                field_bytes.copy_from_slice(&packed.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// `PointWriter` implementation for PCD files, the point cloud format of the [Point Cloud Library](https://pointclouds.org/).
+/// Supports the `ascii`, `binary` and `binary_compressed` encodings. Binary data is written in little-endian byte order.
+///
+/// The builtin attributes `POSITION_3D` and `NORMAL` are written as the `x`/`y`/`z` and `normal_x`/`normal_y`/`normal_z`
+/// fields, `COLOR_RGB` with datatype `Vec3u8` or `Vec3u16` is written as a packed `rgb` field like PCL does it (keeping
+/// only the upper 8 bits of 16-bit colors). All other attributes with a scalar, vector or byte array datatype are written
+/// as a field with the name of the attribute. Attributes with custom datatypes are silently ignored!
+///
+/// Since the PCD header contains the number of points, all points are cached in memory and are only written
+/// during `flush`. Points can't be written after the writer has been flushed
+pub struct PcdWriter<W: Write> {
+    writer: W,
+    data_encoding: PcdDataEncoding,
+    expected_layout: PointLayout,
+    default_layout: PointLayout,
+    cached_points: VectorBuffer,
+    fields: Vec<PcdField>,
+    field_sources: Vec<PcdFieldSource>,
+    requires_flush: bool,
+}
+
+impl<W: Write> PcdWriter<W> {
+    /// Creates a new `PcdWriter` that writes points with the given `point_layout` using the given `data_encoding`
+    /// to `writer`
+    pub fn from_write_and_layout(
+        writer: W,
+        point_layout: PointLayout,
+        data_encoding: PcdDataEncoding,
+    ) -> Self {
+        let mut default_layout = PointLayout::default();
+        let mut fields = vec![];
+        let mut field_sources = vec![];
+        let vector_attributes = pcd_vector_attributes();
+        for attribute in point_layout.attributes() {
+            let offset = attribute.offset() as usize;
+            let datatype = attribute.datatype();
+            let vector_field_names = vector_attributes
+                .iter()
+                .find(|(vector_attribute, _)| vector_attribute.name() == attribute.name())
+                .map(|(_, names)| names);
+            let field_name = attribute.name().replace(char::is_whitespace, "_");
+
+            match (vector_field_names, vec3_component_type_and_size(datatype)) {
+                (Some(names), Some((field_type, size))) => {
+                    for (component, name) in names.iter().enumerate() {
+                        fields.push(PcdField::new((*name).to_owned(), size, field_type));
+                        field_sources.push(PcdFieldSource::Copy {
+                            offset_in_point: offset + component * size,
+                        });
+                    }
+                }
+                _ if attribute.name() == COLOR_RGB.name()
+                    && (datatype == PointAttributeDataType::Vec3u8
+                        || datatype == PointAttributeDataType::Vec3u16) =>
+                {
+                    fields.push(PcdField::new("rgb".to_owned(), 4, PcdFieldType::Float));
+                    field_sources.push(if datatype == PointAttributeDataType::Vec3u8 {
+                        PcdFieldSource::PackRgbU8 {
+                            offset_in_point: offset,
+                        }
+                    } else {
+                        PcdFieldSource::PackRgbU16 {
+                            offset_in_point: offset,
+                        }
+                    });
+                }
+                (_, Some((field_type, size))) => {
+                    fields.push(PcdField {
+                        name: field_name,
+                        size,
+                        field_type,
+                        count: 3,
+                    });
+                    field_sources.push(PcdFieldSource::Copy {
+                        offset_in_point: offset,
+                    });
+                }
+                (_, None) => {
+                    let field = match datatype {
+                        PointAttributeDataType::Vec4u8 => PcdField {
+                            name: field_name,
+                            size: 1,
+                            field_type: PcdFieldType::Unsigned,
+                            count: 4,
+                        },
+                        PointAttributeDataType::ByteArray(length) => PcdField {
+                            name: field_name,
+                            size: 1,
+                            field_type: PcdFieldType::Unsigned,
+                            count: length as usize,
+                        },
+                        other => match PcdField::type_and_size_from_datatype(other) {
+                            Some((field_type, size)) => PcdField::new(field_name, size, field_type),
+                            None => continue,
+                        },
+                    };
+                    fields.push(field);
+                    field_sources.push(PcdFieldSource::Copy {
+                        offset_in_point: offset,
+                    });
+                }
+            }
+            default_layout.add_attribute(
+                attribute.attribute_definition().clone(),
+                FieldAlignment::Packed(1),
+            );
+        }
+
+        Self {
+            writer,
+            data_encoding,
+            cached_points: VectorBuffer::new_from_layout(point_layout.clone()),
+            expected_layout: point_layout,
+            default_layout,
+            fields,
+            field_sources,
+            requires_flush: true,
+        }
+    }
+
+    /// Returns the `PcdDataEncoding` that this writer uses
+    pub fn data_encoding(&self) -> PcdDataEncoding {
+        self.data_encoding
+    }
+
+    /// Calls `f` with the index and bytes of each field of the point at `point_index`
+    fn for_each_field_of_point<F: FnMut(usize, &[u8]) -> Result<()>>(
+        &self,
+        point_index: usize,
+        field_bytes: &mut Vec<u8>,
+        mut f: F,
+    ) -> Result<()> {
+        let point = self.cached_points.get_point_ref(point_index);
+        for (field_index, (field, source)) in self
+            .fields
+            .iter()
+            .zip(self.field_sources.iter())
+            .enumerate()
+        {
+            field_bytes.resize(field.byte_size(), 0);
+            source.extract(point, field_bytes);
+            f(field_index, field_bytes)?;
+        }
+        Ok(())
+    }
+
+    fn write_cached_points(&mut self) -> Result<()> {
+        let num_points = self.cached_points.len();
+        let header = PcdHeader::new(self.fields.clone(), num_points, self.data_encoding);
+        let mut data = vec![];
+        let mut field_bytes = vec![];
+        match self.data_encoding {
+            PcdDataEncoding::Ascii => {
+                for point_index in 0..num_points {
+                    let mut values = vec![];
+                    self.for_each_field_of_point(point_index, &mut field_bytes, |idx, bytes| {
+                        let field = &self.fields[idx];
+                        values.extend(
+                            bytes
+                                .chunks_exact(field.size)
+                                .map(|value| field.format_ascii_value(value)),
+                        );
+                        Ok(())
+                    })?;
+                    writeln!(data, "{}", values.join(" "))?;
+                }
+            }
+            PcdDataEncoding::Binary => {
+                for point_index in 0..num_points {
+                    self.for_each_field_of_point(point_index, &mut field_bytes, |_, bytes| {
+                        data.extend_from_slice(bytes);
+                        Ok(())
+                    })?;
+                }
+            }
+            PcdDataEncoding::BinaryCompressed => {
+                // Compressed data is stored in columnar memory layout, one column per field
+                let mut columns = self
+                    .fields
+                    .iter()
+                    .map(|field| Vec::with_capacity(field.byte_size() * num_points))
+                    .collect::<Vec<_>>();
+                for point_index in 0..num_points {
+                    self.for_each_field_of_point(point_index, &mut field_bytes, |idx, bytes| {
+                        columns[idx].extend_from_slice(bytes);
+                        Ok(())
+                    })?;
+                }
+                let uncompressed = columns.concat();
+                let compressed = lzf_compress(&uncompressed);
+                data.write_u32::<LittleEndian>(compressed.len() as u32)?;
+                data.write_u32::<LittleEndian>(uncompressed.len() as u32)?;
+                data.extend(compressed);
+            }
+        }
+
+        header
+            .write_to(&mut self.writer)
+            .context("Failed to write PCD header")?;
+        self.writer
+            .write_all(&data)
+            .context("Failed to write PCD points")?;
+        self.writer.flush()?;
+        self.requires_flush = false;
+        Ok(())
+    }
+}
+
+impl PcdWriter<BufWriter<File>> {
+    /// Creates a new `PcdWriter` that writes points with the given `point_layout` using the given `data_encoding`
+    /// to a new file at `path`
+    pub fn from_path_and_layout<P: AsRef<Path>>(
+        path: P,
+        point_layout: PointLayout,
+        data_encoding: PcdDataEncoding,
+    ) -> Result<Self> {
+        let file = BufWriter::new(File::create(path.as_ref()).with_context(|| {
+            format!(
+                "Could not open file {} for writing",
+                path.as_ref().display()
+            )
+        })?);
+        Ok(Self::from_write_and_layout(
+            file,
+            point_layout,
+            data_encoding,
+        ))
+    }
+}
+
+impl<W: Write> PointWriter for PcdWriter<W> {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if points.point_layout() != &self.expected_layout {
+            bail!("PointLayout of buffer does not match the PointLayout that this PcdWriter was constructed with");
+        }
+        if !self.requires_flush {
+            bail!("Can't write points into a PcdWriter that has already been flushed");
+        }
+        self.cached_points.append(points);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.requires_flush {
+            return Ok(());
+        }
+        self.write_cached_points()
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.default_layout
+    }
+}
+
+impl<W: Write> Drop for PcdWriter<W> {
+    fn drop(&mut self) {
+        self.flush().expect("Error while flushing PcdWriter")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, io::Cursor};
+
+    use pasture_core::{
+        layout::{
+            attributes::{INTENSITY, NORMAL, POSITION_3D},
+            PointAttributeDefinition, PointType,
+        },
+        nalgebra::Vector3,
+    };
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::{
+        base::{PointReader, SeekToPoint},
+        pcd::PcdReader,
+    };
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct PcdWriterTestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u16>,
+        #[pasture(BUILTIN_NORMAL)]
+        normal: Vector3<f32>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+        #[pasture(attribute = "Label")]
+        label: i32,
+    }
+
+    fn test_points() -> Vec<PcdWriterTestPoint> {
+        (0..1000)
+            .map(|idx| PcdWriterTestPoint {
+                position: Vector3::new(idx as f64 + 0.125, -(idx as f64), 1e6 + idx as f64),
+                color: Vector3::new((idx % 256) << 8, (255 - idx % 256) << 8, 128 << 8),
+                normal: Vector3::new(0.0, (idx % 10) as f32 * 0.5, 1.0),
+                intensity: idx * 10,
+                label: idx as i32 / 100 - 5,
+            })
+            .collect()
+    }
+
+    fn test_pcd_round_trip(data_encoding: PcdDataEncoding) -> Result<()> {
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        let mut data = vec![];
+        {
+            let mut writer = PcdWriter::from_write_and_layout(
+                &mut data,
+                PcdWriterTestPoint::layout(),
+                data_encoding,
+            );
+            writer.write(&points)?;
+            writer.flush()?;
+            assert!(writer.write(&points).is_err());
+        }
+
+        let mut reader = PcdReader::from_read(Cursor::new(data))?;
+        assert_eq!(data_encoding, reader.pcd_metadata().data_encoding());
+        let label_attribute =
+            PointAttributeDefinition::custom(Cow::Borrowed("Label"), PointAttributeDataType::I32);
+        // Colors are written as packed 8-bit values
+        let expected_layout = PointLayout::from_attributes_packed(
+            &[
+                POSITION_3D,
+                COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+                NORMAL,
+                INTENSITY,
+                label_attribute.clone(),
+            ],
+            1,
+        );
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+
+        let mut read_points = VectorBuffer::new_from_layout(PcdWriterTestPoint::layout());
+        read_points.resize(points.len());
+        assert_eq!(
+            points.len(),
+            reader.read_into(&mut read_points, points.len())?
+        );
+        for (expected, actual) in test_points()
+            .into_iter()
+            .zip(read_points.view::<PcdWriterTestPoint>())
+        {
+            let (expected_position, actual_position) = (expected.position, actual.position);
+            assert_eq!(expected_position, actual_position);
+            let (expected_normal, actual_normal) = (expected.normal, actual.normal);
+            assert_eq!(expected_normal, actual_normal);
+            let (expected_intensity, actual_intensity) = (expected.intensity, actual.intensity);
+            assert_eq!(expected_intensity, actual_intensity);
+            let (expected_label, actual_label) = (expected.label, actual.label);
+            assert_eq!(expected_label, actual_label);
+        }
+
+        reader.seek_point(std::io::SeekFrom::Start(0))?;
+        let default_points = reader.read::<VectorBuffer>(points.len())?;
+        let actual_color = default_points
+            .view_attribute::<Vector3<u8>>(
+                &COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+            )
+            .at(300);
+        assert_eq!(Vector3::new(44, 211, 128), actual_color);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pcd_round_trip_ascii() -> Result<()> {
+        test_pcd_round_trip(PcdDataEncoding::Ascii)
+    }
+
+    #[test]
+    fn test_pcd_round_trip_binary() -> Result<()> {
+        test_pcd_round_trip(PcdDataEncoding::Binary)
+    }
+
+    #[test]
+    fn test_pcd_round_trip_binary_compressed() -> Result<()> {
+        test_pcd_round_trip(PcdDataEncoding::BinaryCompressed)
+    }
+
+    #[test]
+    fn test_pcd_generic_reader_and_writer() -> Result<()> {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_pcd_generic_reader_and_writer.pcd");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        crate::base::write_all(&points, &path)?;
+        let read_points = crate::base::read_all::<VectorBuffer, _>(&path)?;
+        assert_eq!(points.len(), read_points.len());
+        let expected_labels = test_points()
+            .iter()
+            .map(|point| point.label)
+            .collect::<Vec<_>>();
+        let actual_labels = read_points
+            .view_attribute::<i32>(&PointAttributeDefinition::custom(
+                Cow::Borrowed("Label"),
+                PointAttributeDataType::I32,
+            ))
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(expected_labels, actual_labels);
+        Ok(())
+    }
+}
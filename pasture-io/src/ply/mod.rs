@@ -0,0 +1,11 @@
+mod ply_header;
+pub use self::ply_header::*;
+
+mod ply_metadata;
+pub use self::ply_metadata::*;
+
+mod ply_reader;
+pub use self::ply_reader::*;
+
+mod ply_writer;
+pub use self::ply_writer::*;
@@ -0,0 +1,520 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Read, Write},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use pasture_core::layout::PointAttributeDataType;
+
+/// The encoding of the element data in a PLY file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    /// Elements are stored as whitespace-separated text, one element per line
+    Ascii,
+    /// Elements are stored as binary little-endian values
+    BinaryLittleEndian,
+    /// Elements are stored as binary big-endian values
+    BinaryBigEndian,
+}
+
+impl PlyFormat {
+    /// Returns the name of this format as it appears in the `format` line of a PLY header
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "ascii" => Ok(PlyFormat::Ascii),
+            "binary_little_endian" => Ok(PlyFormat::BinaryLittleEndian),
+            "binary_big_endian" => Ok(PlyFormat::BinaryBigEndian),
+            other => bail!("Unsupported PLY format {other}"),
+        }
+    }
+}
+
+impl Display for PlyFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The scalar datatypes supported by the PLY format
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyScalarType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl PlyScalarType {
+    /// Returns the name of this type as it is written into a PLY header
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlyScalarType::Char => "char",
+            PlyScalarType::UChar => "uchar",
+            PlyScalarType::Short => "short",
+            PlyScalarType::UShort => "ushort",
+            PlyScalarType::Int => "int",
+            PlyScalarType::UInt => "uint",
+            PlyScalarType::Float => "float",
+            PlyScalarType::Double => "double",
+        }
+    }
+
+    /// Parses a PLY type name. Both the classic names (e.g. `uchar`) and the sized names (e.g. `uint8`) are supported
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "char" | "int8" => Ok(PlyScalarType::Char),
+            "uchar" | "uint8" => Ok(PlyScalarType::UChar),
+            "short" | "int16" => Ok(PlyScalarType::Short),
+            "ushort" | "uint16" => Ok(PlyScalarType::UShort),
+            "int" | "int32" => Ok(PlyScalarType::Int),
+            "uint" | "uint32" => Ok(PlyScalarType::UInt),
+            "float" | "float32" => Ok(PlyScalarType::Float),
+            "double" | "float64" => Ok(PlyScalarType::Double),
+            other => bail!("Unsupported PLY property type {other}"),
+        }
+    }
+
+    /// Size of this type in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            PlyScalarType::Char | PlyScalarType::UChar => 1,
+            PlyScalarType::Short | PlyScalarType::UShort => 2,
+            PlyScalarType::Int | PlyScalarType::UInt | PlyScalarType::Float => 4,
+            PlyScalarType::Double => 8,
+        }
+    }
+
+    /// Returns the corresponding pasture datatype
+    pub fn datatype(&self) -> PointAttributeDataType {
+        match self {
+            PlyScalarType::Char => PointAttributeDataType::I8,
+            PlyScalarType::UChar => PointAttributeDataType::U8,
+            PlyScalarType::Short => PointAttributeDataType::I16,
+            PlyScalarType::UShort => PointAttributeDataType::U16,
+            PlyScalarType::Int => PointAttributeDataType::I32,
+            PlyScalarType::UInt => PointAttributeDataType::U32,
+            PlyScalarType::Float => PointAttributeDataType::F32,
+            PlyScalarType::Double => PointAttributeDataType::F64,
+        }
+    }
+
+    /// Returns the PLY type for the given scalar pasture datatype, or `None` if PLY has no matching type
+    /// (e.g. for 64-bit integers)
+    pub fn from_datatype(datatype: PointAttributeDataType) -> Option<Self> {
+        match datatype {
+            PointAttributeDataType::I8 => Some(PlyScalarType::Char),
+            PointAttributeDataType::U8 => Some(PlyScalarType::UChar),
+            PointAttributeDataType::I16 => Some(PlyScalarType::Short),
+            PointAttributeDataType::U16 => Some(PlyScalarType::UShort),
+            PointAttributeDataType::I32 => Some(PlyScalarType::Int),
+            PointAttributeDataType::U32 => Some(PlyScalarType::UInt),
+            PointAttributeDataType::F32 => Some(PlyScalarType::Float),
+            PointAttributeDataType::F64 => Some(PlyScalarType::Double),
+            _ => None,
+        }
+    }
+
+    /// Returns the 3-component vector datatype with this type as its component type, if pasture has one
+    pub fn vec3_datatype(&self) -> Option<PointAttributeDataType> {
+        match self {
+            PlyScalarType::UChar => Some(PointAttributeDataType::Vec3u8),
+            PlyScalarType::UShort => Some(PointAttributeDataType::Vec3u16),
+            PlyScalarType::Int => Some(PointAttributeDataType::Vec3i32),
+            PlyScalarType::Float => Some(PointAttributeDataType::Vec3f32),
+            PlyScalarType::Double => Some(PointAttributeDataType::Vec3f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the PLY component type of the given 3-component vector datatype, if there is one
+    pub fn from_vec3_datatype(datatype: PointAttributeDataType) -> Option<Self> {
+        match datatype {
+            PointAttributeDataType::Vec3u8 => Some(PlyScalarType::UChar),
+            PointAttributeDataType::Vec3u16 => Some(PlyScalarType::UShort),
+            PointAttributeDataType::Vec3i32 => Some(PlyScalarType::Int),
+            PointAttributeDataType::Vec3f32 => Some(PlyScalarType::Float),
+            PointAttributeDataType::Vec3f64 => Some(PlyScalarType::Double),
+            _ => None,
+        }
+    }
+
+    /// Reads a value of this type from the binary encoded `bytes` using the byte order `B`. All PLY types
+    /// are exactly representable as `f64`
+    pub fn decode<B: ByteOrder>(&self, bytes: &[u8]) -> f64 {
+        match self {
+            PlyScalarType::Char => bytes[0] as i8 as f64,
+            PlyScalarType::UChar => bytes[0] as f64,
+            PlyScalarType::Short => B::read_i16(bytes) as f64,
+            PlyScalarType::UShort => B::read_u16(bytes) as f64,
+            PlyScalarType::Int => B::read_i32(bytes) as f64,
+            PlyScalarType::UInt => B::read_u32(bytes) as f64,
+            PlyScalarType::Float => B::read_f32(bytes) as f64,
+            PlyScalarType::Double => B::read_f64(bytes),
+        }
+    }
+
+    /// Writes `value` as this type into `bytes` using the byte order `B`. Values that are out of range for
+    /// this type are saturated
+    pub fn encode<B: ByteOrder>(&self, value: f64, bytes: &mut [u8]) {
+        match self {
+            PlyScalarType::Char => bytes[0] = value as i8 as u8,
+            PlyScalarType::UChar => bytes[0] = value as u8,
+            PlyScalarType::Short => B::write_i16(bytes, value as i16),
+            PlyScalarType::UShort => B::write_u16(bytes, value as u16),
+            PlyScalarType::Int => B::write_i32(bytes, value as i32),
+            PlyScalarType::UInt => B::write_u32(bytes, value as u32),
+            PlyScalarType::Float => B::write_f32(bytes, value as f32),
+            PlyScalarType::Double => B::write_f64(bytes, value),
+        }
+    }
+
+    /// Like [`Self::decode`], but uses the byte order of the given binary `format`
+    pub(crate) fn decode_with_format(&self, format: PlyFormat, bytes: &[u8]) -> f64 {
+        match format {
+            PlyFormat::BinaryBigEndian => self.decode::<BigEndian>(bytes),
+            _ => self.decode::<LittleEndian>(bytes),
+        }
+    }
+
+    /// Like [`Self::encode`], but uses the byte order of the given binary `format`
+    pub(crate) fn encode_with_format(&self, format: PlyFormat, value: f64, bytes: &mut [u8]) {
+        match format {
+            PlyFormat::BinaryBigEndian => self.encode::<BigEndian>(value, bytes),
+            _ => self.encode::<LittleEndian>(value, bytes),
+        }
+    }
+
+    /// Formats `value` as this type for an ASCII PLY file
+    pub(crate) fn format_ascii(&self, value: f64) -> String {
+        match self {
+            PlyScalarType::Char => (value as i8).to_string(),
+            PlyScalarType::UChar => (value as u8).to_string(),
+            PlyScalarType::Short => (value as i16).to_string(),
+            PlyScalarType::UShort => (value as u16).to_string(),
+            PlyScalarType::Int => (value as i32).to_string(),
+            PlyScalarType::UInt => (value as u32).to_string(),
+            PlyScalarType::Float => (value as f32).to_string(),
+            PlyScalarType::Double => value.to_string(),
+        }
+    }
+}
+
+impl Display for PlyScalarType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The type of a single property of a PLY element
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyPropertyType {
+    /// A single scalar value
+    Scalar(PlyScalarType),
+    /// A variable-length list of values, prefixed by the number of entries
+    List {
+        count_type: PlyScalarType,
+        item_type: PlyScalarType,
+    },
+}
+
+/// A named property of a PLY element
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlyProperty {
+    pub name: String,
+    pub property_type: PlyPropertyType,
+}
+
+/// An element of a PLY file (e.g. `vertex` or `face`) together with its properties
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlyElement {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    /// Returns the size in bytes of a single binary record of this element, or `None` if the element has list
+    /// properties and thus a variable record size
+    pub fn binary_record_size(&self) -> Option<usize> {
+        self.properties
+            .iter()
+            .map(|property| match property.property_type {
+                PlyPropertyType::Scalar(scalar_type) => Some(scalar_type.size()),
+                PlyPropertyType::List { .. } => None,
+            })
+            .sum()
+    }
+
+    /// Skips all records of this element in the given `reader`
+    pub(crate) fn skip_records<R: BufRead>(&self, reader: &mut R, format: PlyFormat) -> Result<()> {
+        match format {
+            PlyFormat::Ascii => {
+                let mut line = String::new();
+                for _ in 0..self.count {
+                    line.clear();
+                    if reader.read_line(&mut line)? == 0 {
+                        bail!("Unexpected end of file in PLY element {}", self.name);
+                    }
+                }
+            }
+            _ => {
+                if let Some(record_size) = self.binary_record_size() {
+                    let num_bytes = (record_size * self.count) as u64;
+                    let skipped =
+                        std::io::copy(&mut reader.by_ref().take(num_bytes), &mut std::io::sink())?;
+                    if skipped != num_bytes {
+                        bail!("Unexpected end of file in PLY element {}", self.name);
+                    }
+                    return Ok(());
+                }
+                let mut buffer = [0; 8];
+                for _ in 0..self.count {
+                    for property in &self.properties {
+                        let (num_values, value_type) = match property.property_type {
+                            PlyPropertyType::Scalar(scalar_type) => (1, scalar_type),
+                            PlyPropertyType::List {
+                                count_type,
+                                item_type,
+                            } => {
+                                let count_bytes = &mut buffer[..count_type.size()];
+                                reader.read_exact(count_bytes)?;
+                                let num_values =
+                                    count_type.decode_with_format(format, count_bytes) as usize;
+                                (num_values, item_type)
+                            }
+                        };
+                        for _ in 0..num_values {
+                            reader.read_exact(&mut buffer[..value_type.size()])?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The header of a PLY file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlyHeader {
+    pub format: PlyFormat,
+    pub elements: Vec<PlyElement>,
+    pub comments: Vec<String>,
+    pub obj_info: Vec<String>,
+}
+
+impl PlyHeader {
+    /// The name of the PLY element that stores the points
+    pub const VERTEX_ELEMENT_NAME: &'static str = "vertex";
+
+    /// Creates a new `PlyHeader` with the given `format` and `elements`
+    pub fn new(format: PlyFormat, elements: Vec<PlyElement>) -> Self {
+        Self {
+            format,
+            elements,
+            comments: vec![],
+            obj_info: vec![],
+        }
+    }
+
+    /// Returns the vertex element of this header, if it exists
+    pub fn vertex_element(&self) -> Option<&PlyElement> {
+        self.elements
+            .iter()
+            .find(|element| element.name == Self::VERTEX_ELEMENT_NAME)
+    }
+
+    /// Reads a `PlyHeader` from the given `reader`. After this call, `reader` is positioned at the start of the
+    /// element data
+    pub fn read_from<R: BufRead>(mut reader: R) -> Result<Self> {
+        let mut line = String::new();
+        let next_line = |reader: &mut R, line: &mut String| -> Result<()> {
+            line.clear();
+            if reader.read_line(line)? == 0 {
+                bail!("Unexpected end of file in PLY header");
+            }
+            Ok(())
+        };
+
+        next_line(&mut reader, &mut line)?;
+        if line.trim_end() != "ply" {
+            bail!("Not a PLY file (magic number 'ply' not found)");
+        }
+
+        let mut format = None;
+        let mut elements: Vec<PlyElement> = vec![];
+        let mut comments = vec![];
+        let mut obj_info = vec![];
+        loop {
+            next_line(&mut reader, &mut line)?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("format") => {
+                    let format_name = tokens
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing format name in PLY header"))?;
+                    let version = tokens.next();
+                    if version != Some("1.0") {
+                        bail!("Unsupported PLY version {:?}", version);
+                    }
+                    format = Some(PlyFormat::from_name(format_name)?);
+                }
+                Some("comment") => {
+                    comments.push(line.trim()["comment".len()..].trim().to_owned());
+                }
+                Some("obj_info") => {
+                    obj_info.push(line.trim()["obj_info".len()..].trim().to_owned());
+                }
+                Some("element") => {
+                    let name = tokens
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing element name in PLY header"))?;
+                    let count = tokens
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing count of element {name} in PLY header"))?
+                        .parse::<usize>()
+                        .with_context(|| format!("Invalid count of element {name}"))?;
+                    elements.push(PlyElement {
+                        name: name.to_owned(),
+                        count,
+                        properties: vec![],
+                    });
+                }
+                Some("property") => {
+                    let element = elements.last_mut().ok_or_else(|| {
+                        anyhow!("Found PLY property before the first element definition")
+                    })?;
+                    let tokens = tokens.collect::<Vec<_>>();
+                    let property = match tokens.as_slice() {
+                        ["list", count_type, item_type, name] => PlyProperty {
+                            name: (*name).to_owned(),
+                            property_type: PlyPropertyType::List {
+                                count_type: PlyScalarType::from_name(count_type)?,
+                                item_type: PlyScalarType::from_name(item_type)?,
+                            },
+                        },
+                        [scalar_type, name] => PlyProperty {
+                            name: (*name).to_owned(),
+                            property_type: PlyPropertyType::Scalar(PlyScalarType::from_name(
+                                scalar_type,
+                            )?),
+                        },
+                        _ => bail!("Invalid PLY property definition '{}'", line.trim()),
+                    };
+                    element.properties.push(property);
+                }
+                Some("end_header") => break,
+                None => continue,
+                Some(other) => bail!("Unexpected keyword {other} in PLY header"),
+            }
+        }
+
+        Ok(Self {
+            format: format.ok_or_else(|| anyhow!("Missing format line in PLY header"))?,
+            elements,
+            comments,
+            obj_info,
+        })
+    }
+
+    /// Writes this `PlyHeader` to the given `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format {} 1.0", self.format)?;
+        for comment in &self.comments {
+            writeln!(writer, "comment {comment}")?;
+        }
+        for info in &self.obj_info {
+            writeln!(writer, "obj_info {info}")?;
+        }
+        for element in &self.elements {
+            writeln!(writer, "element {} {}", element.name, element.count)?;
+            for property in &element.properties {
+                match property.property_type {
+                    PlyPropertyType::Scalar(scalar_type) => {
+                        writeln!(writer, "property {} {}", scalar_type, property.name)?
+                    }
+                    PlyPropertyType::List {
+                        count_type,
+                        item_type,
+                    } => writeln!(
+                        writer,
+                        "property list {} {} {}",
+                        count_type, item_type, property.name
+                    )?,
+                }
+            }
+        }
+        writeln!(writer, "end_header")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_ply_header_round_trip() -> Result<()> {
+        let header_str = "ply\nformat binary_big_endian 1.0\ncomment made by pasture\nelement vertex 8\nproperty float x\nproperty float32 y\nproperty float z\nproperty uchar red\nelement face 6\nproperty list uchar int vertex_indices\nend_header\n";
+        let header = PlyHeader::read_from(Cursor::new(header_str))?;
+        assert_eq!(PlyFormat::BinaryBigEndian, header.format);
+        assert_eq!(vec!["made by pasture".to_owned()], header.comments);
+        assert_eq!(2, header.elements.len());
+
+        let vertex = header.vertex_element().expect("Missing vertex element");
+        assert_eq!(8, vertex.count);
+        assert_eq!(4, vertex.properties.len());
+        assert_eq!(
+            PlyPropertyType::Scalar(PlyScalarType::Float),
+            vertex.properties[1].property_type
+        );
+        assert_eq!(Some(13), vertex.binary_record_size());
+        assert_eq!(None, header.elements[1].binary_record_size());
+
+        let mut written = vec![];
+        header.write_to(&mut written)?;
+        let header_reread = PlyHeader::read_from(Cursor::new(written))?;
+        assert_eq!(header, header_reread);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ply_header_invalid() {
+        assert!(PlyHeader::read_from(Cursor::new("plx\nformat ascii 1.0\nend_header\n")).is_err());
+        assert!(PlyHeader::read_from(Cursor::new("ply\nformat ascii 1.0\n")).is_err());
+        assert!(PlyHeader::read_from(Cursor::new("ply\nend_header\n")).is_err());
+        assert!(PlyHeader::read_from(Cursor::new(
+            "ply\nformat ascii 1.0\nproperty float x\nend_header\n"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_ply_scalar_encoding() {
+        let mut bytes = [0; 8];
+        PlyScalarType::Short.encode::<BigEndian>(-2.0, &mut bytes);
+        assert_eq!([0xff, 0xfe], bytes[..2]);
+        assert_eq!(-2.0, PlyScalarType::Short.decode::<BigEndian>(&bytes));
+        PlyScalarType::Double.encode_with_format(PlyFormat::BinaryLittleEndian, 0.25, &mut bytes);
+        assert_eq!(
+            0.25,
+            PlyScalarType::Double.decode_with_format(PlyFormat::BinaryLittleEndian, &bytes)
+        );
+    }
+}
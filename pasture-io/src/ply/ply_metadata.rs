@@ -0,0 +1,80 @@
+use std::{any::Any, fmt::Display};
+
+use pasture_core::{math::AABB, meta::Metadata};
+
+use super::{PlyFormat, PlyHeader};
+
+/// `Metadata` implementation for PLY files
+#[derive(Clone, Debug)]
+pub struct PlyMetadata {
+    header: PlyHeader,
+}
+
+impl PlyMetadata {
+    /// Creates new `PlyMetadata` from the given `PlyHeader`
+    pub fn new(header: PlyHeader) -> Self {
+        Self { header }
+    }
+
+    /// Returns the header of the PLY file
+    pub fn header(&self) -> &PlyHeader {
+        &self.header
+    }
+
+    /// Returns the encoding of the PLY file
+    pub fn format(&self) -> PlyFormat {
+        self.header.format
+    }
+
+    /// Returns the number of points, i.e. the number of entries in the `vertex` element
+    pub fn point_count(&self) -> usize {
+        self.header
+            .vertex_element()
+            .map(|vertex| vertex.count)
+            .unwrap_or_default()
+    }
+
+    /// Returns all comments of the PLY header
+    pub fn comments(&self) -> &[String] {
+        &self.header.comments
+    }
+}
+
+impl Display for PlyMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "PlyMetadata {{")?;
+        writeln!(f, "\t\"format\": {}", self.header.format)?;
+        writeln!(f, "\t\"point_count\": {}", self.point_count())?;
+        for element in &self.header.elements {
+            writeln!(f, "\t\"element {}\": {}", element.name, element.count)?;
+        }
+        for comment in &self.header.comments {
+            writeln!(f, "\t\"comment\": {}", comment)?;
+        }
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl Metadata for PlyMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        None
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.point_count())
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "format" => Some(Box::new(self.header.format)),
+            "comments" => Some(Box::new(self.header.comments.clone())),
+            "obj_info" => Some(Box::new(self.header.obj_info.clone())),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    borrow::Cow,
+    convert::TryInto,
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::NativeEndian;
+use pasture_core::{
+    containers::{BorrowedMutBuffer, ExternalMemoryBuffer},
+    layout::{
+        attributes::{COLOR_RGB, NORMAL, POSITION_3D},
+        conversion::BufferLayoutConverter,
+        FieldAlignment, PointAttributeDataType, PointAttributeDefinition, PointLayout,
+    },
+    meta::Metadata,
+    nalgebra::clamp,
+};
+
+use crate::base::{PointReader, SeekToPoint};
+
+use super::{PlyElement, PlyFormat, PlyHeader, PlyMetadata, PlyPropertyType, PlyScalarType};
+
+/// Builtin attributes that are stored as three separate properties in a PLY file, together with the names of
+/// these properties
+pub(crate) fn ply_vector_attributes() -> [(PointAttributeDefinition, [&'static str; 3]); 3] {
+    [
+        (POSITION_3D, ["x", "y", "z"]),
+        (COLOR_RGB, ["red", "green", "blue"]),
+        (NORMAL, ["nx", "ny", "nz"]),
+    ]
+}
+
+/// Describes where the value of a single property of the PLY vertex element is located in the file and
+/// where it goes within a point of the default `PointLayout`
+#[derive(Copy, Clone, Debug)]
+struct VertexPropertyMapping {
+    file_type: PlyScalarType,
+    offset_in_file_record: usize,
+    memory_type: PlyScalarType,
+    offset_in_point: usize,
+}
+
+/// Creates the default `PointLayout` for the given PLY vertex element. The properties `x`/`y`/`z`, `red`/`green`/`blue`
+/// and `nx`/`ny`/`nz` are combined into the `POSITION_3D`, `COLOR_RGB` and `NORMAL` attributes. All other properties
+/// become custom attributes with the same name and datatype as the property
+fn layout_from_vertex_element(
+    vertex: &PlyElement,
+) -> Result<(PointLayout, Vec<VertexPropertyMapping>)> {
+    let property_types = vertex
+        .properties
+        .iter()
+        .map(|property| match property.property_type {
+            PlyPropertyType::Scalar(scalar_type) => Ok(scalar_type),
+            PlyPropertyType::List { .. } => bail!(
+                "List property {} in PLY vertex element is not supported",
+                property.name
+            ),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let property_index = |name: &str| {
+        vertex
+            .properties
+            .iter()
+            .position(|property| property.name == name)
+    };
+
+    // For each property: The attribute that it belongs to, the component index within this attribute, and the type in memory
+    let mut property_targets: Vec<Option<(PointAttributeDefinition, usize, PlyScalarType)>> =
+        vec![None; vertex.properties.len()];
+    for (attribute, component_names) in ply_vector_attributes().iter() {
+        let component_indices = component_names
+            .iter()
+            .map(|name| property_index(name))
+            .collect::<Option<Vec<_>>>();
+        if let Some(component_indices) = component_indices {
+            let component_type = property_types[component_indices[0]];
+            let all_components_same_type = component_indices
+                .iter()
+                .all(|idx| property_types[*idx] == component_type);
+            let (vector_datatype, memory_type) = match component_type.vec3_datatype() {
+                Some(datatype) if all_components_same_type => (datatype, component_type),
+                _ => (PointAttributeDataType::Vec3f64, PlyScalarType::Double),
+            };
+            for (component, idx) in component_indices.into_iter().enumerate() {
+                property_targets[idx] = Some((
+                    attribute.with_custom_datatype(vector_datatype),
+                    component,
+                    memory_type,
+                ));
+            }
+        }
+    }
+
+    let mut layout = PointLayout::default();
+    let mut targets = Vec::with_capacity(vertex.properties.len());
+    for (property, target) in vertex.properties.iter().zip(property_targets) {
+        let (attribute, component, memory_type) = match target {
+            Some(target) => target,
+            None => {
+                let scalar_type = match property.property_type {
+                    PlyPropertyType::Scalar(scalar_type) => scalar_type,
+                    PlyPropertyType::List { .. } => unreachable!(),
+                };
+                if layout.has_attribute_with_name(&property.name) {
+                    bail!("Duplicate property {} in PLY vertex element", property.name);
+                }
+                (
+                    PointAttributeDefinition::custom(
+                        Cow::Owned(property.name.clone()),
+                        scalar_type.datatype(),
+                    ),
+                    0,
+                    scalar_type,
+                )
+            }
+        };
+        if !layout.has_attribute_with_name(attribute.name()) {
+            layout.add_attribute(attribute.clone(), FieldAlignment::Packed(1));
+        }
+        targets.push((attribute, component, memory_type));
+    }
+
+    let mut offset_in_file_record = 0;
+    let mappings = targets
+        .into_iter()
+        .zip(property_types)
+        .map(|((attribute, component, memory_type), file_type)| {
+            let attribute_offset = layout
+                .get_attribute_by_name(attribute.name())
+                .expect("Attribute not found in PointLayout")
+                .offset() as usize;
+            let mapping = VertexPropertyMapping {
+                file_type,
+                offset_in_file_record,
+                memory_type,
+                offset_in_point: attribute_offset + component * memory_type.size(),
+            };
+            offset_in_file_record += file_type.size();
+            mapping
+        })
+        .collect();
+
+    Ok((layout, mappings))
+}
+
+/// `PointReader` implementation for PLY files. Supports ASCII, binary little-endian and binary big-endian PLY files.
+/// Points are read from the `vertex` element of the file, all other elements are ignored
+pub struct PlyReader<R: BufRead + Seek> {
+    reader: R,
+    metadata: PlyMetadata,
+    layout: PointLayout,
+    vertex_property_mappings: Vec<VertexPropertyMapping>,
+    size_of_vertex_in_file: usize,
+    offset_to_vertex_data: u64,
+    current_point_index: usize,
+}
+
+impl<R: BufRead + Seek> PlyReader<R> {
+    /// Creates a new `PlyReader` from the given `read`
+    ///
+    /// # Errors
+    ///
+    /// If `read` does not contain a valid PLY file, if the file has no `vertex` element, or if the `vertex` element
+    /// contains list properties, an error is returned
+    pub fn from_read(mut read: R) -> Result<Self> {
+        let header = PlyHeader::read_from(&mut read).context("Could not read PLY header")?;
+        let vertex = header
+            .vertex_element()
+            .ok_or_else(|| anyhow!("PLY file has no vertex element"))?
+            .clone();
+        let (layout, vertex_property_mappings) = layout_from_vertex_element(&vertex)?;
+
+        for element in header
+            .elements
+            .iter()
+            .take_while(|element| element.name != PlyHeader::VERTEX_ELEMENT_NAME)
+        {
+            element
+                .skip_records(&mut read, header.format)
+                .with_context(|| format!("Could not skip PLY element {}", element.name))?;
+        }
+        let offset_to_vertex_data = read.stream_position()?;
+
+        Ok(Self {
+            reader: read,
+            metadata: PlyMetadata::new(header),
+            layout,
+            vertex_property_mappings,
+            size_of_vertex_in_file: vertex.binary_record_size().unwrap_or_default(),
+            offset_to_vertex_data,
+            current_point_index: 0,
+        })
+    }
+
+    /// Returns the `PlyMetadata` of the associated PLY file
+    pub fn ply_metadata(&self) -> &PlyMetadata {
+        &self.metadata
+    }
+
+    fn remaining_points(&self) -> usize {
+        self.metadata.point_count() - self.current_point_index
+    }
+
+    /// Reads the next `point_data.len() / size_of_point` vertices into `point_data` using the default `PointLayout`
+    fn read_vertices(&mut self, point_data: &mut [u8]) -> Result<()> {
+        let size_of_point = self.layout.size_of_point_entry() as usize;
+        let format = self.metadata.format();
+        match format {
+            PlyFormat::Ascii => {
+                let mut line = String::new();
+                for point in point_data.chunks_exact_mut(size_of_point) {
+                    line.clear();
+                    if self.reader.read_line(&mut line)? == 0 {
+                        bail!("Unexpected end of file while reading PLY vertices");
+                    }
+                    let mut tokens = line.split_whitespace();
+                    for mapping in &self.vertex_property_mappings {
+                        let token = tokens.next().ok_or_else(|| {
+                            anyhow!("Too few values in PLY vertex '{}'", line.trim())
+                        })?;
+                        let value = token
+                            .parse::<f64>()
+                            .with_context(|| format!("Invalid value {token} in PLY vertex"))?;
+                        mapping
+                            .memory_type
+                            .encode::<NativeEndian>(value, &mut point[mapping.offset_in_point..]);
+                    }
+                }
+            }
+            _ => {
+                let mut vertex = vec![0; self.size_of_vertex_in_file];
+                for point in point_data.chunks_exact_mut(size_of_point) {
+                    self.reader
+                        .read_exact(&mut vertex)
+                        .context("Failed to read PLY vertex")?;
+                    for mapping in &self.vertex_property_mappings {
+                        let value = mapping
+                            .file_type
+                            .decode_with_format(format, &vertex[mapping.offset_in_file_record..]);
+                        mapping
+                            .memory_type
+                            .encode::<NativeEndian>(value, &mut point[mapping.offset_in_point..]);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips `count` lines in an ASCII PLY file
+    fn skip_ascii_vertices(&mut self, count: usize) -> Result<()> {
+        let mut line = String::new();
+        for _ in 0..count {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                bail!("Unexpected end of file while seeking in PLY vertices");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PlyReader<BufReader<File>> {
+    /// Creates a new `PlyReader` by opening the file at the given `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_read(reader)
+    }
+}
+
+impl<R: BufRead + Seek> PointReader for PlyReader<R> {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let num_points_to_read = usize::min(count, self.remaining_points());
+        if num_points_to_read == 0 {
+            return Ok(0);
+        }
+
+        let source_layout = self.layout.clone();
+        let target_layout = point_buffer.point_layout().clone();
+        let converter = if source_layout == target_layout {
+            None
+        } else {
+            Some(BufferLayoutConverter::for_layouts_with_default(
+                &source_layout,
+                &target_layout,
+            ))
+        };
+
+        // Read point data in chunks of ~1MiB size so that we don't have to allocate a second buffer for all points
+        const CHUNK_MEM_SIZE: usize = 1 << 20;
+        let size_of_point = source_layout.size_of_point_entry() as usize;
+        let num_points_per_chunk = usize::max(1, CHUNK_MEM_SIZE / size_of_point);
+        let mut chunk = vec![0; num_points_per_chunk.min(num_points_to_read) * size_of_point];
+        let mut first_point_in_chunk = 0;
+        while first_point_in_chunk < num_points_to_read {
+            let points_in_chunk =
+                num_points_per_chunk.min(num_points_to_read - first_point_in_chunk);
+            let chunk_bytes = &mut chunk[..points_in_chunk * size_of_point];
+            self.read_vertices(chunk_bytes)?;
+            self.current_point_index += points_in_chunk;
+
+            let target_range = first_point_in_chunk..(first_point_in_chunk + points_in_chunk);
+            match &converter {
+                Some(converter) => {
+                    let chunk_buffer =
+                        ExternalMemoryBuffer::new(&chunk_bytes[..], source_layout.clone());
+                    converter.convert_into_range(
+                        &chunk_buffer,
+                        0..points_in_chunk,
+                        point_buffer,
+                        target_range,
+                    );
+                }
+                // Safe because the buffer has the same `PointLayout` as the chunk
+                None => unsafe {
+                    point_buffer.set_point_range(target_range, chunk_bytes);
+                },
+            }
+            first_point_in_chunk += points_in_chunk;
+        }
+
+        Ok(num_points_to_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+impl<R: BufRead + Seek> SeekToPoint for PlyReader<R> {
+    fn seek_point(&mut self, position: SeekFrom) -> Result<usize> {
+        let point_count = self.metadata.point_count();
+        let new_point_idx: i64 = match position {
+            SeekFrom::Start(offset) => offset.try_into()?,
+            SeekFrom::End(offset) => point_count as i64 + offset,
+            SeekFrom::Current(offset) => self.current_point_index as i64 + offset,
+        };
+        let new_point_idx = clamp(new_point_idx, 0, point_count as i64) as usize;
+        if new_point_idx == self.current_point_index {
+            return Ok(new_point_idx);
+        }
+
+        match self.metadata.format() {
+            PlyFormat::Ascii => {
+                // Vertices in ASCII files have variable size, so we have to skip them line by line
+                if new_point_idx < self.current_point_index {
+                    self.reader
+                        .seek(SeekFrom::Start(self.offset_to_vertex_data))?;
+                    self.current_point_index = 0;
+                }
+                self.skip_ascii_vertices(new_point_idx - self.current_point_index)?;
+            }
+            _ => {
+                let offset = self.offset_to_vertex_data
+                    + (new_point_idx * self.size_of_vertex_in_file) as u64;
+                self.reader.seek(SeekFrom::Start(offset))?;
+            }
+        }
+        self.current_point_index = new_point_idx;
+        Ok(self.current_point_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pasture_core::{
+        containers::{
+            BorrowedBuffer, HashMapBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+        },
+        layout::PointType,
+        nalgebra::Vector3,
+    };
+    use pasture_derive::PointType;
+
+    use super::*;
+
+    const ASCII_PLY: &str = "ply
+format ascii 1.0
+comment test file
+element face 1
+property list uchar int vertex_indices
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+end_header
+3 0 1 2
+1 2 3 255 0 0 0.5
+4 5 6 0 255 0 0.25
+7 8 9 0 0 255 1
+";
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct PlyTestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u16>,
+    }
+
+    fn confidence_attribute() -> PointAttributeDefinition {
+        PointAttributeDefinition::custom(Cow::Borrowed("confidence"), PointAttributeDataType::F32)
+    }
+
+    #[test]
+    fn test_ply_default_layout() -> Result<()> {
+        let reader = PlyReader::from_read(Cursor::new(ASCII_PLY))?;
+        let expected_layout = PointLayout::from_attributes_packed(
+            &[
+                POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32),
+                COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+                confidence_attribute(),
+            ],
+            1,
+        );
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+        assert_eq!(Some(3), reader.get_metadata().number_of_points());
+        assert_eq!(&["test file".to_owned()], reader.ply_metadata().comments());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_ascii_ply() -> Result<()> {
+        let mut reader = PlyReader::from_read(Cursor::new(ASCII_PLY))?;
+        let points = reader.read::<HashMapBuffer>(10)?;
+        assert_eq!(3, points.len());
+
+        let positions = points
+            .view_attribute::<Vector3<f32>>(
+                &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Vector3::new(1.0, 2.0, 3.0),
+                Vector3::new(4.0, 5.0, 6.0),
+                Vector3::new(7.0, 8.0, 9.0)
+            ],
+            positions
+        );
+        let confidences = points
+            .view_attribute::<f32>(&confidence_attribute())
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0.5, 0.25, 1.0], confidences);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_ply_custom_layout_and_seek() -> Result<()> {
+        let mut reader = PlyReader::from_read(Cursor::new(ASCII_PLY))?;
+        assert_eq!(2, reader.seek_point(SeekFrom::Start(2))?);
+        let mut points = VectorBuffer::new_from_layout(PlyTestPoint::layout());
+        points.resize(1);
+        assert_eq!(1, reader.read_into(&mut points, 1)?);
+        assert_eq!(
+            PlyTestPoint {
+                position: Vector3::new(7.0, 8.0, 9.0),
+                color: Vector3::new(0, 0, 255),
+            },
+            points.view::<PlyTestPoint>().at(0)
+        );
+
+        assert_eq!(1, reader.seek_point(SeekFrom::Current(-2))?);
+        assert_eq!(1, reader.read_into(&mut points, 1)?);
+        let position = points.view::<PlyTestPoint>().at(0).position;
+        assert_eq!(Vector3::new(4.0, 5.0, 6.0), position);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_binary_big_endian_ply() -> Result<()> {
+        let mut data = b"ply\nformat binary_big_endian 1.0\nelement vertex 2\nproperty double x\nproperty double y\nproperty double z\nproperty short nx\nproperty short ny\nproperty int nz\nend_header\n".to_vec();
+        for idx in 0..2 {
+            for coordinate in 0..3 {
+                data.extend_from_slice(&((idx * 3 + coordinate) as f64).to_be_bytes());
+            }
+            data.extend_from_slice(&(-1_i16).to_be_bytes());
+            data.extend_from_slice(&(idx as i16).to_be_bytes());
+            data.extend_from_slice(&(1_i32).to_be_bytes());
+        }
+
+        let mut reader = PlyReader::from_read(Cursor::new(data))?;
+        // Normal components have different types, so they are read as Vec3f64
+        let expected_layout = PointLayout::from_attributes_packed(
+            &[
+                POSITION_3D,
+                NORMAL.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            ],
+            1,
+        );
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+
+        reader.seek_point(SeekFrom::Start(1))?;
+        let points = reader.read::<VectorBuffer>(1)?;
+        assert_eq!(
+            Vector3::new(3.0, 4.0, 5.0),
+            points.view_attribute::<Vector3<f64>>(&POSITION_3D).at(0)
+        );
+        assert_eq!(
+            Vector3::new(-1.0, 1.0, 1.0),
+            points
+                .view_attribute::<Vector3<f64>>(
+                    &NORMAL.with_custom_datatype(PointAttributeDataType::Vec3f64)
+                )
+                .at(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ply_vertex_list_property_is_rejected() {
+        let data = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty list uchar int indices\nend_header\n1 0\n";
+        assert!(PlyReader::from_read(Cursor::new(data)).is_err());
+    }
+}
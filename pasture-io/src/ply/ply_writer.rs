@@ -0,0 +1,373 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use byteorder::NativeEndian;
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer, VectorBuffer,
+    },
+    layout::{FieldAlignment, PointLayout},
+};
+
+use crate::base::PointWriter;
+
+use super::{
+    ply_vector_attributes, PlyElement, PlyFormat, PlyHeader, PlyProperty, PlyPropertyType,
+    PlyScalarType,
+};
+
+/// Describes where the value of a single PLY vertex property is located within a point of the written `PointLayout`
+#[derive(Copy, Clone, Debug)]
+struct VertexPropertySource {
+    scalar_type: PlyScalarType,
+    offset_in_point: usize,
+}
+
+/// `PointWriter` implementation for PLY files. Points are written as the `vertex` element of the PLY file.
+///
+/// The builtin attributes `POSITION_3D`, `COLOR_RGB` and `NORMAL` are written as the `x`/`y`/`z`, `red`/`green`/`blue`
+/// and `nx`/`ny`/`nz` properties. All other attributes with a scalar datatype are written as a property with the
+/// name of the attribute. PLY does not support 64-bit integers and vector types other than the ones above, so
+/// attributes with such datatypes are silently ignored!
+///
+/// Since the PLY header contains the number of points, all points are cached in memory and are only written
+/// during `flush`. Points can't be written after the writer has been flushed
+pub struct PlyWriter<W: Write> {
+    writer: W,
+    format: PlyFormat,
+    expected_layout: PointLayout,
+    default_layout: PointLayout,
+    cached_points: VectorBuffer,
+    properties: Vec<PlyProperty>,
+    property_sources: Vec<VertexPropertySource>,
+    comments: Vec<String>,
+    requires_flush: bool,
+}
+
+impl<W: Write> PlyWriter<W> {
+    /// Creates a new `PlyWriter` that writes points with the given `point_layout` in the given `format` to `writer`
+    pub fn from_write_and_layout(writer: W, point_layout: PointLayout, format: PlyFormat) -> Self {
+        let mut default_layout = PointLayout::default();
+        let mut properties = vec![];
+        let mut property_sources = vec![];
+        let vector_attributes = ply_vector_attributes();
+        for attribute in point_layout.attributes() {
+            let offset = attribute.offset() as usize;
+            let vector_property_names = vector_attributes
+                .iter()
+                .find(|(vector_attribute, _)| vector_attribute.name() == attribute.name())
+                .map(|(_, names)| names);
+            let vector_component_type = PlyScalarType::from_vec3_datatype(attribute.datatype());
+            let scalar_type = PlyScalarType::from_datatype(attribute.datatype());
+
+            match (vector_property_names, vector_component_type, scalar_type) {
+                (Some(names), Some(component_type), _) => {
+                    for (component, name) in names.iter().enumerate() {
+                        properties.push(PlyProperty {
+                            name: (*name).to_owned(),
+                            property_type: PlyPropertyType::Scalar(component_type),
+                        });
+                        property_sources.push(VertexPropertySource {
+                            scalar_type: component_type,
+                            offset_in_point: offset + component * component_type.size(),
+                        });
+                    }
+                }
+                (None, _, Some(scalar_type)) => {
+                    properties.push(PlyProperty {
+                        name: attribute.name().replace(char::is_whitespace, "_"),
+                        property_type: PlyPropertyType::Scalar(scalar_type),
+                    });
+                    property_sources.push(VertexPropertySource {
+                        scalar_type,
+                        offset_in_point: offset,
+                    });
+                }
+                _ => continue,
+            }
+            default_layout.add_attribute(
+                attribute.attribute_definition().clone(),
+                FieldAlignment::Packed(1),
+            );
+        }
+
+        Self {
+            writer,
+            format,
+            cached_points: VectorBuffer::new_from_layout(point_layout.clone()),
+            expected_layout: point_layout,
+            default_layout,
+            properties,
+            property_sources,
+            comments: vec![],
+            requires_flush: true,
+        }
+    }
+
+    /// Adds a comment line to the header of the PLY file
+    pub fn add_comment<S: Into<String>>(&mut self, comment: S) {
+        self.comments.push(comment.into());
+    }
+
+    /// Returns the `PlyFormat` that this writer writes
+    pub fn format(&self) -> PlyFormat {
+        self.format
+    }
+
+    fn write_cached_points(&mut self) -> Result<()> {
+        let mut header = PlyHeader::new(
+            self.format,
+            vec![PlyElement {
+                name: PlyHeader::VERTEX_ELEMENT_NAME.to_owned(),
+                count: self.cached_points.len(),
+                properties: self.properties.clone(),
+            }],
+        );
+        header.comments = self.comments.clone();
+        header
+            .write_to(&mut self.writer)
+            .context("Failed to write PLY header")?;
+
+        match self.format {
+            PlyFormat::Ascii => {
+                for point_index in 0..self.cached_points.len() {
+                    let point = self.cached_points.get_point_ref(point_index);
+                    let values = self
+                        .property_sources
+                        .iter()
+                        .map(|source| {
+                            let value = source
+                                .scalar_type
+                                .decode::<NativeEndian>(&point[source.offset_in_point..]);
+                            source.scalar_type.format_ascii(value)
+                        })
+                        .collect::<Vec<_>>();
+                    writeln!(self.writer, "{}", values.join(" "))
+                        .context("Failed to write PLY vertex")?;
+                }
+            }
+            _ => {
+                let size_of_vertex = self
+                    .property_sources
+                    .iter()
+                    .map(|source| source.scalar_type.size())
+                    .sum();
+                let mut vertex = vec![0; size_of_vertex];
+                for point_index in 0..self.cached_points.len() {
+                    let point = self.cached_points.get_point_ref(point_index);
+                    let mut offset_in_vertex = 0;
+                    for source in &self.property_sources {
+                        let value = source
+                            .scalar_type
+                            .decode::<NativeEndian>(&point[source.offset_in_point..]);
+                        source.scalar_type.encode_with_format(
+                            self.format,
+                            value,
+                            &mut vertex[offset_in_vertex..],
+                        );
+                        offset_in_vertex += source.scalar_type.size();
+                    }
+                    self.writer
+                        .write_all(&vertex)
+                        .context("Failed to write PLY vertex")?;
+                }
+            }
+        }
+
+        self.writer.flush()?;
+        self.requires_flush = false;
+        Ok(())
+    }
+}
+
+impl PlyWriter<BufWriter<File>> {
+    /// Creates a new `PlyWriter` that writes points with the given `point_layout` in the given `format` to a new
+    /// file at `path`
+    pub fn from_path_and_layout<P: AsRef<Path>>(
+        path: P,
+        point_layout: PointLayout,
+        format: PlyFormat,
+    ) -> Result<Self> {
+        let file = BufWriter::new(File::create(path.as_ref()).with_context(|| {
+            format!(
+                "Could not open file {} for writing",
+                path.as_ref().display()
+            )
+        })?);
+        Ok(Self::from_write_and_layout(file, point_layout, format))
+    }
+}
+
+impl<W: Write> PointWriter for PlyWriter<W> {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if points.point_layout() != &self.expected_layout {
+            bail!("PointLayout of buffer does not match the PointLayout that this PlyWriter was constructed with");
+        }
+        if !self.requires_flush {
+            bail!("Can't write points into a PlyWriter that has already been flushed");
+        }
+        self.cached_points.append(points);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.requires_flush {
+            return Ok(());
+        }
+        self.write_cached_points()
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.default_layout
+    }
+}
+
+impl<W: Write> Drop for PlyWriter<W> {
+    fn drop(&mut self) {
+        self.flush().expect("Error while flushing PlyWriter")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pasture_core::{
+        layout::{
+            attributes::{COLOR_RGB, INTENSITY, NORMAL, POSITION_3D},
+            PointAttributeDataType, PointType,
+        },
+        nalgebra::Vector3,
+    };
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::{base::PointReader, ply::PlyReader};
+
+    #[repr(C, packed)]
+    #[derive(
+        Copy, Clone, PartialEq, PointType, Debug, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    struct PlyWriterTestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u8>,
+        #[pasture(BUILTIN_NORMAL)]
+        normal: Vector3<f32>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+        #[pasture(BUILTIN_POINT_ID)]
+        point_id: u64,
+    }
+
+    fn test_points() -> Vec<PlyWriterTestPoint> {
+        (0..5)
+            .map(|idx| PlyWriterTestPoint {
+                position: Vector3::new(idx as f64 + 0.125, -(idx as f64), 1e6 + idx as f64),
+                color: Vector3::new(idx as u8, 255 - idx as u8, 128),
+                normal: Vector3::new(0.0, idx as f32 * 0.5, 1.0),
+                intensity: idx * 1000,
+                point_id: idx as u64,
+            })
+            .collect()
+    }
+
+    fn test_ply_round_trip(format: PlyFormat) -> Result<()> {
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        let mut data = vec![];
+        {
+            let mut writer =
+                PlyWriter::from_write_and_layout(&mut data, PlyWriterTestPoint::layout(), format);
+            writer.add_comment("written by pasture");
+            writer.write(&points)?;
+            writer.flush()?;
+            assert!(writer.write(&points).is_err());
+        }
+
+        let mut reader = PlyReader::from_read(Cursor::new(data))?;
+        assert_eq!(format, reader.ply_metadata().format());
+        assert_eq!(
+            &["written by pasture".to_owned()],
+            reader.ply_metadata().comments()
+        );
+        // The point ID can't be represented in PLY, all other attributes are preserved
+        let expected_layout = PointLayout::from_attributes_packed(
+            &[
+                POSITION_3D,
+                COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+                NORMAL,
+                INTENSITY,
+            ],
+            1,
+        );
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+
+        let read_points = reader.read::<VectorBuffer>(points.len())?;
+        assert_eq!(points.len(), read_points.len());
+        for (expected, actual_position) in test_points()
+            .iter()
+            .zip(read_points.view_attribute::<Vector3<f64>>(&POSITION_3D))
+        {
+            let expected_position = expected.position;
+            assert_eq!(expected_position, actual_position);
+        }
+        let expected_intensities = test_points()
+            .iter()
+            .map(|point| point.intensity)
+            .collect::<Vec<_>>();
+        let actual_intensities = read_points
+            .view_attribute::<u16>(&INTENSITY)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(expected_intensities, actual_intensities);
+        let actual_normal = read_points.view_attribute::<Vector3<f32>>(&NORMAL).at(3);
+        assert_eq!(Vector3::new(0.0, 1.5, 1.0), actual_normal);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ply_round_trip_ascii() -> Result<()> {
+        test_ply_round_trip(PlyFormat::Ascii)
+    }
+
+    #[test]
+    fn test_ply_round_trip_binary_little_endian() -> Result<()> {
+        test_ply_round_trip(PlyFormat::BinaryLittleEndian)
+    }
+
+    #[test]
+    fn test_ply_round_trip_binary_big_endian() -> Result<()> {
+        test_ply_round_trip(PlyFormat::BinaryBigEndian)
+    }
+
+    #[test]
+    fn test_ply_generic_reader_and_writer() -> Result<()> {
+        let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test_ply_generic_reader_and_writer.ply");
+        scopeguard::defer! {
+            std::fs::remove_file(&path).expect("Removing test file failed!");
+        }
+
+        let points = test_points().into_iter().collect::<VectorBuffer>();
+        crate::base::write_all(&points, &path)?;
+        let read_points = crate::base::read_all::<VectorBuffer, _>(&path)?;
+        assert_eq!(points.len(), read_points.len());
+        let expected_colors = test_points()
+            .iter()
+            .map(|point| point.color)
+            .collect::<Vec<_>>();
+        let actual_colors = read_points
+            .view_attribute::<Vector3<u8>>(
+                &COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(expected_colors, actual_colors);
+        Ok(())
+    }
+}
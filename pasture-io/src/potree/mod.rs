@@ -0,0 +1,8 @@
+mod potree_types;
+pub use self::potree_types::*;
+
+mod potree_reader;
+pub use self::potree_reader::*;
+
+mod potree_writer;
+pub use self::potree_writer::*;
@@ -0,0 +1,744 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use pasture_core::containers::{
+    BorrowedMutBuffer, ExternalMemoryBuffer, MakeBufferFromLayout, OwningBuffer,
+};
+use pasture_core::layout::attributes::POSITION_3D;
+use pasture_core::layout::conversion::BufferLayoutConverter;
+use pasture_core::layout::{PointAttributeDataType, PointLayout};
+use pasture_core::math::AABB;
+use pasture_core::meta::Metadata;
+use pasture_core::nalgebra::Vector3;
+
+use super::{
+    morton_decode_16, PotreeEncoding, PotreeMetadata, PotreeNode, PotreeNodeType,
+    POTREE_HIERARCHY_FILE_NAME, POTREE_METADATA_FILE_NAME, POTREE_OCTREE_FILE_NAME,
+    POTREE_POSITION_ATTRIBUTE_NAME, POTREE_RGB_ATTRIBUTE_NAME,
+};
+use crate::base::PointReader;
+
+/// A query for the nodes of a Potree octree. By default, a query selects all nodes. The query can be restricted
+/// to nodes that intersect a bounding box, to nodes up to a maximum depth, or to the nodes of a single level
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PotreeQuery {
+    bounds: Option<AABB<f64>>,
+    max_depth: Option<u32>,
+    level: Option<u32>,
+}
+
+impl PotreeQuery {
+    /// Creates a new `PotreeQuery` that selects all nodes
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Only select nodes whose bounds intersect the given `bounds`
+    pub fn with_bounds(mut self, bounds: AABB<f64>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Only select nodes with a level less than or equal to `max_depth`. The root node is at level 0
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Only select nodes at exactly the given `level`. The root node is at level 0
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Returns `true` if the given `node` is selected by this query, given the bounding box of the root node
+    pub fn matches(&self, node: &PotreeNode, root_bounds: &AABB<f64>) -> bool {
+        let level = node.level();
+        if self.max_depth.map(|max_depth| level > max_depth) == Some(true)
+            || self.level.map(|query_level| level != query_level) == Some(true)
+        {
+            return false;
+        }
+        match &self.bounds {
+            Some(bounds) => node.bounds(root_bounds).intersects(bounds),
+            None => true,
+        }
+    }
+}
+
+/// Reader for Potree 2.0 octrees, which consist of a `metadata.json` file, a `hierarchy.bin` file storing the
+/// octree nodes and an `octree.bin` file storing the points of all nodes. Both the `DEFAULT` and the `BROTLI`
+/// encoding are supported.
+///
+/// The `PotreeReader` parses the whole hierarchy upfront, so all nodes are available through [`nodes`](Self::nodes)
+/// and [`node`](Self::node). The points of single nodes can be read using [`read_node_into`](Self::read_node_into),
+/// and the points of all nodes selected by a [`PotreeQuery`] using [`read_query`](Self::read_query). As a
+/// `PointReader`, the `PotreeReader` reads the points of all nodes, in the order returned by `nodes`.
+///
+/// The default `PointLayout` contains all attributes of the octree in the order of `metadata.json`, see
+/// [`PotreeAttribute::point_attribute`](super::PotreeAttribute::point_attribute). The positions are provided in world
+/// space using the `POSITION_3D` attribute with datatype `Vec3f64`
+pub struct PotreeReader<R: Read + Seek> {
+    metadata: PotreeMetadata,
+    nodes: Vec<PotreeNode>,
+    octree: R,
+    /// `PointLayout` that exactly matches the binary layout of a single point in the `DEFAULT` encoding
+    raw_layout: PointLayout,
+    layout: PointLayout,
+    current_node_index: usize,
+    current_node_points: Option<Vec<u8>>,
+    current_point_in_node: usize,
+}
+
+impl PotreeReader<BufReader<File>> {
+    /// Creates a new `PotreeReader` for the Potree 2.0 octree at the given `path`. `path` can either be the
+    /// directory of the octree or the path to its `metadata.json` file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata_path = if path.is_dir() {
+            path.join(POTREE_METADATA_FILE_NAME)
+        } else {
+            path.to_owned()
+        };
+        let directory = metadata_path.parent().unwrap_or_else(|| Path::new(""));
+        let metadata: PotreeMetadata = serde_json::from_reader(BufReader::new(
+            File::open(&metadata_path)
+                .with_context(|| format!("Could not open {}", metadata_path.display()))?,
+        ))
+        .with_context(|| format!("Could not parse {}", metadata_path.display()))?;
+        let hierarchy_path = directory.join(POTREE_HIERARCHY_FILE_NAME);
+        let hierarchy = fs::read(&hierarchy_path)
+            .with_context(|| format!("Could not read {}", hierarchy_path.display()))?;
+        let octree_path = directory.join(POTREE_OCTREE_FILE_NAME);
+        let octree = BufReader::new(
+            File::open(&octree_path)
+                .with_context(|| format!("Could not open {}", octree_path.display()))?,
+        );
+        Self::from_parts(metadata, &hierarchy, octree)
+    }
+}
+
+impl<R: Read + Seek> PotreeReader<R> {
+    /// Creates a new `PotreeReader` from the parsed `metadata`, the contents of the `hierarchy.bin` file, and a
+    /// reader for the `octree.bin` file
+    ///
+    /// # Errors
+    ///
+    /// If `metadata` describes an octree that pasture can't read, or if `hierarchy` is not a valid hierarchy for
+    /// `metadata`, an error is returned
+    pub fn from_parts(metadata: PotreeMetadata, hierarchy: &[u8], octree: R) -> Result<Self> {
+        metadata.verify()?;
+        let nodes = read_hierarchy(hierarchy, metadata.hierarchy.first_chunk_size)?;
+
+        let attributes = metadata
+            .attributes
+            .iter()
+            .map(|attribute| attribute.point_attribute())
+            .collect::<Vec<_>>();
+        let raw_layout = PointLayout::from_attributes_packed(&attributes, 1);
+        let layout = PointLayout::from_attributes(
+            &attributes
+                .iter()
+                .map(|attribute| {
+                    if attribute.name() == POSITION_3D.name() {
+                        PotreeMetadata::position_attribute()
+                    } else {
+                        attribute.clone()
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(Self {
+            metadata,
+            nodes,
+            octree,
+            raw_layout,
+            layout,
+            current_node_index: 0,
+            current_node_points: None,
+            current_point_in_node: 0,
+        })
+    }
+
+    /// Returns the metadata of the associated `PotreeReader`
+    pub fn metadata(&self) -> &PotreeMetadata {
+        &self.metadata
+    }
+
+    /// Returns the default `PointLayout` of the associated `PotreeReader`
+    pub fn point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+
+    /// Returns all nodes of the octree, sorted by level and then by name
+    pub fn nodes(&self) -> &[PotreeNode] {
+        &self.nodes
+    }
+
+    /// Returns the node with the given `name`, or `None` if the octree has no such node
+    pub fn node(&self, name: &str) -> Option<&PotreeNode> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// Returns the maximum depth of the octree, i.e. the largest level of all nodes
+    pub fn max_depth(&self) -> u32 {
+        self.nodes
+            .iter()
+            .map(|node| node.level())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns all nodes that are selected by the given `query`
+    pub fn query_nodes(&self, query: &PotreeQuery) -> Vec<PotreeNode> {
+        let root_bounds = self.metadata.root_bounds();
+        self.nodes
+            .iter()
+            .filter(|node| query.matches(node, &root_bounds))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the total number of points in all nodes selected by the given `query`
+    pub fn query_point_count(&self, query: &PotreeQuery) -> usize {
+        let root_bounds = self.metadata.root_bounds();
+        self.nodes
+            .iter()
+            .filter(|node| query.matches(node, &root_bounds))
+            .map(|node| node.num_points as usize)
+            .sum()
+    }
+
+    /// Reads the points of the given `node` into the given `point_buffer`. Uses the `PointLayout` of `point_buffer`
+    /// for reading, missing attributes are filled with default values. Overwrites existing data in `point_buffer`
+    /// starting at the first point, so the length of `point_buffer` must be at least `node.num_points`. Returns
+    /// the number of points that were read
+    ///
+    /// # Errors
+    ///
+    /// If the point data of the node can't be read or decoded, an error is returned
+    pub fn read_node_into<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &mut self,
+        node: &PotreeNode,
+        point_buffer: &'c mut B,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        self.read_node_into_range(node, point_buffer, 0)
+    }
+
+    /// Reads the points of the node with the given `name` into a new buffer of type `B`. The `PointLayout` of the
+    /// new buffer will be equal to the default `PointLayout` of this `PotreeReader`
+    pub fn read_node<'b, B: OwningBuffer<'b> + MakeBufferFromLayout<'b> + 'b>(
+        &mut self,
+        name: &str,
+    ) -> Result<B> {
+        let node = self
+            .node(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Potree octree has no node named {}", name))?;
+        let mut buffer = B::new_from_layout(self.layout.clone());
+        buffer.resize(node.num_points as usize);
+        self.read_node_into(&node, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reads the points of all nodes selected by `query` into the given `point_buffer`. The points of each node
+    /// are stored consecutively, in the order returned by [`query_nodes`](PotreeReader::query_nodes). The length of
+    /// `point_buffer` must be at least [`query_point_count`](PotreeReader::query_point_count). Returns the number
+    /// of points that were read
+    pub fn read_query_into<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &mut self,
+        query: &PotreeQuery,
+        point_buffer: &'c mut B,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        let mut num_points_read = 0;
+        for node in self.query_nodes(query) {
+            num_points_read += self.read_node_into_range(&node, point_buffer, num_points_read)?;
+        }
+        Ok(num_points_read)
+    }
+
+    /// Reads the points of all nodes selected by `query` into a new buffer of type `B`. The `PointLayout` of the
+    /// new buffer will be equal to the default `PointLayout` of this `PotreeReader`
+    pub fn read_query<'b, B: OwningBuffer<'b> + MakeBufferFromLayout<'b> + 'b>(
+        &mut self,
+        query: &PotreeQuery,
+    ) -> Result<B> {
+        let mut buffer = B::new_from_layout(self.layout.clone());
+        buffer.resize(self.query_point_count(query));
+        self.read_query_into(query, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn read_node_into_range<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &mut self,
+        node: &PotreeNode,
+        point_buffer: &'c mut B,
+        first_point_in_buffer: usize,
+    ) -> Result<usize>
+    where
+        'b: 'c,
+    {
+        let num_points = node.num_points as usize;
+        if num_points == 0 {
+            return Ok(0);
+        }
+        let target_range = first_point_in_buffer..(first_point_in_buffer + num_points);
+        if target_range.end > point_buffer.len() {
+            bail!(
+                "Point buffer is too small to hold the {} points of Potree node {}",
+                num_points,
+                node.name
+            );
+        }
+        let point_data = self.read_node_data(node)?;
+        self.convert_points(&point_data, 0..num_points, point_buffer, target_range)?;
+        Ok(num_points)
+    }
+
+    /// Reads the point data of the given `node` and decodes it into the binary layout of the `DEFAULT` encoding
+    fn read_node_data(&mut self, node: &PotreeNode) -> Result<Vec<u8>> {
+        let mut data = vec![0; node.byte_size as usize];
+        self.octree.seek(SeekFrom::Start(node.byte_offset))?;
+        self.octree
+            .read_exact(&mut data)
+            .with_context(|| format!("Failed to read data of Potree node {}", node.name))?;
+
+        let num_points = node.num_points as usize;
+        let bytes_per_point = self.raw_layout.size_of_point_entry() as usize;
+        let point_data = match self.metadata.encoding {
+            PotreeEncoding::Default => data,
+            PotreeEncoding::Brotli => {
+                let mut decompressed = vec![];
+                brotli::Decompressor::new(data.as_slice(), 4096)
+                    .read_to_end(&mut decompressed)
+                    .with_context(|| format!("Failed to decompress Potree node {}", node.name))?;
+                decode_brotli_attributes(
+                    &decompressed,
+                    &self.metadata,
+                    &self.raw_layout,
+                    num_points,
+                )
+                .with_context(|| format!("Failed to decode Potree node {}", node.name))?
+            }
+        };
+        if point_data.len() < num_points * bytes_per_point {
+            bail!(
+                "Potree node {} contains less data than expected for {} points",
+                node.name,
+                num_points
+            );
+        }
+        Ok(point_data)
+    }
+
+    /// Converts the points in `source_range` of the given `point_data` (in the binary layout of the `DEFAULT` encoding)
+    /// into the `target_range` of `point_buffer`
+    fn convert_points<'b, 'c, B: BorrowedMutBuffer<'b>>(
+        &self,
+        point_data: &[u8],
+        source_range: std::ops::Range<usize>,
+        point_buffer: &'c mut B,
+        target_range: std::ops::Range<usize>,
+    ) -> Result<()>
+    where
+        'b: 'c,
+    {
+        let source_buffer = ExternalMemoryBuffer::new(point_data, self.raw_layout.clone());
+        let target_layout = point_buffer.point_layout().clone();
+        let mut converter =
+            BufferLayoutConverter::for_layouts_with_default(&self.raw_layout, &target_layout);
+        if let Some(position_attribute) = target_layout.get_attribute_by_name(POSITION_3D.name()) {
+            let scale = Vector3::from(self.metadata.scale);
+            let offset = Vector3::from(self.metadata.offset);
+            let raw_position_attribute =
+                POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3i32);
+            match position_attribute.datatype() {
+                PointAttributeDataType::Vec3f64 => converter.set_custom_mapping_with_transformation(
+                    &raw_position_attribute,
+                    position_attribute.attribute_definition(),
+                    move |position: Vector3<f64>| -> Vector3<f64> {
+                        position.component_mul(&scale) + offset
+                    },
+                    false,
+                ),
+                PointAttributeDataType::Vec3f32 => converter.set_custom_mapping_with_transformation(
+                    &raw_position_attribute,
+                    position_attribute.attribute_definition(),
+                    move |position: Vector3<f32>| -> Vector3<f32> {
+                        (position.cast::<f64>().component_mul(&scale) + offset).cast::<f32>()
+                    },
+                    false,
+                ),
+                // Integer positions are provided as they are stored in the octree
+                PointAttributeDataType::Vec3i32 => (),
+                other => bail!(
+                    "Invalid datatype {} for POSITION_3D attribute. Only Vec3f64, Vec3f32 and Vec3i32 are supported!",
+                    other
+                ),
+            }
+        }
+        converter.convert_into_range(&source_buffer, source_range, point_buffer, target_range);
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> PointReader for PotreeReader<R> {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let mut num_read = 0;
+        while num_read < count {
+            // Nodes without points are skipped
+            let node = loop {
+                match self.nodes.get(self.current_node_index) {
+                    Some(node) if node.num_points == 0 => self.current_node_index += 1,
+                    Some(node) => break node.clone(),
+                    None => return Ok(num_read),
+                }
+            };
+            if self.current_node_points.is_none() {
+                self.current_node_points = Some(self.read_node_data(&node)?);
+                self.current_point_in_node = 0;
+            }
+
+            let num_points_in_node = node.num_points as usize;
+            let num_to_copy =
+                (num_points_in_node - self.current_point_in_node).min(count - num_read);
+            let point_data = self
+                .current_node_points
+                .as_ref()
+                .expect("Node data must exist");
+            self.convert_points(
+                point_data,
+                self.current_point_in_node..(self.current_point_in_node + num_to_copy),
+                point_buffer,
+                num_read..(num_read + num_to_copy),
+            )?;
+            num_read += num_to_copy;
+            self.current_point_in_node += num_to_copy;
+            if self.current_point_in_node == num_points_in_node {
+                self.current_node_points = None;
+                self.current_node_index += 1;
+            }
+        }
+        Ok(num_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+/// Parses all hierarchy chunks in the given `hierarchy` data, starting with the root chunk of size
+/// `first_chunk_size`. Returns all nodes that are not proxy nodes, sorted by level and name
+fn read_hierarchy(hierarchy: &[u8], first_chunk_size: u64) -> Result<Vec<PotreeNode>> {
+    let mut nodes = vec![];
+    let mut chunks_to_read = VecDeque::new();
+    chunks_to_read.push_back((PotreeNode::ROOT_NAME.to_owned(), 0, first_chunk_size));
+    while let Some((chunk_root_name, chunk_offset, chunk_size)) = chunks_to_read.pop_front() {
+        let chunk = hierarchy
+            .get(chunk_offset as usize..(chunk_offset + chunk_size) as usize)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Potree hierarchy chunk of node {} is out of bounds",
+                    chunk_root_name
+                )
+            })?;
+        // The nodes within a chunk are stored in breadth-first order, starting with the root node of the chunk, so
+        // the names of all nodes can be derived from the child masks
+        let mut names = VecDeque::from(vec![chunk_root_name]);
+        for (index, node_data) in chunk.chunks_exact(PotreeNode::SIZE).enumerate() {
+            let name = names.pop_front().ok_or_else(|| {
+                anyhow!("Potree hierarchy chunk contains more nodes than the child masks indicate")
+            })?;
+            let node = PotreeNode::read_from(node_data, name)?;
+            if node.node_type == PotreeNodeType::Proxy {
+                if index == 0 {
+                    bail!(
+                        "The root of Potree hierarchy chunk {} is a proxy node",
+                        node.name
+                    );
+                }
+                chunks_to_read.push_back((node.name, node.byte_offset, node.byte_size));
+                continue;
+            }
+            names.extend(
+                node.child_indices()
+                    .map(|child_index| format!("{}{}", node.name, child_index)),
+            );
+            nodes.push(node);
+        }
+        if let Some(name) = names.front() {
+            bail!("Potree hierarchy chunk is missing node {}", name);
+        }
+    }
+    nodes.sort_by(|a, b| (a.name.len(), &a.name).cmp(&(b.name.len(), &b.name)));
+    Ok(nodes)
+}
+
+/// Decodes the decompressed data of a node in the `BROTLI` encoding into the binary layout of the `DEFAULT` encoding.
+/// In the `BROTLI` encoding, the values of each attribute are stored one after another. Positions are stored as two
+/// 48-bit morton codes (one for the upper and one for the lower 16 bits of the coordinates), colors as a single
+/// 48-bit morton code
+fn decode_brotli_attributes(
+    data: &[u8],
+    metadata: &PotreeMetadata,
+    raw_layout: &PointLayout,
+    num_points: usize,
+) -> Result<Vec<u8>> {
+    let bytes_per_point = raw_layout.size_of_point_entry() as usize;
+    let mut point_data = vec![0; num_points * bytes_per_point];
+    let mut offset_in_data = 0;
+    for (attribute, member) in metadata.attributes.iter().zip(raw_layout.attributes()) {
+        let offset_in_point = member.offset() as usize;
+        let encoded_size = match attribute.name.as_str() {
+            POTREE_POSITION_ATTRIBUTE_NAME => 16,
+            POTREE_RGB_ATTRIBUTE_NAME => 8,
+            _ => attribute.size as usize,
+        };
+        let attribute_data = data
+            .get(offset_in_data..(offset_in_data + num_points * encoded_size))
+            .ok_or_else(|| anyhow!("Data of attribute {} is truncated", attribute.name))?;
+        for (point_index, value) in attribute_data.chunks_exact(encoded_size).enumerate() {
+            let target_start = point_index * bytes_per_point + offset_in_point;
+            let target = &mut point_data[target_start..(target_start + attribute.size as usize)];
+            match attribute.name.as_str() {
+                POTREE_POSITION_ATTRIBUTE_NAME => {
+                    let upper = morton_decode_16(LittleEndian::read_u64(&value[0..8]));
+                    let lower = morton_decode_16(LittleEndian::read_u64(&value[8..16]));
+                    for axis in 0..3 {
+                        LittleEndian::write_u32(
+                            &mut target[(axis * 4)..(axis * 4 + 4)],
+                            (upper[axis] << 16) | lower[axis],
+                        );
+                    }
+                }
+                POTREE_RGB_ATTRIBUTE_NAME => {
+                    let rgb = morton_decode_16(LittleEndian::read_u64(value));
+                    for channel in 0..3 {
+                        LittleEndian::write_u16(
+                            &mut target[(channel * 2)..(channel * 2 + 2)],
+                            rgb[channel] as u16,
+                        );
+                    }
+                }
+                _ => target.copy_from_slice(value),
+            }
+        }
+        offset_in_data += num_points * encoded_size;
+    }
+    Ok(point_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pasture_core::containers::{BorrowedBuffer, VectorBuffer};
+    use pasture_core::layout::attributes::{CLASSIFICATION, COLOR_RGB, INTENSITY, SCAN_ANGLE_RANK};
+    use pasture_core::nalgebra::Point3;
+    use std::path::PathBuf;
+
+    /// Path to a small octree in the file layout of the PotreeConverter 2.x (`DEFAULT` encoding, with the attributes
+    /// that the PotreeConverter writes for LAS point format 2). The root node `r` has the children `r0` and `r7`,
+    /// with four points in each node
+    fn potree_converter_test_path() -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resources/test/potree_converter");
+        path
+    }
+
+    fn node(name: &str) -> PotreeNode {
+        PotreeNode {
+            name: name.into(),
+            node_type: PotreeNodeType::Leaf,
+            child_mask: 0,
+            num_points: 1,
+            byte_offset: 0,
+            byte_size: 0,
+        }
+    }
+
+    #[test]
+    fn test_potree_query_matches() {
+        let root_bounds =
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(8.0, 8.0, 8.0));
+        let query = PotreeQuery::new()
+            .with_bounds(AABB::from_min_max(
+                Point3::new(5.0, 1.0, 1.0),
+                Point3::new(6.0, 2.0, 2.0),
+            ))
+            .with_max_depth(1);
+        assert!(query.matches(&node("r"), &root_bounds));
+        assert!(query.matches(&node("r4"), &root_bounds));
+        assert!(!query.matches(&node("r0"), &root_bounds));
+        assert!(!query.matches(&node("r40"), &root_bounds));
+        assert!(PotreeQuery::new()
+            .with_level(2)
+            .matches(&node("r40"), &root_bounds));
+        assert!(!PotreeQuery::new()
+            .with_level(2)
+            .matches(&node("r4"), &root_bounds));
+    }
+
+    #[test]
+    fn test_read_hierarchy_with_proxy_nodes() -> Result<()> {
+        let mut first_chunk = vec![];
+        let mut second_chunk = vec![];
+        // The root has the children 0 and 5, child 5 is a proxy for the second chunk
+        PotreeNode {
+            child_mask: 0b0010_0001,
+            node_type: PotreeNodeType::Normal,
+            ..node("r")
+        }
+        .write_to(&mut first_chunk)?;
+        node("r0").write_to(&mut first_chunk)?;
+        PotreeNode {
+            node_type: PotreeNodeType::Proxy,
+            byte_offset: 3 * PotreeNode::SIZE as u64,
+            byte_size: 2 * PotreeNode::SIZE as u64,
+            ..node("r5")
+        }
+        .write_to(&mut first_chunk)?;
+        PotreeNode {
+            child_mask: 0b1000_0000,
+            node_type: PotreeNodeType::Normal,
+            num_points: 5,
+            ..node("r5")
+        }
+        .write_to(&mut second_chunk)?;
+        node("r57").write_to(&mut second_chunk)?;
+
+        let hierarchy = [first_chunk.as_slice(), second_chunk.as_slice()].concat();
+        let nodes = read_hierarchy(&hierarchy, 3 * PotreeNode::SIZE as u64)?;
+        assert_eq!(
+            vec!["r", "r0", "r5", "r57"],
+            nodes
+                .iter()
+                .map(|node| node.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(5, nodes[2].num_points);
+        assert!(nodes
+            .iter()
+            .all(|node| node.node_type != PotreeNodeType::Proxy));
+
+        assert!(read_hierarchy(&hierarchy, 2 * PotreeNode::SIZE as u64).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_potree_converter_octree() -> Result<()> {
+        let mut reader = PotreeReader::from_path(potree_converter_test_path())?;
+        assert_eq!(12, reader.metadata().points);
+        assert_eq!(27, reader.metadata().bytes_per_point());
+        assert_eq!(
+            vec![("r", 4), ("r0", 4), ("r7", 4)],
+            reader
+                .nodes()
+                .iter()
+                .map(|node| (node.name.as_str(), node.num_points))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, reader.max_depth());
+        let layout = reader.point_layout().clone();
+        assert!(layout.has_attribute(&INTENSITY));
+        assert!(layout.has_attribute(&CLASSIFICATION));
+        assert!(
+            layout.has_attribute(&COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u16))
+        );
+        assert!(
+            layout.has_attribute(&SCAN_ANGLE_RANK.with_custom_datatype(PointAttributeDataType::I8))
+        );
+
+        let root = reader.read_node::<VectorBuffer>("r")?;
+        assert_eq!(
+            vec![
+                Vector3::new(0.5, 0.5, 0.5),
+                Vector3::new(7.5, 0.5, 0.5),
+                Vector3::new(0.5, 7.5, 7.5),
+                Vector3::new(7.5, 7.5, 7.5),
+            ],
+            root.view_attribute::<Vector3<f64>>(&PotreeMetadata::position_attribute())
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![100, 200, 300, 400],
+            root.view_attribute::<u16>(&INTENSITY)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![2, 2, 5, 6],
+            root.view_attribute::<u8>(&CLASSIFICATION)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![-5, 0, 5, 10],
+            root.view_attribute::<i8>(
+                &SCAN_ANGLE_RANK.with_custom_datatype(PointAttributeDataType::I8)
+            )
+            .into_iter()
+            .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Vector3::new(1792, 2048, 2304),
+            root.view_attribute::<Vector3<u16>>(
+                &COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u16)
+            )
+            .at(2)
+        );
+
+        // All points must lie within the bounds of their node
+        let root_bounds = reader.metadata().root_bounds();
+        for node in reader.nodes().to_vec() {
+            let points = reader.read_node::<VectorBuffer>(&node.name)?;
+            let node_bounds = node.bounds(&root_bounds);
+            for position in
+                points.view_attribute::<Vector3<f64>>(&PotreeMetadata::position_attribute())
+            {
+                assert!(node_bounds.contains(&Point3::from(position)));
+            }
+        }
+
+        let query = PotreeQuery::new().with_bounds(AABB::from_min_max(
+            Point3::new(5.0, 5.0, 5.0),
+            Point3::new(6.0, 6.0, 6.0),
+        ));
+        assert_eq!(8, reader.query_point_count(&query));
+        let queried_points = reader.read_query::<VectorBuffer>(&query)?;
+        assert_eq!(
+            vec![210, 220, 230, 240],
+            queried_points
+                .view_attribute::<u16>(&INTENSITY)
+                .into_iter()
+                .skip(4)
+                .collect::<Vec<_>>()
+        );
+
+        let all_points = reader.read::<VectorBuffer>(20)?;
+        assert_eq!(12, all_points.len());
+        Ok(())
+    }
+}
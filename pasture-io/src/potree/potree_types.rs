@@ -0,0 +1,691 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use pasture_core::layout::attributes::{
+    CLASSIFICATION, CLASSIFICATION_FLAGS, COLOR_RGB, EDGE_OF_FLIGHT_LINE, GPS_TIME, INTENSITY, NIR,
+    NORMAL, NUMBER_OF_RETURNS, POINT_SOURCE_ID, POSITION_3D, RETURN_NUMBER,
+    RETURN_POINT_WAVEFORM_LOCATION, SCANNER_CHANNEL, SCAN_ANGLE, SCAN_ANGLE_RANK,
+    SCAN_DIRECTION_FLAG, USER_DATA, WAVEFORM_DATA_OFFSET, WAVEFORM_PACKET_SIZE,
+    WAVEFORM_PARAMETERS, WAVE_PACKET_DESCRIPTOR_INDEX,
+};
+use pasture_core::layout::{PointAttributeDataType, PointAttributeDefinition};
+use pasture_core::math::AABB;
+use pasture_core::meta::Metadata;
+use pasture_core::nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Name of the file that stores the metadata of a Potree 2.0 octree
+pub const POTREE_METADATA_FILE_NAME: &str = "metadata.json";
+/// Name of the file that stores the hierarchy of a Potree 2.0 octree
+pub const POTREE_HIERARCHY_FILE_NAME: &str = "hierarchy.bin";
+/// Name of the file that stores the point data of all nodes of a Potree 2.0 octree
+pub const POTREE_OCTREE_FILE_NAME: &str = "octree.bin";
+/// Name of the attribute that stores the positions in a Potree 2.0 octree
+pub const POTREE_POSITION_ATTRIBUTE_NAME: &str = "position";
+/// Name of the attribute that stores the colors in a Potree 2.0 octree
+pub const POTREE_RGB_ATTRIBUTE_NAME: &str = "rgb";
+
+/// Mapping between the attribute names that the PotreeConverter uses and the builtin attributes of pasture
+const POTREE_BUILTIN_ATTRIBUTES: &[(&str, PointAttributeDefinition)] = &[
+    ("intensity", INTENSITY),
+    ("return number", RETURN_NUMBER),
+    ("number of returns", NUMBER_OF_RETURNS),
+    ("classification", CLASSIFICATION),
+    ("scan angle rank", SCAN_ANGLE_RANK),
+    ("user data", USER_DATA),
+    ("point source id", POINT_SOURCE_ID),
+    ("gps-time", GPS_TIME),
+    ("rgb", COLOR_RGB),
+    ("wave packet descriptor index", WAVE_PACKET_DESCRIPTOR_INDEX),
+    ("byte offset to waveform data", WAVEFORM_DATA_OFFSET),
+    ("waveform packet size", WAVEFORM_PACKET_SIZE),
+    (
+        "return point waveform location",
+        RETURN_POINT_WAVEFORM_LOCATION,
+    ),
+    ("XYZ(t)", WAVEFORM_PARAMETERS),
+    ("classification flags", CLASSIFICATION_FLAGS),
+    ("scanner channel", SCANNER_CHANNEL),
+    ("scan direction flag", SCAN_DIRECTION_FLAG),
+    ("edge of flight line", EDGE_OF_FLIGHT_LINE),
+    ("scan angle", SCAN_ANGLE),
+    ("nir", NIR),
+    ("normal", NORMAL),
+];
+
+/// Encoding of the point data in a Potree 2.0 octree
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PotreeEncoding {
+    /// The points of each node are stored uncompressed and interleaved, in the order of the attributes
+    #[default]
+    #[serde(rename = "DEFAULT")]
+    Default,
+    /// The attributes of each node are stored one after another and the data of each node is compressed using
+    /// Brotli. Positions and colors are stored as morton codes
+    #[serde(rename = "BROTLI")]
+    Brotli,
+}
+
+/// The `hierarchy` object of the Potree 2.0 `metadata.json` file
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PotreeHierarchyInfo {
+    /// Size of the first hierarchy chunk in bytes, which starts at the beginning of `hierarchy.bin`
+    #[serde(rename = "firstChunkSize")]
+    pub first_chunk_size: u64,
+    /// Number of octree levels that each hierarchy chunk spans
+    #[serde(rename = "stepSize")]
+    pub step_size: u32,
+    /// Depth of the octree
+    pub depth: u32,
+}
+
+/// The `boundingBox` object of the Potree 2.0 `metadata.json` file
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PotreeBoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl From<&PotreeBoundingBox> for AABB<f64> {
+    fn from(bounding_box: &PotreeBoundingBox) -> Self {
+        AABB::from_min_max_unchecked(
+            Point3::from(bounding_box.min),
+            Point3::from(bounding_box.max),
+        )
+    }
+}
+
+impl From<&AABB<f64>> for PotreeBoundingBox {
+    fn from(bounds: &AABB<f64>) -> Self {
+        Self {
+            min: bounds.min().coords.into(),
+            max: bounds.max().coords.into(),
+        }
+    }
+}
+
+/// Description of a single point attribute in the Potree 2.0 `metadata.json` file
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PotreeAttribute {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Size of the attribute in bytes
+    pub size: u64,
+    /// Number of components of the attribute
+    #[serde(rename = "numElements")]
+    pub num_elements: u64,
+    /// Size of a single component of the attribute in bytes
+    #[serde(rename = "elementSize")]
+    pub element_size: u64,
+    /// Type of the components of the attribute, e.g. `uint16` or `double`
+    #[serde(rename = "type")]
+    pub attribute_type: String,
+    /// Minimum value of each component
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub min: Vec<f64>,
+    /// Maximum value of each component
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub max: Vec<f64>,
+}
+
+impl PotreeAttribute {
+    /// Returns the pasture datatype that matches the binary representation of this attribute in a Potree 2.0 file.
+    /// Components of known types are mapped to the corresponding primitive or vector types, everything else is
+    /// mapped to a byte array of the size of the attribute
+    pub fn datatype(&self) -> PointAttributeDataType {
+        let datatype = match (self.attribute_type.as_str(), self.num_elements) {
+            ("int8", 1) => PointAttributeDataType::I8,
+            ("uint8", 1) => PointAttributeDataType::U8,
+            ("int16", 1) => PointAttributeDataType::I16,
+            ("uint16", 1) => PointAttributeDataType::U16,
+            ("int32", 1) => PointAttributeDataType::I32,
+            ("uint32", 1) => PointAttributeDataType::U32,
+            ("int64", 1) => PointAttributeDataType::I64,
+            ("uint64", 1) => PointAttributeDataType::U64,
+            ("float", 1) => PointAttributeDataType::F32,
+            ("double", 1) => PointAttributeDataType::F64,
+            ("uint8", 3) => PointAttributeDataType::Vec3u8,
+            ("uint16", 3) => PointAttributeDataType::Vec3u16,
+            ("int32", 3) => PointAttributeDataType::Vec3i32,
+            ("float", 3) => PointAttributeDataType::Vec3f32,
+            ("double", 3) => PointAttributeDataType::Vec3f64,
+            ("uint8", 4) => PointAttributeDataType::Vec4u8,
+            _ => PointAttributeDataType::ByteArray(self.size),
+        };
+        if datatype.size() == self.size {
+            datatype
+        } else {
+            PointAttributeDataType::ByteArray(self.size)
+        }
+    }
+
+    /// Returns the pasture attribute for this Potree attribute. Attributes that the PotreeConverter writes for LAS
+    /// files (e.g. `intensity` or `gps-time`) are mapped to the corresponding builtin attributes, all other attributes
+    /// become custom attributes with the same name. The datatype always matches the binary representation in the file,
+    /// see [`datatype`](Self::datatype). The position attribute is stored as integer coordinates, use
+    /// [`PotreeMetadata::position_attribute`] for the world-space positions
+    pub fn point_attribute(&self) -> PointAttributeDefinition {
+        let datatype = self.datatype();
+        if self.name == POTREE_POSITION_ATTRIBUTE_NAME {
+            return POSITION_3D.with_custom_datatype(datatype);
+        }
+        match POTREE_BUILTIN_ATTRIBUTES
+            .iter()
+            .find(|(potree_name, _)| *potree_name == self.name)
+        {
+            Some((_, attribute)) => attribute.with_custom_datatype(datatype),
+            None => PointAttributeDefinition::custom(Cow::Owned(self.name.clone()), datatype),
+        }
+    }
+
+    /// Creates the Potree attribute for the given pasture `attribute`. This is the inverse of
+    /// [`point_attribute`](Self::point_attribute), with two exceptions: `POSITION_3D` is always stored as
+    /// `int32` coordinates, and `COLOR_RGB` is always stored as `uint16` values, as Potree expects this
+    pub fn from_point_attribute(attribute: &PointAttributeDefinition) -> Self {
+        if attribute.name() == POSITION_3D.name() {
+            return Self::new(
+                POTREE_POSITION_ATTRIBUTE_NAME,
+                PointAttributeDataType::Vec3i32,
+            );
+        }
+        if attribute.name() == COLOR_RGB.name() {
+            return Self::new(POTREE_RGB_ATTRIBUTE_NAME, PointAttributeDataType::Vec3u16);
+        }
+        let name = POTREE_BUILTIN_ATTRIBUTES
+            .iter()
+            .find(|(_, builtin_attribute)| builtin_attribute.name() == attribute.name())
+            .map(|(potree_name, _)| *potree_name)
+            .unwrap_or_else(|| attribute.name());
+        Self::new(name, attribute.datatype())
+    }
+
+    fn new(name: &str, datatype: PointAttributeDataType) -> Self {
+        let (attribute_type, num_elements) = match datatype {
+            PointAttributeDataType::I8 => ("int8", 1),
+            PointAttributeDataType::U8 => ("uint8", 1),
+            PointAttributeDataType::I16 => ("int16", 1),
+            PointAttributeDataType::U16 => ("uint16", 1),
+            PointAttributeDataType::I32 => ("int32", 1),
+            PointAttributeDataType::U32 => ("uint32", 1),
+            PointAttributeDataType::I64 => ("int64", 1),
+            PointAttributeDataType::U64 => ("uint64", 1),
+            PointAttributeDataType::F32 => ("float", 1),
+            PointAttributeDataType::F64 => ("double", 1),
+            PointAttributeDataType::Vec3u8 => ("uint8", 3),
+            PointAttributeDataType::Vec3u16 => ("uint16", 3),
+            PointAttributeDataType::Vec3i32 => ("int32", 3),
+            PointAttributeDataType::Vec3f32 => ("float", 3),
+            PointAttributeDataType::Vec3f64 => ("double", 3),
+            PointAttributeDataType::Vec4u8 => ("uint8", 4),
+            _ => ("undefined", 1),
+        };
+        let size = datatype.size();
+        Self {
+            name: name.to_owned(),
+            description: String::new(),
+            size,
+            num_elements,
+            element_size: size / num_elements,
+            attribute_type: attribute_type.to_owned(),
+            min: vec![],
+            max: vec![],
+        }
+    }
+}
+
+/// The contents of the `metadata.json` file of a Potree 2.0 octree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PotreeMetadata {
+    pub version: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Total number of points in the octree
+    pub points: u64,
+    #[serde(default)]
+    pub projection: String,
+    pub hierarchy: PotreeHierarchyInfo,
+    /// Offset of the integer coordinates of the position attribute
+    pub offset: [f64; 3],
+    /// Scale of the integer coordinates of the position attribute
+    pub scale: [f64; 3],
+    /// Spacing between points at the root level of the octree
+    pub spacing: f64,
+    /// The cubic bounding box of the root node of the octree
+    #[serde(rename = "boundingBox")]
+    pub bounding_box: PotreeBoundingBox,
+    #[serde(default)]
+    pub encoding: PotreeEncoding,
+    pub attributes: Vec<PotreeAttribute>,
+}
+
+impl PotreeMetadata {
+    /// The Potree format version that pasture supports
+    pub const VERSION: &'static str = "2.0";
+
+    /// Returns the cubic bounding box of the root node of the octree
+    pub fn root_bounds(&self) -> AABB<f64> {
+        (&self.bounding_box).into()
+    }
+
+    /// Returns the point spacing at the given level of the octree
+    pub fn spacing_at_level(&self, level: u32) -> f64 {
+        self.spacing / 2.0_f64.powi(level as i32)
+    }
+
+    /// Returns the size of a single point in bytes
+    pub fn bytes_per_point(&self) -> u64 {
+        self.attributes.iter().map(|attribute| attribute.size).sum()
+    }
+
+    /// Returns the `POSITION_3D` attribute with the datatype that pasture uses for positions in world space
+    pub fn position_attribute() -> PointAttributeDefinition {
+        POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64)
+    }
+
+    /// Converts integer coordinates of the position attribute into world space
+    pub fn to_world_position(&self, position: Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(
+            position.x * self.scale[0] + self.offset[0],
+            position.y * self.scale[1] + self.offset[1],
+            position.z * self.scale[2] + self.offset[2],
+        )
+    }
+
+    /// Checks that this metadata describes a Potree 2.0 octree that pasture can read
+    pub fn verify(&self) -> Result<()> {
+        if self.version != Self::VERSION {
+            bail!(
+                "Unsupported Potree version {} (only version {} is supported)",
+                self.version,
+                Self::VERSION
+            );
+        }
+        let position_attribute = self
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name == POTREE_POSITION_ATTRIBUTE_NAME)
+            .ok_or_else(|| anyhow!("Potree metadata contains no position attribute"))?;
+        if position_attribute.datatype() != PointAttributeDataType::Vec3i32 {
+            bail!("Position attribute of Potree octree must consist of three int32 values");
+        }
+        if self.encoding == PotreeEncoding::Brotli {
+            if let Some(rgb_attribute) = self
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name == POTREE_RGB_ATTRIBUTE_NAME)
+            {
+                if rgb_attribute.datatype() != PointAttributeDataType::Vec3u16 {
+                    bail!("RGB attribute of Brotli-encoded Potree octree must consist of three uint16 values");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Metadata for PotreeMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        // The min and max values of the position attribute are the tight bounds, the bounding box is cubic
+        let tight_bounds = self
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name == POTREE_POSITION_ATTRIBUTE_NAME)
+            .and_then(|attribute| {
+                let min: [f64; 3] = attribute.min.as_slice().try_into().ok()?;
+                let max: [f64; 3] = attribute.max.as_slice().try_into().ok()?;
+                Some(AABB::from_min_max_unchecked(
+                    Point3::from(min),
+                    Point3::from(max),
+                ))
+            });
+        Some(tight_bounds.unwrap_or_else(|| self.root_bounds()))
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.points as usize)
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn Any>> {
+        match field_name {
+            "SPACING" => Some(Box::new(self.spacing)),
+            "SCALE" => Some(Box::new(Vector3::from(self.scale))),
+            "OFFSET" => Some(Box::new(Vector3::from(self.offset))),
+            "ENCODING" => Some(Box::new(self.encoding)),
+            "PROJECTION" => Some(Box::new(self.projection.clone())),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
+
+impl Display for PotreeMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "PotreeMetadata {{")?;
+        writeln!(f, "\t\"version\": {}", self.version)?;
+        writeln!(f, "\t\"name\": {}", self.name)?;
+        writeln!(f, "\t\"points\": {}", self.points)?;
+        writeln!(f, "\t\"spacing\": {}", self.spacing)?;
+        writeln!(f, "\t\"encoding\": {:?}", self.encoding)?;
+        writeln!(
+            f,
+            "\t\"attributes\": [{}]",
+            self.attributes
+                .iter()
+                .map(|attribute| attribute.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Type of a node in the Potree 2.0 hierarchy
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PotreeNodeType {
+    /// A node with child nodes
+    Normal,
+    /// A node without child nodes
+    Leaf,
+    /// Placeholder for a node whose hierarchy is stored in a separate hierarchy chunk
+    Proxy,
+}
+
+impl PotreeNodeType {
+    fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Normal),
+            1 => Ok(Self::Leaf),
+            2 => Ok(Self::Proxy),
+            other => bail!("Invalid Potree node type {}", other),
+        }
+    }
+
+    fn as_u8(&self) -> u8 {
+        match self {
+            Self::Normal => 0,
+            Self::Leaf => 1,
+            Self::Proxy => 2,
+        }
+    }
+}
+
+/// A single node of a Potree 2.0 octree. Nodes are named by the path from the root node, which is named `r`. The
+/// name of a child node is the name of its parent followed by the child index in `[0;7]`, so `r04` is the fifth
+/// child of the first child of the root node
+#[derive(Debug, Clone, PartialEq)]
+pub struct PotreeNode {
+    pub name: String,
+    pub node_type: PotreeNodeType,
+    /// Bit mask of the existing child nodes, bit `i` is set if the child with index `i` exists
+    pub child_mask: u8,
+    pub num_points: u32,
+    /// Offset of the point data of this node in `octree.bin`. For proxy nodes, this is the offset of the hierarchy
+    /// chunk in `hierarchy.bin`
+    pub byte_offset: u64,
+    /// Size of the point data of this node in bytes. For proxy nodes, this is the size of the hierarchy chunk
+    pub byte_size: u64,
+}
+
+impl PotreeNode {
+    /// Size of a single node in a hierarchy chunk in bytes
+    pub const SIZE: usize = 22;
+    /// Name of the root node
+    pub const ROOT_NAME: &'static str = "r";
+
+    /// Returns the level of this node in the octree. The root node is at level 0
+    pub fn level(&self) -> u32 {
+        (self.name.len() - 1) as u32
+    }
+
+    /// Returns the indices of all existing child nodes
+    pub fn child_indices(&self) -> impl Iterator<Item = usize> {
+        let child_mask = self.child_mask;
+        (0..8).filter(move |child_index| child_mask & (1 << child_index) != 0)
+    }
+
+    /// Returns the bounding box of this node, given the bounding box of the root node
+    /// ```
+    /// # use pasture_io::potree::potree_node_bounds;
+    /// # use pasture_core::math::AABB;
+    /// # use pasture_core::nalgebra::Point3;
+    /// let root_bounds = AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(4.0, 4.0, 4.0));
+    /// let bounds = potree_node_bounds("r4", &root_bounds).unwrap();
+    /// assert_eq!(*bounds.min(), Point3::new(2.0, 0.0, 0.0));
+    /// assert_eq!(*bounds.max(), Point3::new(4.0, 2.0, 2.0));
+    /// ```
+    pub fn bounds(&self, root_bounds: &AABB<f64>) -> AABB<f64> {
+        potree_node_bounds(&self.name, root_bounds).expect("Invalid Potree node name")
+    }
+
+    /// Parses a single node from the given hierarchy chunk `data`. The name of the node is not stored in the hierarchy
+    pub fn read_from(mut data: &[u8], name: String) -> Result<Self> {
+        Ok(Self {
+            name,
+            node_type: PotreeNodeType::from_u8(data.read_u8()?)?,
+            child_mask: data.read_u8()?,
+            num_points: data.read_u32::<LittleEndian>()?,
+            byte_offset: data.read_u64::<LittleEndian>()?,
+            byte_size: data.read_u64::<LittleEndian>()?,
+        })
+    }
+
+    /// Writes this node in the binary format of the hierarchy chunks
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_u8(self.node_type.as_u8())?;
+        writer.write_u8(self.child_mask)?;
+        writer.write_u32::<LittleEndian>(self.num_points)?;
+        writer.write_u64::<LittleEndian>(self.byte_offset)?;
+        writer.write_u64::<LittleEndian>(self.byte_size)?;
+        Ok(())
+    }
+}
+
+/// Returns the bounding box of the node with the given `name`, given the bounding box of the root node. Bit 2 of each
+/// child index corresponds to the x-axis, bit 1 to the y-axis and bit 0 to the z-axis
+pub fn potree_node_bounds(name: &str, root_bounds: &AABB<f64>) -> Result<AABB<f64>> {
+    let child_indices = name
+        .strip_prefix(PotreeNode::ROOT_NAME)
+        .ok_or_else(|| anyhow!("Invalid Potree node name {}", name))?;
+    let mut min = *root_bounds.min();
+    let mut max = *root_bounds.max();
+    for child_index in child_indices.chars() {
+        let child_index = child_index
+            .to_digit(8)
+            .ok_or_else(|| anyhow!("Invalid Potree node name {}", name))?;
+        let center = AABB::from_min_max_unchecked(min, max).center();
+        for (axis, bit) in [(0, 0b100), (1, 0b010), (2, 0b001)] {
+            if child_index & bit != 0 {
+                min[axis] = center[axis];
+            } else {
+                max[axis] = center[axis];
+            }
+        }
+    }
+    Ok(AABB::from_min_max_unchecked(min, max))
+}
+
+/// Spreads the lowest 16 bits of `value` so that there are two zero bits between each bit
+fn spread_bits_16(value: u32) -> u64 {
+    let mut x = (value & 0xFFFF) as u64;
+    x = (x | (x << 16)) & 0x0000_FF00_00FF;
+    x = (x | (x << 8)) & 0x00F0_0F00_F00F;
+    x = (x | (x << 4)) & 0x0C30_C30C_30C3;
+    x = (x | (x << 2)) & 0x2492_4924_9249;
+    x
+}
+
+/// Inverse of `spread_bits_16`
+fn compact_bits_16(value: u64) -> u32 {
+    let mut x = value & 0x2492_4924_9249;
+    x = (x | (x >> 2)) & 0x0C30_C30C_30C3;
+    x = (x | (x >> 4)) & 0x00F0_0F00_F00F;
+    x = (x | (x >> 8)) & 0x0000_FF00_00FF;
+    x = (x | (x >> 16)) & 0xFFFF;
+    x as u32
+}
+
+/// Encodes the lowest 16 bits of the three given values as a 48-bit morton code, with `x` in the lowest bit
+pub(crate) fn morton_encode_16(x: u32, y: u32, z: u32) -> u64 {
+    spread_bits_16(x) | (spread_bits_16(y) << 1) | (spread_bits_16(z) << 2)
+}
+
+/// Inverse of `morton_encode_16`
+pub(crate) fn morton_decode_16(morton_code: u64) -> [u32; 3] {
+    [
+        compact_bits_16(morton_code),
+        compact_bits_16(morton_code >> 1),
+        compact_bits_16(morton_code >> 2),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_potree_attribute_mapping() {
+        let intensity = PotreeAttribute::from_point_attribute(&INTENSITY);
+        assert_eq!("intensity", intensity.name);
+        assert_eq!("uint16", intensity.attribute_type);
+        assert_eq!(INTENSITY, intensity.point_attribute());
+
+        let position = PotreeAttribute::from_point_attribute(&POSITION_3D);
+        assert_eq!(12, position.size);
+        assert_eq!(
+            POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3i32),
+            position.point_attribute()
+        );
+
+        let custom = PointAttributeDefinition::custom(
+            Cow::Borrowed("Deviation"),
+            PointAttributeDataType::Vec3f32,
+        );
+        let potree_custom = PotreeAttribute::from_point_attribute(&custom);
+        assert_eq!(3, potree_custom.num_elements);
+        assert_eq!(4, potree_custom.element_size);
+        assert_eq!(custom, potree_custom.point_attribute());
+
+        let unknown = PotreeAttribute {
+            attribute_type: "undefined".into(),
+            size: 5,
+            ..potree_custom
+        };
+        assert_eq!(PointAttributeDataType::ByteArray(5), unknown.datatype());
+    }
+
+    #[test]
+    fn test_potree_node_bounds() -> Result<()> {
+        let root_bounds =
+            AABB::from_min_max(Point3::new(0.0, 0.0, 0.0), Point3::new(8.0, 8.0, 8.0));
+        assert_eq!(root_bounds, potree_node_bounds("r", &root_bounds)?);
+        let bounds = potree_node_bounds("r17", &root_bounds)?;
+        assert_eq!(Point3::new(2.0, 2.0, 6.0), *bounds.min());
+        assert_eq!(Point3::new(4.0, 4.0, 8.0), *bounds.max());
+        assert!(potree_node_bounds("r8", &root_bounds).is_err());
+        assert!(potree_node_bounds("x0", &root_bounds).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_morton_code_roundtrip() {
+        for (x, y, z) in [(0, 0, 0), (1, 2, 3), (0xFFFF, 0, 0xABCD), (1234, 65535, 42)] {
+            assert_eq!([x, y, z], morton_decode_16(morton_encode_16(x, y, z)));
+        }
+        assert_eq!(0b111, morton_encode_16(1, 1, 1));
+        assert_eq!(0b100_000, morton_encode_16(0, 0, 2));
+    }
+
+    #[test]
+    fn test_potree_node_roundtrip() -> Result<()> {
+        let node = PotreeNode {
+            name: "r03".into(),
+            node_type: PotreeNodeType::Proxy,
+            child_mask: 0b1010_0001,
+            num_points: 1234,
+            byte_offset: 5678,
+            byte_size: 90,
+        };
+        let mut data = vec![];
+        node.write_to(&mut data)?;
+        assert_eq!(PotreeNode::SIZE, data.len());
+        assert_eq!(node, PotreeNode::read_from(&data, "r03".into())?);
+        assert_eq!(2, node.level());
+        assert_eq!(vec![0, 5, 7], node.child_indices().collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_potree_converter_metadata() -> Result<()> {
+        let json = r#"{
+            "version": "2.0",
+            "name": "lion",
+            "description": "",
+            "points": 4000000,
+            "projection": "",
+            "hierarchy": { "firstChunkSize": 1276, "stepSize": 4, "depth": 5 },
+            "offset": [-0.75, -0.5, -0.25],
+            "scale": [0.001, 0.001, 0.001],
+            "spacing": 0.0234375,
+            "boundingBox": { "min": [-0.75, -0.5, -0.25], "max": [2.25, 2.5, 2.75] },
+            "encoding": "BROTLI",
+            "attributes": [
+                {
+                    "name": "position", "description": "", "size": 12, "numElements": 3,
+                    "elementSize": 4, "type": "int32", "min": [-0.7, -0.4, -0.2], "max": [2.1, 2.4, 2.5]
+                },
+                {
+                    "name": "rgb", "description": "", "size": 6, "numElements": 3,
+                    "elementSize": 2, "type": "uint16", "min": [0, 0, 0], "max": [65280, 65280, 65280]
+                },
+                {
+                    "name": "classification", "description": "", "size": 1, "numElements": 1,
+                    "elementSize": 1, "type": "uint8", "min": [0], "max": [0]
+                },
+                {
+                    "name": "Amplitude", "description": "", "size": 4, "numElements": 1,
+                    "elementSize": 4, "type": "float", "min": [0], "max": [12.5]
+                }
+            ]
+        }"#;
+        let metadata: PotreeMetadata = serde_json::from_str(json)?;
+        metadata.verify()?;
+        assert_eq!(PotreeEncoding::Brotli, metadata.encoding);
+        assert_eq!(1276, metadata.hierarchy.first_chunk_size);
+        assert_eq!(23, metadata.bytes_per_point());
+        assert_eq!(
+            AABB::from_min_max(
+                Point3::new(-0.75, -0.5, -0.25),
+                Point3::new(2.25, 2.5, 2.75)
+            ),
+            metadata.root_bounds()
+        );
+        assert_eq!(0.0234375 / 4.0, metadata.spacing_at_level(2));
+
+        let attributes = metadata
+            .attributes
+            .iter()
+            .map(|attribute| attribute.point_attribute())
+            .collect::<Vec<_>>();
+        assert_eq!(COLOR_RGB, attributes[1]);
+        assert_eq!(CLASSIFICATION, attributes[2]);
+        assert_eq!(
+            PointAttributeDefinition::custom(
+                Cow::Borrowed("Amplitude"),
+                PointAttributeDataType::F32
+            ),
+            attributes[3]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,723 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use pasture_core::containers::{
+    BorrowedBuffer, BorrowedMutBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer,
+    VectorBuffer,
+};
+use pasture_core::layout::attributes::{COLOR_RGB, POSITION_3D};
+use pasture_core::layout::conversion::BufferLayoutConverter;
+use pasture_core::layout::{PointAttributeDataType, PointAttributeDefinition, PointLayout};
+use pasture_core::math::AABB;
+use pasture_core::nalgebra::{Point3, Vector3};
+
+use super::{
+    morton_encode_16, PotreeAttribute, PotreeBoundingBox, PotreeEncoding, PotreeHierarchyInfo,
+    PotreeMetadata, PotreeNode, PotreeNodeType, POTREE_HIERARCHY_FILE_NAME,
+    POTREE_METADATA_FILE_NAME, POTREE_OCTREE_FILE_NAME, POTREE_POSITION_ATTRIBUTE_NAME,
+    POTREE_RGB_ATTRIBUTE_NAME,
+};
+use crate::base::{build_octree, OctreeParameters, PointReader, PointWriter};
+
+/// Number of grid cells along each axis of an octree node that are used for sampling the points of the node
+const GRID_SIZE: i64 = 128;
+/// Maximum depth of the octree. All remaining points of a node at this depth are stored in the node itself
+const MAX_DEPTH: u32 = 20;
+/// Number of points that `write_from` reads at once
+const POINTS_PER_CHUNK: usize = 1 << 16;
+/// Brotli compression quality (0 to 11) for the `BROTLI` encoding
+const BROTLI_QUALITY: u32 = 6;
+/// Base-2 logarithm of the Brotli window size for the `BROTLI` encoding
+const BROTLI_WINDOW_SIZE: u32 = 22;
+
+/// Sorts the points with the given `positions` into a Potree octree with the given cubic `root_bounds` (see
+/// `build_octree`). Returns the names of all nodes together with the indices of the points within each node
+fn build_potree_octree(
+    positions: &[Point3<f64>],
+    root_bounds: &AABB<f64>,
+    max_points_per_node: usize,
+) -> BTreeMap<String, Vec<usize>> {
+    build_octree(
+        positions,
+        PotreeNode::ROOT_NAME.to_owned(),
+        root_bounds,
+        &OctreeParameters {
+            max_points_per_node,
+            grid_size: GRID_SIZE,
+            max_depth: MAX_DEPTH,
+        },
+        // Potree uses bit 2 of the child index for the x-axis, bit 1 for the y-axis and bit 0 for the z-axis
+        |octant| ((octant.x as usize) << 2) | ((octant.y as usize) << 1) | octant.z as usize,
+        |name, child_index| format!("{}{}", name, child_index),
+    )
+    .into_iter()
+    .collect()
+}
+
+/// Encodes the points of a single node from the binary layout of the `DEFAULT` encoding into the `BROTLI` encoding
+/// (without compression). This is the inverse of the decoding in the `PotreeReader`
+fn encode_brotli_attributes(
+    point_data: &[u8],
+    attributes: &[PotreeAttribute],
+    raw_layout: &PointLayout,
+) -> Vec<u8> {
+    let bytes_per_point = raw_layout.size_of_point_entry() as usize;
+    let mut encoded = vec![];
+    for (attribute, member) in attributes.iter().zip(raw_layout.attributes()) {
+        let attribute_range = member.byte_range_within_point();
+        for point in point_data.chunks_exact(bytes_per_point) {
+            let value = &point[attribute_range.clone()];
+            match attribute.name.as_str() {
+                POTREE_POSITION_ATTRIBUTE_NAME => {
+                    let coordinates = [
+                        LittleEndian::read_u32(&value[0..4]),
+                        LittleEndian::read_u32(&value[4..8]),
+                        LittleEndian::read_u32(&value[8..12]),
+                    ];
+                    let upper = morton_encode_16(
+                        coordinates[0] >> 16,
+                        coordinates[1] >> 16,
+                        coordinates[2] >> 16,
+                    );
+                    let lower = morton_encode_16(coordinates[0], coordinates[1], coordinates[2]);
+                    encoded.extend_from_slice(&upper.to_le_bytes());
+                    encoded.extend_from_slice(&lower.to_le_bytes());
+                }
+                POTREE_RGB_ATTRIBUTE_NAME => {
+                    let rgb = morton_encode_16(
+                        LittleEndian::read_u16(&value[0..2]) as u32,
+                        LittleEndian::read_u16(&value[2..4]) as u32,
+                        LittleEndian::read_u16(&value[4..6]) as u32,
+                    );
+                    encoded.extend_from_slice(&rgb.to_le_bytes());
+                }
+                _ => encoded.extend_from_slice(value),
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns the minimum and maximum of each component of the given `attribute`, or `None` if the attribute is not
+/// numeric
+fn attribute_range(
+    points: &VectorBuffer,
+    attribute: &PointAttributeDefinition,
+) -> Option<(Vec<f64>, Vec<f64>)> {
+    let values: Vec<Vec<f64>> = match attribute.datatype() {
+        PointAttributeDataType::Vec3u8
+        | PointAttributeDataType::Vec3u16
+        | PointAttributeDataType::Vec3i32
+        | PointAttributeDataType::Vec3f32
+        | PointAttributeDataType::Vec3f64 => points
+            .view_attribute_with_conversion::<Vector3<f64>>(
+                &attribute.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            )
+            .ok()?
+            .into_iter()
+            .map(|value| vec![value.x, value.y, value.z])
+            .collect(),
+        PointAttributeDataType::Vec4u8
+        | PointAttributeDataType::ByteArray(_)
+        | PointAttributeDataType::Custom { .. } => return None,
+        _ => points
+            .view_attribute_with_conversion::<f64>(
+                &attribute.with_custom_datatype(PointAttributeDataType::F64),
+            )
+            .ok()?
+            .into_iter()
+            .map(|value| vec![value])
+            .collect(),
+    };
+    let first = values.first()?.clone();
+    Some(
+        values
+            .iter()
+            .fold((first.clone(), first), |(min, max), value| {
+                (
+                    min.iter().zip(value).map(|(a, b)| a.min(*b)).collect(),
+                    max.iter().zip(value).map(|(a, b)| a.max(*b)).collect(),
+                )
+            }),
+    )
+}
+
+/// `PointWriter` implementation for Potree 2.0 octrees. The points of all `write` calls are sorted into an octree,
+/// which is written as the `metadata.json`, `hierarchy.bin` and `octree.bin` files into the output directory. The
+/// resulting octree can be read with the [`PotreeReader`](super::PotreeReader) as well as with the Potree viewer.
+/// Use [`write_from`](Self::write_from) to convert the points of any `PointReader` into a Potree octree.
+///
+/// Each node stores a subsample of the points in its bounds (one point per cell of a regular grid), all other points
+/// are passed on to its child nodes. Positions are stored as integer coordinates relative to the minimum of the
+/// bounding box, using the scale set with [`with_scale`](Self::with_scale). Colors are always stored as `uint16`
+/// values, all other attributes are stored with their datatype in pasture.
+///
+/// *NOTE*: The octree can only be built once all points are known, so the `PotreeWriter` keeps all points in memory
+/// and writes the actual files on `flush`. Once you are done writing points, make sure to call `flush`!
+pub struct PotreeWriter {
+    output_directory: PathBuf,
+    points: VectorBuffer,
+    encoding: PotreeEncoding,
+    max_points_per_node: usize,
+    hierarchy_step_size: u32,
+    scale: Vector3<f64>,
+    requires_flush: bool,
+}
+
+impl PotreeWriter {
+    /// The default maximum number of points in an octree node before the node is subdivided
+    pub const DEFAULT_MAX_POINTS_PER_NODE: usize = 50_000;
+    /// The default number of octree levels in each hierarchy chunk
+    pub const DEFAULT_HIERARCHY_STEP_SIZE: u32 = 4;
+    /// The default scale of the integer coordinates of the positions
+    pub const DEFAULT_SCALE: f64 = 0.001;
+
+    /// Creates a new `PotreeWriter` that writes points with the given `point_layout` into a Potree octree in the
+    /// given `output_directory`. The directory is created if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// If `point_layout` does not contain the `POSITION_3D` attribute, or if the output directory can't be created,
+    /// an error is returned
+    pub fn from_path_and_point_layout<P: AsRef<Path>>(
+        output_directory: P,
+        point_layout: &PointLayout,
+    ) -> Result<Self> {
+        if !point_layout.has_attribute_with_name(POSITION_3D.name()) {
+            bail!("PointLayout for PotreeWriter must contain the POSITION_3D attribute");
+        }
+        let output_directory = output_directory.as_ref().to_owned();
+        fs::create_dir_all(&output_directory).with_context(|| {
+            format!(
+                "Could not create output directory {}",
+                output_directory.display()
+            )
+        })?;
+        Ok(Self {
+            output_directory,
+            points: VectorBuffer::new_from_layout(point_layout.clone()),
+            encoding: Default::default(),
+            max_points_per_node: Self::DEFAULT_MAX_POINTS_PER_NODE,
+            hierarchy_step_size: Self::DEFAULT_HIERARCHY_STEP_SIZE,
+            scale: Vector3::new(
+                Self::DEFAULT_SCALE,
+                Self::DEFAULT_SCALE,
+                Self::DEFAULT_SCALE,
+            ),
+            requires_flush: true,
+        })
+    }
+
+    /// Sets the encoding of the point data
+    pub fn with_encoding(mut self, encoding: PotreeEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the maximum number of points in an octree node. Nodes with more points are subdivided
+    pub fn with_max_points_per_node(mut self, max_points_per_node: usize) -> Self {
+        self.max_points_per_node = max_points_per_node.max(1);
+        self
+    }
+
+    /// Sets the number of octree levels that are stored in each hierarchy chunk
+    pub fn with_hierarchy_step_size(mut self, hierarchy_step_size: u32) -> Self {
+        self.hierarchy_step_size = hierarchy_step_size.max(1);
+        self
+    }
+
+    /// Sets the scale of the integer coordinates of the positions, i.e. the precision of the positions
+    pub fn with_scale(mut self, scale: Vector3<f64>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Writes all points of the given `reader`. The points are read in the default `PointLayout` of this writer
+    pub fn write_from<R: PointReader>(&mut self, reader: &mut R) -> Result<()> {
+        let mut chunk =
+            VectorBuffer::with_capacity(POINTS_PER_CHUNK, self.points.point_layout().clone());
+        loop {
+            chunk.resize(POINTS_PER_CHUNK);
+            let num_read = reader.read_into(&mut chunk, POINTS_PER_CHUNK)?;
+            if num_read == 0 {
+                return Ok(());
+            }
+            chunk.resize(num_read);
+            self.write(&chunk)?;
+        }
+    }
+
+    /// Writes the Potree octree from all points that have been written so far
+    fn write_potree_octree(&mut self) -> Result<()> {
+        let positions = self
+            .points
+            .view_attribute_with_conversion::<Vector3<f64>>(&POSITION_3D)?
+            .into_iter()
+            .map(Point3::from)
+            .collect::<Vec<_>>();
+        let tight_bounds = positions
+            .iter()
+            .fold(None, |bounds: Option<AABB<f64>>, position| match bounds {
+                Some(bounds) => Some(AABB::extend_with_point(&bounds, position)),
+                None => Some(AABB::from_min_max_unchecked(*position, *position)),
+            })
+            .unwrap_or_else(|| AABB::from_min_max_unchecked(Point3::origin(), Point3::origin()));
+        let root_bounds = tight_bounds.as_cubic();
+        let offset = root_bounds.min().coords;
+        let max_coordinate = root_bounds.extent().component_div(&self.scale).max();
+        if max_coordinate >= i32::MAX as f64 {
+            bail!(
+                "The extent of the points is too large for the scale {} of the PotreeWriter",
+                self.scale
+            );
+        }
+
+        // The points are stored in the binary layout of the `DEFAULT` encoding, with integer positions and 16-bit colors
+        let mut attributes = vec![];
+        let mut raw_attributes = vec![];
+        for member in self.points.point_layout().attributes() {
+            let attribute = member.attribute_definition();
+            let mut potree_attribute = PotreeAttribute::from_point_attribute(attribute);
+            if attribute.name() == POSITION_3D.name() {
+                potree_attribute.min = tight_bounds.min().coords.as_slice().to_vec();
+                potree_attribute.max = tight_bounds.max().coords.as_slice().to_vec();
+                raw_attributes
+                    .push(attribute.with_custom_datatype(PointAttributeDataType::Vec3i32));
+            } else if attribute.name() == COLOR_RGB.name() {
+                if let Some((min, max)) = attribute_range(&self.points, attribute) {
+                    potree_attribute.min = min;
+                    potree_attribute.max = max;
+                }
+                raw_attributes
+                    .push(attribute.with_custom_datatype(PointAttributeDataType::Vec3u16));
+            } else {
+                if let Some((min, max)) = attribute_range(&self.points, attribute) {
+                    potree_attribute.min = min;
+                    potree_attribute.max = max;
+                }
+                raw_attributes.push(attribute.clone());
+            }
+            attributes.push(potree_attribute);
+        }
+        let raw_layout = PointLayout::from_attributes_packed(&raw_attributes, 1);
+        let mut raw_points =
+            BufferLayoutConverter::for_layouts(self.points.point_layout(), &raw_layout)
+                .convert::<VectorBuffer, _>(&self.points);
+        let integer_positions = positions
+            .iter()
+            .flat_map(|position| {
+                let local = (position.coords - offset).component_div(&self.scale);
+                [
+                    local.x.round() as i32,
+                    local.y.round() as i32,
+                    local.z.round() as i32,
+                ]
+            })
+            .collect::<Vec<_>>();
+        // Safe because the positions are stored as `Vec3i32` in the raw layout
+        unsafe {
+            raw_points.set_attribute_range(
+                &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3i32),
+                0..positions.len(),
+                bytemuck::cast_slice(&integer_positions),
+            );
+        }
+
+        let octree = build_potree_octree(&positions, &root_bounds, self.max_points_per_node);
+
+        // Write the point data of all nodes into `octree.bin`
+        let octree_path = self.output_directory.join(POTREE_OCTREE_FILE_NAME);
+        let mut octree_writer = BufWriter::new(
+            File::create(&octree_path)
+                .with_context(|| format!("Could not create {}", octree_path.display()))?,
+        );
+        let mut nodes = BTreeMap::new();
+        let mut byte_offset = 0;
+        let mut node_data = vec![];
+        for (name, point_indices) in &octree {
+            node_data.clear();
+            for point_index in point_indices {
+                node_data.extend_from_slice(raw_points.get_point_ref(*point_index));
+            }
+            let encoded_data = match self.encoding {
+                PotreeEncoding::Default => node_data.clone(),
+                PotreeEncoding::Brotli => {
+                    let attribute_data =
+                        encode_brotli_attributes(&node_data, &attributes, &raw_layout);
+                    let mut compressed = vec![];
+                    {
+                        let mut compressor = brotli::CompressorWriter::new(
+                            &mut compressed,
+                            4096,
+                            BROTLI_QUALITY,
+                            BROTLI_WINDOW_SIZE,
+                        );
+                        compressor.write_all(&attribute_data)?;
+                    }
+                    compressed
+                }
+            };
+            octree_writer.write_all(&encoded_data)?;
+
+            let child_mask = (0..8)
+                .filter(|child_index| octree.contains_key(&format!("{}{}", name, child_index)))
+                .fold(0_u8, |mask, child_index| mask | (1 << child_index));
+            nodes.insert(
+                name.clone(),
+                PotreeNode {
+                    name: name.clone(),
+                    node_type: if child_mask == 0 {
+                        PotreeNodeType::Leaf
+                    } else {
+                        PotreeNodeType::Normal
+                    },
+                    child_mask,
+                    num_points: point_indices.len() as u32,
+                    byte_offset,
+                    byte_size: encoded_data.len() as u64,
+                },
+            );
+            byte_offset += encoded_data.len() as u64;
+        }
+        octree_writer
+            .flush()
+            .with_context(|| format!("Could not write {}", octree_path.display()))?;
+
+        let hierarchy = self.build_hierarchy(&nodes)?;
+        let hierarchy_path = self.output_directory.join(POTREE_HIERARCHY_FILE_NAME);
+        fs::write(&hierarchy_path, &hierarchy.0)
+            .with_context(|| format!("Could not write {}", hierarchy_path.display()))?;
+
+        let metadata = PotreeMetadata {
+            version: PotreeMetadata::VERSION.to_owned(),
+            name: String::new(),
+            description: String::new(),
+            points: positions.len() as u64,
+            projection: String::new(),
+            hierarchy: PotreeHierarchyInfo {
+                first_chunk_size: hierarchy.1,
+                step_size: self.hierarchy_step_size,
+                depth: nodes
+                    .keys()
+                    .map(|name| name.len() as u32 - 1)
+                    .max()
+                    .unwrap_or_default(),
+            },
+            offset: offset.into(),
+            scale: self.scale.into(),
+            spacing: root_bounds.extent().x / GRID_SIZE as f64,
+            bounding_box: PotreeBoundingBox::from(&root_bounds),
+            encoding: self.encoding,
+            attributes,
+        };
+        let metadata_path = self.output_directory.join(POTREE_METADATA_FILE_NAME);
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&metadata_path)?), &metadata)
+            .with_context(|| format!("Could not write {}", metadata_path.display()))?;
+        Ok(())
+    }
+
+    /// Builds the contents of `hierarchy.bin` from the given `nodes`. Each hierarchy chunk contains the nodes of
+    /// `hierarchy_step_size` levels of the subtree of its root node in breadth-first order. Nodes with children at the
+    /// next level are stored as proxy nodes that reference the chunk which has them as root. Returns the data
+    /// together with the size of the first chunk
+    fn build_hierarchy(&self, nodes: &BTreeMap<String, PotreeNode>) -> Result<(Vec<u8>, u64)> {
+        if nodes.is_empty() {
+            return Ok((vec![], 0));
+        }
+
+        // First determine the nodes of each chunk, then their positions in `hierarchy.bin`
+        let mut chunks = vec![];
+        let mut chunk_roots = VecDeque::from(vec![PotreeNode::ROOT_NAME.to_owned()]);
+        while let Some(chunk_root) = chunk_roots.pop_front() {
+            let max_level = (chunk_root.len() as u32 - 1) + self.hierarchy_step_size;
+            let mut chunk_nodes = vec![];
+            let mut nodes_to_visit = VecDeque::from(vec![chunk_root]);
+            while let Some(name) = nodes_to_visit.pop_front() {
+                let node = &nodes[&name];
+                let is_proxy = node.level() == max_level && node.child_mask != 0;
+                if is_proxy {
+                    chunk_roots.push_back(name.clone());
+                } else {
+                    nodes_to_visit.extend(
+                        node.child_indices()
+                            .map(|child_index| format!("{}{}", name, child_index)),
+                    );
+                }
+                chunk_nodes.push((name, is_proxy));
+            }
+            chunks.push(chunk_nodes);
+        }
+
+        let mut chunk_offsets = BTreeMap::new();
+        let mut offset = 0;
+        for chunk in &chunks {
+            let size = (chunk.len() * PotreeNode::SIZE) as u64;
+            chunk_offsets.insert(chunk[0].0.clone(), (offset, size));
+            offset += size;
+        }
+
+        let mut hierarchy = Vec::with_capacity(offset as usize);
+        for chunk in &chunks {
+            for (name, is_proxy) in chunk {
+                let node = &nodes[name];
+                if *is_proxy {
+                    let (chunk_offset, chunk_size) = chunk_offsets[name];
+                    PotreeNode {
+                        node_type: PotreeNodeType::Proxy,
+                        byte_offset: chunk_offset,
+                        byte_size: chunk_size,
+                        ..node.clone()
+                    }
+                    .write_to(&mut hierarchy)?;
+                } else {
+                    node.write_to(&mut hierarchy)?;
+                }
+            }
+        }
+        let first_chunk_size = (chunks[0].len() * PotreeNode::SIZE) as u64;
+        Ok((hierarchy, first_chunk_size))
+    }
+}
+
+impl PointWriter for PotreeWriter {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if points.point_layout() != self.points.point_layout() {
+            panic!("PointLayout of buffer does not match the PointLayout that this PotreeWriter was constructed with! Make sure that you only pass PointBuffers with the same layout as the one you used to create this PotreeWriter!");
+        }
+        let base_point_index = self.points.len();
+        self.points.resize(base_point_index + points.len());
+        let point_layout = self.points.point_layout().clone();
+        BufferLayoutConverter::for_layouts(&point_layout, &point_layout).convert_into_range(
+            points,
+            0..points.len(),
+            &mut self.points,
+            base_point_index..(base_point_index + points.len()),
+        );
+        self.requires_flush = true;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.requires_flush {
+            return Ok(());
+        }
+        self.write_potree_octree()?;
+        self.requires_flush = false;
+        Ok(())
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        self.points.point_layout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use pasture_core::layout::{attributes::INTENSITY, PointType};
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::potree::{PotreeQuery, PotreeReader};
+
+    #[derive(
+        Copy, Clone, PartialEq, Debug, PointType, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    #[repr(C, packed)]
+    struct TestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u16>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+        #[pasture(attribute = "Deviation")]
+        deviation: f32,
+    }
+
+    /// A dense cluster of points together with a single outlier, so that the octree has several levels
+    fn test_points() -> VectorBuffer {
+        (0..5_000)
+            .map(|index| TestPoint {
+                position: if index == 4_999 {
+                    Vector3::new(1010.0, -490.0, 10.0)
+                } else {
+                    Vector3::new(
+                        1000.0 + (index % 20) as f64 * 0.01,
+                        -500.0 + ((index / 20) % 25) as f64 * 0.01,
+                        (index / 500) as f64 * 0.01,
+                    )
+                },
+                color: Vector3::new(index as u16, 65535 - index as u16, 1234),
+                intensity: index as u16,
+                deviation: index as f32 * 0.5,
+            })
+            .collect()
+    }
+
+    fn test_directory(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pasture_{}_{}", name, std::process::id()))
+    }
+
+    fn sorted_by_intensity(points: &VectorBuffer) -> Vec<TestPoint> {
+        let mut points = points.view::<TestPoint>().into_iter().collect::<Vec<_>>();
+        points.sort_by_key(|point| point.intensity);
+        points
+    }
+
+    fn run_roundtrip_test(name: &str, encoding: PotreeEncoding) -> Result<()> {
+        let directory = test_directory(name);
+        let expected_points = test_points();
+        {
+            let mut writer =
+                PotreeWriter::from_path_and_point_layout(&directory, &TestPoint::layout())?
+                    .with_encoding(encoding)
+                    .with_max_points_per_node(500)
+                    .with_hierarchy_step_size(1);
+            writer.write(&expected_points)?;
+            writer.flush()?;
+        }
+
+        let mut reader = PotreeReader::from_path(&directory)?;
+        assert_eq!(encoding, reader.metadata().encoding);
+        assert_eq!(
+            vec!["position", "rgb", "intensity", "Deviation"],
+            reader
+                .metadata()
+                .attributes
+                .iter()
+                .map(|attribute| attribute.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(5_000), reader.get_metadata().number_of_points());
+        assert!(reader.max_depth() > 1);
+        assert_eq!(
+            5_000,
+            reader
+                .nodes()
+                .iter()
+                .map(|node| node.num_points as usize)
+                .sum::<usize>()
+        );
+        let root_bounds = reader.metadata().root_bounds();
+        let deviation = PointAttributeDefinition::custom(
+            Cow::Borrowed("Deviation"),
+            PointAttributeDataType::F32,
+        );
+        assert!(reader.point_layout().has_attribute(&deviation));
+        assert!(reader.point_layout().has_attribute(&INTENSITY));
+
+        // Read all points as a PointReader, in the layout of the test points
+        let all_points = reader.read::<VectorBuffer>(5_000)?;
+        let mut all_points_in_test_layout =
+            VectorBuffer::with_capacity(all_points.len(), TestPoint::layout());
+        all_points_in_test_layout.resize(all_points.len());
+        BufferLayoutConverter::for_layouts(all_points.point_layout(), &TestPoint::layout())
+            .convert_into(&all_points, &mut all_points_in_test_layout);
+        for (expected, actual) in sorted_by_intensity(&expected_points)
+            .iter()
+            .zip(sorted_by_intensity(&all_points_in_test_layout))
+        {
+            let expected_position = expected.position;
+            let actual_position = actual.position;
+            assert!((expected_position - actual_position).amax() < 1e-3);
+            assert_eq!({ expected.color }, { actual.color });
+            assert_eq!({ expected.deviation }, { actual.deviation });
+        }
+        assert_eq!(0, reader.read::<VectorBuffer>(1)?.len());
+
+        // Node-level access
+        let root_points = reader.read_node::<VectorBuffer>("r")?;
+        assert!(!root_points.is_empty() && root_points.len() < 5_000);
+        let deepest_node = reader.nodes().last().unwrap().clone();
+        let mut node_points =
+            VectorBuffer::with_capacity(deepest_node.num_points as usize, TestPoint::layout());
+        node_points.resize(deepest_node.num_points as usize);
+        reader.read_node_into(&deepest_node, &mut node_points)?;
+        let node_bounds = deepest_node.bounds(&root_bounds);
+        for point in node_points.view::<TestPoint>() {
+            let position = point.position;
+            assert!(node_bounds.contains(&Point3::from(position)));
+        }
+
+        // Queries by level and by bounds
+        let level_one_points =
+            reader.read_query::<VectorBuffer>(&PotreeQuery::new().with_level(1))?;
+        assert_eq!(
+            reader.query_point_count(&PotreeQuery::new().with_level(1)),
+            level_one_points.len()
+        );
+        let query_bounds = AABB::from_min_max(
+            Point3::new(1000.0, -500.0, 0.0),
+            Point3::new(1000.05, -499.9, 0.05),
+        );
+        let queried_points =
+            reader.read_query::<VectorBuffer>(&PotreeQuery::new().with_bounds(query_bounds))?;
+        let count_in_bounds = |points: &VectorBuffer| {
+            points
+                .view_attribute::<Vector3<f64>>(&PotreeMetadata::position_attribute())
+                .into_iter()
+                .filter(|position| query_bounds.contains(&Point3::from(*position)))
+                .count()
+        };
+        assert_eq!(
+            count_in_bounds(&all_points),
+            count_in_bounds(&queried_points)
+        );
+        let outlier_bounds = AABB::from_min_max(
+            Point3::new(1009.0, -491.0, 9.0),
+            Point3::new(1011.0, -489.0, 11.0),
+        );
+        let outlier_query = PotreeQuery::new().with_bounds(outlier_bounds);
+        assert!(reader.query_point_count(&outlier_query) < 5_000);
+        assert!(reader.query_nodes(&outlier_query).len() < reader.nodes().len());
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_potree_roundtrip_default_encoding() -> Result<()> {
+        run_roundtrip_test("potree_default", PotreeEncoding::Default)
+    }
+
+    #[test]
+    fn test_potree_roundtrip_brotli_encoding() -> Result<()> {
+        run_roundtrip_test("potree_brotli", PotreeEncoding::Brotli)
+    }
+
+    #[test]
+    fn test_potree_write_from_reader() -> Result<()> {
+        let directory = test_directory("potree_write_from_reader");
+        let target_directory = test_directory("potree_write_from_reader_copy");
+        {
+            let mut writer =
+                PotreeWriter::from_path_and_point_layout(&directory, &TestPoint::layout())?
+                    .with_max_points_per_node(1_000);
+            writer.write(&test_points())?;
+            writer.flush()?;
+        }
+        let mut reader = PotreeReader::from_path(directory.join(POTREE_METADATA_FILE_NAME))?;
+        {
+            let mut writer = PotreeWriter::from_path_and_point_layout(
+                &target_directory,
+                reader.get_default_point_layout(),
+            )?
+            .with_encoding(PotreeEncoding::Brotli);
+            writer.write_from(&mut reader)?;
+            writer.flush()?;
+        }
+        let copy = PotreeReader::from_path(&target_directory)?;
+        assert_eq!(reader.metadata().points, copy.metadata().points);
+        assert_eq!(reader.get_metadata().bounds(), copy.get_metadata().bounds());
+
+        fs::remove_dir_all(&directory)?;
+        fs::remove_dir_all(&target_directory)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, bail, Result};
-use pasture_core::math::Alignable;
+use pasture_core::{layout::PointAttributeDataType, math::Alignable};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::{
@@ -85,6 +85,61 @@ impl From<&BatchTableEntry> for Value {
     }
 }
 
+impl BatchTableDataReference {
+    /// Returns the pasture datatype that corresponds to the `componentType` and `type` of this reference, or `None` if
+    /// there is no matching pasture datatype (e.g. for `VEC2` types)
+    pub fn datatype(&self) -> Option<PointAttributeDataType> {
+        match (
+            self.component_type.as_str(),
+            self.scalar_or_vector_type.as_str(),
+        ) {
+            ("BYTE", "SCALAR") => Some(PointAttributeDataType::I8),
+            ("UNSIGNED_BYTE", "SCALAR") => Some(PointAttributeDataType::U8),
+            ("SHORT", "SCALAR") => Some(PointAttributeDataType::I16),
+            ("UNSIGNED_SHORT", "SCALAR") => Some(PointAttributeDataType::U16),
+            ("INT", "SCALAR") => Some(PointAttributeDataType::I32),
+            ("UNSIGNED_INT", "SCALAR") => Some(PointAttributeDataType::U32),
+            ("FLOAT", "SCALAR") => Some(PointAttributeDataType::F32),
+            ("DOUBLE", "SCALAR") => Some(PointAttributeDataType::F64),
+            ("UNSIGNED_BYTE", "VEC3") => Some(PointAttributeDataType::Vec3u8),
+            ("UNSIGNED_SHORT", "VEC3") => Some(PointAttributeDataType::Vec3u16),
+            ("INT", "VEC3") => Some(PointAttributeDataType::Vec3i32),
+            ("FLOAT", "VEC3") => Some(PointAttributeDataType::Vec3f32),
+            ("DOUBLE", "VEC3") => Some(PointAttributeDataType::Vec3f64),
+            ("UNSIGNED_BYTE", "VEC4") => Some(PointAttributeDataType::Vec4u8),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the datatype in which values of the given `datatype` are stored inside a BatchTable binary body, together
+/// with the matching `componentType` and `type` values. 64-bit integers have no BatchTable equivalent and are stored as
+/// `DOUBLE`. Returns `None` if values of `datatype` can't be stored inside a BatchTable binary body
+pub fn batch_table_type_from_datatype(
+    datatype: PointAttributeDataType,
+) -> Option<(PointAttributeDataType, &'static str, &'static str)> {
+    match datatype {
+        PointAttributeDataType::I8 => Some((datatype, "BYTE", "SCALAR")),
+        PointAttributeDataType::U8 => Some((datatype, "UNSIGNED_BYTE", "SCALAR")),
+        PointAttributeDataType::I16 => Some((datatype, "SHORT", "SCALAR")),
+        PointAttributeDataType::U16 => Some((datatype, "UNSIGNED_SHORT", "SCALAR")),
+        PointAttributeDataType::I32 => Some((datatype, "INT", "SCALAR")),
+        PointAttributeDataType::U32 => Some((datatype, "UNSIGNED_INT", "SCALAR")),
+        PointAttributeDataType::F32 => Some((datatype, "FLOAT", "SCALAR")),
+        PointAttributeDataType::F64 => Some((datatype, "DOUBLE", "SCALAR")),
+        PointAttributeDataType::U64 | PointAttributeDataType::I64 => {
+            Some((PointAttributeDataType::F64, "DOUBLE", "SCALAR"))
+        }
+        PointAttributeDataType::Vec3u8 => Some((datatype, "UNSIGNED_BYTE", "VEC3")),
+        PointAttributeDataType::Vec3u16 => Some((datatype, "UNSIGNED_SHORT", "VEC3")),
+        PointAttributeDataType::Vec3i32 => Some((datatype, "INT", "VEC3")),
+        PointAttributeDataType::Vec3f32 => Some((datatype, "FLOAT", "VEC3")),
+        PointAttributeDataType::Vec3f64 => Some((datatype, "DOUBLE", "VEC3")),
+        PointAttributeDataType::Vec4u8 => Some((datatype, "UNSIGNED_BYTE", "VEC4")),
+        _ => None,
+    }
+}
+
 /// A 3D Tiles BatchTable header, which is a collection of BatchTableEntries
 pub type BatchTableHeader = HashMap<String, BatchTableEntry>;
 
@@ -159,6 +214,36 @@ mod tests {
         header
     }
 
+    #[test]
+    fn test_batch_table_datatypes() {
+        for datatype in [
+            PointAttributeDataType::I8,
+            PointAttributeDataType::U16,
+            PointAttributeDataType::F64,
+            PointAttributeDataType::Vec3f32,
+            PointAttributeDataType::Vec4u8,
+        ] {
+            let (stored_datatype, component_type, scalar_or_vector_type) =
+                batch_table_type_from_datatype(datatype).unwrap();
+            assert_eq!(datatype, stored_datatype);
+            let reference = BatchTableDataReference {
+                byte_offset: 0,
+                component_type: component_type.into(),
+                scalar_or_vector_type: scalar_or_vector_type.into(),
+            };
+            assert_eq!(Some(datatype), reference.datatype());
+        }
+
+        assert_eq!(
+            Some((PointAttributeDataType::F64, "DOUBLE", "SCALAR")),
+            batch_table_type_from_datatype(PointAttributeDataType::U64)
+        );
+        assert_eq!(
+            None,
+            batch_table_type_from_datatype(PointAttributeDataType::ByteArray(4))
+        );
+    }
+
     #[test]
     fn test_3dtiles_batch_table_io() -> Result<()> {
         let expected_header = dummy_batch_table_header();
@@ -0,0 +1,113 @@
+use pasture_core::nalgebra::{Matrix4, Point3, Vector3};
+
+/// Semi-major axis of the WGS84 ellipsoid
+pub const WGS84_SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+/// Flattening of the WGS84 ellipsoid
+pub const WGS84_FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+/// Squared first eccentricity of the WGS84 ellipsoid
+fn wgs84_eccentricity_squared() -> f64 {
+    WGS84_FLATTENING * (2.0 - WGS84_FLATTENING)
+}
+
+/// Converts the given earth-centered, earth-fixed position into longitude and latitude (both in radians) and height
+/// (in meters) on the WGS84 ellipsoid
+pub fn ecef_to_geodetic(position: &Point3<f64>) -> Vector3<f64> {
+    let e2 = wgs84_eccentricity_squared();
+    let longitude = position.y.atan2(position.x);
+    let p = (position.x * position.x + position.y * position.y).sqrt();
+    let mut latitude = position.z.atan2(p * (1.0 - e2));
+    let mut height = 0.0;
+    // The iteration converges to sub-millimeter precision after a few steps for points near the earth's surface
+    for _ in 0..5 {
+        let sin_latitude = latitude.sin();
+        let n = WGS84_SEMI_MAJOR_AXIS / (1.0 - e2 * sin_latitude * sin_latitude).sqrt();
+        height = if latitude.cos().abs() > 1e-10 {
+            p / latitude.cos() - n
+        } else {
+            position.z.abs() - n * (1.0 - e2)
+        };
+        latitude = position.z.atan2(p * (1.0 - e2 * n / (n + height)));
+    }
+    Vector3::new(longitude, latitude, height)
+}
+
+/// Converts the given longitude and latitude (both in radians) and height (in meters) on the WGS84 ellipsoid into an
+/// earth-centered, earth-fixed position. This is the inverse of `ecef_to_geodetic`
+pub fn geodetic_to_ecef(geodetic: &Vector3<f64>) -> Point3<f64> {
+    let e2 = wgs84_eccentricity_squared();
+    let (sin_longitude, cos_longitude) = geodetic.x.sin_cos();
+    let (sin_latitude, cos_latitude) = geodetic.y.sin_cos();
+    let n = WGS84_SEMI_MAJOR_AXIS / (1.0 - e2 * sin_latitude * sin_latitude).sqrt();
+    Point3::new(
+        (n + geodetic.z) * cos_latitude * cos_longitude,
+        (n + geodetic.z) * cos_latitude * sin_longitude,
+        (n * (1.0 - e2) + geodetic.z) * sin_latitude,
+    )
+}
+
+/// Returns the transformation from the local east-north-up coordinate system with its origin at the given longitude and
+/// latitude (both in radians) and height (in meters) on the WGS84 ellipsoid into earth-centered, earth-fixed coordinates
+pub fn east_north_up_to_ecef(origin: &Vector3<f64>) -> Matrix4<f64> {
+    let (sin_longitude, cos_longitude) = origin.x.sin_cos();
+    let (sin_latitude, cos_latitude) = origin.y.sin_cos();
+    let ecef_origin = geodetic_to_ecef(origin);
+    Matrix4::new(
+        -sin_longitude,
+        -sin_latitude * cos_longitude,
+        cos_latitude * cos_longitude,
+        ecef_origin.x,
+        cos_longitude,
+        -sin_latitude * sin_longitude,
+        cos_latitude * sin_longitude,
+        ecef_origin.y,
+        0.0,
+        cos_latitude,
+        sin_latitude,
+        ecef_origin.z,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ecef_to_geodetic() {
+        let north_pole = ecef_to_geodetic(&Point3::new(0.0, 0.0, 6_356_752.314_245));
+        assert!((north_pole.y - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert!(north_pole.z.abs() < 1e-3);
+
+        let equator = ecef_to_geodetic(&Point3::new(0.0, WGS84_SEMI_MAJOR_AXIS + 100.0, 0.0));
+        assert!((equator.x - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert!(equator.y.abs() < 1e-9);
+        assert!((equator.z - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_geodetic_to_ecef_roundtrip() {
+        let geodetic = Vector3::new(0.2, 0.9, 241.6);
+        let ecef = geodetic_to_ecef(&geodetic);
+        let geodetic_again = ecef_to_geodetic(&ecef);
+        assert!((geodetic - geodetic_again).amax() < 1e-6);
+    }
+
+    #[test]
+    fn test_east_north_up_to_ecef() {
+        let origin = Vector3::new(0.2, 0.9, 241.6);
+        let transform = east_north_up_to_ecef(&origin);
+        let ecef_origin = transform.transform_point(&Point3::origin());
+        assert!((ecef_origin - geodetic_to_ecef(&origin)).amax() < 1e-6);
+
+        // Moving up in the local coordinate system increases the height, moving north increases the latitude
+        let above = ecef_to_geodetic(&transform.transform_point(&Point3::new(0.0, 0.0, 10.0)));
+        assert!((above - (origin + Vector3::new(0.0, 0.0, 10.0))).amax() < 1e-6);
+        let north = ecef_to_geodetic(&transform.transform_point(&Point3::new(0.0, 100.0, 0.0)));
+        assert!(north.y > origin.y);
+        assert!((north.x - origin.x).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,910 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    convert::TryInto,
+    fmt::Display,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, BorrowedMutBuffer, HashMapBuffer, MakeBufferFromLayout, OwningBuffer,
+    },
+    layout::{
+        attributes::{COLOR_RGB, NORMAL, POSITION_3D},
+        conversion::BufferLayoutConverter,
+        FieldAlignment, PointAttributeDataType, PointAttributeDefinition, PointLayout,
+    },
+    math::AABB,
+    meta::Metadata,
+    nalgebra::{Matrix4, Point3, Quaternion, UnitQuaternion, Vector3},
+};
+
+use crate::base::PointReader;
+
+use super::{
+    attributes::COLOR_RGBA, component_types, datatype_from_gltf_accessor,
+    datatype_from_gltf_property_type, gltf_y_up_to_z_up, GlbChunkHeader, GlbHeader, Gltf, GltfNode,
+    GltfPrimitive, MetadataClass, GLTF_MODE_DEFAULT, GLTF_MODE_POINTS,
+};
+
+/// `Metadata` of a `GlbReader`
+#[derive(Clone, Debug)]
+pub struct GlbMetadata {
+    number_of_points: usize,
+    bounds: Option<AABB<f64>>,
+}
+
+impl Metadata for GlbMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        self.bounds
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        Some(self.number_of_points)
+    }
+
+    fn get_named_field(&self, _field_name: &str) -> Option<Box<dyn std::any::Any>> {
+        None
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
+
+impl Display for GlbMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "GlbMetadata {{")?;
+        writeln!(f, "\t\"number_of_points\": {}", self.number_of_points)?;
+        if let Some(bounds) = &self.bounds {
+            writeln!(f, "\t\"bounds\": {} - {}", bounds.min(), bounds.max())?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Reader for point clouds in the binary glTF format (.glb), as written by the `GlbWriter`.
+///
+/// All primitives with mode `POINTS` in the default scene are read. Positions are provided in the z-up coordinate system
+/// of 3D Tiles, i.e. with the transformations of all glTF nodes, the `CESIUM_RTC` extension and the rotation from y-up
+/// into z-up applied. For this reason, the `POSITION_3D` attribute uses `Vec3f64`. `COLOR_0` is provided as `COLOR_RGB`
+/// or `COLOR_RGBA` (depending on the number of components) and `NORMAL` as `NORMAL`.
+///
+/// Properties of the `EXT_structural_metadata` extension are provided as additional attributes, using the name of the
+/// property (or its ID, if it has no name) as attribute name. Both property tables (referenced through the feature IDs
+/// of the `EXT_mesh_features` extension) and property attributes are supported. Properties with types that pasture
+/// does not support (e.g. strings or arrays) are ignored.
+///
+/// The whole file is decoded when the `GlbReader` is created, as glTF files are usually small enough to fit into memory
+pub struct GlbReader {
+    gltf: Gltf,
+    points: HashMapBuffer,
+    metadata: GlbMetadata,
+    current_point_index: usize,
+}
+
+impl GlbReader {
+    /// Creates a new `GlbReader` that reads the .glb file from the given `read`
+    pub fn from_read<R: Read>(mut read: R) -> Result<Self> {
+        let mut bytes = vec![];
+        read.read_to_end(&mut bytes)
+            .context("Could not read GLB data")?;
+        let (gltf, bin) = parse_glb(&bytes)?;
+        let decoder = GltfPointDecoder {
+            gltf: &gltf,
+            bin: bin.unwrap_or(&[]),
+        };
+        let points = decoder.decode_points()?;
+
+        let bounds = points
+            .view_attribute::<Vector3<f64>>(
+                &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            )
+            .into_iter()
+            .map(Point3::from)
+            .fold(None, |bounds: Option<AABB<f64>>, position| match bounds {
+                Some(bounds) => Some(AABB::extend_with_point(&bounds, &position)),
+                None => Some(AABB::from_min_max_unchecked(position, position)),
+            });
+        let metadata = GlbMetadata {
+            number_of_points: points.len(),
+            bounds,
+        };
+        Ok(Self {
+            gltf,
+            points,
+            metadata,
+            current_point_index: 0,
+        })
+    }
+
+    /// Returns the glTF JSON structure of the .glb file
+    pub fn gltf(&self) -> &Gltf {
+        &self.gltf
+    }
+
+    /// Creates a new `GlbReader` that reads the .glb file at the given `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_read(reader)
+    }
+}
+
+impl PointReader for GlbReader {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let num_to_read = count.min(self.points.len() - self.current_point_index);
+        if num_to_read == 0 {
+            return Ok(0);
+        }
+        let target_layout = point_buffer.point_layout().clone();
+        let converter = BufferLayoutConverter::for_layouts_with_default(
+            self.points.point_layout(),
+            &target_layout,
+        );
+        converter.convert_into_range(
+            &self.points,
+            self.current_point_index..(self.current_point_index + num_to_read),
+            point_buffer,
+            0..num_to_read,
+        );
+        self.current_point_index += num_to_read;
+        Ok(num_to_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        &self.metadata
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        self.points.point_layout()
+    }
+}
+
+/// Splits the given .glb file into the glTF JSON structure and the binary chunk (if there is one)
+fn parse_glb(bytes: &[u8]) -> Result<(Gltf, Option<&[u8]>)> {
+    let header: GlbHeader = bincode::deserialize(
+        bytes
+            .get(..GlbHeader::BYTE_LENGTH)
+            .ok_or_else(|| anyhow!("GLB data is too small to contain a header"))?,
+    )
+    .context("Could not deserialize GLB header")?;
+    header.verify()?;
+    let length = (header.length as usize).min(bytes.len());
+
+    let mut gltf = None;
+    let mut bin = None;
+    let mut offset = GlbHeader::BYTE_LENGTH;
+    while offset + GlbChunkHeader::BYTE_LENGTH <= length {
+        let chunk_header: GlbChunkHeader =
+            bincode::deserialize(&bytes[offset..offset + GlbChunkHeader::BYTE_LENGTH])
+                .context("Could not deserialize GLB chunk header")?;
+        let chunk_start = offset + GlbChunkHeader::BYTE_LENGTH;
+        let chunk_end = chunk_start + chunk_header.chunk_length as usize;
+        let chunk = bytes
+            .get(chunk_start..chunk_end)
+            .ok_or_else(|| anyhow!("GLB chunk exceeds the size of the GLB data"))?;
+        match chunk_header.chunk_type {
+            GlbChunkHeader::JSON if gltf.is_none() => {
+                gltf = Some(
+                    serde_json::from_slice::<Gltf>(chunk)
+                        .context("Could not parse glTF JSON chunk")?,
+                );
+            }
+            GlbChunkHeader::BIN if bin.is_none() => bin = Some(chunk),
+            // Unknown chunks must be ignored as per the glTF specification
+            _ => (),
+        }
+        offset = chunk_end;
+    }
+
+    let gltf = gltf.ok_or_else(|| anyhow!("GLB data contains no JSON chunk"))?;
+    if gltf.buffers.iter().any(|buffer| buffer.uri.is_some()) {
+        bail!("glTF buffers with URIs are not supported, only the binary chunk of the GLB file can be used");
+    }
+    Ok((gltf, bin))
+}
+
+/// Returns the local transformation of the given glTF `node`
+fn node_transform(node: &GltfNode) -> Matrix4<f64> {
+    if let Some(matrix) = &node.matrix {
+        return Matrix4::from_column_slice(matrix);
+    }
+    let translation = node
+        .translation
+        .map(|t| Matrix4::new_translation(&Vector3::new(t[0], t[1], t[2])))
+        .unwrap_or_else(Matrix4::identity);
+    let rotation = node
+        .rotation
+        .map(|r| {
+            UnitQuaternion::from_quaternion(Quaternion::new(r[3], r[0], r[1], r[2]))
+                .to_homogeneous()
+        })
+        .unwrap_or_else(Matrix4::identity);
+    let scale = node
+        .scale
+        .map(|s| Matrix4::new_nonuniform_scaling(&Vector3::new(s[0], s[1], s[2])))
+        .unwrap_or_else(Matrix4::identity);
+    translation * rotation * scale
+}
+
+/// Size in bytes of a single component with the given glTF `component_type`
+fn component_size(component_type: u32) -> usize {
+    match component_type {
+        component_types::BYTE | component_types::UNSIGNED_BYTE => 1,
+        component_types::SHORT | component_types::UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+/// Reads a single component with the given glTF `component_type` as `f64`. Normalized integer components are mapped to
+/// `[0;1]` (unsigned) or `[-1;1]` (signed) as per the glTF specification
+fn read_component(component_type: u32, bytes: &[u8], normalized: bool) -> f64 {
+    let (value, max) = match component_type {
+        component_types::BYTE => (bytes[0] as i8 as f64, i8::MAX as f64),
+        component_types::UNSIGNED_BYTE => (bytes[0] as f64, u8::MAX as f64),
+        component_types::SHORT => (
+            i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            i16::MAX as f64,
+        ),
+        component_types::UNSIGNED_SHORT => (
+            u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            u16::MAX as f64,
+        ),
+        component_types::UNSIGNED_INT => (
+            u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            u32::MAX as f64,
+        ),
+        _ => (
+            f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            1.0,
+        ),
+    };
+    if normalized && component_type != component_types::FLOAT {
+        (value / max).max(-1.0)
+    } else {
+        value
+    }
+}
+
+/// Number of components of a glTF accessor with the given `type`
+fn number_of_components(accessor_type: &str) -> Result<usize> {
+    match accessor_type {
+        "SCALAR" => Ok(1),
+        "VEC2" => Ok(2),
+        "VEC3" => Ok(3),
+        "VEC4" => Ok(4),
+        other => bail!("Unsupported glTF accessor type {}", other),
+    }
+}
+
+/// The attributes of a single glTF primitive, each stored as tightly packed values
+type DecodedAttributes = Vec<(PointAttributeDefinition, Vec<u8>)>;
+
+/// Decodes the point primitives of a glTF file
+struct GltfPointDecoder<'a> {
+    gltf: &'a Gltf,
+    bin: &'a [u8],
+}
+
+impl<'a> GltfPointDecoder<'a> {
+    /// Decodes all point primitives in the default scene into a single buffer. The `PointLayout` of the buffer contains
+    /// the attributes of all point primitives, points of primitives that lack some attribute get default values for it
+    fn decode_points(&self) -> Result<HashMapBuffer> {
+        let mut primitives = vec![];
+        let scene_index = self.gltf.scene.unwrap_or(0);
+        let root_nodes = match self.gltf.scenes.get(scene_index) {
+            Some(scene) => scene.nodes.clone(),
+            None => {
+                // Without scenes, all nodes that are not children of other nodes are roots
+                let child_nodes = self
+                    .gltf
+                    .nodes
+                    .iter()
+                    .flat_map(|node| node.children.iter().copied())
+                    .collect::<HashSet<_>>();
+                (0..self.gltf.nodes.len())
+                    .filter(|node_index| !child_nodes.contains(node_index))
+                    .collect()
+            }
+        };
+        for node_index in root_nodes {
+            self.decode_node(node_index, &gltf_y_up_to_z_up(), 0, &mut primitives)?;
+        }
+
+        let mut point_layout = PointLayout::default();
+        for (attributes, _) in &primitives {
+            for (attribute, _) in attributes.iter() {
+                if !point_layout.has_attribute_with_name(attribute.name()) {
+                    point_layout.add_attribute(attribute.clone(), FieldAlignment::Default);
+                }
+            }
+        }
+        if !point_layout.has_attribute_with_name(POSITION_3D.name()) {
+            point_layout.add_attribute(
+                POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+                FieldAlignment::Default,
+            );
+        }
+
+        let mut points = HashMapBuffer::new_from_layout(point_layout.clone());
+        for (attributes, count) in primitives {
+            let primitive_layout = PointLayout::from_attributes(
+                &attributes
+                    .iter()
+                    .map(|(attribute, _)| attribute.clone())
+                    .collect::<Vec<_>>(),
+            );
+            let mut primitive_points = HashMapBuffer::with_capacity(count, primitive_layout);
+            primitive_points.resize(count);
+            for (attribute, data) in &attributes {
+                // Safe because `data` contains `count` tightly packed values of `attribute`
+                unsafe {
+                    primitive_points.set_attribute_range(attribute, 0..count, data);
+                }
+            }
+            let first_point = points.len();
+            points.resize(first_point + count);
+            BufferLayoutConverter::for_layouts_with_default(
+                primitive_points.point_layout(),
+                &point_layout,
+            )
+            .convert_into_range(
+                &primitive_points,
+                0..count,
+                &mut points,
+                first_point..(first_point + count),
+            );
+        }
+        Ok(points)
+    }
+
+    fn decode_node(
+        &self,
+        node_index: usize,
+        parent_transform: &Matrix4<f64>,
+        depth: usize,
+        primitives: &mut Vec<(DecodedAttributes, usize)>,
+    ) -> Result<()> {
+        let node = self
+            .gltf
+            .nodes
+            .get(node_index)
+            .ok_or_else(|| anyhow!("Invalid glTF node index {}", node_index))?;
+        // glTF requires the node hierarchy to be a set of disjoint trees, this guards against invalid files with cycles
+        if depth > self.gltf.nodes.len() {
+            bail!("glTF node hierarchy contains a cycle");
+        }
+        let transform = parent_transform * node_transform(node);
+        if let Some(mesh_index) = node.mesh {
+            let mesh = self
+                .gltf
+                .meshes
+                .get(mesh_index)
+                .ok_or_else(|| anyhow!("Invalid glTF mesh index {}", mesh_index))?;
+            for primitive in &mesh.primitives {
+                if primitive.mode.unwrap_or(GLTF_MODE_DEFAULT) == GLTF_MODE_POINTS {
+                    primitives.push(self.decode_primitive(primitive, &transform)?);
+                }
+            }
+        }
+        for child_index in &node.children {
+            self.decode_node(*child_index, &transform, depth + 1, primitives)?;
+        }
+        Ok(())
+    }
+
+    fn decode_primitive(
+        &self,
+        primitive: &GltfPrimitive,
+        transform: &Matrix4<f64>,
+    ) -> Result<(DecodedAttributes, usize)> {
+        let position_accessor = *primitive
+            .attributes
+            .get("POSITION")
+            .ok_or_else(|| anyhow!("glTF point primitive has no POSITION attribute"))?;
+        let (positions, count) = self.read_accessor_components(position_accessor)?;
+        if positions.len() != count * 3 {
+            bail!("POSITION attribute of glTF primitive must be of type VEC3");
+        }
+        let rtc_center = self
+            .gltf
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.cesium_rtc.as_ref())
+            .map(|rtc| Vector3::new(rtc.center[0], rtc.center[1], rtc.center[2]))
+            .unwrap_or_else(Vector3::zeros);
+        let world_positions = positions
+            .chunks_exact(3)
+            .flat_map(|position| {
+                let world_position =
+                    transform.transform_point(&Point3::new(position[0], position[1], position[2]))
+                        + rtc_center;
+                bytemuck::cast::<_, [u8; 24]>(world_position.coords)
+            })
+            .collect::<Vec<_>>();
+        let mut attributes = vec![(
+            POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            world_positions,
+        )];
+
+        if let Some(color_accessor) = primitive.attributes.get("COLOR_0") {
+            let (colors, _) = self.read_accessor_components(*color_accessor)?;
+            let num_components = colors.len() / count.max(1);
+            let colors_u8 = colors
+                .iter()
+                .map(|component| (component.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect::<Vec<_>>();
+            match num_components {
+                3 => attributes.push((
+                    COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+                    colors_u8,
+                )),
+                4 => attributes.push((COLOR_RGBA, colors_u8)),
+                other => bail!(
+                    "COLOR_0 attribute with {} components is not supported",
+                    other
+                ),
+            }
+        }
+
+        if let Some(normal_accessor) = primitive.attributes.get("NORMAL") {
+            let (normals, _) = self.read_accessor_components(*normal_accessor)?;
+            let normal_matrix = transform
+                .fixed_view::<3, 3>(0, 0)
+                .try_inverse()
+                .ok_or_else(|| anyhow!("glTF node transformation is not invertible"))?
+                .transpose();
+            let world_normals = normals
+                .chunks_exact(3)
+                .flat_map(|normal| {
+                    let world_normal = (normal_matrix
+                        * Vector3::new(normal[0], normal[1], normal[2]))
+                    .normalize()
+                    .cast::<f32>();
+                    bytemuck::cast::<_, [u8; 12]>(world_normal)
+                })
+                .collect::<Vec<_>>();
+            attributes.push((NORMAL, world_normals));
+        }
+
+        attributes.extend(self.decode_property_tables(primitive, count)?);
+        attributes.extend(self.decode_property_attributes(primitive)?);
+        for (attribute, data) in &attributes {
+            if data.len() != count * attribute.size() as usize {
+                bail!(
+                    "Attribute {} of glTF primitive does not have one value per point",
+                    attribute.name()
+                );
+            }
+        }
+        Ok((attributes, count))
+    }
+
+    /// Decodes the properties of all property tables that are referenced through feature IDs of the given `primitive`
+    fn decode_property_tables(
+        &self,
+        primitive: &GltfPrimitive,
+        count: usize,
+    ) -> Result<DecodedAttributes> {
+        let mut attributes = vec![];
+        let (structural_metadata, feature_ids) = match (
+            self.structural_metadata_class_lookup(),
+            primitive
+                .extensions
+                .as_ref()
+                .and_then(|extensions| extensions.mesh_features.as_ref()),
+        ) {
+            (Some(structural_metadata), Some(mesh_features)) => {
+                (structural_metadata, &mesh_features.feature_ids)
+            }
+            _ => return Ok(attributes),
+        };
+
+        for feature_id in feature_ids {
+            let property_table = match feature_id.property_table.and_then(|property_table| {
+                structural_metadata.0.property_tables.get(property_table)
+            }) {
+                Some(property_table) => property_table,
+                None => continue,
+            };
+            if feature_id.texture.is_some() {
+                continue;
+            }
+            let class = match structural_metadata.1.get(&property_table.class) {
+                Some(class) => class,
+                None => continue,
+            };
+            let point_feature_ids = match feature_id.attribute {
+                Some(set_index) => {
+                    let accessor = primitive
+                        .attributes
+                        .get(&format!("_FEATURE_ID_{}", set_index))
+                        .ok_or_else(|| {
+                            anyhow!("glTF primitive has no _FEATURE_ID_{} attribute", set_index)
+                        })?;
+                    self.read_accessor_components(*accessor)?
+                        .0
+                        .into_iter()
+                        .map(|id| id as u64)
+                        .collect::<Vec<_>>()
+                }
+                None => (0..count as u64).collect(),
+            };
+
+            for (property_id, property) in &property_table.properties {
+                let (attribute, value_size) = match self.property_attribute(class, property_id) {
+                    Some(attribute) => attribute,
+                    None => continue,
+                };
+                let values = self.buffer_view_data(property.values)?;
+                let mut data = vec![0; count * value_size];
+                for (point_index, feature_id_of_point) in point_feature_ids.iter().enumerate() {
+                    let value_index = *feature_id_of_point as usize;
+                    if Some(*feature_id_of_point) == feature_id.null_feature_id
+                        || value_index >= property_table.count
+                    {
+                        continue;
+                    }
+                    let value = values
+                        .get(value_index * value_size..(value_index + 1) * value_size)
+                        .ok_or_else(|| {
+                            anyhow!("Values of property {} are out of bounds", property_id)
+                        })?;
+                    data[point_index * value_size..(point_index + 1) * value_size]
+                        .copy_from_slice(value);
+                }
+                attributes.push((attribute, data));
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// Decodes the properties of all property attributes of the given `primitive`
+    fn decode_property_attributes(&self, primitive: &GltfPrimitive) -> Result<DecodedAttributes> {
+        let mut attributes = vec![];
+        let (structural_metadata, property_attribute_indices) = match (
+            self.structural_metadata_class_lookup(),
+            primitive
+                .extensions
+                .as_ref()
+                .and_then(|extensions| extensions.structural_metadata.as_ref()),
+        ) {
+            (Some(structural_metadata), Some(primitive_metadata)) => {
+                (structural_metadata, &primitive_metadata.property_attributes)
+            }
+            _ => return Ok(attributes),
+        };
+
+        for property_attribute in property_attribute_indices
+            .iter()
+            .filter_map(|index| structural_metadata.0.property_attributes.get(*index))
+        {
+            let class = match structural_metadata.1.get(&property_attribute.class) {
+                Some(class) => class,
+                None => continue,
+            };
+            for (property_id, property) in &property_attribute.properties {
+                let (attribute, _) = match self.property_attribute(class, property_id) {
+                    Some(attribute) => attribute,
+                    None => continue,
+                };
+                let accessor = match primitive.attributes.get(&property.attribute) {
+                    Some(accessor) => *accessor,
+                    None => continue,
+                };
+                let (datatype, data) = self.read_accessor(accessor)?;
+                if datatype == attribute.datatype() {
+                    attributes.push((attribute, data));
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// Returns the top-level `EXT_structural_metadata` extension together with the classes of its schema
+    fn structural_metadata_class_lookup(
+        &self,
+    ) -> Option<(
+        &'a super::StructuralMetadata,
+        &'a std::collections::BTreeMap<String, MetadataClass>,
+    )> {
+        let structural_metadata = self
+            .gltf
+            .extensions
+            .as_ref()?
+            .structural_metadata
+            .as_ref()?;
+        let schema = structural_metadata.schema.as_ref()?;
+        Some((structural_metadata, &schema.classes))
+    }
+
+    /// Returns the attribute definition for the property with the given `property_id` in `class`, together with the
+    /// size of a single value. Returns `None` if pasture does not support the type of the property
+    fn property_attribute(
+        &self,
+        class: &MetadataClass,
+        property_id: &str,
+    ) -> Option<(PointAttributeDefinition, usize)> {
+        let class_property = class.properties.get(property_id)?;
+        if class_property.array {
+            return None;
+        }
+        let datatype = datatype_from_gltf_property_type(
+            &class_property.property_type,
+            class_property.component_type.as_deref(),
+        )?;
+        let name = class_property
+            .name
+            .clone()
+            .unwrap_or_else(|| property_id.to_owned());
+        Some((
+            PointAttributeDefinition::custom(Cow::Owned(name), datatype),
+            datatype.size() as usize,
+        ))
+    }
+
+    /// Returns the data of the buffer view with the given index
+    fn buffer_view_data(&self, buffer_view_index: usize) -> Result<&'a [u8]> {
+        let buffer_view = self
+            .gltf
+            .buffer_views
+            .get(buffer_view_index)
+            .ok_or_else(|| anyhow!("Invalid glTF buffer view index {}", buffer_view_index))?;
+        if buffer_view.buffer != 0 {
+            bail!("Only the binary chunk of the GLB file is supported as glTF buffer");
+        }
+        self.bin
+            .get(buffer_view.byte_offset..buffer_view.byte_offset + buffer_view.byte_length)
+            .ok_or_else(|| anyhow!("glTF buffer view {} is out of bounds", buffer_view_index))
+    }
+
+    /// Returns the raw bytes of all elements of the accessor with the given index, without any padding between the
+    /// elements, together with the datatype of the elements
+    fn read_accessor(&self, accessor_index: usize) -> Result<(PointAttributeDataType, Vec<u8>)> {
+        let accessor = self
+            .gltf
+            .accessors
+            .get(accessor_index)
+            .ok_or_else(|| anyhow!("Invalid glTF accessor index {}", accessor_index))?;
+        let datatype =
+            datatype_from_gltf_accessor(accessor.component_type, &accessor.accessor_type)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unsupported glTF accessor with type {} and componentType {}",
+                        accessor.accessor_type,
+                        accessor.component_type
+                    )
+                })?;
+        let element_size = datatype.size() as usize;
+        Ok((
+            datatype,
+            self.read_accessor_elements(accessor_index, element_size)?,
+        ))
+    }
+
+    /// Reads all components of all elements of the accessor with the given index as `f64` values. Returns the components
+    /// together with the number of elements
+    fn read_accessor_components(&self, accessor_index: usize) -> Result<(Vec<f64>, usize)> {
+        let accessor = self
+            .gltf
+            .accessors
+            .get(accessor_index)
+            .ok_or_else(|| anyhow!("Invalid glTF accessor index {}", accessor_index))?;
+        let component_size = component_size(accessor.component_type);
+        let element_size = component_size * number_of_components(&accessor.accessor_type)?;
+        let components = self
+            .read_accessor_elements(accessor_index, element_size)?
+            .chunks_exact(component_size)
+            .map(|component| {
+                read_component(accessor.component_type, component, accessor.normalized)
+            })
+            .collect();
+        Ok((components, accessor.count))
+    }
+
+    /// Returns the raw bytes of all elements of the accessor with the given index, where each element has `element_size`
+    /// bytes. Takes the byte stride of the buffer view into account
+    fn read_accessor_elements(
+        &self,
+        accessor_index: usize,
+        element_size: usize,
+    ) -> Result<Vec<u8>> {
+        let accessor = &self.gltf.accessors[accessor_index];
+        let buffer_view_index = match accessor.buffer_view {
+            Some(buffer_view_index) => buffer_view_index,
+            // Accessors without buffer view are initialized with zeros
+            None => return Ok(vec![0; accessor.count * element_size]),
+        };
+        let data = self.buffer_view_data(buffer_view_index)?;
+        let stride = self.gltf.buffer_views[buffer_view_index]
+            .byte_stride
+            .unwrap_or(element_size);
+        (0..accessor.count)
+            .map(|element_index| {
+                let start = accessor.byte_offset + element_index * stride;
+                data.get(start..start + element_size).ok_or_else(|| {
+                    anyhow!(
+                        "Elements of glTF accessor {} are out of bounds",
+                        accessor_index
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|elements| elements.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pasture_core::{containers::VectorBuffer, layout::PointType};
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::{base::PointWriter, tiles3d::GlbWriter};
+
+    #[derive(
+        Copy, Clone, PartialEq, Debug, PointType, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    #[repr(C, packed)]
+    struct TestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_COLOR_RGB)]
+        color: Vector3<u16>,
+        #[pasture(BUILTIN_NORMAL)]
+        normal: Vector3<f32>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+        #[pasture(BUILTIN_RETURN_NUMBER)]
+        return_number: u8,
+        #[pasture(attribute = "Point deviation")]
+        deviation: f64,
+    }
+
+    fn test_points() -> Vec<TestPoint> {
+        (0..100)
+            .map(|index| TestPoint {
+                position: Vector3::new(1000.0 + index as f64, -(index as f64), 0.5 * index as f64),
+                color: Vector3::new(index as u16, 2 * index as u16, 255 - index as u16),
+                normal: Vector3::new(0.0, (index as f32).sin(), (index as f32).cos()),
+                intensity: 10 * index as u16,
+                return_number: (index % 4) as u8,
+                deviation: index as f64 * 0.25,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_glb_roundtrip() -> Result<()> {
+        let expected_points = test_points();
+        let mut glb = vec![];
+        {
+            let mut writer =
+                GlbWriter::from_write_and_layout(Cursor::new(&mut glb), TestPoint::layout());
+            writer.set_rtc_center(Vector3::new(1000.0, 0.0, 0.0));
+            let relative_points = expected_points
+                .iter()
+                .map(|point| TestPoint {
+                    position: point.position - Vector3::new(1000.0, 0.0, 0.0),
+                    ..*point
+                })
+                .collect::<VectorBuffer>();
+            writer.write(&relative_points)?;
+        }
+        assert_eq!(b"glTF", &glb[..4]);
+        assert_eq!(glb.len() as u32, u32::from_le_bytes(glb[8..12].try_into()?));
+
+        let mut reader = GlbReader::from_read(Cursor::new(&glb))?;
+        let gltf = reader.gltf();
+        assert_eq!(
+            vec![
+                "EXT_mesh_features".to_owned(),
+                "EXT_structural_metadata".to_owned()
+            ],
+            gltf.extensions_used
+        );
+        let primitive = &gltf.meshes[0].primitives[0];
+        assert_eq!(Some(GLTF_MODE_POINTS), primitive.mode);
+        assert_eq!(
+            vec!["COLOR_0", "NORMAL", "POSITION"],
+            primitive.attributes.keys().collect::<Vec<_>>()
+        );
+        let class = &gltf
+            .extensions
+            .as_ref()
+            .unwrap()
+            .structural_metadata
+            .as_ref()
+            .unwrap()
+            .schema
+            .as_ref()
+            .unwrap()
+            .classes["point"];
+        assert_eq!(
+            Some("Point deviation"),
+            class.properties["Point_deviation"].name.as_deref()
+        );
+
+        assert_eq!(Some(100), reader.get_metadata().number_of_points());
+        let points = reader.read::<VectorBuffer>(100)?;
+        let layout = points.point_layout().clone();
+        for attribute in [
+            "Intensity",
+            "ReturnNumber",
+            "Point deviation",
+            "ColorRGB",
+            "Normal",
+        ] {
+            assert!(layout.has_attribute_with_name(attribute), "{}", attribute);
+        }
+
+        let positions = points
+            .view_attribute::<Vector3<f64>>(
+                &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        let colors = points
+            .view_attribute::<Vector3<u8>>(
+                &COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        let normals = points
+            .view_attribute::<Vector3<f32>>(&NORMAL)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let intensities = points
+            .view_attribute::<u16>(&pasture_core::layout::attributes::INTENSITY)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let deviations = points
+            .view_attribute::<f64>(&PointAttributeDefinition::custom(
+                Cow::Borrowed("Point deviation"),
+                PointAttributeDataType::F64,
+            ))
+            .into_iter()
+            .collect::<Vec<_>>();
+        for (index, expected) in expected_points.iter().enumerate() {
+            let expected_position = expected.position;
+            let expected_normal = expected.normal;
+            let expected_color = expected.color;
+            let expected_intensity = expected.intensity;
+            let expected_deviation = expected.deviation;
+            assert!((expected_position - positions[index]).amax() < 1e-4);
+            assert!((expected_normal - normals[index]).amax() < 1e-5);
+            assert_eq!(expected_color.map(|c| c as u8), colors[index]);
+            assert_eq!(expected_intensity, intensities[index]);
+            assert_eq!(expected_deviation, deviations[index]);
+        }
+
+        assert_eq!(0, reader.read_into(&mut points.clone(), 1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_glb_empty() -> Result<()> {
+        let mut glb = vec![];
+        {
+            let mut writer =
+                GlbWriter::from_write_and_layout(Cursor::new(&mut glb), TestPoint::layout());
+            writer.flush()?;
+        }
+        let reader = GlbReader::from_read(Cursor::new(&glb))?;
+        assert_eq!(Some(0), reader.get_metadata().number_of_points());
+        Ok(())
+    }
+}
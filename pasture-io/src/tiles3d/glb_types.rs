@@ -0,0 +1,459 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use pasture_core::{layout::PointAttributeDataType, nalgebra::Matrix4};
+use serde::{Deserialize, Serialize};
+use static_assertions::const_assert;
+
+/// Header of binary glTF (.glb) files
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GlbHeader {
+    pub magic: [u8; 4],
+    pub version: u32,
+    pub length: u32,
+}
+
+impl GlbHeader {
+    /// Length of a .glb header in bytes
+    pub const BYTE_LENGTH: usize = 12;
+    /// The glTF version that pasture reads and writes
+    pub const VERSION: u32 = 2;
+
+    pub fn new(length: u32) -> Self {
+        Self {
+            magic: *b"glTF",
+            version: Self::VERSION,
+            length,
+        }
+    }
+
+    /// Returns an Err if the magic bytes or the version in this header are not correct
+    pub fn verify(&self) -> Result<()> {
+        if self.magic != *b"glTF" {
+            bail!("No valid GLB file, expected first four bytes to be equal to 'glTF', but was '{:?}' instead", self.magic);
+        }
+        if self.version != Self::VERSION {
+            bail!(
+                "Unsupported glTF version {}, only version {} is supported",
+                self.version,
+                Self::VERSION
+            );
+        }
+        Ok(())
+    }
+}
+
+const_assert!(GlbHeader::BYTE_LENGTH == std::mem::size_of::<GlbHeader>());
+
+/// Header of a single chunk within a .glb file
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GlbChunkHeader {
+    pub chunk_length: u32,
+    pub chunk_type: u32,
+}
+
+impl GlbChunkHeader {
+    /// Length of a chunk header in bytes
+    pub const BYTE_LENGTH: usize = 8;
+    /// Chunk type of the JSON chunk ('JSON' in ASCII)
+    pub const JSON: u32 = 0x4E4F534A;
+    /// Chunk type of the binary chunk ('BIN\0' in ASCII)
+    pub const BIN: u32 = 0x004E4942;
+}
+
+const_assert!(GlbChunkHeader::BYTE_LENGTH == std::mem::size_of::<GlbChunkHeader>());
+
+/// glTF accessor component types, as defined by the glTF specification
+pub mod component_types {
+    pub const BYTE: u32 = 5120;
+    pub const UNSIGNED_BYTE: u32 = 5121;
+    pub const SHORT: u32 = 5122;
+    pub const UNSIGNED_SHORT: u32 = 5123;
+    pub const UNSIGNED_INT: u32 = 5125;
+    pub const FLOAT: u32 = 5126;
+}
+
+/// glTF primitive mode for point primitives
+pub const GLTF_MODE_POINTS: u32 = 0;
+/// glTF primitive mode that is used if a primitive does not define a mode
+pub const GLTF_MODE_DEFAULT: u32 = 4;
+/// glTF buffer view target for vertex attributes
+pub const GLTF_TARGET_ARRAY_BUFFER: u32 = 34962;
+
+/// Returns the transformation from the y-up coordinate system of glTF into the z-up coordinate system of 3D Tiles, which
+/// is a rotation of 90 degrees around the x-axis. 3D Tiles applies this transformation to all glTF content
+pub fn gltf_y_up_to_z_up() -> Matrix4<f64> {
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0, //
+        0.0, 0.0, -1.0, 0.0, //
+        0.0, 1.0, 0.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+/// The subset of the glTF 2.0 JSON structure that pasture uses for point clouds. Unknown properties are ignored
+/// during deserialization
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Gltf {
+    pub asset: GltfAsset,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenes: Vec<GltfScene>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<GltfNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meshes: Vec<GltfMesh>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accessors: Vec<GltfAccessor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffer_views: Vec<GltfBufferView>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffers: Vec<GltfBuffer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions_used: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<GltfExtensions>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GltfAsset {
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+}
+
+impl Default for GltfAsset {
+    fn default() -> Self {
+        Self {
+            version: "2.0".into(),
+            generator: Some("pasture".into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GltfScene {
+    #[serde(default)]
+    pub nodes: Vec<usize>,
+}
+
+/// A node in the glTF scene graph. The transformation of a node is either given as a column-major `matrix`, or
+/// through `translation`, `rotation` (as quaternion `[x, y, z, w]`) and `scale`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GltfNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<[f64; 16]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f64; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f64; 3]>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GltfMesh {
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GltfPrimitive {
+    pub attributes: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<GltfPrimitiveExtensions>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GltfPrimitiveExtensions {
+    #[serde(rename = "EXT_mesh_features", skip_serializing_if = "Option::is_none")]
+    pub mesh_features: Option<MeshFeatures>,
+    #[serde(
+        rename = "EXT_structural_metadata",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub structural_metadata: Option<PrimitiveStructuralMetadata>,
+}
+
+/// The `EXT_mesh_features` extension of a glTF primitive
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshFeatures {
+    pub feature_ids: Vec<FeatureId>,
+}
+
+/// A set of feature IDs. If neither `attribute` nor `texture` are given, the feature ID of each vertex is its index
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureId {
+    pub feature_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null_feature_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property_table: Option<usize>,
+}
+
+/// The `EXT_structural_metadata` extension of a glTF primitive
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimitiveStructuralMetadata {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub property_attributes: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfAccessor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buffer_view: Option<usize>,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub component_type: u32,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub normalized: bool,
+    pub count: usize,
+    #[serde(rename = "type")]
+    pub accessor_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Vec<f64>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfBufferView {
+    pub buffer: usize,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_stride: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfBuffer {
+    pub byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+/// Top-level extensions of a glTF file that pasture supports
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GltfExtensions {
+    #[serde(
+        rename = "EXT_structural_metadata",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub structural_metadata: Option<StructuralMetadata>,
+    #[serde(rename = "CESIUM_RTC", skip_serializing_if = "Option::is_none")]
+    pub cesium_rtc: Option<CesiumRtc>,
+}
+
+/// The top-level `EXT_structural_metadata` extension. Only embedded schemas are supported
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuralMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<MetadataSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub property_tables: Vec<PropertyTable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub property_attributes: Vec<PropertyAttribute>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetadataSchema {
+    pub id: String,
+    #[serde(default)]
+    pub classes: BTreeMap<String, MetadataClass>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetadataClass {
+    #[serde(default)]
+    pub properties: BTreeMap<String, MetadataClassProperty>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataClassProperty {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub property_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component_type: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub array: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PropertyTable {
+    pub class: String,
+    pub count: usize,
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyTableProperty>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PropertyTableProperty {
+    pub values: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PropertyAttribute {
+    pub class: String,
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyAttributeProperty>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PropertyAttributeProperty {
+    pub attribute: String,
+}
+
+/// The `CESIUM_RTC` extension, which defines a center that all positions are relative to
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct CesiumRtc {
+    pub center: [f64; 3],
+}
+
+/// Returns the datatype in which values of the given `datatype` are stored inside an `EXT_structural_metadata` property
+/// table, together with the matching `type` and `componentType` values. Returns `None` if values of `datatype` can't be
+/// stored inside a property table
+pub fn gltf_property_type_from_datatype(
+    datatype: PointAttributeDataType,
+) -> Option<(PointAttributeDataType, &'static str, &'static str)> {
+    match datatype {
+        PointAttributeDataType::I8 => Some((datatype, "SCALAR", "INT8")),
+        PointAttributeDataType::U8 => Some((datatype, "SCALAR", "UINT8")),
+        PointAttributeDataType::I16 => Some((datatype, "SCALAR", "INT16")),
+        PointAttributeDataType::U16 => Some((datatype, "SCALAR", "UINT16")),
+        PointAttributeDataType::I32 => Some((datatype, "SCALAR", "INT32")),
+        PointAttributeDataType::U32 => Some((datatype, "SCALAR", "UINT32")),
+        PointAttributeDataType::I64 => Some((datatype, "SCALAR", "INT64")),
+        PointAttributeDataType::U64 => Some((datatype, "SCALAR", "UINT64")),
+        PointAttributeDataType::F32 => Some((datatype, "SCALAR", "FLOAT32")),
+        PointAttributeDataType::F64 => Some((datatype, "SCALAR", "FLOAT64")),
+        PointAttributeDataType::Vec3u8 => Some((datatype, "VEC3", "UINT8")),
+        PointAttributeDataType::Vec3u16 => Some((datatype, "VEC3", "UINT16")),
+        PointAttributeDataType::Vec3i32 => Some((datatype, "VEC3", "INT32")),
+        PointAttributeDataType::Vec3f32 => Some((datatype, "VEC3", "FLOAT32")),
+        PointAttributeDataType::Vec3f64 => Some((datatype, "VEC3", "FLOAT64")),
+        PointAttributeDataType::Vec4u8 => Some((datatype, "VEC4", "UINT8")),
+        _ => None,
+    }
+}
+
+/// Returns the datatype for values of an `EXT_structural_metadata` property with the given `type` and `componentType`.
+/// This is the inverse of `gltf_property_type_from_datatype`. Returns `None` for types that pasture does not support
+pub fn datatype_from_gltf_property_type(
+    property_type: &str,
+    component_type: Option<&str>,
+) -> Option<PointAttributeDataType> {
+    match (property_type, component_type?) {
+        ("SCALAR", "INT8") => Some(PointAttributeDataType::I8),
+        ("SCALAR", "UINT8") => Some(PointAttributeDataType::U8),
+        ("SCALAR", "INT16") => Some(PointAttributeDataType::I16),
+        ("SCALAR", "UINT16") => Some(PointAttributeDataType::U16),
+        ("SCALAR", "INT32") => Some(PointAttributeDataType::I32),
+        ("SCALAR", "UINT32") => Some(PointAttributeDataType::U32),
+        ("SCALAR", "INT64") => Some(PointAttributeDataType::I64),
+        ("SCALAR", "UINT64") => Some(PointAttributeDataType::U64),
+        ("SCALAR", "FLOAT32") => Some(PointAttributeDataType::F32),
+        ("SCALAR", "FLOAT64") => Some(PointAttributeDataType::F64),
+        ("VEC3", "UINT8") => Some(PointAttributeDataType::Vec3u8),
+        ("VEC3", "UINT16") => Some(PointAttributeDataType::Vec3u16),
+        ("VEC3", "INT32") => Some(PointAttributeDataType::Vec3i32),
+        ("VEC3", "FLOAT32") => Some(PointAttributeDataType::Vec3f32),
+        ("VEC3", "FLOAT64") => Some(PointAttributeDataType::Vec3f64),
+        ("VEC4", "UINT8") => Some(PointAttributeDataType::Vec4u8),
+        _ => None,
+    }
+}
+
+/// Returns the datatype for the elements of a glTF accessor with the given `componentType` and `type`. Returns `None`
+/// for accessor types that have no pasture equivalent
+pub fn datatype_from_gltf_accessor(
+    component_type: u32,
+    accessor_type: &str,
+) -> Option<PointAttributeDataType> {
+    match (accessor_type, component_type) {
+        ("SCALAR", component_types::BYTE) => Some(PointAttributeDataType::I8),
+        ("SCALAR", component_types::UNSIGNED_BYTE) => Some(PointAttributeDataType::U8),
+        ("SCALAR", component_types::SHORT) => Some(PointAttributeDataType::I16),
+        ("SCALAR", component_types::UNSIGNED_SHORT) => Some(PointAttributeDataType::U16),
+        ("SCALAR", component_types::UNSIGNED_INT) => Some(PointAttributeDataType::U32),
+        ("SCALAR", component_types::FLOAT) => Some(PointAttributeDataType::F32),
+        ("VEC3", component_types::UNSIGNED_BYTE) => Some(PointAttributeDataType::Vec3u8),
+        ("VEC3", component_types::UNSIGNED_SHORT) => Some(PointAttributeDataType::Vec3u16),
+        ("VEC3", component_types::FLOAT) => Some(PointAttributeDataType::Vec3f32),
+        ("VEC4", component_types::UNSIGNED_BYTE) => Some(PointAttributeDataType::Vec4u8),
+        _ => None,
+    }
+}
+
+/// Turns the given attribute name into a valid identifier for an `EXT_structural_metadata` property, i.e. an identifier
+/// that only contains alphanumeric characters and underscores and does not start with a digit
+pub fn gltf_property_id_from_name(name: &str) -> String {
+    let id = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    match id.chars().next() {
+        Some(c) if !c.is_ascii_digit() => id,
+        _ => format!("_{}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gltf_property_types() {
+        for datatype in [
+            PointAttributeDataType::U8,
+            PointAttributeDataType::I64,
+            PointAttributeDataType::F64,
+            PointAttributeDataType::Vec3u16,
+            PointAttributeDataType::Vec4u8,
+        ] {
+            let (stored_datatype, property_type, component_type) =
+                gltf_property_type_from_datatype(datatype).unwrap();
+            assert_eq!(datatype, stored_datatype);
+            assert_eq!(
+                Some(datatype),
+                datatype_from_gltf_property_type(property_type, Some(component_type))
+            );
+        }
+        assert_eq!(
+            None,
+            gltf_property_type_from_datatype(PointAttributeDataType::ByteArray(4))
+        );
+    }
+
+    #[test]
+    fn test_gltf_property_id_from_name() {
+        assert_eq!("Intensity", gltf_property_id_from_name("Intensity"));
+        assert_eq!("Return_number", gltf_property_id_from_name("Return number"));
+        assert_eq!("_3D_id", gltf_property_id_from_name("3D id"));
+    }
+}
@@ -0,0 +1,362 @@
+use std::{borrow::Cow, collections::HashSet, convert::TryInto, io::Write};
+
+use anyhow::{Context, Result};
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, ColumnarBuffer, HashMapBuffer, MakeBufferFromLayout, OwningBuffer,
+    },
+    layout::{
+        attributes::{COLOR_RGB, NORMAL, POSITION_3D},
+        conversion::BufferLayoutConverter,
+        FieldAlignment, PointAttributeDataType, PointAttributeDefinition, PointLayout,
+    },
+    math::Alignable,
+    nalgebra::{Matrix4, Vector3},
+};
+
+use crate::base::PointWriter;
+
+use super::{
+    component_types, gltf_property_id_from_name, gltf_property_type_from_datatype,
+    gltf_y_up_to_z_up, FeatureId, GlbChunkHeader, GlbHeader, Gltf, GltfAccessor, GltfBuffer,
+    GltfBufferView, GltfExtensions, GltfMesh, GltfNode, GltfPrimitive, GltfPrimitiveExtensions,
+    GltfScene, MeshFeatures, MetadataClass, MetadataClassProperty, MetadataSchema, PropertyTable,
+    PropertyTableProperty, StructuralMetadata, GLTF_MODE_POINTS, GLTF_TARGET_ARRAY_BUFFER,
+};
+
+/// Alignment of all buffer views in the binary chunk. Property tables require 8-byte alignment
+const GLB_BUFFER_VIEW_ALIGNMENT: usize = 8;
+/// Name of the metadata class that describes the properties of a single point
+const GLB_POINT_CLASS: &str = "point";
+
+/// Writer for point clouds in the binary glTF format (.glb), which is the content format of 3D Tiles 1.1.
+///
+/// All points are written as a single glTF primitive with mode `POINTS`. Positions, colors and normals are written
+/// as `POSITION`, `COLOR_0` and `NORMAL` vertex attributes. All further attributes are written into a property table
+/// using the `EXT_structural_metadata` extension, where each point is a feature with its index as feature ID (using
+/// the `EXT_mesh_features` extension).
+///
+/// As with the `PntsWriter`, all points are cached in memory and only written during `flush`. glTF uses a y-up coordinate
+/// system, so the `GlbWriter` writes a node transformation that rotates the points from the z-up coordinate system of
+/// pasture (and 3D Tiles) into y-up. Viewers for 3D Tiles undo this rotation, so the points appear in their original
+/// coordinates
+pub struct GlbWriter<W: Write> {
+    writer: W,
+    expected_layout: PointLayout,
+    default_layout: PointLayout,
+    cached_points: HashMapBuffer,
+    rtc_center: Option<Vector3<f64>>,
+    requires_flush: bool,
+}
+
+impl<W: Write> GlbWriter<W> {
+    /// Creates a new `GlbWriter` writing to the given `writer` and using the given `point_layout`. Attributes whose
+    /// datatype can't be represented in a property table (e.g. `ByteArray`) are silently ignored!
+    pub fn from_write_and_layout(writer: W, point_layout: PointLayout) -> Self {
+        let cache_layout = Self::make_compatible_layout(&point_layout);
+        Self {
+            writer,
+            expected_layout: point_layout,
+            cached_points: HashMapBuffer::new_from_layout(cache_layout.clone()),
+            default_layout: cache_layout,
+            rtc_center: None,
+            requires_flush: true,
+        }
+    }
+
+    /// Sets a center point that all positions are relative to. The center is stored as the translation of the glTF node
+    /// that contains the points, which gives the same precision benefits as the `RTC_CENTER` semantic of the .pnts format.
+    /// As with `PntsWriter::set_rtc_center`, **points are not automatically translated relative to this center!**
+    pub fn set_rtc_center(&mut self, rtc_center: Vector3<f64>) {
+        self.rtc_center = Some(rtc_center);
+    }
+
+    /// Makes the given `PointLayout` compatible with glTF. Positions and normals are converted to `Vec3f32` and colors to
+    /// `Vec3u8`, as required for the corresponding vertex attributes. All other attributes are converted to a datatype that
+    /// property tables support, or discarded if there is none
+    fn make_compatible_layout(point_layout: &PointLayout) -> PointLayout {
+        let mut compatible_layout = PointLayout::default();
+        for attribute in point_layout.attributes() {
+            let datatype =
+                if attribute.name() == POSITION_3D.name() || attribute.name() == NORMAL.name() {
+                    Some(PointAttributeDataType::Vec3f32)
+                } else if attribute.name() == COLOR_RGB.name() {
+                    Some(PointAttributeDataType::Vec3u8)
+                } else {
+                    gltf_property_type_from_datatype(attribute.datatype())
+                        .map(|(datatype, _, _)| datatype)
+                };
+            if let Some(datatype) = datatype {
+                compatible_layout.add_attribute(
+                    PointAttributeDefinition::custom(
+                        Cow::Owned(attribute.name().to_owned()),
+                        datatype,
+                    ),
+                    FieldAlignment::Default,
+                );
+            }
+        }
+        compatible_layout
+    }
+
+    /// Creates the glTF JSON structure and the binary chunk for all cached points
+    fn create_gltf(&self) -> (Gltf, Vec<u8>) {
+        let num_points = self.cached_points.len();
+        let mut gltf = Gltf::default();
+        let mut bin = vec![];
+        // A glTF accessor must have at least one element, so an empty point cloud is written as an empty scene
+        if num_points == 0 {
+            gltf.scene = Some(0);
+            gltf.scenes.push(GltfScene::default());
+            return (gltf, bin);
+        }
+
+        let mut push_buffer_view =
+            |data: &[u8], byte_stride: Option<usize>, target: Option<u32>| {
+                let byte_offset = bin.len();
+                bin.extend_from_slice(data);
+                bin.resize(bin.len().align_to(GLB_BUFFER_VIEW_ALIGNMENT), 0);
+                gltf.buffer_views.push(GltfBufferView {
+                    buffer: 0,
+                    byte_offset,
+                    byte_length: data.len(),
+                    byte_stride,
+                    target,
+                });
+                gltf.buffer_views.len() - 1
+            };
+
+        let mut primitive = GltfPrimitive {
+            mode: Some(GLTF_MODE_POINTS),
+            ..Default::default()
+        };
+        let mut accessors = vec![];
+        let mut class = MetadataClass::default();
+        let mut property_table = PropertyTable {
+            class: GLB_POINT_CLASS.into(),
+            count: num_points,
+            properties: Default::default(),
+        };
+        let mut used_property_ids = HashSet::new();
+
+        for attribute in self.default_layout.attributes() {
+            let attribute = attribute.attribute_definition();
+            let data = self
+                .cached_points
+                .get_attribute_range_ref(attribute, 0..num_points);
+            if attribute.name() == POSITION_3D.name() || attribute.name() == NORMAL.name() {
+                let buffer_view = push_buffer_view(data, None, Some(GLTF_TARGET_ARRAY_BUFFER));
+                let (min, max) = if attribute.name() == POSITION_3D.name() {
+                    let positions = self.cached_points.view_attribute::<Vector3<f32>>(attribute);
+                    let first_position = positions.at(0);
+                    let (min, max) = positions
+                        .into_iter()
+                        .fold((first_position, first_position), |(min, max), position| {
+                            (min.inf(&position), max.sup(&position))
+                        });
+                    // glTF requires the bounds of the positions
+                    (
+                        Some(min.iter().map(|v| *v as f64).collect()),
+                        Some(max.iter().map(|v| *v as f64).collect()),
+                    )
+                } else {
+                    (None, None)
+                };
+                accessors.push(GltfAccessor {
+                    buffer_view: Some(buffer_view),
+                    component_type: component_types::FLOAT,
+                    count: num_points,
+                    accessor_type: "VEC3".into(),
+                    min,
+                    max,
+                    ..Default::default()
+                });
+                let semantic = if attribute.name() == POSITION_3D.name() {
+                    "POSITION"
+                } else {
+                    "NORMAL"
+                };
+                primitive
+                    .attributes
+                    .insert(semantic.into(), accessors.len() - 1);
+            } else if attribute.name() == COLOR_RGB.name() {
+                // Each element of a vertex attribute must be aligned to 4 bytes, so RGB colors are padded
+                let padded_colors = data
+                    .chunks_exact(3)
+                    .flat_map(|color| [color[0], color[1], color[2], 0])
+                    .collect::<Vec<_>>();
+                let buffer_view =
+                    push_buffer_view(&padded_colors, Some(4), Some(GLTF_TARGET_ARRAY_BUFFER));
+                accessors.push(GltfAccessor {
+                    buffer_view: Some(buffer_view),
+                    component_type: component_types::UNSIGNED_BYTE,
+                    normalized: true,
+                    count: num_points,
+                    accessor_type: "VEC3".into(),
+                    ..Default::default()
+                });
+                primitive
+                    .attributes
+                    .insert("COLOR_0".into(), accessors.len() - 1);
+            } else {
+                let (_, property_type, component_type) =
+                    gltf_property_type_from_datatype(attribute.datatype())
+                        .expect("Invalid property table datatype");
+                let mut property_id = gltf_property_id_from_name(attribute.name());
+                while !used_property_ids.insert(property_id.clone()) {
+                    property_id.push('_');
+                }
+                let buffer_view = push_buffer_view(data, None, None);
+                class.properties.insert(
+                    property_id.clone(),
+                    MetadataClassProperty {
+                        name: if property_id == attribute.name() {
+                            None
+                        } else {
+                            Some(attribute.name().to_owned())
+                        },
+                        property_type: property_type.into(),
+                        component_type: Some(component_type.into()),
+                        array: false,
+                    },
+                );
+                property_table.properties.insert(
+                    property_id,
+                    PropertyTableProperty {
+                        values: buffer_view,
+                    },
+                );
+            }
+        }
+
+        if !class.properties.is_empty() {
+            primitive.extensions = Some(GltfPrimitiveExtensions {
+                mesh_features: Some(MeshFeatures {
+                    feature_ids: vec![FeatureId {
+                        feature_count: num_points,
+                        property_table: Some(0),
+                        ..Default::default()
+                    }],
+                }),
+                structural_metadata: None,
+            });
+            gltf.extensions_used =
+                vec!["EXT_mesh_features".into(), "EXT_structural_metadata".into()];
+            gltf.extensions = Some(GltfExtensions {
+                structural_metadata: Some(StructuralMetadata {
+                    schema: Some(MetadataSchema {
+                        id: "pasture".into(),
+                        classes: std::iter::once((GLB_POINT_CLASS.to_owned(), class)).collect(),
+                    }),
+                    property_tables: vec![property_table],
+                    property_attributes: vec![],
+                }),
+                cesium_rtc: None,
+            });
+        }
+
+        let node_transform = gltf_y_up_to_z_up()
+            .try_inverse()
+            .expect("Rotation must be invertible")
+            * Matrix4::new_translation(&self.rtc_center.unwrap_or_else(Vector3::zeros));
+        gltf.accessors = accessors;
+        gltf.meshes.push(GltfMesh {
+            primitives: vec![primitive],
+        });
+        gltf.nodes.push(GltfNode {
+            mesh: Some(0),
+            matrix: Some(
+                node_transform
+                    .as_slice()
+                    .try_into()
+                    .expect("Matrix4 has 16 elements"),
+            ),
+            ..Default::default()
+        });
+        gltf.scene = Some(0);
+        gltf.scenes.push(GltfScene { nodes: vec![0] });
+        gltf.buffers.push(GltfBuffer {
+            byte_length: bin.len(),
+            uri: None,
+        });
+        (gltf, bin)
+    }
+
+    fn write_cached_points(&mut self) -> Result<()> {
+        let (gltf, mut bin) = self.create_gltf();
+        let mut json = serde_json::to_vec(&gltf).context("Error while serializing glTF JSON")?;
+        // Both chunks must be aligned to 4 bytes. The JSON chunk is padded with spaces, the binary chunk with zeros
+        json.resize(json.len().align_to(4), b' ');
+        bin.resize(bin.len().align_to(4), 0);
+
+        let mut total_byte_length =
+            GlbHeader::BYTE_LENGTH + GlbChunkHeader::BYTE_LENGTH + json.len();
+        if !bin.is_empty() {
+            total_byte_length += GlbChunkHeader::BYTE_LENGTH + bin.len();
+        }
+        let header = GlbHeader::new(
+            total_byte_length
+                .try_into()
+                .expect("Size of .glb file exceeds maximum size of 4GiB!"),
+        );
+        bincode::serialize_into(&mut self.writer, &header)
+            .context("Error while serializing .glb header")?;
+
+        for (chunk_type, chunk) in [(GlbChunkHeader::JSON, &json), (GlbChunkHeader::BIN, &bin)] {
+            if chunk.is_empty() {
+                continue;
+            }
+            let chunk_header = GlbChunkHeader {
+                chunk_length: chunk.len() as u32,
+                chunk_type,
+            };
+            bincode::serialize_into(&mut self.writer, &chunk_header)
+                .context("Error while serializing .glb chunk header")?;
+            self.writer
+                .write_all(chunk)
+                .context("Error while writing .glb chunk")?;
+        }
+
+        self.requires_flush = false;
+        Ok(())
+    }
+}
+
+impl<W: Write> PointWriter for GlbWriter<W> {
+    fn write<'a, B: BorrowedBuffer<'a>>(&mut self, points: &'a B) -> Result<()> {
+        if points.point_layout() != &self.expected_layout {
+            panic!("PointLayout of buffer does not match the PointLayout that this GlbWriter was constructed with! Make sure that you only pass PointBuffers with the same layout as the one you used to create this GlbWriter!");
+        }
+
+        let converter =
+            BufferLayoutConverter::for_layouts(points.point_layout(), &self.default_layout);
+        let base_point_index = self.cached_points.len();
+        self.cached_points.resize(base_point_index + points.len());
+        converter.convert_into_range(
+            points,
+            0..points.len(),
+            &mut self.cached_points,
+            base_point_index..(base_point_index + points.len()),
+        );
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.requires_flush {
+            return Ok(());
+        }
+        self.write_cached_points()?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.default_layout
+    }
+}
+
+impl<W: Write> Drop for GlbWriter<W> {
+    fn drop(&mut self) {
+        self.flush().expect("Error while flushing GlbWriter")
+    }
+}
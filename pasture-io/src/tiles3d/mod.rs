@@ -7,6 +7,9 @@ pub use self::pnts_writer::*;
 mod pnts_types;
 pub use self::pnts_types::*;
 
+mod pnts_encoding;
+pub use self::pnts_encoding::*;
+
 mod pnts_metadata;
 pub use self::pnts_metadata::*;
 
@@ -21,3 +24,21 @@ pub use self::common::*;
 
 mod tileset;
 pub use self::tileset::*;
+
+mod tileset_reader;
+pub use self::tileset_reader::*;
+
+mod geodesy;
+pub use self::geodesy::*;
+
+mod tiler;
+pub use self::tiler::*;
+
+mod glb_types;
+pub use self::glb_types::*;
+
+mod glb_writer;
+pub use self::glb_writer::*;
+
+mod glb_reader;
+pub use self::glb_reader::*;
@@ -0,0 +1,182 @@
+use pasture_core::nalgebra::{Vector2, Vector3};
+
+/// Maximum value of a quantized position component, as defined by the 3D Tiles specification
+const QUANTIZED_POSITION_MAX: f32 = 65535.0;
+
+/// Defines how a `PntsWriter` encodes positions in the FeatureTable binary body
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PntsPositionEncoding {
+    /// Positions are written as `POSITION` semantic, i.e. as three `float32` values
+    #[default]
+    Float,
+    /// Positions are written as `POSITION_QUANTIZED` semantic, i.e. as three `uint16` values relative to the
+    /// quantized volume given by `QUANTIZED_VOLUME_OFFSET` and `QUANTIZED_VOLUME_SCALE`. The quantized volume
+    /// is the bounding box of all points written
+    Quantized,
+}
+
+/// Defines how a `PntsWriter` encodes RGB colors in the FeatureTable binary body
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PntsColorEncoding {
+    /// Colors are written as `RGB` semantic, i.e. as three `uint8` values
+    #[default]
+    Rgb,
+    /// Colors are written as `RGB565` semantic, i.e. as a single `uint16` value with 5 bits red, 6 bits green
+    /// and 5 bits blue
+    Rgb565,
+}
+
+/// Defines how a `PntsWriter` encodes normals in the FeatureTable binary body
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum PntsNormalEncoding {
+    /// Normals are written as `NORMAL` semantic, i.e. as three `float32` values
+    #[default]
+    Float,
+    /// Normals are written as `NORMAL_OCT16P` semantic, i.e. as two `uint8` values using oct-encoding
+    Oct16P,
+}
+
+/// Quantizes the given `position` relative to the quantized volume defined by `offset` and `scale`
+pub fn quantize_position(
+    position: &Vector3<f32>,
+    offset: &Vector3<f32>,
+    scale: &Vector3<f32>,
+) -> Vector3<u16> {
+    let quantize_component = |value: f32, offset: f32, scale: f32| -> u16 {
+        if scale == 0.0 {
+            return 0;
+        }
+        let normalized = ((value - offset) / scale).clamp(0.0, 1.0);
+        (normalized * QUANTIZED_POSITION_MAX).round() as u16
+    };
+    Vector3::new(
+        quantize_component(position.x, offset.x, scale.x),
+        quantize_component(position.y, offset.y, scale.y),
+        quantize_component(position.z, offset.z, scale.z),
+    )
+}
+
+/// Reverts `quantize_position`, i.e. computes `quantized * scale / 65535 + offset`
+pub fn dequantize_position(
+    quantized: &Vector3<u16>,
+    offset: &Vector3<f32>,
+    scale: &Vector3<f32>,
+) -> Vector3<f32> {
+    quantized
+        .map(|component| component as f32 / QUANTIZED_POSITION_MAX)
+        .component_mul(scale)
+        + offset
+}
+
+/// Encodes the given 8-bit RGB color into a 16-bit RGB565 color
+pub fn encode_rgb565(color: &Vector3<u8>) -> u16 {
+    let r = (color.x >> 3) as u16;
+    let g = (color.y >> 2) as u16;
+    let b = (color.z >> 3) as u16;
+    (r << 11) | (g << 5) | b
+}
+
+/// Decodes the given 16-bit RGB565 color into an 8-bit RGB color. The missing low bits of each channel are filled
+/// by replicating the high bits, so that the full range `[0;255]` is covered
+pub fn decode_rgb565(color: u16) -> Vector3<u8> {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    Vector3::new(
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    )
+}
+
+fn sign_not_zero(value: f32) -> f32 {
+    if value < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Encodes the given `normal` using oct-encoding with 8 bits per component, as used by the `NORMAL_OCT16P` semantic.
+/// The normal does not have to be normalized
+pub fn encode_oct16p(normal: &Vector3<f32>) -> Vector2<u8> {
+    let l1_norm = normal.x.abs() + normal.y.abs() + normal.z.abs();
+    if l1_norm == 0.0 {
+        return Vector2::new(128, 128);
+    }
+    let mut projected = Vector2::new(normal.x / l1_norm, normal.y / l1_norm);
+    if normal.z < 0.0 {
+        projected = Vector2::new(
+            (1.0 - projected.y.abs()) * sign_not_zero(projected.x),
+            (1.0 - projected.x.abs()) * sign_not_zero(projected.y),
+        );
+    }
+    projected.map(|component| ((component.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0).round() as u8)
+}
+
+/// Decodes the given oct-encoded normal (see `encode_oct16p`). The resulting normal is normalized
+pub fn decode_oct16p(encoded: &Vector2<u8>) -> Vector3<f32> {
+    let projected = encoded.map(|component| component as f32 / 255.0 * 2.0 - 1.0);
+    let z = 1.0 - projected.x.abs() - projected.y.abs();
+    let normal = if z < 0.0 {
+        Vector3::new(
+            (1.0 - projected.y.abs()) * sign_not_zero(projected.x),
+            (1.0 - projected.x.abs()) * sign_not_zero(projected.y),
+            z,
+        )
+    } else {
+        Vector3::new(projected.x, projected.y, z)
+    };
+    normal.normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantize_position_roundtrip() {
+        let offset = Vector3::new(-10.0_f32, 0.0, 100.0);
+        let scale = Vector3::new(20.0_f32, 5.0, 0.0);
+        let position = Vector3::new(2.5_f32, 1.25, 100.0);
+
+        let quantized = quantize_position(&position, &offset, &scale);
+        assert_eq!(0, quantized.z);
+        let dequantized = dequantize_position(&quantized, &offset, &scale);
+        for (expected, actual) in position.iter().zip(dequantized.iter()) {
+            assert!((expected - actual).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_rgb565_roundtrip() {
+        assert_eq!(Vector3::new(255, 255, 255), decode_rgb565(0xFFFF));
+        assert_eq!(Vector3::new(0, 0, 0), decode_rgb565(0));
+        assert_eq!(0xF800, encode_rgb565(&Vector3::new(255, 0, 0)));
+
+        let color = Vector3::new(0x80_u8, 0x40, 0x20);
+        let decoded = decode_rgb565(encode_rgb565(&color));
+        for (expected, actual) in color.iter().zip(decoded.iter()) {
+            assert!((*expected as i32 - *actual as i32).abs() <= 8);
+        }
+    }
+
+    #[test]
+    fn test_oct16p_roundtrip() {
+        let normals = vec![
+            Vector3::new(0.0_f32, 0.0, 1.0),
+            Vector3::new(0.0_f32, 0.0, -1.0),
+            Vector3::new(1.0_f32, 0.0, 0.0),
+            Vector3::new(0.3_f32, -0.5, -0.8).normalize(),
+        ];
+        for normal in normals {
+            let decoded = decode_oct16p(&encode_oct16p(&normal));
+            assert!(
+                normal.dot(&decoded) > 0.999,
+                "Expected {} but got {}",
+                normal,
+                decoded
+            );
+        }
+    }
+}
@@ -46,6 +46,21 @@ impl PntsMetadata {
     pub fn rtc_center(&self) -> Option<Vector3<f64>> {
         self.rtc_center
     }
+
+    /// Access the `QUANTIZED_VOLUME_OFFSET` field of the metadata
+    pub fn quantized_volume_offset(&self) -> Option<Vector3<f32>> {
+        self.quantized_volume_offset
+    }
+
+    /// Access the `QUANTIZED_VOLUME_SCALE` field of the metadata
+    pub fn quantized_volume_scale(&self) -> Option<Vector3<f32>> {
+        self.quantized_volume_scale
+    }
+
+    /// Access the `BATCH_LENGTH` field of the metadata, i.e. the number of distinct batches if the `BATCH_ID` semantic is present
+    pub fn batch_length(&self) -> Option<usize> {
+        self.batch_length
+    }
 }
 
 impl Metadata for PntsMetadata {
@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
     convert::TryInto,
     fs::File,
@@ -12,16 +13,22 @@ use pasture_core::{
     layout::{
         attributes::{COLOR_RGB, NORMAL, POSITION_3D},
         conversion::get_converter_for_attributes,
-        FieldAlignment, PointAttributeDataType, PointLayout,
+        FieldAlignment, PointAttributeDataType, PointAttributeDefinition, PointLayout,
     },
     meta::Metadata,
-    nalgebra::{clamp, Vector3},
+    nalgebra::{clamp, Vector2, Vector3},
 };
 
-use crate::tiles3d::{deser_feature_table_header, FeatureTableValue, PntsHeader};
+use crate::tiles3d::{
+    deser_batch_table_header, deser_feature_table_header, BatchTableEntry, FeatureTableValue,
+    PntsHeader,
+};
 use crate::{
     base::{PointReader, SeekToPoint},
-    tiles3d::{attributes::COLOR_RGBA, json_arr_to_vec3f32, json_arr_to_vec4u8},
+    tiles3d::{
+        attributes::{BATCH_ID, COLOR_RGBA},
+        decode_oct16p, decode_rgb565, dequantize_position, json_arr_to_vec3f32, json_arr_to_vec4u8,
+    },
 };
 
 use super::{json_arr_to_vec3f64, PntsMetadata};
@@ -37,13 +44,63 @@ pub enum PntsReadPositionsMode {
     Absolute,
 }
 
-/// A reader for points in the 3D Tiles PNTS format
+/// How a point attribute is stored within the FeatureTable binary body
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PntsStoredEncoding {
+    /// The attribute is stored exactly as in the `PointLayout` of the `PntsReader`
+    Raw,
+    /// `POSITION_QUANTIZED`, three `uint16` values
+    QuantizedPosition,
+    /// `RGB565`, a single `uint16` value
+    Rgb565,
+    /// `NORMAL_OCT16P`, two `uint8` values
+    Oct16P,
+}
+
+impl PntsStoredEncoding {
+    /// Size of a single stored value with this encoding, given the size of the decoded attribute
+    fn stored_size(&self, decoded_size: u64) -> u64 {
+        match self {
+            PntsStoredEncoding::Raw => decoded_size,
+            PntsStoredEncoding::QuantizedPosition => 6,
+            PntsStoredEncoding::Rgb565 => 2,
+            PntsStoredEncoding::Oct16P => 2,
+        }
+    }
+}
+
+/// A BatchTable property whose values are kept in memory. This is the case for properties stored in the BatchTable JSON
+/// header, as well as for properties that are stored per batch instead of per point
+struct PntsInMemoryProperty {
+    /// Values of the property in the datatype of the corresponding attribute in the `PointLayout` of the `PntsReader`
+    data: Vec<u8>,
+    /// Are the values indexed by the `BATCH_ID` of each point instead of by the point index?
+    indexed_by_batch_id: bool,
+}
+
+/// The `PointLayout` of a PNTS file together with the offsets and encodings of all attributes in the FeatureTable binary body
+type PntsLayoutInfo = (
+    PointLayout,
+    HashMap<String, u64>,
+    HashMap<String, PntsStoredEncoding>,
+);
+
+/// A reader for points in the 3D Tiles PNTS format. Positions stored as `POSITION_QUANTIZED`, colors stored as
+/// `RGB565` and normals stored as `NORMAL_OCT16P` are decoded during reading, so they are always provided with
+/// the same datatypes as their `POSITION`, `RGB` and `NORMAL` counterparts.
+///
+/// Properties in the BatchTable are provided as additional attributes, using the property name as attribute name. If the
+/// `BATCH_ID` semantic is present, it is provided as the `BATCH_ID` attribute and the per-batch properties are looked up
+/// through the batch ID of each point. Properties with types that pasture does not support (e.g. `VEC2` or non-numeric JSON
+/// values) are ignored
 pub struct PntsReader<R: BufRead + Seek> {
     reader: R,
     metadata: PntsMetadata,
     layout: PointLayout,
     current_point_index: usize,
     attribute_offsets: HashMap<String, u64>,
+    attribute_encodings: HashMap<String, PntsStoredEncoding>,
+    in_memory_properties: HashMap<String, PntsInMemoryProperty>,
     read_positions_mode: PntsReadPositionsMode,
 }
 
@@ -68,14 +125,21 @@ impl<R: BufRead + Seek> PntsReader<R> {
             header.feature_table_json_byte_length as usize,
             position_after_header,
         )?;
-        // TODO BatchTable support
 
         // The following functions mutate the feature table header HashMap and remove the entries that
         // are relevant. This is done because both point semantics and global semantics are stored in the
         // same header, so this makes parsing easier
-        let (layout, mut attribute_offsets) =
+        let (layout, mut attribute_offsets, attribute_encodings) =
             Self::layout_from_feature_table_header(&mut feature_table_header)?;
         let metadata = Self::metadata_from_feature_table_header(&mut feature_table_header)?;
+        if attribute_encodings
+            .values()
+            .any(|encoding| *encoding == PntsStoredEncoding::QuantizedPosition)
+            && (metadata.quantized_volume_offset().is_none()
+                || metadata.quantized_volume_scale().is_none())
+        {
+            bail!("Found PNTS attribute POSITION_QUANTIZED but QUANTIZED_VOLUME_OFFSET or QUANTIZED_VOLUME_SCALE is missing");
+        }
 
         // TODO Log all parameters that could not be parsed. This requires logging support for pasture
 
@@ -85,14 +149,27 @@ impl<R: BufRead + Seek> PntsReader<R> {
             *offset += feature_table_binary_offset;
         }
 
-        Ok(Self {
+        let mut pnts_reader = Self {
             reader: read,
             metadata,
             layout,
             current_point_index: 0,
             attribute_offsets,
+            attribute_encodings,
+            in_memory_properties: HashMap::new(),
             read_positions_mode: PntsReadPositionsMode::Absolute,
-        })
+        };
+
+        let batch_table_json_byte_length = header.batch_table_json_byte_length as usize;
+        if batch_table_json_byte_length > 0 {
+            let batch_table_offset =
+                feature_table_binary_offset + header.feature_table_binary_byte_length as u64;
+            pnts_reader
+                .read_batch_table(batch_table_offset, batch_table_json_byte_length)
+                .context("Could not read BatchTable of PNTS file")?;
+        }
+
+        Ok(pnts_reader)
     }
 
     /// Sets the `PntsReadPositionsMode` for this `PntsReader`
@@ -110,31 +187,49 @@ impl<R: BufRead + Seek> PntsReader<R> {
     /// use the order in which they are defined in the header, however we are using a HashMap for easy lookup, so we don't have the
     /// order at this point. Instead, we check all supported attributes ('point semantics' in 3D Tiles jargon) in exactly the order
     /// that they are defined in [here](https://github.com/CesiumGS/3d-tiles/blob/master/specification/TileFormats/PointCloud/README.md#semantics).
+    ///
+    /// Encoded semantics (`POSITION_QUANTIZED`, `RGB565` and `NORMAL_OCT16P`) map to the same attributes as their unencoded
+    /// counterparts, which take precedence if both are present
     fn layout_from_feature_table_header(
         header: &mut HashMap<String, FeatureTableValue>,
-    ) -> Result<(PointLayout, HashMap<String, u64>)> {
+    ) -> Result<PntsLayoutInfo> {
         // 3D Tiles .pnts has very few supported point attributes, so we can just enumerate them by hand
         let mut layout: PointLayout = Default::default();
         let mut attribute_offsets = HashMap::new();
+        let mut attribute_encodings = HashMap::new();
         if header.contains_key("POSITION") {
             let pos_attribute = &header["POSITION"];
             match pos_attribute {
                 FeatureTableValue::DataReference(reference) => {
                     attribute_offsets.insert(POSITION_3D.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(POSITION_3D.name().to_owned(), PntsStoredEncoding::Raw);
                     layout.add_attribute(POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32), FieldAlignment::Packed(1));
                 },
                 _ => bail!("Found PNTS attribute POSITION ({:?}) but it was not a reference to the feature table binary!", pos_attribute),
             }
             header.remove("POSITION");
+            header.remove("POSITION_QUANTIZED");
         }
 
-        // TODO Quantized positions, which probably require an option to de-quantize during reading (similar to LAS/LAZ)
+        if header.contains_key("POSITION_QUANTIZED") {
+            let pos_attribute = &header["POSITION_QUANTIZED"];
+            match pos_attribute {
+                FeatureTableValue::DataReference(reference) => {
+                    attribute_offsets.insert(POSITION_3D.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(POSITION_3D.name().to_owned(), PntsStoredEncoding::QuantizedPosition);
+                    layout.add_attribute(POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32), FieldAlignment::Packed(1));
+                },
+                _ => bail!("Found PNTS attribute POSITION_QUANTIZED ({:?}) but it was not a reference to the feature table binary!", pos_attribute),
+            }
+            header.remove("POSITION_QUANTIZED");
+        }
 
         if header.contains_key("RGBA") {
             let color_attribute = &header["RGBA"];
             match color_attribute {
                 FeatureTableValue::DataReference(reference) => {
                     attribute_offsets.insert(COLOR_RGBA.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(COLOR_RGBA.name().to_owned(), PntsStoredEncoding::Raw);
                     layout.add_attribute(COLOR_RGBA, FieldAlignment::Packed(1));
                 },
                 _ => bail!("Found PNTS attribute RGBA ({:?}) but it was not a reference to the feature table binary!", color_attribute),
@@ -147,32 +242,75 @@ impl<R: BufRead + Seek> PntsReader<R> {
             match color_attribute {
                 FeatureTableValue::DataReference(reference) => {
                     attribute_offsets.insert(COLOR_RGB.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(COLOR_RGB.name().to_owned(), PntsStoredEncoding::Raw);
                     layout.add_attribute(COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8), FieldAlignment::Packed(1));
                 },
                 _ => bail!("Found PNTS attribute RGB ({:?}) but it was not a reference to the feature table binary!", color_attribute),
             }
             header.remove("RGB");
+            header.remove("RGB565");
         }
 
-        // TOOD RGB565
+        if header.contains_key("RGB565") {
+            let color_attribute = &header["RGB565"];
+            match color_attribute {
+                FeatureTableValue::DataReference(reference) => {
+                    attribute_offsets.insert(COLOR_RGB.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(COLOR_RGB.name().to_owned(), PntsStoredEncoding::Rgb565);
+                    layout.add_attribute(COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8), FieldAlignment::Packed(1));
+                },
+                _ => bail!("Found PNTS attribute RGB565 ({:?}) but it was not a reference to the feature table binary!", color_attribute),
+            }
+            header.remove("RGB565");
+        }
 
         if header.contains_key("NORMAL") {
             let normal_attribute = &header["NORMAL"];
             match normal_attribute {
                 FeatureTableValue::DataReference(reference) => {
                     attribute_offsets.insert(NORMAL.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(NORMAL.name().to_owned(), PntsStoredEncoding::Raw);
                     layout.add_attribute(NORMAL ,FieldAlignment::Packed(1));
                 },
                 _ => bail!("Found PNTS attribute NORMAL ({:?}) but it was not a reference to the feature table binary!", normal_attribute),
             }
             header.remove("NORMAL");
+            header.remove("NORMAL_OCT16P");
         }
 
-        // Normal oct16p
+        if header.contains_key("NORMAL_OCT16P") {
+            let normal_attribute = &header["NORMAL_OCT16P"];
+            match normal_attribute {
+                FeatureTableValue::DataReference(reference) => {
+                    attribute_offsets.insert(NORMAL.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(NORMAL.name().to_owned(), PntsStoredEncoding::Oct16P);
+                    layout.add_attribute(NORMAL ,FieldAlignment::Packed(1));
+                },
+                _ => bail!("Found PNTS attribute NORMAL_OCT16P ({:?}) but it was not a reference to the feature table binary!", normal_attribute),
+            }
+            header.remove("NORMAL_OCT16P");
+        }
 
-        // Batch ID
+        if header.contains_key("BATCH_ID") {
+            let batch_id_attribute = &header["BATCH_ID"];
+            match batch_id_attribute {
+                FeatureTableValue::DataReference(reference) => {
+                    let datatype = match reference.component_type.as_deref() {
+                        Some("UNSIGNED_BYTE") => PointAttributeDataType::U8,
+                        None | Some("UNSIGNED_SHORT") => PointAttributeDataType::U16,
+                        Some("UNSIGNED_INT") => PointAttributeDataType::U32,
+                        Some(other) => bail!("Invalid componentType {} for PNTS attribute BATCH_ID", other),
+                    };
+                    attribute_offsets.insert(BATCH_ID.name().to_owned(), reference.byte_offset as u64);
+                    attribute_encodings.insert(BATCH_ID.name().to_owned(), PntsStoredEncoding::Raw);
+                    layout.add_attribute(BATCH_ID.with_custom_datatype(datatype), FieldAlignment::Packed(1));
+                },
+                _ => bail!("Found PNTS attribute BATCH_ID ({:?}) but it was not a reference to the feature table binary!", batch_id_attribute),
+            }
+            header.remove("BATCH_ID");
+        }
 
-        Ok((layout, attribute_offsets))
+        Ok((layout, attribute_offsets, attribute_encodings))
     }
 
     fn metadata_from_feature_table_header(
@@ -244,6 +382,147 @@ impl<R: BufRead + Seek> PntsReader<R> {
         ))
     }
 
+    /// Reads the BatchTable starting at `batch_table_offset` in the file and adds all its properties to the `PointLayout`
+    /// of this reader. Per-point properties in the binary body are read like point semantics, all other properties are
+    /// loaded into memory
+    fn read_batch_table(
+        &mut self,
+        batch_table_offset: u64,
+        batch_table_json_byte_length: usize,
+    ) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(batch_table_offset))?;
+        let batch_table_header = deser_batch_table_header(
+            &mut self.reader,
+            batch_table_json_byte_length,
+            batch_table_offset as usize,
+        )?;
+        let batch_table_binary_offset = self.reader.stream_position()?;
+
+        // As per the 3D Tiles specification, the BatchTable stores per-batch properties if the BATCH_ID semantic is present
+        let indexed_by_batch_id = self.layout.has_attribute_with_name(BATCH_ID.name());
+        let num_values = if indexed_by_batch_id {
+            self.metadata.batch_length().ok_or_else(|| {
+                anyhow!("PNTS file has BATCH_ID semantic but no BATCH_LENGTH value")
+            })?
+        } else {
+            self.metadata.points_length()
+        };
+
+        // Sort by name so that the order of the attributes in the PointLayout is deterministic
+        let mut properties = batch_table_header.into_iter().collect::<Vec<_>>();
+        properties.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+
+        for (name, entry) in properties {
+            if self.layout.has_attribute_with_name(&name) {
+                continue;
+            }
+            match entry {
+                BatchTableEntry::DataReference(reference) => {
+                    let datatype = match reference.datatype() {
+                        Some(datatype) => datatype,
+                        None => continue,
+                    };
+                    let offset = batch_table_binary_offset + reference.byte_offset as u64;
+                    if indexed_by_batch_id {
+                        let mut data = vec![0; num_values * datatype.size() as usize];
+                        self.reader.seek(SeekFrom::Start(offset))?;
+                        self.reader.read_exact(&mut data).with_context(|| {
+                            format!("Could not read values of BatchTable property {}", name)
+                        })?;
+                        self.in_memory_properties.insert(
+                            name.clone(),
+                            PntsInMemoryProperty {
+                                data,
+                                indexed_by_batch_id,
+                            },
+                        );
+                    } else {
+                        self.attribute_offsets.insert(name.clone(), offset);
+                        self.attribute_encodings
+                            .insert(name.clone(), PntsStoredEncoding::Raw);
+                    }
+                    self.layout.add_attribute(
+                        PointAttributeDefinition::custom(Cow::Owned(name), datatype),
+                        FieldAlignment::Packed(1),
+                    );
+                }
+                BatchTableEntry::ArrayData(values) => {
+                    let numeric_values = match values
+                        .iter()
+                        .map(|value| value.as_f64())
+                        .collect::<Option<Vec<_>>>()
+                    {
+                        Some(numeric_values) => numeric_values,
+                        None => continue,
+                    };
+                    if numeric_values.len() != num_values {
+                        bail!(
+                            "BatchTable property {} has {} values but {} values were expected",
+                            name,
+                            numeric_values.len(),
+                            num_values
+                        );
+                    }
+                    let data = numeric_values
+                        .iter()
+                        .flat_map(|value| value.to_ne_bytes())
+                        .collect();
+                    self.in_memory_properties.insert(
+                        name.clone(),
+                        PntsInMemoryProperty {
+                            data,
+                            indexed_by_batch_id,
+                        },
+                    );
+                    self.layout.add_attribute(
+                        PointAttributeDefinition::custom(
+                            Cow::Owned(name),
+                            PointAttributeDataType::F64,
+                        ),
+                        FieldAlignment::Packed(1),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the batch IDs of the next `count` points, starting at the current point
+    fn read_batch_ids(&mut self, count: usize) -> Result<Vec<usize>> {
+        let batch_id_attribute = self
+            .layout
+            .get_attribute_by_name(BATCH_ID.name())
+            .ok_or_else(|| anyhow!("PNTS file has no BATCH_ID semantic"))?;
+        let datatype = batch_id_attribute.datatype();
+        let stride = datatype.size();
+        let offset = self.attribute_offsets[BATCH_ID.name()];
+        self.reader.seek(SeekFrom::Start(
+            offset + self.current_point_index as u64 * stride,
+        ))?;
+
+        let mut data = vec![0; count * stride as usize];
+        self.reader.read_exact(&mut data)?;
+        let batch_ids = data
+            .chunks_exact(stride as usize)
+            .map(|bytes| match datatype {
+                PointAttributeDataType::U8 => bytes[0] as usize,
+                PointAttributeDataType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+                _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            })
+            .collect::<Vec<_>>();
+
+        let batch_length = self.metadata.batch_length().unwrap_or_default();
+        if let Some(invalid_batch_id) = batch_ids.iter().find(|id| **id >= batch_length) {
+            bail!(
+                "BATCH_ID {} is out of bounds for BATCH_LENGTH {}",
+                invalid_batch_id,
+                batch_length
+            );
+        }
+        Ok(batch_ids)
+    }
+
     fn apply_rtc_center_offset<'a, 'b, B: BorrowedMutBuffer<'a>>(
         &self,
         point_buffer: &'b mut B,
@@ -281,6 +560,33 @@ impl<R: BufRead + Seek> PntsReader<R> {
         }
         Ok(())
     }
+
+    /// Decodes a single stored value with the given `encoding` into `decoded`, which has the datatype of the
+    /// corresponding attribute in the `PointLayout` of this reader
+    fn decode_attribute(&self, encoding: PntsStoredEncoding, stored: &[u8], decoded: &mut [u8]) {
+        // PNTS is little-endian, the decoded values use native endianness like all pasture buffers
+        let read_u16 =
+            |index: usize| u16::from_le_bytes([stored[2 * index], stored[2 * index + 1]]);
+        match encoding {
+            PntsStoredEncoding::Raw => decoded.copy_from_slice(stored),
+            PntsStoredEncoding::QuantizedPosition => {
+                // Presence of the quantized volume is checked in `from_read`
+                let offset = self.metadata.quantized_volume_offset().unwrap();
+                let scale = self.metadata.quantized_volume_scale().unwrap();
+                let quantized = Vector3::new(read_u16(0), read_u16(1), read_u16(2));
+                let position = dequantize_position(&quantized, &offset, &scale);
+                decoded.copy_from_slice(bytemuck::bytes_of(&position));
+            }
+            PntsStoredEncoding::Rgb565 => {
+                let color = decode_rgb565(read_u16(0));
+                decoded.copy_from_slice(bytemuck::bytes_of(&color));
+            }
+            PntsStoredEncoding::Oct16P => {
+                let normal = decode_oct16p(&Vector2::new(stored[0], stored[1]));
+                decoded.copy_from_slice(bytemuck::bytes_of(&normal));
+            }
+        }
+    }
 }
 
 impl PntsReader<BufReader<File>> {
@@ -306,10 +612,59 @@ impl<R: BufRead + Seek> PointReader for PntsReader<R> {
         }
 
         let target_layout = point_buffer.point_layout().clone();
+        let requires_batch_ids = self.in_memory_properties.iter().any(|(name, property)| {
+            property.indexed_by_batch_id && target_layout.has_attribute_with_name(name)
+        });
+        let batch_ids = if requires_batch_ids {
+            self.read_batch_ids(num_to_read)?
+        } else {
+            vec![]
+        };
+
         for attribute in self.layout.attributes() {
             // Try to read this attribute only if it exists in the target buffer's PointLayout
             if let Some(target_attribute) = target_layout.get_attribute_by_name(attribute.name()) {
-                let attribute_stride = attribute.size();
+                let converter = get_converter_for_attributes(
+                    attribute.attribute_definition(),
+                    target_attribute.attribute_definition(),
+                );
+                let target_attribute_def = target_attribute.attribute_definition();
+
+                if let Some(property) = self.in_memory_properties.get(attribute.name()) {
+                    let value_size = attribute.size() as usize;
+                    let mut dst_buf: Vec<u8> = vec![0; target_attribute.size() as usize];
+                    let value_indices: Box<dyn Iterator<Item = usize>> = if property
+                        .indexed_by_batch_id
+                    {
+                        Box::new(batch_ids.iter().copied())
+                    } else {
+                        Box::new(self.current_point_index..self.current_point_index + num_to_read)
+                    };
+                    for (point_index, value_index) in value_indices.enumerate() {
+                        let value = &property.data
+                            [value_index * value_size..(value_index + 1) * value_size];
+                        unsafe {
+                            if let Some(conversion_fn) = converter {
+                                conversion_fn(value, dst_buf.as_mut_slice());
+                                point_buffer.set_attribute(
+                                    target_attribute_def,
+                                    point_index,
+                                    dst_buf.as_slice(),
+                                );
+                            } else {
+                                point_buffer.set_attribute(
+                                    target_attribute_def,
+                                    point_index,
+                                    value,
+                                );
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                let encoding = self.attribute_encodings[attribute.name()];
+                let attribute_stride = encoding.stored_size(attribute.size());
                 let offset_to_first_point_of_attribute =
                     *self.attribute_offsets.get(attribute.name()).unwrap();
                 let offset_to_current_point_of_attribute = offset_to_first_point_of_attribute
@@ -319,16 +674,13 @@ impl<R: BufRead + Seek> PointReader for PntsReader<R> {
                     .seek(SeekFrom::Start(offset_to_current_point_of_attribute))?;
 
                 // Maybe we have to convert the datatype?
-                let converter = get_converter_for_attributes(
-                    attribute.attribute_definition(),
-                    target_attribute.attribute_definition(),
-                );
                 if let Some(conversion_fn) = converter {
+                    let mut stored_buf: Vec<u8> = vec![0; attribute_stride as usize];
                     let mut src_buf: Vec<u8> = vec![0; attribute.size() as usize];
                     let mut dst_buf: Vec<u8> = vec![0; target_attribute.size() as usize];
-                    let target_attribute_def = target_attribute.attribute_definition();
                     for point_index in 0..num_to_read {
-                        self.reader.read_exact(src_buf.as_mut_slice())?;
+                        self.reader.read_exact(stored_buf.as_mut_slice())?;
+                        self.decode_attribute(encoding, &stored_buf, &mut src_buf);
                         unsafe {
                             conversion_fn(src_buf.as_slice(), dst_buf.as_mut_slice());
                             point_buffer.set_attribute(
@@ -339,10 +691,11 @@ impl<R: BufRead + Seek> PointReader for PntsReader<R> {
                         }
                     }
                 } else {
+                    let mut stored_buf: Vec<u8> = vec![0; attribute_stride as usize];
                     let mut buf: Vec<u8> = vec![0; attribute.size() as usize];
-                    let target_attribute_def = target_attribute.attribute_definition();
                     for point_index in 0..num_to_read {
-                        self.reader.read_exact(buf.as_mut_slice())?;
+                        self.reader.read_exact(stored_buf.as_mut_slice())?;
+                        self.decode_attribute(encoding, &stored_buf, &mut buf);
                         unsafe {
                             point_buffer.set_attribute(
                                 target_attribute_def,
@@ -397,7 +750,13 @@ impl<R: BufRead + Seek> SeekToPoint for PntsReader<R> {
 mod tests {
     use std::io::Cursor;
 
-    use crate::{base::PointWriter, tiles3d::PntsWriter};
+    use crate::{
+        base::PointWriter,
+        tiles3d::{
+            ser_batch_table_header, ser_feature_table_header, BatchTableDataReference,
+            BatchTableHeader, FeatureTableDataReference, FeatureTableHeader, PntsWriter,
+        },
+    };
 
     use super::*;
     use pasture_core::{
@@ -405,6 +764,7 @@ mod tests {
         layout::PointType,
     };
     use pasture_derive::PointType;
+    use serde_json::json;
 
     #[repr(C, packed)]
     #[derive(
@@ -460,4 +820,137 @@ mod tests {
             assert_eq!(test_points, actual_points);
         }
     }
+    /// Creates a PNTS file with a BATCH_ID semantic and a per-batch BatchTable with one binary and two JSON properties
+    fn pnts_with_batch_ids() -> Result<Vec<u8>> {
+        let positions: Vec<f32> = vec![1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 3.0];
+        let batch_ids: Vec<u8> = vec![1, 0, 1];
+        let classifications: Vec<u8> = vec![7, 9];
+
+        let mut feature_table_header = FeatureTableHeader::new();
+        feature_table_header.insert(
+            "POINTS_LENGTH".into(),
+            FeatureTableValue::SingleValue(json!(3)),
+        );
+        feature_table_header.insert(
+            "BATCH_LENGTH".into(),
+            FeatureTableValue::SingleValue(json!(2)),
+        );
+        feature_table_header.insert(
+            "POSITION".into(),
+            FeatureTableValue::DataReference(FeatureTableDataReference {
+                byte_offset: 0,
+                component_type: None,
+            }),
+        );
+        feature_table_header.insert(
+            "BATCH_ID".into(),
+            FeatureTableValue::DataReference(FeatureTableDataReference {
+                byte_offset: 40,
+                component_type: Some("UNSIGNED_BYTE".into()),
+            }),
+        );
+        let mut feature_table_body = bytemuck::cast_slice::<f32, u8>(&positions).to_vec();
+        feature_table_body.resize(40, 0);
+        feature_table_body.extend_from_slice(&batch_ids);
+        feature_table_body.resize(48, 0);
+
+        let mut batch_table_header = BatchTableHeader::new();
+        batch_table_header.insert(
+            "classification".into(),
+            BatchTableEntry::DataReference(BatchTableDataReference {
+                byte_offset: 0,
+                component_type: "UNSIGNED_BYTE".into(),
+                scalar_or_vector_type: "SCALAR".into(),
+            }),
+        );
+        batch_table_header.insert(
+            "height".into(),
+            BatchTableEntry::ArrayData(vec![json!(1.5), json!(2.5)]),
+        );
+        batch_table_header.insert(
+            "name".into(),
+            BatchTableEntry::ArrayData(vec![json!("a"), json!("b")]),
+        );
+        let mut batch_table_body = classifications;
+        batch_table_body.resize(8, 0);
+
+        let mut feature_table_json = vec![];
+        ser_feature_table_header(
+            &mut feature_table_json,
+            &feature_table_header,
+            PntsHeader::BYTE_LENGTH,
+        )?;
+        let batch_table_start =
+            PntsHeader::BYTE_LENGTH + feature_table_json.len() + feature_table_body.len();
+        let mut batch_table_json = vec![];
+        ser_batch_table_header(
+            Cursor::new(&mut batch_table_json),
+            &batch_table_header,
+            batch_table_start,
+        )?;
+
+        let header = PntsHeader::new(
+            1,
+            (batch_table_start + batch_table_json.len() + batch_table_body.len()) as u32,
+            feature_table_json.len() as u32,
+            feature_table_body.len() as u32,
+            batch_table_json.len() as u32,
+            batch_table_body.len() as u32,
+        );
+        let mut data = bincode::serialize(&header)?;
+        data.extend(feature_table_json);
+        data.extend(feature_table_body);
+        data.extend(batch_table_json);
+        data.extend(batch_table_body);
+        Ok(data)
+    }
+
+    #[test]
+    fn test_pnts_reader_batch_ids() -> Result<()> {
+        let mut reader = PntsReader::from_read(Cursor::new(pnts_with_batch_ids()?))?;
+
+        let classification = PointAttributeDefinition::custom(
+            Cow::Borrowed("classification"),
+            PointAttributeDataType::U8,
+        );
+        let height =
+            PointAttributeDefinition::custom(Cow::Borrowed("height"), PointAttributeDataType::F64);
+        let expected_layout = PointLayout::from_attributes_packed(
+            &[
+                POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32),
+                BATCH_ID.with_custom_datatype(PointAttributeDataType::U8),
+                classification.clone(),
+                height.clone(),
+            ],
+            1,
+        );
+        // The non-numeric 'name' property is not supported
+        assert_eq!(&expected_layout, reader.get_default_point_layout());
+
+        reader.seek_point(SeekFrom::Start(1))?;
+        let points = reader.read::<HashMapBuffer>(2)?;
+        assert_eq!(
+            vec![0_u8, 1],
+            points
+                .view_attribute::<u8>(&BATCH_ID.with_custom_datatype(PointAttributeDataType::U8))
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![7_u8, 9],
+            points
+                .view_attribute::<u8>(&classification)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1.5, 2.5],
+            points
+                .view_attribute::<f64>(&height)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
 }
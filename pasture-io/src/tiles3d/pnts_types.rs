@@ -12,6 +12,11 @@ pub mod attributes {
         Cow::Borrowed("ColorRGBA"),
         PointAttributeDataType::Vec4u8,
     );
+
+    /// Attribute definition for the batch ID of a point in the 3D Tiles format (`BATCH_ID` semantic). The datatype of this
+    /// attribute depends on the `componentType` used in the PNTS file and can be `U8`, `U16` or `U32`
+    pub const BATCH_ID: PointAttributeDefinition =
+        PointAttributeDefinition::custom(Cow::Borrowed("BatchId"), PointAttributeDataType::U16);
 }
 
 /// Header of .pnts files
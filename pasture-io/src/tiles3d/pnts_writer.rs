@@ -24,29 +24,54 @@ use serde_json::json;
 use crate::{
     base::PointWriter,
     tiles3d::{
-        attributes::COLOR_RGBA, ser_batch_table_header, ser_feature_table_header, PntsHeader,
+        attributes::COLOR_RGBA, batch_table_type_from_datatype, encode_oct16p, encode_rgb565,
+        quantize_position, ser_batch_table_header, ser_feature_table_header, PntsColorEncoding,
+        PntsHeader, PntsNormalEncoding, PntsPositionEncoding,
     },
 };
 
-use super::{BatchTableHeader, FeatureTableDataReference, FeatureTableHeader, FeatureTableValue};
+use super::{
+    BatchTableDataReference, BatchTableEntry, BatchTableHeader, FeatureTableDataReference,
+    FeatureTableHeader, FeatureTableValue,
+};
 
 /// Maximum required alignment
 const PNTS_SEMANTICS_MAX_ALIGNMENT: usize = 8;
 /// The current .pnts version of 3D Tiles
 const PNTS_VERSION: u32 = 1;
 
-/// Returns the corresponding point semantic name for the given `attribute`
+/// Is the given `attribute` stored as a point semantic in the FeatureTable? All other attributes are stored in the BatchTable
+fn is_pnts_semantic(attribute: &PointAttributeDefinition) -> bool {
+    attribute.name() == POSITION_3D.name()
+        || attribute.name() == COLOR_RGB.name()
+        || attribute.name() == COLOR_RGBA.name()
+        || attribute.name() == NORMAL.name()
+}
+
+/// Returns the corresponding point semantic name for the given `attribute`, taking the given encodings into account
 fn pnts_semantics_name_from_point_attribute(
     attribute: &PointAttributeDefinition,
+    position_encoding: PntsPositionEncoding,
+    color_encoding: PntsColorEncoding,
+    normal_encoding: PntsNormalEncoding,
 ) -> Option<String> {
     if attribute.name() == POSITION_3D.name() {
-        Some("POSITION".into())
+        match position_encoding {
+            PntsPositionEncoding::Float => Some("POSITION".into()),
+            PntsPositionEncoding::Quantized => Some("POSITION_QUANTIZED".into()),
+        }
     } else if attribute.name() == COLOR_RGB.name() {
-        Some("RGB".into())
+        match color_encoding {
+            PntsColorEncoding::Rgb => Some("RGB".into()),
+            PntsColorEncoding::Rgb565 => Some("RGB565".into()),
+        }
     } else if attribute.name() == COLOR_RGBA.name() {
         Some("RGBA".into())
     } else if attribute.name() == NORMAL.name() {
-        Some("NORMAL".into())
+        match normal_encoding {
+            PntsNormalEncoding::Float => Some("NORMAL".into()),
+            PntsNormalEncoding::Oct16P => Some("NORMAL_OCT16P".into()),
+        }
     } else {
         None
     }
@@ -64,6 +89,9 @@ fn pnts_semantics_name_from_point_attribute(
 ///    the `flush` call
 ///
 /// This `PntsWriter` implementation uses the second approach
+///
+/// All attributes that do not correspond to a point semantic are written as per-point properties into the binary body of
+/// the BatchTable, using the attribute name as property name
 pub struct PntsWriter<W: Write + Seek> {
     writer: W,
     expected_layout: PointLayout,
@@ -71,13 +99,17 @@ pub struct PntsWriter<W: Write + Seek> {
     cached_points: HashMapBuffer,
     attribute_converters: HashMap<String, Option<AttributeConversionFn>>,
     rtc_center: Option<Vector3<f64>>,
+    position_encoding: PntsPositionEncoding,
+    color_encoding: PntsColorEncoding,
+    normal_encoding: PntsNormalEncoding,
     requires_flush: bool,
 }
 
 impl<W: Write + Seek> PntsWriter<W> {
-    /// Creates a new `PntsWriter` writing to the given `writer` and using the given `point_layout`. Please note that
-    /// while 3D Tiles does in principle support arbitrary point attributes, currently only the default point semantics
-    /// are supported (see [3D Tiles specification](https://github.com/CesiumGS/3d-tiles/blob/master/specification/TileFormats/PointCloud/README.md#semantics)). All further attributes are simply ignored silently!
+    /// Creates a new `PntsWriter` writing to the given `writer` and using the given `point_layout`. Attributes matching the
+    /// default point semantics (see [3D Tiles specification](https://github.com/CesiumGS/3d-tiles/blob/master/specification/TileFormats/PointCloud/README.md#semantics))
+    /// are written into the FeatureTable, all further attributes are written into the BatchTable. Attributes whose datatype
+    /// can't be represented in a BatchTable (e.g. `ByteArray`) are silently ignored!
     pub fn from_write_and_layout(writer: W, point_layout: PointLayout) -> Self {
         // The PntsWriter can accept any kind of point buffer, but it will silently discard attributes that are not
         // supported by 3D Tiles. All supported attributes that are also in `point_layout` are described by `cache_layout`
@@ -90,6 +122,9 @@ impl<W: Write + Seek> PntsWriter<W> {
             cached_points: cache,
             attribute_converters,
             rtc_center: None,
+            position_encoding: Default::default(),
+            color_encoding: Default::default(),
+            normal_encoding: Default::default(),
             requires_flush: true,
         }
     }
@@ -101,20 +136,35 @@ impl<W: Write + Seek> PntsWriter<W> {
         self.rtc_center = Some(rtc_center);
     }
 
+    /// Sets the encoding for positions. With `PntsPositionEncoding::Quantized`, positions are written as `POSITION_QUANTIZED`
+    /// relative to the bounding box of all written points, which is stored in `QUANTIZED_VOLUME_OFFSET` and `QUANTIZED_VOLUME_SCALE`
+    pub fn set_position_encoding(&mut self, position_encoding: PntsPositionEncoding) {
+        self.position_encoding = position_encoding;
+    }
+
+    /// Sets the encoding for RGB colors. With `PntsColorEncoding::Rgb565`, colors are written as `RGB565`, which
+    /// drops the lowest bits of each color channel. RGBA colors are not affected by this setting
+    pub fn set_color_encoding(&mut self, color_encoding: PntsColorEncoding) {
+        self.color_encoding = color_encoding;
+    }
+
+    /// Sets the encoding for normals. With `PntsNormalEncoding::Oct16P`, normals are written as oct-encoded `NORMAL_OCT16P`
+    pub fn set_normal_encoding(&mut self, normal_encoding: PntsNormalEncoding) {
+        self.normal_encoding = normal_encoding;
+    }
+
     /// Makes the given `PointLayout` compatible with the supported point semantics of the 3D Tiles .pnts format. Doing
     /// so is done by iterating through the attributes in the `point_layout` and checking each attribute if it is one of
-    /// the supported point semantics. If not, it is discarded. Supported semantics are then converted to the default data
-    /// type as per the [3D Tiles standard](https://github.com/CesiumGS/3d-tiles/blob/master/specification/TileFormats/PointCloud/README.md#semantics)
+    /// the supported point semantics. Supported semantics are then converted to the default data type as per the
+    /// [3D Tiles standard](https://github.com/CesiumGS/3d-tiles/blob/master/specification/TileFormats/PointCloud/README.md#semantics).
+    /// All other attributes are converted to a datatype that the BatchTable supports, or discarded if there is none
     fn make_compatible_layout(
         point_layout: &PointLayout,
     ) -> (PointLayout, HashMap<String, Option<AttributeConversionFn>>) {
         let mut compatible_layout = PointLayout::default();
         let mut conversion_fns: HashMap<String, Option<AttributeConversionFn>> = HashMap::new();
-        // TODO Support for other attributes:
-        // * Quantized positions
-        // * RGB565 colors
-        // * Normal oct encoded
-        // * Batch ID (and batch table with custom attributes)
+        // Encoded attributes (quantized positions, RGB565 colors, oct-encoded normals) are cached in their default
+        // datatype and only encoded in `write_feature_table_body`
 
         let color_rgba = COLOR_RGBA;
         let supported_attributes: HashMap<&str, PointAttributeDataType> = vec![
@@ -127,11 +177,16 @@ impl<W: Write + Seek> PntsWriter<W> {
         .collect();
 
         for src_attribute in point_layout.attributes() {
-            if let Some(dst_attribute_datatype) = supported_attributes.get(&src_attribute.name()) {
+            let dst_attribute_datatype = match supported_attributes.get(&src_attribute.name()) {
+                Some(datatype) => Some(*datatype),
+                None => batch_table_type_from_datatype(src_attribute.datatype())
+                    .map(|(datatype, _, _)| datatype),
+            };
+            if let Some(dst_attribute_datatype) = dst_attribute_datatype {
                 compatible_layout.add_attribute(
                     PointAttributeDefinition::custom(
                         Cow::Owned(src_attribute.name().to_owned()),
-                        *dst_attribute_datatype,
+                        dst_attribute_datatype,
                     ),
                     FieldAlignment::Default,
                 );
@@ -186,8 +241,7 @@ impl<W: Write + Seek> PntsWriter<W> {
         )
         .context("Error serializing BatchTable header")?;
         let batch_table_byte_size = batch_table_blob.len();
-        //TODO Support batch table body
-        let batch_table_body_byte_size: usize = 0;
+        let batch_table_body_byte_size = self.calc_batch_table_body_length();
 
         let total_byte_length =
             start_of_batch_table_header + batch_table_byte_size + batch_table_body_byte_size;
@@ -220,7 +274,7 @@ impl<W: Write + Seek> PntsWriter<W> {
         self.writer
             .write(batch_table_blob.as_slice())
             .context("Error while writing BatchTable header")?;
-        // TODO Write BatchTable binary body. For now, it doesn't exist, so we don't have to write anything
+        self.write_batch_table_body()?;
 
         self.requires_flush = false;
 
@@ -229,25 +283,33 @@ impl<W: Write + Seek> PntsWriter<W> {
 
     fn create_feature_table(&self) -> FeatureTableHeader {
         let num_points = self.cached_points.len();
-        let cumulative_attribute_offsets = self
+        let semantic_attributes = self
             .default_layout
             .attributes()
+            .filter(|attribute| is_pnts_semantic(attribute.attribute_definition()))
+            .collect::<Vec<_>>();
+        let cumulative_attribute_offsets = semantic_attributes
+            .iter()
             .scan(0, |state, attribute| {
                 let ret = *state;
-                *state +=
-                    (attribute.size() as usize * num_points).align_to(PNTS_SEMANTICS_MAX_ALIGNMENT);
+                *state += (self.encoded_attribute_size(attribute.attribute_definition())
+                    * num_points)
+                    .align_to(PNTS_SEMANTICS_MAX_ALIGNMENT);
                 Some(ret)
             })
             .collect::<Vec<_>>();
 
-        let mut point_semantics = self
-            .default_layout
-            .attributes()
+        let mut point_semantics = semantic_attributes
+            .iter()
             .enumerate()
             .map(|(idx, attribute)| -> (String, FeatureTableValue) {
-                let semantic_name =
-                    pnts_semantics_name_from_point_attribute(attribute.attribute_definition())
-                        .expect("Invalid point semantic");
+                let semantic_name = pnts_semantics_name_from_point_attribute(
+                    attribute.attribute_definition(),
+                    self.position_encoding,
+                    self.color_encoding,
+                    self.normal_encoding,
+                )
+                .expect("Invalid point semantic");
                 (
                     semantic_name,
                     FeatureTableValue::DataReference(FeatureTableDataReference {
@@ -275,11 +337,124 @@ impl<W: Write + Seek> PntsWriter<W> {
             );
         }
 
+        if let Some((offset, scale)) = self.quantized_volume() {
+            point_semantics.insert(
+                "QUANTIZED_VOLUME_OFFSET".into(),
+                FeatureTableValue::Array(vec![json!(offset.x), json!(offset.y), json!(offset.z)]),
+            );
+            point_semantics.insert(
+                "QUANTIZED_VOLUME_SCALE".into(),
+                FeatureTableValue::Array(vec![json!(scale.x), json!(scale.y), json!(scale.z)]),
+            );
+        }
+
         point_semantics
     }
 
+    /// Returns the size in bytes of a single value of the given `attribute` in the FeatureTable binary body, taking the
+    /// encodings into account
+    fn encoded_attribute_size(&self, attribute: &PointAttributeDefinition) -> usize {
+        if attribute.name() == POSITION_3D.name()
+            && self.position_encoding == PntsPositionEncoding::Quantized
+        {
+            6
+        } else if (attribute.name() == COLOR_RGB.name()
+            && self.color_encoding == PntsColorEncoding::Rgb565)
+            || (attribute.name() == NORMAL.name()
+                && self.normal_encoding == PntsNormalEncoding::Oct16P)
+        {
+            // RGB565 is a single uint16, NORMAL_OCT16P are two uint8 values
+            2
+        } else {
+            attribute.size() as usize
+        }
+    }
+
+    /// Returns the `QUANTIZED_VOLUME_OFFSET` and `QUANTIZED_VOLUME_SCALE` if positions are quantized. The quantized volume is
+    /// the bounding box of all cached positions
+    fn quantized_volume(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        if self.position_encoding != PntsPositionEncoding::Quantized {
+            return None;
+        }
+        let position_attribute = self
+            .default_layout
+            .get_attribute_by_name(POSITION_3D.name())?;
+        let positions = self
+            .cached_points
+            .view_attribute::<Vector3<f32>>(position_attribute.attribute_definition());
+        let mut positions_iter = positions.into_iter();
+        let first_position = positions_iter.next().unwrap_or_else(Vector3::zeros);
+        let (min, max) = positions_iter
+            .fold((first_position, first_position), |(min, max), position| {
+                (min.inf(&position), max.sup(&position))
+            });
+        Some((min, max - min))
+    }
+
+    /// Encodes the data of the given `attribute` for all cached points into the format that is stored in the FeatureTable
+    /// binary body. Returns `None` if the attribute is stored without encoding
+    fn encode_attribute_data(&self, attribute: &PointAttributeDefinition) -> Option<Vec<u8>> {
+        if attribute.name() == POSITION_3D.name() {
+            if let Some((offset, scale)) = self.quantized_volume() {
+                return Some(
+                    self.cached_points
+                        .view_attribute::<Vector3<f32>>(attribute)
+                        .into_iter()
+                        .flat_map(|position| {
+                            quantize_position(&position, &offset, &scale)
+                                .iter()
+                                .flat_map(|component| component.to_le_bytes())
+                                .collect::<Vec<_>>()
+                        })
+                        .collect(),
+                );
+            }
+        } else if attribute.name() == COLOR_RGB.name()
+            && self.color_encoding == PntsColorEncoding::Rgb565
+        {
+            return Some(
+                self.cached_points
+                    .view_attribute::<Vector3<u8>>(attribute)
+                    .into_iter()
+                    .flat_map(|color| encode_rgb565(&color).to_le_bytes())
+                    .collect(),
+            );
+        } else if attribute.name() == NORMAL.name()
+            && self.normal_encoding == PntsNormalEncoding::Oct16P
+        {
+            return Some(
+                self.cached_points
+                    .view_attribute::<Vector3<f32>>(attribute)
+                    .into_iter()
+                    .flat_map(|normal| {
+                        let encoded = encode_oct16p(&normal);
+                        vec![encoded.x, encoded.y]
+                    })
+                    .collect(),
+            );
+        }
+        None
+    }
+
     fn create_batch_table(&self) -> BatchTableHeader {
-        Default::default()
+        let num_points = self.cached_points.len();
+        self.default_layout
+            .attributes()
+            .filter(|attribute| !is_pnts_semantic(attribute.attribute_definition()))
+            .scan(0, |byte_offset, attribute| {
+                let (_, component_type, scalar_or_vector_type) =
+                    batch_table_type_from_datatype(attribute.datatype())
+                        .expect("Invalid BatchTable datatype");
+                let entry = BatchTableEntry::DataReference(BatchTableDataReference {
+                    byte_offset: *byte_offset,
+                    component_type: component_type.into(),
+                    scalar_or_vector_type: scalar_or_vector_type.into(),
+                });
+                *byte_offset +=
+                    (attribute.size() as usize * num_points).align_to(PNTS_SEMANTICS_MAX_ALIGNMENT);
+                Some((attribute.name().to_owned(), entry))
+            })
+            .collect()
     }
 
     /// Calculate the length in bytes of the FeatureTable binary body. This is based on the default PointLayout
@@ -291,8 +466,10 @@ impl<W: Write + Seek> PntsWriter<W> {
         let num_points = self.cached_points.len();
         self.default_layout
             .attributes()
+            .filter(|attribute| is_pnts_semantic(attribute.attribute_definition()))
             .map(|attribute| {
-                (num_points * attribute.size() as usize).align_to(PNTS_SEMANTICS_MAX_ALIGNMENT)
+                (num_points * self.encoded_attribute_size(attribute.attribute_definition()))
+                    .align_to(PNTS_SEMANTICS_MAX_ALIGNMENT)
             })
             .sum()
     }
@@ -300,15 +477,23 @@ impl<W: Write + Seek> PntsWriter<W> {
     fn write_feature_table_body(&mut self) -> Result<()> {
         let num_points = self.cached_points.len();
 
-        for attribute in self.default_layout.attributes() {
-            let attribute_data = self
-                .cached_points
-                .get_attribute_range_ref(attribute.attribute_definition(), 0..num_points);
+        for attribute in self
+            .default_layout
+            .attributes()
+            .filter(|attribute| is_pnts_semantic(attribute.attribute_definition()))
+        {
+            let encoded_data = self.encode_attribute_data(attribute.attribute_definition());
+            let attribute_data = match &encoded_data {
+                Some(data) => data.as_slice(),
+                None => self
+                    .cached_points
+                    .get_attribute_range_ref(attribute.attribute_definition(), 0..num_points),
+            };
             self.writer
                 .write_all(attribute_data)
                 .context("Error while writing attribute data")?;
 
-            let blob_byte_size = attribute.size() as usize * self.cached_points.len();
+            let blob_byte_size = attribute_data.len();
             let num_padding_bytes =
                 blob_byte_size.align_to(PNTS_SEMANTICS_MAX_ALIGNMENT) - blob_byte_size;
             if num_padding_bytes != 0 {
@@ -330,6 +515,47 @@ impl<W: Write + Seek> PntsWriter<W> {
 
         Ok(())
     }
+
+    /// Calculate the length in bytes of the BatchTable binary body. Since every attribute is aligned to
+    /// PNTS_SEMANTICS_MAX_ALIGNMENT, the body always ends at an 8-byte boundary
+    fn calc_batch_table_body_length(&self) -> usize {
+        let num_points = self.cached_points.len();
+        self.default_layout
+            .attributes()
+            .filter(|attribute| !is_pnts_semantic(attribute.attribute_definition()))
+            .map(|attribute| {
+                (num_points * attribute.size() as usize).align_to(PNTS_SEMANTICS_MAX_ALIGNMENT)
+            })
+            .sum()
+    }
+
+    fn write_batch_table_body(&mut self) -> Result<()> {
+        let num_points = self.cached_points.len();
+
+        for attribute in self
+            .default_layout
+            .attributes()
+            .filter(|attribute| !is_pnts_semantic(attribute.attribute_definition()))
+        {
+            let attribute_data = self
+                .cached_points
+                .get_attribute_range_ref(attribute.attribute_definition(), 0..num_points);
+            self.writer
+                .write_all(attribute_data)
+                .context("Error while writing BatchTable attribute data")?;
+
+            let blob_byte_size = attribute_data.len();
+            let num_padding_bytes =
+                blob_byte_size.align_to(PNTS_SEMANTICS_MAX_ALIGNMENT) - blob_byte_size;
+            if num_padding_bytes != 0 {
+                self.writer
+                    .write_all(&vec![0; num_padding_bytes])
+                    .context("Error while writing padding bytes")?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<W: Write + Seek> PointWriter for PntsWriter<W> {
@@ -415,7 +641,10 @@ mod tests {
     use super::*;
     use pasture_core::{
         containers::VectorBuffer,
-        layout::PointType,
+        layout::{
+            attributes::{CLASSIFICATION, GPS_TIME, INTENSITY, POINT_ID},
+            PointType,
+        },
         nalgebra::{Vector3, Vector4},
     };
     use pasture_derive::PointType;
@@ -532,7 +761,7 @@ mod tests {
 
         cursor.seek(SeekFrom::Start(0))?;
 
-        // Read back in, data read should equal data written, but missing the intensity
+        // Read back in, data read should equal data written, with the intensity stored in the BatchTable
         {
             let mut reader =
                 PntsReader::from_read(&mut cursor).context("Error while creating PntsReader")?;
@@ -544,11 +773,20 @@ mod tests {
                 &[
                     POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f32),
                     COLOR_RGB.with_custom_datatype(PointAttributeDataType::Vec3u8),
+                    INTENSITY,
                 ],
                 1,
             );
             assert_eq!(read_points_layout, *read_points.point_layout());
 
+            assert_eq!(
+                vec![10_000_u16, 20_000],
+                read_points
+                    .view_attribute::<u16>(&INTENSITY)
+                    .into_iter()
+                    .collect::<Vec<_>>()
+            );
+
             assert_eq!(read_points.len(), test_point_buffer.len());
 
             let expected_pos_1: Vector3<f32> = Vector3::new(1.0, 2.0, 3.0);
@@ -592,6 +830,182 @@ mod tests {
             );
         }
 
+        Ok(())
+    }
+    #[test]
+    fn test_write_pnts_encoded_attributes() -> Result<()> {
+        let mut cursor = Cursor::new(Vec::<u8>::new());
+
+        let test_data = [
+            PntsDefaultPoint {
+                position: Vector3::new(1.0, 2.0, 3.0),
+                color: Vector3::new(0xF8, 0xFC, 0xF8),
+                color_rgba: Vector4::new(11, 21, 31, 41),
+                normal: Vector3::new(0.0, 0.0, 1.0),
+            },
+            PntsDefaultPoint {
+                position: Vector3::new(11.0, 22.0, 33.0),
+                color: Vector3::new(0x08, 0x04, 0x00),
+                color_rgba: Vector4::new(22, 44, 66, 88),
+                normal: Vector3::new(0.6, 0.0, -0.8),
+            },
+            PntsDefaultPoint {
+                position: Vector3::new(6.0, 12.0, 18.0),
+                color: Vector3::new(0x80, 0x40, 0x20),
+                color_rgba: Vector4::new(33, 66, 99, 132),
+                normal: Vector3::new(0.0, -1.0, 0.0),
+            },
+        ];
+        let test_point_buffer = test_data.iter().copied().collect::<HashMapBuffer>();
+
+        {
+            let mut writer =
+                PntsWriter::from_write_and_layout(&mut cursor, PntsDefaultPoint::layout());
+            writer.set_position_encoding(PntsPositionEncoding::Quantized);
+            writer.set_color_encoding(PntsColorEncoding::Rgb565);
+            writer.set_normal_encoding(PntsNormalEncoding::Oct16P);
+
+            writer
+                .write(&test_point_buffer)
+                .context("Error while writing points to PntsWriter")?;
+        }
+
+        cursor.seek(SeekFrom::Start(0))?;
+
+        {
+            let mut reader =
+                PntsReader::from_read(&mut cursor).context("Error while creating PntsReader")?;
+            let quantized_volume_offset = reader
+                .get_metadata()
+                .get_named_field("QUANTIZED_VOLUME_OFFSET")
+                .and_then(|v| v.downcast::<Vector3<f32>>().ok());
+            assert_eq!(
+                Some(Vector3::new(1.0, 2.0, 3.0)),
+                quantized_volume_offset.map(|v| *v)
+            );
+            let quantized_volume_scale = reader
+                .get_metadata()
+                .get_named_field("QUANTIZED_VOLUME_SCALE")
+                .and_then(|v| v.downcast::<Vector3<f32>>().ok());
+            assert_eq!(
+                Some(Vector3::new(10.0, 20.0, 30.0)),
+                quantized_volume_scale.map(|v| *v)
+            );
+
+            let mut read_points =
+                VectorBuffer::with_capacity(test_point_buffer.len(), PntsDefaultPoint::layout());
+            read_points.resize(test_point_buffer.len());
+            reader
+                .read_into(&mut read_points, test_point_buffer.len())
+                .context("Error while reading points from PntsReader")?;
+
+            for (expected_point, actual_point) in
+                test_data.iter().zip(read_points.view::<PntsDefaultPoint>())
+            {
+                let expected_position = expected_point.position;
+                let actual_position = actual_point.position;
+                assert!((expected_position - actual_position).amax() < 1e-3);
+
+                // The test colors are exactly representable in RGB565, the low bits of the decoded colors are
+                // filled by bit replication
+                let expected_color = expected_point.color;
+                let actual_color = actual_point.color;
+                let expected_color_rgb565 =
+                    crate::tiles3d::decode_rgb565(crate::tiles3d::encode_rgb565(&expected_color));
+                assert_eq!(expected_color_rgb565, actual_color);
+
+                assert_eq!(expected_point.color_rgba, actual_point.color_rgba);
+
+                let expected_normal = expected_point.normal;
+                let actual_normal = actual_point.normal;
+                assert!(expected_normal.dot(&actual_normal) > 0.999);
+            }
+        }
+
+        Ok(())
+    }
+    #[derive(
+        Debug, PointType, Copy, Clone, PartialEq, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    #[repr(C, packed)]
+    struct PntsBatchTablePoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f32>,
+        #[pasture(BUILTIN_CLASSIFICATION)]
+        classification: u8,
+        #[pasture(BUILTIN_GPS_TIME)]
+        gps_time: f64,
+        #[pasture(BUILTIN_POINT_ID)]
+        point_id: u64,
+        #[pasture(attribute = "Custom")]
+        custom: Vector3<i32>,
+    }
+
+    #[test]
+    fn test_write_pnts_batch_table() -> Result<()> {
+        let mut cursor = Cursor::new(Vec::<u8>::new());
+
+        let test_data = vec![
+            PntsBatchTablePoint {
+                position: Vector3::new(1.0, 2.0, 3.0),
+                classification: 2,
+                gps_time: 1234.5,
+                point_id: 42,
+                custom: Vector3::new(-1, 0, 1),
+            },
+            PntsBatchTablePoint {
+                position: Vector3::new(2.0, 4.0, 6.0),
+                classification: 6,
+                gps_time: 2345.5,
+                point_id: 43,
+                custom: Vector3::new(-2, 0, 2),
+            },
+            PntsBatchTablePoint {
+                position: Vector3::new(3.0, 6.0, 9.0),
+                classification: 9,
+                gps_time: 3456.5,
+                point_id: 44,
+                custom: Vector3::new(-3, 0, 3),
+            },
+        ];
+        let test_point_buffer = test_data.iter().copied().collect::<VectorBuffer>();
+
+        {
+            let mut writer =
+                PntsWriter::from_write_and_layout(&mut cursor, PntsBatchTablePoint::layout());
+            writer
+                .write(&test_point_buffer)
+                .context("Error while writing points to PntsWriter")?;
+        }
+
+        assert_eq!(0, cursor.get_ref().len() % 8);
+        cursor.seek(SeekFrom::Start(0))?;
+
+        {
+            let mut reader =
+                PntsReader::from_read(&mut cursor).context("Error while creating PntsReader")?;
+            let default_layout = reader.get_default_point_layout().clone();
+            assert!(default_layout.has_attribute(&CLASSIFICATION));
+            assert!(default_layout.has_attribute(&GPS_TIME));
+            // 64-bit integers are stored as DOUBLE in the BatchTable
+            assert!(default_layout
+                .has_attribute(&POINT_ID.with_custom_datatype(PointAttributeDataType::F64)));
+
+            // Reading into the original layout converts the point IDs back to u64
+            let mut read_points =
+                VectorBuffer::with_capacity(test_data.len(), PntsBatchTablePoint::layout());
+            read_points.resize(test_data.len());
+            reader
+                .read_into(&mut read_points, test_data.len())
+                .context("Error while reading points from PntsReader")?;
+
+            let actual_points = read_points
+                .view::<PntsBatchTablePoint>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            assert_eq!(test_data, actual_points);
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,979 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, BorrowedMutBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer,
+        VectorBuffer,
+    },
+    layout::{attributes::POSITION_3D, PointAttributeDataType, PointLayout},
+    math::AABB,
+    nalgebra::{Matrix4, Point3, Vector3},
+};
+
+use crate::base::{GenericPointReader, GridSampler, PointReader, PointWriter};
+
+use super::{
+    east_north_up_to_ecef, ecef_to_geodetic, BoundingBox, BoundingRegion, BoundingVolume,
+    GlbWriter, PntsWriter, Refinement, RootTileset, Tileset, TilesetAssetInfo, TilesetBuilder,
+};
+
+/// Number of grid cells along each axis of a tile that are used for sampling the points of the tile
+const GRID_SIZE: i64 = 64;
+/// Maximum depth of the octree. All remaining points of a tile at this depth are stored in the tile itself
+const MAX_DEPTH: u32 = 24;
+/// Number of points that are read at once, both from the input readers and from temporary files
+const POINTS_PER_CHUNK: usize = 1 << 16;
+/// Name of the directory within the output directory that holds temporary files during tiling
+const TEMPORARY_DIRECTORY_NAME: &str = ".pasture_tiler";
+
+/// The type of bounding volume that the `PntsTiler` writes for each tile
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TilerBoundingVolume {
+    /// Axis-aligned bounding boxes of the points in each tile
+    #[default]
+    Box,
+    /// Bounding regions (longitude, latitude and height on the WGS84 ellipsoid). The positions of the points are
+    /// converted into earth-centered, earth-fixed (ECEF) coordinates first if `PntsTiler::with_local_origin` is set
+    Region,
+}
+
+/// The format of the content files that the `PntsTiler` writes for each tile
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TilerContentFormat {
+    /// Point cloud files (`.pnts`), written using the `PntsWriter`
+    #[default]
+    Pnts,
+    /// Binary glTF files (`.glb`) with structural metadata, written using the `GlbWriter`. Tilesets with this content
+    /// format use 3D Tiles version 1.1
+    Glb,
+}
+
+impl TilerContentFormat {
+    /// The file extension of content files in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TilerContentFormat::Pnts => "pnts",
+            TilerContentFormat::Glb => "glb",
+        }
+    }
+}
+
+/// Key of a tile within the octree of the `PntsTiler`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct TileKey {
+    level: u32,
+    x: u64,
+    y: u64,
+    z: u64,
+}
+
+impl TileKey {
+    const ROOT: TileKey = TileKey {
+        level: 0,
+        x: 0,
+        y: 0,
+        z: 0,
+    };
+
+    fn child(&self, child_index: usize) -> TileKey {
+        TileKey {
+            level: self.level + 1,
+            x: (self.x << 1) | (child_index & 1) as u64,
+            y: (self.y << 1) | ((child_index >> 1) & 1) as u64,
+            z: (self.z << 1) | ((child_index >> 2) & 1) as u64,
+        }
+    }
+
+    /// Returns the bounds of this tile, given the cubic bounds of the root tile
+    fn bounds(&self, root_bounds: &AABB<f64>) -> AABB<f64> {
+        let tile_size = root_bounds.extent().x / (1_u64 << self.level) as f64;
+        let min = root_bounds.min()
+            + Vector3::new(self.x as f64, self.y as f64, self.z as f64) * tile_size;
+        AABB::from_min_max_unchecked(min, min + Vector3::new(tile_size, tile_size, tile_size))
+    }
+
+    /// Name of the tile, which is used for the file names of the tile content
+    fn name(&self) -> String {
+        format!("{}-{}-{}-{}", self.level, self.x, self.y, self.z)
+    }
+}
+
+/// Points of a single tile during tiling. Points are kept in memory if there are few enough of them, and stored in a
+/// temporary file otherwise
+enum TilerPoints {
+    InMemory(VectorBuffer),
+    OnDisk { path: PathBuf, count: usize },
+}
+
+impl TilerPoints {
+    fn len(&self) -> usize {
+        match self {
+            TilerPoints::InMemory(points) => points.len(),
+            TilerPoints::OnDisk { count, .. } => *count,
+        }
+    }
+
+    /// Loads all points into memory, removing the temporary file if there is one
+    fn into_memory(self, point_layout: &PointLayout) -> Result<VectorBuffer> {
+        match self {
+            TilerPoints::InMemory(points) => Ok(points),
+            TilerPoints::OnDisk { path, count } => {
+                let mut points = VectorBuffer::with_capacity(count, point_layout.clone());
+                let mut reader = BufReader::new(File::open(&path)?);
+                let mut chunk = VectorBuffer::new_from_layout(point_layout.clone());
+                while read_chunk_from_file(&mut reader, &mut chunk)? {
+                    points.append_interleaved(&chunk);
+                }
+                fs::remove_file(&path)?;
+                Ok(points)
+            }
+        }
+    }
+}
+
+/// Destination for the points of a tile during tiling, which becomes `TilerPoints` once all points are pushed
+enum TilerPointsSink {
+    InMemory(VectorBuffer),
+    OnDisk {
+        path: PathBuf,
+        writer: BufWriter<File>,
+        count: usize,
+    },
+}
+
+impl TilerPointsSink {
+    fn push_point(&mut self, point: &[u8]) -> Result<()> {
+        match self {
+            // Safe because `point` is exactly one point in the layout of the buffer
+            TilerPointsSink::InMemory(points) => unsafe { points.push_points(point) },
+            TilerPointsSink::OnDisk { writer, count, .. } => {
+                writer.write_all(point)?;
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<TilerPoints> {
+        match self {
+            TilerPointsSink::InMemory(points) => Ok(TilerPoints::InMemory(points)),
+            TilerPointsSink::OnDisk {
+                path,
+                writer,
+                count,
+            } => {
+                writer.into_inner().map_err(|e| e.into_error())?;
+                Ok(TilerPoints::OnDisk { path, count })
+            }
+        }
+    }
+}
+
+/// Reads the next chunk of points from a temporary file into `chunk`. Returns `false` if there are no more points
+fn read_chunk_from_file<R: Read>(reader: &mut R, chunk: &mut VectorBuffer) -> Result<bool> {
+    let size_of_point = chunk.point_layout().size_of_point_entry() as usize;
+    let mut bytes = Vec::with_capacity(POINTS_PER_CHUNK * size_of_point);
+    reader
+        .take((POINTS_PER_CHUNK * size_of_point) as u64)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() % size_of_point != 0 {
+        bail!("Temporary point file is truncated");
+    }
+    chunk.clear();
+    // Safe because `bytes` contains a whole number of points in the layout of `chunk`
+    unsafe {
+        chunk.push_points(&bytes);
+    }
+    Ok(!chunk.is_empty())
+}
+
+/// Returns the positions of all points in `points` as `f64` values
+fn positions_of(points: &VectorBuffer) -> Result<Vec<Point3<f64>>> {
+    Ok(points
+        .view_attribute_with_conversion::<Vector3<f64>>(&POSITION_3D)?
+        .into_iter()
+        .map(Point3::from)
+        .collect())
+}
+
+/// Bounds of the points within a tile (and all its child tiles)
+#[derive(Copy, Clone, Debug)]
+struct TileBounds {
+    bounds: AABB<f64>,
+    /// Minimum and maximum of longitude, latitude and height, only calculated for `TilerBoundingVolume::Region`
+    geodetic_bounds: Option<AABB<f64>>,
+}
+
+impl TileBounds {
+    /// Calculates the bounds of the given `positions`. `local_to_ecef` is the transformation of the positions into
+    /// earth-centered, earth-fixed coordinates, which is needed for the geodetic bounds
+    fn from_positions(
+        positions: &[Point3<f64>],
+        bounding_volume: TilerBoundingVolume,
+        local_to_ecef: Option<&Matrix4<f64>>,
+    ) -> Self {
+        let bounds_of = |points: &mut dyn Iterator<Item = Point3<f64>>| -> AABB<f64> {
+            let first = points.next().expect("Tile must not be empty");
+            points.fold(
+                AABB::from_min_max_unchecked(first, first),
+                |bounds, point| AABB::extend_with_point(&bounds, &point),
+            )
+        };
+        let geodetic_bounds = match bounding_volume {
+            TilerBoundingVolume::Box => None,
+            TilerBoundingVolume::Region => Some(bounds_of(
+                &mut positions
+                    .iter()
+                    .map(|position| match local_to_ecef {
+                        Some(local_to_ecef) => local_to_ecef.transform_point(position),
+                        None => *position,
+                    })
+                    .map(|position| Point3::from(ecef_to_geodetic(&position))),
+            )),
+        };
+        Self {
+            bounds: bounds_of(&mut positions.iter().copied()),
+            geodetic_bounds,
+        }
+    }
+
+    fn union(&self, other: &TileBounds) -> Self {
+        Self {
+            bounds: AABB::union(&self.bounds, &other.bounds),
+            geodetic_bounds: self
+                .geodetic_bounds
+                .zip(other.geodetic_bounds)
+                .map(|(a, b)| AABB::union(&a, &b)),
+        }
+    }
+
+    fn bounding_volume(&self) -> BoundingVolume {
+        match self.geodetic_bounds {
+            Some(geodetic_bounds) => BoundingVolume::Region(BoundingRegion::new(
+                geodetic_bounds.min().x,
+                geodetic_bounds.min().y,
+                geodetic_bounds.max().x,
+                geodetic_bounds.max().y,
+                geodetic_bounds.min().z,
+                geodetic_bounds.max().z,
+            )),
+            None => BoundingVolume::Box(BoundingBox::from(&self.bounds)),
+        }
+    }
+}
+
+/// Points read from all input readers, together with their bounds
+struct IngestedPoints {
+    point_layout: PointLayout,
+    sink: TilerPointsSink,
+    bounds: Option<AABB<f64>>,
+}
+
+/// Out-of-core tiler that builds a complete 3D Tiles tileset from a point cloud of arbitrary size. The points are
+/// sorted into an octree, where each tile stores a subsample of its points (one point per cell of a regular grid) and
+/// passes the remaining points on to its child tiles. Each tile is written as a `.pnts` file using the `PntsWriter`
+/// (or as a `.glb` file using the `GlbWriter`, see `with_content_format`), relative to the center of the tile, and all
+/// tiles are referenced from a `tileset.json` file with additive refinement. Attributes other than the point semantics
+/// are written into the BatchTable (or the property table) of each tile.
+///
+/// Points that don't fit into memory (see `with_max_points_in_memory`) are stored in temporary files within the output
+/// directory, so datasets that are much larger than the main memory can be tiled.
+///
+/// 3D Tiles places all tilesets on the WGS84 ellipsoid, so by default the input positions are expected to be in
+/// earth-centered, earth-fixed coordinates (ECEF, EPSG:4978). Point clouds in a projected or local coordinate system
+/// in meters can be placed on the globe using `with_local_origin`, which writes a root transform from a local
+/// east-north-up coordinate system into ECEF coordinates. Other coordinate systems have to be reprojected before
+/// tiling. In both cases, the content of each tile is stored relative to the center of the tile (`RTC_CENTER`), so that
+/// the positions keep their precision as 32-bit floating point values.
+///
+/// ```no_run
+/// # use pasture_io::tiles3d::PntsTiler;
+/// # fn main() -> anyhow::Result<()> {
+/// let tiler = PntsTiler::new("tileset_directory").with_max_points_per_tile(50_000);
+/// tiler.tile_files(&["first.las", "second.las"])?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PntsTiler {
+    output_directory: PathBuf,
+    max_points_per_tile: usize,
+    max_points_in_memory: usize,
+    bounding_volume: TilerBoundingVolume,
+    content_format: TilerContentFormat,
+    point_layout: Option<PointLayout>,
+    local_to_ecef: Option<Matrix4<f64>>,
+}
+
+impl PntsTiler {
+    /// The default maximum number of points in a tile before the tile is subdivided
+    pub const DEFAULT_MAX_POINTS_PER_TILE: usize = 100_000;
+    /// The default maximum number of points that the `PntsTiler` keeps in memory at once
+    pub const DEFAULT_MAX_POINTS_IN_MEMORY: usize = 5_000_000;
+
+    /// Creates a new `PntsTiler` that writes the tileset into the given `output_directory`. The directory is created if
+    /// it does not exist
+    pub fn new<P: AsRef<Path>>(output_directory: P) -> Self {
+        Self {
+            output_directory: output_directory.as_ref().to_owned(),
+            max_points_per_tile: Self::DEFAULT_MAX_POINTS_PER_TILE,
+            max_points_in_memory: Self::DEFAULT_MAX_POINTS_IN_MEMORY,
+            bounding_volume: Default::default(),
+            content_format: Default::default(),
+            point_layout: None,
+            local_to_ecef: None,
+        }
+    }
+
+    /// Sets the maximum number of points in a tile. Tiles with more points are subdivided
+    pub fn with_max_points_per_tile(mut self, max_points_per_tile: usize) -> Self {
+        self.max_points_per_tile = max_points_per_tile;
+        self
+    }
+
+    /// Sets the maximum number of points that are kept in memory at once. All further points are stored in temporary files
+    pub fn with_max_points_in_memory(mut self, max_points_in_memory: usize) -> Self {
+        self.max_points_in_memory = max_points_in_memory;
+        self
+    }
+
+    /// Sets the type of bounding volume that is written for each tile
+    pub fn with_bounding_volume(mut self, bounding_volume: TilerBoundingVolume) -> Self {
+        self.bounding_volume = bounding_volume;
+        self
+    }
+
+    /// Sets the format of the content files of the tiles
+    pub fn with_content_format(mut self, content_format: TilerContentFormat) -> Self {
+        self.content_format = content_format;
+        self
+    }
+
+    /// Sets the `PointLayout` of the points in the tiles. By default, the default `PointLayout` of the first input reader
+    /// is used. The layout must contain the `POSITION_3D` attribute
+    pub fn with_point_layout(mut self, point_layout: PointLayout) -> Self {
+        self.point_layout = Some(point_layout);
+        self
+    }
+
+    /// Places the point cloud on the globe by interpreting the input positions as coordinates in meters within a local
+    /// east-north-up coordinate system, whose origin is at the given `longitude` and `latitude` (both in radians) and
+    /// `height` (in meters) on the WGS84 ellipsoid. The transformation from this coordinate system into
+    /// earth-centered, earth-fixed coordinates is written as the `transform` of the root tile. Without a local origin,
+    /// the input positions have to be earth-centered, earth-fixed coordinates
+    pub fn with_local_origin(mut self, longitude: f64, latitude: f64, height: f64) -> Self {
+        self.local_to_ecef = Some(east_north_up_to_ecef(&Vector3::new(
+            longitude, latitude, height,
+        )));
+        self
+    }
+
+    /// Builds a tileset from all points in the given `reader`. Returns the root tileset, which is also written to the
+    /// `tileset.json` file in the output directory
+    pub fn tile<R: PointReader>(&self, reader: &mut R) -> Result<RootTileset> {
+        self.with_temporary_directory(|temporary_directory| {
+            let mut ingested_points = None;
+            self.ingest(reader, &mut ingested_points, temporary_directory)?;
+            self.build_tileset(ingested_points, temporary_directory)
+        })
+    }
+
+    /// Builds a single tileset from all points of all the given `readers`
+    pub fn tile_readers<R: PointReader, I: IntoIterator<Item = R>>(
+        &self,
+        readers: I,
+    ) -> Result<RootTileset> {
+        self.with_temporary_directory(|temporary_directory| {
+            let mut ingested_points = None;
+            for mut reader in readers {
+                self.ingest(&mut reader, &mut ingested_points, temporary_directory)?;
+            }
+            self.build_tileset(ingested_points, temporary_directory)
+        })
+    }
+
+    /// Builds a single tileset from all points in the given files. The format of each file is determined by its extension
+    /// and content, see [`GenericPointReader`]
+    pub fn tile_files<P: AsRef<Path>, I: IntoIterator<Item = P>>(
+        &self,
+        paths: I,
+    ) -> Result<RootTileset> {
+        self.with_temporary_directory(|temporary_directory| {
+            let mut ingested_points = None;
+            for path in paths {
+                let mut reader =
+                    GenericPointReader::open_file(path.as_ref()).with_context(|| {
+                        format!(
+                            "Could not open point cloud file {}",
+                            path.as_ref().display()
+                        )
+                    })?;
+                self.ingest(&mut reader, &mut ingested_points, temporary_directory)
+                    .with_context(|| {
+                        format!(
+                            "Could not read point cloud file {}",
+                            path.as_ref().display()
+                        )
+                    })?;
+            }
+            self.build_tileset(ingested_points, temporary_directory)
+        })
+    }
+
+    /// Creates the output directory and the temporary directory within it, runs `func` and removes the temporary
+    /// directory again
+    fn with_temporary_directory<F: FnOnce(&Path) -> Result<RootTileset>>(
+        &self,
+        func: F,
+    ) -> Result<RootTileset> {
+        let temporary_directory = self.output_directory.join(TEMPORARY_DIRECTORY_NAME);
+        fs::create_dir_all(&temporary_directory).with_context(|| {
+            format!(
+                "Could not create output directory {}",
+                self.output_directory.display()
+            )
+        })?;
+        let result = func(&temporary_directory);
+        fs::remove_dir_all(&temporary_directory).with_context(|| {
+            format!(
+                "Could not remove temporary directory {}",
+                temporary_directory.display()
+            )
+        })?;
+        result
+    }
+
+    /// Reads all points from `reader` and adds them to `ingested_points`. Points are kept in memory until there are more
+    /// than `max_points_in_memory` points, after which all points are moved to a temporary file
+    fn ingest<R: PointReader>(
+        &self,
+        reader: &mut R,
+        ingested_points: &mut Option<IngestedPoints>,
+        temporary_directory: &Path,
+    ) -> Result<()> {
+        let ingested = ingested_points.get_or_insert_with(|| {
+            let point_layout = self
+                .point_layout
+                .clone()
+                .unwrap_or_else(|| reader.get_default_point_layout().clone());
+            IngestedPoints {
+                sink: TilerPointsSink::InMemory(VectorBuffer::new_from_layout(
+                    point_layout.clone(),
+                )),
+                point_layout,
+                bounds: None,
+            }
+        });
+        if !ingested
+            .point_layout
+            .has_attribute_with_name(POSITION_3D.name())
+        {
+            bail!("PointLayout for tiling must contain the POSITION_3D attribute");
+        }
+
+        let total_points = reader.get_metadata().number_of_points();
+        let mut points_read = 0;
+        let mut chunk = VectorBuffer::new_from_layout(ingested.point_layout.clone());
+        loop {
+            let points_to_read = match total_points {
+                Some(total_points) => POINTS_PER_CHUNK.min(total_points - points_read),
+                None => POINTS_PER_CHUNK,
+            };
+            if points_to_read == 0 {
+                break;
+            }
+            chunk.resize(points_to_read);
+            let num_read = reader.read_into(&mut chunk, points_to_read)?;
+            if num_read == 0 {
+                break;
+            }
+            chunk.resize(num_read);
+            points_read += num_read;
+
+            for position in positions_of(&chunk)? {
+                ingested.bounds = Some(match &ingested.bounds {
+                    Some(bounds) => AABB::extend_with_point(bounds, &position),
+                    None => AABB::from_min_max_unchecked(position, position),
+                });
+            }
+
+            if let TilerPointsSink::InMemory(points) = &mut ingested.sink {
+                if points.len() + chunk.len() > self.max_points_in_memory {
+                    let path = temporary_directory.join("root.bin");
+                    let mut writer = BufWriter::new(File::create(&path)?);
+                    writer.write_all(points.get_point_range_ref(0..points.len()))?;
+                    ingested.sink = TilerPointsSink::OnDisk {
+                        path,
+                        writer,
+                        count: points.len(),
+                    };
+                }
+            }
+            for point_index in 0..chunk.len() {
+                ingested.sink.push_point(chunk.get_point_ref(point_index))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the octree from the ingested points, writes all tiles and the `tileset.json` file
+    fn build_tileset(
+        &self,
+        ingested_points: Option<IngestedPoints>,
+        temporary_directory: &Path,
+    ) -> Result<RootTileset> {
+        let ingested = ingested_points.ok_or_else(|| anyhow!("No point cloud to tile"))?;
+        let bounds = match ingested.bounds {
+            Some(bounds) => bounds,
+            None => bail!("Point cloud to tile contains no points"),
+        };
+        // Make sure that the root bounds have a non-zero extent, even if all points are at the same position
+        let root_bounds = AABB::from_min_max_unchecked(
+            *bounds.min(),
+            bounds.max() + Vector3::new(f64::EPSILON, f64::EPSILON, f64::EPSILON),
+        )
+        .as_cubic();
+
+        let root_points = ingested.sink.finish()?;
+        let (mut root, _) = self.build_tile(
+            TileKey::ROOT,
+            root_points,
+            &root_bounds,
+            &ingested.point_layout,
+            temporary_directory,
+        )?;
+        root.transform = self.local_to_ecef;
+
+        let root_tileset = RootTileset {
+            geometric_error: root_bounds.extent().x,
+            root,
+            asset: TilesetAssetInfo {
+                version: match self.content_format {
+                    TilerContentFormat::Pnts => "1.0".into(),
+                    TilerContentFormat::Glb => "1.1".into(),
+                },
+                tileset_version: None,
+            },
+            ..Default::default()
+        };
+        let tileset_path = self.output_directory.join("tileset.json");
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&tileset_path)?), &root_tileset)
+            .with_context(|| format!("Could not write {}", tileset_path.display()))?;
+        Ok(root_tileset)
+    }
+
+    /// Builds the tile with the given `key` from the given `points`, including all child tiles. Returns the `Tileset` for
+    /// the tile together with the bounds of all its points
+    fn build_tile(
+        &self,
+        key: TileKey,
+        points: TilerPoints,
+        root_bounds: &AABB<f64>,
+        point_layout: &PointLayout,
+        temporary_directory: &Path,
+    ) -> Result<(Tileset, TileBounds)> {
+        let points = if points.len() <= self.max_points_in_memory {
+            TilerPoints::InMemory(points.into_memory(point_layout)?)
+        } else {
+            points
+        };
+
+        let tile_bounds = key.bounds(root_bounds);
+        let is_leaf = points.len() <= self.max_points_per_tile || key.level >= MAX_DEPTH;
+        let (tile_points, children_points) = if is_leaf {
+            (points.into_memory(point_layout)?, vec![])
+        } else {
+            self.sample_points(key, points, &tile_bounds, point_layout, temporary_directory)?
+        };
+
+        let mut bounds = self.write_tile(key, tile_points, &tile_bounds)?;
+        let mut children = vec![];
+        for (child_key, child_points) in children_points {
+            let (child, child_bounds) = self.build_tile(
+                child_key,
+                child_points,
+                root_bounds,
+                point_layout,
+                temporary_directory,
+            )?;
+            children.push(child);
+            bounds = bounds.union(&child_bounds);
+        }
+
+        // The geometric error of a tile is the spacing of its sampling grid, as this is the error that is made when rendering
+        // only this tile but none of its children
+        let geometric_error = if children.is_empty() {
+            0.0
+        } else {
+            tile_bounds.extent().x / GRID_SIZE as f64
+        };
+        let mut builder = TilesetBuilder::default()
+            .bounding_volume(bounds.bounding_volume())
+            .geometric_error(geometric_error)
+            .content(
+                format!("{}.{}", key.name(), self.content_format.extension()),
+                None,
+            )
+            .add_children(children);
+        if key == TileKey::ROOT {
+            builder = builder.refinement(Refinement::Add);
+        }
+        Ok((builder.into(), bounds))
+    }
+
+    /// Samples the given `points` of the tile with the given `key`. Returns the points that are stored in the tile itself,
+    /// together with the points of all non-empty child tiles
+    fn sample_points(
+        &self,
+        key: TileKey,
+        points: TilerPoints,
+        tile_bounds: &AABB<f64>,
+        point_layout: &PointLayout,
+        temporary_directory: &Path,
+    ) -> Result<(VectorBuffer, Vec<(TileKey, TilerPoints)>)> {
+        let mut children_sinks = (0..8)
+            .map(|child_index| -> Result<TilerPointsSink> {
+                match &points {
+                    TilerPoints::InMemory(_) => Ok(TilerPointsSink::InMemory(
+                        VectorBuffer::new_from_layout(point_layout.clone()),
+                    )),
+                    TilerPoints::OnDisk { .. } => {
+                        let path = temporary_directory
+                            .join(format!("{}.bin", key.child(child_index).name()));
+                        Ok(TilerPointsSink::OnDisk {
+                            writer: BufWriter::new(File::create(&path)?),
+                            path,
+                            count: 0,
+                        })
+                    }
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut sampler = GridSampler::new(tile_bounds, GRID_SIZE);
+        let mut tile_points = VectorBuffer::new_from_layout(point_layout.clone());
+        let mut sample_chunk = |chunk: &VectorBuffer| -> Result<()> {
+            for (point_index, position) in positions_of(chunk)?.iter().enumerate() {
+                let point = chunk.get_point_ref(point_index);
+                match sampler.sample(position) {
+                    // Safe because `point` is exactly one point in the layout of `tile_points`
+                    None => unsafe {
+                        tile_points.push_points(point);
+                    },
+                    Some(octant) => {
+                        let child_index = octant.x as usize
+                            | ((octant.y as usize) << 1)
+                            | ((octant.z as usize) << 2);
+                        children_sinks[child_index].push_point(point)?;
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        match points {
+            TilerPoints::InMemory(points) => sample_chunk(&points)?,
+            TilerPoints::OnDisk { path, .. } => {
+                let mut reader = BufReader::new(File::open(&path)?);
+                let mut chunk = VectorBuffer::new_from_layout(point_layout.clone());
+                while read_chunk_from_file(&mut reader, &mut chunk)? {
+                    sample_chunk(&chunk)?;
+                }
+                fs::remove_file(&path)?;
+            }
+        }
+
+        let children_points = children_sinks
+            .into_iter()
+            .enumerate()
+            .map(|(child_index, sink)| -> Result<(TileKey, TilerPoints)> {
+                Ok((key.child(child_index), sink.finish()?))
+            })
+            .filter(|child| !matches!(child, Ok((_, child_points)) if child_points.len() == 0))
+            .collect::<Result<Vec<_>>>()?;
+        Ok((tile_points, children_points))
+    }
+
+    /// Writes the given `points` as the content file of the tile with the given `key`, relative to the center of the tile.
+    /// Returns the bounds of the points
+    fn write_tile(
+        &self,
+        key: TileKey,
+        mut points: VectorBuffer,
+        tile_bounds: &AABB<f64>,
+    ) -> Result<TileBounds> {
+        let positions = positions_of(&points)?;
+        let bounds = TileBounds::from_positions(
+            &positions,
+            self.bounding_volume,
+            self.local_to_ecef.as_ref(),
+        );
+
+        let center = tile_bounds.center().coords;
+        let position_attribute = points
+            .point_layout()
+            .get_attribute_by_name(POSITION_3D.name())
+            .expect("PointLayout must contain POSITION_3D")
+            .attribute_definition()
+            .clone();
+        match position_attribute.datatype() {
+            PointAttributeDataType::Vec3f32 => points.transform_attribute(
+                &position_attribute,
+                |_, position: Vector3<f32>| -> Vector3<f32> {
+                    Vector3::new(
+                        (position.x as f64 - center.x) as f32,
+                        (position.y as f64 - center.y) as f32,
+                        (position.z as f64 - center.z) as f32,
+                    )
+                },
+            ),
+            PointAttributeDataType::Vec3f64 => points.transform_attribute(
+                &position_attribute,
+                |_, position: Vector3<f64>| -> Vector3<f64> { position - center },
+            ),
+            other => bail!("Unsupported datatype {} for POSITION_3D attribute", other),
+        }
+
+        let tile_path = self.output_directory.join(format!(
+            "{}.{}",
+            key.name(),
+            self.content_format.extension()
+        ));
+        let file = BufWriter::new(File::create(&tile_path)?);
+        let result = match self.content_format {
+            TilerContentFormat::Pnts => {
+                let mut writer =
+                    PntsWriter::from_write_and_layout(file, points.point_layout().clone());
+                writer.set_rtc_center(center);
+                writer.write(&points).and_then(|_| writer.flush())
+            }
+            TilerContentFormat::Glb => {
+                let mut writer =
+                    GlbWriter::from_write_and_layout(file, points.point_layout().clone());
+                writer.set_rtc_center(center);
+                writer.write(&points).and_then(|_| writer.flush())
+            }
+        };
+        result.with_context(|| format!("Could not write tile {}", tile_path.display()))?;
+
+        Ok(bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    use pasture_core::{containers::HashMapBuffer, layout::PointType};
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::tiles3d::{PntsReader, WGS84_SEMI_MAJOR_AXIS};
+
+    #[derive(
+        Copy, Clone, PartialEq, Debug, PointType, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    #[repr(C, packed)]
+    struct TestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+    }
+
+    fn test_points(count: usize) -> VectorBuffer {
+        (0..count)
+            .map(|index| TestPoint {
+                position: Vector3::new(
+                    (index % 100) as f64,
+                    ((index / 100) % 100) as f64,
+                    (index / 10_000) as f64,
+                ),
+                intensity: index as u16,
+            })
+            .collect()
+    }
+
+    /// Returns all tiles of the given tileset in depth-first order
+    fn all_tiles(tileset: &Tileset) -> Vec<&Tileset> {
+        std::iter::once(tileset)
+            .chain(tileset.children.iter().flat_map(all_tiles))
+            .collect()
+    }
+
+    /// Reads the points of all tiles of the given tileset in absolute coordinates
+    fn read_all_tiles(directory: &Path, tileset: &Tileset) -> Result<Vec<TestPoint>> {
+        let mut points = vec![];
+        for tile in all_tiles(tileset) {
+            let uri = &tile.content.as_ref().expect("Tile must have content").uri;
+            let mut reader = PntsReader::from_path(directory.join(uri))?;
+            let count = reader.get_metadata().number_of_points().unwrap();
+            let mut tile_points = VectorBuffer::with_capacity(count, TestPoint::layout());
+            tile_points.resize(count);
+            reader.read_into(&mut tile_points, count)?;
+            points.extend(tile_points.view::<TestPoint>());
+        }
+        Ok(points)
+    }
+
+    fn run_tiler_test(test_name: &str, tiler: impl Fn(PntsTiler) -> PntsTiler) -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("pasture_{}_{}", test_name, std::process::id()));
+        let expected_points = test_points(20_000);
+
+        // Use a PNTS file as input, as it is a format that all builds of pasture can read
+        let mut input = Cursor::new(vec![]);
+        {
+            let mut writer = PntsWriter::from_write_and_layout(&mut input, TestPoint::layout());
+            writer.write(&expected_points)?;
+        }
+        input.seek(SeekFrom::Start(0))?;
+        let mut reader = PntsReader::from_read(input)?;
+
+        let root_tileset = tiler(PntsTiler::new(&directory).with_max_points_per_tile(1_000))
+            .with_point_layout(TestPoint::layout())
+            .tile(&mut reader)?;
+
+        let tileset_from_file: RootTileset =
+            serde_json::from_reader(File::open(directory.join("tileset.json"))?)?;
+        assert_eq!(root_tileset, tileset_from_file);
+        assert!(!directory.join(TEMPORARY_DIRECTORY_NAME).exists());
+        // Without a local origin, the positions are already earth-centered, earth-fixed coordinates
+        assert_eq!(None, root_tileset.root.transform);
+
+        let tiles = all_tiles(&root_tileset.root);
+        assert!(tiles.len() > 1);
+        for tile in &tiles {
+            if tile.children.is_empty() {
+                assert_eq!(0.0, tile.geometric_error);
+            } else {
+                assert!(tile
+                    .children
+                    .iter()
+                    .all(|child| child.geometric_error < tile.geometric_error));
+            }
+        }
+
+        let mut actual_points = read_all_tiles(&directory, &root_tileset.root)?;
+        actual_points.sort_by_key(|point| point.intensity);
+        let expected_points = expected_points
+            .view::<TestPoint>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(expected_points.len(), actual_points.len());
+        for (expected, actual) in expected_points.iter().zip(actual_points.iter()) {
+            let expected_position = expected.position;
+            let actual_position = actual.position;
+            assert!((expected_position - actual_position).amax() < 1e-4);
+            assert_eq!({ expected.intensity }, { actual.intensity });
+        }
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiler_in_memory() -> Result<()> {
+        run_tiler_test("tiler_in_memory", |tiler| tiler)
+    }
+
+    #[test]
+    fn test_tiler_out_of_core() -> Result<()> {
+        run_tiler_test("tiler_out_of_core", |tiler| {
+            tiler.with_max_points_in_memory(3_000)
+        })
+    }
+
+    #[test]
+    fn test_tiler_bounding_regions() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("pasture_tiler_regions_{}", std::process::id()));
+        // A few points on the equator at the prime meridian, in ECEF coordinates
+        let points = (0..10)
+            .map(|index| TestPoint {
+                position: Vector3::new(
+                    WGS84_SEMI_MAJOR_AXIS + index as f64,
+                    index as f64,
+                    index as f64,
+                ),
+                intensity: index,
+            })
+            .collect::<HashMapBuffer>();
+        let mut input = Cursor::new(vec![]);
+        {
+            let mut writer = PntsWriter::from_write_and_layout(&mut input, TestPoint::layout());
+            writer.write(&points)?;
+        }
+        input.seek(SeekFrom::Start(0))?;
+
+        let root_tileset = PntsTiler::new(&directory)
+            .with_bounding_volume(TilerBoundingVolume::Region)
+            .tile(&mut PntsReader::from_read(input)?)?;
+        fs::remove_dir_all(&directory)?;
+
+        match root_tileset.root.bounding_volume {
+            BoundingVolume::Region(region) => {
+                assert!(region.west() >= 0.0 && region.east() < 1e-5);
+                assert!(region.south() >= 0.0 && region.north() < 1e-5);
+                assert!(region.min_height().abs() < 1e-3);
+                assert!((region.max_height() - 9.0).abs() < 1e-3);
+            }
+            other => panic!("Expected bounding region but got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiler_local_origin() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("pasture_tiler_local_origin_{}", std::process::id()));
+        // A few points within 10 meters around the local origin
+        let points = (0..10)
+            .map(|index| TestPoint {
+                position: Vector3::new(index as f64, -(index as f64), index as f64),
+                intensity: index,
+            })
+            .collect::<HashMapBuffer>();
+        let mut input = Cursor::new(vec![]);
+        {
+            let mut writer = PntsWriter::from_write_and_layout(&mut input, TestPoint::layout());
+            writer.write(&points)?;
+        }
+        input.seek(SeekFrom::Start(0))?;
+
+        let (longitude, latitude, height) = (0.2, 0.9, 100.0);
+        let root_tileset = PntsTiler::new(&directory)
+            .with_local_origin(longitude, latitude, height)
+            .with_bounding_volume(TilerBoundingVolume::Region)
+            .tile(&mut PntsReader::from_read(input)?)?;
+        let tileset_from_file: RootTileset =
+            serde_json::from_reader(File::open(directory.join("tileset.json"))?)?;
+        fs::remove_dir_all(&directory)?;
+
+        let expected_transform = east_north_up_to_ecef(&Vector3::new(longitude, latitude, height));
+        assert_eq!(Some(expected_transform), root_tileset.root.transform);
+        // JSON does not round-trip all floating point values exactly
+        let transform_from_file = tileset_from_file
+            .root
+            .transform
+            .expect("tileset.json must contain the root transform");
+        assert!((transform_from_file - expected_transform).amax() < 1e-6);
+        match root_tileset.root.bounding_volume {
+            BoundingVolume::Region(region) => {
+                assert!(region.west() <= longitude && region.east() > longitude);
+                assert!(region.south() < latitude && region.north() >= latitude);
+                assert!(region.east() - region.west() < 1e-5);
+                assert!(region.north() - region.south() < 1e-5);
+                assert!((region.min_height() - height).abs() < 1e-3);
+                assert!((region.max_height() - height - 9.0).abs() < 1e-3);
+            }
+            other => panic!("Expected bounding region but got {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -3,9 +3,11 @@ use std::collections::HashMap;
 
 use pasture_core::{
     math::AABB,
-    nalgebra::{Matrix4, Vector3},
+    nalgebra::{Matrix4, Point3, Vector3},
 };
 
+use super::geodetic_to_ecef;
+
 /// 3D Tiles refinement strategy
 #[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub enum Refinement {
@@ -139,6 +141,86 @@ impl Default for BoundingVolume {
     }
 }
 
+impl BoundingVolume {
+    /// Returns the axis-aligned bounding box of this `BoundingVolume` in world coordinates. `transform` is the
+    /// transformation from the local coordinates of the tile into world coordinates. As defined by the 3D Tiles
+    /// specification, bounding regions are not affected by `transform`, they are converted into earth-centered,
+    /// earth-fixed coordinates instead
+    pub fn world_bounds(&self, transform: &Matrix4<f64>) -> AABB<f64> {
+        let bounds_of = |points: &mut dyn Iterator<Item = Point3<f64>>| -> AABB<f64> {
+            let first = points
+                .next()
+                .expect("Bounding volume must have at least one point");
+            points.fold(
+                AABB::from_min_max_unchecked(first, first),
+                |bounds, point| AABB::extend_with_point(&bounds, &point),
+            )
+        };
+        match self {
+            BoundingVolume::Box(bounding_box) => {
+                let mut corners = (0..8).map(|corner_index| {
+                    let sign = |bit: usize| if corner_index & bit == 0 { -1.0 } else { 1.0 };
+                    let corner = bounding_box.center()
+                        + bounding_box.x() * sign(1)
+                        + bounding_box.y() * sign(2)
+                        + bounding_box.z() * sign(4);
+                    transform.transform_point(&Point3::from(corner))
+                });
+                bounds_of(&mut corners)
+            }
+            BoundingVolume::Sphere(sphere) => {
+                let center = transform.transform_point(&Point3::from(sphere.center()));
+                let max_scale = transform
+                    .fixed_view::<3, 3>(0, 0)
+                    .column_iter()
+                    .map(|column| column.norm())
+                    .fold(0.0, f64::max);
+                let radius = sphere.radius() * max_scale;
+                let half_extent = Vector3::new(radius, radius, radius);
+                AABB::from_min_max_unchecked(center - half_extent, center + half_extent)
+            }
+            BoundingVolume::Region(region) => {
+                // The extrema of the ECEF coordinates within a region are either at the boundary of the region, at the
+                // equator or at the longitudes where the region crosses one of the ECEF axes
+                let contains_longitude = |longitude: f64| {
+                    if region.west() <= region.east() {
+                        longitude >= region.west() && longitude <= region.east()
+                    } else {
+                        longitude >= region.west() || longitude <= region.east()
+                    }
+                };
+                let longitudes = [
+                    -std::f64::consts::PI,
+                    -std::f64::consts::FRAC_PI_2,
+                    0.0,
+                    std::f64::consts::FRAC_PI_2,
+                    std::f64::consts::PI,
+                ]
+                .iter()
+                .copied()
+                .filter(|longitude| contains_longitude(*longitude))
+                .chain([region.west(), region.east()])
+                .collect::<Vec<_>>();
+                let latitudes = std::iter::once(0.0)
+                    .filter(|latitude| *latitude >= region.south() && *latitude <= region.north())
+                    .chain([region.south(), region.north()])
+                    .collect::<Vec<_>>();
+                let mut corners = longitudes.iter().flat_map(|longitude| {
+                    latitudes.iter().flat_map(move |latitude| {
+                        [region.min_height(), region.max_height()]
+                            .iter()
+                            .map(move |height| {
+                                geodetic_to_ecef(&Vector3::new(*longitude, *latitude, *height))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                });
+                bounds_of(&mut corners)
+            }
+        }
+    }
+}
+
 /// Content of a `Tileset`. This refers to the file that contains the actual geometry, in the case of pasture
 /// this will mostly be `.pnts` files.
 #[derive(Clone, Serialize, Deserialize, Default, PartialEq, Debug)]
@@ -0,0 +1,915 @@
+use std::{
+    cell::{Cell, OnceCell},
+    fmt::Display,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use pasture_core::{
+    containers::{
+        BorrowedBuffer, BorrowedMutBuffer, InterleavedBuffer, MakeBufferFromLayout, OwningBuffer,
+        VectorBuffer,
+    },
+    layout::{
+        attributes::POSITION_3D, conversion::BufferLayoutConverter, PointAttributeDataType,
+        PointLayout,
+    },
+    math::AABB,
+    meta::Metadata,
+    nalgebra::{Matrix4, Point3, Vector3},
+};
+
+use crate::base::PointReader;
+
+use super::{GlbReader, PntsReader, Refinement, RootTileset, Tileset};
+
+/// A single tile of a 3D Tiles tileset, as resolved by the `TilesetReader`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TilesetTile {
+    /// Path to the content file of this tile, or `None` if the tile has no content
+    pub content_path: Option<PathBuf>,
+    /// Transformation from the local coordinates of this tile into world coordinates. This is the product of the
+    /// transforms of this tile and all its ancestors, including the tiles that reference external tilesets
+    pub transform: Matrix4<f64>,
+    /// Geometric error of this tile
+    pub geometric_error: f64,
+    /// Refinement strategy of this tile, which is inherited from the parent tile if the tile does not define one
+    pub refinement: Refinement,
+    /// Axis-aligned bounding box of the bounding volume of this tile in world coordinates
+    pub bounds: AABB<f64>,
+    /// Depth of this tile within the tileset hierarchy. The root tile has depth 0
+    pub depth: usize,
+}
+
+/// A tile within the tree of tiles of the `TilesetReader`
+struct TileNode {
+    tile: TilesetTile,
+    children: Vec<usize>,
+    /// Number of points in the content of this tile, which is only known once the content has been opened
+    point_count: Cell<Option<usize>>,
+}
+
+/// `Metadata` of a `TilesetReader`, which covers all tiles that are currently selected for reading. The number of
+/// points is `None` if the content of a selected tile can't be opened
+#[derive(Clone, Debug)]
+pub struct TilesetMetadata {
+    number_of_points: Option<usize>,
+    bounds: Option<AABB<f64>>,
+    geometric_error: f64,
+}
+
+impl TilesetMetadata {
+    /// Returns the geometric error of the root tileset
+    pub fn geometric_error(&self) -> f64 {
+        self.geometric_error
+    }
+}
+
+impl Metadata for TilesetMetadata {
+    fn bounds(&self) -> Option<AABB<f64>> {
+        self.bounds
+    }
+
+    fn number_of_points(&self) -> Option<usize> {
+        self.number_of_points
+    }
+
+    fn get_named_field(&self, field_name: &str) -> Option<Box<dyn std::any::Any>> {
+        match field_name {
+            "GEOMETRIC_ERROR" => Some(Box::new(self.geometric_error)),
+            _ => None,
+        }
+    }
+
+    fn clone_into_box(&self) -> Box<dyn Metadata> {
+        Box::new(self.clone())
+    }
+}
+
+impl Display for TilesetMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "TilesetMetadata {{")?;
+        if let Some(number_of_points) = self.number_of_points {
+            writeln!(f, "\t\"number_of_points\": {}", number_of_points)?;
+        }
+        if let Some(bounds) = &self.bounds {
+            writeln!(f, "\t\"bounds\": {} - {}", bounds.min(), bounds.max())?;
+        }
+        writeln!(f, "\t\"geometric_error\": {}", self.geometric_error)?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Reader for point clouds that are stored as 3D Tiles tilesets. The `TilesetReader` parses the `tileset.json` file,
+/// follows references to external tilesets and resolves all content URIs relative to the tileset file that contains
+/// them. Only local files are supported, and the content of all tiles must be in the `.pnts` or the `.glb` format.
+///
+/// All points are provided in world coordinates, i.e. with the `RTC_CENTER` of each `.pnts` file (or the node
+/// transformations of each `.glb` file) and the `transform`
+/// of each tile and all its ancestors applied. For this reason, the `POSITION_3D` attribute in the default `PointLayout`
+/// of the `TilesetReader` uses `Vec3f64`. The remaining attributes are taken from the first tile with content.
+///
+/// By default, all points of the tileset are read. For additive refinement, this includes the content of all tiles,
+/// for replacement refinement only the content of the tiles that are not refined further. Use
+/// `set_max_geometric_error` and `set_bounds_filter` to restrict reading to the tiles up to a given level of detail
+/// or to the tiles within some bounds.
+///
+/// 3D Tiles store the number of points only within the content files, so the `TilesetReader` opens the content of a
+/// tile only once its points are read or the metadata is requested. The number of points of each tile is cached, so
+/// changing the filters does not open any tile twice.
+pub struct TilesetReader {
+    root_tileset: RootTileset,
+    nodes: Vec<TileNode>,
+    max_geometric_error: Option<f64>,
+    bounds_filter: Option<AABB<f64>>,
+    /// Indices of all selected nodes with content
+    selected_nodes: Vec<usize>,
+    layout: PointLayout,
+    /// Metadata of the selected tiles, which is determined on first access
+    metadata: OnceCell<TilesetMetadata>,
+    current_tile_index: usize,
+    current_tile_points: Option<VectorBuffer>,
+    current_point_in_tile: usize,
+}
+
+impl TilesetReader {
+    /// Creates a new `TilesetReader` from the `tileset.json` file at the given `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let root_tileset = read_root_tileset(path)?;
+        let mut nodes = vec![];
+        let mut loading_tilesets = vec![path.to_owned()];
+        load_tile(
+            &root_tileset.root,
+            parent_directory(path),
+            &Matrix4::identity(),
+            Refinement::Add,
+            0,
+            &mut nodes,
+            &mut loading_tilesets,
+        )?;
+
+        let layout = match nodes
+            .iter()
+            .find_map(|node| node.tile.content_path.as_ref())
+        {
+            Some(content_path) => {
+                let reader = TileContentReader::from_path(content_path)?;
+                world_layout(reader.default_point_layout())
+            }
+            None => world_layout(&PointLayout::from_attributes(&[POSITION_3D])),
+        };
+
+        let mut reader = Self {
+            root_tileset,
+            nodes,
+            max_geometric_error: None,
+            bounds_filter: None,
+            selected_nodes: vec![],
+            layout,
+            metadata: OnceCell::new(),
+            current_tile_index: 0,
+            current_tile_points: None,
+            current_point_in_tile: 0,
+        };
+        reader.select_tiles();
+        Ok(reader)
+    }
+
+    /// Returns the `RootTileset` of the `tileset.json` file that this `TilesetReader` was created from. External
+    /// tilesets are not included here, use `all_tiles` to access all tiles including those of external tilesets
+    pub fn root_tileset(&self) -> &RootTileset {
+        &self.root_tileset
+    }
+
+    /// Returns all tiles of the tileset in depth-first order, including the tiles of all external tilesets
+    pub fn all_tiles(&self) -> impl Iterator<Item = &TilesetTile> + '_ {
+        self.nodes.iter().map(|node| &node.tile)
+    }
+
+    /// Returns all tiles with content that are currently selected for reading, in the order in which their points
+    /// are read
+    pub fn selected_tiles(&self) -> impl Iterator<Item = &TilesetTile> + '_ {
+        self.selected_nodes
+            .iter()
+            .map(move |node_index| &self.nodes[*node_index].tile)
+    }
+
+    /// Only read tiles up to the given geometric error. Starting from the root tile, the children of a tile are only
+    /// read if the geometric error of the tile is larger than `max_geometric_error`. Passing `None` reads the tileset
+    /// at the highest level of detail. This resets the reader to the first point
+    pub fn set_max_geometric_error(&mut self, max_geometric_error: Option<f64>) -> Result<()> {
+        self.max_geometric_error = max_geometric_error;
+        self.select_tiles();
+        Ok(())
+    }
+
+    /// Only read tiles whose bounding volume intersects the given `bounds` (in world coordinates). Passing `None` reads
+    /// all tiles. This resets the reader to the first point
+    pub fn set_bounds_filter(&mut self, bounds: Option<AABB<f64>>) -> Result<()> {
+        self.bounds_filter = bounds;
+        self.select_tiles();
+        Ok(())
+    }
+
+    /// Reads all points of the given `tile` in world coordinates, using the default `PointLayout` of this reader
+    pub fn read_tile(&self, tile: &TilesetTile) -> Result<VectorBuffer> {
+        let content_path = match &tile.content_path {
+            Some(content_path) => content_path,
+            None => return Ok(VectorBuffer::new_from_layout(self.layout.clone())),
+        };
+        let mut reader = TileContentReader::from_path(content_path)?;
+        let count = reader.number_of_points();
+        let mut points = VectorBuffer::with_capacity(count, self.layout.clone());
+        points.resize(count);
+        if count > 0 {
+            // The content readers apply the RTC_CENTER, so only the transform of the tile is missing
+            reader.read_into(&mut points, count)?;
+        }
+        let transform = tile.transform;
+        points.transform_attribute(
+            &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            |_, position: Vector3<f64>| -> Vector3<f64> {
+                transform.transform_point(&Point3::from(position)).coords
+            },
+        );
+        Ok(points)
+    }
+
+    /// Returns an iterator over the points of all selected tiles, one buffer per tile. See `read_tile`
+    pub fn read_tiles(&self) -> impl Iterator<Item = Result<(&TilesetTile, VectorBuffer)>> + '_ {
+        self.selected_tiles()
+            .map(move |tile| self.read_tile(tile).map(|points| (tile, points)))
+    }
+
+    /// Determines the selected tiles based on the current filters and resets the reader to the first point
+    fn select_tiles(&mut self) {
+        let mut selected_nodes = vec![];
+        if !self.nodes.is_empty() {
+            self.select_tiles_recursive(0, &mut selected_nodes);
+        }
+        self.selected_nodes = selected_nodes;
+        self.metadata = OnceCell::new();
+
+        self.current_tile_index = 0;
+        self.current_tile_points = None;
+        self.current_point_in_tile = 0;
+    }
+
+    /// Returns the number of points of the node at `node_index`, opening its content if the number is not yet known
+    fn point_count(&self, node_index: usize) -> Result<usize> {
+        let node = &self.nodes[node_index];
+        if let Some(count) = node.point_count.get() {
+            return Ok(count);
+        }
+        // Only the header of the tile is read here to get the number of points
+        let content_path = node
+            .tile
+            .content_path
+            .as_ref()
+            .expect("Selected tiles have content");
+        let count = TileContentReader::from_path(content_path)?.number_of_points();
+        node.point_count.set(Some(count));
+        Ok(count)
+    }
+
+    fn compute_metadata(&self) -> TilesetMetadata {
+        let number_of_points = self
+            .selected_nodes
+            .iter()
+            .map(|node_index| self.point_count(*node_index))
+            .sum::<Result<usize>>()
+            .ok();
+        let bounds = self
+            .selected_tiles()
+            .map(|tile| tile.bounds)
+            .reduce(|a, b| AABB::union(&a, &b));
+        TilesetMetadata {
+            number_of_points,
+            bounds,
+            geometric_error: self.root_tileset.geometric_error,
+        }
+    }
+
+    fn select_tiles_recursive(&self, node_index: usize, selected_nodes: &mut Vec<usize>) {
+        let node = &self.nodes[node_index];
+        if let Some(bounds_filter) = &self.bounds_filter {
+            if !node.tile.bounds.intersects(bounds_filter) {
+                return;
+            }
+        }
+        let refine = !node.children.is_empty()
+            && self
+                .max_geometric_error
+                .map(|max_geometric_error| node.tile.geometric_error > max_geometric_error)
+                .unwrap_or(true);
+        let is_replaced = refine && node.tile.refinement == Refinement::Replace;
+        if node.tile.content_path.is_some() && !is_replaced {
+            selected_nodes.push(node_index);
+        }
+        if refine {
+            for child_index in &node.children {
+                self.select_tiles_recursive(*child_index, selected_nodes);
+            }
+        }
+    }
+
+    /// Returns the points of the current tile together with the index of the next point to read within the tile, loading
+    /// the next tile if all points of the current tile have been read. Returns `None` if all tiles have been read
+    fn current_tile_points(&mut self) -> Result<Option<(&VectorBuffer, usize)>> {
+        loop {
+            if let Some(points) = &self.current_tile_points {
+                if self.current_point_in_tile < points.len() {
+                    break;
+                }
+                self.current_tile_points = None;
+                self.current_tile_index += 1;
+            }
+            let node_index = match self.selected_nodes.get(self.current_tile_index) {
+                Some(node_index) => *node_index,
+                None => return Ok(None),
+            };
+            let points = self.read_tile(&self.nodes[node_index].tile)?;
+            self.nodes[node_index].point_count.set(Some(points.len()));
+            self.current_tile_points = Some(points);
+            self.current_point_in_tile = 0;
+        }
+        Ok(self
+            .current_tile_points
+            .as_ref()
+            .map(|points| (points, self.current_point_in_tile)))
+    }
+}
+
+impl PointReader for TilesetReader {
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        let source_layout = self.layout.clone();
+        let target_layout = point_buffer.point_layout().clone();
+        let converter = if source_layout == target_layout {
+            None
+        } else {
+            Some(BufferLayoutConverter::for_layouts_with_default(
+                &source_layout,
+                &target_layout,
+            ))
+        };
+
+        let mut num_read = 0;
+        while num_read < count {
+            let (tile_points, current_point_in_tile) = match self.current_tile_points()? {
+                Some(current) => current,
+                None => break,
+            };
+            let num_to_copy = (tile_points.len() - current_point_in_tile).min(count - num_read);
+            let source_range = current_point_in_tile..(current_point_in_tile + num_to_copy);
+            let target_range = num_read..(num_read + num_to_copy);
+            match &converter {
+                Some(converter) => converter.convert_into_range(
+                    tile_points,
+                    source_range,
+                    point_buffer,
+                    target_range,
+                ),
+                // Safe because the buffer has the same `PointLayout` as the tile points
+                None => unsafe {
+                    point_buffer.set_point_range(
+                        target_range,
+                        tile_points.get_point_range_ref(source_range),
+                    );
+                },
+            }
+            self.current_point_in_tile += num_to_copy;
+            num_read += num_to_copy;
+        }
+        Ok(num_read)
+    }
+
+    fn get_metadata(&self) -> &dyn Metadata {
+        self.metadata.get_or_init(|| self.compute_metadata())
+    }
+
+    fn get_default_point_layout(&self) -> &PointLayout {
+        &self.layout
+    }
+}
+
+/// Reader for the content of a single tile, depending on the format of the content file
+enum TileContentReader {
+    Pnts(PntsReader<BufReader<File>>),
+    Glb(GlbReader),
+}
+
+impl TileContentReader {
+    fn from_path(content_path: &Path) -> Result<Self> {
+        let is_glb = content_path
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case("glb"))
+            .unwrap_or(false);
+        let reader = if is_glb {
+            GlbReader::from_path(content_path).map(Self::Glb)
+        } else {
+            PntsReader::from_path(content_path).map(Self::Pnts)
+        };
+        reader.with_context(|| format!("Could not open tile content {}", content_path.display()))
+    }
+
+    fn default_point_layout(&self) -> &PointLayout {
+        match self {
+            Self::Pnts(reader) => reader.get_default_point_layout(),
+            Self::Glb(reader) => reader.get_default_point_layout(),
+        }
+    }
+
+    fn number_of_points(&self) -> usize {
+        let metadata = match self {
+            Self::Pnts(reader) => reader.get_metadata(),
+            Self::Glb(reader) => reader.get_metadata(),
+        };
+        metadata
+            .number_of_points()
+            .expect("PNTS and GLB readers always know the number of points")
+    }
+
+    fn read_into<'a, 'b, B: BorrowedMutBuffer<'a>>(
+        &mut self,
+        point_buffer: &'b mut B,
+        count: usize,
+    ) -> Result<usize>
+    where
+        'a: 'b,
+    {
+        match self {
+            Self::Pnts(reader) => reader.read_into(point_buffer, count),
+            Self::Glb(reader) => reader.read_into(point_buffer, count),
+        }
+    }
+}
+
+/// Returns the given `PointLayout` with the `POSITION_3D` attribute converted to `Vec3f64`
+fn world_layout(point_layout: &PointLayout) -> PointLayout {
+    let attributes = point_layout
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.attribute_definition();
+            if attribute.name() == POSITION_3D.name() {
+                attribute.with_custom_datatype(PointAttributeDataType::Vec3f64)
+            } else {
+                attribute.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    if point_layout.has_attribute_with_name(POSITION_3D.name()) {
+        PointLayout::from_attributes(&attributes)
+    } else {
+        let mut attributes = attributes;
+        attributes.insert(
+            0,
+            POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+        );
+        PointLayout::from_attributes(&attributes)
+    }
+}
+
+fn parent_directory(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new(""))
+}
+
+fn read_root_tileset(path: &Path) -> Result<RootTileset> {
+    let file = File::open(path)
+        .with_context(|| format!("Could not open tileset file {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Could not parse tileset file {}", path.display()))
+}
+
+/// Resolves the given content `uri` relative to `directory`. Query parameters are ignored
+fn resolve_uri(uri: &str, directory: &Path) -> Result<PathBuf> {
+    if uri.contains("://") {
+        bail!(
+            "Content URI {} is not a local file, which is not supported",
+            uri
+        );
+    }
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    Ok(directory.join(path))
+}
+
+/// Adds the given `tileset` and all its children (including external tilesets) to `nodes`. Returns the index of the
+/// node for `tileset`
+fn load_tile(
+    tileset: &Tileset,
+    directory: &Path,
+    parent_transform: &Matrix4<f64>,
+    parent_refinement: Refinement,
+    depth: usize,
+    nodes: &mut Vec<TileNode>,
+    loading_tilesets: &mut Vec<PathBuf>,
+) -> Result<usize> {
+    let transform = match &tileset.transform {
+        Some(transform) => parent_transform * transform,
+        None => *parent_transform,
+    };
+    let refinement = tileset.refinement.unwrap_or(parent_refinement);
+    let content_path = tileset
+        .content
+        .as_ref()
+        .map(|content| resolve_uri(&content.uri, directory))
+        .transpose()?;
+    let is_external_tileset = content_path
+        .as_ref()
+        .and_then(|path| path.extension())
+        .map(|extension| extension.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let node_index = nodes.len();
+    nodes.push(TileNode {
+        tile: TilesetTile {
+            content_path: if is_external_tileset {
+                None
+            } else {
+                content_path.clone()
+            },
+            transform,
+            geometric_error: tileset.geometric_error,
+            refinement,
+            bounds: tileset.bounding_volume.world_bounds(&transform),
+            depth,
+        },
+        children: vec![],
+        point_count: Cell::new(None),
+    });
+
+    let mut children = vec![];
+    if is_external_tileset {
+        // The root tile of the external tileset becomes the only child of this tile
+        let external_path = content_path.unwrap();
+        if loading_tilesets.contains(&external_path) {
+            bail!(
+                "Tileset {} references itself (directly or indirectly)",
+                external_path.display()
+            );
+        }
+        let external_tileset = read_root_tileset(&external_path)?;
+        loading_tilesets.push(external_path.clone());
+        children.push(load_tile(
+            &external_tileset.root,
+            parent_directory(&external_path),
+            &transform,
+            refinement,
+            depth + 1,
+            nodes,
+            loading_tilesets,
+        )?);
+        loading_tilesets.pop();
+    }
+    for child in &tileset.children {
+        children.push(load_tile(
+            child,
+            directory,
+            &transform,
+            refinement,
+            depth + 1,
+            nodes,
+            loading_tilesets,
+        )?);
+    }
+    nodes[node_index].children = children;
+
+    Ok(node_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufWriter;
+
+    use pasture_core::{containers::HashMapBuffer, layout::PointType};
+    use pasture_derive::PointType;
+
+    use super::*;
+    use crate::{
+        base::PointWriter,
+        tiles3d::{
+            BoundingBox, BoundingVolume, PntsTiler, PntsWriter, TilerContentFormat, TilesetBuilder,
+        },
+    };
+
+    #[derive(
+        Copy, Clone, PartialEq, Debug, PointType, bytemuck::AnyBitPattern, bytemuck::NoUninit,
+    )]
+    #[repr(C, packed)]
+    struct TestPoint {
+        #[pasture(BUILTIN_POSITION_3D)]
+        position: Vector3<f64>,
+        #[pasture(BUILTIN_INTENSITY)]
+        intensity: u16,
+    }
+
+    fn test_directory(test_name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("pasture_{}_{}", test_name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn write_pnts(path: &Path, positions: &[Vector3<f64>], rtc_center: Vector3<f64>) -> Result<()> {
+        let points = positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| TestPoint {
+                position: *position,
+                intensity: index as u16,
+            })
+            .collect::<HashMapBuffer>();
+        let mut writer = PntsWriter::from_write_and_layout(
+            BufWriter::new(File::create(path)?),
+            TestPoint::layout(),
+        );
+        writer.set_rtc_center(rtc_center);
+        writer.write(&points)?;
+        writer.flush()
+    }
+
+    fn write_tileset(path: &Path, root: Tileset, geometric_error: f64) -> Result<()> {
+        let root_tileset = RootTileset {
+            geometric_error,
+            root,
+            ..Default::default()
+        };
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &root_tileset)?;
+        Ok(())
+    }
+
+    fn box_volume(half_extent: f64) -> BoundingVolume {
+        BoundingVolume::Box(BoundingBox::from(AABB::from_min_max(
+            Point3::new(-half_extent, -half_extent, -half_extent),
+            Point3::new(half_extent, half_extent, half_extent),
+        )))
+    }
+
+    #[test]
+    fn test_tileset_reader_transforms_and_external_tilesets() -> Result<()> {
+        let directory = test_directory("tileset_reader_transforms");
+        std::fs::create_dir_all(directory.join("external"))?;
+
+        // Root tile with translation (100, 0, 0), referencing one .pnts file and an external tileset whose root has an
+        // additional translation of (0, 10, 0)
+        write_pnts(
+            &directory.join("root.pnts"),
+            &[Vector3::new(0.0, 0.0, 0.0)],
+            Vector3::new(0.5, 0.0, 0.0),
+        )?;
+        write_pnts(
+            &directory.join("external/child.pnts"),
+            &[Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0)],
+            Vector3::new(0.0, 0.0, 0.0),
+        )?;
+        write_tileset(
+            &directory.join("external/tileset.json"),
+            TilesetBuilder::default()
+                .bounding_volume(box_volume(1.0))
+                .geometric_error(0.0)
+                .transform(Matrix4::new_translation(&Vector3::new(0.0, 10.0, 0.0)))
+                .content("child.pnts".into(), None)
+                .into(),
+            0.0,
+        )?;
+        write_tileset(
+            &directory.join("tileset.json"),
+            TilesetBuilder::default()
+                .bounding_volume(box_volume(20.0))
+                .geometric_error(2.0)
+                .refinement(Refinement::Add)
+                .transform(Matrix4::new_translation(&Vector3::new(100.0, 0.0, 0.0)))
+                .content("root.pnts".into(), None)
+                .add_child(
+                    TilesetBuilder::default()
+                        .bounding_volume(box_volume(20.0))
+                        .geometric_error(1.0)
+                        .content("external/tileset.json".into(), None)
+                        .into(),
+                )
+                .into(),
+            2.0,
+        )?;
+
+        let mut reader = TilesetReader::from_path(directory.join("tileset.json"))?;
+        assert_eq!(3, reader.all_tiles().count());
+        assert_eq!(Some(3), reader.get_metadata().number_of_points());
+        assert_eq!(
+            PointAttributeDataType::Vec3f64,
+            reader
+                .get_default_point_layout()
+                .get_attribute_by_name(POSITION_3D.name())
+                .unwrap()
+                .datatype()
+        );
+
+        let points = reader.read::<VectorBuffer>(3)?;
+        let positions = points
+            .view_attribute::<Vector3<f64>>(
+                &POSITION_3D.with_custom_datatype(PointAttributeDataType::Vec3f64),
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Vector3::new(100.5, 0.0, 0.0),
+                Vector3::new(100.0, 10.0, 1.0),
+                Vector3::new(100.0, 10.0, -1.0)
+            ],
+            positions
+        );
+        assert_eq!(0, reader.read_into(&mut points.clone(), 3)?);
+
+        // Only the root tile is within the geometric error
+        reader.set_max_geometric_error(Some(5.0))?;
+        assert_eq!(Some(1), reader.get_metadata().number_of_points());
+        assert_eq!(
+            vec![Some(directory.join("root.pnts"))],
+            reader
+                .selected_tiles()
+                .map(|tile| tile.content_path.clone())
+                .collect::<Vec<_>>()
+        );
+
+        // The external tile does not intersect these bounds
+        reader.set_max_geometric_error(None)?;
+        reader.set_bounds_filter(Some(AABB::from_min_max(
+            Point3::new(90.0, -15.0, -5.0),
+            Point3::new(110.0, -5.0, 5.0),
+        )))?;
+        let tiles = reader.read_tiles().collect::<Result<Vec<_>>>()?;
+        assert_eq!(1, tiles.len());
+        assert_eq!(Some(directory.join("root.pnts")), tiles[0].0.content_path);
+        assert_eq!(1, tiles[0].1.len());
+
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tileset_reader_replace_refinement() -> Result<()> {
+        let directory = test_directory("tileset_reader_replace");
+        write_pnts(
+            &directory.join("parent.pnts"),
+            &[Vector3::new(0.0, 0.0, 0.0)],
+            Vector3::zeros(),
+        )?;
+        write_pnts(
+            &directory.join("child.pnts"),
+            &[Vector3::new(0.5, 0.0, 0.0), Vector3::new(-0.5, 0.0, 0.0)],
+            Vector3::zeros(),
+        )?;
+        write_tileset(
+            &directory.join("tileset.json"),
+            TilesetBuilder::default()
+                .bounding_volume(box_volume(1.0))
+                .geometric_error(1.0)
+                .refinement(Refinement::Replace)
+                .content("parent.pnts".into(), None)
+                .add_child(
+                    TilesetBuilder::default()
+                        .bounding_volume(box_volume(1.0))
+                        .geometric_error(0.0)
+                        .content("child.pnts".into(), None)
+                        .into(),
+                )
+                .into(),
+            1.0,
+        )?;
+
+        let mut reader = TilesetReader::from_path(directory.join("tileset.json"))?;
+        assert_eq!(Some(2), reader.get_metadata().number_of_points());
+        reader.set_max_geometric_error(Some(1.0))?;
+        assert_eq!(Some(1), reader.get_metadata().number_of_points());
+
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tileset_reader_counts_points_lazily() -> Result<()> {
+        let directory = test_directory("tileset_reader_lazy_counts");
+        write_pnts(
+            &directory.join("parent.pnts"),
+            &[Vector3::new(0.0, 0.0, 0.0)],
+            Vector3::zeros(),
+        )?;
+        write_tileset(
+            &directory.join("tileset.json"),
+            TilesetBuilder::default()
+                .bounding_volume(box_volume(1.0))
+                .geometric_error(1.0)
+                .refinement(Refinement::Add)
+                .content("parent.pnts".into(), None)
+                .add_child(
+                    TilesetBuilder::default()
+                        .bounding_volume(box_volume(1.0))
+                        .geometric_error(0.0)
+                        .content("child.pnts".into(), None)
+                        .into(),
+                )
+                .into(),
+            1.0,
+        )?;
+
+        // The content of the child tile does not exist yet, which is fine as long as the child is not selected
+        let mut reader = TilesetReader::from_path(directory.join("tileset.json"))?;
+        assert_eq!(None, reader.get_metadata().number_of_points());
+        reader.set_max_geometric_error(Some(1.0))?;
+        assert_eq!(Some(1), reader.get_metadata().number_of_points());
+
+        write_pnts(
+            &directory.join("child.pnts"),
+            &[Vector3::new(0.5, 0.0, 0.0), Vector3::new(-0.5, 0.0, 0.0)],
+            Vector3::zeros(),
+        )?;
+        reader.set_max_geometric_error(None)?;
+        assert_eq!(Some(3), reader.get_metadata().number_of_points());
+
+        // Counts are cached, so the content files are not opened again when the filters change
+        std::fs::remove_file(directory.join("child.pnts"))?;
+        reader.set_max_geometric_error(Some(1.0))?;
+        reader.set_max_geometric_error(None)?;
+        assert_eq!(Some(3), reader.get_metadata().number_of_points());
+
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tileset_reader_reads_tiler_output() -> Result<()> {
+        run_tiler_output_test("tileset_reader_tiler_output", TilerContentFormat::Pnts)
+    }
+
+    #[test]
+    fn test_tileset_reader_reads_glb_tiler_output() -> Result<()> {
+        run_tiler_output_test("tileset_reader_glb_tiler_output", TilerContentFormat::Glb)
+    }
+
+    fn run_tiler_output_test(test_name: &str, content_format: TilerContentFormat) -> Result<()> {
+        let directory = test_directory(test_name);
+        let expected_points = (0..5_000)
+            .map(|index| TestPoint {
+                position: Vector3::new(
+                    1000.0 + (index % 50) as f64,
+                    (index / 50) as f64,
+                    (index % 7) as f64,
+                ),
+                intensity: index as u16,
+            })
+            .collect::<VectorBuffer>();
+        let input_path = directory.join("input.pnts");
+        {
+            let mut writer = PntsWriter::from_write_and_layout(
+                BufWriter::new(File::create(&input_path)?),
+                TestPoint::layout(),
+            );
+            writer.write(&expected_points)?;
+        }
+        PntsTiler::new(directory.join("tiles"))
+            .with_max_points_per_tile(500)
+            .with_content_format(content_format)
+            .with_point_layout(TestPoint::layout())
+            .tile(&mut PntsReader::from_path(&input_path)?)?;
+
+        let mut reader = TilesetReader::from_path(directory.join("tiles/tileset.json"))?;
+        assert!(reader.selected_tiles().count() > 1);
+        assert!(reader.selected_tiles().all(|tile| tile
+            .content_path
+            .as_ref()
+            .and_then(|path| path.extension())
+            .map(|extension| extension == content_format.extension())
+            .unwrap_or(false)));
+        let mut points = vec![
+            TestPoint {
+                position: Vector3::zeros(),
+                intensity: 0
+            };
+            5_000
+        ];
+        let mut buffer = VectorBuffer::with_capacity(5_000, TestPoint::layout());
+        buffer.resize(5_000);
+        assert_eq!(5_000, reader.read_into(&mut buffer, 5_000)?);
+        for point in buffer.view::<TestPoint>() {
+            points[point.intensity as usize] = point;
+        }
+        for (expected, actual) in expected_points.view::<TestPoint>().into_iter().zip(points) {
+            let expected_position = expected.position;
+            let actual_position = actual.position;
+            assert!((expected_position - actual_position).amax() < 1e-3);
+            assert_eq!({ expected.intensity }, { actual.intensity });
+        }
+
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
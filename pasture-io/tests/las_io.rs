@@ -329,6 +329,33 @@ fn test_write_large_file_format10() -> Result<()> {
     Ok(())
 }
 
+/// LAZ files with the point formats 9 and 10 are rejected when reading and writing, since laz-rs does not decode the
+/// wave packets of these formats like LASzip encodes them
+#[test]
+fn test_laz_formats_with_wave_packets_are_rejected() -> Result<()> {
+    for format in [9, 10].iter() {
+        let test_file_path = format!(
+            "{}/resources/test/10_points_format_{}.laz",
+            env!("CARGO_MANIFEST_DIR"),
+            format
+        );
+        assert!(LASReader::from_path(&test_file_path, false).is_err());
+    }
+    assert!(LASWriter::from_writer_and_point_layout(
+        Cursor::new(Vec::<u8>::new()),
+        &LasPointFormat9::layout(),
+        true
+    )
+    .is_err());
+    assert!(LASWriter::from_writer_and_point_layout(
+        Cursor::new(Vec::<u8>::new()),
+        &LasPointFormat10::layout(),
+        true
+    )
+    .is_err());
+    Ok(())
+}
+
 #[test]
 fn test_write_large_file_with_unsupported_attribute() -> Result<()> {
     const COUNT: usize = 333333;